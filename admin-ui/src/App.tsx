@@ -1,6 +1,7 @@
 import { useState, useEffect } from 'react'
 import { storage } from '@/lib/storage'
 import { initCsrfToken } from '@/api/credentials'
+import { probeSession } from '@/api/client'
 import { LoginPage } from '@/components/login-page'
 import { UnifiedDashboard } from '@/components/unified-dashboard'
 import { SettingsPage } from '@/components/settings-page'
@@ -18,6 +19,11 @@ function App() {
       setIsLoggedIn(true)
       // 初始化 CSRF Token
       initCsrfToken()
+    } else {
+      // 通过一次性登录链接进入时，使用会话 Cookie 登录
+      probeSession().then((ok) => {
+        if (ok) setIsLoggedIn(true)
+      })
     }
   }, [])
 
@@ -75,8 +75,8 @@ api.interceptors.request.use(async (config) => {
   if (method === 'POST' || method === 'PUT' || method === 'DELETE') {
     let csrfToken = storage.getCsrfToken()
 
-    // 如果没有 CSRF Token，先获取一个（带竞态保护）
-    if (!csrfToken && apiKey) {
+    // 如果没有 CSRF Token，先获取一个（带竞态保护；一次性登录链接的会话 Cookie 也可获取）
+    if (!csrfToken) {
       try {
         csrfToken = await fetchCsrfTokenSafe()
       } catch (error) {
@@ -187,6 +187,18 @@ export async function getCsrfToken(): Promise<CsrfTokenResponse> {
   return data
 }
 
+/**
+ * 检查是否已通过一次性登录链接建立会话（未保存 API Key 时调用）
+ */
+export async function probeSession(): Promise<boolean> {
+  try {
+    await fetchCsrfTokenSafe()
+    return true
+  } catch {
+    return false
+  }
+}
+
 /**
  * 初始化 CSRF Token（登录成功后调用）
  */
@@ -5,21 +5,54 @@ import type {
   SuccessResponse,
   SetDisabledRequest,
   SetPriorityRequest,
+  PriorityUpdate,
+  BulkPriorityResponse,
+  CredentialHistoryResponse,
+  CredentialTimelineResponse,
   AddCredentialRequest,
   AddCredentialResponse,
   ImportCredentialsRequest,
   ImportCredentialsResponse,
+  KiroIdeCredentialItem,
   SchedulingMode,
   CsrfTokenResponse,
+  WarmupReportResponse,
+  UserSessionsResponse,
+  AggregatedStats,
+  TimelineResponse,
+  CredentialValidationResponse,
+  RefreshTokenResponse,
+  CredentialTestResponse,
+  SimulationScenario,
+  SimulationReport,
+  CredentialsQuery,
+  UpdateTagsRequest,
+  CredentialTagsResponse,
 } from '@/types/api'
 
 // 导出 CSRF Token 相关函数
 export { getCsrfToken, initCsrfToken }
 export type { CsrfTokenResponse }
 
-// 获取所有凭据状态
-export async function getCredentials(): Promise<CredentialsStatusResponse> {
-  const { data } = await api.get<CredentialsStatusResponse>('/credentials')
+// 获取凭据状态（可选服务端筛选）
+export async function getCredentials(
+  query?: CredentialsQuery
+): Promise<CredentialsStatusResponse> {
+  const { data } = await api.get<CredentialsStatusResponse>('/credentials', {
+    params: query,
+  })
+  return data
+}
+
+// 添加/移除凭据标签
+export async function updateCredentialTags(
+  id: number,
+  request: UpdateTagsRequest
+): Promise<CredentialTagsResponse> {
+  const { data } = await api.post<CredentialTagsResponse>(
+    `/credentials/${id}/tags`,
+    request
+  )
   return data
 }
 
@@ -47,6 +80,43 @@ export async function setCredentialPriority(
   return data
 }
 
+// 按拖拽排序结果批量设置凭据优先级
+export async function setCredentialPriorities(
+  priorities: PriorityUpdate[]
+): Promise<BulkPriorityResponse> {
+  const { data } = await api.put<BulkPriorityResponse>('/credentials/priorities', priorities)
+  return data
+}
+
+// 获取凭据历史版本
+export async function getCredentialHistory(id: number): Promise<CredentialHistoryResponse> {
+  const { data } = await api.get<CredentialHistoryResponse>(`/credentials/${id}/history`)
+  return data
+}
+
+// 获取凭据调用时间线
+export async function getCredentialTimeline(
+  id: number,
+  windowMinutes = 60,
+  granularityMinutes = 5
+): Promise<CredentialTimelineResponse> {
+  const { data } = await api.get<CredentialTimelineResponse>(`/credentials/${id}/timeline`, {
+    params: { window_minutes: windowMinutes, granularity_minutes: granularityMinutes },
+  })
+  return data
+}
+
+// 回滚凭据到历史版本
+export async function rollbackCredential(
+  id: number,
+  version: number
+): Promise<SuccessResponse> {
+  const { data } = await api.post<SuccessResponse>(`/credentials/${id}/rollback`, null, {
+    params: { version },
+  })
+  return data
+}
+
 // 重置失败计数
 export async function resetCredentialFailure(
   id: number
@@ -61,6 +131,27 @@ export async function getCredentialBalance(id: number): Promise<BalanceResponse>
   return data
 }
 
+// 检查凭据配置警告
+export async function validateCredential(id: number): Promise<CredentialValidationResponse> {
+  const { data } = await api.get<CredentialValidationResponse>(`/credentials/${id}/validate`)
+  return data
+}
+
+// 立即刷新凭据 Token（force 默认 true，即使未过期也刷新）
+export async function refreshCredentialToken(
+  id: number,
+  force = true
+): Promise<RefreshTokenResponse> {
+  const { data } = await api.post<RefreshTokenResponse>(`/credentials/${id}/refresh`, { force })
+  return data
+}
+
+// 测试凭据连通性（不影响失败计数）
+export async function testCredential(id: number): Promise<CredentialTestResponse> {
+  const { data } = await api.post<CredentialTestResponse>(`/credentials/${id}/test`)
+  return data
+}
+
 // 添加新凭据
 export async function addCredential(
   req: AddCredentialRequest
@@ -83,8 +174,53 @@ export async function importCredentials(
   return data
 }
 
+// 批量导入 Kiro IDE 导出格式的凭据
+export async function importKiroIdeCredentials(
+  items: KiroIdeCredentialItem[],
+  poolId?: string
+): Promise<ImportCredentialsResponse> {
+  const { data } = await api.post<ImportCredentialsResponse>(
+    '/credentials/import-kiro-ide',
+    items,
+    { params: poolId ? { pool_id: poolId } : undefined }
+  )
+  return data
+}
+
 // 设置调度模式
 export async function setSchedulingMode(mode: SchedulingMode): Promise<SuccessResponse> {
   const { data } = await api.post<SuccessResponse>('/scheduling-mode', { mode })
   return data
 }
+
+// 获取启动凭据预热报告
+export async function getWarmupReport(): Promise<WarmupReportResponse> {
+  const { data } = await api.get<WarmupReportResponse>('/warmup-report')
+  return data
+}
+
+// 获取各用户活跃会话数
+export async function getUserSessions(): Promise<UserSessionsResponse> {
+  const { data } = await api.get<UserSessionsResponse>('/user-sessions')
+  return data
+}
+
+// 获取所有池的凭据汇总统计
+export async function getCredentialStats(): Promise<AggregatedStats> {
+  const { data } = await api.get<AggregatedStats>('/stats/credentials')
+  return data
+}
+
+// 获取所有池的每分钟调用统计
+export async function getStatsTimeline(windowSecs?: number): Promise<TimelineResponse> {
+  const { data } = await api.get<TimelineResponse>('/stats/timeline', {
+    params: { window_secs: windowSecs },
+  })
+  return data
+}
+
+// 运行调度模拟（合成负载，不涉及真实凭据）
+export async function simulateScheduling(scenario: SimulationScenario): Promise<SimulationReport> {
+  const { data } = await api.post<SimulationReport>('/simulate', scenario)
+  return data
+}
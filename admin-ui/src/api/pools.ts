@@ -6,8 +6,15 @@ import type {
   UpdatePoolRequest,
   SetPoolDisabledRequest,
   AssignCredentialToPoolRequest,
+  AssignCredentialToPoolResponse,
   SuccessResponse,
   PoolCredentialsResponse,
+  PoolApiKeysResponse,
+  RoutingStatsResponse,
+  RebalancePoolsRequest,
+  RebalanceResult,
+  TransferCredentialPoolRequest,
+  TransferCredentialPoolResponse,
 } from '@/types/api'
 
 // 获取所有池
@@ -16,6 +23,12 @@ export async function fetchPools(): Promise<PoolsListResponse> {
   return data
 }
 
+// 获取自动路由统计
+export async function fetchRoutingStats(): Promise<RoutingStatsResponse> {
+  const { data } = await api.get<RoutingStatsResponse>('/pools/routing-stats')
+  return data
+}
+
 // 获取单个池详情
 export async function fetchPool(poolId: string): Promise<PoolStatusItem> {
   const { data } = await api.get<PoolStatusItem>(`/pools/${encodeURIComponent(poolId)}`)
@@ -28,6 +41,12 @@ export async function fetchPoolCredentials(poolId: string): Promise<PoolCredenti
   return data
 }
 
+// 获取能访问该池的 API Key
+export async function fetchPoolApiKeys(poolId: string): Promise<PoolApiKeysResponse> {
+  const { data } = await api.get<PoolApiKeysResponse>(`/pools/${encodeURIComponent(poolId)}/api-keys`)
+  return data
+}
+
 // 创建新池
 export async function createPool(request: CreatePoolRequest): Promise<SuccessResponse> {
   const { data } = await api.post<SuccessResponse>('/pools', request)
@@ -41,8 +60,14 @@ export async function updatePool(poolId: string, request: UpdatePoolRequest): Pr
 }
 
 // 删除池
-export async function deletePool(poolId: string): Promise<SuccessResponse> {
-  const { data } = await api.delete<SuccessResponse>(`/pools/${encodeURIComponent(poolId)}`)
+// 池内仍有凭据时需指定 reassignTo 或 force（转入默认池），否则返回 409
+export async function deletePool(
+  poolId: string,
+  options?: { reassignTo?: string; force?: boolean }
+): Promise<SuccessResponse> {
+  const { data } = await api.delete<SuccessResponse>(`/pools/${encodeURIComponent(poolId)}`, {
+    params: { reassign_to: options?.reassignTo, force: options?.force },
+  })
   return data
 }
 
@@ -52,11 +77,32 @@ export async function setPoolDisabled(poolId: string, request: SetPoolDisabledRe
   return data
 }
 
+// 按策略在池之间重新分配凭据
+export async function rebalancePools(request: RebalancePoolsRequest): Promise<RebalanceResult> {
+  const { data } = await api.post<RebalanceResult>('/pools/rebalance', request)
+  return data
+}
+
 // 将凭据分配到池
 export async function assignCredentialToPool(
   credentialId: number,
   request: AssignCredentialToPoolRequest
-): Promise<SuccessResponse> {
-  const { data } = await api.post<SuccessResponse>(`/credentials/${credentialId}/pool`, request)
+): Promise<AssignCredentialToPoolResponse> {
+  const { data } = await api.post<AssignCredentialToPoolResponse>(
+    `/credentials/${credentialId}/pool`,
+    request
+  )
+  return data
+}
+
+// 将凭据转移到另一个池（可迁移进行中的会话）
+export async function transferCredentialPool(
+  credentialId: number,
+  request: TransferCredentialPoolRequest
+): Promise<TransferCredentialPoolResponse> {
+  const { data } = await api.post<TransferCredentialPoolResponse>(
+    `/credentials/${credentialId}/transfer-pool`,
+    request
+  )
   return data
 }
@@ -1,9 +1,19 @@
 import api from './client'
 import type {
   ConfigResponse,
+  TlsInfoResponse,
+  BackupListResponse,
+  RestoreBackupResponse,
+  MagicLinkResponse,
+  LogoutResponse,
   UpdateConfigRequest,
+  FeatureFlagsResponse,
+  SetFeatureFlagRequest,
+  RateLimitExemption,
+  RateLimitExemptionsResponse,
   ApiKeyItem,
   CreateApiKeyRequest,
+  BulkImportApiKeysResponse,
   UpdateApiKeyRequest,
   SuccessResponse,
 } from '@/types/api'
@@ -22,6 +32,83 @@ export async function updateConfig(config: UpdateConfigRequest): Promise<Success
   return data
 }
 
+// 获取当前生效的 TLS 配置
+export async function getTlsInfo(): Promise<TlsInfoResponse> {
+  const { data } = await api.get<TlsInfoResponse>('/tls-info')
+  return data
+}
+
+// ============ 配置备份 ============
+
+// 列出备份快照
+export async function getBackups(): Promise<BackupListResponse> {
+  const { data } = await api.get<BackupListResponse>('/backups')
+  return data
+}
+
+// 从快照恢复（校验失败时不做任何修改）
+export async function restoreBackup(timestamp: string): Promise<RestoreBackupResponse> {
+  const { data } = await api.post<RestoreBackupResponse>(
+    `/backups/${encodeURIComponent(timestamp)}/restore`
+  )
+  return data
+}
+
+// 生成 Admin UI 一次性登录链接
+export async function createMagicLink(): Promise<MagicLinkResponse> {
+  const { data } = await api.post<MagicLinkResponse>('/auth/magic-link')
+  return data
+}
+
+// 注销当前登录会话并清除会话 Cookie
+export async function logout(): Promise<LogoutResponse> {
+  const { data } = await api.post<LogoutResponse>('/auth/logout')
+  return data
+}
+
+// ============ 功能开关 ============
+
+// 获取所有功能开关
+export async function getFeatures(): Promise<FeatureFlagsResponse> {
+  const { data } = await api.get<FeatureFlagsResponse>('/features')
+  return data
+}
+
+// 运行时切换功能开关
+export async function setFeature(
+  name: string,
+  req: SetFeatureFlagRequest
+): Promise<FeatureFlagsResponse> {
+  const { data } = await api.put<FeatureFlagsResponse>(`/features/${name}`, req)
+  return data
+}
+
+// ============ 限流豁免 ============
+
+// 获取限流豁免规则
+export async function getRateLimitExemptions(): Promise<RateLimitExemptionsResponse> {
+  const { data } = await api.get<RateLimitExemptionsResponse>('/rate-limit/exemptions')
+  return data
+}
+
+// 追加限流豁免规则
+export async function addRateLimitExemption(
+  req: RateLimitExemption
+): Promise<RateLimitExemptionsResponse> {
+  const { data } = await api.post<RateLimitExemptionsResponse>('/rate-limit/exemptions', req)
+  return data
+}
+
+// 按下标删除限流豁免规则
+export async function deleteRateLimitExemption(
+  index: number
+): Promise<RateLimitExemptionsResponse> {
+  const { data } = await api.delete<RateLimitExemptionsResponse>(
+    `/rate-limit/exemptions/${index}`
+  )
+  return data
+}
+
 // ============ API Key 管理 ============
 
 // 获取所有 API Keys
@@ -36,6 +123,14 @@ export async function createApiKey(req: CreateApiKeyRequest): Promise<ApiKeyItem
   return data
 }
 
+// 批量导入 API Key（最多 100 个，响应包含完整 Key）
+export async function bulkImportApiKeys(
+  reqs: CreateApiKeyRequest[]
+): Promise<BulkImportApiKeysResponse> {
+  const { data } = await api.post<BulkImportApiKeysResponse>('/api-keys/bulk-import', reqs)
+  return data
+}
+
 // 更新 API Key
 export async function updateApiKey(id: number, req: UpdateApiKeyRequest): Promise<ApiKeyItem> {
   const { data } = await api.put<ApiKeyItem>(`/api-keys/${id}`, req)
@@ -53,7 +53,13 @@ import {
 import { usePools } from "@/hooks/use-pools";
 import { extractErrorMessage } from "@/lib/utils";
 import { FadeIn, SlideIn } from "@/components/ui/motion";
-import type { ApiKeyItem } from "@/types/api";
+import type { ApiKeyItem, PoolBinding } from "@/types/api";
+
+// 将池绑定统一为按回退顺序的池 ID 列表
+function toPoolIds(binding: PoolBinding | null): string[] {
+  if (!binding) return [];
+  return Array.isArray(binding) ? binding : [binding];
+}
 
 interface SettingsPageProps {
   onBack: () => void;
@@ -103,6 +109,7 @@ export function SettingsPage({ onBack }: SettingsPageProps) {
   const [editPoolDialogOpen, setEditPoolDialogOpen] = useState(false);
   const [editingApiKey, setEditingApiKey] = useState<ApiKeyItem | null>(null);
   const [editPoolId, setEditPoolId] = useState<string>("__auto__");
+  const [editFallbackPoolIds, setEditFallbackPoolIds] = useState<string[]>([]);
 
   // 删除确认对话框
   const [deleteDialogOpen, setDeleteDialogOpen] = useState(false);
@@ -229,21 +236,36 @@ export function SettingsPage({ onBack }: SettingsPageProps) {
   // 打开编辑池绑定对话框
   const handleOpenEditPoolDialog = (key: ApiKeyItem) => {
     setEditingApiKey(key);
-    // poolId 必须有值，如果没有则默认自动路由
-    setEditPoolId(key.poolId || "__auto__");
+    // poolId 必须有值，如果没有则默认自动路由；数组拆分为主池和回退池
+    const [primary, ...fallbacks] = toPoolIds(key.poolId);
+    setEditPoolId(primary || "__auto__");
+    setEditFallbackPoolIds(fallbacks);
     setEditPoolDialogOpen(true);
   };
 
+  // 切换回退池
+  const handleToggleFallbackPool = (poolId: string, checked: boolean) => {
+    setEditFallbackPoolIds((prev) =>
+      checked ? [...prev, poolId] : prev.filter((id) => id !== poolId)
+    );
+  };
+
   // 保存池绑定
   const handleSavePoolBinding = async () => {
     if (!editingApiKey) return;
+    const fallbackPoolIds =
+      editPoolId === "__auto__"
+        ? []
+        : editFallbackPoolIds.filter((id) => id !== editPoolId);
 
     try {
       await updateApiKey.mutateAsync({
         id: editingApiKey.id,
         req: {
-          // 必须绑定池
-          poolId: editPoolId,
+          // 必须绑定池；有回退池时按顺序提交数组
+          poolId: fallbackPoolIds.length
+            ? [editPoolId, ...fallbackPoolIds]
+            : editPoolId,
         },
       });
       toast.success(
@@ -369,7 +391,7 @@ export function SettingsPage({ onBack }: SettingsPageProps) {
                 )}
               </div>
               <CardDescription>
-                服务器基础配置，修改后需要重启服务
+                服务器基础配置，主机地址和端口修改后立即切换监听，其他项需要重启服务
               </CardDescription>
             </CardHeader>
             <CardContent className="space-y-4">
@@ -639,18 +661,29 @@ export function SettingsPage({ onBack }: SettingsPageProps) {
                           >
                             {key.enabled ? t("common.enabled") : t("common.disabled")}
                           </Badge>
-                          {key.poolId === "__auto__" ? (
+                          {Array.isArray(key.poolId) && key.poolId.length > 1 ? (
+                            <Badge variant="outline" className="gap-1">
+                              <Link className="h-3 w-3" />
+                              {key.poolId
+                                .map(
+                                  (id) =>
+                                    pools.find((p) => p.id === id)?.name || id
+                                )
+                                .join(" → ")}
+                            </Badge>
+                          ) : toPoolIds(key.poolId)[0] === "__auto__" ? (
                             <Badge
                               variant="default"
                               className="gap-1 bg-gradient-to-r from-cyan-500 to-blue-600"
                             >
                               🔄 自动路由
                             </Badge>
-                          ) : key.poolId ? (
+                          ) : toPoolIds(key.poolId).length ? (
                             <Badge variant="outline" className="gap-1">
                               <Link className="h-3 w-3" />
-                              {pools.find((p) => p.id === key.poolId)?.name ||
-                                key.poolId}
+                              {pools.find(
+                                (p) => p.id === toPoolIds(key.poolId)[0]
+                              )?.name || toPoolIds(key.poolId)[0]}
                             </Badge>
                           ) : (
                             <Badge variant="warning" className="gap-1">
@@ -868,6 +901,36 @@ export function SettingsPage({ onBack }: SettingsPageProps) {
                 选择自动路由或绑定到特定池
               </p>
             </div>
+            {editPoolId !== "__auto__" && (
+              <div>
+                <label className="text-sm font-medium">回退池</label>
+                <div className="space-y-2 mt-2">
+                  {pools
+                    .filter((pool) => pool.id !== editPoolId)
+                    .map((pool) => (
+                      <div
+                        key={pool.id}
+                        className="flex items-center justify-between"
+                      >
+                        <span className="text-sm">
+                          {pool.name} ({pool.id})
+                          {editFallbackPoolIds.includes(pool.id) &&
+                            ` · #${editFallbackPoolIds.indexOf(pool.id) + 1}`}
+                        </span>
+                        <Switch
+                          checked={editFallbackPoolIds.includes(pool.id)}
+                          onCheckedChange={(checked) =>
+                            handleToggleFallbackPool(pool.id, checked)
+                          }
+                        />
+                      </div>
+                    ))}
+                </div>
+                <p className="text-xs text-muted-foreground mt-1">
+                  主池无可用凭据时，按开启顺序依次回退
+                </p>
+              </div>
+            )}
           </div>
           <DialogFooter>
             <Button
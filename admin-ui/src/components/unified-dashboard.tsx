@@ -2,6 +2,7 @@ import { useQueryClient } from "@tanstack/react-query";
 import { useTranslation } from "react-i18next";
 import { toast } from "sonner";
 import { storage } from "@/lib/storage";
+import { logout } from "@/api/settings";
 import { BalanceDialog } from "@/components/balance-dialog";
 import { AddCredentialDialog } from "@/components/add-credential-dialog";
 import { ImportCredentialsDialog } from "@/components/import-credentials-dialog";
@@ -72,7 +73,9 @@ export function UnifiedDashboard({
     toast.success(t('common.refreshed'));
   };
 
-  const handleLogout = () => {
+  const handleLogout = async () => {
+    // 注销一次性登录链接换发的会话（仅使用 API Key 时无会话可注销）
+    await logout().catch(() => {});
     storage.removeApiKey();
     queryClient.clear();
     onLogout();
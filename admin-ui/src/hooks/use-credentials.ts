@@ -14,7 +14,7 @@ import type { AddCredentialRequest } from '@/types/api'
 export function useCredentials() {
   return useQuery({
     queryKey: ['credentials'],
-    queryFn: getCredentials,
+    queryFn: () => getCredentials(),
     refetchInterval: 30000, // 每 30 秒刷新一次
   })
 }
@@ -2,19 +2,76 @@
 export interface CredentialsStatusResponse {
   total: number
   available: number
+  /** 满足筛选条件的凭据数（分页前） */
+  matched: number
   currentId: number
   credentials: CredentialStatusItem[]
   // 会话缓存统计
   sessionCacheSize: number
+  sessionCacheCapacity: number
+  sessionCacheTtlSecs: number
   roundRobinCounter: number
   // 调度模式
   schedulingMode: SchedulingMode
 }
 
+// 按用户公平调度的会话统计
+export interface UserSessionsResponse {
+  enabled: boolean
+  maxShare: number
+  users: UserSessionCount[]
+}
+
+// 单个用户的活跃会话统计（用户标识为哈希值）
+export interface UserSessionCount {
+  userHash: string
+  sessions: number
+  credentials: number
+}
+
+// 启动凭据预热报告
+export interface WarmupReportResponse {
+  total: number
+  ready: number
+  failed: number
+  /** 成功刷新 Token 的凭据数 */
+  refreshed: number
+  /** Token 仍有效、跳过刷新的凭据数 */
+  skipped: number
+  /** 预热总耗时（毫秒） */
+  elapsedMs: number
+  entries: WarmupEntryItem[]
+}
+
+// 单个凭据的预热结果
+export interface WarmupEntryItem {
+  id: number
+  authMethod: string
+  priority: number
+  /** 耗时（毫秒） */
+  durationMs: number
+  /** 是否实际刷新了 Token */
+  refreshed: boolean
+  success: boolean
+  /** 失败原因 */
+  error: string | null
+}
+
 // 调度模式
 export type SchedulingMode = 'round_robin' | 'priority_fill'
 
+// 凭据失败分类
+export type FailureClass =
+  | 'network_error'
+  | 'auth_error'
+  | 'quota_exceeded'
+  | 'token_expired'
+  | 'unknown'
+
 // 单个凭据状态
+// 凭据设置的生效来源
+export type SettingSource = 'credential' | 'pool' | 'global'
+
 export interface CredentialStatusItem {
   id: number
   priority: number
@@ -24,6 +81,36 @@ export interface CredentialStatusItem {
   expiresAt: string | null
   authMethod: string | null
   hasProfileArn: boolean
+  /** 备注 */
+  notes?: string
+  /** 标签 */
+  tags: string[]
+  /** 凭据级 Region */
+  region?: string
+  /** 生效的 Region（凭据 > 池默认值 > 全局） */
+  effectiveRegion: string
+  /** 生效 Region 的来源 */
+  regionSource: SettingSource
+  /** 生效代理的来源（未配置任何代理时为 null） */
+  proxySource: SettingSource | null
+  /** 禁用原因（可读文本，未禁用时为 null） */
+  disabledReason: string | null
+  /** 最近一次禁用时间（RFC3339，重新启用后保留） */
+  disabledAt: string | null
+  /** 最近禁用历史中的禁用次数（越大越不稳定） */
+  flapCount: number
+  /** 当前进行中的调用数 */
+  currentConcurrency: number
+  /** 最大并发请求数（null 表示不限制） */
+  maxConcurrency: number | null
+  /** 最近一次导致失败的错误信息 */
+  lastError: string | null
+  /** 上游限流退避截止时间（退避期内不参与调度） */
+  retryAfterUntil: string | null
+  /** 失败分类（用于着色） */
+  failureClassification: FailureClass | null
+  /** 运行时状态最后变化时间（Unix 毫秒） */
+  lastChanged: number
   // ============ 调用统计字段 ============
   /** 成功调用次数（总计） */
   successCount: number
@@ -63,6 +150,33 @@ export interface BalanceResponse {
   nextResetAt: number | null
 }
 
+// 凭据配置检查
+export interface ValidationWarningItem {
+  code: string
+  message: string
+}
+
+export interface CredentialValidationResponse {
+  id: number
+  warnings: ValidationWarningItem[]
+}
+
+// 凭据连通性测试响应
+export interface CredentialTestResponse {
+  success: boolean
+  latencyMs: number
+  error: string | null
+  tokenValid: boolean
+  quotaRemaining: number | null
+}
+
+// 立即刷新凭据 Token 响应
+export interface RefreshTokenResponse {
+  success: boolean
+  newExpiresAt: string | null
+  durationMs: number
+}
+
 // 成功响应
 export interface SuccessResponse {
   success: boolean
@@ -78,6 +192,8 @@ export interface CsrfTokenResponse {
 export interface AdminErrorResponse {
   error: {
     type: string
+    /** 机器可读的稳定错误码 */
+    code: string
     message: string
   }
 }
@@ -91,6 +207,64 @@ export interface SetPriorityRequest {
   priority: number
 }
 
+// 批量修改优先级条目（请求体为条目数组，同一批次优先级不可重复）
+export interface PriorityUpdate {
+  id: number
+  priority: number
+}
+
+// 批量修改优先级响应
+export interface BulkPriorityResponse {
+  updated: number[]
+  failed: Array<{ id: number; error: string }>
+}
+
+// 凭据历史版本（version 1 为最近一次变更前的状态）
+export interface CredentialVersionItem {
+  version: number
+  takenAt: number
+  priority: number
+  disabled: boolean
+  notes?: string
+  tags: string[]
+}
+
+// 凭据历史版本响应
+export interface CredentialHistoryResponse {
+  id: number
+  versions: CredentialVersionItem[]
+  /** 禁用/启用记录（最新的在前，最多 20 条） */
+  disableHistory: DisableTransitionItem[]
+}
+
+// 凭据调用时间线的一个时间桶
+export interface TimelineBucket {
+  bucketStart: string
+  requests: number
+  successes: number
+  failures: number
+  /** 平均延迟（毫秒，无请求时为 0） */
+  avgLatencyMs: number
+}
+
+// 凭据调用时间线响应
+export interface CredentialTimelineResponse {
+  id: number
+  windowMinutes: number
+  granularityMinutes: number
+  /** 按时间升序，无调用的桶也保留 */
+  buckets: TimelineBucket[]
+}
+
+// 凭据禁用状态的一次变化
+export interface DisableTransitionItem {
+  timestamp: string
+  disabled: boolean
+  reason: string
+  /** 操作者（自动变化为 system，手动操作为 Admin 身份） */
+  actor: string
+}
+
 // 添加凭据请求
 export interface AddCredentialRequest {
   refreshToken: string
@@ -98,6 +272,8 @@ export interface AddCredentialRequest {
   clientId?: string
   clientSecret?: string
   priority?: number
+  /** 最大并发请求数（未配置时不限制） */
+  maxConcurrentRequests?: number
   region?: string
   machineId?: string
   poolId?: string
@@ -105,6 +281,36 @@ export interface AddCredentialRequest {
   proxyUrl?: string
   proxyUsername?: string
   proxyPassword?: string
+  notes?: string
+  tags?: string[]
+}
+
+// 凭据列表筛选参数
+export interface CredentialsQuery {
+  tag?: string
+  disabled?: boolean
+  auth_method?: string
+  pool_id?: string
+  /** 匹配标签或 Region */
+  q?: string
+  /** 只返回该时间（Unix 毫秒，含）之后状态有变化的凭据 */
+  updated_since?: number
+  /** 分页偏移（按优先级排序后） */
+  offset?: number
+  /** 分页大小（不指定时返回全部） */
+  limit?: number
+}
+
+// 添加/移除凭据标签请求
+export interface UpdateTagsRequest {
+  add?: string[]
+  remove?: string[]
+}
+
+// 凭据标签响应
+export interface CredentialTagsResponse {
+  id: number
+  tags: string[]
 }
 
 // 添加凭据响应
@@ -129,6 +335,22 @@ export interface IdcCredentialItem {
   region?: string
 }
 
+// Kiro IDE 导出格式的凭据（token 为 refreshToken，type 为认证方式）
+export interface KiroIdeCredentialItem {
+  label?: string
+  email?: string
+  token?: string
+  type?: string
+  credentials?: {
+    accessToken?: string
+    expiresAt?: string
+  }
+  profileArn?: string
+  clientId?: string
+  clientSecret?: string
+  region?: string
+}
+
 // 批量导入凭据请求
 export interface ImportCredentialsRequest {
   credentials: IdcCredentialItem[]
@@ -154,15 +376,66 @@ export interface ConfigResponse {
   region: string
   kiroVersion: string
   tlsBackend: 'rustls' | 'native-tls'
+  tlsMinVersion: 'TLS12' | 'TLS13'
   sessionCacheMaxCapacity: number
   sessionCacheTtlSecs: number
   proxyUrl: string | null
   proxyUsername: string | null
   proxyPassword: string | null
+  /** 客户端 API Key（脱敏，仅显示前 4 个字符） */
+  apiKey?: string
   hasApiKey: boolean
   hasAdminApiKey: boolean
 }
 
+// 当前生效的 TLS 配置
+export interface TlsInfoResponse {
+  backend: 'rustls' | 'native-tls'
+  minVersion: 'TLS12' | 'TLS13'
+  /** 可协商的协议版本（native-tls 由系统决定，为 null） */
+  protocolVersions: string[] | null
+  /** 可协商的密码套件（native-tls 由系统决定，为 null） */
+  cipherSuites: string[] | null
+}
+
+// 备份文件类型
+export type BackupKind = 'config' | 'credentials' | 'pools' | 'api_keys'
+
+// 备份快照
+export interface BackupInfo {
+  /** 快照标识（目录名，如 20260101T000000Z） */
+  timestamp: string
+  createdAt: string
+  files: { kind: BackupKind; name: string; sizeBytes: number }[]
+}
+
+// 备份列表响应（按时间倒序）
+export interface BackupListResponse {
+  backups: BackupInfo[]
+}
+
+// 恢复备份响应
+export interface RestoreBackupResponse {
+  success: boolean
+  message: string
+  timestamp: string
+  /** 已恢复并重新加载的文件类型 */
+  restored: BackupKind[]
+}
+
+// 一次性登录链接响应（15 分钟内有效，只能使用一次）
+export interface MagicLinkResponse {
+  token: string
+  expiresAt: string
+  /** Admin UI 登录地址，如 /admin/login?token=... */
+  loginUrl: string
+}
+
+export interface LogoutResponse {
+  /** 注销的会话数量 */
+  revokedSessions: number
+}
+
 // 更新配置请求
 export interface UpdateConfigRequest {
   host?: string
@@ -176,8 +449,44 @@ export interface UpdateConfigRequest {
   apiKey?: string
 }
 
+// ============ 功能开关 ============
+
+// 功能开关状态
+export interface FeatureFlagItem {
+  name: string
+  enabled: boolean
+  description: string
+}
+
+// 功能开关列表响应
+export interface FeatureFlagsResponse {
+  features: FeatureFlagItem[]
+}
+
+// 切换功能开关请求
+export interface SetFeatureFlagRequest {
+  enabled: boolean
+}
+
+// ============ 限流豁免 ============
+
+// 限流豁免规则（keyPrefix 与 cidr 至少配置一个）
+export interface RateLimitExemption {
+  keyPrefix?: string
+  cidr?: string
+  reason: string
+}
+
+// 限流豁免规则列表（按配置顺序，删除时使用数组下标）
+export interface RateLimitExemptionsResponse {
+  exemptions: RateLimitExemption[]
+}
+
 // ============ API Key 管理 ============
 
+// API Key 绑定的池：单个池 ID，或按顺序回退的池 ID 列表
+export type PoolBinding = string | string[]
+
 // API Key 条目
 export interface ApiKeyItem {
   id: number
@@ -186,7 +495,22 @@ export interface ApiKeyItem {
   description: string | null
   createdAt: string
   enabled: boolean
-  poolId: string | null // 绑定的池 ID
+  poolId: PoolBinding | null // 绑定的池 ID
+  poolNames?: string[] // 绑定池的名称（顺序与 poolId 一致，池不存在时为池 ID）
+}
+
+// API Key 与池的绑定方式
+export type PoolApiKeyBinding = 'direct' | 'auto' | 'default'
+
+// 能访问某个池的 API Key
+export interface PoolApiKeyItem extends ApiKeyItem {
+  binding: PoolApiKeyBinding
+}
+
+// 池的 API Key 列表响应
+export interface PoolApiKeysResponse {
+  poolId: string
+  apiKeys: PoolApiKeyItem[]
 }
 
 // 创建 API Key 请求
@@ -194,7 +518,27 @@ export interface CreateApiKeyRequest {
   name: string
   description?: string
   key?: string // 可选，不提供则自动生成
-  poolId?: string // 绑定的池 ID
+  poolId?: PoolBinding // 绑定的池 ID
+}
+
+// 批量导入时跳过的条目
+export interface SkippedApiKey {
+  name: string
+  reason: string
+}
+
+// 批量导入时新建的 API Key
+export interface CreatedApiKey {
+  id: number
+  name: string
+  key: string // 完整 Key，仅在批量导入响应中返回
+}
+
+// 批量导入 API Key 响应
+export interface BulkImportApiKeysResponse {
+  imported: number
+  skipped: SkippedApiKey[]
+  created: CreatedApiKey[]
 }
 
 // 更新 API Key 请求
@@ -202,7 +546,7 @@ export interface UpdateApiKeyRequest {
   name?: string
   description?: string
   enabled?: boolean
-  poolId?: string | null // 绑定的池 ID，设为 null 可解绑
+  poolId?: PoolBinding | null // 绑定的池 ID，设为 null 可解绑
 }
 
 // ============ 池管理 ============
@@ -216,11 +560,52 @@ export interface PoolStatusItem {
   schedulingMode: SchedulingMode
   hasProxy: boolean
   priority: number
+  // 自动路由使用的优先级（开启池优先级自动调整时可能与 priority 不同）
+  effectivePriority: number
+  overflowPoolId: string | null
+  defaultRegion: string | null
+  defaultAuthMethod: string | null
+  defaultMachineId: string | null
+  hasDefaultProxy: boolean
   totalCredentials: number
   availableCredentials: number
   currentId: number
   sessionCacheSize: number
+  sessionCacheCapacity: number
+  sessionCacheTtlSecs: number
   roundRobinCounter: number
+  // 绑定到该池的 API Key 数量（默认池含未绑定池的 Key，不含自动路由）
+  boundApiKeyCount: number
+  // 最近 60 分钟的性能历史（仅池详情返回）
+  performanceHistory?: PerformanceBucket[]
+}
+
+// 池性能历史（单分钟）
+export interface PerformanceBucket {
+  minute: string
+  requestCount: number
+  successCount: number
+  failureCount: number
+  avgLatencyMs: number
+}
+
+// 所有池的凭据汇总统计
+export interface AggregatedStats {
+  totalCredentials: number
+  totalAvailable: number
+  totalDisabled: number
+  totalSuccessCalls: number
+  totalFailureCalls: number
+  totalTokenRefreshes: number
+  avgHealthScore: number
+  credentialsByAuthMethod: Record<string, number>
+  credentialsByPool: Record<string, number>
+}
+
+// 所有池的每分钟调用统计
+export interface TimelineResponse {
+  windowSecs: number
+  buckets: PerformanceBucket[]
 }
 
 // 池列表响应
@@ -228,6 +613,19 @@ export interface PoolsListResponse {
   pools: PoolStatusItem[]
 }
 
+// 自动路由统计
+export interface PoolRoutingStat {
+  poolId: string
+  autoRouteCount: number
+  lastSelectedAt: string | null
+}
+
+export interface RoutingStatsResponse {
+  routeCountTotal: number
+  missCountTotal: number
+  pools: PoolRoutingStat[]
+}
+
 // 创建池请求
 export interface CreatePoolRequest {
   id: string
@@ -238,6 +636,15 @@ export interface CreatePoolRequest {
   proxyUsername?: string
   proxyPassword?: string
   priority?: number
+  sessionCacheMaxCapacity?: number
+  sessionCacheTtlSecs?: number
+  overflowPoolId?: string
+  defaultRegion?: string
+  defaultAuthMethod?: string
+  defaultMachineId?: string
+  defaultProxyUrl?: string
+  defaultProxyUsername?: string
+  defaultProxyPassword?: string
 }
 
 // 更新池请求
@@ -250,6 +657,15 @@ export interface UpdatePoolRequest {
   proxyUsername?: string
   proxyPassword?: string
   priority?: number
+  sessionCacheMaxCapacity?: number
+  sessionCacheTtlSecs?: number
+  overflowPoolId?: string
+  defaultRegion?: string
+  defaultAuthMethod?: string
+  defaultMachineId?: string
+  defaultProxyUrl?: string
+  defaultProxyUsername?: string
+  defaultProxyPassword?: string
 }
 
 // 设置池禁用状态请求
@@ -262,6 +678,44 @@ export interface AssignCredentialToPoolRequest {
   poolId: string
 }
 
+// 分配凭据到池响应
+export interface AssignCredentialToPoolResponse {
+  sourcePoolId: string
+  poolId: string
+  credential: CredentialStatusItem
+}
+
+// 转移凭据到池请求
+export interface TransferCredentialPoolRequest {
+  targetPoolId: string
+  migrateActiveSessions?: boolean
+}
+
+// 转移凭据到池响应
+export interface TransferCredentialPoolResponse {
+  movedSessions: number
+}
+
+// 池间重新分配凭据策略
+export type RebalanceStrategy = 'even' | 'by_priority' | 'by_health'
+
+// 池间重新分配凭据请求
+export interface RebalancePoolsRequest {
+  strategy: RebalanceStrategy
+}
+
+// 单个凭据的移动记录
+export interface CredentialMove {
+  credentialId: number
+  fromPool: string
+  toPool: string
+}
+
+// 池间重新分配凭据结果
+export interface RebalanceResult {
+  moved: CredentialMove[]
+}
+
 // 池凭证列表响应
 export interface PoolCredentialsResponse {
   poolId: string
@@ -271,3 +725,46 @@ export interface PoolCredentialsResponse {
   credentials: CredentialStatusItem[]
   schedulingMode: SchedulingMode
 }
+
+// 调度模拟：模拟凭据
+export interface SimulatedCredential {
+  priority?: number
+  /** 每次请求消耗的额度（默认 1） */
+  weight?: number
+  /** 剩余额度（不设置表示不限额度） */
+  quotaRemaining?: number | null
+}
+
+// 调度模拟场景
+export interface SimulationScenario {
+  requestsPerMinute: number
+  /** 请求复用已有会话的比例（0-1） */
+  sessionReuseRatio?: number
+  durationMinutes: number
+  schedulingMode?: SchedulingMode
+  credentials: SimulatedCredential[]
+  seed?: number
+}
+
+// 单个模拟凭据的结果
+export interface SimulatedCredentialResult {
+  id: number
+  priority: number
+  assignments: number
+  quotaUsed: number
+  quotaRemaining: number | null
+  /** 模拟期间额度耗尽的时间（分钟） */
+  exhaustedAtMinute: number | null
+  /** 预计额度耗尽时间（分钟，按平均消耗速度外推） */
+  predictedExhaustionMinute: number | null
+}
+
+// 调度模拟报告
+export interface SimulationReport {
+  totalRequests: number
+  rejectedRequests: number
+  reusedSessionRequests: number
+  sessionHits: number
+  sessionHitRate: number
+  credentials: SimulatedCredentialResult[]
+}
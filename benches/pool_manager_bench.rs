@@ -0,0 +1,158 @@
+//! PoolManager 并发读取池基准测试
+//!
+//! 运行：`cargo bench --bench pool_manager_bench`
+//!
+//! 对比两种池映射加锁方式在"一个池正在更新"时读取另一个池的吞吐量：
+//! - `global_rwlock`：旧实现，`RwLock<HashMap<String, Arc<Pool>>>`，更新任一池都持有全局写锁
+//! - `per_pool_rwlock`：新实现，`DashMap<String, Arc<RwLock<Pool>>>`，只锁定被更新的池
+//!
+//! 另测 `PoolManager::get_pool_for_api_key` 在另一个池被写锁定时的实际开销。
+//! 后台写线程每次持锁约 50 微秒（模拟更新池配置），读线程只读取未被更新的池。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use dashmap::DashMap;
+use kiro_rs::kiro::pool::Pool;
+use kiro_rs::kiro::pool_manager::PoolManager;
+use kiro_rs::model::config::Config;
+use parking_lot::RwLock;
+
+/// 并发读线程数
+const READERS: usize = 8;
+/// 每个读线程的读取次数
+const ITERATIONS: usize = 1_000;
+/// 写线程每次持锁时长
+const WRITE_HOLD: Duration = Duration::from_micros(50);
+
+/// 在后台持续执行 `write`，直到返回的标志被置位
+fn spawn_writer(write: impl Fn() + Send + 'static) -> (Arc<AtomicBool>, thread::JoinHandle<()>) {
+    let stop = Arc::new(AtomicBool::new(false));
+    let flag = stop.clone();
+    let handle = thread::spawn(move || {
+        while !flag.load(Ordering::Relaxed) {
+            write();
+            thread::yield_now();
+        }
+    });
+    (stop, handle)
+}
+
+/// READERS 个线程各调用 ITERATIONS 次 `read`
+fn run_readers(read: Arc<dyn Fn() + Send + Sync>) {
+    let handles: Vec<_> = (0..READERS)
+        .map(|_| {
+            let read = read.clone();
+            thread::spawn(move || {
+                for _ in 0..ITERATIONS {
+                    read();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+fn bench_get_pool(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_pool_while_other_pool_updates");
+    group.throughput(Throughput::Elements((READERS * ITERATIONS) as u64));
+
+    // 旧实现：全局读写锁
+    let global: Arc<RwLock<HashMap<String, Arc<Pool>>>> = Arc::new(RwLock::new(
+        ["alpha", "beta"]
+            .into_iter()
+            .map(|id| (id.to_string(), Arc::new(Pool::new(id, id))))
+            .collect(),
+    ));
+    let writer = global.clone();
+    let (stop, handle) = spawn_writer(move || {
+        let mut pools = writer.write();
+        let mut alpha = (*pools["alpha"]).clone();
+        alpha.priority = alpha.priority.wrapping_add(1);
+        thread::sleep(WRITE_HOLD);
+        pools.insert("alpha".to_string(), Arc::new(alpha));
+    });
+    let reader = global.clone();
+    group.bench_function("global_rwlock", |b| {
+        let read: Arc<dyn Fn() + Send + Sync> = Arc::new({
+            let reader = reader.clone();
+            move || {
+                let pool = reader.read().get("beta").cloned().unwrap();
+                std::hint::black_box(pool.priority);
+            }
+        });
+        b.iter(|| run_readers(read.clone()))
+    });
+    stop.store(true, Ordering::Relaxed);
+    handle.join().unwrap();
+
+    // 新实现：每个池独立加锁
+    let per_pool: Arc<DashMap<String, Arc<RwLock<Pool>>>> = Arc::new(
+        ["alpha", "beta"]
+            .into_iter()
+            .map(|id| (id.to_string(), Arc::new(RwLock::new(Pool::new(id, id)))))
+            .collect(),
+    );
+    let alpha = per_pool.get("alpha").unwrap().value().clone();
+    let (stop, handle) = spawn_writer(move || {
+        let mut alpha = alpha.write();
+        alpha.priority = alpha.priority.wrapping_add(1);
+        thread::sleep(WRITE_HOLD);
+    });
+    group.bench_function("per_pool_rwlock", |b| {
+        let read: Arc<dyn Fn() + Send + Sync> = Arc::new({
+            let per_pool = per_pool.clone();
+            move || {
+                let pool = per_pool.get("beta").unwrap().value().clone();
+                std::hint::black_box(pool.read().priority);
+            }
+        });
+        b.iter(|| run_readers(read.clone()))
+    });
+    stop.store(true, Ordering::Relaxed);
+    handle.join().unwrap();
+
+    // PoolManager：alpha 被持续写锁定时按 API Key 绑定读取 beta
+    let dir = tempfile::tempdir().unwrap();
+    let credentials_path = dir.path().join("credentials.json");
+    std::fs::write(&credentials_path, "[]").unwrap();
+    let manager = Arc::new(
+        PoolManager::new(
+            Config::default(),
+            None,
+            dir.path().join("pools.json"),
+            &credentials_path,
+        )
+        .unwrap(),
+    );
+    manager.create_pool(Pool::new("alpha", "Alpha")).unwrap();
+    manager.create_pool(Pool::new("beta", "Beta")).unwrap();
+    let alpha = manager.get_pool("alpha").unwrap();
+    let (stop, handle) = spawn_writer(move || {
+        let _alpha = alpha.write();
+        thread::sleep(WRITE_HOLD);
+    });
+    group.bench_function("pool_manager_get_pool_for_api_key", |b| {
+        let read: Arc<dyn Fn() + Send + Sync> = Arc::new({
+            let manager = manager.clone();
+            move || {
+                let pool = manager.get_pool_for_api_key(Some("beta")).unwrap();
+                std::hint::black_box(pool.read().config.priority);
+            }
+        });
+        b.iter(|| run_readers(read.clone()))
+    });
+    stop.store(true, Ordering::Relaxed);
+    handle.join().unwrap();
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_get_pool);
+criterion_main!(benches);
@@ -0,0 +1,113 @@
+//! MultiTokenManager 并发获取凭据基准测试
+//!
+//! 运行：`cargo bench --bench token_manager_bench`
+//!
+//! 每次迭代由 CONCURRENCY 个 tokio 任务各调用 ITERATIONS 次
+//! `acquire_context_for_session`，凭据持有未过期的 Token，不产生网络请求。
+//!
+//! 预期吞吐量（参考值，各场景相近）：
+//! - 未优化构建（`cargo build --benches` 后直接运行）：约 2 万次/秒
+//! - release 构建（`cargo bench`）：通常高出一个数量级
+//!
+//! 获取凭据在单次请求中的开销为微秒级，远小于上游 API 延迟。
+
+use std::sync::Arc;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use kiro_rs::kiro::model::credentials::KiroCredentials;
+use kiro_rs::kiro::token_manager::{MultiTokenManager, SchedulingMode};
+use kiro_rs::model::config::Config;
+
+/// 并发任务数
+const CONCURRENCY: usize = 16;
+/// 每个任务的请求次数
+const ITERATIONS: usize = 64;
+
+/// 创建持有有效 Token 的凭据管理器
+fn create_manager(credential_count: usize, mode: SchedulingMode) -> Arc<MultiTokenManager> {
+    let expires_at = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+    let credentials = (0..credential_count)
+        .map(|i| KiroCredentials {
+            access_token: Some(format!("bench-access-token-{}", i)),
+            refresh_token: Some(format!("{}{}", "r".repeat(150), i)),
+            expires_at: Some(expires_at.clone()),
+            priority: i as u32,
+            ..KiroCredentials::default()
+        })
+        .collect();
+    let manager = MultiTokenManager::new(Config::default(), credentials, None, None).unwrap();
+    manager.set_scheduling_mode(mode);
+    Arc::new(manager)
+}
+
+fn bench_acquire_context(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("acquire_context");
+
+    // (名称, 凭据数, 调度模式, 会话池大小, 并发任务数, 每任务请求数)
+    // 会话池为 None 时每个请求都是新会话（走调度模式选择凭据并写入会话缓存）
+    let cases = [
+        (
+            "single_credential",
+            1,
+            SchedulingMode::RoundRobin,
+            None,
+            CONCURRENCY,
+            ITERATIONS,
+        ),
+        (
+            "10_credentials_round_robin",
+            10,
+            SchedulingMode::RoundRobin,
+            None,
+            CONCURRENCY,
+            ITERATIONS,
+        ),
+        (
+            "10_credentials_priority_fill",
+            10,
+            SchedulingMode::PriorityFill,
+            None,
+            CONCURRENCY,
+            ITERATIONS,
+        ),
+        (
+            "100_sessions_cached",
+            10,
+            SchedulingMode::RoundRobin,
+            Some(100),
+            CONCURRENCY,
+            ITERATIONS,
+        ),
+        (
+            "100_unique_sessions",
+            10,
+            SchedulingMode::RoundRobin,
+            None,
+            100,
+            1,
+        ),
+    ];
+
+    for (name, credential_count, mode, session_pool, concurrency, iterations) in cases {
+        let manager = create_manager(credential_count, mode);
+        group.throughput(Throughput::Elements((concurrency * iterations) as u64));
+        group.bench_function(name, |b| {
+            b.to_async(&runtime).iter(|| {
+                manager.benchmark_acquire_with_sessions(concurrency, iterations, session_pool)
+            })
+        });
+    }
+    group.finish();
+
+    // 输出一次延迟分位数，便于与吞吐量对照
+    let manager = create_manager(10, SchedulingMode::RoundRobin);
+    let result = runtime.block_on(manager.benchmark_acquire(CONCURRENCY, ITERATIONS));
+    println!(
+        "10 凭据轮询: {:.0} 次/秒, p50={:?}, p95={:?}, p99={:?}",
+        result.throughput_rps, result.p50, result.p95, result.p99
+    );
+}
+
+criterion_group!(benches, bench_acquire_context);
+criterion_main!(benches);
@@ -0,0 +1,35 @@
+//! 配置文件命令
+
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+use kiro_rs::model::config::Config;
+
+/// 验证配置文件（打印错误和警告）
+pub async fn validate(file: &str) -> Result<()> {
+    if !Path::new(file).exists() {
+        println!("配置文件不存在: {}（将使用默认配置）", file);
+    }
+
+    let config = Config::load(file).with_context(|| format!("加载配置文件失败: {}", file))?;
+
+    match config.validate() {
+        Ok(warnings) => {
+            for warning in &warnings {
+                println!("⚠ {}", warning);
+            }
+            if warnings.is_empty() {
+                println!("✓ 配置有效");
+            } else {
+                println!("✓ 配置有效（{} 个警告）", warnings.len());
+            }
+            Ok(())
+        }
+        Err(errors) => {
+            for error in &errors {
+                println!("✗ {}", error);
+            }
+            bail!("配置验证失败（{} 个错误）", errors.len())
+        }
+    }
+}
@@ -3,9 +3,11 @@
 use anyhow::{Context, Result};
 use serde_json;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use kiro_rs::kiro::model::credentials::{CredentialsConfig, KiroCredentials};
+use kiro_rs::kiro::model::credentials_csv::parse_credentials_csv;
+use kiro_rs::kiro::model::kiro_ide;
 
 /// 列出所有凭据
 pub async fn list(file: &str) -> Result<()> {
@@ -42,6 +44,10 @@ pub async fn list(file: &str) -> Result<()> {
         println!("  Region: {}", region);
         println!("  池 ID: {}", pool_id);
 
+        if let Some(ref notes) = cred.notes {
+            println!("  备注: {}", truncate_chars(notes, NOTES_PREVIEW_CHARS));
+        }
+
         if let Some(ref expires_at) = cred.expires_at {
             println!("  过期时间: {}", expires_at);
         }
@@ -107,12 +113,16 @@ pub async fn add(
         client_id,
         client_secret,
         priority,
+        max_concurrent_requests: None,
         region,
         machine_id: None,
+        notes: None,
+        tags: Vec::new(),
         pool_id: None,
         proxy_url: None,
         proxy_username: None,
         proxy_password: None,
+        custom_headers: Default::default(),
         success_count: 0,
         total_failure_count: 0,
         last_call_time: None,
@@ -120,6 +130,9 @@ pub async fn add(
         token_refresh_count: 0,
         token_refresh_failure_count: 0,
         last_token_refresh_time: None,
+        disabled_at: None,
+        disabled_reason: None,
+        source: None,
     };
 
     credentials.push(new_cred);
@@ -163,7 +176,7 @@ pub async fn delete(file: &str, id: u64) -> Result<()> {
 }
 
 /// 导入凭据
-pub async fn import(input: &str, output: &str, format: &str) -> Result<()> {
+pub async fn import(input: &str, output: &str, format: Option<&str>) -> Result<()> {
     let input_path = Path::new(input);
 
     if !input_path.exists() {
@@ -173,12 +186,21 @@ pub async fn import(input: &str, output: &str, format: &str) -> Result<()> {
     let content = fs::read_to_string(input_path)
         .with_context(|| format!("读取导入文件失败: {}", input))?;
 
-    let imported_credentials: Vec<KiroCredentials> = match format {
+    let format = resolve_format(format, input);
+    let imported_credentials: Vec<KiroCredentials> = match format.as_str() {
         "json" => serde_json::from_str(&content)
             .with_context(|| format!("解析 JSON 文件失败: {}", input))?,
         "yaml" | "yml" => serde_yaml::from_str(&content)
             .with_context(|| format!("解析 YAML 文件失败: {}", input))?,
-        _ => anyhow::bail!("不支持的格式: {}，支持 json 或 yaml", format),
+        "csv" => {
+            let parsed = parse_credentials_csv(&content)
+                .with_context(|| format!("解析 CSV 文件失败: {}", input))?;
+            for skipped in &parsed.skipped {
+                println!("跳过第 {} 行: {}", skipped.row, skipped.reason);
+            }
+            parsed.credentials.into_iter().map(|(_, cred)| cred).collect()
+        }
+        _ => anyhow::bail!("不支持的格式: {}，支持 json、yaml 或 csv", format),
     };
 
     if imported_credentials.is_empty() {
@@ -226,8 +248,96 @@ pub async fn import(input: &str, output: &str, format: &str) -> Result<()> {
     Ok(())
 }
 
+/// 从 Kiro IDE 本地缓存导入凭据（按 refreshToken 跳过已存在的凭据）
+pub async fn import_from_kiro_ide(
+    output: &str,
+    cache_dir: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    let cache_dirs = match cache_dir {
+        Some(dir) => vec![PathBuf::from(dir)],
+        None => kiro_ide::default_cache_dirs(),
+    };
+
+    let mut discovered = Vec::new();
+    for dir in &cache_dirs {
+        match kiro_ide::discover(dir) {
+            Ok(Some(found)) => discovered.push(found),
+            Ok(None) => tracing::info!("未找到 Kiro IDE 凭据: {}", dir.display()),
+            Err(e) => tracing::warn!("跳过 {}: {:#}", dir.display(), e),
+        }
+    }
+
+    if discovered.is_empty() {
+        println!("没有可导入的凭据（请先在 Kiro IDE 中登录）");
+        return Ok(());
+    }
+
+    let output_path = Path::new(output);
+    let mut existing_credentials = if output_path.exists() {
+        let config = CredentialsConfig::load(output_path)
+            .with_context(|| format!("加载目标凭据文件失败: {}", output))?;
+        config.into_sorted_credentials()
+    } else {
+        Vec::new()
+    };
+
+    let mut next_id = existing_credentials
+        .iter()
+        .filter_map(|c| c.id)
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    let mut added_count = 0;
+    for found in discovered {
+        let mut cred = found.credentials;
+        let duplicate = existing_credentials
+            .iter()
+            .any(|c| c.refresh_token == cred.refresh_token);
+
+        println!("来源: {}", found.source.display());
+        println!(
+            "  登录方式: {}",
+            found.provider.as_deref().unwrap_or("unknown")
+        );
+        println!("  认证方式: {}", cred.auth_method.as_deref().unwrap_or("unknown"));
+        println!("  Region: {}", cred.region.as_deref().unwrap_or("default"));
+        if let Some(ref expires_at) = cred.expires_at {
+            println!("  过期时间: {}", expires_at);
+        }
+        if duplicate {
+            println!("  已存在相同 refreshToken 的凭据，跳过\n");
+            continue;
+        }
+        println!();
+
+        cred.id = Some(next_id);
+        next_id += 1;
+        existing_credentials.push(cred);
+        added_count += 1;
+    }
+
+    if dry_run {
+        println!("[dry-run] 将导入 {} 个凭据到 {}", added_count, output);
+        return Ok(());
+    }
+
+    if added_count == 0 {
+        println!("没有新凭据需要导入");
+        return Ok(());
+    }
+
+    save_credentials(output_path, &existing_credentials)?;
+
+    println!("导入成功! 共导入 {} 个凭据", added_count);
+    println!("目标文件: {}", output);
+
+    Ok(())
+}
+
 /// 导出凭据
-pub async fn export(input: &str, output: &str, format: &str) -> Result<()> {
+pub async fn export(input: &str, output: &str, format: Option<&str>) -> Result<()> {
     let input_path = Path::new(input);
 
     if !input_path.exists() {
@@ -252,7 +362,8 @@ pub async fn export(input: &str, output: &str, format: &str) -> Result<()> {
             .with_context(|| format!("创建输出目录失败: {:?}", parent))?;
     }
 
-    let content = match format {
+    let format = resolve_format(format, output);
+    let content = match format.as_str() {
         "json" => serde_json::to_string_pretty(&credentials)
             .with_context(|| "序列化为 JSON 失败")?,
         "yaml" | "yml" => {
@@ -270,6 +381,17 @@ pub async fn export(input: &str, output: &str, format: &str) -> Result<()> {
     Ok(())
 }
 
+/// `list` 输出中备注的最大显示字符数
+const NOTES_PREVIEW_CHARS: usize = 50;
+
+/// 按字符截断文本，超出部分以 `...` 表示
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
 /// 保存凭据到文件
 fn save_credentials(path: &Path, credentials: &[KiroCredentials]) -> Result<()> {
     // 确保目录存在
@@ -278,11 +400,28 @@ fn save_credentials(path: &Path, credentials: &[KiroCredentials]) -> Result<()>
             .with_context(|| format!("创建目录失败: {:?}", parent))?;
     }
 
-    let content = serde_json::to_string_pretty(credentials)
-        .with_context(|| "序列化凭据失败")?;
-
-    fs::write(path, content)
+    // 按扩展名保持目标文件格式（.yaml/.yml 为 YAML，其他为 JSON）
+    CredentialsConfig::from(credentials.to_vec())
+        .save(path)
         .with_context(|| format!("写入凭据文件失败: {:?}", path))?;
 
     Ok(())
 }
+
+/// 解析文件格式参数（未指定时按文件扩展名推断，默认 json）
+fn resolve_format(format: Option<&str>, path: &str) -> String {
+    if let Some(format) = format {
+        return format.to_ascii_lowercase();
+    }
+
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("yaml") | Some("yml") => "yaml",
+        Some("csv") => "csv",
+        _ => "json",
+    }
+    .to_string()
+}
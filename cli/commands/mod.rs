@@ -1,3 +1,4 @@
 pub mod credentials;
 pub mod token;
 pub mod auth;
+pub mod config;
@@ -28,6 +28,10 @@ enum Commands {
     /// OAuth 登录链接生成
     #[command(subcommand)]
     Auth(AuthCommands),
+
+    /// 配置文件管理
+    #[command(subcommand)]
+    Config(ConfigCommands),
 }
 
 #[derive(Subcommand)]
@@ -84,16 +88,28 @@ enum CredentialsCommands {
     /// 导入凭据
     Import {
         /// 导入文件路径
-        #[arg(short, long)]
-        input: String,
+        #[arg(short, long, required_unless_present = "from_kiro_ide")]
+        input: Option<String>,
 
         /// 目标凭据文件路径
         #[arg(short, long, default_value = "config/credentials.json")]
         output: String,
 
-        /// 文件格式 (json/yaml)
-        #[arg(long, default_value = "json")]
-        format: String,
+        /// 文件格式 (json/yaml/csv)，未指定时按文件扩展名推断
+        #[arg(long)]
+        format: Option<String>,
+
+        /// 从 Kiro IDE 本地缓存（~/.aws/sso/cache）自动发现并导入凭据
+        #[arg(long, conflicts_with_all = ["input", "format"])]
+        from_kiro_ide: bool,
+
+        /// Kiro IDE 缓存目录（默认按系统自动发现）
+        #[arg(long, requires = "from_kiro_ide")]
+        cache_dir: Option<String>,
+
+        /// 仅打印将要导入的凭据，不写入文件
+        #[arg(long, requires = "from_kiro_ide")]
+        dry_run: bool,
     },
 
     /// 导出凭据
@@ -106,9 +122,9 @@ enum CredentialsCommands {
         #[arg(short, long)]
         output: String,
 
-        /// 文件格式 (json/yaml)
-        #[arg(long, default_value = "json")]
-        format: String,
+        /// 文件格式 (json/yaml)，未指定时按文件扩展名推断
+        #[arg(long)]
+        format: Option<String>,
     },
 }
 
@@ -170,6 +186,16 @@ enum AuthCommands {
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// 验证配置文件（✓ 有效 / ⚠ 警告 / ✗ 错误）
+    Validate {
+        /// 配置文件路径
+        #[arg(short, long, default_value = "config/config.json")]
+        file: String,
+    },
+}
+
 #[tokio::main]
 async fn main() {
     // 初始化日志
@@ -212,12 +238,27 @@ async fn main() {
                 input,
                 output,
                 format,
-            } => commands::credentials::import(&input, &output, &format).await,
+                from_kiro_ide,
+                cache_dir,
+                dry_run,
+            } => match input {
+                Some(input) if !from_kiro_ide => {
+                    commands::credentials::import(&input, &output, format.as_deref()).await
+                }
+                _ => {
+                    commands::credentials::import_from_kiro_ide(
+                        &output,
+                        cache_dir.as_deref(),
+                        dry_run,
+                    )
+                    .await
+                }
+            },
             CredentialsCommands::Export {
                 input,
                 output,
                 format,
-            } => commands::credentials::export(&input, &output, &format).await,
+            } => commands::credentials::export(&input, &output, format.as_deref()).await,
         },
         Commands::Token(cmd) => match cmd {
             TokenCommands::Scan { file } => commands::token::scan(&file).await,
@@ -235,6 +276,9 @@ async fn main() {
                 client_id,
             } => commands::auth::generate_login_link(&auth_method, &region, client_id).await,
         },
+        Commands::Config(cmd) => match cmd {
+            ConfigCommands::Validate { file } => commands::config::validate(&file).await,
+        },
     };
 
     if let Err(e) = result {
@@ -9,8 +9,13 @@ use axum::{
     response::IntoResponse,
 };
 
+use crate::common::i18n::{ErrorCode, Locale};
+
 use super::{
-    api_keys::{ApiKeyError, CreateApiKeyRequest, UpdateApiKeyRequest},
+    api_keys::{
+        ApiKeyError, BulkImportApiKeysRequest, CreateApiKeyRequest, MAX_BULK_IMPORT_API_KEYS,
+        UpdateApiKeyRequest,
+    },
     middleware::AdminState,
     types::{AdminErrorResponse, SuccessResponse},
 };
@@ -18,7 +23,19 @@ use super::{
 /// GET /api/admin/api-keys
 /// 获取所有 API Keys
 pub async fn get_api_keys(State(state): State<AdminState>) -> impl IntoResponse {
-    let keys = state.api_key_manager.list();
+    let mut keys = state.api_key_manager.list();
+    // 附带池名称，便于前端直接展示
+    if let Some(pm) = &state.pool_manager {
+        for key in &mut keys {
+            if let Some(binding) = &key.pool_id {
+                key.pool_names = binding
+                    .pool_ids()
+                    .into_iter()
+                    .map(|id| pm.with_pool(&id, |p| p.config.name.clone()).unwrap_or(id))
+                    .collect();
+            }
+        }
+    }
     Json(keys)
 }
 
@@ -26,43 +43,103 @@ pub async fn get_api_keys(State(state): State<AdminState>) -> impl IntoResponse
 /// 创建新 API Key
 pub async fn create_api_key(
     State(state): State<AdminState>,
+    locale: Locale,
     Json(payload): Json<CreateApiKeyRequest>,
 ) -> impl IntoResponse {
     match state.api_key_manager.create_with_full_key(payload) {
         Ok(key) => (StatusCode::CREATED, Json(key)).into_response(),
         Err(e) => match e {
-            ApiKeyError::DuplicateName(_) => (
+            ApiKeyError::DuplicateName(name) => (
+                StatusCode::CONFLICT,
+                Json(AdminErrorResponse::invalid_request(
+                    ErrorCode::ApiKeyDuplicateName.arg("name", name),
+                    locale,
+                )),
+            )
+                .into_response(),
+            ApiKeyError::LimitExceeded(max) => (
                 StatusCode::CONFLICT,
-                Json(AdminErrorResponse::invalid_request(e.to_string())),
+                Json(AdminErrorResponse::invalid_request(
+                    ErrorCode::ApiKeyLimitExceeded.arg("max", max),
+                    locale,
+                )),
             )
                 .into_response(),
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AdminErrorResponse::internal_error(format!("创建 API Key 失败: {}", e))),
+                Json(AdminErrorResponse::internal_error(
+                    ErrorCode::ApiKeyCreateFailed.arg("detail", e),
+                    locale,
+                )),
             )
                 .into_response(),
         },
     }
 }
 
+/// POST /api/admin/api-keys/bulk-import
+/// 批量导入 API Key（名称重复或超出数量上限的条目跳过）
+pub async fn bulk_import_api_keys(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Json(payload): Json<BulkImportApiKeysRequest>,
+) -> impl IntoResponse {
+    if payload.0.len() > MAX_BULK_IMPORT_API_KEYS {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(AdminErrorResponse::invalid_request(
+                ErrorCode::ApiKeyBulkImportTooLarge.arg("max", MAX_BULK_IMPORT_API_KEYS),
+                locale,
+            )),
+        )
+            .into_response();
+    }
+
+    match state.api_key_manager.bulk_import(payload.0) {
+        Ok(result) => {
+            tracing::info!(
+                "批量导入 API Key: 成功 {} 个，跳过 {} 个",
+                result.imported,
+                result.skipped.len()
+            );
+            Json(result).into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(AdminErrorResponse::internal_error(
+                ErrorCode::ApiKeyCreateFailed.arg("detail", e),
+                locale,
+            )),
+        )
+            .into_response(),
+    }
+}
+
 /// PUT /api/admin/api-keys/:id
 /// 更新 API Key
 pub async fn update_api_key(
     State(state): State<AdminState>,
+    locale: Locale,
     Path(id): Path<u64>,
     Json(payload): Json<UpdateApiKeyRequest>,
 ) -> impl IntoResponse {
     match state.api_key_manager.update(id, payload) {
         Ok(key) => Json(key).into_response(),
         Err(e) => match e {
-            ApiKeyError::NotFound(_) => (
+            ApiKeyError::NotFound(id) => (
                 StatusCode::NOT_FOUND,
-                Json(AdminErrorResponse::not_found(e.to_string())),
+                Json(AdminErrorResponse::not_found(
+                    ErrorCode::ApiKeyNotFound.arg("id", id),
+                    locale,
+                )),
             )
                 .into_response(),
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AdminErrorResponse::internal_error(e.to_string())),
+                Json(AdminErrorResponse::internal_error(
+                    ErrorCode::InternalError.arg("detail", e),
+                    locale,
+                )),
             )
                 .into_response(),
         },
@@ -73,19 +150,26 @@ pub async fn update_api_key(
 /// 删除 API Key
 pub async fn delete_api_key(
     State(state): State<AdminState>,
+    locale: Locale,
     Path(id): Path<u64>,
 ) -> impl IntoResponse {
     match state.api_key_manager.delete(id) {
         Ok(_) => Json(SuccessResponse::new(format!("API Key #{} 已删除", id))).into_response(),
         Err(e) => match e {
-            ApiKeyError::NotFound(_) => (
+            ApiKeyError::NotFound(id) => (
                 StatusCode::NOT_FOUND,
-                Json(AdminErrorResponse::not_found(e.to_string())),
+                Json(AdminErrorResponse::not_found(
+                    ErrorCode::ApiKeyNotFound.arg("id", id),
+                    locale,
+                )),
             )
                 .into_response(),
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AdminErrorResponse::internal_error(e.to_string())),
+                Json(AdminErrorResponse::internal_error(
+                    ErrorCode::InternalError.arg("detail", e),
+                    locale,
+                )),
             )
                 .into_response(),
         },
@@ -3,12 +3,26 @@
 //! 支持多 API Key 的 CRUD 操作，持久化到 api_keys.json
 
 use chrono::{DateTime, Utc};
+use dashmap::DashMap;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use thiserror::Error;
 
+use crate::common::persist::{Change, PersistWriter};
+
+/// API Key 数量上限默认值
+pub const DEFAULT_MAX_API_KEYS: usize = 1000;
+
+/// 单次批量导入的 API Key 数量上限
+pub const MAX_BULK_IMPORT_API_KEYS: usize = 100;
+
+/// 最近请求量统计的时间窗口（分钟）
+const RECENT_USAGE_MINUTES: i64 = 60;
+
 /// API Key 操作错误
 #[derive(Debug, Error)]
 pub enum ApiKeyError {
@@ -18,6 +32,9 @@ pub enum ApiKeyError {
     #[error("API Key 名称已存在: {0}")]
     DuplicateName(String),
 
+    #[error("API Key 数量已达上限: {0}")]
+    LimitExceeded(usize),
+
     #[error("保存失败: {0}")]
     PersistError(#[from] std::io::Error),
 
@@ -25,6 +42,68 @@ pub enum ApiKeyError {
     SerializeError(#[from] serde_json::Error),
 }
 
+/// API Key 绑定的池
+///
+/// 兼容两种格式：单个池 ID 字符串（含自动路由 `__auto__`），
+/// 或按顺序回退的池 ID 数组（如 `["premium", "overflow"]`）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PoolBinding {
+    /// 单个池
+    Single(String),
+    /// 按顺序回退的池列表
+    Ordered(Vec<String>),
+}
+
+impl PoolBinding {
+    /// 按回退顺序返回池 ID
+    pub fn pool_ids(&self) -> Vec<String> {
+        match self {
+            Self::Single(id) => vec![id.clone()],
+            Self::Ordered(ids) => ids.clone(),
+        }
+    }
+
+    /// 绑定中是否包含指定池
+    pub fn contains(&self, pool_id: &str) -> bool {
+        match self {
+            Self::Single(id) => id == pool_id,
+            Self::Ordered(ids) => ids.iter().any(|id| id == pool_id),
+        }
+    }
+
+    /// 将绑定中的 `old_pool_id` 替换为 `new_pool_id`，返回是否有修改
+    ///
+    /// 池列表中已包含 `new_pool_id` 时去除重复项（保留靠前的位置）
+    pub fn rename(&mut self, old_pool_id: &str, new_pool_id: &str) -> bool {
+        let mut changed = false;
+        let ids = match self {
+            Self::Single(id) => std::slice::from_mut(id),
+            Self::Ordered(ids) => ids.as_mut_slice(),
+        };
+        for id in ids.iter_mut().filter(|id| *id == old_pool_id) {
+            *id = new_pool_id.to_string();
+            changed = true;
+        }
+        if changed && let Self::Ordered(ids) = self {
+            let mut seen = std::collections::HashSet::new();
+            ids.retain(|id| seen.insert(id.clone()));
+        }
+        changed
+    }
+
+    /// 空列表视为未绑定
+    fn normalize(binding: Option<Self>) -> Option<Self> {
+        binding.filter(|b| !matches!(b, Self::Ordered(ids) if ids.is_empty()))
+    }
+}
+
+impl From<&str> for PoolBinding {
+    fn from(pool_id: &str) -> Self {
+        Self::Single(pool_id.to_string())
+    }
+}
+
 /// API Key 条目
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -43,10 +122,26 @@ pub struct ApiKey {
     /// 是否启用
     #[serde(default = "default_enabled")]
     pub enabled: bool,
-    /// 绑定的池 ID（未配置时使用默认池）
+    /// 绑定的池 ID 或按顺序回退的池 ID 列表（未配置时使用默认池）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool_id: Option<PoolBinding>,
+    /// WebSearch 每小时请求数上限（覆盖全局 websearchRateLimitPerHour）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub websearch_rate_limit_per_hour: Option<u64>,
+    /// 允许使用的模型（glob，支持 `*` 和 `?`，未配置时不限制）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_models: Option<Vec<String>>,
+    /// 禁止使用的模型（glob，优先于 allowed_models）
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub pool_id: Option<String>,
+    pub denied_models: Option<Vec<String>>,
+    /// max_tokens 上限（超过时截断为该值）
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens_limit: Option<u32>,
 }
 
 fn default_enabled() -> bool {
@@ -64,8 +159,19 @@ pub struct ApiKeyMasked {
     pub description: Option<String>,
     pub created_at: DateTime<Utc>,
     pub enabled: bool,
-    /// 绑定的池 ID
-    pub pool_id: Option<String>,
+    /// 绑定的池 ID 或池 ID 列表
+    pub pool_id: Option<PoolBinding>,
+    /// WebSearch 每小时请求数上限（None 表示使用全局配置）
+    pub websearch_rate_limit_per_hour: Option<u64>,
+    /// 允许使用的模型（None 表示不限制）
+    pub allowed_models: Option<Vec<String>>,
+    /// 禁止使用的模型
+    pub denied_models: Option<Vec<String>>,
+    /// max_tokens 上限
+    pub max_tokens_limit: Option<u32>,
+    /// 绑定的池名称（与池 ID 顺序一致，池不存在时为池 ID；仅列表接口返回）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pool_names: Vec<String>,
 }
 
 impl From<&ApiKey> for ApiKeyMasked {
@@ -84,8 +190,150 @@ impl From<&ApiKey> for ApiKeyMasked {
             created_at: key.created_at,
             enabled: key.enabled,
             pool_id: key.pool_id.clone(),
+            websearch_rate_limit_per_hour: key.websearch_rate_limit_per_hour,
+            allowed_models: key.allowed_models.clone(),
+            denied_models: key.denied_models.clone(),
+            max_tokens_limit: key.max_tokens_limit,
+            pool_names: Vec::new(),
+        }
+    }
+}
+
+/// API Key 最近请求量（按最近 1 小时的请求数排序，用于 Admin 仪表盘）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyUsageItem {
+    pub id: u64,
+    pub name: String,
+    /// 脱敏后的 Key（只显示前 8 位）
+    pub key: String,
+    /// 最近 1 小时的请求数
+    pub requests_last_hour: u64,
+}
+
+/// 单个 API Key 的最近请求量（按分钟计数，只保留最近 1 小时，仅保存在内存中）
+#[derive(Debug, Default)]
+struct RecentUsage {
+    /// `(Unix 分钟数, 请求数)`，最旧的在前
+    minutes: VecDeque<(i64, u64)>,
+}
+
+impl RecentUsage {
+    fn record(&mut self, minute: i64) {
+        match self.minutes.back_mut() {
+            Some((last, count)) if *last == minute => *count += 1,
+            _ => self.minutes.push_back((minute, 1)),
+        }
+        while self
+            .minutes
+            .front()
+            .is_some_and(|(m, _)| *m <= minute - RECENT_USAGE_MINUTES)
+        {
+            self.minutes.pop_front();
+        }
+    }
+
+    /// 截至 `minute` 的最近 1 小时请求数
+    fn total(&self, minute: i64) -> u64 {
+        self.minutes
+            .iter()
+            .filter(|(m, _)| *m > minute - RECENT_USAGE_MINUTES)
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+/// 当前 Unix 分钟数
+fn current_minute() -> i64 {
+    Utc::now().timestamp().div_euclid(60)
+}
+
+/// API Key 的模型访问策略（在调用上游前检查）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelPolicy {
+    /// 允许使用的模型（glob）
+    pub allowed_models: Option<Vec<String>>,
+    /// 禁止使用的模型（glob）
+    pub denied_models: Option<Vec<String>>,
+    /// max_tokens 上限
+    pub max_tokens_limit: Option<u32>,
+}
+
+impl ModelPolicy {
+    /// 模型是否允许使用（禁止列表优先；配置了允许列表时必须命中其中之一）
+    pub fn allows_model(&self, model: &str) -> bool {
+        let matches_any = |patterns: &Vec<String>| patterns.iter().any(|p| glob_match(p, model));
+        if self.denied_models.as_ref().is_some_and(matches_any) {
+            return false;
+        }
+        self.allowed_models.as_ref().is_none_or(matches_any)
+    }
+
+    /// 请求能否使用模型（按实际发往上游的模型判断）
+    ///
+    /// 请求的模型名按子串映射到上游模型（`upstream`），只检查原始名称时 `my-opus`
+    /// 之类的名称可以绕过 `claude-opus-*` 规则。上游模型 ID 同时按 `.` 替换为 `-` 的
+    /// 形式匹配（`claude-opus-4.5` 也命中 `claude-opus-4-5*`）；原始名称命中禁止列表时同样拒绝。
+    /// 无法映射的模型按原始名称判断
+    pub fn allows_request(&self, requested: &str, upstream: Option<&str>) -> bool {
+        let Some(upstream) = upstream else {
+            return self.allows_model(requested);
+        };
+        let dashed = upstream.replace('.', "-");
+        let upstream_names = [upstream, dashed.as_str()];
+        let matches_any = |patterns: &Vec<String>, names: &[&str]| {
+            patterns
+                .iter()
+                .any(|p| names.iter().any(|name| glob_match(p, name)))
+        };
+        if self
+            .denied_models
+            .as_ref()
+            .is_some_and(|p| matches_any(p, &[requested, upstream, &dashed]))
+        {
+            return false;
+        }
+        self.allowed_models
+            .as_ref()
+            .is_none_or(|p| matches_any(p, &upstream_names))
+    }
+
+    /// 按上限截断 max_tokens，返回 Some(截断后的值) 如果发生了截断
+    pub fn clamp_max_tokens(&self, max_tokens: i32) -> Option<i32> {
+        let limit = i32::try_from(self.max_tokens_limit?).unwrap_or(i32::MAX);
+        (max_tokens > limit).then_some(limit)
+    }
+}
+
+/// glob 匹配（`*` 匹配任意字符序列，`?` 匹配单个字符，忽略 ASCII 大小写）
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 `*` 的位置及其当前匹配到的文本位置，用于回溯
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c.eq_ignore_ascii_case(&text[t]) => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
         }
     }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 /// 创建 API Key 请求
@@ -98,9 +346,55 @@ pub struct CreateApiKeyRequest {
     /// 可选，如果不提供则自动生成
     #[serde(default)]
     pub key: Option<String>,
-    /// 绑定的池 ID
+    /// 绑定的池 ID 或按顺序回退的池 ID 列表
+    #[serde(default)]
+    pub pool_id: Option<PoolBinding>,
+    /// WebSearch 每小时请求数上限（不提供则使用全局配置）
+    #[serde(default)]
+    pub websearch_rate_limit_per_hour: Option<u64>,
+    /// 允许使用的模型（glob，不提供则不限制）
+    #[serde(default)]
+    pub allowed_models: Option<Vec<String>>,
+    /// 禁止使用的模型（glob）
     #[serde(default)]
-    pub pool_id: Option<String>,
+    pub denied_models: Option<Vec<String>>,
+    /// max_tokens 上限（不提供则不限制）
+    #[serde(default)]
+    pub max_tokens_limit: Option<u32>,
+}
+
+/// 批量导入 API Key 请求（JSON 数组）
+#[derive(Debug, Deserialize)]
+pub struct BulkImportApiKeysRequest(pub Vec<CreateApiKeyRequest>);
+
+/// 批量导入 API Key 响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkImportApiKeysResponse {
+    /// 导入成功的数量
+    pub imported: usize,
+    /// 跳过的条目及原因
+    pub skipped: Vec<SkippedApiKey>,
+    /// 新建的 API Key（含完整 Key，仅在此响应中返回）
+    pub created: Vec<CreatedApiKey>,
+}
+
+/// 批量导入时跳过的条目
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedApiKey {
+    pub name: String,
+    pub reason: String,
+}
+
+/// 批量导入时新建的 API Key
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedApiKey {
+    pub id: u64,
+    pub name: String,
+    /// 完整 Key
+    pub key: String,
 }
 
 /// 更新 API Key 请求
@@ -117,21 +411,38 @@ pub struct UpdateApiKeyRequest {
     /// - 不传此字段：不修改
     /// - 传 null：解绑（清除 pool_id）
     /// - 传字符串：绑定到指定池
+    /// - 传数组：按顺序回退绑定多个池
     #[serde(default, deserialize_with = "deserialize_optional_nullable")]
-    pub pool_id: Option<Option<String>>,
+    pub pool_id: Option<Option<PoolBinding>>,
+    /// WebSearch 每小时请求数上限
+    /// - 不传此字段：不修改
+    /// - 传 null：清除覆盖，使用全局配置
+    /// - 传数字：设置覆盖值
+    #[serde(default, deserialize_with = "deserialize_optional_nullable")]
+    pub websearch_rate_limit_per_hour: Option<Option<u64>>,
+    /// 允许使用的模型（不传不修改，传 null 清除限制）
+    #[serde(default, deserialize_with = "deserialize_optional_nullable")]
+    pub allowed_models: Option<Option<Vec<String>>>,
+    /// 禁止使用的模型（不传不修改，传 null 清除）
+    #[serde(default, deserialize_with = "deserialize_optional_nullable")]
+    pub denied_models: Option<Option<Vec<String>>>,
+    /// max_tokens 上限（不传不修改，传 null 清除）
+    #[serde(default, deserialize_with = "deserialize_optional_nullable")]
+    pub max_tokens_limit: Option<Option<u32>>,
 }
 
 /// 自定义反序列化器，用于区分 "字段不存在" 和 "字段为 null"
 /// - 字段不存在 -> None（不修改）
 /// - 字段为 null -> Some(None)（清除）
 /// - 字段有值 -> Some(Some(value))（设置）
-fn deserialize_optional_nullable<'de, D>(deserializer: D) -> Result<Option<Option<String>>, D::Error>
+fn deserialize_optional_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
 where
     D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
 {
-    // 如果字段存在，反序列化为 Option<String>
+    // 如果字段存在，反序列化为 Option<T>
     // null -> Some(None), "value" -> Some(Some("value"))
-    let value: Option<String> = Option::deserialize(deserializer)?;
+    let value: Option<T> = Option::deserialize(deserializer)?;
     Ok(Some(value))
 }
 
@@ -140,6 +451,12 @@ pub struct ApiKeyManager {
     keys: RwLock<Vec<ApiKey>>,
     file_path: PathBuf,
     next_id: RwLock<u64>,
+    /// 文件写入器（与其他管理器共享同一文件的写入线程）
+    writer: Arc<PersistWriter>,
+    /// API Key 数量上限
+    max_keys: usize,
+    /// 各 API Key（按 ID）最近 1 小时的请求量
+    usage: DashMap<u64, RecentUsage>,
 }
 
 impl ApiKeyManager {
@@ -153,13 +470,22 @@ impl ApiKeyManager {
 
         Ok(Self {
             keys: RwLock::new(keys),
+            writer: PersistWriter::for_path(&file_path),
             file_path,
             next_id: RwLock::new(max_id + 1),
+            max_keys: DEFAULT_MAX_API_KEYS,
+            usage: DashMap::new(),
         })
     }
 
+    /// 设置 API Key 数量上限
+    pub fn with_max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = max_keys;
+        self
+    }
+
     /// 从文件加载 API Keys
-    fn load_from_file(path: &Path) -> anyhow::Result<Vec<ApiKey>> {
+    pub(crate) fn load_from_file(path: &Path) -> anyhow::Result<Vec<ApiKey>> {
         if !path.exists() {
             return Ok(Vec::new());
         }
@@ -173,12 +499,55 @@ impl ApiKeyManager {
         Ok(keys)
     }
 
-    /// 保存到文件
-    fn persist(&self) -> Result<(), ApiKeyError> {
-        let keys = self.keys.read();
-        let content = serde_json::to_string_pretty(&*keys)?;
-        fs::write(&self.file_path, content)?;
-        Ok(())
+    /// 从文件重新加载（备份恢复后调用），返回加载的 API Key 数量
+    pub fn reload(&self) -> anyhow::Result<usize> {
+        let keys = Self::load_from_file(&self.file_path)?;
+        let count = keys.len();
+
+        *self.next_id.write() = keys.iter().map(|k| k.id).max().unwrap_or(0) + 1;
+        *self.keys.write() = keys;
+        Ok(count)
+    }
+
+    /// 保存到文件（经写入线程原子写入，并记录变更）
+    fn persist(&self, summary: String) -> Result<(), ApiKeyError> {
+        self.writer
+            .replace(Change::new("api_keys", summary), || {
+                let keys = self.keys.read();
+                Ok(serde_json::to_string_pretty(&*keys)?)
+            })
+            .wait_blocking()
+            .map_err(|e| ApiKeyError::PersistError(std::io::Error::other(format!("{:#}", e))))
+    }
+
+    /// 将所有绑定中的池 ID 从 `old_pool_id` 改为 `new_pool_id`
+    ///
+    /// 更新后的内容先交给 `commit` 写入（与池、凭据文件在同一事务中提交），
+    /// 写入成功后才更新内存；返回修改的 API Key 数量
+    pub fn rename_pool_binding<E>(
+        &self,
+        old_pool_id: &str,
+        new_pool_id: &str,
+        commit: impl FnOnce(&Path, String) -> Result<(), E>,
+    ) -> Result<usize, E>
+    where
+        E: From<serde_json::Error>,
+    {
+        let mut keys = self.keys.write();
+
+        let mut renamed_keys = keys.clone();
+        let mut renamed = 0;
+        for binding in renamed_keys.iter_mut().filter_map(|k| k.pool_id.as_mut()) {
+            if binding.rename(old_pool_id, new_pool_id) {
+                renamed += 1;
+            }
+        }
+
+        let content = serde_json::to_string_pretty(&renamed_keys)?;
+        commit(&self.file_path, content)?;
+
+        *keys = renamed_keys;
+        Ok(renamed)
     }
 
     /// 获取所有 API Keys（脱敏）
@@ -186,6 +555,16 @@ impl ApiKeyManager {
         self.keys.read().iter().map(ApiKeyMasked::from).collect()
     }
 
+    /// 获取显式绑定到指定池的 API Keys（脱敏）
+    pub fn bound_to_pool(&self, pool_id: &str) -> Vec<ApiKeyMasked> {
+        self.keys
+            .read()
+            .iter()
+            .filter(|k| k.pool_id.as_ref().is_some_and(|b| b.contains(pool_id)))
+            .map(ApiKeyMasked::from)
+            .collect()
+    }
+
     /// 验证 API Key 是否有效
     #[allow(dead_code)]
     pub fn validate(&self, key: &str) -> bool {
@@ -195,28 +574,99 @@ impl ApiKeyManager {
             .any(|k| k.enabled && k.key == key)
     }
 
-    /// 验证 API Key 并返回绑定的 pool_id
+    /// 验证 API Key 并返回绑定的池 ID 列表（按回退顺序）
     ///
-    /// 返回 Some(pool_id) 如果 Key 有效，pool_id 可能为 None（使用默认池）
+    /// 返回 Some(pool_ids) 如果 Key 有效，pool_ids 为空表示使用默认池
     /// 返回 None 如果 Key 无效或被禁用
-    pub fn validate_and_get_pool(&self, key: &str) -> Option<Option<String>> {
+    pub fn validate_and_get_pool(&self, key: &str) -> Option<Vec<String>> {
         self.keys
             .read()
             .iter()
             .find(|k| k.enabled && k.key == key)
-            .map(|k| k.pool_id.clone())
+            .map(|k| {
+                k.pool_id
+                    .as_ref()
+                    .map(PoolBinding::pool_ids)
+                    .unwrap_or_default()
+            })
+    }
+
+    /// 记录一次通过认证的请求（config.json 中的 `apiKey` 不在统计范围内）
+    pub fn record_request(&self, key: &str) {
+        let Some(id) = self.keys.read().iter().find(|k| k.key == key).map(|k| k.id) else {
+            return;
+        };
+        self.usage.entry(id).or_default().record(current_minute());
+    }
+
+    /// 最近 1 小时请求数最多的 `limit` 个 API Key（没有请求的 Key 不返回）
+    pub fn top_recent_usage(&self, limit: usize) -> Vec<ApiKeyUsageItem> {
+        let minute = current_minute();
+        let mut items: Vec<ApiKeyUsageItem> = self
+            .keys
+            .read()
+            .iter()
+            .filter_map(|k| {
+                let requests_last_hour = self.usage.get(&k.id)?.total(minute);
+                (requests_last_hour > 0).then(|| ApiKeyUsageItem {
+                    id: k.id,
+                    name: k.name.clone(),
+                    key: ApiKeyMasked::from(k).key,
+                    requests_last_hour,
+                })
+            })
+            .collect();
+        items.sort_by(|a, b| {
+            b.requests_last_hour
+                .cmp(&a.requests_last_hour)
+                .then(a.id.cmp(&b.id))
+        });
+        items.truncate(limit);
+        items
+    }
+
+    /// 获取 API Key 的 WebSearch 每小时限额覆盖值
+    ///
+    /// 返回 None 如果 Key 不存在或未配置覆盖（使用全局配置）
+    pub fn websearch_limit_override(&self, key: &str) -> Option<u64> {
+        self.keys
+            .read()
+            .iter()
+            .find(|k| k.key == key)
+            .and_then(|k| k.websearch_rate_limit_per_hour)
+    }
+
+    /// 获取 API Key 的模型访问策略
+    ///
+    /// 返回 None 如果 Key 不存在（如 config.json 中的 `apiKey`，不受限制）
+    pub fn model_policy(&self, key: &str) -> Option<ModelPolicy> {
+        self.keys
+            .read()
+            .iter()
+            .find(|k| k.key == key)
+            .map(|k| ModelPolicy {
+                allowed_models: k.allowed_models.clone(),
+                denied_models: k.denied_models.clone(),
+                max_tokens_limit: k.max_tokens_limit,
+            })
+    }
+
+    /// 检查能否创建指定名称的 API Key（名称唯一且未达数量上限）
+    fn check_can_create(&self, name: &str) -> Result<(), ApiKeyError> {
+        let keys = self.keys.read();
+        if keys.iter().any(|k| k.name == name) {
+            return Err(ApiKeyError::DuplicateName(name.to_string()));
+        }
+        if keys.len() >= self.max_keys {
+            return Err(ApiKeyError::LimitExceeded(self.max_keys));
+        }
+        Ok(())
     }
 
     /// 创建新的 API Key
     #[allow(dead_code)]
     pub fn create(&self, req: CreateApiKeyRequest) -> Result<ApiKeyMasked, ApiKeyError> {
-        // 检查名称唯一性
-        {
-            let keys = self.keys.read();
-            if keys.iter().any(|k| k.name == req.name) {
-                return Err(ApiKeyError::DuplicateName(req.name));
-            }
-        }
+        self.check_can_create(&req.name)?;
 
         let key_value = req.key.unwrap_or_else(|| Self::generate_key());
 
@@ -234,29 +684,28 @@ impl ApiKeyManager {
             description: req.description,
             created_at: Utc::now(),
             enabled: true,
-            pool_id: req.pool_id,
+            pool_id: PoolBinding::normalize(req.pool_id),
+            websearch_rate_limit_per_hour: req.websearch_rate_limit_per_hour,
+            allowed_models: req.allowed_models,
+            denied_models: req.denied_models,
+            max_tokens_limit: req.max_tokens_limit,
         };
 
         let masked = ApiKeyMasked::from(&api_key);
+        let summary = format!("创建 API Key #{} ({})", id, api_key.name);
 
         {
             let mut keys = self.keys.write();
             keys.push(api_key);
         }
 
-        self.persist()?;
+        self.persist(summary)?;
         Ok(masked)
     }
 
     /// 创建新的 API Key（返回完整 Key，仅在创建时使用）
     pub fn create_with_full_key(&self, req: CreateApiKeyRequest) -> Result<ApiKey, ApiKeyError> {
-        // 检查名称唯一性
-        {
-            let keys = self.keys.read();
-            if keys.iter().any(|k| k.name == req.name) {
-                return Err(ApiKeyError::DuplicateName(req.name));
-            }
-        }
+        self.check_can_create(&req.name)?;
 
         let key_value = req.key.unwrap_or_else(|| Self::generate_key());
 
@@ -274,20 +723,61 @@ impl ApiKeyManager {
             description: req.description,
             created_at: Utc::now(),
             enabled: true,
-            pool_id: req.pool_id,
+            pool_id: PoolBinding::normalize(req.pool_id),
+            websearch_rate_limit_per_hour: req.websearch_rate_limit_per_hour,
+            allowed_models: req.allowed_models,
+            denied_models: req.denied_models,
+            max_tokens_limit: req.max_tokens_limit,
         };
 
         let result = api_key.clone();
+        let summary = format!("创建 API Key #{} ({})", id, api_key.name);
 
         {
             let mut keys = self.keys.write();
             keys.push(api_key);
         }
 
-        self.persist()?;
+        self.persist(summary)?;
         Ok(result)
     }
 
+    /// 批量创建 API Key（返回完整 Key，仅在创建时使用）
+    ///
+    /// 名称重复或已达数量上限的条目跳过并记录原因，其余错误（如写入失败）立即返回
+    pub fn bulk_import(
+        &self,
+        items: Vec<CreateApiKeyRequest>,
+    ) -> Result<BulkImportApiKeysResponse, ApiKeyError> {
+        let mut skipped = Vec::new();
+        let mut created = Vec::new();
+
+        for item in items {
+            let name = item.name.clone();
+            match self.create_with_full_key(item) {
+                Ok(key) => created.push(CreatedApiKey {
+                    id: key.id,
+                    name: key.name,
+                    key: key.key,
+                }),
+                Err(e @ (ApiKeyError::DuplicateName(_) | ApiKeyError::LimitExceeded(_))) => {
+                    tracing::warn!("批量导入跳过 API Key {}: {}", name, e);
+                    skipped.push(SkippedApiKey {
+                        name,
+                        reason: e.to_string(),
+                    });
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(BulkImportApiKeysResponse {
+            imported: created.len(),
+            skipped,
+            created,
+        })
+    }
+
     /// 更新 API Key
     pub fn update(&self, id: u64, req: UpdateApiKeyRequest) -> Result<ApiKeyMasked, ApiKeyError> {
         let mut keys = self.keys.write();
@@ -309,15 +799,27 @@ impl ApiKeyManager {
         // pool_id 处理：
         // - None: 不修改
         // - Some(None): 解绑（清除 pool_id）
-        // - Some(Some(value)): 绑定到指定池
+        // - Some(Some(value)): 绑定到指定池（或池列表）
         if let Some(pool_id_option) = req.pool_id {
-            key.pool_id = pool_id_option;
+            key.pool_id = PoolBinding::normalize(pool_id_option);
+        }
+        if let Some(limit_option) = req.websearch_rate_limit_per_hour {
+            key.websearch_rate_limit_per_hour = limit_option;
+        }
+        if let Some(allowed_models) = req.allowed_models {
+            key.allowed_models = allowed_models;
+        }
+        if let Some(denied_models) = req.denied_models {
+            key.denied_models = denied_models;
+        }
+        if let Some(max_tokens_limit) = req.max_tokens_limit {
+            key.max_tokens_limit = max_tokens_limit;
         }
 
         let masked = ApiKeyMasked::from(&*key);
         drop(keys);
 
-        self.persist()?;
+        self.persist(format!("更新 API Key #{} ({})", id, masked.name))?;
         Ok(masked)
     }
 
@@ -330,10 +832,11 @@ impl ApiKeyManager {
             .position(|k| k.id == id)
             .ok_or(ApiKeyError::NotFound(id))?;
 
-        keys.remove(pos);
+        let removed = keys.remove(pos);
         drop(keys);
+        self.usage.remove(&id);
 
-        self.persist()?;
+        self.persist(format!("删除 API Key #{} ({})", id, removed.name))?;
         Ok(())
     }
 
@@ -381,6 +884,56 @@ mod tests {
         assert_eq!(key.len(), 35); // "sk-" + 32 chars
     }
 
+    #[test]
+    fn test_recent_usage_window() {
+        let mut usage = RecentUsage::default();
+        usage.record(100);
+        usage.record(100);
+        usage.record(130);
+        assert_eq!(usage.total(130), 3);
+        // 超出 1 小时的分钟桶不计入并被丢弃
+        assert_eq!(usage.total(160), 1);
+        usage.record(160);
+        assert_eq!(usage.minutes.len(), 2);
+        assert_eq!(usage.total(160), 2);
+    }
+
+    #[test]
+    fn test_top_recent_usage() {
+        let dir = tempdir().unwrap();
+        let manager = ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap();
+        let create = |name: &str| {
+            manager
+                .create_with_full_key(CreateApiKeyRequest {
+                    name: name.to_string(),
+                    description: None,
+                    key: None,
+                    pool_id: None,
+                    websearch_rate_limit_per_hour: None,
+                    allowed_models: None,
+                    denied_models: None,
+                    max_tokens_limit: None,
+                })
+                .unwrap()
+        };
+        let (a, b, idle) = (create("a"), create("b"), create("idle"));
+        manager.record_request(&a.key);
+        for _ in 0..3 {
+            manager.record_request(&b.key);
+        }
+        manager.record_request("unknown-key");
+
+        let top = manager.top_recent_usage(5);
+        let summary: Vec<_> = top.iter().map(|i| (i.id, i.requests_last_hour)).collect();
+        assert_eq!(summary, vec![(b.id, 3), (a.id, 1)]);
+        assert!(top[0].key.ends_with("***"));
+        assert!(!top.iter().any(|i| i.id == idle.id));
+        assert_eq!(manager.top_recent_usage(1).len(), 1);
+
+        manager.delete(b.id).unwrap();
+        assert_eq!(manager.top_recent_usage(5)[0].id, a.id);
+    }
+
     #[test]
     fn test_api_key_crud() {
         let dir = tempdir().unwrap();
@@ -395,6 +948,10 @@ mod tests {
                 description: Some("Test description".to_string()),
                 key: None,
                 pool_id: None,
+                websearch_rate_limit_per_hour: None,
+                allowed_models: None,
+                denied_models: None,
+                max_tokens_limit: None,
             })
             .unwrap();
 
@@ -419,6 +976,10 @@ mod tests {
                     description: None,
                     enabled: Some(false),
                     pool_id: None, // 不修改 pool_id
+                    websearch_rate_limit_per_hour: None,
+                    allowed_models: None,
+                    denied_models: None,
+                    max_tokens_limit: None,
                 },
             )
             .unwrap();
@@ -447,15 +1008,19 @@ mod tests {
                 name: "Premium Key".to_string(),
                 description: None,
                 key: None,
-                pool_id: Some("premium".to_string()),
+                pool_id: Some("premium".into()),
+                websearch_rate_limit_per_hour: None,
+                allowed_models: None,
+                denied_models: None,
+                max_tokens_limit: None,
             })
             .unwrap();
 
-        assert_eq!(key.pool_id, Some("premium".to_string()));
+        assert_eq!(key.pool_id, Some("premium".into()));
 
         // Validate and get pool
         let pool_id = manager.validate_and_get_pool(&key.key);
-        assert_eq!(pool_id, Some(Some("premium".to_string())));
+        assert_eq!(pool_id, Some(vec!["premium".to_string()]));
 
         // Update pool_id
         let updated = manager
@@ -465,12 +1030,16 @@ mod tests {
                     name: None,
                     description: None,
                     enabled: None,
-                    pool_id: Some(Some("default".to_string())), // 绑定到 default 池
+                    pool_id: Some(Some("default".into())), // 绑定到 default 池
+                    websearch_rate_limit_per_hour: None,
+                    allowed_models: None,
+                    denied_models: None,
+                    max_tokens_limit: None,
                 },
             )
             .unwrap();
 
-        assert_eq!(updated.pool_id, Some("default".to_string()));
+        assert_eq!(updated.pool_id, Some("default".into()));
 
         // Unbind pool_id (set to null)
         let unbound = manager
@@ -481,10 +1050,220 @@ mod tests {
                     description: None,
                     enabled: None,
                     pool_id: Some(None), // 解绑
+                    websearch_rate_limit_per_hour: None,
+                    allowed_models: None,
+                    denied_models: None,
+                    max_tokens_limit: None,
                 },
             )
             .unwrap();
 
         assert_eq!(unbound.pool_id, None);
+        assert_eq!(manager.validate_and_get_pool(&key.key), Some(vec![]));
+    }
+
+    #[test]
+    fn test_api_key_with_ordered_pools() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("api_keys.json");
+
+        // 旧格式（单个字符串）与新格式（数组）均可直接加载
+        fs::write(
+            &file_path,
+            r#"[
+                {"id": 1, "name": "old", "key": "sk-old", "createdAt": "2025-01-01T00:00:00Z", "poolId": "premium"},
+                {"id": 2, "name": "new", "key": "sk-new", "createdAt": "2025-01-01T00:00:00Z", "poolId": ["premium", "overflow"]}
+            ]"#,
+        )
+        .unwrap();
+        let manager = ApiKeyManager::new(&file_path).unwrap();
+
+        assert_eq!(
+            manager.validate_and_get_pool("sk-old"),
+            Some(vec!["premium".to_string()])
+        );
+        assert_eq!(
+            manager.validate_and_get_pool("sk-new"),
+            Some(vec!["premium".to_string(), "overflow".to_string()])
+        );
+
+        // 保存后单个字符串仍写回为字符串
+        let req: UpdateApiKeyRequest =
+            serde_json::from_str(r#"{"poolId": ["overflow", "premium"]}"#).unwrap();
+        manager.update(2, req).unwrap();
+        let saved: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&file_path).unwrap()).unwrap();
+        assert_eq!(saved[0]["poolId"], "premium");
+        assert_eq!(
+            saved[1]["poolId"],
+            serde_json::json!(["overflow", "premium"])
+        );
+
+        // 空数组视为未绑定
+        let req: UpdateApiKeyRequest = serde_json::from_str(r#"{"poolId": []}"#).unwrap();
+        let updated = manager.update(2, req).unwrap();
+        assert_eq!(updated.pool_id, None);
+    }
+
+    #[test]
+    fn test_api_key_websearch_limit_override() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("api_keys.json");
+
+        let manager = ApiKeyManager::new(&file_path).unwrap();
+
+        let key = manager
+            .create_with_full_key(CreateApiKeyRequest {
+                name: "Search Key".to_string(),
+                description: None,
+                key: None,
+                pool_id: None,
+                websearch_rate_limit_per_hour: Some(5),
+                allowed_models: None,
+                denied_models: None,
+                max_tokens_limit: None,
+            })
+            .unwrap();
+
+        assert_eq!(manager.websearch_limit_override(&key.key), Some(5));
+        assert_eq!(manager.websearch_limit_override("invalid-key"), None);
+
+        // 清除覆盖值
+        let updated: UpdateApiKeyRequest =
+            serde_json::from_str(r#"{"websearchRateLimitPerHour": null}"#).unwrap();
+        manager.update(key.id, updated).unwrap();
+        assert_eq!(manager.websearch_limit_override(&key.key), None);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("claude-sonnet-*", "claude-sonnet-4-5-20250929"));
+        assert!(glob_match("*OPUS*", "claude-opus-4-5"));
+        assert!(glob_match("claude-haiku-4-?", "claude-haiku-4-5"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("claude-haiku-4-?", "claude-haiku-4-50"));
+        assert!(!glob_match("claude-sonnet-*", "claude-opus-4-5"));
+    }
+
+    #[test]
+    fn test_api_key_model_policy() {
+        let dir = tempdir().unwrap();
+        let manager = ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap();
+
+        let key = manager
+            .create_with_full_key(CreateApiKeyRequest {
+                name: "Collaborator".to_string(),
+                description: None,
+                key: None,
+                pool_id: None,
+                websearch_rate_limit_per_hour: None,
+                allowed_models: Some(vec!["claude-*".to_string()]),
+                denied_models: Some(vec!["*opus*".to_string()]),
+                max_tokens_limit: Some(8192),
+            })
+            .unwrap();
+        assert_eq!(manager.model_policy("invalid-key"), None);
+
+        let policy = manager.model_policy(&key.key).unwrap();
+        assert!(policy.allows_model("claude-sonnet-4-5"));
+        // 禁止列表优先于允许列表
+        assert!(!policy.allows_model("claude-opus-4-5"));
+        assert!(!policy.allows_model("gpt-4o"));
+        // 按映射后的上游模型判断，别名无法绕过禁止规则
+        assert!(policy.allows_request("claude-sonnet-4-5", Some("claude-sonnet-4.5")));
+        assert!(!policy.allows_request("claude-haiku-opus", Some("claude-opus-4.6")));
+        let haiku_only = ModelPolicy {
+            allowed_models: Some(vec!["claude-haiku-4-5*".to_string()]),
+            ..Default::default()
+        };
+        assert!(haiku_only.allows_request("claude-haiku-4-5-20251001", Some("claude-haiku-4.5")));
+        assert!(!haiku_only.allows_request("claude-haiku-4-5-opus", Some("claude-opus-4.6")));
+        assert!(!haiku_only.allows_request("gpt-4o", None));
+        assert_eq!(policy.clamp_max_tokens(32000), Some(8192));
+        assert_eq!(policy.clamp_max_tokens(8192), None);
+
+        // 传 null 清除限制
+        let updated: UpdateApiKeyRequest = serde_json::from_str(
+            r#"{"allowedModels": null, "deniedModels": null, "maxTokensLimit": null}"#,
+        )
+        .unwrap();
+        let masked = manager.update(key.id, updated).unwrap();
+        assert_eq!(masked.allowed_models, None);
+        assert_eq!(manager.model_policy(&key.key), Some(ModelPolicy::default()));
+        assert!(ModelPolicy::default().allows_model("claude-opus-4-5"));
+    }
+
+    #[test]
+    fn test_bulk_import_skips_duplicate_names() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("api_keys.json");
+
+        let manager = ApiKeyManager::new(&file_path).unwrap();
+        manager
+            .create_with_full_key(CreateApiKeyRequest {
+                name: "existing".to_string(),
+                description: None,
+                key: None,
+                pool_id: None,
+                websearch_rate_limit_per_hour: None,
+                allowed_models: None,
+                denied_models: None,
+                max_tokens_limit: None,
+            })
+            .unwrap();
+
+        // 10 个条目：1 个与已有 Key 重名，1 个与数组内前一个条目重名
+        let mut items: Vec<serde_json::Value> = (0..8)
+            .map(|i| serde_json::json!({"name": format!("team-{}", i), "poolId": "premium"}))
+            .collect();
+        items.insert(3, serde_json::json!({"name": "existing"}));
+        items.push(serde_json::json!({"name": "team-2", "key": "sk-duplicate-key"}));
+        let request: BulkImportApiKeysRequest =
+            serde_json::from_value(serde_json::Value::Array(items)).unwrap();
+        assert_eq!(request.0.len(), 10);
+
+        let result = manager.bulk_import(request.0).unwrap();
+        assert_eq!(result.imported, 8);
+        assert_eq!(result.created.len(), 8);
+        let skipped: Vec<&str> = result.skipped.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(skipped, vec!["existing", "team-2"]);
+        assert!(result.skipped[0].reason.contains("existing"));
+
+        // 响应包含完整 Key，可直接用于认证
+        assert!(result.created.iter().all(|k| manager.validate(&k.key)));
+        assert_eq!(manager.count(), 9);
+        let reloaded = ApiKeyManager::new(&file_path).unwrap();
+        assert_eq!(reloaded.count(), 9);
+    }
+
+    #[test]
+    fn test_api_key_limit() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("api_keys.json");
+
+        let manager = ApiKeyManager::new(&file_path).unwrap().with_max_keys(2);
+        let items: Vec<CreateApiKeyRequest> = (0..3)
+            .map(|i| {
+                serde_json::from_value(serde_json::json!({"name": format!("key-{}", i)})).unwrap()
+            })
+            .collect();
+
+        let result = manager.bulk_import(items).unwrap();
+        assert_eq!(result.imported, 2);
+        assert_eq!(result.skipped[0].name, "key-2");
+
+        let err = manager
+            .create_with_full_key(CreateApiKeyRequest {
+                name: "extra".to_string(),
+                description: None,
+                key: None,
+                pool_id: None,
+                websearch_rate_limit_per_hour: None,
+                allowed_models: None,
+                denied_models: None,
+                max_tokens_limit: None,
+            })
+            .unwrap_err();
+        assert!(matches!(err, ApiKeyError::LimitExceeded(2)));
     }
 }
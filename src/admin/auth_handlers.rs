@@ -0,0 +1,121 @@
+//! Admin 登录相关 HTTP 处理器
+//!
+//! 生成 Admin UI 一次性登录链接，便于从监控面板等外部系统直接打开管理后台；
+//! 注销登录会话
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{NestedPath, Query, State, rejection::NestedPathRejection},
+    http::{Extensions, HeaderMap, HeaderValue, StatusCode, header},
+    response::{AppendHeaders, IntoResponse, Response},
+};
+
+use crate::common::auth;
+use crate::common::i18n::{ErrorCode, Locale};
+
+use super::{
+    magic_link,
+    middleware::AdminState,
+    types::{AdminErrorResponse, LogoutQuery, LogoutResponse, MagicLinkRequest, MagicLinkResponse},
+};
+
+/// POST /api/admin/auth/magic-link
+/// 生成一次性登录链接（15 分钟内有效）
+///
+/// Admin Key 可放在 `x-api-key` / `Authorization: Bearer` 头中，或请求体 `{"adminKey": "..."}`。
+/// 同一客户端 IP 在 1 分钟内校验失败 10 次后返回 429，直到统计窗口结束
+pub async fn create_magic_link(
+    State(state): State<AdminState>,
+    locale: Locale,
+    nested_path: Result<NestedPath, NestedPathRejection>,
+    extensions: Extensions,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let client = magic_link::client_key(&extensions);
+    if let Some(wait) = magic_link::attempts_blocked(&client) {
+        let retry_after = wait.as_secs().max(1);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, HeaderValue::from(retry_after))],
+            Json(AdminErrorResponse::rate_limited(
+                ErrorCode::MagicLinkRateLimited.arg("retry_after", retry_after),
+                locale,
+            )),
+        )
+            .into_response();
+    }
+
+    let payload = if body.is_empty() {
+        MagicLinkRequest::default()
+    } else {
+        match serde_json::from_slice::<MagicLinkRequest>(&body) {
+            Ok(payload) => payload,
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(AdminErrorResponse::invalid_request(
+                        ErrorCode::InvalidJson.arg("detail", e),
+                        locale,
+                    )),
+                )
+                    .into_response();
+            }
+        }
+    };
+
+    let admin_key = auth::extract_api_key_from_headers(&headers).or(payload.admin_key);
+    let Some(admin_key) = admin_key.filter(|key| auth::constant_time_eq(key, &state.admin_api_key))
+    else {
+        magic_link::record_failed_attempt(&client);
+        tracing::warn!("生成 Admin UI 登录链接时 Admin Key 校验失败: {}", client);
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(AdminErrorResponse::authentication_error(locale)),
+        )
+            .into_response();
+    };
+
+    let link = magic_link::create_magic_link(&admin_key);
+    tracing::info!(
+        "已生成 Admin UI 一次性登录链接，有效期至 {}",
+        link.expires_at
+    );
+
+    let nested_path = nested_path.ok();
+    let paths = magic_link::AdminPaths::from_api_path(nested_path.as_ref().map(NestedPath::as_str));
+    Json(MagicLinkResponse {
+        login_url: link.login_url(&paths),
+        expires_at: link.expires_at.to_rfc3339(),
+        token: link.token,
+    })
+    .into_response()
+}
+
+/// POST /api/admin/auth/logout
+/// 注销当前登录会话并清除会话 Cookie；`?all=true` 时注销所有会话
+pub async fn logout(
+    nested_path: Result<NestedPath, NestedPathRejection>,
+    extensions: Extensions,
+    headers: HeaderMap,
+    Query(query): Query<LogoutQuery>,
+) -> Response {
+    let revoked_sessions = if query.all {
+        magic_link::revoke_all_sessions()
+    } else {
+        magic_link::revoke_sessions(&headers)
+    };
+    tracing::info!("已注销 {} 个 Admin UI 登录会话", revoked_sessions);
+
+    let nested_path = nested_path.ok();
+    let paths = magic_link::AdminPaths::from_api_path(nested_path.as_ref().map(NestedPath::as_str));
+    let secure = magic_link::is_https(&headers, &extensions);
+    (
+        AppendHeaders(
+            magic_link::expired_session_cookies(&paths, secure).map(|c| (header::SET_COOKIE, c)),
+        ),
+        Json(LogoutResponse { revoked_sessions }),
+    )
+        .into_response()
+}
@@ -0,0 +1,722 @@
+//! 配置文件自动备份
+//!
+//! 定期将 config、credentials、pools、api_keys 文件复制到备份目录下的时间戳子目录，
+//! 内容与最近一次备份相同时跳过，超出保留数量时删除最旧的备份。
+//! 恢复时先校验快照中的所有文件，任一文件无效则不做任何修改；全部通过后经各文件的
+//! 持久化写入线程写回（与运行时的回写排队，不会互相覆盖）。
+//! 凭据文件按 ID 合并：仍存在的凭据保留当前的 Token，避免恢复已被刷新轮换掉的 refreshToken。
+//! 代理服务和各运行组件持有启动时的配置，配置项无法在运行时切换，
+//! 因此快照中的配置与运行配置不同时拒绝恢复
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use super::api_keys::ApiKeyManager;
+use crate::common::file_format::{FileFormat, parse_by_path};
+use crate::common::persist::{Change, PersistWriter, write_atomic};
+use crate::kiro::model::credentials::CredentialsConfig;
+use crate::kiro::pool::PoolsConfig;
+use crate::model::config::Config;
+
+/// 快照清单文件名
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// 快照目录名格式（UTC，按字典序即时间顺序）
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// 未设置时随机取默认值的配置项
+const RANDOM_DEFAULT_CONFIG_FIELDS: [&str; 1] = ["systemVersion"];
+
+/// 刷新时会轮换的凭据字段（恢复时保留当前文件中的值）
+const TOKEN_FIELDS: [&str; 3] = ["accessToken", "refreshToken", "expiresAt"];
+
+/// 备份的文件类型（决定恢复前的校验方式和恢复后的重新加载路径）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupKind {
+    Config,
+    Credentials,
+    Pools,
+    ApiKeys,
+}
+
+impl BackupKind {
+    /// 校验快照中的文件可被正常加载
+    fn validate(self, path: &Path) -> anyhow::Result<()> {
+        match self {
+            Self::Config => {
+                let config = Config::load(path)?;
+                config
+                    .validate()
+                    .map_err(|errors| anyhow::anyhow!(errors.join("; ")))?;
+            }
+            Self::Credentials => {
+                CredentialsConfig::load(path)?;
+            }
+            Self::Pools => {
+                PoolsConfig::load(path)?;
+            }
+            Self::ApiKeys => {
+                ApiKeyManager::load_from_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 快照清单（写入每个快照目录的 manifest.json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupManifest {
+    created_at: DateTime<Utc>,
+    /// 所有文件内容的 SHA-256（用于跳过未变化的备份周期）
+    hash: String,
+    files: Vec<BackupFileEntry>,
+}
+
+/// 快照中的单个文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupFileEntry {
+    pub kind: BackupKind,
+    /// 文件名（与源文件同名）
+    pub name: String,
+    pub size_bytes: u64,
+}
+
+/// 快照信息（Admin API）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    /// 快照标识（目录名，如 `20260101T000000Z`）
+    pub timestamp: String,
+    pub created_at: DateTime<Utc>,
+    pub files: Vec<BackupFileEntry>,
+}
+
+/// 备份错误
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    /// 快照不存在
+    #[error("备份不存在: {0}")]
+    NotFound(String),
+    /// 快照中的文件校验失败（未做任何修改）
+    #[error("备份中的 {file} 校验失败: {detail}")]
+    Invalid { file: String, detail: String },
+    /// 快照中的配置与运行配置不同，这些配置项需要重启服务才能生效（未做任何修改）
+    #[error("备份中的配置项与运行配置不同，需要重启服务才能生效: {}", fields.join(", "))]
+    RequiresRestart { fields: Vec<String> },
+    /// 读写失败
+    #[error(transparent)]
+    Io(#[from] anyhow::Error),
+}
+
+/// 备份管理器
+pub struct BackupManager {
+    /// 备份根目录
+    dir: PathBuf,
+    /// 需要备份的源文件
+    sources: Vec<(BackupKind, PathBuf)>,
+    /// 保留的快照数量
+    retention: usize,
+    /// 备份与恢复互斥
+    lock: Mutex<()>,
+}
+
+impl BackupManager {
+    /// 创建备份管理器（`retention` 至少为 1）
+    pub fn new(dir: impl Into<PathBuf>, retention: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            sources: Vec::new(),
+            retention: retention.max(1),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// 添加需要备份的源文件
+    pub fn with_source(mut self, kind: BackupKind, path: impl Into<PathBuf>) -> Self {
+        self.sources.push((kind, path.into()));
+        self
+    }
+
+    /// 指定类型的源文件路径
+    pub fn source_path(&self, kind: BackupKind) -> Option<&Path> {
+        self.sources
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, path)| path.as_path())
+    }
+
+    /// 立即备份
+    ///
+    /// 内容与最近一次快照相同时跳过并返回 None，否则返回新快照的标识
+    pub fn backup_now(&self) -> anyhow::Result<Option<String>> {
+        let _guard = self.lock.lock();
+        self.backup_locked()
+    }
+
+    fn backup_locked(&self) -> anyhow::Result<Option<String>> {
+        // 读取源文件（不存在的文件不备份）
+        let mut contents = Vec::new();
+        for (kind, path) in &self.sources {
+            match fs::read(path) {
+                Ok(bytes) => {
+                    let name = file_name(path)?;
+                    contents.push((*kind, name, bytes));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).with_context(|| format!("读取文件失败: {:?}", path)),
+            }
+        }
+
+        let mut hasher = Sha256::new();
+        for (_, name, bytes) in &contents {
+            hasher.update(name.as_bytes());
+            hasher.update([0]);
+            hasher.update((bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        }
+        let hash = hex::encode(hasher.finalize());
+
+        if self
+            .read_manifests()?
+            .first()
+            .is_some_and(|(_, manifest)| manifest.hash == hash)
+        {
+            tracing::debug!("配置文件未变化，跳过本次备份");
+            return Ok(None);
+        }
+
+        let created_at = Utc::now();
+        let timestamp = self.next_timestamp(created_at);
+        let snapshot_dir = self.dir.join(&timestamp);
+        fs::create_dir_all(&snapshot_dir)
+            .with_context(|| format!("创建备份目录失败: {:?}", snapshot_dir))?;
+
+        let mut files = Vec::with_capacity(contents.len());
+        for (kind, name, bytes) in contents {
+            let path = snapshot_dir.join(&name);
+            fs::write(&path, &bytes).with_context(|| format!("写入备份失败: {:?}", path))?;
+            files.push(BackupFileEntry {
+                kind,
+                name,
+                size_bytes: bytes.len() as u64,
+            });
+        }
+
+        // 清单最后写入：缺少清单的目录不会被视为有效快照
+        let manifest = BackupManifest {
+            created_at,
+            hash,
+            files,
+        };
+        fs::write(
+            snapshot_dir.join(MANIFEST_FILE),
+            serde_json::to_string_pretty(&manifest)?,
+        )
+        .with_context(|| format!("写入备份清单失败: {:?}", snapshot_dir))?;
+
+        self.prune()?;
+        tracing::info!("已创建配置备份: {}", timestamp);
+        Ok(Some(timestamp))
+    }
+
+    /// 生成快照目录名（同一秒内多次备份时追加序号）
+    fn next_timestamp(&self, created_at: DateTime<Utc>) -> String {
+        let base = created_at.format(TIMESTAMP_FORMAT).to_string();
+        let mut timestamp = base.clone();
+        let mut seq = 1;
+        while self.dir.join(&timestamp).exists() {
+            timestamp = format!("{}-{}", base, seq);
+            seq += 1;
+        }
+        timestamp
+    }
+
+    /// 删除超出保留数量的最旧快照
+    fn prune(&self) -> anyhow::Result<()> {
+        for (timestamp, _) in self.read_manifests()?.into_iter().skip(self.retention) {
+            let path = self.dir.join(&timestamp);
+            match fs::remove_dir_all(&path) {
+                Ok(()) => tracing::info!("已删除过期备份: {}", timestamp),
+                Err(e) => tracing::warn!("删除过期备份失败: {:?}: {}", path, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取所有有效快照（按时间倒序）
+    fn read_manifests(&self) -> anyhow::Result<Vec<(String, BackupManifest)>> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("读取备份目录失败: {:?}", self.dir)),
+        };
+
+        let mut manifests = Vec::new();
+        for entry in read_dir {
+            let entry = entry?;
+            let Ok(timestamp) = entry.file_name().into_string() else {
+                continue;
+            };
+            let Ok(content) = fs::read_to_string(entry.path().join(MANIFEST_FILE)) else {
+                continue;
+            };
+            match serde_json::from_str::<BackupManifest>(&content) {
+                Ok(manifest) => manifests.push((timestamp, manifest)),
+                Err(e) => tracing::warn!("忽略无效的备份清单 {}: {}", timestamp, e),
+            }
+        }
+        manifests.sort_by(|(a, ma), (b, mb)| mb.created_at.cmp(&ma.created_at).then(b.cmp(a)));
+        Ok(manifests)
+    }
+
+    /// 列出所有快照（按时间倒序）
+    pub fn list(&self) -> anyhow::Result<Vec<BackupInfo>> {
+        Ok(self
+            .read_manifests()?
+            .into_iter()
+            .map(|(timestamp, manifest)| BackupInfo {
+                timestamp,
+                created_at: manifest.created_at,
+                files: manifest.files,
+            })
+            .collect())
+    }
+
+    /// 恢复快照：校验所有文件后逐个写回源文件，返回已恢复的文件类型
+    ///
+    /// 快照中的配置与 `running_config` 有任何配置项不同时返回 `RequiresRestart`，不修改任何文件。
+    /// 恢复前先备份当前文件（内容未变化时跳过），便于撤销恢复操作。
+    /// 凭据文件中仍存在的凭据保留当前的 Token 字段。
+    /// 任一文件写回失败时，已写回的文件恢复为原内容，不会留下部分恢复的状态。
+    /// 写回后需由调用方触发各文件对应的内存重新加载
+    pub fn restore(
+        &self,
+        timestamp: &str,
+        running_config: &Config,
+    ) -> Result<Vec<BackupKind>, BackupError> {
+        let _guard = self.lock.lock();
+
+        let manifest = self
+            .read_manifests()?
+            .into_iter()
+            .find(|(t, _)| t == timestamp)
+            .map(|(_, manifest)| manifest)
+            .ok_or_else(|| BackupError::NotFound(timestamp.to_string()))?;
+        let snapshot_dir = self.dir.join(timestamp);
+
+        // 先校验全部文件，任一失败都不修改当前文件
+        let mut staged = Vec::with_capacity(manifest.files.len());
+        for file in &manifest.files {
+            let Some(target) = self.source_path(file.kind) else {
+                tracing::warn!("备份中的 {} 在当前部署中未启用，跳过", file.name);
+                continue;
+            };
+            let path = snapshot_dir.join(&file.name);
+            let invalid = |e: anyhow::Error| BackupError::Invalid {
+                file: file.name.clone(),
+                detail: format!("{:#}", e),
+            };
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("读取备份文件失败: {:?}", path))
+                .map_err(invalid)?;
+            file.kind.validate(&path).map_err(invalid)?;
+            if file.kind == BackupKind::Config {
+                let fields = config_changes(running_config, &path).map_err(invalid)?;
+                if !fields.is_empty() {
+                    return Err(BackupError::RequiresRestart { fields });
+                }
+            }
+            let original = read_original(target)?;
+            staged.push((file.kind, target.to_path_buf(), content, original));
+        }
+
+        if let Err(e) = self.backup_locked() {
+            tracing::warn!("恢复前备份当前文件失败: {}", e);
+        }
+
+        let summary = format!("从备份 {} 恢复", timestamp);
+        let mut written = Vec::with_capacity(staged.len());
+        for (kind, target, content, original) in staged {
+            let result = write_restored(
+                kind,
+                &target,
+                content,
+                Change::new("backup", summary.clone()),
+            );
+            if let Err(e) = result {
+                let error = e.context(format!("写回文件失败: {:?}", target));
+                return Err(rollback_restored(written, &summary, error).into());
+            }
+            written.push((kind, target, original));
+        }
+        let restored: Vec<BackupKind> = written.into_iter().map(|(kind, ..)| kind).collect();
+
+        tracing::info!("已从备份 {} 恢复 {} 个文件", timestamp, restored.len());
+        Ok(restored)
+    }
+}
+
+/// 写回单个文件
+///
+/// config 没有持久化写入线程（仅由 Admin 配置接口整体保存），直接原子写入；
+/// 其他文件经各自的写入线程排队写入
+fn write_restored(
+    kind: BackupKind,
+    target: &Path,
+    content: String,
+    change: Change,
+) -> anyhow::Result<()> {
+    match kind {
+        BackupKind::Config => Ok(write_atomic(target, content.as_bytes())?),
+        BackupKind::Credentials => {
+            let path = target.to_path_buf();
+            PersistWriter::for_path(target)
+                .submit(change, || {
+                    Ok(move |current: Option<&str>| keep_current_tokens(&path, &content, current))
+                })
+                .wait_blocking()
+        }
+        BackupKind::Pools | BackupKind::ApiKeys => PersistWriter::for_path(target)
+            .replace(change, || Ok(content))
+            .wait_blocking(),
+    }
+}
+
+/// 快照配置与运行配置中值不同的配置项（按字段名排序）
+///
+/// 快照文件未设置的随机默认值字段（每次加载结果不同）不参与比较
+fn config_changes(running: &Config, snapshot: &Path) -> anyhow::Result<Vec<String>> {
+    let fields = |config: &Config| match serde_json::to_value(config) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+    let explicit: Value = parse_by_path(snapshot, &fs::read_to_string(snapshot)?)?;
+    let (running, restored) = (fields(running), fields(&Config::load(snapshot)?));
+    let mut changed: Vec<String> = running
+        .keys()
+        .chain(restored.keys())
+        .filter(|key| {
+            !RANDOM_DEFAULT_CONFIG_FIELDS.contains(&key.as_str()) || explicit.get(*key).is_some()
+        })
+        .filter(|key| running.get(*key) != restored.get(*key))
+        .cloned()
+        .collect();
+    changed.sort();
+    changed.dedup();
+    Ok(changed)
+}
+
+/// 读取恢复前的文件内容（不存在时为 None）
+fn read_original(path: &Path) -> anyhow::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("读取当前文件失败: {:?}", path)),
+    }
+}
+
+/// 写回失败时逆序恢复已写回的文件，返回附带回滚结果的错误
+fn rollback_restored(
+    written: Vec<(BackupKind, PathBuf, Option<String>)>,
+    summary: &str,
+    error: anyhow::Error,
+) -> anyhow::Error {
+    let mut unrecovered = Vec::new();
+    for (kind, target, original) in written.into_iter().rev() {
+        let change = Change::new("backup", format!("{}失败，回滚", summary));
+        let result = match original {
+            Some(original) => match kind {
+                BackupKind::Config => {
+                    write_atomic(&target, original.as_bytes()).map_err(Into::into)
+                }
+                _ => PersistWriter::for_path(&target)
+                    .replace(change, || Ok(original))
+                    .wait_blocking(),
+            },
+            None => fs::remove_file(&target).map_err(Into::into),
+        };
+        if let Err(e) = result {
+            tracing::error!("回滚文件失败: {:?}: {:#}", target, e);
+            unrecovered.push(format!("{:?}", target));
+        }
+    }
+    if unrecovered.is_empty() {
+        error.context("恢复失败，已回滚所有已写回的文件")
+    } else {
+        error.context(format!(
+            "恢复失败且回滚未完成，以下文件可能不一致: {}",
+            unrecovered.join(", ")
+        ))
+    }
+}
+
+/// 按 ID 合并凭据快照与当前文件：当前文件中仍存在的凭据使用当前的 Token 字段
+///
+/// 当前文件不存在或无法解析时直接使用快照内容
+fn keep_current_tokens(
+    path: &Path,
+    snapshot: &str,
+    current: Option<&str>,
+) -> anyhow::Result<String> {
+    let current: Value = match current.filter(|c| !c.trim().is_empty()) {
+        Some(current) => match parse_by_path(path, current) {
+            Ok(current) => current,
+            Err(e) => {
+                tracing::warn!("当前凭据文件无法解析，按备份内容恢复: {:#}", e);
+                return Ok(snapshot.to_string());
+            }
+        },
+        None => return Ok(snapshot.to_string()),
+    };
+    let current_by_id: HashMap<u64, &Map<String, Value>> = credential_objects(&current)
+        .into_iter()
+        .filter_map(|cred| Some((cred.get("id")?.as_u64()?, cred)))
+        .collect();
+
+    let mut restored: Value = parse_by_path(path, snapshot)?;
+    let entries: Vec<&mut Map<String, Value>> = match &mut restored {
+        Value::Array(items) => items.iter_mut().filter_map(Value::as_object_mut).collect(),
+        Value::Object(cred) => vec![cred],
+        _ => Vec::new(),
+    };
+    for cred in entries {
+        let Some(existing) = cred
+            .get("id")
+            .and_then(Value::as_u64)
+            .and_then(|id| current_by_id.get(&id))
+        else {
+            continue;
+        };
+        for field in TOKEN_FIELDS {
+            match existing.get(field) {
+                Some(value) => cred.insert(field.to_string(), value.clone()),
+                None => cred.remove(field),
+            };
+        }
+    }
+    FileFormat::for_write(path).to_string(&restored)
+}
+
+/// 凭据文件中的凭据对象（支持单个对象和数组两种格式）
+fn credential_objects(value: &Value) -> Vec<&Map<String, Value>> {
+    match value {
+        Value::Array(items) => items.iter().filter_map(Value::as_object).collect(),
+        Value::Object(cred) => vec![cred],
+        _ => Vec::new(),
+    }
+}
+
+/// 源文件的文件名
+fn file_name(path: &Path) -> anyhow::Result<String> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("无效的文件路径: {:?}", path))
+}
+
+/// 启动后台备份任务
+///
+/// 启动时立即执行一次（内容未变化时跳过），之后每 `interval_hours` 小时执行一次
+pub fn start_backup_task(
+    manager: Arc<BackupManager>,
+    interval_hours: u64,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval_hours * 3600));
+        loop {
+            ticker.tick().await;
+            let manager = manager.clone();
+            match tokio::task::spawn_blocking(move || manager.backup_now()).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("自动备份失败: {:#}", e),
+                Err(e) => tracing::warn!("自动备份任务异常: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(dir: &Path, retention: usize) -> BackupManager {
+        BackupManager::new(dir.join("backups"), retention)
+            .with_source(BackupKind::Config, dir.join("config.json"))
+            .with_source(BackupKind::Pools, dir.join("pools.json"))
+            .with_source(BackupKind::ApiKeys, dir.join("api_keys.json"))
+    }
+
+    #[test]
+    fn test_backup_skips_unchanged_and_prunes() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(dir.path(), 2);
+        fs::write(dir.path().join("config.json"), r#"{"port": 8080}"#).unwrap();
+
+        let first = manager.backup_now().unwrap().unwrap();
+        assert_eq!(manager.backup_now().unwrap(), None);
+
+        fs::write(dir.path().join("config.json"), r#"{"port": 8081}"#).unwrap();
+        let second = manager.backup_now().unwrap().unwrap();
+        fs::write(dir.path().join("config.json"), r#"{"port": 8082}"#).unwrap();
+        let third = manager.backup_now().unwrap().unwrap();
+
+        let list = manager.list().unwrap();
+        let timestamps: Vec<_> = list.iter().map(|b| b.timestamp.clone()).collect();
+        assert_eq!(timestamps, vec![third, second]);
+        assert!(!dir.path().join("backups").join(first).exists());
+        assert_eq!(list[0].files.len(), 1);
+        assert_eq!(list[0].files[0].kind, BackupKind::Config);
+    }
+
+    #[test]
+    fn test_restore_writes_files_and_rejects_invalid_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(dir.path(), 5);
+        let config_path = dir.path().join("config.json");
+        let keys_path = dir.path().join("api_keys.json");
+        fs::write(&config_path, r#"{"port": 8080}"#).unwrap();
+        fs::write(&keys_path, "[]").unwrap();
+        let good = manager.backup_now().unwrap().unwrap();
+        let running = Config::load(&config_path).unwrap();
+
+        fs::write(
+            &keys_path,
+            r#"[{"name": "team", "key": "sk-kiro-rs-123456"}]"#,
+        )
+        .unwrap();
+        let restored = manager.restore(&good, &running).unwrap();
+        assert_eq!(restored, vec![BackupKind::Config, BackupKind::ApiKeys]);
+        assert_eq!(fs::read_to_string(&keys_path).unwrap(), "[]");
+
+        // 快照中的配置与运行配置不同：需要重启才能生效，拒绝恢复且不修改任何文件
+        fs::write(&config_path, r#"{"port": 9090}"#).unwrap();
+        fs::write(
+            &keys_path,
+            r#"[{"name": "team", "key": "sk-kiro-rs-123456"}]"#,
+        )
+        .unwrap();
+        let running = Config::load(&config_path).unwrap();
+        match manager.restore(&good, &running) {
+            Err(BackupError::RequiresRestart { fields }) => assert_eq!(fields, vec!["port"]),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            r#"{"port": 9090}"#
+        );
+        assert_ne!(fs::read_to_string(&keys_path).unwrap(), "[]");
+
+        // 快照中的 api_keys.json 损坏：拒绝恢复，当前文件保持不变
+        fs::write(&config_path, r#"{"port": 7070}"#).unwrap();
+        let running = Config::load(&config_path).unwrap();
+        let snapshot = manager.backup_now().unwrap().unwrap();
+        fs::write(
+            dir.path()
+                .join("backups")
+                .join(&snapshot)
+                .join("api_keys.json"),
+            "not json",
+        )
+        .unwrap();
+        fs::write(&config_path, r#"{"port": 6060}"#).unwrap();
+        match manager.restore(&snapshot, &running) {
+            Err(BackupError::Invalid { file, .. }) => assert_eq!(file, "api_keys.json"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            r#"{"port": 6060}"#
+        );
+
+        assert!(matches!(
+            manager.restore("../etc", &running),
+            Err(BackupError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_restore_keeps_current_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let credentials_path = dir.path().join("credentials.json");
+        let manager = BackupManager::new(dir.path().join("backups"), 5)
+            .with_source(BackupKind::Credentials, &credentials_path);
+        fs::write(
+            &credentials_path,
+            r#"[
+                {"id": 1, "refreshToken": "old-1", "accessToken": "a-1", "priority": 1},
+                {"id": 2, "refreshToken": "old-2", "priority": 2}
+            ]"#,
+        )
+        .unwrap();
+        let snapshot = manager.backup_now().unwrap().unwrap();
+
+        // 之后凭据 1 的 Token 被刷新轮换、优先级被修改，凭据 2 被删除
+        fs::write(
+            &credentials_path,
+            r#"[{"id": 1, "refreshToken": "new-1", "expiresAt": "2030-01-01T00:00:00Z", "priority": 5}]"#,
+        )
+        .unwrap();
+        manager.restore(&snapshot, &Config::default()).unwrap();
+
+        let restored = CredentialsConfig::load(&credentials_path)
+            .unwrap()
+            .into_sorted_credentials();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored[0].id, Some(1));
+        assert_eq!(restored[0].priority, 1);
+        assert_eq!(restored[0].refresh_token.as_deref(), Some("new-1"));
+        assert_eq!(restored[0].access_token, None);
+        assert_eq!(
+            restored[0].expires_at.as_deref(),
+            Some("2030-01-01T00:00:00Z")
+        );
+        // 已删除的凭据按备份内容恢复
+        assert_eq!(restored[1].refresh_token.as_deref(), Some("old-2"));
+    }
+
+    #[test]
+    fn test_restore_rolls_back_when_a_later_write_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(dir.path(), 5);
+        let config_path = dir.path().join("config.json");
+        let keys_path = dir.path().join("api_keys.json");
+        fs::write(&config_path, r#"{"port": 8080}"#).unwrap();
+        fs::write(&keys_path, "[]").unwrap();
+        let snapshot = manager.backup_now().unwrap().unwrap();
+
+        // config 先写回成功（配置项相同，仅格式不同），api_keys 的临时文件路径被目录占用导致写回失败
+        fs::write(&config_path, r#"{"port": 8080, "host": "127.0.0.1"}"#).unwrap();
+        let running = Config::load(&config_path).unwrap();
+        fs::write(
+            &keys_path,
+            r#"[{"name": "team", "key": "sk-kiro-rs-123456"}]"#,
+        )
+        .unwrap();
+        let blocker = dir.path().join("api_keys.json.persist.tmp");
+        fs::create_dir(&blocker).unwrap();
+
+        let err = manager.restore(&snapshot, &running).unwrap_err();
+        assert!(format!("{:#}", err).contains("已回滚"));
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            r#"{"port": 8080, "host": "127.0.0.1"}"#
+        );
+        assert_eq!(
+            fs::read_to_string(&keys_path).unwrap(),
+            r#"[{"name": "team", "key": "sk-kiro-rs-123456"}]"#
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! 配置备份 HTTP 处理器
+//!
+//! 列出自动备份的快照，并从快照恢复配置、凭据、池和 API Key 文件（无需重启；
+//! 配置只能恢复与运行配置相同的快照，配置项不同时拒绝恢复）
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use crate::common::i18n::{ErrorCode, Locale};
+use crate::kiro::model::credentials::CredentialsConfig;
+
+use super::{
+    backup::{BackupError, BackupKind, BackupManager},
+    middleware::AdminState,
+    types::{AdminErrorResponse, BackupListResponse, RestoreBackupResponse},
+};
+
+/// 未启用自动备份
+fn backups_disabled(locale: Locale) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(AdminErrorResponse::not_found(
+            ErrorCode::BackupsDisabled,
+            locale,
+        )),
+    )
+        .into_response()
+}
+
+/// GET /api/admin/backups
+/// 列出可用的备份快照（按时间倒序）
+pub async fn get_backups(State(state): State<AdminState>, locale: Locale) -> Response {
+    let Some(manager) = state.backup_manager.clone() else {
+        return backups_disabled(locale);
+    };
+    let listed = tokio::task::spawn_blocking(move || manager.list())
+        .await
+        .unwrap_or_else(|e| Err(anyhow::anyhow!("读取备份列表任务异常: {}", e)));
+    match listed {
+        Ok(backups) => Json(BackupListResponse { backups }).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(AdminErrorResponse::internal_error(
+                ErrorCode::InternalError.arg("detail", format!("{:#}", e)),
+                locale,
+            )),
+        )
+            .into_response(),
+    }
+}
+
+/// POST /api/admin/backups/:timestamp/restore
+/// 从快照恢复文件并重新加载内存状态
+///
+/// 快照中任一文件校验失败，或配置与运行配置不同（运行时无法切换）时拒绝恢复，
+/// 当前文件和运行状态保持不变
+pub async fn restore_backup(
+    State(state): State<AdminState>,
+    Path(timestamp): Path<String>,
+    locale: Locale,
+) -> Response {
+    let Some(manager) = state.backup_manager.clone() else {
+        return backups_disabled(locale);
+    };
+
+    // 校验、写回和重新加载都是阻塞的文件操作，避免阻塞异步运行时
+    let task_state = state.clone();
+    let task_timestamp = timestamp.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let restored = manager.restore(&task_timestamp, &task_state.get_config())?;
+        reload_restored(&task_state, &manager, &restored)?;
+        Ok::<_, BackupError>(restored)
+    })
+    .await
+    .unwrap_or_else(|e| Err(BackupError::Io(anyhow::anyhow!("恢复任务异常: {}", e))));
+
+    let restored = match result {
+        Ok(restored) => restored,
+        Err(BackupError::NotFound(_)) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(AdminErrorResponse::not_found(
+                    ErrorCode::BackupNotFound.arg("timestamp", &timestamp),
+                    locale,
+                )),
+            )
+                .into_response();
+        }
+        Err(BackupError::Invalid { file, detail }) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(AdminErrorResponse::invalid_request(
+                    ErrorCode::BackupInvalid
+                        .arg("timestamp", &timestamp)
+                        .arg("file", file)
+                        .arg("detail", detail),
+                    locale,
+                )),
+            )
+                .into_response();
+        }
+        Err(BackupError::RequiresRestart { fields }) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(AdminErrorResponse::invalid_request(
+                    ErrorCode::BackupRequiresRestart
+                        .arg("timestamp", &timestamp)
+                        .arg("fields", fields.join(", ")),
+                    locale,
+                )),
+            )
+                .into_response();
+        }
+        Err(BackupError::Io(e)) => return restore_failed(&timestamp, e, locale),
+    };
+
+    Json(RestoreBackupResponse {
+        success: true,
+        message: format!("已从备份 {} 恢复", timestamp),
+        timestamp,
+        restored,
+    })
+    .into_response()
+}
+
+/// 恢复失败（文件写入或重新加载出错）
+fn restore_failed(timestamp: &str, e: anyhow::Error, locale: Locale) -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(AdminErrorResponse::internal_error(
+            ErrorCode::BackupRestoreFailed
+                .arg("timestamp", timestamp)
+                .arg("detail", format!("{:#}", e)),
+            locale,
+        )),
+    )
+        .into_response()
+}
+
+/// 按恢复的文件类型重新加载内存状态
+///
+/// 中途失败时返回的错误中列出已重新加载的部分，其余部分的内存状态仍为恢复前的内容
+fn reload_restored(
+    state: &AdminState,
+    manager: &BackupManager,
+    restored: &[BackupKind],
+) -> anyhow::Result<()> {
+    let mut reloaded = Vec::new();
+    reload_stores(state, manager, restored, &mut reloaded).map_err(|e| {
+        let reloaded = if reloaded.is_empty() {
+            "无".to_string()
+        } else {
+            reloaded.join(", ")
+        };
+        e.context(format!(
+            "文件已写回，重新加载内存状态中断（已重新加载: {}），其余部分与文件不一致，需重启服务",
+            reloaded
+        ))
+    })
+}
+
+/// 依次重新加载各部分内存状态，`reloaded` 记录已完成的部分
+fn reload_stores(
+    state: &AdminState,
+    manager: &BackupManager,
+    restored: &[BackupKind],
+    reloaded: &mut Vec<&'static str>,
+) -> anyhow::Result<()> {
+    // 配置只在配置项与运行配置相同时恢复（见 BackupManager::restore），内存配置无需替换
+
+    if restored.contains(&BackupKind::ApiKeys) {
+        let count = state.api_key_manager.reload()?;
+        tracing::info!("已重新加载 {} 个 API Key", count);
+        reloaded.push("api_keys");
+    }
+
+    if restored.contains(&BackupKind::Credentials)
+        && let Some(path) = manager.source_path(BackupKind::Credentials)
+    {
+        let mut credentials = CredentialsConfig::load(path)?.into_sorted_credentials();
+        // 额外凭据目录中的凭据不在备份范围内，与启动时一样合并后再替换
+        let credential_dirs = state.config.read().credential_dir_paths(state.config_dir());
+        if !credential_dirs.is_empty() {
+            let mut taken_ids = credentials.iter().filter_map(|c| c.id).collect();
+            credentials.extend(CredentialsConfig::load_from_dirs(
+                &credential_dirs,
+                &mut taken_ids,
+            ));
+            credentials.sort_by_key(|c| c.priority);
+        }
+        state.service.replace_credentials(credentials)?;
+        reloaded.push("credentials");
+    }
+
+    // 池运行时同时依赖 pools.json 和 credentials.json
+    if (restored.contains(&BackupKind::Pools) || restored.contains(&BackupKind::Credentials))
+        && let Some(pool_manager) = &state.pool_manager
+    {
+        pool_manager.reload()?;
+        reloaded.push("pools");
+    }
+
+    Ok(())
+}
@@ -9,9 +9,16 @@ use axum::{
     response::IntoResponse,
 };
 
+use crate::common::i18n::{ErrorCode, Locale};
+use crate::http_client;
+use crate::model::config::Config;
+
 use super::{
     middleware::AdminState,
-    types::{AdminErrorResponse, ConfigResponse, SuccessResponse, UpdateConfigRequest},
+    types::{
+        AdminErrorResponse, ConfigResponse, EffectiveConfigDerived, EffectiveConfigResponse,
+        SuccessResponse, UpdateConfigRequest,
+    },
 };
 
 /// GET /api/admin/config
@@ -25,25 +32,86 @@ pub async fn get_config(State(state): State<AdminState>) -> impl IntoResponse {
         region: config.region,
         kiro_version: config.kiro_version,
         tls_backend: config.tls_backend,
+        tls_min_version: config.tls_min_version,
         session_cache_max_capacity: config.session_cache_max_capacity,
         session_cache_ttl_secs: config.session_cache_ttl_secs,
         proxy_url: config.proxy_url,
         proxy_username: config.proxy_username,
         // 脱敏代理密码
         proxy_password: config.proxy_password.map(|_| "***".to_string()),
+        has_api_key: config.api_key.is_some(),
+        api_key: config.api_key.as_deref().map(mask_api_key),
         has_admin_api_key: config.admin_api_key.is_some(),
     };
 
     Json(response)
 }
 
+/// GET /api/admin/config/effective
+/// 获取所有配置项的生效值和来源（default / file / admin_override，敏感项脱敏），
+/// 以及凭据/池/API Key 文件路径等运行时推导值
+pub async fn get_effective_config(State(state): State<AdminState>) -> impl IntoResponse {
+    let config = state.get_config();
+    let config_dir = state.config_dir();
+    let display = |path: &std::path::Path| path.display().to_string();
+
+    Json(EffectiveConfigResponse {
+        fields: state.config_provenance.effective_fields(&config),
+        derived: EffectiveConfigDerived {
+            config_path: display(&state.config_path),
+            credentials_path: state.credentials_path.as_deref().map(display),
+            pools_path: display(&config_dir.join("pools.json")),
+            api_keys_path: display(&config_dir.join("api_keys.json")),
+            tls_backend: config.tls_backend,
+            // 与启动时挂载 Admin 路由的判断一致：空字符串视为未配置
+            admin_enabled: config
+                .admin_api_key
+                .as_deref()
+                .is_some_and(|k| !k.trim().is_empty()),
+            pool_manager_enabled: state.pool_manager.is_some(),
+        },
+    })
+}
+
+/// GET /api/admin/tls-info
+/// 获取当前生效的 TLS 配置（协议版本、密码套件）
+pub async fn get_tls_info(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.get_config().tls_options().info())
+}
+
 /// PUT /api/admin/config
 /// 更新配置
 pub async fn update_config(
     State(state): State<AdminState>,
+    locale: Locale,
     Json(payload): Json<UpdateConfigRequest>,
 ) -> impl IntoResponse {
-    match state.update_config(|config| {
+    if let Some(api_key) = payload.api_key.as_deref().filter(|k| !k.is_empty())
+        && let Err(e) = Config::validate_api_key(api_key)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(AdminErrorResponse::invalid_request(e, locale)),
+        )
+            .into_response();
+    }
+    if let Some(capacity) = payload.session_cache_max_capacity
+        && let Err(e) = Config::validate_session_cache_capacity(capacity)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(AdminErrorResponse::invalid_request(e, locale)),
+        )
+            .into_response();
+    }
+
+    let proxy_changed = payload.proxy_url.is_some()
+        || payload.proxy_username.is_some()
+        || payload.proxy_password.is_some();
+
+    // 先保存配置，保存成功后再切换监听地址；切换失败时恢复原配置
+    let current = state.get_config();
+    let updated = match state.update_config(|config| {
         if let Some(host) = payload.host {
             config.host = host;
         }
@@ -82,12 +150,303 @@ pub async fn update_config(
             }
             // 空字符串：不修改
         }
+        if let Some(api_key) = payload.api_key {
+            config.api_key = if api_key.is_empty() {
+                None
+            } else {
+                Some(api_key)
+            };
+        }
     }) {
-        Ok(_) => Json(SuccessResponse::new("配置已更新，部分配置需要重启服务后生效")).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(AdminErrorResponse::internal_error(format!("保存配置失败: {}", e))),
+        Ok(updated) => updated,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AdminErrorResponse::internal_error(
+                    ErrorCode::ConfigSaveFailed.arg("detail", e),
+                    locale,
+                )),
+            )
+                .into_response();
+        }
+    };
+
+    let mut rebound = None;
+    if (updated.host != current.host || updated.port != current.port)
+        && let Some(server) = &state.server
+    {
+        let addr = format!("{}:{}", updated.host, updated.port);
+        match server.rebind(&addr).await {
+            Ok(bound) => rebound = Some(bound),
+            Err(e) => {
+                // 绑定失败时保留原监听器，配置文件和内存配置恢复原值
+                if let Err(restore_err) = state.update_config(|config| *config = current.clone()) {
+                    tracing::error!("监听地址切换失败后恢复配置失败: {:#}", restore_err);
+                }
+                return (
+                    StatusCode::CONFLICT,
+                    Json(AdminErrorResponse::invalid_request(
+                        ErrorCode::ListenerRebindFailed
+                            .arg("addr", &addr)
+                            .arg("detail", e),
+                        locale,
+                    )),
+                )
+                    .into_response();
+            }
+        }
+    }
+
+    // 代理变更后丢弃缓存的 HTTP Client，之后按新配置重新构建
+    if proxy_changed {
+        http_client::clear_client_cache();
+    }
+    let message = match rebound {
+        // Admin TLS 监听只在启动时绑定，不随 host 切换
+        Some(addr) if current.admin_mtls_ca_path.is_some() => format!(
+            "配置已更新，服务已切换到 {}，Admin TLS 监听地址需要重启服务后切换",
+            addr
+        ),
+        Some(addr) => format!(
+            "配置已更新，服务已切换到 {}，部分配置需要重启服务后生效",
+            addr
+        ),
+        None => "配置已更新，部分配置需要重启服务后生效".to_string(),
+    };
+    Json(SuccessResponse::new(message)).into_response()
+}
+
+/// 脱敏客户端 API Key（仅显示前 4 个字符）
+fn mask_api_key(api_key: &str) -> String {
+    let prefix: String = api_key.chars().take(4).collect();
+    format!("{}***", prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::admin::{AdminService, ApiKeyManager};
+    use crate::kiro::token_manager::MultiTokenManager;
+    use crate::server::ServerSupervisor;
+
+    #[tokio::test]
+    async fn test_port_change_rebind_failure_keeps_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.json");
+        let config = Config {
+            port: 0,
+            ..Default::default()
+        };
+        config.save(&config_path).unwrap();
+        let original = std::fs::read_to_string(&config_path).unwrap();
+
+        let token_manager =
+            Arc::new(MultiTokenManager::new(config.clone(), vec![], None, None).unwrap());
+        let api_key_manager =
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap());
+        let (supervisor, server) = ServerSupervisor::new();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        tokio::spawn(supervisor.serve(listener, axum::Router::new()));
+        let state = AdminState::new(
+            "admin-key",
+            AdminService::new(token_manager),
+            config,
+            &config_path,
+            api_key_manager,
+        )
+        .with_server(server);
+
+        // 目标端口已被占用
+        let occupied = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let payload: UpdateConfigRequest = serde_json::from_value(serde_json::json!({
+            "host": "127.0.0.1",
+            "port": occupied.local_addr().unwrap().port(),
+        }))
+        .unwrap();
+        let response = update_config(State(state.clone()), Locale::En, Json(payload))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "listener_rebind_failed");
+
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), original);
+        assert_eq!(state.get_config().port, 0);
+    }
+
+    #[tokio::test]
+    async fn test_port_change_save_failure_keeps_listener() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.json");
+        let config = Config {
+            port: 0,
+            ..Default::default()
+        };
+
+        let token_manager =
+            Arc::new(MultiTokenManager::new(config.clone(), vec![], None, None).unwrap());
+        let api_key_manager =
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap());
+        let (supervisor, server) = ServerSupervisor::new();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let old_addr = listener.local_addr().unwrap();
+        tokio::spawn(supervisor.serve(listener, axum::Router::new()));
+        let state = AdminState::new(
+            "admin-key",
+            AdminService::new(token_manager),
+            config,
+            &config_path,
+            api_key_manager,
         )
-            .into_response(),
+        .with_server(server);
+
+        // 配置文件位置是目录，保存失败
+        std::fs::create_dir(&config_path).unwrap();
+        let free_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let payload: UpdateConfigRequest = serde_json::from_value(serde_json::json!({
+            "host": "127.0.0.1",
+            "port": free_port,
+        }))
+        .unwrap();
+        let response = update_config(State(state.clone()), Locale::En, Json(payload))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        // 未切换监听地址，内存配置不变
+        assert_eq!(state.get_config().port, 0);
+        assert!(tokio::net::TcpStream::connect(old_addr).await.is_ok());
+        assert!(
+            tokio::net::TcpStream::connect(("127.0.0.1", free_port))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_cache_capacity_below_minimum_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.json");
+        let config = Config::default();
+        config.save(&config_path).unwrap();
+        let original = std::fs::read_to_string(&config_path).unwrap();
+
+        let token_manager =
+            Arc::new(MultiTokenManager::new(config.clone(), vec![], None, None).unwrap());
+        let api_key_manager =
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap());
+        let state = AdminState::new(
+            "admin-key",
+            AdminService::new(token_manager),
+            config,
+            &config_path,
+            api_key_manager,
+        );
+
+        let payload: UpdateConfigRequest =
+            serde_json::from_value(serde_json::json!({"sessionCacheMaxCapacity": 5})).unwrap();
+        let response = update_config(State(state.clone()), Locale::En, Json(payload))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "session_cache_capacity_too_small");
+
+        // 不写入会导致下次启动校验失败的配置
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), original);
+        assert_eq!(
+            state.get_config().session_cache_max_capacity,
+            Config::default().session_cache_max_capacity
+        );
+    }
+
+    #[tokio::test]
+    async fn test_effective_config_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.json");
+        std::fs::write(&config_path, r#"{"port": 9000, "adminApiKey": "admin-key"}"#).unwrap();
+        let config = Config::load(&config_path).unwrap();
+
+        let token_manager =
+            Arc::new(MultiTokenManager::new(config.clone(), vec![], None, None).unwrap());
+        let api_key_manager =
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap());
+        let state = AdminState::new(
+            "admin-key",
+            AdminService::new(token_manager),
+            config,
+            &config_path,
+            api_key_manager,
+        )
+        .with_credentials_path(dir.path().join("credentials.json"));
+        state
+            .update_config(|config| config.region = "eu-west-1".to_string())
+            .unwrap();
+
+        let response = get_effective_config(State(state)).await.into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let keys = |value: &serde_json::Value| -> Vec<String> {
+            let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(keys(&body), ["derived", "fields"]);
+        assert_eq!(
+            keys(&body["derived"]),
+            [
+                "adminEnabled",
+                "apiKeysPath",
+                "configPath",
+                "credentialsPath",
+                "poolManagerEnabled",
+                "poolsPath",
+                "tlsBackend",
+            ]
+        );
+        assert_eq!(body["derived"]["poolManagerEnabled"], false);
+        assert!(
+            body["derived"]["poolsPath"]
+                .as_str()
+                .unwrap()
+                .ends_with("pools.json")
+        );
+
+        // 每个配置字段都列出（包括未设置时不序列化的 apiKey）
+        let mut expected = keys(&serde_json::to_value(Config::default()).unwrap());
+        expected.push("apiKey".to_string());
+        expected.sort();
+        assert_eq!(keys(&body["fields"]), expected);
+
+        let fields = &body["fields"];
+        assert_eq!(keys(&fields["port"]), ["provenance", "value"]);
+        assert_eq!(fields["port"]["value"], 9000);
+        assert_eq!(fields["port"]["provenance"], "file");
+        assert_eq!(fields["host"]["provenance"], "default");
+        assert_eq!(fields["region"]["value"], "eu-west-1");
+        assert_eq!(fields["region"]["provenance"], "admin_override");
+        assert_eq!(
+            fields["adminApiKey"],
+            serde_json::json!({"value": "***", "provenance": "file", "set": true})
+        );
+        assert_eq!(
+            fields["apiKey"],
+            serde_json::json!({"value": null, "provenance": "default", "set": false})
+        );
     }
 }
@@ -4,6 +4,9 @@ use std::fmt;
 
 use axum::http::StatusCode;
 
+use crate::common::i18n::{ErrorCode, Locale, LocalizedError};
+use crate::kiro::error::KiroError;
+
 use super::types::AdminErrorResponse;
 
 /// Admin 服务错误类型
@@ -20,6 +23,9 @@ pub enum AdminServiceError {
 
     /// 凭据无效（验证失败）
     InvalidCredential(String),
+
+    /// 操作过于频繁
+    RateLimited { id: u64, retry_after_secs: u64 },
 }
 
 impl fmt::Display for AdminServiceError {
@@ -31,12 +37,43 @@ impl fmt::Display for AdminServiceError {
             AdminServiceError::UpstreamError(msg) => write!(f, "上游服务错误: {}", msg),
             AdminServiceError::InternalError(msg) => write!(f, "内部错误: {}", msg),
             AdminServiceError::InvalidCredential(msg) => write!(f, "凭据无效: {}", msg),
+            AdminServiceError::RateLimited {
+                id,
+                retry_after_secs,
+            } => write!(
+                f,
+                "凭据 #{} 操作过于频繁，{} 秒后重试",
+                id, retry_after_secs
+            ),
         }
     }
 }
 
 impl std::error::Error for AdminServiceError {}
 
+impl From<KiroError> for AdminServiceError {
+    fn from(e: KiroError) -> Self {
+        match e {
+            KiroError::CredentialNotFound(id) => AdminServiceError::NotFound { id },
+            // refreshToken 无效、额度用尽或上游拒绝（4xx）属于凭据本身的问题
+            KiroError::InvalidRefreshToken { .. }
+            | KiroError::RotatingRefreshToken(_)
+            | KiroError::QuotaExceeded { .. }
+            | KiroError::UpstreamError {
+                status: 400..=499, ..
+            } => AdminServiceError::InvalidCredential(e.to_string()),
+            KiroError::UpstreamError { .. }
+            | KiroError::TokenRefreshFailed { .. }
+            | KiroError::RefreshTimeout { .. }
+            | KiroError::CredentialThrottled { .. }
+            | KiroError::AllCredentialsExhausted { .. } => {
+                AdminServiceError::UpstreamError(e.to_string())
+            }
+            KiroError::IoError(_) => AdminServiceError::InternalError(e.to_string()),
+        }
+    }
+}
+
 impl AdminServiceError {
     /// 获取对应的 HTTP 状态码
     pub fn status_code(&self) -> StatusCode {
@@ -45,20 +82,125 @@ impl AdminServiceError {
             AdminServiceError::UpstreamError(_) => StatusCode::BAD_GATEWAY,
             AdminServiceError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AdminServiceError::InvalidCredential(_) => StatusCode::BAD_REQUEST,
+            AdminServiceError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    /// 错误码及参数
+    pub fn localized(&self) -> LocalizedError {
+        match self {
+            AdminServiceError::NotFound { id } => ErrorCode::CredentialNotFound.arg("id", id),
+            AdminServiceError::UpstreamError(msg) => {
+                ErrorCode::UpstreamServiceError.arg("detail", msg)
+            }
+            AdminServiceError::InternalError(msg) => ErrorCode::InternalError.arg("detail", msg),
+            AdminServiceError::InvalidCredential(msg) => {
+                ErrorCode::InvalidCredential.arg("detail", msg)
+            }
+            AdminServiceError::RateLimited {
+                id,
+                retry_after_secs,
+            } => ErrorCode::CredentialRateLimited
+                .arg("id", id)
+                .arg("retry_after", retry_after_secs),
+        }
+    }
+
+    /// 建议的重试等待秒数（用于 Retry-After 响应头）
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            AdminServiceError::RateLimited {
+                retry_after_secs, ..
+            } => Some(*retry_after_secs),
+            _ => None,
         }
     }
 
     /// 转换为 API 错误响应
-    pub fn into_response(self) -> AdminErrorResponse {
-        match &self {
-            AdminServiceError::NotFound { .. } => AdminErrorResponse::not_found(self.to_string()),
-            AdminServiceError::UpstreamError(_) => AdminErrorResponse::api_error(self.to_string()),
+    pub fn into_response(self, locale: Locale) -> AdminErrorResponse {
+        let error = self.localized();
+        match self {
+            AdminServiceError::NotFound { .. } => AdminErrorResponse::not_found(error, locale),
+            AdminServiceError::UpstreamError(_) => AdminErrorResponse::api_error(error, locale),
             AdminServiceError::InternalError(_) => {
-                AdminErrorResponse::internal_error(self.to_string())
+                AdminErrorResponse::internal_error(error, locale)
             }
             AdminServiceError::InvalidCredential(_) => {
-                AdminErrorResponse::invalid_request(self.to_string())
+                AdminErrorResponse::invalid_request(error, locale)
+            }
+            AdminServiceError::RateLimited { .. } => {
+                AdminErrorResponse::rate_limited(error, locale)
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kiro_error_http_status() {
+        let cases = [
+            (KiroError::CredentialNotFound(3), StatusCode::NOT_FOUND),
+            (
+                KiroError::InvalidRefreshToken {
+                    reason: "refreshToken 为空".to_string(),
+                },
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                KiroError::QuotaExceeded { credential_id: 1 },
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                KiroError::UpstreamError {
+                    status: 429,
+                    body: "请求过于频繁，已被限流".to_string(),
+                },
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                KiroError::UpstreamError {
+                    status: 503,
+                    body: "服务器错误".to_string(),
+                },
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                KiroError::TokenRefreshFailed {
+                    credential_id: 1,
+                    reason: "error trying to connect".to_string(),
+                },
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                KiroError::AllCredentialsExhausted {
+                    available: 0,
+                    total: 2,
+                },
+                StatusCode::BAD_GATEWAY,
+            ),
+            (
+                KiroError::IoError(std::io::Error::other("磁盘已满")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+
+        for (error, expected) in cases {
+            let message = error.to_string();
+            let admin_error = AdminServiceError::from(error);
+            assert_eq!(admin_error.status_code(), expected, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_credential_not_found_keeps_id() {
+        let error = AdminServiceError::from(KiroError::CredentialNotFound(42));
+        assert!(matches!(error, AdminServiceError::NotFound { id: 42 }));
+        assert_eq!(
+            error.into_response(Locale::En).error.code,
+            ErrorCode::CredentialNotFound.as_str()
+        );
+    }
+}
@@ -0,0 +1,38 @@
+//! Admin 实时事件
+//!
+//! 凭据/池状态变化时通过 broadcast 通道推送给 Admin UI（SSE）
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// 事件通道容量（订阅者处理过慢时旧事件会被丢弃）
+pub const ADMIN_EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Admin 实时事件
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum AdminEvent {
+    /// 凭据状态变化（调用成功/失败、启用/禁用）
+    CredentialStatusChanged {
+        id: u64,
+        disabled: bool,
+        failure_count: u32,
+    },
+    /// 池可用凭据数量变化
+    PoolStatusChanged {
+        id: String,
+        available_credentials: usize,
+    },
+    /// 新的上游请求
+    NewRequest { model: String, credential_id: u64 },
+}
+
+/// 创建 Admin 事件广播通道
+pub fn channel() -> broadcast::Sender<AdminEvent> {
+    let (sender, _) = broadcast::channel(ADMIN_EVENT_CHANNEL_CAPACITY);
+    sender
+}
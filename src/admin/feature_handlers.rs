@@ -0,0 +1,71 @@
+//! 功能开关 HTTP 处理器
+//!
+//! 查询和运行时切换功能开关，切换结果写入配置目录下的 features.json
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use crate::common::features::{FeatureFlagError, FeatureFlags, KNOWN_FLAGS};
+use crate::common::i18n::{ErrorCode, Locale};
+
+use super::{
+    middleware::AdminState,
+    types::{AdminErrorResponse, FeatureFlagItem, FeatureFlagsResponse, SetFeatureFlagRequest},
+};
+
+/// 构建功能开关列表（按名称排序）
+fn feature_list(features: &FeatureFlags) -> FeatureFlagsResponse {
+    let features = features
+        .snapshot()
+        .into_iter()
+        .map(|(name, enabled)| FeatureFlagItem {
+            description: KNOWN_FLAGS
+                .iter()
+                .find(|(flag, _, _)| *flag == name)
+                .map(|(_, _, description)| description.to_string())
+                .unwrap_or_default(),
+            name,
+            enabled,
+        })
+        .collect();
+    FeatureFlagsResponse { features }
+}
+
+/// GET /api/admin/features
+/// 获取所有功能开关的当前状态
+pub async fn get_features(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(feature_list(&state.features))
+}
+
+/// PUT /api/admin/features/:name
+/// 运行时切换功能开关
+pub async fn set_feature(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    locale: Locale,
+    Json(payload): Json<SetFeatureFlagRequest>,
+) -> Response {
+    match state.features.set(&name, payload.enabled) {
+        Ok(()) => Json(feature_list(&state.features)).into_response(),
+        Err(FeatureFlagError::Unknown(name)) => (
+            StatusCode::NOT_FOUND,
+            Json(AdminErrorResponse::not_found(
+                ErrorCode::UnknownFeatureFlag.arg("name", name),
+                locale,
+            )),
+        )
+            .into_response(),
+        Err(FeatureFlagError::Save(e)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(AdminErrorResponse::internal_error(
+                ErrorCode::FeatureFlagSaveFailed.arg("detail", e),
+                locale,
+            )),
+        )
+            .into_response(),
+    }
+}
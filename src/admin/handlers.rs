@@ -4,16 +4,30 @@
 
 use axum::{
     Json,
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
 };
 
+use std::collections::HashMap;
+
+use crate::common::etag::{content_etag, etag_matches};
+use crate::common::i18n::{ErrorCode, Locale};
+use crate::kiro::compression::compression_stats;
+use crate::kiro::model::credentials::validate_custom_headers;
+use crate::kiro::simulation::SimulationScenario;
+
 use super::{
-    middleware::AdminState,
+    error::AdminServiceError,
+    middleware::{AdminActor, AdminState},
     types::{
-        AddCredentialRequest, AdminErrorResponse, CsrfTokenResponse, ImportCredentialsRequest,
-        SetDisabledRequest, SetPriorityRequest, SetSchedulingModeRequest, SuccessResponse,
+        AddCredentialRequest, AdminErrorResponse, BulkPriorityFailure, BulkPriorityRequest,
+        BulkPriorityResponse, CloneCredentialRequest, CredentialHeadersResponse,
+        CredentialTagsResponse, CredentialTimelineQuery, CredentialsQuery, CsrfTokenResponse,
+        ImportCredentialsRequest, ImportKiroIdeQuery, RefreshTokenRequest, RollbackQuery,
+        SetCustomHeadersRequest, SetDisabledRequest, SetNotesRequest, SetPriorityRequest,
+        SetSchedulingModeRequest, StatsResponse, SuccessResponse, TimelineQuery, UpdateTagsRequest,
     },
 };
 
@@ -27,26 +41,183 @@ pub async fn get_csrf_token(State(state): State<AdminState>) -> impl IntoRespons
     Json(CsrfTokenResponse { token })
 }
 
+/// GET /api/admin/stats
+/// 获取运行统计
+pub async fn get_stats(State(state): State<AdminState>) -> impl IntoResponse {
+    let (websearch_requests, websearch_rate_limited) = state
+        .websearch_limiter
+        .as_ref()
+        .map(|l| (l.total_requests(), l.rejected_requests()))
+        .unwrap_or((0, 0));
+    let (token_bucket_requests, token_bucket_exempt_requests) = state
+        .token_bucket
+        .as_ref()
+        .map(|b| (b.total_requests(), b.exempt_requests()))
+        .unwrap_or((0, 0));
+    let repairs = crate::anthropic::repair_stats();
+    let compression = compression_stats();
+
+    Json(StatsResponse {
+        websearch_requests,
+        websearch_rate_limited,
+        token_bucket_requests,
+        token_bucket_exempt_requests,
+        text_artifacts_stripped: repairs.text_artifacts_stripped,
+        tool_json_repaired: repairs.tool_json_repaired,
+        tool_json_repair_failed: repairs.tool_json_repair_failed,
+        compressed_requests: compression.compressed_requests,
+        request_bytes_before_compression: compression.bytes_before,
+        request_bytes_after_compression: compression.bytes_after,
+    })
+}
+
+/// GET /api/admin/dashboard
+/// 获取 Admin UI 仪表盘（池概要、最近请求量、活跃 API Key、近期禁用、版本与运行时长）
+pub async fn get_dashboard(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_dashboard(&state.api_key_manager))
+}
+
+/// GET /api/admin/stats/credentials
+/// 汇总所有池的凭据统计
+pub async fn get_credential_stats(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.aggregate_stats())
+}
+
+/// GET /api/admin/stats/timeline
+/// 获取所有池最近一段时间（`?window_secs=`，默认且最长 3600）的每分钟调用统计
+pub async fn get_stats_timeline(
+    State(state): State<AdminState>,
+    Query(query): Query<TimelineQuery>,
+) -> impl IntoResponse {
+    Json(state.service.get_timeline(query.window_secs))
+}
+
+/// GET /api/admin/warmup-report
+/// 获取启动时的凭据预热报告
+pub async fn get_warmup_report(
+    State(state): State<AdminState>,
+    locale: Locale,
+) -> impl IntoResponse {
+    match state.service.get_warmup_report() {
+        Some(report) => Json(report).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(AdminErrorResponse::not_found(
+                ErrorCode::WarmupReportUnavailable,
+                locale,
+            )),
+        )
+            .into_response(),
+    }
+}
+
+/// GET /api/admin/user-sessions
+/// 获取各用户的活跃会话数（用户标识为哈希值，用于调试公平调度）
+pub async fn get_user_sessions(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_user_sessions())
+}
+
+/// GET /api/admin/stickiness
+/// 获取粘性会话诊断统计（会话标识来源分布与会话缓存命中率）
+pub async fn get_stickiness(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.service.get_stickiness())
+}
+
+/// POST /api/admin/simulate
+/// 用合成负载运行调度模拟（不涉及真实凭据和上游调用）
+pub async fn simulate_scheduling(
+    locale: Locale,
+    Json(scenario): Json<SimulationScenario>,
+) -> impl IntoResponse {
+    if let Err(reason) = scenario.validate() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(AdminErrorResponse::invalid_request(
+                ErrorCode::InvalidSimulationScenario.arg("reason", reason),
+                locale,
+            )),
+        )
+            .into_response();
+    }
+
+    // 大场景可能运行数百毫秒，避免阻塞异步运行时
+    match tokio::task::spawn_blocking(move || scenario.run()).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(AdminErrorResponse::internal_error(
+                ErrorCode::InternalError.arg("detail", e),
+                locale,
+            )),
+        )
+            .into_response(),
+    }
+}
+
 /// GET /api/admin/credentials
-/// 获取所有凭据状态
-pub async fn get_all_credentials(State(state): State<AdminState>) -> impl IntoResponse {
-    let response = state.service.get_all_credentials();
-    Json(response)
+/// 获取凭据状态（支持 `?tag=`、`?disabled=`、`?auth_method=`、`?pool_id=`、`?q=` 筛选，
+/// `?updated_since=` 增量查询，`?offset=`/`?limit=` 分页）
+///
+/// 响应携带基于内容的 ETag，If-None-Match 命中时返回 304，便于 Admin UI 轮询
+pub async fn get_all_credentials(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Query(query): Query<CredentialsQuery>,
+) -> Response {
+    let response = state.service.get_all_credentials(&query);
+    json_with_etag(&headers, &response)
+}
+
+/// 序列化为 JSON 响应并携带基于内容的 ETag，If-None-Match 命中时返回 304
+fn json_with_etag(headers: &HeaderMap, value: &impl serde::Serialize) -> Response {
+    let body = match serde_json::to_vec(value) {
+        Ok(body) => body,
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    let etag = content_etag(&body);
+
+    if etag_matches(headers, &etag) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [
+                (header::ETAG, etag),
+                (header::CACHE_CONTROL, "no-cache".to_string()),
+            ],
+        )
+            .into_response();
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+        ],
+        body,
+    )
+        .into_response()
 }
 
 /// POST /api/admin/credentials/:id/disabled
 /// 设置凭据禁用状态
 pub async fn set_credential_disabled(
     State(state): State<AdminState>,
+    locale: Locale,
+    actor: AdminActor,
     Path(id): Path<u64>,
     Json(payload): Json<SetDisabledRequest>,
 ) -> impl IntoResponse {
-    match state.service.set_disabled(id, payload.disabled) {
+    match state
+        .service
+        .set_disabled(id, payload.disabled, actor.as_str())
+    {
         Ok(_) => {
             let action = if payload.disabled { "禁用" } else { "启用" };
             Json(SuccessResponse::new(format!("凭据 #{} 已{}", id, action))).into_response()
         }
-        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
     }
 }
 
@@ -54,6 +225,7 @@ pub async fn set_credential_disabled(
 /// 设置凭据优先级
 pub async fn set_credential_priority(
     State(state): State<AdminState>,
+    locale: Locale,
     Path(id): Path<u64>,
     Json(payload): Json<SetPriorityRequest>,
 ) -> impl IntoResponse {
@@ -63,35 +235,352 @@ pub async fn set_credential_priority(
             id, payload.priority
         )))
         .into_response(),
-        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// GET /api/admin/credentials/:id/history
+/// 获取凭据最近的历史版本（最新的在前，最多 5 个）和禁用/启用记录（最多 20 条）
+pub async fn get_credential_history(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    match state.service.credential_history(id) {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// GET /api/admin/credentials/:id/latency-histogram
+/// 获取凭据最近 100 次成功调用的响应时间直方图（按 50ms 分桶）
+pub async fn get_credential_latency_histogram(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    match state.service.latency_histogram(id) {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// GET /api/admin/credentials/:id/timeline?window_minutes=60&granularity_minutes=5
+/// 获取凭据最近一段时间按时间桶聚合的调用统计
+pub async fn get_credential_timeline(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+    Query(query): Query<CredentialTimelineQuery>,
+) -> impl IntoResponse {
+    match state
+        .service
+        .credential_timeline(id, query.window_minutes, query.granularity_minutes)
+    {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// POST /api/admin/credentials/:id/rollback?version=1
+/// 将凭据恢复到指定历史版本（回滚本身也会记录历史）
+pub async fn rollback_credential(
+    State(state): State<AdminState>,
+    locale: Locale,
+    actor: AdminActor,
+    Path(id): Path<u64>,
+    Query(query): Query<RollbackQuery>,
+) -> Response {
+    let available = match state.service.credential_history(id) {
+        Ok(history) => history.versions.len(),
+        Err(e) => return (e.status_code(), Json(e.into_response(locale))).into_response(),
+    };
+    if query.version == 0 || query.version > available {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(AdminErrorResponse::not_found(
+                ErrorCode::CredentialVersionNotFound
+                    .arg("id", id)
+                    .arg("version", query.version)
+                    .arg("max", available),
+                locale,
+            )),
+        )
+            .into_response();
+    }
+
+    match state
+        .service
+        .rollback_credential(id, query.version, actor.as_str())
+    {
+        Ok(_) => Json(SuccessResponse::new(format!(
+            "凭据 #{} 已回滚到版本 {}",
+            id, query.version
+        )))
+        .into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// PUT /api/admin/credentials/priorities
+/// 按拖拽排序结果批量设置凭据优先级（全部更新后只持久化一次）
+pub async fn set_credential_priorities(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Json(payload): Json<BulkPriorityRequest>,
+) -> Response {
+    let mut seen = std::collections::HashSet::new();
+    if let Some(item) = payload
+        .items
+        .iter()
+        .find(|item| !seen.insert(item.priority))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(AdminErrorResponse::invalid_request(
+                ErrorCode::DuplicatePriority.arg("priority", item.priority),
+                locale,
+            )),
+        )
+            .into_response();
+    }
+
+    let priorities: Vec<(u64, u32)> = payload
+        .items
+        .iter()
+        .map(|item| (item.id, item.priority))
+        .collect();
+    match state.service.set_priorities(&priorities) {
+        Ok(missing) => {
+            let (failed, updated): (Vec<u64>, Vec<u64>) = payload
+                .items
+                .iter()
+                .map(|item| item.id)
+                .partition(|id| missing.contains(id));
+            let failed = failed
+                .into_iter()
+                .map(|id| BulkPriorityFailure {
+                    id,
+                    error: AdminServiceError::NotFound { id }
+                        .localized()
+                        .render(locale),
+                })
+                .collect();
+            Json(BulkPriorityResponse { updated, failed }).into_response()
+        }
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// PATCH /api/admin/credentials/:id/notes
+/// 修改凭据备注（`notes` 为 null 时清除）
+pub async fn set_credential_notes(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+    Json(payload): Json<SetNotesRequest>,
+) -> Response {
+    if let Some(response) = reject_long_notes(payload.notes.as_deref(), locale) {
+        return response;
+    }
+
+    let cleared = payload.notes.is_none();
+    match state.service.set_notes(id, payload.notes) {
+        Ok(_) => {
+            let action = if cleared { "已清除" } else { "已更新" };
+            Json(SuccessResponse::new(format!("凭据 #{} 备注{}", id, action))).into_response()
+        }
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// 备注最大长度（字符数）
+const MAX_NOTES_CHARS: usize = 1000;
+
+/// 校验备注长度，超出时返回 400 响应
+fn reject_long_notes(notes: Option<&str>, locale: Locale) -> Option<Response> {
+    let notes = notes?;
+    if notes.chars().count() <= MAX_NOTES_CHARS {
+        return None;
+    }
+    Some(
+        (
+            StatusCode::BAD_REQUEST,
+            Json(AdminErrorResponse::invalid_request(
+                ErrorCode::NotesTooLong.arg("max", MAX_NOTES_CHARS),
+                locale,
+            )),
+        )
+            .into_response(),
+    )
+}
+
+/// POST /api/admin/credentials/:id/tags
+/// 添加/移除凭据标签
+pub async fn update_credential_tags(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+    Json(payload): Json<UpdateTagsRequest>,
+) -> Response {
+    if let Some(response) = reject_invalid_tags(&payload.add, locale) {
+        return response;
+    }
+
+    match state.service.update_tags(id, payload.add, &payload.remove) {
+        Ok(tags) => Json(CredentialTagsResponse { id, tags }).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
     }
 }
 
+/// 单个标签最大长度（字符数）
+const MAX_TAG_CHARS: usize = 64;
+
+/// 校验标签长度，超出时返回 400 响应
+fn reject_invalid_tags(tags: &[String], locale: Locale) -> Option<Response> {
+    if tags
+        .iter()
+        .all(|t| t.trim().chars().count() <= MAX_TAG_CHARS)
+    {
+        return None;
+    }
+    Some(
+        (
+            StatusCode::BAD_REQUEST,
+            Json(AdminErrorResponse::invalid_request(
+                ErrorCode::TagTooLong.arg("max", MAX_TAG_CHARS),
+                locale,
+            )),
+        )
+            .into_response(),
+    )
+}
+
+/// PATCH /api/admin/credentials/:id/headers
+/// 替换凭据自定义上游请求头（`{"headers": {...}}`，空对象清除）
+pub async fn set_credential_headers(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+    Json(payload): Json<SetCustomHeadersRequest>,
+) -> Response {
+    if let Some(response) = reject_invalid_custom_headers(&payload.headers, locale) {
+        return response;
+    }
+
+    match state.service.set_custom_headers(id, payload.headers) {
+        Ok(headers) => Json(CredentialHeadersResponse { id, headers }).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// 校验自定义请求头，不合法时返回 400 响应
+fn reject_invalid_custom_headers(
+    headers: &HashMap<String, String>,
+    locale: Locale,
+) -> Option<Response> {
+    let detail = validate_custom_headers(headers).err()?;
+    Some(
+        (
+            StatusCode::BAD_REQUEST,
+            Json(AdminErrorResponse::invalid_request(
+                ErrorCode::InvalidCustomHeader.arg("detail", detail),
+                locale,
+            )),
+        )
+            .into_response(),
+    )
+}
+
 /// POST /api/admin/credentials/:id/reset
 /// 重置失败计数并重新启用
 pub async fn reset_failure_count(
     State(state): State<AdminState>,
+    locale: Locale,
+    actor: AdminActor,
     Path(id): Path<u64>,
 ) -> impl IntoResponse {
-    match state.service.reset_and_enable(id) {
+    match state.service.reset_and_enable(id, actor.as_str()) {
         Ok(_) => Json(SuccessResponse::new(format!(
             "凭据 #{} 失败计数已重置并重新启用",
             id
         )))
         .into_response(),
-        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
     }
 }
 
+/// POST /api/admin/credentials/:id/refresh
+/// 立即刷新凭据 Token（请求体可选：`{"force": true}`）
+pub async fn refresh_credential_token(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+    body: Bytes,
+) -> Response {
+    let payload = if body.is_empty() {
+        RefreshTokenRequest::default()
+    } else {
+        match Json::<RefreshTokenRequest>::from_bytes(&body) {
+            Ok(Json(payload)) => payload,
+            Err(rejection) => return rejection.into_response(),
+        }
+    };
+
+    match state.service.refresh_token(id, payload.force).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => rate_limited_error_response(e, locale),
+    }
+}
+
+/// POST /api/admin/credentials/:id/test
+/// 测试凭据与上游的连通性（不影响失败计数）
+pub async fn test_credential(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+) -> Response {
+    match state.service.test_credential(id).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => rate_limited_error_response(e, locale),
+    }
+}
+
+/// 错误响应，被限流时附带 Retry-After 响应头
+fn rate_limited_error_response(e: AdminServiceError, locale: Locale) -> Response {
+    let retry_after = e.retry_after_secs();
+    let mut response = (e.status_code(), Json(e.into_response(locale))).into_response();
+    if let Some(secs) = retry_after {
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    }
+    response
+}
+
 /// GET /api/admin/credentials/:id/balance
 /// 获取指定凭据的余额
 pub async fn get_credential_balance(
     State(state): State<AdminState>,
+    locale: Locale,
     Path(id): Path<u64>,
 ) -> impl IntoResponse {
     match state.service.get_balance(id).await {
         Ok(response) => Json(response).into_response(),
-        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// GET /api/admin/credentials/:id/validate
+/// 检查凭据配置，返回警告列表
+pub async fn validate_credential(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    match state.service.validate_credential(id) {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
     }
 }
 
@@ -99,11 +588,45 @@ pub async fn get_credential_balance(
 /// 添加新凭据
 pub async fn add_credential(
     State(state): State<AdminState>,
+    locale: Locale,
     Json(payload): Json<AddCredentialRequest>,
 ) -> impl IntoResponse {
+    if let Some(response) = reject_long_notes(payload.notes.as_deref(), locale) {
+        return response;
+    }
+    if let Some(response) = reject_invalid_tags(&payload.tags, locale) {
+        return response;
+    }
+    if let Some(response) = reject_invalid_custom_headers(&payload.custom_headers, locale) {
+        return response;
+    }
+
     match state.service.add_credential(payload).await {
         Ok(response) => Json(response).into_response(),
-        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// POST /api/admin/credentials/:id/clone
+/// 克隆凭据（请求体可选：`{"newPriority": 5, "newPoolId": "staging", "newRegion": "eu-west-1"}`）
+pub async fn clone_credential(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+    body: Bytes,
+) -> Response {
+    let payload = if body.is_empty() {
+        CloneCredentialRequest::default()
+    } else {
+        match Json::<CloneCredentialRequest>::from_bytes(&body) {
+            Ok(Json(payload)) => payload,
+            Err(rejection) => return rejection.into_response(),
+        }
+    };
+
+    match state.service.clone_credential(id, payload) {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
     }
 }
 
@@ -111,31 +634,103 @@ pub async fn add_credential(
 /// 删除凭据
 pub async fn delete_credential(
     State(state): State<AdminState>,
+    locale: Locale,
     Path(id): Path<u64>,
 ) -> impl IntoResponse {
     match state.service.delete_credential(id) {
         Ok(_) => Json(SuccessResponse::new(format!("凭据 #{} 已删除", id))).into_response(),
-        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
     }
 }
 
 /// POST /api/admin/credentials/import
-/// 批量导入凭据（支持 IdC 格式）
+/// 批量导入凭据（支持 IdC 格式 JSON，以及 `Content-Type: text/csv` 的 CSV）
 pub async fn import_credentials(
     State(state): State<AdminState>,
-    Json(payload): Json<ImportCredentialsRequest>,
-) -> impl IntoResponse {
+    locale: Locale,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if is_csv_content_type(&headers) {
+        return import_credentials_csv(&state, &body, locale).await;
+    }
+
+    let payload = match Json::<ImportCredentialsRequest>::from_bytes(&body) {
+        Ok(Json(payload)) => payload,
+        Err(rejection) => return rejection.into_response(),
+    };
+
     if payload.credentials.is_empty() {
         return (
             StatusCode::BAD_REQUEST,
-            Json(AdminErrorResponse::invalid_request("凭据列表不能为空")),
+            Json(AdminErrorResponse::invalid_request(
+                ErrorCode::EmptyCredentialList,
+                locale,
+            )),
         )
             .into_response();
     }
 
     match state.service.import_credentials(payload.credentials, payload.pool_id).await {
         Ok(response) => Json(response).into_response(),
-        Err(e) => (e.status_code(), Json(e.into_response())).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// POST /api/admin/credentials/import-kiro-ide
+/// 批量导入 Kiro IDE 导出格式的凭据（请求体为 JSON 数组，可选 `?pool_id=`）
+pub async fn import_kiro_ide_credentials(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Query(query): Query<ImportKiroIdeQuery>,
+    Json(items): Json<Vec<serde_json::Value>>,
+) -> Response {
+    if items.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(AdminErrorResponse::invalid_request(
+                ErrorCode::EmptyCredentialList,
+                locale,
+            )),
+        )
+            .into_response();
+    }
+
+    match state
+        .service
+        .import_kiro_ide_credentials(items, query.pool_id)
+        .await
+    {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
+    }
+}
+
+/// 请求体是否为 CSV
+fn is_csv_content_type(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("text/csv"))
+}
+
+/// 从 CSV 请求体批量导入凭据
+async fn import_credentials_csv(state: &AdminState, body: &[u8], locale: Locale) -> Response {
+    let Ok(content) = std::str::from_utf8(body) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(AdminErrorResponse::invalid_request(
+                ErrorCode::CsvNotUtf8,
+                locale,
+            )),
+        )
+            .into_response();
+    };
+
+    match state.service.import_credentials_csv(content).await {
+        Ok(result) => Json(result).into_response(),
+        Err(e) => (e.status_code(), Json(e.into_response(locale))).into_response(),
     }
 }
 
@@ -146,9 +741,130 @@ pub async fn set_scheduling_mode(
     Json(payload): Json<SetSchedulingModeRequest>,
 ) -> impl IntoResponse {
     state.service.set_scheduling_mode(payload.mode);
-    let mode_name = match payload.mode {
-        crate::kiro::token_manager::SchedulingMode::RoundRobin => "轮询模式",
-        crate::kiro::token_manager::SchedulingMode::PriorityFill => "优先填充模式",
-    };
-    Json(SuccessResponse::new(format!("调度模式已切换为: {}", mode_name)))
+    Json(SuccessResponse::new(format!(
+        "调度模式已切换为: {}",
+        payload.mode.label()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_json_with_etag_not_modified() {
+        let value = serde_json::json!({"total": 2, "credentials": [{"id": 1}, {"id": 2}]});
+        let response = json_with_etag(&HeaderMap::new(), &value);
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].clone();
+
+        // 内容未变化：304 且不携带响应体
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        let response = json_with_etag(&headers, &value);
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+
+        // 内容变化：返回新的完整响应
+        let changed = serde_json::json!({"total": 1, "credentials": [{"id": 1}]});
+        let response = json_with_etag(&headers, &changed);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG], etag);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_priority_reorder() {
+        use crate::admin::{AdminService, ApiKeyManager};
+        use crate::kiro::model::credentials::KiroCredentials;
+        use crate::kiro::token_manager::MultiTokenManager;
+        use crate::model::config::Config;
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir().unwrap();
+        let credentials_path = dir.path().join("credentials.json");
+        let credentials: Vec<KiroCredentials> = (1..=5)
+            .map(|id| KiroCredentials {
+                id: Some(id),
+                priority: (id - 1) as u32,
+                refresh_token: Some(format!("{}{}", "r".repeat(150), id)),
+                ..KiroCredentials::default()
+            })
+            .collect();
+        std::fs::write(
+            &credentials_path,
+            serde_json::to_string(&credentials).unwrap(),
+        )
+        .unwrap();
+        let token_manager = Arc::new(
+            MultiTokenManager::new(
+                Config::default(),
+                credentials,
+                None,
+                Some(credentials_path.clone()),
+            )
+            .unwrap(),
+        );
+        assert_eq!(token_manager.snapshot().current_id, 1);
+        let state = AdminState::new(
+            "admin-key",
+            AdminService::new(token_manager.clone()),
+            Config::default(),
+            dir.path().join("config.json"),
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap()),
+        );
+        let request = |items: serde_json::Value| -> BulkPriorityRequest {
+            serde_json::from_value(items).unwrap()
+        };
+
+        // 同一批次优先级重复：400 且不修改任何凭据
+        let response = set_credential_priorities(
+            State(state.clone()),
+            Locale::En,
+            Json(request(serde_json::json!([
+                {"id": 1, "priority": 0},
+                {"id": 2, "priority": 0}
+            ]))),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // 拖拽后的顺序：5, 3, 1, 4, 2，另含一个不存在的凭据
+        let order = [5u64, 3, 1, 4, 2];
+        let mut items: Vec<serde_json::Value> = order
+            .iter()
+            .enumerate()
+            .map(|(priority, id)| serde_json::json!({"id": id, "priority": priority}))
+            .collect();
+        items.push(serde_json::json!({"id": 99, "priority": 10}));
+        let response = set_credential_priorities(
+            State(state.clone()),
+            Locale::En,
+            Json(request(serde_json::Value::Array(items))),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["updated"], serde_json::json!(order));
+        assert_eq!(body["failed"][0]["id"], 99);
+        assert_eq!(body["failed"].as_array().unwrap().len(), 1);
+
+        // 内存与文件中的优先级一致，当前凭据切换为优先级最高的 #5
+        let snapshot = token_manager.snapshot();
+        assert_eq!(snapshot.current_id, 5);
+        let saved: Vec<KiroCredentials> =
+            serde_json::from_str(&std::fs::read_to_string(&credentials_path).unwrap()).unwrap();
+        for (priority, id) in order.iter().enumerate() {
+            let entry = snapshot.entries.iter().find(|e| e.id == *id).unwrap();
+            assert_eq!(entry.priority, priority as u32);
+            let saved = saved.iter().find(|c| c.id == Some(*id)).unwrap();
+            assert_eq!(saved.priority, priority as u32);
+        }
+    }
 }
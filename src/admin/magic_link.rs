@@ -0,0 +1,394 @@
+//! Admin UI 一次性登录链接
+//!
+//! `POST /api/admin/auth/magic-link` 生成 15 分钟内有效的一次性 Token，
+//! 访问 `/admin/login?token=...` 时兑换为 HttpOnly 会话 Cookie。
+//! 会话与 Admin API Key 等效（修改操作仍需 CSRF Token），绑定签发时完整 Admin Key 的哈希，
+//! 仅保存在内存中，重启或调用 `POST /api/admin/auth/logout` 后失效。
+//! Admin Key 校验失败次数过多的客户端会被暂时拒绝，防止通过该端点暴力猜测 Admin Key
+
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use axum::extract::ConnectInfo;
+use axum::http::{Extensions, HeaderMap, header};
+use dashmap::DashMap;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use super::mtls::AdminPeer;
+use crate::common::auth;
+
+/// 登录 Token 有效期
+pub const MAGIC_LINK_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// 会话有效期
+pub const SESSION_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// 会话 Cookie 名称
+pub const SESSION_COOKIE: &str = "kiro_admin_session";
+
+/// Admin API 默认挂载路径
+pub const ADMIN_API_PATH: &str = "/api/admin";
+
+/// Admin UI 默认挂载路径
+pub const ADMIN_UI_PATH: &str = "/admin";
+
+/// 单个客户端在统计窗口内允许的 Admin Key 校验失败次数
+pub const MAX_FAILED_ATTEMPTS: u32 = 10;
+
+/// 校验失败次数的统计窗口
+pub const FAILED_ATTEMPT_WINDOW: Duration = Duration::from_secs(60);
+
+/// 登录 Token：token -> (过期时间, Admin Key 指纹)
+static MAGIC_LINKS: OnceLock<DashMap<String, (Instant, String)>> = OnceLock::new();
+
+/// 会话：session id -> (过期时间, Admin Key 指纹)
+static SESSIONS: OnceLock<DashMap<String, (Instant, String)>> = OnceLock::new();
+
+/// Admin Key 校验失败记录：客户端 -> (窗口起始时间, 失败次数)
+static FAILED_ATTEMPTS: OnceLock<DashMap<String, (Instant, u32)>> = OnceLock::new();
+
+fn magic_links() -> &'static DashMap<String, (Instant, String)> {
+    MAGIC_LINKS.get_or_init(DashMap::new)
+}
+
+fn sessions() -> &'static DashMap<String, (Instant, String)> {
+    SESSIONS.get_or_init(DashMap::new)
+}
+
+fn failed_attempts() -> &'static DashMap<String, (Instant, u32)> {
+    FAILED_ATTEMPTS.get_or_init(DashMap::new)
+}
+
+/// 生成 256 位随机 Token（十六进制）
+fn random_token() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().r#gen();
+    hex::encode(bytes)
+}
+
+/// 完整 Admin Key 的 SHA-256 指纹（十六进制），内存中不保存明文
+fn key_fingerprint(admin_key: &str) -> String {
+    hex::encode(Sha256::digest(admin_key.as_bytes()))
+}
+
+/// 清理过期条目
+fn purge_expired(store: &DashMap<String, (Instant, String)>) {
+    let now = Instant::now();
+    store.retain(|_, (expires_at, _)| *expires_at > now);
+}
+
+/// Admin 路由的实际挂载路径（嵌入宿主应用时带有宿主前缀）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminPaths {
+    /// Admin API 路径，如 `/api/admin`
+    pub api: String,
+    /// Admin UI 路径，如 `/admin`
+    pub ui: String,
+}
+
+impl Default for AdminPaths {
+    fn default() -> Self {
+        Self::with_prefix("")
+    }
+}
+
+impl AdminPaths {
+    fn with_prefix(prefix: &str) -> Self {
+        let prefix = prefix.trim_end_matches('/');
+        Self {
+            api: format!("{}{}", prefix, ADMIN_API_PATH),
+            ui: format!("{}{}", prefix, ADMIN_UI_PATH),
+        }
+    }
+
+    /// 由 Admin API 的挂载路径（axum `NestedPath`）推导
+    pub fn from_api_path(nested_path: Option<&str>) -> Self {
+        nested_path
+            .and_then(|path| path.strip_suffix(ADMIN_API_PATH))
+            .map(Self::with_prefix)
+            .unwrap_or_default()
+    }
+
+    /// 由 Admin UI 的挂载路径（axum `NestedPath`）推导
+    pub fn from_ui_path(nested_path: Option<&str>) -> Self {
+        nested_path
+            .and_then(|path| path.strip_suffix(ADMIN_UI_PATH))
+            .map(Self::with_prefix)
+            .unwrap_or_default()
+    }
+}
+
+/// 请求是否经 HTTPS 到达（Admin mTLS 监听器，或反向代理设置了 `X-Forwarded-Proto: https`）
+///
+/// 仅此时会话 Cookie 才带 `Secure` 属性，纯 HTTP 部署下浏览器仍能保存会话
+pub fn is_https(headers: &HeaderMap, extensions: &Extensions) -> bool {
+    if extensions.get::<ConnectInfo<AdminPeer>>().is_some() {
+        return true;
+    }
+    headers
+        .get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"))
+}
+
+/// 失败次数统计使用的客户端标识（取不到对端地址时所有请求共用一个计数）
+pub fn client_key(extensions: &Extensions) -> String {
+    extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// 客户端当前是否因校验失败过多被拒绝，是则返回剩余等待时间
+pub fn attempts_blocked(client: &str) -> Option<Duration> {
+    let (window_start, failures) = *failed_attempts().get(client)?;
+    let elapsed = window_start.elapsed();
+    (failures >= MAX_FAILED_ATTEMPTS && elapsed < FAILED_ATTEMPT_WINDOW)
+        .then(|| FAILED_ATTEMPT_WINDOW - elapsed)
+}
+
+/// 记录一次 Admin Key 校验失败
+pub fn record_failed_attempt(client: &str) {
+    let now = Instant::now();
+    failed_attempts()
+        .retain(|_, (window_start, _)| now.duration_since(*window_start) < FAILED_ATTEMPT_WINDOW);
+
+    let mut entry = failed_attempts()
+        .entry(client.to_string())
+        .or_insert((now, 0));
+    entry.1 += 1;
+}
+
+/// 已生成的登录 Token
+pub struct MagicLink {
+    pub token: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl MagicLink {
+    /// Admin UI 登录地址
+    pub fn login_url(&self, paths: &AdminPaths) -> String {
+        format!("{}/login?token={}", paths.ui, self.token)
+    }
+}
+
+/// 为已认证的 Admin Key 生成一次性登录 Token
+pub fn create_magic_link(admin_key: &str) -> MagicLink {
+    purge_expired(magic_links());
+
+    let token = random_token();
+    magic_links().insert(
+        token.clone(),
+        (Instant::now() + MAGIC_LINK_TTL, key_fingerprint(admin_key)),
+    );
+
+    MagicLink {
+        token,
+        expires_at: chrono::Utc::now()
+            + chrono::Duration::from_std(MAGIC_LINK_TTL).expect("TTL 超出范围"),
+    }
+}
+
+/// 兑换登录 Token
+///
+/// Token 无论是否过期都会被删除（一次性），成功时返回签发时的 Admin Key 指纹
+pub fn redeem_magic_link(token: &str) -> Option<String> {
+    let (_, (expires_at, fingerprint)) = magic_links().remove(token)?;
+    (Instant::now() < expires_at).then_some(fingerprint)
+}
+
+/// 创建会话，返回 session id
+pub fn create_session(key_fingerprint: String) -> String {
+    purge_expired(sessions());
+
+    let session_id = random_token();
+    sessions().insert(
+        session_id.clone(),
+        (Instant::now() + SESSION_TTL, key_fingerprint),
+    );
+    session_id
+}
+
+/// 构建会话 Cookie（Set-Cookie 头的值）
+///
+/// Admin API 与 Admin UI（实时状态推送）各设置一个，作用域限定在对应路径下
+pub fn session_cookies(session_id: &str, paths: &AdminPaths, secure: bool) -> [String; 2] {
+    [&paths.api, &paths.ui].map(|path| cookie(session_id, path, SESSION_TTL.as_secs(), secure))
+}
+
+/// 构建清除会话的 Cookie（Set-Cookie 头的值）
+pub fn expired_session_cookies(paths: &AdminPaths, secure: bool) -> [String; 2] {
+    [&paths.api, &paths.ui].map(|path| cookie("", path, 0, secure))
+}
+
+fn cookie(value: &str, path: &str, max_age: u64, secure: bool) -> String {
+    format!(
+        "{}={}; Path={}; Max-Age={}; HttpOnly;{} SameSite=Strict",
+        SESSION_COOKIE,
+        value,
+        path,
+        max_age,
+        if secure { " Secure;" } else { "" }
+    )
+}
+
+/// Cookie 头中携带的所有会话 id
+fn session_ids(headers: &HeaderMap) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .filter(|(name, value)| *name == SESSION_COOKIE && !value.is_empty())
+        .map(|(_, value)| value)
+}
+
+/// 从 Cookie 头中提取会话并校验
+///
+/// 会话须未过期，且签发时的 Admin Key 指纹与当前 Admin Key 一致；成功时返回当前 Admin Key
+pub fn validate_session(headers: &HeaderMap, admin_api_key: &str) -> Option<String> {
+    let fingerprint = key_fingerprint(admin_api_key);
+    session_ids(headers)
+        .any(|session_id| {
+            let Some((expires_at, issued_for)) = sessions()
+                .get(session_id)
+                .map(|entry| entry.value().clone())
+            else {
+                return false;
+            };
+            if Instant::now() >= expires_at {
+                sessions().remove(session_id);
+                return false;
+            }
+            auth::constant_time_eq(&issued_for, &fingerprint)
+        })
+        .then(|| admin_api_key.to_string())
+}
+
+/// 注销请求携带的会话，返回注销的数量
+pub fn revoke_sessions(headers: &HeaderMap) -> usize {
+    session_ids(headers)
+        .filter(|session_id| sessions().remove(*session_id).is_some())
+        .count()
+}
+
+/// 注销所有会话，返回注销的数量
+pub fn revoke_all_sessions() -> usize {
+    let count = sessions().len();
+    sessions().clear();
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn cookie_headers(session_id: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_str(&format!("theme=dark; {}={}", SESSION_COOKIE, session_id))
+                .unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_magic_link_is_single_use() {
+        let link = create_magic_link("sk-admin-test");
+        assert_eq!(link.token.len(), 64);
+        assert_eq!(
+            link.login_url(&AdminPaths::default()),
+            format!("/admin/login?token={}", link.token)
+        );
+
+        let fingerprint = redeem_magic_link(&link.token).unwrap();
+        assert_eq!(fingerprint, key_fingerprint("sk-admin-test"));
+        assert!(!fingerprint.contains("sk-a"));
+        assert_eq!(redeem_magic_link(&link.token), None);
+        assert_eq!(redeem_magic_link("unknown"), None);
+    }
+
+    #[test]
+    fn test_expired_magic_link_is_rejected() {
+        let link = create_magic_link("sk-admin-test");
+        magic_links().get_mut(&link.token).unwrap().0 = Instant::now() - Duration::from_secs(1);
+
+        assert_eq!(redeem_magic_link(&link.token), None);
+        assert!(!magic_links().contains_key(&link.token));
+    }
+
+    #[test]
+    fn test_validate_session_cookie() {
+        let session_id = create_session(key_fingerprint("sk-admin-test"));
+        let headers = cookie_headers(&session_id);
+
+        assert_eq!(
+            validate_session(&headers, "sk-admin-test").as_deref(),
+            Some("sk-admin-test")
+        );
+        // Admin Key 更换后旧会话失效（即使前缀相同）
+        assert_eq!(validate_session(&headers, "sk-admin-other"), None);
+        assert_eq!(validate_session(&headers, "other-key"), None);
+
+        sessions().get_mut(&session_id).unwrap().0 = Instant::now() - Duration::from_secs(1);
+        assert_eq!(validate_session(&headers, "sk-admin-test"), None);
+        assert!(!sessions().contains_key(&session_id));
+    }
+
+    #[test]
+    fn test_revoke_session() {
+        let session_id = create_session(key_fingerprint("sk-admin-test"));
+        let headers = cookie_headers(&session_id);
+        assert!(validate_session(&headers, "sk-admin-test").is_some());
+
+        assert_eq!(revoke_sessions(&headers), 1);
+        assert_eq!(validate_session(&headers, "sk-admin-test"), None);
+        assert_eq!(revoke_sessions(&headers), 0);
+    }
+
+    #[test]
+    fn test_session_cookies_follow_mount_paths_and_scheme() {
+        let paths = AdminPaths::from_ui_path(Some("/llm/admin"));
+        assert_eq!(paths, AdminPaths::from_api_path(Some("/llm/api/admin")));
+        assert_eq!(paths.ui, "/llm/admin");
+        assert_eq!(AdminPaths::from_api_path(None), AdminPaths::default());
+
+        let [api, ui] = session_cookies("abc", &paths, false);
+        assert!(api.starts_with("kiro_admin_session=abc; Path=/llm/api/admin;"));
+        assert!(ui.contains("Path=/llm/admin;"));
+        assert!(api.contains("HttpOnly; SameSite=Strict"));
+        assert!(!api.contains("Secure"));
+
+        let [api, _] = session_cookies("abc", &paths, true);
+        assert!(api.contains("HttpOnly; Secure; SameSite=Strict"));
+        let [expired, _] = expired_session_cookies(&paths, false);
+        assert!(expired.starts_with("kiro_admin_session=; Path=/llm/api/admin; Max-Age=0;"));
+
+        let mut headers = HeaderMap::new();
+        let extensions = Extensions::new();
+        assert!(!is_https(&headers, &extensions));
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("HTTPS"));
+        assert!(is_https(&headers, &extensions));
+    }
+
+    #[test]
+    fn test_failed_attempts_are_limited() {
+        let client = "198.51.100.7";
+        for _ in 0..MAX_FAILED_ATTEMPTS - 1 {
+            record_failed_attempt(client);
+        }
+        assert_eq!(attempts_blocked(client), None);
+
+        record_failed_attempt(client);
+        assert!(attempts_blocked(client).is_some());
+        assert_eq!(attempts_blocked("198.51.100.8"), None);
+
+        failed_attempts().get_mut(client).unwrap().0 = Instant::now() - FAILED_ATTEMPT_WINDOW;
+        assert_eq!(attempts_blocked(client), None);
+    }
+}
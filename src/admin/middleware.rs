@@ -1,24 +1,39 @@
 //! Admin API 中间件
 
 use parking_lot::RwLock;
-use std::path::PathBuf;
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use axum::{
     body::Body,
-    extract::State,
-    http::{Method, Request, StatusCode},
+    extract::{FromRequestParts, State},
+    http::{Method, Request, StatusCode, request::Parts},
     middleware::Next,
     response::{IntoResponse, Json, Response},
 };
 
+use tokio::sync::broadcast;
+
 use super::api_keys::ApiKeyManager;
+use super::backup::BackupManager;
 use super::csrf::CsrfManager;
+use super::effective_config::ConfigProvenanceTracker;
+use super::events::{self, AdminEvent};
+use super::magic_link;
+use super::mtls::ClientCertIdentity;
+use super::preferences::UiPreferencesStore;
 use super::service::AdminService;
 use super::types::AdminErrorResponse;
+use crate::anthropic::{
+    RateLimitExemptions, RateLimiter, TokenBucketLimiter, WebSearchRateLimiter,
+};
 use crate::common::auth;
+use crate::common::features::FeatureFlags;
+use crate::common::i18n::{ErrorCode, Locale};
 use crate::kiro::pool_manager::PoolManager;
 use crate::model::config::Config;
+use crate::server::ServerHandle;
 
 /// Admin API 共享状态
 #[derive(Clone)]
@@ -37,6 +52,28 @@ pub struct AdminState {
     pub pool_manager: Option<Arc<PoolManager>>,
     /// CSRF 管理器
     pub csrf_manager: Arc<CsrfManager>,
+    /// WebSearch 限流器（可选，用于运行统计）
+    pub websearch_limiter: Option<Arc<WebSearchRateLimiter>>,
+    /// 滑动窗口限流器（可选，用于限流统计）
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// 令牌桶限流器（可选，用于运行统计）
+    pub token_bucket: Option<Arc<TokenBucketLimiter>>,
+    /// 限流豁免规则（与 Anthropic API 共享，增删后立即生效）
+    pub rate_limit_exemptions: Arc<RateLimitExemptions>,
+    /// Admin 实时事件广播通道（凭据/池状态变化）
+    pub event_sender: broadcast::Sender<AdminEvent>,
+    /// Admin UI 偏好设置（存储于配置目录的 ui_preferences.json）
+    pub ui_preferences: Arc<UiPreferencesStore>,
+    /// 功能开关（运行时切换写入配置目录的 features.json）
+    pub features: Arc<FeatureFlags>,
+    /// 服务句柄（可选，修改 host/port 时切换监听地址）
+    pub server: Option<ServerHandle>,
+    /// 备份管理器（可选，配置 backupDir 时启用）
+    pub backup_manager: Option<Arc<BackupManager>>,
+    /// 凭据文件路径（可选，使用内存凭据时为 None）
+    pub credentials_path: Option<PathBuf>,
+    /// 配置项来源追踪（配置文件 / Admin 修改）
+    pub config_provenance: Arc<ConfigProvenanceTracker>,
 }
 
 impl AdminState {
@@ -47,15 +84,43 @@ impl AdminState {
         config_path: impl Into<PathBuf>,
         api_key_manager: Arc<ApiKeyManager>,
     ) -> Self {
+        let config_path = config_path.into();
+        let config_dir = config_path
+            .parent()
+            .unwrap_or(std::path::Path::new("."))
+            .to_path_buf();
+
+        let features = Arc::new(FeatureFlags::load(
+            FeatureFlags::default_path(&config_dir),
+            &config.feature_flags,
+        ));
+        let rate_limit_exemptions = Arc::new(RateLimitExemptions::new(
+            config.rate_limit_exemptions.clone(),
+        ));
+        let config_provenance = Arc::new(ConfigProvenanceTracker::load(&config_path));
+
         Self {
             admin_api_key: admin_api_key.into(),
             service: Arc::new(service),
             config: Arc::new(RwLock::new(config)),
-            config_path: config_path.into(),
+            config_path,
             api_key_manager,
             pool_manager: None,
             // CSRF Token 有效期：1 小时
             csrf_manager: Arc::new(CsrfManager::new(3600)),
+            websearch_limiter: None,
+            rate_limiter: None,
+            token_bucket: None,
+            rate_limit_exemptions,
+            event_sender: events::channel(),
+            ui_preferences: Arc::new(UiPreferencesStore::load(
+                UiPreferencesStore::default_path(&config_dir),
+            )),
+            features,
+            server: None,
+            backup_manager: None,
+            credentials_path: None,
+            config_provenance,
         }
     }
 
@@ -65,6 +130,65 @@ impl AdminState {
         self
     }
 
+    /// 设置 WebSearch 限流器
+    pub fn with_websearch_limiter(mut self, limiter: Arc<WebSearchRateLimiter>) -> Self {
+        self.websearch_limiter = Some(limiter);
+        self
+    }
+
+    /// 设置滑动窗口限流器（与 Anthropic API 共享）
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// 设置令牌桶限流器（与 Anthropic API 共享）
+    pub fn with_token_bucket(mut self, bucket: Arc<TokenBucketLimiter>) -> Self {
+        self.token_bucket = Some(bucket);
+        self
+    }
+
+    /// 设置限流豁免规则（与 Anthropic API 共享）
+    pub fn with_rate_limit_exemptions(mut self, exemptions: Arc<RateLimitExemptions>) -> Self {
+        self.rate_limit_exemptions = exemptions;
+        self
+    }
+
+    /// 设置 Admin 事件广播通道（与 Token 管理器共享）
+    pub fn with_event_sender(mut self, sender: broadcast::Sender<AdminEvent>) -> Self {
+        self.event_sender = sender;
+        self
+    }
+
+    /// 设置功能开关（与 Anthropic API 共享，切换后立即生效）
+    pub fn with_features(mut self, features: Arc<FeatureFlags>) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// 设置服务句柄（修改 host/port 时无需重启即可切换监听地址）
+    pub fn with_server(mut self, server: ServerHandle) -> Self {
+        self.server = Some(server);
+        self
+    }
+
+    /// 设置备份管理器（与后台备份任务共享）
+    pub fn with_backup_manager(mut self, manager: Arc<BackupManager>) -> Self {
+        self.backup_manager = Some(manager);
+        self
+    }
+
+    /// 设置凭据文件路径（用于展示生效配置）
+    pub fn with_credentials_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.credentials_path = Some(path.into());
+        self
+    }
+
+    /// 配置文件所在目录（`api_keys.json`、`pools.json` 等文件的位置）
+    pub fn config_dir(&self) -> &Path {
+        self.config_path.parent().unwrap_or(Path::new("."))
+    }
+
     /// 获取配置的克隆
     pub fn get_config(&self) -> Config {
         self.config.read().clone()
@@ -76,9 +200,50 @@ impl AdminState {
         F: FnOnce(&mut Config),
     {
         let mut config = self.config.write();
-        updater(&mut config);
-        config.save(&self.config_path)?;
-        Ok(config.clone())
+        let mut updated = config.clone();
+        updater(&mut updated);
+        // 保存成功后才替换内存配置
+        updated.save(&self.config_path)?;
+        self.config_provenance.record_update(&config, &updated);
+        *config = updated.clone();
+        Ok(updated)
+    }
+}
+
+/// Admin 请求的操作者身份（认证通过后由中间件写入请求扩展，用于禁用历史等记录）
+///
+/// 目前只有一个 Admin Key，身份为 `admin:` 加脱敏的 Key 前缀；
+/// 通过 mTLS 出示客户端证书时为 `mtls:` 加证书 CN
+#[derive(Debug, Clone)]
+pub struct AdminActor(String);
+
+impl AdminActor {
+    /// 从 Admin Key 生成身份（仅保留前 4 个字符）
+    fn from_key(key: &str) -> Self {
+        let prefix: String = key.chars().take(4).collect();
+        Self(format!("admin:{}***", prefix))
+    }
+
+    /// 从客户端证书 CN 生成身份
+    fn from_client_cert(common_name: &str) -> Self {
+        Self(format!("mtls:{}", common_name))
+    }
+
+    /// 身份文本
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AdminActor {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<AdminActor>()
+            .cloned()
+            .unwrap_or_else(|| Self("admin".to_string())))
     }
 }
 
@@ -88,12 +253,36 @@ pub async fn admin_auth_middleware(
     request: Request<Body>,
     next: Next,
 ) -> Response {
-    let api_key = auth::extract_api_key(&request);
+    require_admin_key(request, next, &state.admin_api_key).await
+}
 
-    match api_key {
-        Some(key) if auth::constant_time_eq(&key, &state.admin_api_key) => next.run(request).await,
-        _ => {
-            let error = AdminErrorResponse::authentication_error();
+/// 校验 Admin Key（或登录会话 Cookie），通过后写入操作者身份
+///
+/// 经 mTLS 监听器出示了客户端证书时，Admin Key 作为第二因素仍需校验，身份取证书 CN
+pub(crate) async fn require_admin_key(
+    mut request: Request<Body>,
+    next: Next,
+    admin_api_key: &str,
+) -> Response {
+    let authenticated = match auth::extract_api_key(&request) {
+        Some(key) if auth::constant_time_eq(&key, admin_api_key) => Some(key),
+        // 未携带有效 Admin Key 时，接受一次性登录链接换发的会话 Cookie
+        _ => magic_link::validate_session(request.headers(), admin_api_key),
+    };
+    let client_cert = request.extensions().get::<ClientCertIdentity>().cloned();
+    let actor = authenticated.map(|key| match client_cert {
+        Some(ClientCertIdentity(common_name)) => AdminActor::from_client_cert(&common_name),
+        None => AdminActor::from_key(&key),
+    });
+
+    match actor {
+        Some(actor) => {
+            request.extensions_mut().insert(actor);
+            next.run(request).await
+        }
+        None => {
+            let error =
+                AdminErrorResponse::authentication_error(Locale::from_headers(request.headers()));
             (StatusCode::UNAUTHORIZED, Json(error)).into_response()
         }
     }
@@ -101,7 +290,7 @@ pub async fn admin_auth_middleware(
 
 /// CSRF 验证中间件
 ///
-/// 对 POST/PUT/DELETE 请求验证 x-csrf-token 头
+/// 对 POST/PUT/PATCH/DELETE 请求验证 x-csrf-token 头
 pub async fn csrf_middleware(
     State(state): State<AdminState>,
     request: Request<Body>,
@@ -110,7 +299,11 @@ pub async fn csrf_middleware(
     let method = request.method().clone();
 
     // 只对修改操作验证 CSRF Token
-    if method == Method::POST || method == Method::PUT || method == Method::DELETE {
+    if method == Method::POST
+        || method == Method::PUT
+        || method == Method::PATCH
+        || method == Method::DELETE
+    {
         // 获取 CSRF Token
         let csrf_token = request
             .headers()
@@ -123,7 +316,11 @@ pub async fn csrf_middleware(
                 next.run(request).await
             }
             _ => {
-                let error = AdminErrorResponse::new("csrf_error", "Invalid or missing CSRF token");
+                let error = AdminErrorResponse::new(
+                    "csrf_error",
+                    ErrorCode::CsrfTokenInvalid,
+                    Locale::from_headers(request.headers()),
+                );
                 (StatusCode::FORBIDDEN, Json(error)).into_response()
             }
         }
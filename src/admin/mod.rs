@@ -8,9 +8,14 @@
 //! - 修改凭据优先级
 //! - 重置失败计数
 //! - 查询凭据余额
-//! - 配置管理（读取/更新）
+//! - 配置管理（读取/更新、生效配置及来源）
 //! - API Key 管理（CRUD）
 //! - 池管理（CRUD）
+//! - 凭据/池状态实时事件（供 Admin UI SSE 订阅）
+//! - Admin UI 偏好设置持久化
+//! - 功能开关查询与运行时切换
+//! - 配置文件定期备份与恢复
+//! - 可选的 mTLS 客户端证书认证（独立 Admin 端口）
 //!
 //! # 使用
 //! ```ignore
@@ -21,17 +26,27 @@
 
 pub mod api_keys;
 mod api_key_handlers;
+mod auth_handlers;
+pub mod backup;
+mod backup_handlers;
 mod config_handlers;
 pub mod csrf;
+pub mod effective_config;
 mod error;
+pub mod events;
+mod feature_handlers;
 mod handlers;
+pub mod magic_link;
 mod middleware;
+pub mod mtls;
 mod pool_handlers;
+pub mod preferences;
+mod rate_limit_handlers;
 mod router;
 mod service;
 pub mod types;
 
 pub use api_keys::ApiKeyManager;
-pub use middleware::AdminState;
+pub use middleware::{AdminState, admin_auth_middleware};
 pub use router::create_admin_router;
 pub use service::AdminService;
@@ -0,0 +1,469 @@
+//! Admin API 双向 TLS（mTLS）
+//!
+//! 配置 `adminMtlsCaPath` 后，Admin API 和 Admin UI 不再挂载到主端口，
+//! 改为在独立端口 `adminPort` 上通过 TLS 提供服务：
+//! - 服务端出示 `adminTlsCertPath` / `adminTlsKeyPath` 证书
+//! - 客户端证书链按 CA 校验；`adminMtlsRequired` 开启时未出示证书的连接在握手阶段被拒绝
+//! - 客户端证书的 CN 作为 Admin 操作者身份写入请求扩展（Admin Key 仍需校验，作为第二因素）
+//!
+//! Anthropic API 路由不受影响，仍由主端口以明文 HTTP 提供服务
+
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::{
+    Router,
+    body::Body,
+    extract::{ConnectInfo, connect_info::Connected},
+    http::Request,
+    middleware::{self, Next},
+    response::Response,
+    serve::{IncomingStream, Listener},
+};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Semaphore, mpsc};
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+
+use crate::model::config::Config;
+
+/// TLS 握手超时（超时的连接直接关闭，不影响后续连接）
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 接受 TCP 连接失败（如文件描述符耗尽）后的重试间隔
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// 同时进行中的 TLS 握手上限（达到上限时暂停接受新连接）
+const MAX_PENDING_HANDSHAKES: usize = 256;
+
+/// 已完成握手、等待 HTTP 服务取走的连接队列容量
+const HANDSHAKED_QUEUE_CAPACITY: usize = 64;
+
+/// 构建 Admin 监听器的 TLS 配置
+///
+/// 证书路径为相对路径时相对于配置文件所在目录；未配置 `adminMtlsCaPath` 时返回 None
+pub fn server_config(
+    config: &Config,
+    config_dir: &Path,
+) -> anyhow::Result<Option<Arc<rustls::ServerConfig>>> {
+    let Some(ca_path) = &config.admin_mtls_ca_path else {
+        return Ok(None);
+    };
+    let (Some(cert_path), Some(key_path)) =
+        (&config.admin_tls_cert_path, &config.admin_tls_key_path)
+    else {
+        anyhow::bail!("启用 Admin mTLS 需要配置 adminTlsCertPath 和 adminTlsKeyPath");
+    };
+    build_server_config(
+        &config_dir.join(cert_path),
+        &config_dir.join(key_path),
+        &config_dir.join(ca_path),
+        config.admin_mtls_required,
+    )
+    .map(Some)
+}
+
+/// 按证书文件构建 TLS 配置（ring 加密实现，与上游 HTTP 客户端一致）
+fn build_server_config(
+    cert_path: &Path,
+    key_path: &Path,
+    ca_path: &Path,
+    required: bool,
+) -> anyhow::Result<Arc<rustls::ServerConfig>> {
+    let certs = read_certs(cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("读取 Admin TLS 私钥失败: {}", key_path.display()))?;
+
+    let mut roots = rustls::RootCertStore::empty();
+    for ca in read_certs(ca_path)? {
+        roots
+            .add(ca)
+            .with_context(|| format!("Admin mTLS CA 证书无效: {}", ca_path.display()))?;
+    }
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone());
+    let verifier = if required {
+        verifier.build()
+    } else {
+        verifier.allow_unauthenticated().build()
+    }
+    .context("创建 Admin mTLS 客户端证书校验器失败")?;
+
+    let mut server_config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .context("Admin TLS 协议版本配置无效")?
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)
+        .context("Admin TLS 证书与私钥不匹配")?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Arc::new(server_config))
+}
+
+/// 读取 PEM 证书链
+fn read_certs(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("读取证书失败: {}", path.display()))?;
+    anyhow::ensure!(!certs.is_empty(), "证书文件中没有证书: {}", path.display());
+    Ok(certs)
+}
+
+/// Admin 连接信息（对端地址和客户端证书 CN）
+#[derive(Debug, Clone)]
+pub struct AdminPeer {
+    /// 对端地址
+    pub addr: SocketAddr,
+    /// 客户端证书 CN（未出示证书或证书中没有 CN 时为 None）
+    pub client_cn: Option<String>,
+}
+
+impl Connected<IncomingStream<'_, MtlsListener>> for AdminPeer {
+    fn connect_info(stream: IncomingStream<'_, MtlsListener>) -> Self {
+        stream.remote_addr().clone()
+    }
+}
+
+/// 客户端证书身份（由 [`client_cert_middleware`] 写入请求扩展）
+#[derive(Debug, Clone)]
+pub struct ClientCertIdentity(pub String);
+
+/// TLS 监听器：握手完成后才交给 HTTP 服务，握手失败的连接被丢弃
+///
+/// 后台任务接受 TCP 连接，每个连接在独立任务中握手，完成握手的连接经通道交给
+/// [`Listener::accept`]，慢速或恶意客户端不会阻塞其他连接的握手
+pub struct MtlsListener {
+    local_addr: SocketAddr,
+    handshaked: mpsc::Receiver<(TlsStream<TcpStream>, AdminPeer)>,
+}
+
+impl MtlsListener {
+    pub fn new(
+        listener: TcpListener,
+        server_config: Arc<rustls::ServerConfig>,
+    ) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (sender, handshaked) = mpsc::channel(HANDSHAKED_QUEUE_CAPACITY);
+        tokio::spawn(accept_loop(
+            listener,
+            TlsAcceptor::from(server_config),
+            sender,
+        ));
+        Ok(Self {
+            local_addr,
+            handshaked,
+        })
+    }
+}
+
+/// 接受 TCP 连接并为每个连接启动握手任务（监听器被丢弃后退出）
+async fn accept_loop(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    sender: mpsc::Sender<(TlsStream<TcpStream>, AdminPeer)>,
+) {
+    let handshakes = Arc::new(Semaphore::new(MAX_PENDING_HANDSHAKES));
+    loop {
+        let permit = tokio::select! {
+            _ = sender.closed() => return,
+            permit = handshakes.clone().acquire_owned() => match permit {
+                Ok(permit) => permit,
+                Err(_) => return,
+            },
+        };
+        let (stream, addr) = tokio::select! {
+            _ = sender.closed() => return,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!("Admin 监听器接受连接失败: {}", e);
+                    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                    continue;
+                }
+            },
+        };
+
+        let acceptor = acceptor.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(tls)) => {
+                    let client_cn = tls
+                        .get_ref()
+                        .1
+                        .peer_certificates()
+                        .and_then(|chain| chain.first())
+                        .and_then(|cert| certificate_common_name(cert));
+                    let _ = sender.send((tls, AdminPeer { addr, client_cn })).await;
+                }
+                Ok(Err(e)) => tracing::warn!("Admin TLS 握手失败 ({}): {}", addr, e),
+                Err(_) => tracing::warn!("Admin TLS 握手超时 ({})", addr),
+            }
+        });
+    }
+}
+
+impl Listener for MtlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = AdminPeer;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.handshaked.recv().await {
+            Some(accepted) => accepted,
+            // 接受任务只在监听器被丢弃后退出，这里不会到达
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(AdminPeer {
+            addr: self.local_addr,
+            client_cn: None,
+        })
+    }
+}
+
+/// 将连接信息转为请求扩展：对端地址（与主端口一致的 `ConnectInfo<SocketAddr>`）
+/// 和客户端证书身份
+async fn client_cert_middleware(mut request: Request<Body>, next: Next) -> Response {
+    if let Some(ConnectInfo(peer)) = request
+        .extensions()
+        .get::<ConnectInfo<AdminPeer>>()
+        .cloned()
+    {
+        request.extensions_mut().insert(ConnectInfo(peer.addr));
+        if let Some(cn) = peer.client_cn {
+            request.extensions_mut().insert(ClientCertIdentity(cn));
+        }
+    }
+    next.run(request).await
+}
+
+/// 在 TLS 监听器上运行 Admin 服务
+pub async fn serve(
+    listener: TcpListener,
+    server_config: Arc<rustls::ServerConfig>,
+    app: Router,
+) -> io::Result<()> {
+    let app = app.layer(middleware::from_fn(client_cert_middleware));
+    axum::serve(
+        MtlsListener::new(listener, server_config)?,
+        app.into_make_service_with_connect_info::<AdminPeer>(),
+    )
+    .await
+}
+
+/// 读取一个 DER 元素，返回 (tag, 内容, 剩余字节)
+fn der_read(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first & 0x80 == 0 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        (len, &rest[count..])
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// 提取证书 subject 中的 CN（有多个时取最后一个）
+///
+/// 证书链已由 rustls 校验，这里只按 X.509 结构定位 subject 字段
+fn certificate_common_name(der: &[u8]) -> Option<String> {
+    const SEQUENCE: u8 = 0x30;
+    const EXPLICIT_VERSION: u8 = 0xa0;
+    const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+
+    let (_, certificate, _) = der_read(der).filter(|(tag, ..)| *tag == SEQUENCE)?;
+    let (_, tbs, _) = der_read(certificate).filter(|(tag, ..)| *tag == SEQUENCE)?;
+
+    // TBSCertificate: [0] version（可选）, serialNumber, signature, issuer, validity, subject
+    let mut fields = tbs;
+    if der_read(fields)?.0 == EXPLICIT_VERSION {
+        fields = der_read(fields)?.2;
+    }
+    for _ in 0..4 {
+        fields = der_read(fields)?.2;
+    }
+    let (_, mut rdns, _) = der_read(fields)?;
+
+    // subject: SEQUENCE OF SET OF { type OID, value }
+    let mut common_name = None;
+    while !rdns.is_empty() {
+        let (_, mut attributes, rest) = der_read(rdns)?;
+        rdns = rest;
+        while !attributes.is_empty() {
+            let (_, attribute, rest) = der_read(attributes)?;
+            attributes = rest;
+            let (_, oid, value) = der_read(attribute)?;
+            if oid == OID_COMMON_NAME {
+                let (_, text, _) = der_read(value)?;
+                common_name = Some(String::from_utf8_lossy(text).into_owned());
+            }
+        }
+    }
+    common_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+
+    use crate::admin::middleware::AdminActor;
+
+    /// 测试证书（openssl 生成的 P-256 自签名 CA 及其签发的证书，有效期 100 年）
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/mtls")
+            .join(name)
+    }
+
+    fn fixture_bytes(name: &str) -> Vec<u8> {
+        std::fs::read(fixture(name)).unwrap()
+    }
+
+    /// 启动带 Admin 认证的 TLS 服务，返回监听地址
+    async fn start(required: bool) -> SocketAddr {
+        let server_config = build_server_config(
+            &fixture("server.pem"),
+            &fixture("server.key"),
+            &fixture("ca.pem"),
+            required,
+        )
+        .unwrap();
+        let app = Router::new()
+            .route(
+                "/whoami",
+                get(|actor: AdminActor| async move { actor.as_str().to_string() }),
+            )
+            .layer(middleware::from_fn(
+                |request: Request<Body>, next: Next| async move {
+                    crate::admin::middleware::require_admin_key(request, next, "admin-secret").await
+                },
+            ));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, server_config, app));
+        addr
+    }
+
+    fn client(identity: Option<(&str, &str)>) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .use_rustls_tls()
+            .tls_built_in_root_certs(false)
+            .add_root_certificate(
+                reqwest::Certificate::from_pem(&fixture_bytes("ca.pem")).unwrap(),
+            );
+        if let Some((cert, key)) = identity {
+            let mut pem = fixture_bytes(cert);
+            pem.extend(fixture_bytes(key));
+            builder = builder.identity(reqwest::Identity::from_pem(&pem).unwrap());
+        }
+        builder.build().unwrap()
+    }
+
+    async fn whoami(
+        client: &reqwest::Client,
+        addr: SocketAddr,
+        key: Option<&str>,
+    ) -> reqwest::Result<(u16, String)> {
+        let mut request = client.get(format!("https://localhost:{}/whoami", addr.port()));
+        if let Some(key) = key {
+            request = request.header("x-api-key", key);
+        }
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        Ok((status, response.text().await?))
+    }
+
+    #[test]
+    fn test_certificate_common_name() {
+        let cert = CertificateDer::from_pem_file(fixture("client.pem")).unwrap();
+        assert_eq!(certificate_common_name(&cert).as_deref(), Some("ops-admin"));
+        assert_eq!(certificate_common_name(b"\x30\x03\x02\x01"), None);
+    }
+
+    #[tokio::test]
+    async fn test_required_client_certificate() {
+        let addr = start(true).await;
+        let trusted = client(Some(("client.pem", "client.key")));
+
+        // 受信任的客户端证书 + Admin Key：CN 作为操作者身份
+        let (status, actor) = whoami(&trusted, addr, Some("admin-secret")).await.unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(actor, "mtls:ops-admin");
+
+        // Admin Key 仍然必需
+        let (status, _) = whoami(&trusted, addr, None).await.unwrap();
+        assert_eq!(status, 401);
+        let (status, _) = whoami(&trusted, addr, Some("wrong")).await.unwrap();
+        assert_eq!(status, 401);
+
+        // 未出示证书或证书不受信任时握手失败
+        assert!(
+            whoami(&client(None), addr, Some("admin-secret"))
+                .await
+                .is_err()
+        );
+        let untrusted = client(Some(("untrusted_client.pem", "untrusted_client.key")));
+        assert!(
+            whoami(&untrusted, addr, Some("admin-secret"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_optional_client_certificate() {
+        let addr = start(false).await;
+
+        // 未出示证书时按 Admin Key 识别身份
+        let (status, actor) = whoami(&client(None), addr, Some("admin-secret"))
+            .await
+            .unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(actor, "admin:admi***");
+
+        // 出示的证书仍需通过 CA 校验
+        let trusted = client(Some(("client.pem", "client.key")));
+        let (_, actor) = whoami(&trusted, addr, Some("admin-secret")).await.unwrap();
+        assert_eq!(actor, "mtls:ops-admin");
+        let untrusted = client(Some(("untrusted_client.pem", "untrusted_client.key")));
+        assert!(
+            whoami(&untrusted, addr, Some("admin-secret"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stalled_handshake_does_not_block_other_connections() {
+        let addr = start(false).await;
+
+        // 只建立 TCP 连接、不发送 ClientHello 的客户端不影响其他连接握手
+        let _stalled = TcpStream::connect(addr).await.unwrap();
+        let (status, _) = tokio::time::timeout(
+            Duration::from_secs(5),
+            whoami(&client(None), addr, Some("admin-secret")),
+        )
+        .await
+        .expect("握手被未完成的连接阻塞")
+        .unwrap();
+        assert_eq!(status, 200);
+    }
+}
@@ -5,79 +5,120 @@
 use axum::{
     Json,
     body::Body,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
 };
 
-use crate::kiro::pool::{Pool, PoolError};
-use crate::kiro::pool_manager::UpdatePoolRequest as PoolUpdateRequest;
+use crate::common::i18n::{ErrorCode, Locale};
+use crate::kiro::pool::{DEFAULT_POOL_ID, Pool, PoolError};
+use crate::kiro::pool_manager::{
+    DeletePoolStrategy, PoolManager, UpdatePoolRequest as PoolUpdateRequest,
+};
 
 use super::{
+    api_keys::ApiKeyMasked,
     middleware::AdminState,
     types::{
-        AdminErrorResponse, AssignCredentialToPoolRequest, CreatePoolRequest, CredentialStatusItem,
-        PoolCredentialsResponse, PoolStatusItem, PoolsListResponse, SetPoolDisabledRequest,
-        SuccessResponse, UpdatePoolRequest,
+        AdminErrorResponse, AssignCredentialToPoolRequest, AssignCredentialToPoolResponse,
+        CreatePoolRequest, CredentialStatusItem, DeletePoolQuery, PoolApiKeyBinding,
+        PoolApiKeyItem, PoolApiKeysResponse, PoolCredentialsResponse, PoolStatusItem,
+        PoolsListResponse, RebalancePoolsRequest, RenamePoolRequest, SetPoolDisabledRequest,
+        SetSchedulingModeRequest, SuccessResponse, TransferCredentialPoolRequest, TransferCredentialPoolResponse,
+        UpdatePoolRequest,
     },
 };
 
 /// 将 PoolError 转换为 HTTP 响应
-fn pool_error_to_response(e: PoolError) -> Response<Body> {
-    match &e {
-        PoolError::PoolNotFound { .. } => (
-            StatusCode::NOT_FOUND,
-            Json(AdminErrorResponse::not_found(e.to_string())),
-        )
-            .into_response(),
-        PoolError::PoolAlreadyExists { .. } => (
-            StatusCode::CONFLICT,
-            Json(AdminErrorResponse::invalid_request(e.to_string())),
-        )
-            .into_response(),
-        PoolError::CannotDeleteDefaultPool => (
-            StatusCode::BAD_REQUEST,
-            Json(AdminErrorResponse::invalid_request(e.to_string())),
-        )
-            .into_response(),
-        PoolError::CredentialNotFound { .. } => (
-            StatusCode::NOT_FOUND,
-            Json(AdminErrorResponse::not_found(e.to_string())),
-        )
-            .into_response(),
-        PoolError::ConfigLoadFailed { .. } => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(AdminErrorResponse::internal_error(e.to_string())),
-        )
-            .into_response(),
-        PoolError::IoError(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(AdminErrorResponse::internal_error(e.to_string())),
-        )
-            .into_response(),
-        PoolError::JsonError(_) => (
-            StatusCode::BAD_REQUEST,
-            Json(AdminErrorResponse::invalid_request(e.to_string())),
-        )
-            .into_response(),
-        PoolError::TokenManagerError(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(AdminErrorResponse::internal_error(e.to_string())),
-        )
-            .into_response(),
+fn pool_error_to_response(e: PoolError, locale: Locale) -> Response<Body> {
+    let (status, error_type) = match &e {
+        PoolError::PoolNotFound { .. } | PoolError::CredentialNotFound { .. } => {
+            (StatusCode::NOT_FOUND, "not_found")
+        }
+        PoolError::PoolAlreadyExists { .. }
+        | PoolError::PoolDisabled { .. }
+        | PoolError::PoolNotEmpty { .. }
+        | PoolError::PoolHasApiKeys { .. } => (StatusCode::CONFLICT, "invalid_request"),
+        PoolError::CannotDeleteDefaultPool
+        | PoolError::CannotRenameDefaultPool
+        | PoolError::InvalidPoolId { .. }
+        | PoolError::InvalidPoolConfig { .. }
+        | PoolError::JsonError(_) => (StatusCode::BAD_REQUEST, "invalid_request"),
+        PoolError::ConfigLoadFailed { .. }
+        | PoolError::PersistFailed { .. }
+        | PoolError::IoError(_)
+        | PoolError::TokenManagerError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+    };
+    (
+        status,
+        Json(AdminErrorResponse::new(error_type, e.localized(), locale)),
+    )
+        .into_response()
+}
+
+/// 池管理器未初始化时的响应
+fn pool_manager_unavailable(locale: Locale) -> Response<Body> {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(AdminErrorResponse::api_error(
+            ErrorCode::PoolManagerUnavailable,
+            locale,
+        )),
+    )
+        .into_response()
+}
+
+/// API Key 与池的绑定方式（与该池无关时返回 None）
+fn api_key_binding(key: &ApiKeyMasked, pool_id: &str) -> Option<PoolApiKeyBinding> {
+    match &key.pool_id {
+        None => (pool_id == DEFAULT_POOL_ID).then_some(PoolApiKeyBinding::Default),
+        Some(binding) if binding.contains(pool_id) => Some(PoolApiKeyBinding::Direct),
+        Some(binding) if binding.contains(PoolManager::AUTO_ROUTE_POOL_ID) => {
+            Some(PoolApiKeyBinding::Auto)
+        }
+        Some(_) => None,
     }
 }
 
+/// 能访问指定池的 API Key（显式绑定的在前）
+fn pool_api_keys(keys: &[ApiKeyMasked], pool_id: &str) -> Vec<PoolApiKeyItem> {
+    let mut items: Vec<PoolApiKeyItem> = keys
+        .iter()
+        .filter_map(|key| {
+            Some(PoolApiKeyItem {
+                binding: api_key_binding(key, pool_id)?,
+                api_key: key.clone(),
+            })
+        })
+        .collect();
+    items.sort_by_key(|item| item.binding);
+    items
+}
+
+/// 绑定到指定池的 API Key 数量（不含自动路由）
+fn bound_api_key_count(keys: &[ApiKeyMasked], pool_id: &str) -> usize {
+    keys.iter()
+        .filter(|key| {
+            matches!(
+                api_key_binding(key, pool_id),
+                Some(PoolApiKeyBinding::Direct | PoolApiKeyBinding::Default)
+            )
+        })
+        .count()
+}
+
 /// GET /api/admin/pools
 /// 获取所有池
-pub async fn get_all_pools(State(state): State<AdminState>) -> impl IntoResponse {
+pub async fn get_all_pools(State(state): State<AdminState>, locale: Locale) -> impl IntoResponse {
     match &state.pool_manager {
         Some(pm) => {
             let pools = pm.snapshot();
+            let api_keys = state.api_key_manager.list();
             Json(PoolsListResponse {
                 pools: pools
                     .into_iter()
                     .map(|p| PoolStatusItem {
+                        bound_api_key_count: bound_api_key_count(&api_keys, &p.id),
                         id: p.id,
                         name: p.name,
                         description: p.description,
@@ -85,21 +126,38 @@ pub async fn get_all_pools(State(state): State<AdminState>) -> impl IntoResponse
                         scheduling_mode: p.scheduling_mode,
                         has_proxy: p.has_proxy,
                         priority: p.priority,
+                        effective_priority: p.effective_priority,
+                        overflow_pool_id: p.overflow_pool_id,
+                        default_region: p.default_region,
+                        default_auth_method: p.default_auth_method,
+                        default_machine_id: p.default_machine_id,
+                        has_default_proxy: p.has_default_proxy,
                         total_credentials: p.total_credentials,
                         available_credentials: p.available_credentials,
                         current_id: p.current_id,
                         session_cache_size: p.session_cache_size,
+                        session_cache_capacity: p.session_cache_capacity,
+                        session_cache_ttl_secs: p.session_cache_ttl_secs,
                         round_robin_counter: p.round_robin_counter,
+                        performance_history: None,
                     })
                     .collect(),
             })
             .into_response()
         }
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(AdminErrorResponse::api_error("池管理器未初始化")),
-        )
-            .into_response(),
+        None => pool_manager_unavailable(locale),
+    }
+}
+
+/// GET /api/admin/pools/routing-stats
+/// 获取自动路由统计
+pub async fn get_routing_stats(
+    State(state): State<AdminState>,
+    locale: Locale,
+) -> impl IntoResponse {
+    match &state.pool_manager {
+        Some(pm) => Json(pm.routing_stats()).into_response(),
+        None => pool_manager_unavailable(locale),
     }
 }
 
@@ -107,13 +165,23 @@ pub async fn get_all_pools(State(state): State<AdminState>) -> impl IntoResponse
 /// 创建新池
 pub async fn create_pool(
     State(state): State<AdminState>,
+    locale: Locale,
     Json(payload): Json<CreatePoolRequest>,
 ) -> impl IntoResponse {
     match &state.pool_manager {
         Some(pm) => {
             let pool = Pool::new(&payload.id, &payload.name)
                 .with_scheduling_mode(payload.scheduling_mode)
-                .with_priority(payload.priority);
+                .with_priority(payload.priority)
+                .with_session_cache(
+                    payload.session_cache_max_capacity.filter(|&c| c > 0),
+                    payload.session_cache_ttl_secs.filter(|&t| t > 0),
+                )
+                .with_timeouts(
+                    payload.connect_timeout_secs.filter(|&t| t > 0),
+                    payload.refresh_request_timeout_secs.filter(|&t| t > 0),
+                    payload.upstream_first_byte_timeout_secs.filter(|&t| t > 0),
+                );
 
             let pool = if let Some(desc) = payload.description {
                 pool.with_description(desc)
@@ -127,20 +195,39 @@ pub async fn create_pool(
                 pool
             };
 
+            let pool = match payload.overflow_pool_id.filter(|id| !id.is_empty()) {
+                Some(overflow_pool_id) => pool.with_overflow_pool(overflow_pool_id),
+                None => pool,
+            };
+
+            let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+            let pool = pool.with_credential_defaults(
+                non_empty(payload.default_region),
+                non_empty(payload.default_auth_method).map(|m| m.to_ascii_lowercase()),
+            );
+            let pool = match non_empty(payload.default_machine_id) {
+                Some(machine_id) => pool.with_default_machine_id(machine_id),
+                None => pool,
+            };
+            let pool = match non_empty(payload.default_proxy_url) {
+                Some(proxy_url) => pool.with_default_proxy(
+                    proxy_url,
+                    payload.default_proxy_username,
+                    payload.default_proxy_password,
+                ),
+                None => pool,
+            };
+
             match pm.create_pool(pool) {
                 Ok(_) => (
                     StatusCode::CREATED,
                     Json(SuccessResponse::new(format!("池 {} 创建成功", payload.id))),
                 )
                     .into_response(),
-                Err(e) => pool_error_to_response(e),
+                Err(e) => pool_error_to_response(e, locale),
             }
         }
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(AdminErrorResponse::api_error("池管理器未初始化")),
-        )
-            .into_response(),
+        None => pool_manager_unavailable(locale),
     }
 }
 
@@ -148,11 +235,13 @@ pub async fn create_pool(
 /// 获取池详情
 pub async fn get_pool(
     State(state): State<AdminState>,
+    locale: Locale,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
     match &state.pool_manager {
         Some(pm) => match pm.get_pool(&id) {
             Some(pool) => {
+                let pool = pool.read();
                 let snapshot = pool.token_manager.snapshot();
                 Json(PoolStatusItem {
                     id: pool.config.id.clone(),
@@ -162,25 +251,37 @@ pub async fn get_pool(
                     scheduling_mode: pool.config.scheduling_mode,
                     has_proxy: pool.config.has_proxy(),
                     priority: pool.config.priority,
+                    effective_priority: pool.priority(),
+                    overflow_pool_id: pool.config.overflow_pool_id.clone(),
+                    default_region: pool.config.default_region.clone(),
+                    default_auth_method: pool.config.default_auth_method.clone(),
+                    default_machine_id: pool.config.default_machine_id.clone(),
+                    has_default_proxy: pool.config.default_proxy_url.is_some(),
                     total_credentials: snapshot.total,
                     available_credentials: snapshot.available,
                     current_id: snapshot.current_id,
                     session_cache_size: snapshot.session_cache_size as u64,
+                    session_cache_capacity: snapshot.session_cache_capacity,
+                    session_cache_ttl_secs: snapshot.session_cache_ttl_secs,
                     round_robin_counter: snapshot.round_robin_counter,
+                    bound_api_key_count: bound_api_key_count(
+                        &state.api_key_manager.list(),
+                        &pool.config.id,
+                    ),
+                    performance_history: Some(pool.performance.snapshot()),
                 })
                 .into_response()
             }
             None => (
                 StatusCode::NOT_FOUND,
-                Json(AdminErrorResponse::not_found(format!("池不存在: {}", id))),
+                Json(AdminErrorResponse::not_found(
+                    ErrorCode::PoolNotFound.arg("pool_id", &id),
+                    locale,
+                )),
             )
                 .into_response(),
         },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(AdminErrorResponse::api_error("池管理器未初始化")),
-        )
-            .into_response(),
+        None => pool_manager_unavailable(locale),
     }
 }
 
@@ -188,6 +289,7 @@ pub async fn get_pool(
 /// 更新池配置
 pub async fn update_pool(
     State(state): State<AdminState>,
+    locale: Locale,
     Path(id): Path<String>,
     Json(payload): Json<UpdatePoolRequest>,
 ) -> impl IntoResponse {
@@ -202,18 +304,26 @@ pub async fn update_pool(
                 proxy_username: payload.proxy_username,
                 proxy_password: payload.proxy_password,
                 priority: payload.priority,
+                session_cache_max_capacity: payload.session_cache_max_capacity,
+                session_cache_ttl_secs: payload.session_cache_ttl_secs,
+                connect_timeout_secs: payload.connect_timeout_secs,
+                refresh_request_timeout_secs: payload.refresh_request_timeout_secs,
+                upstream_first_byte_timeout_secs: payload.upstream_first_byte_timeout_secs,
+                overflow_pool_id: payload.overflow_pool_id,
+                default_region: payload.default_region,
+                default_auth_method: payload.default_auth_method,
+                default_machine_id: payload.default_machine_id,
+                default_proxy_url: payload.default_proxy_url,
+                default_proxy_username: payload.default_proxy_username,
+                default_proxy_password: payload.default_proxy_password,
             };
 
             match pm.update_pool(&id, updates) {
                 Ok(_) => Json(SuccessResponse::new(format!("池 {} 已更新", id))).into_response(),
-                Err(e) => pool_error_to_response(e),
+                Err(e) => pool_error_to_response(e, locale),
             }
         }
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(AdminErrorResponse::api_error("池管理器未初始化")),
-        )
-            .into_response(),
+        None => pool_manager_unavailable(locale),
     }
 }
 
@@ -221,18 +331,29 @@ pub async fn update_pool(
 /// 删除池
 pub async fn delete_pool(
     State(state): State<AdminState>,
+    locale: Locale,
     Path(id): Path<String>,
+    Query(query): Query<DeletePoolQuery>,
 ) -> impl IntoResponse {
+    // 池内仍有凭据或仍被 API Key 绑定时：指定 reassign_to 或 force=true（转入默认池）才允许删除
+    let strategy = match query.reassign_to {
+        Some(target) => DeletePoolStrategy::Reassign(target),
+        None if query.force => DeletePoolStrategy::Reassign(DEFAULT_POOL_ID.to_string()),
+        None => DeletePoolStrategy::Refuse,
+    };
     match &state.pool_manager {
-        Some(pm) => match pm.delete_pool(&id) {
-            Ok(_) => Json(SuccessResponse::new(format!("池 {} 已删除", id))).into_response(),
-            Err(e) => pool_error_to_response(e),
-        },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(AdminErrorResponse::api_error("池管理器未初始化")),
-        )
+        Some(pm) => match pm.delete_pool(&id, strategy, &state.api_key_manager) {
+            Ok(summary) if summary.credentials == 0 && summary.api_keys == 0 => {
+                Json(SuccessResponse::new(format!("池 {} 已删除", id))).into_response()
+            }
+            Ok(summary) => Json(SuccessResponse::new(format!(
+                "池 {} 已删除，{} 个凭据、{} 个 API Key 已重新分配",
+                id, summary.credentials, summary.api_keys
+            )))
             .into_response(),
+            Err(e) => pool_error_to_response(e, locale),
+        },
+        None => pool_manager_unavailable(locale),
     }
 }
 
@@ -240,6 +361,7 @@ pub async fn delete_pool(
 /// 设置池禁用状态
 pub async fn set_pool_disabled(
     State(state): State<AdminState>,
+    locale: Locale,
     Path(id): Path<String>,
     Json(payload): Json<SetPoolDisabledRequest>,
 ) -> impl IntoResponse {
@@ -249,37 +371,139 @@ pub async fn set_pool_disabled(
                 let action = if payload.disabled { "禁用" } else { "启用" };
                 Json(SuccessResponse::new(format!("池 {} 已{}", id, action))).into_response()
             }
-            Err(e) => pool_error_to_response(e),
+            Err(e) => pool_error_to_response(e, locale),
         },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(AdminErrorResponse::api_error("池管理器未初始化")),
-        )
+        None => pool_manager_unavailable(locale),
+    }
+}
+
+/// POST /api/admin/pools/:id/scheduling-mode
+/// 设置池的调度模式（写入池配置，重启后保持）
+pub async fn set_pool_scheduling_mode(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<String>,
+    Json(payload): Json<SetSchedulingModeRequest>,
+) -> impl IntoResponse {
+    match &state.pool_manager {
+        Some(pm) => match pm.set_pool_scheduling_mode(&id, payload.mode) {
+            Ok(_) => Json(SuccessResponse::new(format!(
+                "池 {} 调度模式已切换为: {}",
+                id,
+                payload.mode.label()
+            )))
             .into_response(),
+            Err(e) => pool_error_to_response(e, locale),
+        },
+        None => pool_manager_unavailable(locale),
+    }
+}
+
+/// POST /api/admin/pools/:id/rename
+/// 重命名池（同步更新凭据和 API Key 绑定）
+pub async fn rename_pool(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<String>,
+    Json(payload): Json<RenamePoolRequest>,
+) -> impl IntoResponse {
+    match &state.pool_manager {
+        Some(pm) => match pm.rename_pool(&id, &payload.new_id, &state.api_key_manager) {
+            Ok(summary) => Json(SuccessResponse::new(format!(
+                "池 {} 已重命名为 {}（更新 {} 个凭据、{} 个 API Key）",
+                id, payload.new_id, summary.credentials, summary.api_keys
+            )))
+            .into_response(),
+            Err(e) => pool_error_to_response(e, locale),
+        },
+        None => pool_manager_unavailable(locale),
+    }
+}
+
+/// POST /api/admin/pools/rebalance
+/// 按策略在池之间重新分配凭据
+pub async fn rebalance_pools(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Json(payload): Json<RebalancePoolsRequest>,
+) -> impl IntoResponse {
+    match &state.pool_manager {
+        Some(pm) => match pm.rebalance(payload.strategy) {
+            Ok(result) => Json(result).into_response(),
+            Err(e) => pool_error_to_response(e, locale),
+        },
+        None => pool_manager_unavailable(locale),
     }
 }
 
 /// POST /api/admin/credentials/:id/pool
-/// 将凭据分配到池
+/// 将凭据分配到池（只更新源池和目标池，返回凭据的新状态）
 pub async fn assign_credential_to_pool(
     State(state): State<AdminState>,
+    locale: Locale,
     Path(id): Path<u64>,
     Json(payload): Json<AssignCredentialToPoolRequest>,
 ) -> impl IntoResponse {
     match &state.pool_manager {
         Some(pm) => match pm.assign_credential_to_pool(id, &payload.pool_id) {
-            Ok(_) => Json(SuccessResponse::new(format!(
-                "凭据 #{} 已分配到池 {}",
-                id, payload.pool_id
-            )))
+            Ok(assignment) => Json(AssignCredentialToPoolResponse {
+                source_pool_id: assignment.source_pool_id,
+                pool_id: payload.pool_id,
+                credential: CredentialStatusItem::from_snapshot(
+                    assignment.credential,
+                    assignment.is_current,
+                ),
+            })
             .into_response(),
-            Err(e) => pool_error_to_response(e),
+            Err(e) => pool_error_to_response(e, locale),
         },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(AdminErrorResponse::api_error("池管理器未初始化")),
-        )
+        None => pool_manager_unavailable(locale),
+    }
+}
+
+/// POST /api/admin/credentials/:id/scheduling-mode
+/// 设置凭据所在池的调度模式（写入池配置，重启后保持）
+pub async fn set_credential_scheduling_mode(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+    Json(payload): Json<SetSchedulingModeRequest>,
+) -> impl IntoResponse {
+    match &state.pool_manager {
+        Some(pm) => match pm.set_credential_pool_scheduling_mode(id, payload.mode) {
+            Ok(pool_id) => Json(SuccessResponse::new(format!(
+                "凭据 #{} 所在池 {} 调度模式已切换为: {}",
+                id,
+                pool_id,
+                payload.mode.label()
+            )))
             .into_response(),
+            Err(e) => pool_error_to_response(e, locale),
+        },
+        None => pool_manager_unavailable(locale),
+    }
+}
+
+/// POST /api/admin/credentials/:id/transfer-pool
+/// 将凭据转移到另一个池（不重新加载池，可迁移进行中的会话）
+pub async fn transfer_credential_pool(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<u64>,
+    Json(payload): Json<TransferCredentialPoolRequest>,
+) -> impl IntoResponse {
+    match &state.pool_manager {
+        Some(pm) => match pm.transfer_credential(
+            id,
+            &payload.target_pool_id,
+            payload.migrate_active_sessions,
+        ) {
+            Ok(moved_sessions) => {
+                Json(TransferCredentialPoolResponse { moved_sessions }).into_response()
+            }
+            Err(e) => pool_error_to_response(e, locale),
+        },
+        None => pool_manager_unavailable(locale),
     }
 }
 
@@ -287,26 +511,21 @@ pub async fn assign_credential_to_pool(
 /// 获取池的凭证列表
 pub async fn get_pool_credentials(
     State(state): State<AdminState>,
+    locale: Locale,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
     match &state.pool_manager {
         Some(pm) => match pm.get_pool(&id) {
             Some(pool) => {
-                let snapshot = pool.token_manager.snapshot();
+                let snapshot = pool.read().token_manager.snapshot();
                 let current_id = snapshot.current_id;
 
                 let mut credentials: Vec<CredentialStatusItem> = snapshot
                     .entries
                     .into_iter()
-                    .map(|entry| CredentialStatusItem {
-                        id: entry.id,
-                        priority: entry.priority,
-                        disabled: entry.disabled,
-                        failure_count: entry.failure_count,
-                        is_current: entry.id == current_id,
-                        expires_at: entry.expires_at,
-                        auth_method: entry.auth_method,
-                        has_profile_arn: entry.has_profile_arn,
+                    .map(|entry| {
+                        let is_current = entry.id == current_id;
+                        CredentialStatusItem::from_snapshot(entry, is_current)
                     })
                     .collect();
 
@@ -325,14 +544,132 @@ pub async fn get_pool_credentials(
             }
             None => (
                 StatusCode::NOT_FOUND,
-                Json(AdminErrorResponse::not_found(format!("池不存在: {}", id))),
+                Json(AdminErrorResponse::not_found(
+                    ErrorCode::PoolNotFound.arg("pool_id", &id),
+                    locale,
+                )),
             )
                 .into_response(),
         },
-        None => (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(AdminErrorResponse::api_error("池管理器未初始化")),
-        )
+        None => pool_manager_unavailable(locale),
+    }
+}
+
+/// GET /api/admin/pools/:id/api-keys
+/// 获取能访问该池的 API Key（脱敏），标注绑定方式
+pub async fn get_pool_api_keys(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match &state.pool_manager {
+        Some(pm) => match pm.get_pool(&id) {
+            Some(_) => Json(PoolApiKeysResponse {
+                api_keys: pool_api_keys(&state.api_key_manager.list(), &id),
+                pool_id: id,
+            })
             .into_response(),
+            None => (
+                StatusCode::NOT_FOUND,
+                Json(AdminErrorResponse::not_found(
+                    ErrorCode::PoolNotFound.arg("pool_id", &id),
+                    locale,
+                )),
+            )
+                .into_response(),
+        },
+        None => pool_manager_unavailable(locale),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn error_body(e: PoolError, locale: Locale) -> (StatusCode, serde_json::Value) {
+        let response = pool_error_to_response(e, locale);
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_pool_error_localized() {
+        let not_found = || PoolError::PoolNotFound {
+            pool_id: "premium".to_string(),
+        };
+
+        let (status, zh) = error_body(not_found(), Locale::Zh).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(zh["error"]["type"], "not_found");
+        assert_eq!(zh["error"]["code"], "pool_not_found");
+        assert_eq!(zh["error"]["message"], "池不存在: premium");
+
+        let (_, en) = error_body(not_found(), Locale::En).await;
+        assert_eq!(en["error"]["code"], "pool_not_found");
+        assert_eq!(en["error"]["message"], "Pool not found: premium");
+
+        let (status, en) = error_body(PoolError::CannotDeleteDefaultPool, Locale::En).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(en["error"]["code"], "cannot_delete_default_pool");
+        assert_eq!(en["error"]["message"], "The default pool cannot be deleted");
+
+        let not_empty = PoolError::PoolNotEmpty {
+            pool_id: "premium".to_string(),
+            credential_ids: vec![1, 3],
+        };
+        let (status, zh) = error_body(not_empty, Locale::Zh).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(zh["error"]["code"], "pool_not_empty");
+        assert!(zh["error"]["message"].as_str().unwrap().contains("#1, #3"));
+    }
+
+    #[test]
+    fn test_pool_api_keys_binding() {
+        let key = |id: u64, binding: Option<serde_json::Value>| ApiKeyMasked {
+            id,
+            name: format!("key-{}", id),
+            key: "sk-***".to_string(),
+            description: None,
+            created_at: chrono::Utc::now(),
+            enabled: true,
+            pool_id: binding.map(|b| serde_json::from_value(b).unwrap()),
+            websearch_rate_limit_per_hour: None,
+            allowed_models: None,
+            denied_models: None,
+            max_tokens_limit: None,
+            pool_names: Vec::new(),
+        };
+        let keys = vec![
+            key(1, Some(serde_json::json!("__auto__"))),
+            key(2, Some(serde_json::json!("premium"))),
+            key(3, Some(serde_json::json!(["premium", "default"]))),
+            key(4, None),
+            key(5, Some(serde_json::json!("other"))),
+        ];
+
+        let items = pool_api_keys(&keys, "premium");
+        let summary: Vec<_> = items.iter().map(|i| (i.api_key.id, i.binding)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, PoolApiKeyBinding::Direct),
+                (3, PoolApiKeyBinding::Direct),
+                (1, PoolApiKeyBinding::Auto),
+            ]
+        );
+        assert_eq!(bound_api_key_count(&keys, "premium"), 2);
+
+        // 未绑定池的 Key 使用默认池
+        let items = pool_api_keys(&keys, DEFAULT_POOL_ID);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[2].binding, PoolApiKeyBinding::Default);
+        assert_eq!(bound_api_key_count(&keys, DEFAULT_POOL_ID), 2);
+
+        let json = serde_json::to_value(&items[0]).unwrap();
+        assert_eq!(json["id"], 3);
+        assert_eq!(json["binding"], "direct");
     }
 }
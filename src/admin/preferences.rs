@@ -0,0 +1,159 @@
+//! Admin UI 偏好设置存储
+//!
+//! 偏好设置为非敏感数据，持久化到配置目录下的 ui_preferences.json（首次保存时创建）
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 界面主题
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UiTheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// Admin UI 偏好设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UiPreferences {
+    /// 界面主题
+    #[serde(default)]
+    pub theme: UiTheme,
+    /// 默认选中的池
+    #[serde(default)]
+    pub default_pool: Option<String>,
+    /// 自动刷新间隔（毫秒）
+    #[serde(default = "default_refresh_interval_ms")]
+    pub refresh_interval_ms: u64,
+    /// 凭据列表显示的列
+    #[serde(default)]
+    pub columns: Vec<String>,
+}
+
+fn default_refresh_interval_ms() -> u64 {
+    5000
+}
+
+impl Default for UiPreferences {
+    fn default() -> Self {
+        Self {
+            theme: UiTheme::default(),
+            default_pool: None,
+            refresh_interval_ms: default_refresh_interval_ms(),
+            columns: Vec::new(),
+        }
+    }
+}
+
+/// 偏好设置存储
+pub struct UiPreferencesStore {
+    value: RwLock<serde_json::Value>,
+    file_path: PathBuf,
+}
+
+impl UiPreferencesStore {
+    /// 获取配置目录下的默认文件路径
+    pub fn default_path(config_dir: &Path) -> PathBuf {
+        config_dir.join("ui_preferences.json")
+    }
+
+    /// 从文件加载偏好设置
+    ///
+    /// 文件不存在或解析失败时使用默认值（不会创建文件）
+    pub fn load<P: AsRef<Path>>(file_path: P) -> Self {
+        let file_path = file_path.as_ref().to_path_buf();
+        let preferences = match Self::load_from_file(&file_path) {
+            Ok(Some(p)) => p,
+            Ok(None) => UiPreferences::default(),
+            Err(e) => {
+                tracing::warn!("加载 UI 偏好设置失败: {}，使用默认值", e);
+                UiPreferences::default()
+            }
+        };
+
+        Self {
+            value: RwLock::new(
+                serde_json::to_value(preferences).unwrap_or(serde_json::Value::Null),
+            ),
+            file_path,
+        }
+    }
+
+    fn load_from_file(path: &Path) -> anyhow::Result<Option<UiPreferences>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// 获取当前偏好设置
+    pub fn get(&self) -> serde_json::Value {
+        self.value.read().clone()
+    }
+
+    /// 保存偏好设置（写入文件后更新内存）
+    pub fn save(&self, preferences: UiPreferences) -> anyhow::Result<serde_json::Value> {
+        let value = serde_json::to_value(preferences)?;
+        let mut current = self.value.write();
+        fs::write(&self.file_path, serde_json::to_string_pretty(&value)?)?;
+        *current = value.clone();
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_missing_file_uses_defaults() {
+        let dir = tempdir().unwrap();
+        let path = UiPreferencesStore::default_path(dir.path());
+
+        let store = UiPreferencesStore::load(&path);
+        let preferences: UiPreferences = serde_json::from_value(store.get()).unwrap();
+
+        assert_eq!(preferences, UiPreferences::default());
+        assert_eq!(preferences.refresh_interval_ms, 5000);
+        // 仅加载不创建文件
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempdir().unwrap();
+        let path = UiPreferencesStore::default_path(dir.path());
+
+        let store = UiPreferencesStore::load(&path);
+        let preferences: UiPreferences = serde_json::from_str(
+            r#"{"theme": "dark", "default_pool": "premium", "refresh_interval_ms": 10000, "columns": ["id", "priority"]}"#,
+        )
+        .unwrap();
+        let saved = store.save(preferences.clone()).unwrap();
+
+        assert!(path.exists());
+        assert_eq!(saved["theme"], "dark");
+        assert_eq!(store.get(), saved);
+
+        // 重新加载后保持一致
+        let reloaded = UiPreferencesStore::load(&path);
+        let loaded: UiPreferences = serde_json::from_value(reloaded.get()).unwrap();
+        assert_eq!(loaded, preferences);
+    }
+
+    #[test]
+    fn test_invalid_theme_rejected() {
+        let result = serde_json::from_str::<UiPreferences>(r#"{"theme": "blue"}"#);
+        assert!(result.is_err());
+    }
+}
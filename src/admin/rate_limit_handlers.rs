@@ -0,0 +1,142 @@
+//! 限流豁免与限流统计 HTTP 处理器
+//!
+//! 查询和运行时增删限流豁免规则，修改写入配置文件并立即对 Anthropic API 生效；
+//! 查询和重置滑动窗口限流器的计数
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+use crate::common::i18n::{ErrorCode, Locale};
+use crate::model::config::RateLimitExemption;
+
+use super::{
+    middleware::AdminState,
+    types::{
+        AdminErrorResponse, RateLimitExemptionsResponse, RateLimitStatsQuery, SuccessResponse,
+    },
+};
+
+/// 限流统计默认返回的 API Key 数量
+const DEFAULT_RATE_LIMIT_STATS_TOP: usize = 10;
+
+/// 持久化新的豁免规则列表并同步到限流中间件
+fn save_exemptions(
+    state: &AdminState,
+    exemptions: Vec<RateLimitExemption>,
+    locale: Locale,
+) -> Response {
+    let saved = exemptions.clone();
+    match state.update_config(|config| config.rate_limit_exemptions = saved) {
+        Ok(_) => {
+            state.rate_limit_exemptions.replace(exemptions.clone());
+            Json(RateLimitExemptionsResponse { exemptions }).into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(AdminErrorResponse::internal_error(
+                ErrorCode::ConfigSaveFailed.arg("detail", e),
+                locale,
+            )),
+        )
+            .into_response(),
+    }
+}
+
+/// GET /api/admin/rate-limit/exemptions
+/// 获取当前限流豁免规则
+pub async fn get_rate_limit_exemptions(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(RateLimitExemptionsResponse {
+        exemptions: state.rate_limit_exemptions.list(),
+    })
+}
+
+/// POST /api/admin/rate-limit/exemptions
+/// 追加一条限流豁免规则
+pub async fn add_rate_limit_exemption(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Json(payload): Json<RateLimitExemption>,
+) -> Response {
+    if let Err(e) = payload.validate() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(AdminErrorResponse::invalid_request(
+                ErrorCode::InvalidRateLimitExemption.arg("detail", e),
+                locale,
+            )),
+        )
+            .into_response();
+    }
+
+    let mut exemptions = state.rate_limit_exemptions.list();
+    exemptions.push(payload);
+    save_exemptions(&state, exemptions, locale)
+}
+
+/// DELETE /api/admin/rate-limit/exemptions/:index
+/// 按下标删除限流豁免规则
+pub async fn delete_rate_limit_exemption(
+    State(state): State<AdminState>,
+    Path(index): Path<usize>,
+    locale: Locale,
+) -> Response {
+    let mut exemptions = state.rate_limit_exemptions.list();
+    if index >= exemptions.len() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(AdminErrorResponse::not_found(
+                ErrorCode::RateLimitExemptionNotFound.arg("index", index),
+                locale,
+            )),
+        )
+            .into_response();
+    }
+
+    let removed = exemptions.remove(index);
+    tracing::info!("删除限流豁免规则 #{}: {}", index, removed.reason);
+    save_exemptions(&state, exemptions, locale)
+}
+
+/// 未启用滑动窗口限流
+fn rate_limit_stats_unavailable(locale: Locale) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(AdminErrorResponse::not_found(
+            ErrorCode::RateLimitStatsUnavailable,
+            locale,
+        )),
+    )
+        .into_response()
+}
+
+/// GET /api/admin/rate-limit/stats
+/// 获取全局及每 API Key 的限流计数（按当前分钟请求数降序，`?top=` 限制 Key 数量）
+pub async fn get_rate_limit_stats(
+    State(state): State<AdminState>,
+    Query(query): Query<RateLimitStatsQuery>,
+    locale: Locale,
+) -> Response {
+    let Some(limiter) = &state.rate_limiter else {
+        return rate_limit_stats_unavailable(locale);
+    };
+    let mut stats = limiter.stats();
+    stats
+        .per_key
+        .truncate(query.top.unwrap_or(DEFAULT_RATE_LIMIT_STATS_TOP));
+    Json(stats).into_response()
+}
+
+/// POST /api/admin/rate-limit/stats/reset
+/// 清空限流计数（限流窗口随之重置）
+pub async fn reset_rate_limit_stats(State(state): State<AdminState>, locale: Locale) -> Response {
+    let Some(limiter) = &state.rate_limiter else {
+        return rate_limit_stats_unavailable(locale);
+    };
+    limiter.reset_stats();
+    tracing::info!("已重置限流计数");
+    Json(SuccessResponse::new("限流计数已重置")).into_response()
+}
@@ -2,21 +2,38 @@
 
 use axum::{
     Router, middleware,
-    routing::{delete, get, post, put},
+    routing::{delete, get, patch, post, put},
 };
 
 use super::{
-    api_key_handlers::{create_api_key, delete_api_key, get_api_keys, update_api_key},
-    config_handlers::{get_config, update_config},
+    api_key_handlers::{
+        bulk_import_api_keys, create_api_key, delete_api_key, get_api_keys, update_api_key,
+    },
+    auth_handlers::{create_magic_link, logout},
+    backup_handlers::{get_backups, restore_backup},
+    config_handlers::{get_config, get_effective_config, get_tls_info, update_config},
+    feature_handlers::{get_features, set_feature},
     handlers::{
-        add_credential, delete_credential, get_all_credentials, get_credential_balance,
-        get_csrf_token, import_credentials, reset_failure_count, set_credential_disabled,
-        set_credential_priority, set_scheduling_mode,
+        add_credential, clone_credential, delete_credential, get_all_credentials,
+        get_credential_balance, get_credential_history, get_credential_latency_histogram,
+        get_credential_stats, get_credential_timeline, get_csrf_token,
+        get_dashboard, get_stats, get_stats_timeline, get_stickiness, get_user_sessions,
+        get_warmup_report, import_credentials, import_kiro_ide_credentials,
+        refresh_credential_token, reset_failure_count, rollback_credential,
+        set_credential_disabled, set_credential_headers, set_credential_notes,
+        set_credential_priorities, set_credential_priority, set_scheduling_mode,
+        simulate_scheduling, test_credential, update_credential_tags, validate_credential,
     },
     middleware::{AdminState, admin_auth_middleware, csrf_middleware},
     pool_handlers::{
         assign_credential_to_pool, create_pool, delete_pool, get_all_pools, get_pool,
-        get_pool_credentials, set_pool_disabled, update_pool,
+        get_pool_api_keys, get_pool_credentials, get_routing_stats, rebalance_pools, rename_pool,
+        set_credential_scheduling_mode, set_pool_disabled, set_pool_scheduling_mode,
+        transfer_credential_pool, update_pool,
+    },
+    rate_limit_handlers::{
+        add_rate_limit_exemption, delete_rate_limit_exemption, get_rate_limit_exemptions,
+        get_rate_limit_stats, reset_rate_limit_stats,
     },
 };
 
@@ -25,38 +42,93 @@ use super::{
 /// # 端点
 ///
 /// ## CSRF 保护
-/// - `GET /csrf-token` - 获取 CSRF Token（POST/PUT/DELETE 请求需要携带）
+/// - `GET /csrf-token` - 获取 CSRF Token（POST/PUT/PATCH/DELETE 请求需要携带）
+///
+/// ## 登录
+/// - `POST /auth/magic-link` - 生成 Admin UI 一次性登录链接（15 分钟有效，自行校验 Admin Key，
+///   校验失败过多时限流）
+/// - `POST /auth/logout` - 注销当前登录会话并清除 Cookie（`?all=true` 注销所有会话）
 ///
 /// ## 凭据管理
-/// - `GET /credentials` - 获取所有凭据状态
+/// - `GET /credentials` - 获取凭据状态（支持 tag/disabled/auth_method/pool_id/q 筛选、
+///   updated_since 增量查询、offset/limit 分页，携带 ETag）
 /// - `POST /credentials` - 添加新凭据
-/// - `POST /credentials/import` - 批量导入凭据（IdC 格式）
+/// - `POST /credentials/import` - 批量导入凭据（IdC 格式 JSON 或 CSV）
+/// - `POST /credentials/import-kiro-ide` - 批量导入 Kiro IDE 导出格式的凭据
+/// - `PUT /credentials/priorities` - 按拖拽排序结果批量设置优先级（同一批次优先级不可重复）
 /// - `DELETE /credentials/:id` - 删除凭据
+/// - `POST /credentials/:id/clone` - 克隆凭据（新 ID，可覆盖优先级、池和区域，立即刷新验证）
 /// - `POST /credentials/:id/disabled` - 设置凭据禁用状态
 /// - `POST /credentials/:id/priority` - 设置凭据优先级
+/// - `PATCH /credentials/:id/notes` - 修改凭据备注
+/// - `POST /credentials/:id/tags` - 添加/移除凭据标签
+/// - `PATCH /credentials/:id/headers` - 替换凭据自定义上游请求头
 /// - `POST /credentials/:id/reset` - 重置失败计数
+/// - `GET /credentials/:id/history` - 获取最近 5 个历史版本（禁用、优先级、Token 变更前记录）
+/// - `GET /credentials/:id/latency-histogram` - 获取最近 100 次成功调用的响应时间直方图（50ms 分桶）
+/// - `GET /credentials/:id/timeline` - 获取按时间桶聚合的调用时间线（`?window_minutes=60&granularity_minutes=5`）
+/// - `POST /credentials/:id/rollback?version=N` - 恢复到指定历史版本
+/// - `POST /credentials/:id/refresh` - 立即刷新凭据 Token（每个凭据 30 秒内最多一次）
+/// - `POST /credentials/:id/test` - 测试凭据连通性（每个凭据 60 秒内最多一次，不影响失败计数）
 /// - `GET /credentials/:id/balance` - 获取凭据余额
+/// - `GET /credentials/:id/validate` - 检查凭据配置警告
 /// - `POST /credentials/:id/pool` - 将凭据分配到池
+/// - `POST /credentials/:id/transfer-pool` - 将凭据转移到另一个池（可迁移会话，不重新加载池）
 ///
 /// ## 调度模式
-/// - `POST /scheduling-mode` - 设置调度模式（round_robin / priority_fill）
+/// - `POST /scheduling-mode` - 设置默认 Token 管理器的调度模式（round_robin / priority_fill，不持久化）
+/// - `POST /pools/:id/scheduling-mode` - 设置池的调度模式（写入 pools.json）
+/// - `POST /credentials/:id/scheduling-mode` - 设置凭据所在池的调度模式（写入 pools.json）
 ///
 /// ## 池管理
 /// - `GET /pools` - 获取所有池
 /// - `POST /pools` - 创建新池
+/// - `GET /pools/routing-stats` - 获取自动路由统计
+/// - `POST /pools/rebalance` - 按策略在池之间重新分配凭据（even / by_priority / by_health）
 /// - `GET /pools/:id` - 获取池详情
 /// - `PUT /pools/:id` - 更新池配置
-/// - `DELETE /pools/:id` - 删除池
+/// - `DELETE /pools/:id` - 删除池（`?reassign_to=` / `?force=true` 重新分配池内凭据）
 /// - `POST /pools/:id/disabled` - 设置池禁用状态
 /// - `GET /pools/:id/credentials` - 获取池的凭证列表
+/// - `GET /pools/:id/api-keys` - 获取能访问该池的 API Key（标注直接绑定 / 自动路由 / 默认池）
+///
+/// ## 运行统计
+/// - `GET /dashboard` - 获取仪表盘汇总（池概要、最近 1 小时请求量、活跃 API Key、近 24 小时禁用、
+///   版本与运行时长；只读取内存统计，不调用上游）
+/// - `GET /stats` - 获取运行统计（WebSearch 请求数等）
+/// - `GET /stats/credentials` - 汇总所有池的凭据统计
+/// - `GET /stats/timeline` - 所有池的每分钟调用统计（`?window_secs=`，默认且最长 3600）
+/// - `GET /warmup-report` - 获取启动时的凭据预热报告
+/// - `GET /user-sessions` - 获取各用户的活跃会话数（按用户公平调度调试）
+/// - `GET /stickiness` - 获取粘性会话诊断统计（会话标识来源、缓存命中率，按池）
+/// - `POST /simulate` - 用合成负载运行调度模拟（容量评估）
 ///
 /// ## 配置管理
 /// - `GET /config` - 获取当前配置
 /// - `PUT /config` - 更新配置
+/// - `GET /config/effective` - 获取所有配置项的生效值及来源（default / file / admin_override）
+///   和运行时推导值（文件路径、TLS 后端、池管理器是否启用）
+/// - `GET /tls-info` - 获取当前生效的 TLS 配置（协议版本、密码套件）
+///
+/// ## 配置备份
+/// - `GET /backups` - 列出自动备份的快照（需配置 backupDir）
+/// - `POST /backups/:timestamp/restore` - 校验并恢复快照，重新加载配置/凭据/池/API Key
+///
+/// ## 功能开关
+/// - `GET /features` - 获取所有功能开关的当前状态
+/// - `PUT /features/:name` - 运行时切换功能开关（写入 features.json）
+///
+/// ## 限流豁免
+/// - `GET /rate-limit/exemptions` - 获取限流豁免规则
+/// - `POST /rate-limit/exemptions` - 追加限流豁免规则（按 API Key 前缀或客户端 IP 段）
+/// - `DELETE /rate-limit/exemptions/:index` - 按下标删除限流豁免规则
+/// - `GET /rate-limit/stats` - 获取全局及每 API Key 的限流计数（`?top=`，默认 10）
+/// - `POST /rate-limit/stats/reset` - 清空限流计数
 ///
 /// ## API Key 管理
 /// - `GET /api-keys` - 获取所有 API Keys
 /// - `POST /api-keys` - 创建新 API Key
+/// - `POST /api-keys/bulk-import` - 批量导入 API Key（最多 100 个，返回完整 Key）
 /// - `PUT /api-keys/:id` - 更新 API Key
 /// - `DELETE /api-keys/:id` - 删除 API Key
 ///
@@ -64,38 +136,105 @@ use super::{
 /// 需要 Admin API Key 认证，支持：
 /// - `x-api-key` header
 /// - `Authorization: Bearer <token>` header
+/// - 一次性登录链接换发的会话 Cookie（`kiro_admin_session`）
 ///
 /// # CSRF 保护
-/// POST/PUT/DELETE 请求需要携带 `x-csrf-token` 头
+/// POST/PUT/PATCH/DELETE 请求需要携带 `x-csrf-token` 头
 pub fn create_admin_router(state: AdminState) -> Router {
-    // 需要 CSRF 保护的路由（POST/PUT/DELETE 操作）
+    // 需要 CSRF 保护的路由（POST/PUT/PATCH/DELETE 操作）
     let protected_routes = Router::new()
+        // 登录会话
+        .route("/auth/logout", post(logout))
         // 凭据管理
         .route(
             "/credentials",
             get(get_all_credentials).post(add_credential),
         )
         .route("/credentials/import", post(import_credentials))
+        .route(
+            "/credentials/import-kiro-ide",
+            post(import_kiro_ide_credentials),
+        )
+        .route("/credentials/priorities", put(set_credential_priorities))
         .route("/credentials/{id}", delete(delete_credential))
+        .route("/credentials/{id}/clone", post(clone_credential))
         .route("/credentials/{id}/disabled", post(set_credential_disabled))
         .route("/credentials/{id}/priority", post(set_credential_priority))
+        .route("/credentials/{id}/notes", patch(set_credential_notes))
+        .route("/credentials/{id}/tags", post(update_credential_tags))
+        .route("/credentials/{id}/headers", patch(set_credential_headers))
         .route("/credentials/{id}/reset", post(reset_failure_count))
+        .route("/credentials/{id}/history", get(get_credential_history))
+        .route(
+            "/credentials/{id}/latency-histogram",
+            get(get_credential_latency_histogram),
+        )
+        .route("/credentials/{id}/timeline", get(get_credential_timeline))
+        .route("/credentials/{id}/rollback", post(rollback_credential))
+        .route("/credentials/{id}/refresh", post(refresh_credential_token))
+        .route("/credentials/{id}/test", post(test_credential))
         .route("/credentials/{id}/balance", get(get_credential_balance))
+        .route("/credentials/{id}/validate", get(validate_credential))
         .route("/credentials/{id}/pool", post(assign_credential_to_pool))
+        .route(
+            "/credentials/{id}/transfer-pool",
+            post(transfer_credential_pool),
+        )
         // 调度模式
         .route("/scheduling-mode", post(set_scheduling_mode))
+        .route(
+            "/pools/{id}/scheduling-mode",
+            post(set_pool_scheduling_mode),
+        )
+        .route(
+            "/credentials/{id}/scheduling-mode",
+            post(set_credential_scheduling_mode),
+        )
         // 池管理
         .route("/pools", get(get_all_pools).post(create_pool))
+        .route("/pools/routing-stats", get(get_routing_stats))
+        .route("/pools/rebalance", post(rebalance_pools))
         .route(
             "/pools/{id}",
             get(get_pool).put(update_pool).delete(delete_pool),
         )
         .route("/pools/{id}/disabled", post(set_pool_disabled))
+        .route("/pools/{id}/rename", post(rename_pool))
         .route("/pools/{id}/credentials", get(get_pool_credentials))
+        .route("/pools/{id}/api-keys", get(get_pool_api_keys))
+        // 运行统计
+        .route("/dashboard", get(get_dashboard))
+        .route("/stats", get(get_stats))
+        .route("/stats/credentials", get(get_credential_stats))
+        .route("/stats/timeline", get(get_stats_timeline))
+        .route("/warmup-report", get(get_warmup_report))
+        .route("/user-sessions", get(get_user_sessions))
+        .route("/stickiness", get(get_stickiness))
+        .route("/simulate", post(simulate_scheduling))
         // 配置管理
         .route("/config", get(get_config).put(update_config))
+        .route("/config/effective", get(get_effective_config))
+        .route("/tls-info", get(get_tls_info))
+        // 配置备份
+        .route("/backups", get(get_backups))
+        .route("/backups/{timestamp}/restore", post(restore_backup))
+        // 功能开关
+        .route("/features", get(get_features))
+        .route("/features/{name}", put(set_feature))
+        // 限流豁免
+        .route(
+            "/rate-limit/exemptions",
+            get(get_rate_limit_exemptions).post(add_rate_limit_exemption),
+        )
+        .route(
+            "/rate-limit/exemptions/{index}",
+            delete(delete_rate_limit_exemption),
+        )
+        .route("/rate-limit/stats", get(get_rate_limit_stats))
+        .route("/rate-limit/stats/reset", post(reset_rate_limit_stats))
         // API Key 管理
         .route("/api-keys", get(get_api_keys).post(create_api_key))
+        .route("/api-keys/bulk-import", post(bulk_import_api_keys))
         .route(
             "/api-keys/{id}",
             put(update_api_key).delete(delete_api_key),
@@ -111,6 +250,9 @@ pub fn create_admin_router(state: AdminState) -> Router {
         // CSRF Token 端点（用于获取 Token）
         .route("/csrf-token", get(get_csrf_token));
 
+    // 登录链接端点在处理器内校验 Admin Key（支持请求体传入），不经过认证中间件
+    let login_routes = Router::new().route("/auth/magic-link", post(create_magic_link));
+
     // 合并路由并应用认证中间件
     Router::new()
         .merge(protected_routes)
@@ -119,5 +261,6 @@ pub fn create_admin_router(state: AdminState) -> Router {
             state.clone(),
             admin_auth_middleware,
         ))
+        .merge(login_routes)
         .with_state(state)
 }
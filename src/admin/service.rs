@@ -1,15 +1,39 @@
 //! Admin API 业务逻辑服务
 
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::kiro::model::credentials::KiroCredentials;
-use crate::kiro::token_manager::MultiTokenManager;
+use chrono::Utc;
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+
+use crate::kiro::error::KiroError;
+use crate::kiro::model::credentials::{KiroCredentials, normalize_tags};
+use crate::kiro::model::credentials_csv::{SkippedRow, parse_credentials_csv};
+use crate::kiro::performance::PERFORMANCE_HISTORY_MINUTES;
+use crate::kiro::pool::{DEFAULT_POOL_ID, Pool};
 use crate::kiro::pool_manager::PoolManager;
+use crate::kiro::stickiness::StickinessSnapshot;
+use crate::kiro::timeline::TIMELINE_MAX_WINDOW_MINUTES;
+use crate::kiro::token_manager::{
+    CredentialCloneOverrides, CredentialDefaults, LATENCY_BUCKET_MS, ManagerSnapshot,
+    MultiTokenManager, validate_refresh_token,
+};
+use crate::kiro::upstream_error::UpstreamErrorKind;
 
+use super::api_keys::ApiKeyManager;
 use super::error::AdminServiceError;
 use super::types::{
-    AddCredentialRequest, AddCredentialResponse, BalanceResponse, CredentialStatusItem,
-    CredentialsStatusResponse, IdcCredentialItem, ImportCredentialsResponse,
+    AddCredentialRequest, AddCredentialResponse, AggregatedStats, BalanceResponse,
+    CloneCredentialRequest, CloneCredentialResponse, CredentialHistoryResponse,
+    CredentialStatusItem, CredentialTestResponse, CredentialTimelineResponse,
+    CredentialValidationResponse, CredentialVersionItem, CredentialsQuery,
+    CredentialsStatusResponse, DashboardDisabledStats, DashboardPoolItem, DashboardRequestStats,
+    DashboardResponse, DisableTransitionItem, IdcCredentialItem, ImportCredentialsResponse,
+    ImportResult, KiroIdeCredentialFormat, LatencyHistogramResponse, PoolStickinessItem,
+    RefreshTokenResponse, StickinessResponse, TimelineResponse, UserSessionsResponse,
+    ValidationWarningItem, WarmupEntryItem, WarmupReportResponse,
 };
 use crate::kiro::token_manager::SchedulingMode;
 
@@ -19,6 +43,51 @@ use crate::kiro::token_manager::SchedulingMode;
 pub struct AdminService {
     token_manager: Arc<MultiTokenManager>,
     pool_manager: Option<Arc<PoolManager>>,
+    /// 各凭据最近一次强制刷新的时间
+    last_force_refresh: DashMap<u64, Instant>,
+    /// 各凭据最近一次连通性测试的时间
+    last_credential_test: DashMap<u64, Instant>,
+    /// 服务启动时间（用于仪表盘运行时长）
+    started_at: Instant,
+}
+
+/// 仪表盘展示的 API Key 数量
+const DASHBOARD_TOP_API_KEYS: usize = 5;
+
+/// 仪表盘请求统计窗口（秒）
+const DASHBOARD_REQUEST_WINDOW_SECS: u64 = 3600;
+
+/// 仪表盘近期禁用统计窗口（秒）
+const DASHBOARD_DISABLED_WINDOW_SECS: u64 = 24 * 3600;
+
+/// 同一凭据两次强制刷新的最小间隔
+const FORCE_REFRESH_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// 同一凭据两次连通性测试的最小间隔
+const CREDENTIAL_TEST_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// 检查并记录凭据操作时间，间隔不足 `cooldown` 时返回 RateLimited
+fn check_cooldown(
+    last_calls: &DashMap<u64, Instant>,
+    id: u64,
+    cooldown: Duration,
+) -> Result<(), AdminServiceError> {
+    match last_calls.entry(id) {
+        Entry::Occupied(mut last) => {
+            let elapsed = last.get().elapsed();
+            if elapsed < cooldown {
+                return Err(AdminServiceError::RateLimited {
+                    id,
+                    retry_after_secs: (cooldown - elapsed).as_secs_f64().ceil() as u64,
+                });
+            }
+            last.insert(Instant::now());
+        }
+        Entry::Vacant(last) => {
+            last.insert(Instant::now());
+        }
+    }
+    Ok(())
 }
 
 impl AdminService {
@@ -26,6 +95,9 @@ impl AdminService {
         Self {
             token_manager,
             pool_manager: None,
+            last_force_refresh: DashMap::new(),
+            last_credential_test: DashMap::new(),
+            started_at: Instant::now(),
         }
     }
 
@@ -35,59 +107,301 @@ impl AdminService {
         self
     }
 
-    /// 获取所有凭据状态
-    pub fn get_all_credentials(&self) -> CredentialsStatusResponse {
+    /// 替换默认 Token 管理器的全部凭据（从备份恢复后调用）
+    pub fn replace_credentials(&self, credentials: Vec<KiroCredentials>) -> anyhow::Result<usize> {
+        self.token_manager.replace_credentials(credentials)
+    }
+
+    /// 汇总所有池的凭据统计
+    pub fn aggregate_stats(&self) -> AggregatedStats {
+        let snapshots: Vec<(String, ManagerSnapshot)> = match &self.pool_manager {
+            Some(pool_manager) => pool_manager
+                .pool_ids()
+                .into_iter()
+                .filter_map(|id| {
+                    let snapshot = pool_manager.with_pool(&id, |p| p.token_manager.snapshot())?;
+                    Some((id, snapshot))
+                })
+                .collect(),
+            None => vec![(DEFAULT_POOL_ID.to_string(), self.token_manager.snapshot())],
+        };
+
+        let mut stats = AggregatedStats::default();
+        let mut total_health = 0.0;
+        for (pool_id, snapshot) in snapshots {
+            stats.total_credentials += snapshot.total;
+            stats.total_available += snapshot.available;
+            stats
+                .credentials_by_pool
+                .insert(pool_id, snapshot.entries.len());
+            for entry in &snapshot.entries {
+                stats.total_success_calls += entry.success_count;
+                stats.total_failure_calls += entry.total_failure_count;
+                stats.total_token_refreshes +=
+                    entry.token_refresh_count + entry.token_refresh_failure_count;
+                total_health += entry.health_score();
+                // 未配置认证方式时按 social 处理
+                let auth_method = entry.auth_method.as_deref().unwrap_or("social");
+                *stats
+                    .credentials_by_auth_method
+                    .entry(auth_method.to_string())
+                    .or_default() += 1;
+            }
+        }
+        stats.total_disabled = stats.total_credentials - stats.total_available;
+        if stats.total_credentials > 0 {
+            stats.avg_health_score = total_health / stats.total_credentials as f64;
+        }
+        stats
+    }
+
+    /// 所有池最近 `window_secs` 秒（默认且最长 60 分钟）的每分钟调用统计
+    pub fn get_timeline(&self, window_secs: Option<u64>) -> TimelineResponse {
+        let max_window = PERFORMANCE_HISTORY_MINUTES as u64 * 60;
+        let window_secs = window_secs.unwrap_or(max_window).min(max_window);
+        let buckets = self
+            .pool_manager
+            .as_ref()
+            .map(|pm| pm.performance_timeline(window_secs))
+            .unwrap_or_default();
+        TimelineResponse {
+            window_secs,
+            buckets,
+        }
+    }
+
+    /// 组装 Admin 仪表盘（只读取内存中的快照和统计，不调用上游）
+    pub fn get_dashboard(&self, api_keys: &ApiKeyManager) -> DashboardResponse {
+        let disabled_since =
+            Utc::now() - chrono::Duration::seconds(DASHBOARD_DISABLED_WINDOW_SECS as i64);
+        let mut by_reason = std::collections::BTreeMap::new();
+        let mut disabled_count = 0;
+        let mut summarize = |manager: &MultiTokenManager| {
+            for (_, reason) in manager.disabled_since(disabled_since) {
+                disabled_count += 1;
+                *by_reason.entry(reason).or_insert(0) += 1;
+            }
+            manager.cached_remaining_quota_percentage()
+        };
+
+        let mut pools: Vec<DashboardPoolItem> = match &self.pool_manager {
+            Some(pool_manager) => pool_manager
+                .snapshot()
+                .into_iter()
+                .map(|pool| DashboardPoolItem {
+                    quota_remaining_percentage: pool_manager
+                        .with_pool(&pool.id, |runtime| summarize(&runtime.token_manager))
+                        .flatten(),
+                    id: pool.id,
+                    name: pool.name,
+                    enabled: pool.enabled,
+                    scheduling_mode: pool.scheduling_mode,
+                    available_credentials: pool.available_credentials,
+                    total_credentials: pool.total_credentials,
+                })
+                .collect(),
+            None => {
+                let snapshot = self.token_manager.snapshot();
+                let default_pool = Pool::default_pool();
+                vec![DashboardPoolItem {
+                    quota_remaining_percentage: summarize(&self.token_manager),
+                    id: default_pool.id,
+                    name: default_pool.name,
+                    enabled: default_pool.enabled,
+                    scheduling_mode: snapshot.scheduling_mode,
+                    available_credentials: snapshot.available,
+                    total_credentials: snapshot.total,
+                }]
+            }
+        };
+        pools.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let (requests, errors) = self
+            .pool_manager
+            .as_ref()
+            .map(|pm| pm.performance_timeline(DASHBOARD_REQUEST_WINDOW_SECS))
+            .unwrap_or_default()
+            .iter()
+            .fold((0, 0), |(requests, errors), bucket| {
+                (
+                    requests + bucket.request_count,
+                    errors + bucket.failure_count,
+                )
+            });
+
+        DashboardResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            generated_at: Utc::now().to_rfc3339(),
+            pools,
+            requests: DashboardRequestStats {
+                window_secs: DASHBOARD_REQUEST_WINDOW_SECS,
+                requests,
+                errors,
+            },
+            top_api_keys: api_keys.top_recent_usage(DASHBOARD_TOP_API_KEYS),
+            recently_disabled: DashboardDisabledStats {
+                window_secs: DASHBOARD_DISABLED_WINDOW_SECS,
+                count: disabled_count,
+                by_reason,
+            },
+        }
+    }
+
+    /// 获取按用户公平调度的活跃会话统计
+    pub fn get_user_sessions(&self) -> UserSessionsResponse {
+        let config = self.token_manager.config();
+        UserSessionsResponse {
+            enabled: config.user_fairness_enabled,
+            max_share: config.user_max_share,
+            users: self.token_manager.user_session_counts(),
+        }
+    }
+
+    /// 获取粘性会话诊断统计（启用池时按池统计，否则只有默认池）
+    pub fn get_stickiness(&self) -> StickinessResponse {
+        let mut pools: Vec<PoolStickinessItem> = match &self.pool_manager {
+            Some(pool_manager) => pool_manager
+                .pool_ids()
+                .into_iter()
+                .filter_map(|id| {
+                    let stats =
+                        pool_manager.with_pool(&id, |p| p.token_manager.stickiness_snapshot())?;
+                    Some(PoolStickinessItem { pool_id: id, stats })
+                })
+                .collect(),
+            None => vec![PoolStickinessItem {
+                pool_id: DEFAULT_POOL_ID.to_string(),
+                stats: self.token_manager.stickiness_snapshot(),
+            }],
+        };
+        pools.sort_by(|a, b| a.pool_id.cmp(&b.pool_id));
+
+        StickinessResponse {
+            system_hash_warn_percent: self
+                .token_manager
+                .config()
+                .stickiness_system_hash_warn_percent,
+            total: StickinessSnapshot::merge(pools.iter().map(|p| &p.stats)),
+            pools,
+        }
+    }
+
+    /// 获取启动预热报告（尚未预热时返回 None）
+    pub fn get_warmup_report(&self) -> Option<WarmupReportResponse> {
+        // 启用池管理时启动预热由各池执行，报告取自默认池
+        let default_pool_manager = self
+            .pool_manager
+            .as_ref()
+            .and_then(|pm| pm.get_default_pool())
+            .map(|pool| pool.read().token_manager.clone());
+        let token_manager = default_pool_manager.as_ref().unwrap_or(&self.token_manager);
+        let report = token_manager.warmup_report()?.clone();
+        Some(WarmupReportResponse {
+            total: report.total(),
+            ready: report.ready(),
+            failed: report.failed(),
+            refreshed: report.refreshed(),
+            skipped: report.skipped(),
+            elapsed_ms: report.elapsed_ms,
+            entries: report
+                .entries
+                .iter()
+                .map(|e| WarmupEntryItem {
+                    id: e.id,
+                    auth_method: e.auth_method.clone(),
+                    priority: e.priority,
+                    duration_ms: e.duration_ms,
+                    refreshed: e.refreshed,
+                    success: e.result.is_ok(),
+                    error: e.result.as_ref().err().cloned(),
+                })
+                .collect(),
+        })
+    }
+
+    /// 获取凭据状态（按查询参数在服务端筛选）
+    ///
+    /// 指定 `pool_id` 时列出该池的凭据，否则列出默认池的凭据；
+    /// 筛选只影响 `credentials` 列表，统计字段仍为整个池的数据
+    pub fn get_all_credentials(&self, query: &CredentialsQuery) -> CredentialsStatusResponse {
+        let pool_snapshot = query.pool_id.as_deref().and_then(|pool_id| {
+            self.pool_manager.as_ref()?.with_pool(pool_id, |pool| {
+                pool.token_manager.snapshot_since(query.updated_since)
+            })
+        });
+
         // 如果有池管理器，从默认池获取凭证
-        let snapshot = if let Some(ref pool_manager) = self.pool_manager {
+        let snapshot = if let Some(snapshot) = pool_snapshot {
+            snapshot
+        } else if let Some(ref pool_manager) = self.pool_manager {
             if let Some(default_pool) = pool_manager.get_default_pool() {
-                default_pool.token_manager.snapshot()
+                default_pool
+                    .read()
+                    .token_manager
+                    .snapshot_since(query.updated_since)
             } else {
                 // 默认池不存在，返回空快照
                 tracing::warn!("默认池不存在，返回空凭证列表");
-                self.token_manager.snapshot()
+                self.token_manager.snapshot_since(query.updated_since)
             }
         } else {
             // 没有池管理器，使用原来的 token_manager（兼容旧版本）
-            self.token_manager.snapshot()
+            self.token_manager.snapshot_since(query.updated_since)
         };
 
         let mut credentials: Vec<CredentialStatusItem> = snapshot
             .entries
             .into_iter()
-            .map(|entry| CredentialStatusItem {
-                id: entry.id,
-                priority: entry.priority,
-                disabled: entry.disabled,
-                failure_count: entry.failure_count,
-                is_current: entry.id == snapshot.current_id,
-                expires_at: entry.expires_at,
-                auth_method: entry.auth_method,
-                has_profile_arn: entry.has_profile_arn,
+            .filter(|entry| {
+                query.pool_id.as_deref().is_none_or(|pool_id| {
+                    entry.pool_id.as_deref().unwrap_or(DEFAULT_POOL_ID) == pool_id
+                })
+            })
+            .map(|entry| {
+                let is_current = entry.id == snapshot.current_id;
+                CredentialStatusItem::from_snapshot(entry, is_current)
             })
+            .filter(|item| query.matches(item))
             .collect();
 
-        // 按优先级排序（数字越小优先级越高）
-        credentials.sort_by_key(|c| c.priority);
+        // 按优先级排序（数字越小优先级越高，相同时按 ID），再分页
+        credentials.sort_by_key(|c| (c.priority, c.id));
+        let matched = credentials.len();
+        let offset = query.offset.unwrap_or(0).min(matched);
+        let end = query
+            .limit
+            .map_or(matched, |limit| offset.saturating_add(limit).min(matched));
+        credentials.truncate(end);
+        credentials.drain(..offset);
 
         CredentialsStatusResponse {
             total: snapshot.total,
             available: snapshot.available,
+            matched,
             current_id: snapshot.current_id,
             credentials,
             session_cache_size: snapshot.session_cache_size,
+            session_cache_capacity: snapshot.session_cache_capacity,
+            session_cache_ttl_secs: snapshot.session_cache_ttl_secs,
             round_robin_counter: snapshot.round_robin_counter,
             scheduling_mode: snapshot.scheduling_mode,
         }
     }
 
-    /// 设置凭据禁用状态
-    pub fn set_disabled(&self, id: u64, disabled: bool) -> Result<(), AdminServiceError> {
+    /// 设置凭据禁用状态（`actor` 为操作者身份）
+    pub fn set_disabled(
+        &self,
+        id: u64,
+        disabled: bool,
+        actor: &str,
+    ) -> Result<(), AdminServiceError> {
         // 先获取当前凭据 ID，用于判断是否需要切换
         let snapshot = self.token_manager.snapshot();
         let current_id = snapshot.current_id;
 
         self.token_manager
-            .set_disabled(id, disabled)
+            .set_disabled(id, disabled, actor)
             .map_err(|e| self.classify_error(e, id))?;
 
         // 只有禁用的是当前凭据时才尝试切换到下一个
@@ -104,17 +418,259 @@ impl AdminService {
             .map_err(|e| self.classify_error(e, id))
     }
 
-    /// 重置失败计数并重新启用
-    pub fn reset_and_enable(&self, id: u64) -> Result<(), AdminServiceError> {
+    /// 批量设置凭据优先级，返回不存在的凭据 ID
+    pub fn set_priorities(&self, priorities: &[(u64, u32)]) -> Result<Vec<u64>, AdminServiceError> {
+        self.token_manager
+            .set_priorities(priorities)
+            .map_err(|e| AdminServiceError::InternalError(e.to_string()))
+    }
+
+    /// 获取凭据历史版本和禁用/启用记录（均为最新的在前）
+    pub fn credential_history(
+        &self,
+        id: u64,
+    ) -> Result<CredentialHistoryResponse, AdminServiceError> {
+        let history = self
+            .token_manager
+            .credential_history(id)
+            .ok_or(AdminServiceError::NotFound { id })?;
+        let now_ms = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let versions = history
+            .into_iter()
+            .enumerate()
+            .map(|(i, snapshot)| CredentialVersionItem {
+                version: i + 1,
+                taken_at: now_ms.saturating_sub(snapshot.timestamp.elapsed().as_millis() as u64),
+                priority: snapshot.priority,
+                disabled: snapshot.disabled,
+                notes: snapshot.notes,
+                tags: snapshot.tags,
+            })
+            .collect();
+        let disable_history = self
+            .token_manager
+            .disable_history(id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|transition| DisableTransitionItem {
+                timestamp: transition.timestamp.to_rfc3339(),
+                disabled: transition.disabled,
+                reason: transition.reason,
+                actor: transition.actor,
+            })
+            .collect();
+        Ok(CredentialHistoryResponse {
+            id,
+            versions,
+            disable_history,
+        })
+    }
+
+    /// 获取凭据最近响应时间的直方图
+    ///
+    /// 响应时间记录在凭据所属池的 Token 管理器上，凭据不属于任何池时使用默认管理器
+    pub fn latency_histogram(&self, id: u64) -> Result<LatencyHistogramResponse, AdminServiceError> {
+        let buckets = self
+            .credential_manager(id)
+            .latency_histogram(id)
+            .ok_or(AdminServiceError::NotFound { id })?;
+        Ok(LatencyHistogramResponse {
+            id,
+            bucket_ms: LATENCY_BUCKET_MS,
+            sample_count: buckets.iter().map(|b| b.count).sum(),
+            buckets,
+        })
+    }
+
+    /// 获取凭据最近一段时间的调用时间线
+    ///
+    /// 池中的调用记录在所属池的 Token 管理器上，凭据不属于任何池时使用默认管理器
+    pub fn credential_timeline(
+        &self,
+        id: u64,
+        window_minutes: Option<u64>,
+        granularity_minutes: Option<u64>,
+    ) -> Result<CredentialTimelineResponse, AdminServiceError> {
+        let window_minutes = window_minutes
+            .unwrap_or(60)
+            .clamp(1, TIMELINE_MAX_WINDOW_MINUTES);
+        let granularity_minutes = granularity_minutes.unwrap_or(5).clamp(1, window_minutes);
+        let buckets = self
+            .credential_manager(id)
+            .call_timeline(id, window_minutes, granularity_minutes)
+            .ok_or(AdminServiceError::NotFound { id })?;
+        Ok(CredentialTimelineResponse {
+            id,
+            window_minutes,
+            granularity_minutes,
+            buckets,
+        })
+    }
+
+    /// 回滚凭据到历史版本（`actor` 为操作者身份）
+    pub fn rollback_credential(
+        &self,
+        id: u64,
+        version: usize,
+        actor: &str,
+    ) -> Result<(), AdminServiceError> {
+        self.token_manager
+            .rollback_credential(id, version, actor)
+            .map_err(|e| self.classify_error(e, id))
+    }
+
+    /// 设置凭据备注（None 表示清除）
+    pub fn set_notes(&self, id: u64, notes: Option<String>) -> Result<(), AdminServiceError> {
         self.token_manager
-            .reset_and_enable(id)
+            .set_notes(id, notes)
             .map_err(|e| self.classify_error(e, id))
     }
 
+    /// 添加/移除凭据标签，返回更新后的标签
+    pub fn update_tags(
+        &self,
+        id: u64,
+        add: Vec<String>,
+        remove: &[String],
+    ) -> Result<Vec<String>, AdminServiceError> {
+        self.token_manager
+            .update_tags(id, add, remove)
+            .map_err(|e| self.classify_error(e, id))
+    }
+
+    /// 替换凭据自定义请求头，返回更新后的请求头
+    pub fn set_custom_headers(
+        &self,
+        id: u64,
+        headers: HashMap<String, String>,
+    ) -> Result<HashMap<String, String>, AdminServiceError> {
+        self.token_manager
+            .set_custom_headers(id, headers)
+            .map_err(|e| self.classify_error(e, id))
+    }
+
+    /// 重置失败计数并重新启用（`actor` 为操作者身份）
+    pub fn reset_and_enable(&self, id: u64, actor: &str) -> Result<(), AdminServiceError> {
+        self.token_manager
+            .reset_and_enable(id, actor)
+            .map_err(|e| self.classify_error(e, id))
+    }
+
+    /// 立即刷新凭据 Token（同一凭据每 30 秒最多一次）
+    pub async fn refresh_token(
+        &self,
+        id: u64,
+        force: bool,
+    ) -> Result<RefreshTokenResponse, AdminServiceError> {
+        // 先确认凭据存在，不存在的 ID 不占用冷却记录
+        let manager = self.credential_manager(id);
+        if !manager.contains(id) {
+            return Err(AdminServiceError::NotFound { id });
+        }
+        check_cooldown(&self.last_force_refresh, id, FORCE_REFRESH_COOLDOWN)?;
+
+        let started = Instant::now();
+        let credentials = manager
+            .force_refresh(id, force)
+            .await
+            .map_err(|e| self.classify_balance_error(e, id))?;
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        tracing::info!(
+            "凭据 #{} 已手动刷新 Token（force={}，耗时 {}ms，新过期时间 {:?}）",
+            id,
+            force,
+            duration_ms,
+            credentials.expires_at
+        );
+
+        Ok(RefreshTokenResponse {
+            success: true,
+            new_expires_at: credentials.expires_at,
+            duration_ms,
+        })
+    }
+
+    /// 测试凭据与上游的连通性（同一凭据每 60 秒最多一次）
+    ///
+    /// 调用 getUsageLimits 验证 Token 和上游可达性；结果只做报告，
+    /// 不计入凭据失败次数，不影响调度
+    pub async fn test_credential(
+        &self,
+        id: u64,
+    ) -> Result<CredentialTestResponse, AdminServiceError> {
+        let manager = self.credential_manager(id);
+        if !manager.contains(id) {
+            return Err(AdminServiceError::NotFound { id });
+        }
+        check_cooldown(&self.last_credential_test, id, CREDENTIAL_TEST_COOLDOWN)?;
+
+        let timeout = Duration::from_secs(manager.config().test_timeout_secs);
+        let started = Instant::now();
+        let result = tokio::time::timeout(timeout, manager.get_usage_limits_for(id))
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("测试超时（{} 秒）", timeout.as_secs())));
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let response = match result {
+            Ok(usage) => CredentialTestResponse {
+                success: true,
+                latency_ms,
+                error: None,
+                token_valid: true,
+                quota_remaining: Some((usage.usage_limit() - usage.current_usage()).max(0.0)),
+            },
+            Err(e) => {
+                // 上游拒绝认证，或刷新失败导致没有可用 Token
+                let auth_rejected = UpstreamErrorKind::of(&e) == UpstreamErrorKind::AuthExpired
+                    || e.to_string().contains("认证失败");
+                CredentialTestResponse {
+                    success: false,
+                    latency_ms,
+                    error: Some(e.to_string()),
+                    token_valid: !auth_rejected && manager.has_valid_token(id),
+                    quota_remaining: None,
+                }
+            }
+        };
+
+        tracing::debug!(
+            "凭据 #{} 连通性测试: success={}, token_valid={}, 耗时 {}ms, error={:?}",
+            id,
+            response.success,
+            response.token_valid,
+            latency_ms,
+            response.error
+        );
+        Ok(response)
+    }
+
+    /// 检查凭据配置，返回警告列表
+    pub fn validate_credential(
+        &self,
+        id: u64,
+    ) -> Result<CredentialValidationResponse, AdminServiceError> {
+        let warnings = self
+            .token_manager
+            .validate_credential_by_id(id)
+            .ok_or(AdminServiceError::NotFound { id })?;
+
+        Ok(CredentialValidationResponse {
+            id,
+            warnings: warnings
+                .into_iter()
+                .map(|w| ValidationWarningItem {
+                    code: w.code(),
+                    message: w.to_string(),
+                })
+                .collect(),
+        })
+    }
+
     /// 获取凭据余额
     pub async fn get_balance(&self, id: u64) -> Result<BalanceResponse, AdminServiceError> {
         let usage = self
-            .token_manager
+            .credential_manager(id)
             .get_usage_limits_for(id)
             .await
             .map_err(|e| self.classify_balance_error(e, id))?;
@@ -155,13 +711,17 @@ impl AdminService {
             client_id: req.client_id,
             client_secret: req.client_secret,
             priority: req.priority,
+            max_concurrent_requests: req.max_concurrent_requests,
             region: req.region,
             machine_id: req.machine_id,
+            notes: req.notes,
+            tags: normalize_tags(req.tags),
             // 池和代理配置
             pool_id: req.pool_id,
             proxy_url: req.proxy_url,
             proxy_username: req.proxy_username,
             proxy_password: req.proxy_password,
+            custom_headers: req.custom_headers,
             // 统计字段（新凭据初始化为 0）
             success_count: 0,
             total_failure_count: 0,
@@ -170,14 +730,19 @@ impl AdminService {
             token_refresh_count: 0,
             token_refresh_failure_count: 0,
             last_token_refresh_time: None,
+            // 禁用记录
+            disabled_at: None,
+            disabled_reason: None,
+            source: None,
         };
 
-        // 调用 token_manager 添加凭据
+        // 调用 token_manager 添加凭据（按目标池的默认设置验证）
+        let defaults = self.pool_credential_defaults(new_cred.pool_id.as_deref());
         let credential_id = self
             .token_manager
-            .add_credential(new_cred)
+            .add_credential_with_defaults(new_cred, &defaults)
             .await
-            .map_err(|e| self.classify_add_error(e))?;
+            .map_err(AdminServiceError::from)?;
 
         Ok(AddCredentialResponse {
             success: true,
@@ -186,11 +751,47 @@ impl AdminService {
         })
     }
 
+    /// 克隆凭据（分配新 ID，并立即刷新 Token 验证克隆结果）
+    pub fn clone_credential(
+        &self,
+        id: u64,
+        req: CloneCredentialRequest,
+    ) -> Result<CloneCredentialResponse, AdminServiceError> {
+        let overrides = CredentialCloneOverrides {
+            priority: req.new_priority,
+            pool_id: req.new_pool_id,
+            region: req.new_region,
+        };
+        let manager = self.credential_manager(id);
+        let result = manager.clone_credential(id, overrides);
+        let credential_id = result.map_err(|e| match e.downcast::<KiroError>() {
+            Ok(e) => AdminServiceError::from(e),
+            Err(e) => AdminServiceError::InternalError(e.to_string()),
+        })?;
+
+        // 克隆时不刷新 Token，两个凭据共用同一个 refreshToken
+        let warning = manager.shares_refresh_token(id, credential_id).then(|| {
+            format!(
+                "凭据 #{} 与原凭据 #{} 使用同一个 refreshToken，若上游刷新时轮换 refreshToken，任一凭据刷新后另一个会失效",
+                credential_id, id
+            )
+        });
+
+        Ok(CloneCredentialResponse {
+            credential_id,
+            warning,
+        })
+    }
+
     /// 删除凭据
     pub fn delete_credential(&self, id: u64) -> Result<(), AdminServiceError> {
         self.token_manager
             .delete_credential(id)
-            .map_err(|e| self.classify_delete_error(e, id))
+            .map_err(|e| self.classify_delete_error(e, id))?;
+        // 清理冷却记录，避免已删除凭据的条目一直保留
+        self.last_force_refresh.remove(&id);
+        self.last_credential_test.remove(&id);
+        Ok(())
     }
 
     /// 批量导入凭据（从 IdC 格式转换）
@@ -234,13 +835,17 @@ impl AdminService {
                 client_id: item.client_id,
                 client_secret: item.client_secret,
                 priority: 0,
+                max_concurrent_requests: None,
                 region: item.region,
                 machine_id: None,
+                notes: None,
+                tags: Vec::new(),
                 // 池配置（使用传入的 pool_id）
                 pool_id: pool_id.clone(),
                 proxy_url: None,
                 proxy_username: None,
                 proxy_password: None,
+                custom_headers: HashMap::new(),
                 // 统计字段（新凭据初始化为 0）
                 success_count: 0,
                 total_failure_count: 0,
@@ -249,10 +854,19 @@ impl AdminService {
                 token_refresh_count: 0,
                 token_refresh_failure_count: 0,
                 last_token_refresh_time: None,
+                // 禁用记录
+                disabled_at: None,
+                disabled_reason: None,
+                source: None,
             };
 
             // 尝试添加凭据
-            match self.token_manager.add_credential(new_cred).await {
+            let defaults = self.pool_credential_defaults(pool_id.as_deref());
+            match self
+                .token_manager
+                .add_credential_with_defaults(new_cred, &defaults)
+                .await
+            {
                 Ok(id) => {
                     credential_ids.push(id);
                     imported_count += 1;
@@ -278,6 +892,95 @@ impl AdminService {
         })
     }
 
+    /// 批量导入 Kiro IDE 导出格式的凭据
+    ///
+    /// 缺少 `token` 或 token 已被截断的条目直接跳过，不调用上游
+    pub async fn import_kiro_ide_credentials(
+        &self,
+        items: Vec<serde_json::Value>,
+        pool_id: Option<String>,
+    ) -> Result<ImportCredentialsResponse, AdminServiceError> {
+        let mut credential_ids = Vec::new();
+        let mut skipped_items = Vec::new();
+
+        for (index, raw) in items.into_iter().enumerate() {
+            let label = ["label", "email"]
+                .iter()
+                .find_map(|key| raw.get(key).and_then(|v| v.as_str()))
+                .unwrap_or("未知")
+                .to_string();
+
+            let Some(mut new_cred) = from_kiro_ide_format(raw) else {
+                skipped_items.push(format!(
+                    "#{}: {} - 缺少 token 或 token 已被截断",
+                    index + 1,
+                    label
+                ));
+                continue;
+            };
+            new_cred.pool_id = pool_id.clone();
+
+            let defaults = self.pool_credential_defaults(pool_id.as_deref());
+            match self
+                .token_manager
+                .add_credential_with_defaults(new_cred, &defaults)
+                .await
+            {
+                Ok(id) => credential_ids.push(id),
+                Err(e) => skipped_items.push(format!("#{}: {} - {}", index + 1, label, e)),
+            }
+        }
+
+        let imported_count = credential_ids.len();
+        let skipped_count = skipped_items.len();
+        Ok(ImportCredentialsResponse {
+            success: imported_count > 0,
+            message: format!(
+                "导入完成：成功 {} 个，跳过 {} 个",
+                imported_count, skipped_count
+            ),
+            imported_count,
+            skipped_count,
+            credential_ids,
+            skipped_items,
+        })
+    }
+
+    /// 从 CSV 批量导入凭据
+    ///
+    /// 逐行校验，校验失败或添加失败的行记录在 `skipped` 中
+    pub async fn import_credentials_csv(
+        &self,
+        content: &str,
+    ) -> Result<ImportResult, AdminServiceError> {
+        let parsed = parse_credentials_csv(content)
+            .map_err(|e| AdminServiceError::InvalidCredential(e.to_string()))?;
+
+        let mut skipped = parsed.skipped;
+        let mut credential_ids = Vec::new();
+        for (row, cred) in parsed.credentials {
+            let defaults = self.pool_credential_defaults(cred.pool_id.as_deref());
+            match self
+                .token_manager
+                .add_credential_with_defaults(cred, &defaults)
+                .await
+            {
+                Ok(id) => credential_ids.push(id),
+                Err(e) => skipped.push(SkippedRow {
+                    row,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+        skipped.sort_by_key(|s| s.row);
+
+        Ok(ImportResult {
+            imported: credential_ids.len(),
+            skipped,
+            credential_ids,
+        })
+    }
+
     /// 设置调度模式
     pub fn set_scheduling_mode(&self, mode: SchedulingMode) {
         self.token_manager.set_scheduling_mode(mode);
@@ -289,7 +992,30 @@ impl AdminService {
         self.token_manager.get_scheduling_mode()
     }
 
-    /// 分类简单操作错误（set_disabled, set_priority, reset_and_enable）
+    /// 凭据所属池的 Token 管理器（未启用池或凭据不属于任何池时为全局管理器）
+    ///
+    /// 刷新、测试和余额查询需按所属池的默认设置和超时配置调用上游
+    fn credential_manager(&self, id: u64) -> Arc<MultiTokenManager> {
+        self.pool_manager
+            .as_ref()
+            .and_then(|pm| pm.credential_token_manager(id))
+            .unwrap_or_else(|| self.token_manager.clone())
+    }
+
+    /// 新凭据所属池的凭据默认设置
+    ///
+    /// 未指定池或池不存在时按默认池处理（与池管理器加载凭据时一致），未启用池时为全局管理器的设置
+    fn pool_credential_defaults(&self, pool_id: Option<&str>) -> CredentialDefaults {
+        let Some(pm) = &self.pool_manager else {
+            return self.token_manager.credential_defaults();
+        };
+        let defaults = |pool_id: &str| pm.with_pool(pool_id, |p| p.config.credential_defaults());
+        defaults(pool_id.unwrap_or(DEFAULT_POOL_ID))
+            .or_else(|| defaults(DEFAULT_POOL_ID))
+            .unwrap_or_default()
+    }
+
+    /// 分类简单操作错误（set_disabled, set_priority, set_notes, update_tags, reset_and_enable）
     fn classify_error(&self, e: anyhow::Error, id: u64) -> AdminServiceError {
         let msg = e.to_string();
         if msg.contains("不存在") {
@@ -332,30 +1058,6 @@ impl AdminService {
         }
     }
 
-    /// 分类添加凭据错误
-    fn classify_add_error(&self, e: anyhow::Error) -> AdminServiceError {
-        let msg = e.to_string();
-
-        // 凭据验证失败（refreshToken 无效、格式错误等）
-        let is_invalid_credential = msg.contains("缺少 refreshToken")
-            || msg.contains("refreshToken 为空")
-            || msg.contains("refreshToken 已被截断")
-            || msg.contains("凭证已过期或无效")
-            || msg.contains("权限不足")
-            || msg.contains("已被限流");
-
-        if is_invalid_credential {
-            AdminServiceError::InvalidCredential(msg)
-        } else if msg.contains("error trying to connect")
-            || msg.contains("connection")
-            || msg.contains("timeout")
-        {
-            AdminServiceError::UpstreamError(msg)
-        } else {
-            AdminServiceError::InternalError(msg)
-        }
-    }
-
     /// 分类删除凭据错误
     fn classify_delete_error(&self, e: anyhow::Error, id: u64) -> AdminServiceError {
         let msg = e.to_string();
@@ -368,3 +1070,639 @@ impl AdminService {
         }
     }
 }
+
+/// 将 Kiro IDE 导出格式转换为 `KiroCredentials`
+///
+/// 字段映射：`token` → refreshToken，`type` → authMethod，
+/// `credentials.accessToken` / `credentials.expiresAt` → accessToken / expiresAt。
+/// 格式无法解析、缺少 `token` 或 token 已被截断时返回 None
+pub fn from_kiro_ide_format(raw: serde_json::Value) -> Option<KiroCredentials> {
+    let item: KiroIdeCredentialFormat = serde_json::from_value(raw).ok()?;
+
+    // 未指定 type 时，有 clientId 和 clientSecret 则为 IdC，否则为 Social
+    let is_idc = match item.auth_type.as_deref() {
+        Some(t) => !t.eq_ignore_ascii_case("social"),
+        None => item.client_id.is_some() && item.client_secret.is_some(),
+    };
+    let nested = item.credentials.unwrap_or_default();
+
+    let credentials = KiroCredentials {
+        access_token: nested.access_token,
+        refresh_token: item.token.filter(|t| !t.trim().is_empty()),
+        profile_arn: item.profile_arn,
+        expires_at: nested.expires_at,
+        auth_method: Some(if is_idc { "idc" } else { "social" }.to_string()),
+        client_id: item.client_id,
+        client_secret: item.client_secret,
+        region: item.region,
+        notes: item.label.or(item.email),
+        ..KiroCredentials::default()
+    };
+    validate_refresh_token(&credentials).ok()?;
+    Some(credentials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::config::Config;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// 指向 mock 上游、持有有效 accessToken 的凭据管理服务
+    async fn service_with_usage_response(response: ResponseTemplate) -> (MockServer, AdminService) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/getUsageLimits"))
+            .and(header("Authorization", "Bearer valid-access-token"))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+
+        let config = Config {
+            upstream_base_url: Some(server.uri()),
+            ..Config::default()
+        };
+        let credential = KiroCredentials {
+            access_token: Some("valid-access-token".to_string()),
+            refresh_token: Some("r".repeat(150)),
+            expires_at: Some((chrono::Utc::now() + chrono::Duration::hours(6)).to_rfc3339()),
+            ..KiroCredentials::default()
+        };
+        let manager = MultiTokenManager::new(config, vec![credential], None, None).unwrap();
+        (server, AdminService::new(Arc::new(manager)))
+    }
+
+    fn failure_count(service: &AdminService) -> u32 {
+        service.token_manager.snapshot().entries[0].failure_count
+    }
+
+    #[tokio::test]
+    async fn test_credential_test_success() {
+        let (_server, service) = service_with_usage_response(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "usageBreakdownList": [{
+                    "currentUsageWithPrecision": 20.0,
+                    "usageLimitWithPrecision": 50.0
+                }]
+            })),
+        )
+        .await;
+
+        let result = service.test_credential(1).await.unwrap();
+        assert!(result.success);
+        assert!(result.token_valid);
+        assert!(result.error.is_none());
+        assert_eq!(result.quota_remaining, Some(30.0));
+        assert_eq!(failure_count(&service), 0);
+
+        // 60 秒内重复测试被限流
+        let err = service.test_credential(1).await.unwrap_err();
+        assert_eq!(err.status_code(), axum::http::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(err.retry_after_secs(), Some(60));
+    }
+
+    #[tokio::test]
+    async fn test_credential_test_failure_does_not_affect_scheduling() {
+        let (_server, service) =
+            service_with_usage_response(ResponseTemplate::new(401).set_body_string("expired"))
+                .await;
+
+        let result = service.test_credential(1).await.unwrap();
+        assert!(!result.success);
+        assert!(!result.token_valid);
+        assert!(result.error.unwrap().contains("认证失败"));
+        assert!(result.quota_remaining.is_none());
+
+        // 测试失败不计入失败次数，凭据保持可用
+        assert_eq!(failure_count(&service), 0);
+        assert_eq!(service.token_manager.available_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_credential_test_unknown_id() {
+        let (_server, service) = service_with_usage_response(ResponseTemplate::new(200)).await;
+        assert!(matches!(
+            service.test_credential(99).await,
+            Err(AdminServiceError::NotFound { id: 99 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_force_refresh_failure_restores_expires_at() {
+        let (server, service) = service_with_usage_response(ResponseTemplate::new(200)).await;
+        Mock::given(method("POST"))
+            .and(path("/refreshToken"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .mount(&server)
+            .await;
+        let original = service.token_manager.snapshot().entries[0]
+            .expires_at
+            .clone();
+
+        assert!(service.refresh_token(1, true).await.is_err());
+        assert_eq!(
+            service.token_manager.snapshot().entries[0].expires_at,
+            original
+        );
+
+        // 不存在的 ID 返回 404，且不写入冷却记录
+        assert!(matches!(
+            service.refresh_token(99, true).await,
+            Err(AdminServiceError::NotFound { id: 99 })
+        ));
+        assert!(!service.last_force_refresh.contains_key(&99));
+
+        // 删除凭据后清理冷却记录
+        assert!(service.last_force_refresh.contains_key(&1));
+        service.token_manager.set_disabled(1, true, "test").unwrap();
+        service.delete_credential(1).unwrap();
+        assert!(!service.last_force_refresh.contains_key(&1));
+    }
+
+    #[test]
+    fn test_get_all_credentials_filters() {
+        let credential =
+            |id: u64, auth_method: &str, region: Option<&str>, tags: &[&str]| KiroCredentials {
+                id: Some(id),
+                refresh_token: Some("r".repeat(150)),
+                auth_method: Some(auth_method.to_string()),
+                region: region.map(String::from),
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                ..KiroCredentials::default()
+            };
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![
+                credential(1, "social", None, &["team-a"]),
+                credential(2, "idc", Some("eu-west-1"), &["team-b", "vendor-x"]),
+                credential(3, "builder-id", Some("us-east-1"), &[]),
+            ],
+            None,
+            None,
+        )
+        .unwrap();
+        manager.set_disabled(3, true, "admin").unwrap();
+        let service = AdminService::new(Arc::new(manager));
+
+        let ids = |query: CredentialsQuery| -> Vec<u64> {
+            let mut ids: Vec<u64> = service
+                .get_all_credentials(&query)
+                .credentials
+                .iter()
+                .map(|c| c.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids(CredentialsQuery::default()), vec![1, 2, 3]);
+        assert_eq!(
+            ids(CredentialsQuery {
+                tag: Some("TEAM-A".to_string()),
+                ..Default::default()
+            }),
+            vec![1]
+        );
+        assert_eq!(
+            ids(CredentialsQuery {
+                disabled: Some(false),
+                auth_method: Some("idc".to_string()),
+                ..Default::default()
+            }),
+            vec![2]
+        );
+        assert_eq!(
+            ids(CredentialsQuery {
+                auth_method: Some("iam".to_string()),
+                ..Default::default()
+            }),
+            vec![2, 3]
+        );
+        assert_eq!(
+            ids(CredentialsQuery {
+                q: Some("vendor".to_string()),
+                ..Default::default()
+            }),
+            vec![2]
+        );
+        assert_eq!(
+            ids(CredentialsQuery {
+                q: Some("us-east".to_string()),
+                ..Default::default()
+            }),
+            vec![3]
+        );
+        assert_eq!(
+            ids(CredentialsQuery {
+                pool_id: Some("default".to_string()),
+                ..Default::default()
+            }),
+            vec![1, 2, 3]
+        );
+        assert!(
+            ids(CredentialsQuery {
+                pool_id: Some("premium".to_string()),
+                ..Default::default()
+            })
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_get_all_credentials_pagination_and_updated_since() {
+        let credentials = (1..=5)
+            .map(|id| KiroCredentials {
+                id: Some(id),
+                refresh_token: Some("r".repeat(150)),
+                ..KiroCredentials::default()
+            })
+            .collect();
+        let manager = MultiTokenManager::new(Config::default(), credentials, None, None).unwrap();
+        let manager = Arc::new(manager);
+        let service = AdminService::new(manager.clone());
+
+        let page = service.get_all_credentials(&CredentialsQuery {
+            offset: Some(1),
+            limit: Some(2),
+            ..Default::default()
+        });
+        assert_eq!(page.total, 5);
+        assert_eq!(page.matched, 5);
+        assert_eq!(
+            page.credentials.iter().map(|c| c.id).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+        let past_end = service.get_all_credentials(&CredentialsQuery {
+            offset: Some(10),
+            ..Default::default()
+        });
+        assert!(past_end.credentials.is_empty());
+
+        // 只返回 updated_since 之后状态有变化的凭据
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let since = chrono::Utc::now().timestamp_millis() as u64;
+        manager.set_notes(4, Some("rotated".to_string())).unwrap();
+        manager.report_failure(2, "claude-sonnet-4.5", Some("timeout"), 0);
+
+        let changed = service.get_all_credentials(&CredentialsQuery {
+            updated_since: Some(since),
+            ..Default::default()
+        });
+        assert_eq!(changed.total, 5);
+        assert_eq!(
+            changed.credentials.iter().map(|c| c.id).collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+        assert!(changed.credentials.iter().all(|c| c.last_changed >= since));
+    }
+
+    #[test]
+    fn test_from_kiro_ide_format() {
+        let export: Vec<serde_json::Value> =
+            serde_json::from_str(include_str!("../../tests/fixtures/kiro_ide/export.json"))
+                .unwrap();
+        let converted: Vec<Option<KiroCredentials>> =
+            export.into_iter().map(from_kiro_ide_format).collect();
+
+        let social = converted[0].as_ref().unwrap();
+        assert_eq!(social.auth_method.as_deref(), Some("social"));
+        assert!(
+            social
+                .refresh_token
+                .as_ref()
+                .unwrap()
+                .starts_with("aorAAAAAG")
+        );
+        assert_eq!(
+            social.access_token.as_deref(),
+            Some("aoaAAAAAGfixture-access-token")
+        );
+        assert_eq!(
+            social.expires_at.as_deref(),
+            Some("2025-06-01T08:00:00.000Z")
+        );
+        assert_eq!(social.region.as_deref(), Some("us-east-1"));
+        assert!(social.profile_arn.is_some());
+        assert!(social.client_id.is_none());
+
+        let idc = converted[1].as_ref().unwrap();
+        assert_eq!(idc.auth_method.as_deref(), Some("idc"));
+        assert_eq!(idc.client_id.as_deref(), Some("fixture-client-id"));
+        assert_eq!(idc.client_secret.as_deref(), Some("fixture-client-secret"));
+        assert_eq!(idc.region.as_deref(), Some("eu-west-1"));
+
+        // 截断的 token 和缺少 token 的条目被跳过
+        assert!(converted[2].is_none());
+        assert!(converted[3].is_none());
+    }
+
+    #[tokio::test]
+    async fn test_credential_operations_use_owning_pool_manager() {
+        use crate::kiro::pool_manager::UpdatePoolRequest;
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/getUsageLimits"))
+            .and(header("Authorization", "Bearer pool-access-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "usageBreakdownList": [{
+                    "currentUsageWithPrecision": 5.0,
+                    "usageLimitWithPrecision": 50.0
+                }]
+            })))
+            .mount(&server)
+            .await;
+        let config = Config {
+            upstream_base_url: Some(server.uri()),
+            ..Config::default()
+        };
+        let expires_at = (chrono::Utc::now() + chrono::Duration::hours(6)).to_rfc3339();
+
+        let dir = tempfile::tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        let credentials = serde_json::json!([{
+            "id": 1,
+            "refreshToken": "r".repeat(150),
+            "accessToken": "pool-access-token",
+            "expiresAt": expires_at,
+        }]);
+        std::fs::write(&credentials_path, credentials.to_string()).unwrap();
+        let pool_manager = Arc::new(
+            PoolManager::new(config.clone(), None, &pools_path, &credentials_path).unwrap(),
+        );
+
+        // 全局管理器中的同一凭据持有另一个 Token，上游只接受池中的 Token
+        let global = KiroCredentials {
+            id: Some(1),
+            access_token: Some("global-access-token".to_string()),
+            refresh_token: Some("r".repeat(150)),
+            expires_at: Some(expires_at),
+            ..KiroCredentials::default()
+        };
+        let global = MultiTokenManager::new(config, vec![global], None, None).unwrap();
+        let service = AdminService::new(Arc::new(global)).with_pool_manager(pool_manager.clone());
+
+        assert_eq!(service.get_balance(1).await.unwrap().remaining, 45.0);
+        assert!(service.test_credential(1).await.unwrap().success);
+
+        // 新凭据按目标池（未指定或池不存在时为默认池）的默认设置验证
+        pool_manager
+            .update_pool(
+                DEFAULT_POOL_ID,
+                UpdatePoolRequest {
+                    default_region: Some("eu-west-1".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        for pool_id in [None, Some(DEFAULT_POOL_ID), Some("missing")] {
+            assert_eq!(
+                service.pool_credential_defaults(pool_id).region.as_deref(),
+                Some("eu-west-1")
+            );
+        }
+    }
+
+    #[test]
+    fn test_aggregate_stats_matches_pool_snapshots() {
+        use crate::kiro::pool::Pool;
+
+        let dir = tempfile::tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        let token = "a".repeat(150);
+        let credentials = serde_json::json!([
+            {"refreshToken": token},
+            {"refreshToken": token, "poolId": "alpha", "authMethod": "idc"},
+            {"refreshToken": token, "poolId": "alpha"},
+            {"refreshToken": token, "poolId": "beta"},
+        ]);
+        std::fs::write(&credentials_path, credentials.to_string()).unwrap();
+
+        let pool_manager = Arc::new(
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap(),
+        );
+        pool_manager
+            .create_pool(Pool::new("alpha", "Alpha"))
+            .unwrap();
+        pool_manager.create_pool(Pool::new("beta", "Beta")).unwrap();
+        pool_manager.reload().unwrap();
+
+        let alpha = pool_manager.get_pool("alpha").unwrap();
+        let alpha_ids: Vec<u64> = alpha
+            .read()
+            .token_manager
+            .snapshot()
+            .entries
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        alpha
+            .read()
+            .token_manager
+            .report_success_with_time(alpha_ids[0], Some(100));
+        alpha
+            .read()
+            .token_manager
+            .report_failure_with_time(alpha_ids[1], None, Some(300));
+        let beta = pool_manager.get_pool("beta").unwrap();
+        let beta_id = beta.read().token_manager.snapshot().entries[0].id;
+        beta.read()
+            .token_manager
+            .set_disabled(beta_id, true, "admin")
+            .unwrap();
+        let default_pool = pool_manager.get_pool(DEFAULT_POOL_ID).unwrap();
+        let default_id = default_pool.read().token_manager.snapshot().entries[0].id;
+        default_pool
+            .read()
+            .token_manager
+            .report_success_with_time(default_id, Some(200));
+
+        let service = AdminService::new(default_pool.read().token_manager.clone())
+            .with_pool_manager(pool_manager.clone());
+        let stats = service.aggregate_stats();
+
+        let snapshots: Vec<_> = pool_manager
+            .pool_ids()
+            .iter()
+            .map(|id| {
+                pool_manager
+                    .get_pool(id)
+                    .unwrap()
+                    .read()
+                    .token_manager
+                    .snapshot()
+            })
+            .collect();
+        let entries = || snapshots.iter().flat_map(|s| s.entries.iter());
+        assert_eq!(
+            stats.total_credentials,
+            snapshots.iter().map(|s| s.total).sum::<usize>()
+        );
+        assert_eq!(
+            stats.total_available,
+            snapshots.iter().map(|s| s.available).sum::<usize>()
+        );
+        assert_eq!(
+            stats.total_success_calls,
+            entries().map(|e| e.success_count).sum::<u64>()
+        );
+        assert_eq!(
+            stats.total_failure_calls,
+            entries().map(|e| e.total_failure_count).sum::<u64>()
+        );
+        assert_eq!(stats.total_credentials, 4);
+        assert_eq!(stats.total_disabled, 1);
+        assert_eq!(stats.total_success_calls, 2);
+        assert_eq!(stats.total_failure_calls, 1);
+        assert_eq!(stats.credentials_by_auth_method["idc"], 1);
+        assert_eq!(stats.credentials_by_auth_method["social"], 3);
+        assert_eq!(stats.credentials_by_pool["alpha"], 2);
+        assert_eq!(stats.credentials_by_pool["beta"], 1);
+        assert_eq!(stats.credentials_by_pool[DEFAULT_POOL_ID], 1);
+        assert!(stats.avg_health_score > 0.0 && stats.avg_health_score < 100.0);
+
+        // 延迟直方图读取凭据所属池的响应时间
+        let histogram = service.latency_histogram(alpha_ids[0]).unwrap();
+        assert_eq!(histogram.sample_count, 1);
+        assert_eq!(histogram.buckets[2].count, 1);
+
+        // 时间线合并所有池的分钟桶
+        let timeline = service.get_timeline(Some(7200));
+        assert_eq!(timeline.window_secs, 3600);
+        assert_eq!(
+            timeline
+                .buckets
+                .iter()
+                .map(|b| b.request_count)
+                .sum::<u64>(),
+            3
+        );
+        assert_eq!(
+            timeline
+                .buckets
+                .iter()
+                .map(|b| b.failure_count)
+                .sum::<u64>(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_dashboard_schema() {
+        use crate::admin::api_keys::CreateApiKeyRequest;
+
+        let dir = tempfile::tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        let token = "a".repeat(150);
+        let credentials = serde_json::json!([
+            {"refreshToken": token},
+            {"refreshToken": token, "poolId": "alpha"},
+            {"refreshToken": token, "poolId": "alpha"},
+        ]);
+        std::fs::write(&credentials_path, credentials.to_string()).unwrap();
+
+        let pool_manager = Arc::new(
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap(),
+        );
+        pool_manager
+            .create_pool(
+                Pool::new("alpha", "Alpha").with_scheduling_mode(SchedulingMode::PriorityFill),
+            )
+            .unwrap();
+        pool_manager.reload().unwrap();
+
+        let alpha = pool_manager.get_pool("alpha").unwrap();
+        let alpha_ids: Vec<u64> = alpha
+            .read()
+            .token_manager
+            .snapshot()
+            .entries
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        alpha
+            .read()
+            .token_manager
+            .set_disabled(alpha_ids[0], true, "admin")
+            .unwrap();
+        alpha
+            .read()
+            .token_manager
+            .set_cached_usage(alpha_ids[1], 25.0, 100.0);
+        alpha
+            .read()
+            .token_manager
+            .report_failure_with_time(alpha_ids[1], None, Some(300));
+
+        let api_keys = ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap();
+        let key = api_keys
+            .create_with_full_key(CreateApiKeyRequest {
+                name: "ci".to_string(),
+                description: None,
+                key: Some("sk-dashboard-test-key".to_string()),
+                pool_id: None,
+                websearch_rate_limit_per_hour: None,
+                allowed_models: None,
+                denied_models: None,
+                max_tokens_limit: None,
+            })
+            .unwrap();
+        api_keys.record_request(&key.key);
+        api_keys.record_request(&key.key);
+
+        let default_pool = pool_manager.get_pool(DEFAULT_POOL_ID).unwrap();
+        let service = AdminService::new(default_pool.read().token_manager.clone())
+            .with_pool_manager(pool_manager.clone());
+        let mut dashboard = serde_json::to_value(service.get_dashboard(&api_keys)).unwrap();
+
+        // 与时间相关的字段只检查类型
+        assert!(dashboard["uptimeSecs"].is_u64());
+        assert!(dashboard["generatedAt"].is_string());
+        assert_eq!(dashboard["version"], env!("CARGO_PKG_VERSION"));
+        dashboard["uptimeSecs"] = serde_json::json!(0);
+        dashboard["generatedAt"] = serde_json::json!("");
+        dashboard["version"] = serde_json::json!("");
+
+        assert_eq!(
+            dashboard,
+            serde_json::json!({
+                "version": "",
+                "uptimeSecs": 0,
+                "generatedAt": "",
+                "pools": [
+                    {
+                        "id": "alpha",
+                        "name": "Alpha",
+                        "enabled": true,
+                        "schedulingMode": "priority_fill",
+                        "availableCredentials": 1,
+                        "totalCredentials": 2,
+                        "quotaRemainingPercentage": 75.0
+                    },
+                    {
+                        "id": DEFAULT_POOL_ID,
+                        "name": Pool::default_pool().name,
+                        "enabled": true,
+                        "schedulingMode": "round_robin",
+                        "availableCredentials": 1,
+                        "totalCredentials": 1,
+                        "quotaRemainingPercentage": null
+                    }
+                ],
+                "requests": {"windowSecs": 3600, "requests": 1, "errors": 1},
+                "topApiKeys": [
+                    {"id": key.id, "name": "ci", "key": "sk-dashb***", "requestsLastHour": 2}
+                ],
+                "recentlyDisabled": {
+                    "windowSecs": 86400,
+                    "count": 1,
+                    "byReason": {"手动禁用": 1}
+                }
+            })
+        );
+    }
+}
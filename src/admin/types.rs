@@ -1,9 +1,23 @@
 //! Admin API 类型定义
 
+use std::collections::{BTreeMap, HashMap};
+
 use serde::{Deserialize, Serialize};
 
-use crate::kiro::token_manager::SchedulingMode;
-use crate::model::config::TlsBackend;
+use crate::admin::api_keys::{ApiKeyMasked, ApiKeyUsageItem};
+use crate::admin::backup::{BackupInfo, BackupKind};
+use crate::admin::effective_config::EffectiveConfigField;
+use crate::common::i18n::{ErrorCode, Locale, LocalizedError};
+use crate::kiro::fairness::UserSessionCount;
+use crate::kiro::model::credentials_csv::SkippedRow;
+use crate::kiro::performance::PerformanceBucket;
+use crate::kiro::pool_manager::RebalanceStrategy;
+use crate::kiro::stickiness::StickinessSnapshot;
+use crate::kiro::timeline::TimelineBucket;
+use crate::kiro::token_manager::{
+    CredentialEntrySnapshot, FailureClass, LatencyBucket, SchedulingMode, SettingSource,
+};
+use crate::model::config::{RateLimitExemption, TlsBackend, TlsVersion};
 
 // ============ 凭据状态 ============
 
@@ -15,12 +29,18 @@ pub struct CredentialsStatusResponse {
     pub total: usize,
     /// 可用凭据数量（未禁用）
     pub available: usize,
+    /// 满足筛选条件的凭据数（分页前）
+    pub matched: usize,
     /// 当前活跃凭据 ID
     pub current_id: u64,
     /// 各凭据状态列表
     pub credentials: Vec<CredentialStatusItem>,
     /// 会话缓存大小
     pub session_cache_size: usize,
+    /// 会话缓存容量上限
+    pub session_cache_capacity: u64,
+    /// 会话缓存 TTL（秒）
+    pub session_cache_ttl_secs: u64,
     /// 轮询计数器
     pub round_robin_counter: u64,
     /// 当前调度模式
@@ -47,6 +67,130 @@ pub struct CredentialStatusItem {
     pub auth_method: Option<String>,
     /// 是否有 Profile ARN
     pub has_profile_arn: bool,
+    /// 备注
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// 标签
+    pub tags: Vec<String>,
+    /// 凭据级 Region
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// 生效的 Region（凭据 > 池默认值 > 全局）
+    pub effective_region: String,
+    /// 生效 Region 的来源（credential / pool / global）
+    pub region_source: SettingSource,
+    /// 生效代理的来源（未配置任何代理时为 null）
+    pub proxy_source: Option<SettingSource>,
+    /// 禁用原因（可读文本，未禁用时为 null）
+    pub disabled_reason: Option<String>,
+    /// 最近一次禁用时间（RFC3339，重新启用后保留）
+    pub disabled_at: Option<String>,
+    /// 最近禁用历史中的禁用次数（用于按不稳定程度排序）
+    pub flap_count: usize,
+    /// 当前进行中的调用数
+    pub current_concurrency: u32,
+    /// 最大并发请求数（null 表示不限制）
+    pub max_concurrency: Option<u32>,
+    /// 最近一次导致失败的错误信息
+    pub last_error: Option<String>,
+    /// 上游限流退避截止时间（RFC3339，退避期内不参与调度）
+    pub retry_after_until: Option<String>,
+    /// 失败分类（用于管理面板着色）
+    pub failure_classification: Option<FailureClass>,
+    /// 运行时状态最后变化时间（Unix 时间戳毫秒）
+    pub last_changed: u64,
+}
+
+impl CredentialStatusItem {
+    /// 从 Token 管理器的凭据快照构建
+    pub fn from_snapshot(entry: CredentialEntrySnapshot, is_current: bool) -> Self {
+        Self {
+            id: entry.id,
+            priority: entry.priority,
+            disabled: entry.disabled,
+            failure_count: entry.failure_count,
+            is_current,
+            expires_at: entry.expires_at,
+            auth_method: entry.auth_method,
+            has_profile_arn: entry.has_profile_arn,
+            notes: entry.notes,
+            tags: entry.tags,
+            region: entry.region,
+            effective_region: entry.effective_region,
+            region_source: entry.region_source,
+            proxy_source: entry.proxy_source,
+            disabled_reason: entry.disabled_reason,
+            disabled_at: entry.disabled_at,
+            flap_count: entry.flap_count,
+            current_concurrency: entry.current_concurrency,
+            max_concurrency: entry.max_concurrency,
+            last_error: entry.last_error,
+            retry_after_until: entry.retry_after_until,
+            failure_classification: entry.failure_classification,
+            last_changed: entry.last_changed,
+        }
+    }
+}
+
+/// 凭据列表查询参数（均为可选，筛选条件同时指定时取交集）
+#[derive(Debug, Default, Deserialize)]
+pub struct CredentialsQuery {
+    /// 包含该标签（大小写不敏感）
+    pub tag: Option<String>,
+    /// 按禁用状态筛选
+    pub disabled: Option<bool>,
+    /// 按认证方式筛选（social / idc）
+    pub auth_method: Option<String>,
+    /// 按所属池筛选
+    pub pool_id: Option<String>,
+    /// 自由文本，匹配标签或 Region（大小写不敏感的子串匹配）
+    pub q: Option<String>,
+    /// 只返回该时间（Unix 时间戳毫秒，含）之后状态有变化的凭据
+    pub updated_since: Option<u64>,
+    /// 分页偏移（按优先级排序后）
+    pub offset: Option<usize>,
+    /// 分页大小（不指定时返回全部）
+    pub limit: Option<usize>,
+}
+
+impl CredentialsQuery {
+    /// 凭据是否满足筛选条件（`pool_id` 由调用方按凭据来源池筛选）
+    pub fn matches(&self, item: &CredentialStatusItem) -> bool {
+        if let Some(tag) = self.tag.as_deref()
+            && !item.tags.iter().any(|t| t.eq_ignore_ascii_case(tag.trim()))
+        {
+            return false;
+        }
+        if self.disabled.is_some_and(|d| d != item.disabled) {
+            return false;
+        }
+        if let Some(method) = self.auth_method.as_deref() {
+            let method = match method.to_ascii_lowercase().as_str() {
+                "builder-id" | "iam" => "idc".to_string(),
+                other => other.to_string(),
+            };
+            if !item
+                .auth_method
+                .as_deref()
+                .is_some_and(|m| m.eq_ignore_ascii_case(&method))
+            {
+                return false;
+            }
+        }
+        if let Some(q) = self.q.as_deref().map(|q| q.trim().to_lowercase())
+            && !q.is_empty()
+        {
+            let hit = item
+                .tags
+                .iter()
+                .chain(item.region.as_ref())
+                .any(|text| text.to_lowercase().contains(&q));
+            if !hit {
+                return false;
+            }
+        }
+        true
+    }
 }
 
 // ============ 操作请求 ============
@@ -67,6 +211,201 @@ pub struct SetPriorityRequest {
     pub priority: u32,
 }
 
+/// 批量修改优先级的单个条目
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityUpdate {
+    /// 凭据 ID
+    pub id: u64,
+    /// 新优先级值（同一批次内不可重复）
+    pub priority: u32,
+}
+
+/// 批量修改优先级请求（请求体为条目数组，用于拖拽排序）
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub struct BulkPriorityRequest {
+    pub items: Vec<PriorityUpdate>,
+}
+
+/// 批量修改优先级失败的条目
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkPriorityFailure {
+    pub id: u64,
+    pub error: String,
+}
+
+/// 批量修改优先级响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkPriorityResponse {
+    /// 已更新的凭据 ID（按请求顺序）
+    pub updated: Vec<u64>,
+    /// 未能更新的凭据及原因
+    pub failed: Vec<BulkPriorityFailure>,
+}
+
+/// 凭据历史版本（不含 Token 等敏感字段）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialVersionItem {
+    /// 版本号（1 为最近一次变更前的状态）
+    pub version: usize,
+    /// 快照时间（Unix 时间戳毫秒）
+    pub taken_at: u64,
+    /// 优先级
+    pub priority: u32,
+    /// 是否被禁用
+    pub disabled: bool,
+    /// 备注
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// 标签
+    pub tags: Vec<String>,
+}
+
+/// 凭据禁用状态的一次变化
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisableTransitionItem {
+    /// 变化时间（RFC3339）
+    pub timestamp: String,
+    /// 变化后是否禁用
+    pub disabled: bool,
+    /// 原因（可读文本）
+    pub reason: String,
+    /// 操作者（自动变化为 `system`，手动操作为 Admin 身份）
+    pub actor: String,
+}
+
+/// 凭据历史版本响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialHistoryResponse {
+    /// 凭据 ID
+    pub id: u64,
+    /// 历史版本（最新的在前，最多 5 个）
+    pub versions: Vec<CredentialVersionItem>,
+    /// 禁用/启用记录（最新的在前，最多 20 条，仅保存在内存中）
+    pub disable_history: Vec<DisableTransitionItem>,
+}
+
+/// 凭据响应时间直方图响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyHistogramResponse {
+    /// 凭据 ID
+    pub id: u64,
+    /// 桶宽（毫秒）
+    pub bucket_ms: u64,
+    /// 样本数（最近的成功调用，最多 100 个，仅保存在内存中）
+    pub sample_count: usize,
+    /// 各桶样本数（从 0 毫秒起连续分桶，空桶也保留；超出范围的样本计入末尾 `endMs` 为 null 的溢出桶）
+    pub buckets: Vec<LatencyBucket>,
+}
+
+/// 凭据调用时间线查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct CredentialTimelineQuery {
+    /// 时间窗口（分钟，默认 60，最长 1440）
+    pub window_minutes: Option<u64>,
+    /// 分桶粒度（分钟，默认 5，不超过时间窗口）
+    pub granularity_minutes: Option<u64>,
+}
+
+/// 凭据调用时间线响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialTimelineResponse {
+    /// 凭据 ID
+    pub id: u64,
+    /// 实际使用的时间窗口（分钟）
+    pub window_minutes: u64,
+    /// 实际使用的分桶粒度（分钟）
+    pub granularity_minutes: u64,
+    /// 各时间桶的调用统计（按时间升序，无调用的桶也保留；仅保存在内存中）
+    pub buckets: Vec<TimelineBucket>,
+}
+
+/// 回滚凭据查询参数
+#[derive(Debug, Deserialize)]
+pub struct RollbackQuery {
+    /// 要恢复的版本号（见 `GET /credentials/:id/history`）
+    pub version: usize,
+}
+
+/// 修改备注请求（`notes` 为 null 或省略时清除备注）
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetNotesRequest {
+    /// 新备注
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// 添加/移除凭据标签请求（先移除再添加）
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateTagsRequest {
+    /// 要添加的标签
+    #[serde(default)]
+    pub add: Vec<String>,
+    /// 要移除的标签（大小写不敏感）
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+/// 替换凭据自定义请求头请求（`headers` 为空或省略时清除全部自定义请求头）
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetCustomHeadersRequest {
+    /// 新的自定义请求头（整体替换）
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+/// 凭据自定义请求头响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialHeadersResponse {
+    /// 凭据 ID
+    pub id: u64,
+    /// 更新后的自定义请求头
+    pub headers: HashMap<String, String>,
+}
+
+/// 凭据标签响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialTagsResponse {
+    /// 凭据 ID
+    pub id: u64,
+    /// 更新后的标签
+    pub tags: Vec<String>,
+}
+
+/// 强制刷新 Token 请求（请求体可省略）
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshTokenRequest {
+    /// 是否忽略过期时间强制刷新（默认 true）
+    #[serde(default = "default_force")]
+    pub force: bool,
+}
+
+impl Default for RefreshTokenRequest {
+    fn default() -> Self {
+        Self {
+            force: default_force(),
+        }
+    }
+}
+
+fn default_force() -> bool {
+    true
+}
+
 /// 设置调度模式请求
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -96,6 +435,9 @@ pub struct AddCredentialRequest {
     #[serde(default)]
     pub priority: u32,
 
+    /// 最大并发请求数（可选，未配置时不限制）
+    pub max_concurrent_requests: Option<u32>,
+
     /// 凭据级 Region 配置（用于 OIDC token 刷新）
     /// 未配置时回退到 config.json 的全局 region
     pub region: Option<String>,
@@ -115,6 +457,17 @@ pub struct AddCredentialRequest {
 
     /// 凭据级代理密码
     pub proxy_password: Option<String>,
+
+    /// 备注（可选，最多 1000 个字符）
+    pub notes: Option<String>,
+
+    /// 标签（可选）
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// 自定义上游请求头（可选）
+    #[serde(default)]
+    pub custom_headers: HashMap<String, String>,
 }
 
 fn default_auth_method() -> String {
@@ -131,6 +484,32 @@ pub struct AddCredentialResponse {
     pub credential_id: u64,
 }
 
+/// 克隆凭据请求（请求体可省略，未设置的字段沿用原凭据）
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloneCredentialRequest {
+    /// 新凭据的优先级
+    #[serde(default)]
+    pub new_priority: Option<u32>,
+    /// 新凭据所属池 ID
+    #[serde(default)]
+    pub new_pool_id: Option<String>,
+    /// 新凭据的区域
+    #[serde(default)]
+    pub new_region: Option<String>,
+}
+
+/// 克隆凭据成功响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloneCredentialResponse {
+    /// 新凭据 ID
+    pub credential_id: u64,
+    /// 克隆后与原凭据仍使用同一个 refreshToken 时的提示
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
 // ============ 余额查询 ============
 
 /// 余额查询响应
@@ -153,6 +532,55 @@ pub struct BalanceResponse {
     pub next_reset_at: Option<f64>,
 }
 
+// ============ Token 刷新 ============
+
+/// 强制刷新 Token 响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshTokenResponse {
+    pub success: bool,
+    /// 刷新后的过期时间
+    pub new_expires_at: Option<String>,
+    /// 刷新耗时（毫秒）
+    pub duration_ms: u64,
+}
+
+/// 凭据连通性测试响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialTestResponse {
+    /// 是否成功调用上游
+    pub success: bool,
+    /// 测试耗时（毫秒）
+    pub latency_ms: u64,
+    /// 失败原因
+    pub error: Option<String>,
+    /// Token 是否有效（可正常获取且未被上游拒绝）
+    pub token_valid: bool,
+    /// 剩余额度
+    pub quota_remaining: Option<f64>,
+}
+
+// ============ 凭据配置检查 ============
+
+/// 凭据配置警告
+#[derive(Debug, Serialize)]
+pub struct ValidationWarningItem {
+    /// 警告码
+    pub code: &'static str,
+    /// 警告描述
+    pub message: String,
+}
+
+/// 凭据配置检查响应
+#[derive(Debug, Serialize)]
+pub struct CredentialValidationResponse {
+    /// 凭据 ID
+    pub id: u64,
+    /// 警告列表（为空表示未发现问题）
+    pub warnings: Vec<ValidationWarningItem>,
+}
+
 // ============ 通用响应 ============
 
 /// 操作成功响应
@@ -188,37 +616,54 @@ pub struct AdminErrorResponse {
 pub struct AdminError {
     #[serde(rename = "type")]
     pub error_type: String,
+    /// 机器可读的稳定错误码
+    pub code: String,
     pub message: String,
 }
 
 impl AdminErrorResponse {
-    pub fn new(error_type: impl Into<String>, message: impl Into<String>) -> Self {
+    /// 创建错误响应，消息按客户端语言渲染
+    pub fn new(
+        error_type: impl Into<String>,
+        error: impl Into<LocalizedError>,
+        locale: Locale,
+    ) -> Self {
+        let error = error.into();
         Self {
             error: AdminError {
                 error_type: error_type.into(),
-                message: message.into(),
+                code: error.code().as_str().to_string(),
+                message: error.render(locale),
             },
         }
     }
 
-    pub fn invalid_request(message: impl Into<String>) -> Self {
-        Self::new("invalid_request", message)
+    pub fn invalid_request(error: impl Into<LocalizedError>, locale: Locale) -> Self {
+        Self::new("invalid_request", error, locale)
     }
 
-    pub fn authentication_error() -> Self {
-        Self::new("authentication_error", "Invalid or missing admin API key")
+    pub fn authentication_error(locale: Locale) -> Self {
+        Self::new(
+            "authentication_error",
+            ErrorCode::AdminAuthenticationFailed,
+            locale,
+        )
     }
 
-    pub fn not_found(message: impl Into<String>) -> Self {
-        Self::new("not_found", message)
+    pub fn not_found(error: impl Into<LocalizedError>, locale: Locale) -> Self {
+        Self::new("not_found", error, locale)
     }
 
-    pub fn api_error(message: impl Into<String>) -> Self {
-        Self::new("api_error", message)
+    pub fn api_error(error: impl Into<LocalizedError>, locale: Locale) -> Self {
+        Self::new("api_error", error, locale)
     }
 
-    pub fn internal_error(message: impl Into<String>) -> Self {
-        Self::new("internal_error", message)
+    pub fn internal_error(error: impl Into<LocalizedError>, locale: Locale) -> Self {
+        Self::new("internal_error", error, locale)
+    }
+
+    pub fn rate_limited(error: impl Into<LocalizedError>, locale: Locale) -> Self {
+        Self::new("rate_limit_error", error, locale)
     }
 }
 
@@ -238,6 +683,8 @@ pub struct ConfigResponse {
     pub kiro_version: String,
     /// TLS 后端
     pub tls_backend: TlsBackend,
+    /// TLS 最低协议版本
+    pub tls_min_version: TlsVersion,
     /// 会话缓存最大容量
     pub session_cache_max_capacity: u64,
     /// 会话缓存 TTL（秒）
@@ -248,10 +695,140 @@ pub struct ConfigResponse {
     pub proxy_username: Option<String>,
     /// 代理密码（脱敏）
     pub proxy_password: Option<String>,
+    /// 客户端 API Key（脱敏，仅显示前 4 个字符）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// 是否配置了客户端 API Key
+    pub has_api_key: bool,
     /// 是否配置了 Admin API Key
     pub has_admin_api_key: bool,
 }
 
+/// 生效配置响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfigResponse {
+    /// 所有配置项的当前值和来源（按字段名排序）
+    pub fields: BTreeMap<String, EffectiveConfigField>,
+    /// 运行时推导出的值
+    pub derived: EffectiveConfigDerived,
+}
+
+/// 运行时推导出的配置（不直接来自配置文件字段）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfigDerived {
+    /// 配置文件路径
+    pub config_path: String,
+    /// 凭据文件路径（使用内存凭据时为 null）
+    pub credentials_path: Option<String>,
+    /// 池配置文件路径
+    pub pools_path: String,
+    /// API Key 文件路径
+    pub api_keys_path: String,
+    /// 当前 TLS 后端
+    pub tls_backend: TlsBackend,
+    /// Admin API 是否启用
+    pub admin_enabled: bool,
+    /// 池管理器是否启用
+    pub pool_manager_enabled: bool,
+}
+
+// ============ 功能开关 ============
+
+/// 功能开关状态
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureFlagItem {
+    /// 开关名称
+    pub name: String,
+    /// 是否启用
+    pub enabled: bool,
+    /// 说明
+    pub description: String,
+}
+
+/// 功能开关列表响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureFlagsResponse {
+    pub features: Vec<FeatureFlagItem>,
+}
+
+/// 备份列表响应（按时间倒序）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupListResponse {
+    pub backups: Vec<BackupInfo>,
+}
+
+/// 恢复备份响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreBackupResponse {
+    pub success: bool,
+    pub message: String,
+    /// 恢复的快照标识
+    pub timestamp: String,
+    /// 已恢复并重新加载的文件类型
+    pub restored: Vec<BackupKind>,
+}
+
+/// 生成一次性登录链接请求（Admin Key 也可放在 x-api-key / Authorization 头中）
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MagicLinkRequest {
+    pub admin_key: Option<String>,
+}
+
+/// 一次性登录链接响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MagicLinkResponse {
+    pub token: String,
+    pub expires_at: String,
+    /// Admin UI 登录地址（访问后换发会话 Cookie 并跳转到 /admin）
+    pub login_url: String,
+}
+
+/// 注销查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct LogoutQuery {
+    /// 为 true 时注销所有登录会话（如 Admin Key 疑似泄露）
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// 注销响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogoutResponse {
+    /// 注销的会话数量
+    pub revoked_sessions: usize,
+}
+
+/// 限流豁免规则列表响应（按配置顺序，删除时使用数组下标）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitExemptionsResponse {
+    pub exemptions: Vec<RateLimitExemption>,
+}
+
+/// 限流统计查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct RateLimitStatsQuery {
+    /// 返回的 API Key 数量上限（默认 10）
+    pub top: Option<usize>,
+}
+
+/// 切换功能开关请求
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetFeatureFlagRequest {
+    /// 是否启用
+    pub enabled: bool,
+}
+
 // ============ 批量导入凭据 ============
 
 /// IdC 格式的凭据（从 Kiro Account Manager 导出）
@@ -280,6 +857,61 @@ pub struct IdcCredentialItem {
     pub region: Option<String>,
 }
 
+/// Kiro IDE 导出格式的凭据
+///
+/// 与 kiro.rs 字段名不同：`token` 为 refreshToken，`type` 为认证方式，
+/// accessToken / expiresAt 嵌套在 `credentials` 中
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KiroIdeCredentialFormat {
+    /// 标签（导入后作为备注）
+    #[serde(default)]
+    pub label: Option<String>,
+    /// 邮箱（无标签时作为备注）
+    #[serde(default)]
+    pub email: Option<String>,
+    /// 刷新令牌
+    #[serde(default)]
+    pub token: Option<String>,
+    /// 认证方式（social / IdC / BuilderId 等）
+    #[serde(default, rename = "type")]
+    pub auth_type: Option<String>,
+    /// 嵌套的访问令牌信息
+    #[serde(default)]
+    pub credentials: Option<KiroIdeTokenCredentials>,
+    /// Profile ARN
+    #[serde(default)]
+    pub profile_arn: Option<String>,
+    /// OIDC Client ID
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// OIDC Client Secret
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    /// Region
+    #[serde(default)]
+    pub region: Option<String>,
+}
+
+/// Kiro IDE 导出格式中嵌套的访问令牌信息
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KiroIdeTokenCredentials {
+    /// 访问令牌
+    #[serde(default)]
+    pub access_token: Option<String>,
+    /// 过期时间
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+/// Kiro IDE 格式导入的查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportKiroIdeQuery {
+    /// 导入到指定池（可选，默认为 default）
+    pub pool_id: Option<String>,
+}
+
 /// 批量导入凭据请求
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -306,6 +938,18 @@ pub struct ImportCredentialsResponse {
     pub skipped_items: Vec<String>,
 }
 
+/// CSV 批量导入凭据响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    /// 成功导入的数量
+    pub imported: usize,
+    /// 跳过的行及原因
+    pub skipped: Vec<SkippedRow>,
+    /// 导入的凭据 ID 列表
+    pub credential_ids: Vec<u64>,
+}
+
 /// 更新配置请求
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -334,6 +978,9 @@ pub struct UpdateConfigRequest {
     /// 代理密码
     #[serde(default)]
     pub proxy_password: Option<String>,
+    /// 客户端 API Key（空字符串表示清除）
+    #[serde(default)]
+    pub api_key: Option<String>,
 }
 
 // ============ 池管理 ============
@@ -364,6 +1011,18 @@ pub struct PoolStatusItem {
     pub has_proxy: bool,
     /// 优先级
     pub priority: u32,
+    /// 自动路由使用的优先级（开启池优先级自动调整时可能与 `priority` 不同）
+    pub effective_priority: u32,
+    /// 溢出池 ID
+    pub overflow_pool_id: Option<String>,
+    /// 成员凭据的默认 Region
+    pub default_region: Option<String>,
+    /// 成员凭据的默认认证方式
+    pub default_auth_method: Option<String>,
+    /// 成员凭据的默认 machineId
+    pub default_machine_id: Option<String>,
+    /// 是否配置了成员凭据的默认代理
+    pub has_default_proxy: bool,
     /// 凭据总数
     pub total_credentials: usize,
     /// 可用凭据数量
@@ -372,8 +1031,49 @@ pub struct PoolStatusItem {
     pub current_id: u64,
     /// 会话缓存大小
     pub session_cache_size: u64,
+    /// 会话缓存容量上限
+    pub session_cache_capacity: u64,
+    /// 会话缓存 TTL（秒）
+    pub session_cache_ttl_secs: u64,
     /// 轮询计数器
     pub round_robin_counter: u64,
+    /// 绑定到该池的 API Key 数量（默认池含未绑定池的 Key，不含自动路由）
+    pub bound_api_key_count: usize,
+    /// 最近 60 分钟的性能历史（仅池详情返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub performance_history: Option<Vec<PerformanceBucket>>,
+}
+
+/// API Key 与池的绑定方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolApiKeyBinding {
+    /// 显式绑定该池（单个池或池列表之一）
+    Direct,
+    /// 通过 `__auto__` 自动路由，可能被路由到该池
+    Auto,
+    /// 未绑定池，使用默认池
+    Default,
+}
+
+/// 池的 API Key 条目
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolApiKeyItem {
+    #[serde(flatten)]
+    pub api_key: ApiKeyMasked,
+    /// 绑定方式
+    pub binding: PoolApiKeyBinding,
+}
+
+/// 池的 API Key 列表响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolApiKeysResponse {
+    /// 池 ID
+    pub pool_id: String,
+    /// API Key 列表（显式绑定的在前）
+    pub api_keys: Vec<PoolApiKeyItem>,
 }
 
 /// 池凭证列表响应
@@ -420,6 +1120,42 @@ pub struct CreatePoolRequest {
     /// 优先级
     #[serde(default)]
     pub priority: u32,
+    /// 池级会话缓存容量（未设置或 0 时使用全局配置）
+    #[serde(default)]
+    pub session_cache_max_capacity: Option<u64>,
+    /// 池级会话缓存 TTL（秒，未设置或 0 时使用全局配置）
+    #[serde(default)]
+    pub session_cache_ttl_secs: Option<u64>,
+    /// 池级建立连接超时（秒，未设置或 0 时使用全局配置）
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u64>,
+    /// 池级 Token 刷新请求超时（秒，未设置或 0 时使用全局配置）
+    #[serde(default)]
+    pub refresh_request_timeout_secs: Option<u64>,
+    /// 池级上游首字节超时（秒，未设置或 0 时使用全局配置）
+    #[serde(default)]
+    pub upstream_first_byte_timeout_secs: Option<u64>,
+    /// 溢出池 ID（本池无可用凭据时将请求转到该池）
+    #[serde(default)]
+    pub overflow_pool_id: Option<String>,
+    /// 成员凭据的默认 Region（凭据未配置 region 时使用）
+    #[serde(default)]
+    pub default_region: Option<String>,
+    /// 成员凭据的默认认证方式（social / idc）
+    #[serde(default)]
+    pub default_auth_method: Option<String>,
+    /// 成员凭据的默认 machineId（凭据未配置 machineId 时使用）
+    #[serde(default)]
+    pub default_machine_id: Option<String>,
+    /// 成员凭据的默认代理 URL（凭据未配置代理时使用）
+    #[serde(default)]
+    pub default_proxy_url: Option<String>,
+    /// 成员凭据的默认代理用户名
+    #[serde(default)]
+    pub default_proxy_username: Option<String>,
+    /// 成员凭据的默认代理密码
+    #[serde(default)]
+    pub default_proxy_password: Option<String>,
 }
 
 /// 更新池请求
@@ -450,6 +1186,42 @@ pub struct UpdatePoolRequest {
     /// 优先级
     #[serde(default)]
     pub priority: Option<u32>,
+    /// 池级会话缓存容量（0 表示清除覆盖，恢复全局配置）
+    #[serde(default)]
+    pub session_cache_max_capacity: Option<u64>,
+    /// 池级会话缓存 TTL（秒，0 表示清除覆盖，恢复全局配置）
+    #[serde(default)]
+    pub session_cache_ttl_secs: Option<u64>,
+    /// 池级建立连接超时（秒，0 表示清除覆盖；变更后重新加载池）
+    #[serde(default)]
+    pub connect_timeout_secs: Option<u64>,
+    /// 池级 Token 刷新请求超时（秒，0 表示清除覆盖；变更后重新加载池）
+    #[serde(default)]
+    pub refresh_request_timeout_secs: Option<u64>,
+    /// 池级上游首字节超时（秒，0 表示清除覆盖；变更后重新加载池）
+    #[serde(default)]
+    pub upstream_first_byte_timeout_secs: Option<u64>,
+    /// 溢出池 ID（空字符串表示清除）
+    #[serde(default)]
+    pub overflow_pool_id: Option<String>,
+    /// 成员凭据的默认 Region（空字符串表示清除）
+    #[serde(default)]
+    pub default_region: Option<String>,
+    /// 成员凭据的默认认证方式（social / idc，空字符串表示清除）
+    #[serde(default)]
+    pub default_auth_method: Option<String>,
+    /// 成员凭据的默认 machineId（空字符串表示清除）
+    #[serde(default)]
+    pub default_machine_id: Option<String>,
+    /// 成员凭据的默认代理 URL（空字符串表示清除）
+    #[serde(default)]
+    pub default_proxy_url: Option<String>,
+    /// 成员凭据的默认代理用户名（空字符串表示清除）
+    #[serde(default)]
+    pub default_proxy_username: Option<String>,
+    /// 成员凭据的默认代理密码（空字符串表示清除）
+    #[serde(default)]
+    pub default_proxy_password: Option<String>,
 }
 
 /// 设置池禁用状态请求
@@ -460,6 +1232,32 @@ pub struct SetPoolDisabledRequest {
     pub disabled: bool,
 }
 
+/// 重命名池请求
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamePoolRequest {
+    /// 新池 ID
+    pub new_id: String,
+}
+
+/// 池间重新分配凭据请求
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RebalancePoolsRequest {
+    /// 分配策略（even / by_priority / by_health）
+    pub strategy: RebalanceStrategy,
+}
+
+/// 删除池查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct DeletePoolQuery {
+    /// 池内凭据重新分配的目标池
+    pub reassign_to: Option<String>,
+    /// 未指定目标池时，将池内凭据重新分配到默认池
+    #[serde(default)]
+    pub force: bool,
+}
+
 /// 分配凭据到池请求
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -468,3 +1266,234 @@ pub struct AssignCredentialToPoolRequest {
     pub pool_id: String,
 }
 
+/// 分配凭据到池响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignCredentialToPoolResponse {
+    /// 分配前所在的池
+    pub source_pool_id: String,
+    /// 目标池 ID
+    pub pool_id: String,
+    /// 凭据在目标池中的新状态
+    pub credential: CredentialStatusItem,
+}
+
+/// 转移凭据到池请求
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferCredentialPoolRequest {
+    /// 目标池 ID
+    pub target_pool_id: String,
+    /// 是否将源池中绑定到该凭据的会话迁移到目标池
+    #[serde(default)]
+    pub migrate_active_sessions: bool,
+}
+
+/// 转移凭据到池响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferCredentialPoolResponse {
+    /// 迁移的会话数
+    pub moved_sessions: usize,
+}
+
+
+/// 运行统计响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsResponse {
+    /// 已放行的 WebSearch 请求数
+    pub websearch_requests: u64,
+    /// 被 WebSearch 限流拒绝的请求数
+    pub websearch_rate_limited: u64,
+    /// 令牌桶放行的请求数（含豁免请求，未启用令牌桶时为 0）
+    pub token_bucket_requests: u64,
+    /// 命中豁免规则、未消耗令牌的请求数
+    pub token_bucket_exempt_requests: u64,
+    /// 清理过控制字符、BOM 或多余空白的文本增量数
+    pub text_artifacts_stripped: u64,
+    /// 修复成功的 tool_use 输入 JSON 数
+    pub tool_json_repaired: u64,
+    /// 无法修复、原样透传的 tool_use 输入 JSON 数
+    pub tool_json_repair_failed: u64,
+    /// 以 gzip 压缩发送的上游请求数
+    pub compressed_requests: u64,
+    /// 压缩前的上游请求体总字节数
+    pub request_bytes_before_compression: u64,
+    /// 压缩后的上游请求体总字节数
+    pub request_bytes_after_compression: u64,
+}
+
+/// 所有池的凭据统计汇总
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregatedStats {
+    /// 凭据总数
+    pub total_credentials: usize,
+    /// 可用凭据数（未禁用）
+    pub total_available: usize,
+    /// 已禁用凭据数
+    pub total_disabled: usize,
+    /// 成功调用次数（总计）
+    pub total_success_calls: u64,
+    /// 失败调用次数（总计）
+    pub total_failure_calls: u64,
+    /// Token 刷新次数（成功 + 失败）
+    pub total_token_refreshes: u64,
+    /// 平均健康分（0~100，无凭据时为 0）
+    pub avg_health_score: f64,
+    /// 按认证方式统计的凭据数
+    pub credentials_by_auth_method: HashMap<String, usize>,
+    /// 按池统计的凭据数
+    pub credentials_by_pool: HashMap<String, usize>,
+}
+
+/// 调用时间线查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct TimelineQuery {
+    /// 时间窗口（秒，默认 3600，最长 3600）
+    pub window_secs: Option<u64>,
+}
+
+/// 调用时间线响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineResponse {
+    /// 实际使用的时间窗口（秒）
+    pub window_secs: u64,
+    /// 所有池合并后的每分钟调用统计（按时间升序，无调用的分钟省略）
+    pub buckets: Vec<PerformanceBucket>,
+}
+
+// ============ 仪表盘 ============
+
+/// Admin 仪表盘响应（只读取内存中的快照和统计，不调用上游）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardResponse {
+    /// 服务版本
+    pub version: String,
+    /// 运行时长（秒）
+    pub uptime_secs: u64,
+    /// 生成时间（RFC3339）
+    pub generated_at: String,
+    /// 各池概要（按池 ID 排序）
+    pub pools: Vec<DashboardPoolItem>,
+    /// 最近 1 小时的请求统计（所有池合计）
+    pub requests: DashboardRequestStats,
+    /// 最近 1 小时请求数最多的 5 个 API Key
+    pub top_api_keys: Vec<ApiKeyUsageItem>,
+    /// 最近 24 小时内被禁用过的凭据
+    pub recently_disabled: DashboardDisabledStats,
+}
+
+/// 仪表盘中的池概要
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardPoolItem {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub scheduling_mode: SchedulingMode,
+    /// 可用凭据数
+    pub available_credentials: usize,
+    /// 凭据总数
+    pub total_credentials: usize,
+    /// 可用凭据的剩余额度百分比（基于缓存的余额，没有查询过余额时为 null）
+    pub quota_remaining_percentage: Option<f64>,
+}
+
+/// 仪表盘中的请求统计
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardRequestStats {
+    /// 统计窗口（秒）
+    pub window_secs: u64,
+    /// 上游调用数
+    pub requests: u64,
+    /// 失败的上游调用数
+    pub errors: u64,
+}
+
+/// 仪表盘中的近期禁用统计
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardDisabledStats {
+    /// 统计窗口（秒）
+    pub window_secs: u64,
+    /// 窗口内被禁用过的凭据数
+    pub count: usize,
+    /// 按最近一次禁用原因统计的凭据数
+    pub by_reason: BTreeMap<String, usize>,
+}
+
+/// 按用户公平调度的会话统计响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSessionsResponse {
+    /// 是否启用按用户公平调度
+    pub enabled: bool,
+    /// 单个用户最多占用的可用凭据比例
+    pub max_share: f64,
+    /// 各用户的活跃会话数（用户标识为哈希值）
+    pub users: Vec<UserSessionCount>,
+}
+
+/// 单个池的粘性会话诊断统计
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolStickinessItem {
+    /// 池 ID
+    pub pool_id: String,
+    /// 统计数据
+    #[serde(flatten)]
+    pub stats: StickinessSnapshot,
+}
+
+/// 粘性会话诊断响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StickinessResponse {
+    /// system 哈希兜底占比告警阈值（百分比，0 表示不告警）
+    pub system_hash_warn_percent: u8,
+    /// 所有池合计
+    pub total: StickinessSnapshot,
+    /// 各池统计（按池 ID 排序）
+    pub pools: Vec<PoolStickinessItem>,
+}
+
+/// 启动预热报告响应
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupReportResponse {
+    /// 参与预热的凭据数
+    pub total: usize,
+    /// 校验通过的凭据数
+    pub ready: usize,
+    /// 校验失败的凭据数
+    pub failed: usize,
+    /// 成功刷新 Token 的凭据数
+    pub refreshed: usize,
+    /// Token 仍有效、跳过刷新的凭据数
+    pub skipped: usize,
+    /// 预热总耗时（毫秒）
+    pub elapsed_ms: u64,
+    /// 各凭据结果
+    pub entries: Vec<WarmupEntryItem>,
+}
+
+/// 单个凭据的预热结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupEntryItem {
+    pub id: u64,
+    pub auth_method: String,
+    pub priority: u32,
+    /// 耗时（毫秒）
+    pub duration_ms: u64,
+    /// 是否实际刷新了 Token
+    pub refreshed: bool,
+    pub success: bool,
+    /// 失败原因
+    pub error: Option<String>,
+}
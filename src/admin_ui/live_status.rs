@@ -0,0 +1,48 @@
+//! Admin UI 实时状态推送
+//!
+//! 通过 SSE 将凭据/池状态变化实时推送给 Admin UI
+
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::{Stream, stream};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::admin::AdminState;
+
+/// Keep-alive ping 间隔（15 秒）
+const KEEP_ALIVE_INTERVAL_SECS: u64 = 15;
+
+/// GET /admin/api/live-status
+///
+/// 订阅 Admin 事件通道，将每个事件编码为 JSON 作为 SSE data 推送
+pub async fn live_status(
+    State(state): State<AdminState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.event_sender.subscribe();
+
+    let stream = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => match Event::default().json_data(&event) {
+                    Ok(sse_event) => return Some((Ok(sse_event), receiver)),
+                    Err(e) => tracing::warn!("序列化 Admin 事件失败: {}", e),
+                },
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Admin 实时状态订阅者处理过慢，已丢弃 {} 个事件", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(
+        KeepAlive::new()
+            .interval(Duration::from_secs(KEEP_ALIVE_INTERVAL_SECS))
+            .text("ping"),
+    )
+}
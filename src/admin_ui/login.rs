@@ -0,0 +1,69 @@
+//! Admin UI 一次性登录
+//!
+//! 校验 `POST /api/admin/auth/magic-link` 生成的 Token，换发会话 Cookie 后跳转到管理后台
+
+use axum::{
+    Json,
+    extract::{NestedPath, Query, rejection::NestedPathRejection},
+    http::{Extensions, HeaderMap, StatusCode, header},
+    response::{AppendHeaders, IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::admin::magic_link;
+use crate::admin::types::AdminErrorResponse;
+use crate::common::i18n::{ErrorCode, Locale};
+
+/// 登录查询参数
+#[derive(Debug, Deserialize)]
+pub struct LoginQuery {
+    pub token: Option<String>,
+}
+
+/// GET /admin/login?token=...
+/// 兑换一次性登录 Token，设置会话 Cookie 并跳转到 Admin UI 首页
+///
+/// 跳转地址和 Cookie 作用域按实际挂载路径计算；仅 HTTPS 请求的 Cookie 带 `Secure` 属性
+pub async fn login(
+    locale: Locale,
+    nested_path: Result<NestedPath, NestedPathRejection>,
+    extensions: Extensions,
+    headers: HeaderMap,
+    Query(query): Query<LoginQuery>,
+) -> Response {
+    let Some(fingerprint) = query
+        .token
+        .as_deref()
+        .and_then(magic_link::redeem_magic_link)
+    else {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::CACHE_CONTROL, "no-store")],
+            Json(AdminErrorResponse::new(
+                "authentication_error",
+                ErrorCode::MagicLinkInvalid,
+                locale,
+            )),
+        )
+            .into_response();
+    };
+
+    let session_id = magic_link::create_session(fingerprint);
+    tracing::info!("Admin UI 一次性登录链接已使用，已创建会话");
+
+    let nested_path = nested_path.ok();
+    let paths = magic_link::AdminPaths::from_ui_path(nested_path.as_ref().map(NestedPath::as_str));
+    let secure = magic_link::is_https(&headers, &extensions);
+    (
+        StatusCode::SEE_OTHER,
+        [
+            (header::LOCATION, paths.ui.clone()),
+            (header::CACHE_CONTROL, "no-store".to_string()),
+        ],
+        AppendHeaders(
+            magic_link::session_cookies(&session_id, &paths, secure)
+                .map(|cookie| (header::SET_COOKIE, cookie)),
+        ),
+    )
+        .into_response()
+}
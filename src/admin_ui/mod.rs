@@ -1,7 +1,10 @@
 //! Admin UI 静态文件服务模块
 //!
-//! 使用 rust-embed 嵌入前端构建产物
+//! 使用 rust-embed 嵌入前端构建产物，并提供实时状态推送（SSE）和偏好设置接口
 
+mod live_status;
+mod login;
+mod preferences;
 mod router;
 
 pub use router::create_admin_ui_router;
@@ -0,0 +1,50 @@
+//! Admin UI 偏好设置接口
+//!
+//! 偏好设置为非敏感数据，不需要认证
+
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+
+use crate::admin::AdminState;
+use crate::admin::preferences::UiPreferences;
+use crate::admin::types::AdminErrorResponse;
+use crate::common::i18n::{ErrorCode, Locale};
+
+/// GET /admin/api/preferences
+/// 获取当前偏好设置
+pub async fn get_preferences(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(state.ui_preferences.get())
+}
+
+/// POST /admin/api/preferences
+/// 保存偏好设置
+pub async fn save_preferences(
+    State(state): State<AdminState>,
+    locale: Locale,
+    Json(payload): Json<UiPreferences>,
+) -> Response {
+    match state.ui_preferences.save(payload) {
+        Ok(saved) => Json(saved).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(AdminErrorResponse::internal_error(
+                ErrorCode::PreferencesSaveFailed.arg("detail", e),
+                locale,
+            )),
+        )
+            .into_response(),
+    }
+}
+
+/// GET /admin/api/init
+/// SPA 启动时获取的初始化数据
+pub async fn get_init(State(state): State<AdminState>) -> impl IntoResponse {
+    Json(json!({
+        "preferences": state.ui_preferences.get(),
+    }))
+}
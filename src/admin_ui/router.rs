@@ -3,11 +3,20 @@
 use axum::{
     Router,
     body::Body,
-    http::{Response, StatusCode, Uri, header},
+    http::{HeaderMap, HeaderValue, Response, StatusCode, Uri, header},
+    middleware,
     response::IntoResponse,
     routing::get,
 };
-use rust_embed::Embed;
+use rust_embed::{Embed, EmbeddedFile};
+use tower_http::compression::CompressionLayer;
+use tower_http::set_header::SetResponseHeaderLayer;
+
+use super::live_status::live_status;
+use super::login::login;
+use super::preferences::{get_init, get_preferences, save_preferences};
+use crate::admin::{AdminState, admin_auth_middleware};
+use crate::common::etag::etag_matches;
 
 /// 嵌入前端构建产物
 #[derive(Embed)]
@@ -15,19 +24,73 @@ use rust_embed::Embed;
 struct Asset;
 
 /// 创建 Admin UI 路由
-pub fn create_admin_ui_router() -> Router {
-    Router::new()
-        .route("/", get(index_handler))
-        .route("/{*file}", get(static_handler))
+///
+/// # 端点
+/// - `GET /` - 前端首页
+/// - `GET /login?token=...` - 兑换一次性登录链接，设置会话 Cookie 后跳转到首页
+/// - `GET /api/live-status` - 凭据/池状态实时推送（SSE，需要 Admin API Key 认证）
+/// - `GET /api/init` - SPA 初始化数据（包含偏好设置）
+/// - `GET /api/preferences` - 获取偏好设置
+/// - `POST /api/preferences` - 保存偏好设置
+/// - `GET /*file` - 静态资源
+///
+/// 静态资源按 Accept-Encoding 进行 gzip/brotli 压缩，并携带基于内容哈希的 ETag；
+/// 配置了 `adminUiCsp` 时附加 Content-Security-Policy 响应头
+pub fn create_admin_ui_router(state: AdminState) -> Router {
+    let csp = state.config.read().admin_ui_csp.clone();
+    let static_routes = with_static_layers(
+        Router::new()
+            .route("/", get(index_handler))
+            .route("/{*file}", get(static_handler)),
+        csp.as_deref(),
+    );
+
+    let live_routes = Router::new()
+        .route("/api/live-status", get(live_status))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin_auth_middleware,
+        ))
+        .with_state(state.clone());
+
+    // 偏好设置为非敏感数据，不需要认证；登录页自行校验一次性 Token
+    let preference_routes = Router::new()
+        .route("/login", get(login))
+        .route("/api/init", get(get_init))
+        .route("/api/preferences", get(get_preferences).post(save_preferences))
+        .with_state(state);
+
+    static_routes.merge(live_routes).merge(preference_routes)
+}
+
+/// 为静态资源路由添加压缩和 CSP 响应头
+///
+/// SSE 等接口不经过此处，避免压缩缓冲影响实时推送
+fn with_static_layers(router: Router, csp: Option<&str>) -> Router {
+    let router = router.layer(CompressionLayer::new());
+
+    let Some(csp) = csp.map(str::trim).filter(|v| !v.is_empty()) else {
+        return router;
+    };
+    match HeaderValue::from_str(csp) {
+        Ok(value) => router.layer(SetResponseHeaderLayer::overriding(
+            header::CONTENT_SECURITY_POLICY,
+            value,
+        )),
+        Err(_) => {
+            tracing::warn!("adminUiCsp 包含非法字符，已忽略 Content-Security-Policy 配置");
+            router
+        }
+    }
 }
 
 /// 处理首页请求
-async fn index_handler() -> impl IntoResponse {
-    serve_index()
+async fn index_handler(headers: HeaderMap) -> impl IntoResponse {
+    serve_index(&headers)
 }
 
 /// 处理静态文件请求
-async fn static_handler(uri: Uri) -> impl IntoResponse {
+async fn static_handler(uri: Uri, headers: HeaderMap) -> impl IntoResponse {
     let path = uri.path().trim_start_matches('/');
 
     // 安全检查：拒绝包含 .. 的路径
@@ -47,17 +110,12 @@ async fn static_handler(uri: Uri) -> impl IntoResponse {
         // 根据文件类型设置不同的缓存策略
         let cache_control = get_cache_control(path);
 
-        return Response::builder()
-            .status(StatusCode::OK)
-            .header(header::CONTENT_TYPE, mime)
-            .header(header::CACHE_CONTROL, cache_control)
-            .body(Body::from(content.data.into_owned()))
-            .expect("Failed to build response");
+        return serve_asset(content, &mime, cache_control, &headers);
     }
 
     // SPA fallback: 如果文件不存在且不是资源文件，返回 index.html
     if !is_asset_path(path) {
-        return serve_index();
+        return serve_index(&headers);
     }
 
     // 404
@@ -68,14 +126,9 @@ async fn static_handler(uri: Uri) -> impl IntoResponse {
 }
 
 /// 提供 index.html
-fn serve_index() -> Response<Body> {
+fn serve_index(headers: &HeaderMap) -> Response<Body> {
     match Asset::get("index.html") {
-        Some(content) => Response::builder()
-            .status(StatusCode::OK)
-            .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
-            .header(header::CACHE_CONTROL, "no-cache")
-            .body(Body::from(content.data.into_owned()))
-            .expect("Failed to build response"),
+        Some(content) => serve_asset(content, "text/html; charset=utf-8", "no-cache", headers),
         None => Response::builder()
             .status(StatusCode::NOT_FOUND)
             .body(Body::from(
@@ -85,6 +138,40 @@ fn serve_index() -> Response<Body> {
     }
 }
 
+/// 构建嵌入资源响应
+///
+/// If-None-Match 命中 ETag 时返回 304，不携带响应体
+fn serve_asset(
+    content: EmbeddedFile,
+    content_type: &str,
+    cache_control: &str,
+    headers: &HeaderMap,
+) -> Response<Body> {
+    let etag = asset_etag(&content);
+
+    if etag_matches(headers, &etag) {
+        return Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, etag)
+            .header(header::CACHE_CONTROL, cache_control)
+            .body(Body::empty())
+            .expect("Failed to build response");
+    }
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CACHE_CONTROL, cache_control)
+        .header(header::ETAG, etag)
+        .body(Body::from(content.data.into_owned()))
+        .expect("Failed to build response")
+}
+
+/// 由嵌入资源的 SHA-256 生成强 ETag
+fn asset_etag(content: &EmbeddedFile) -> String {
+    format!("\"{}\"", hex::encode(content.metadata.sha256_hash()))
+}
+
 /// 根据文件类型返回合适的缓存策略
 fn get_cache_control(path: &str) -> &'static str {
     if path.ends_with(".html") {
@@ -107,3 +194,156 @@ fn is_asset_path(path: &str) -> bool {
         .map(|filename| filename.contains('.'))
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn static_router(csp: Option<&str>) -> Router {
+        with_static_layers(
+            Router::new()
+                .route("/", get(index_handler))
+                .route("/{*file}", get(static_handler)),
+            csp,
+        )
+    }
+
+    async fn get_response(router: Router, path: &str, headers: &[(&str, &str)]) -> Response<Body> {
+        let mut request = Request::builder().uri(path);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        router
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    async fn body_len(response: Response<Body>) -> usize {
+        to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .len()
+    }
+
+    #[tokio::test]
+    async fn test_index_etag_and_not_modified() {
+        let response = get_response(static_router(None), "/", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(etag.starts_with('"') && etag.ends_with('"'));
+
+        let response = get_response(
+            static_router(None),
+            "/",
+            &[("if-none-match", etag.as_str())],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        assert_eq!(body_len(response).await, 0);
+
+        let response =
+            get_response(static_router(None), "/", &[("if-none-match", "\"stale\"")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_compression_byte_savings() {
+        // 模拟打包后的 JS 资源，并合并实际嵌入的资源（前端已构建时）
+        let bundle = "export function render(state){return state.items.map(i=>`<li>${i}</li>`)}\n"
+            .repeat(2048);
+        let bundle_len = bundle.len();
+        let router =
+            with_static_layers(
+                Router::new().route(
+                    "/assets/bundle.js",
+                    get(move || async move {
+                        ([(header::CONTENT_TYPE, "application/javascript")], bundle)
+                    }),
+                ),
+                None,
+            );
+        let mut assets = vec![("/assets/bundle.js".to_string(), bundle_len, router)];
+        for path in Asset::iter() {
+            let size = Asset::get(&path).unwrap().data.len();
+            if size >= 1024 {
+                assets.push((format!("/{}", path), size, static_router(None)));
+            }
+        }
+
+        let mut original_total = 0;
+        for (path, size, router) in assets {
+            original_total += size;
+            for encoding in ["gzip", "br"] {
+                let response =
+                    get_response(router.clone(), &path, &[("accept-encoding", encoding)]).await;
+                assert_eq!(response.status(), StatusCode::OK);
+                assert_eq!(response.headers()[header::CONTENT_ENCODING], encoding);
+                let compressed = body_len(response).await;
+                // 文本资源至少节省 10%，高度重复的打包资源压缩到 10% 以下
+                let ratio = compressed as f64 / size as f64;
+                let max_ratio = if path == "/assets/bundle.js" {
+                    0.1
+                } else {
+                    0.9
+                };
+                assert!(
+                    ratio < max_ratio,
+                    "{} ({}): {} -> {} 字节，压缩比 {:.3} >= {}",
+                    path,
+                    encoding,
+                    size,
+                    compressed,
+                    ratio,
+                    max_ratio
+                );
+            }
+        }
+        assert!(original_total >= bundle_len);
+
+        // 未声明 Accept-Encoding 时返回原始内容
+        let response = get_response(static_router(None), "/", &[]).await;
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_csp_header() {
+        let csp = "default-src 'self'";
+        let response = get_response(static_router(Some(csp)), "/", &[]).await;
+        assert_eq!(response.headers()[header::CONTENT_SECURITY_POLICY], csp);
+
+        let response = get_response(static_router(None), "/", &[]).await;
+        assert!(
+            response
+                .headers()
+                .get(header::CONTENT_SECURITY_POLICY)
+                .is_none()
+        );
+
+        let response = get_response(static_router(Some("  ")), "/", &[]).await;
+        assert!(
+            response
+                .headers()
+                .get(header::CONTENT_SECURITY_POLICY)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_cache_control() {
+        assert_eq!(get_cache_control("index.html"), "no-cache");
+        assert_eq!(
+            get_cache_control("assets/index-abc123.js"),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(get_cache_control("favicon.ico"), "public, max-age=3600");
+    }
+}
@@ -0,0 +1,134 @@
+//! 上游并发限制
+//!
+//! 对话请求调用上游前需要取得并发名额，名额用尽时排队等待，超过等待上限后
+//! 直接返回 429（不调用上游），避免突发的大量并行请求打到上游触发限流并
+//! 累计凭据失败次数。流式请求持有名额直到 SSE 流结束或客户端断开。
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 上游并发名额（释放时归还）
+pub type UpstreamPermit = OwnedSemaphorePermit;
+
+/// 上游并发限制器
+pub struct UpstreamConcurrencyLimiter {
+    semaphore: Arc<Semaphore>,
+    /// 并发上限
+    max: usize,
+    /// 最长排队等待时间
+    queue_timeout: Duration,
+    /// 当前排队请求数
+    waiting: AtomicUsize,
+}
+
+/// 并发状态（健康检查展示）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamConcurrencyStats {
+    /// 并发上限
+    pub max: usize,
+    /// 正在使用的名额数
+    pub in_use: usize,
+    /// 排队等待的请求数
+    pub waiting: usize,
+}
+
+/// 排队登记（结束等待时移除）
+struct WaitingGuard<'a>(&'a AtomicUsize);
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl UpstreamConcurrencyLimiter {
+    /// 创建限制器
+    ///
+    /// # Arguments
+    /// * `max` - 并发上限
+    /// * `queue_timeout_ms` - 最长排队等待时间（毫秒，0 表示不等待）
+    pub fn new(max: usize, queue_timeout_ms: u64) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max)),
+            max,
+            queue_timeout: Duration::from_millis(queue_timeout_ms),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// 获取并发名额，等待超时返回 None
+    pub async fn acquire(&self) -> Option<UpstreamPermit> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+        if self.queue_timeout.is_zero() {
+            return None;
+        }
+
+        self.waiting.fetch_add(1, Ordering::AcqRel);
+        let _guard = WaitingGuard(&self.waiting);
+        tokio::time::timeout(self.queue_timeout, self.semaphore.clone().acquire_owned())
+            .await
+            .ok()?
+            .ok()
+    }
+
+    /// 建议客户端的重试等待时间（秒，按排队上限向上取整，至少 1 秒）
+    pub fn retry_after_secs(&self) -> u64 {
+        self.queue_timeout.as_secs_f64().ceil().max(1.0) as u64
+    }
+
+    /// 并发上限
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// 当前并发状态
+    pub fn stats(&self) -> UpstreamConcurrencyStats {
+        UpstreamConcurrencyStats {
+            max: self.max,
+            in_use: self.max - self.semaphore.available_permits(),
+            waiting: self.waiting.load(Ordering::Acquire),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_acquire_waits_and_times_out() {
+        let limiter = Arc::new(UpstreamConcurrencyLimiter::new(1, 50));
+        let permit = limiter.acquire().await.unwrap();
+        assert_eq!(limiter.stats().in_use, 1);
+
+        // 名额用尽：排队等待，超时返回 None
+        assert!(limiter.acquire().await.is_none());
+        assert_eq!(limiter.stats().waiting, 0);
+
+        // 等待期间归还名额：排队的请求取得名额
+        let waiter = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire().await.is_some() }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(limiter.stats().waiting, 1);
+        drop(permit);
+        assert!(waiter.await.unwrap());
+        assert_eq!(limiter.stats().in_use, 0);
+        assert_eq!(limiter.stats().waiting, 0);
+    }
+
+    #[tokio::test]
+    async fn test_zero_timeout_fails_fast() {
+        let limiter = UpstreamConcurrencyLimiter::new(1, 0);
+        let _permit = limiter.acquire().await.unwrap();
+        assert!(limiter.acquire().await.is_none());
+        assert_eq!(limiter.retry_after_secs(), 1);
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! 负责将 Anthropic API 请求格式转换为 Kiro API 请求格式
 
+use serde::Serialize;
 use uuid::Uuid;
 
 use crate::kiro::model::requests::conversation::{
@@ -38,11 +39,106 @@ pub fn map_model(model: &str) -> Option<String> {
     }
 }
 
+/// 文档内容块默认大小上限（1 MiB）
+pub const DEFAULT_MAX_DOCUMENT_BYTES: usize = 1024 * 1024;
+
+/// 单张图片默认大小上限（5 MiB，按 base64 解码后计算）
+pub const DEFAULT_MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// 单个请求所有图片默认总大小上限（20 MiB）
+pub const DEFAULT_MAX_REQUEST_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// 续写模式下发送给上游的当前消息
+///
+/// Kiro 不支持 assistant 预填充，以 assistant 结尾的对话会把该片段放入历史，
+/// 再用这条指令让上游从片段末尾继续生成，而不是重新回答
+pub const CONTINUATION_PROMPT: &str = "Continue your previous response from exactly where it was \
+    cut off. Do not repeat any text that was already written and do not add any preamble.";
+
+/// 转换选项
+#[derive(Debug, Clone)]
+pub struct ConversionOptions {
+    /// 单个文档内容块的大小上限（字节）
+    pub max_document_bytes: usize,
+    /// 单张图片的大小上限（字节，含 tool_result 中的图片）
+    pub max_image_bytes: usize,
+    /// 单个请求所有图片的总大小上限（字节）
+    pub max_request_image_bytes: usize,
+}
+
+impl Default for ConversionOptions {
+    fn default() -> Self {
+        Self {
+            max_document_bytes: DEFAULT_MAX_DOCUMENT_BYTES,
+            max_image_bytes: DEFAULT_MAX_IMAGE_BYTES,
+            max_request_image_bytes: DEFAULT_MAX_REQUEST_IMAGE_BYTES,
+        }
+    }
+}
+
+/// 转换警告代码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+    /// 不支持或无法解析的内容块类型，已丢弃
+    UnsupportedContentBlock,
+    /// 不支持的图片格式，已丢弃
+    UnsupportedImageFormat,
+    /// Kiro 上游不支持采样参数，已忽略
+    SamplingParamsIgnored,
+    /// 找不到对应 tool_use 的 tool_result，已丢弃
+    OrphanedToolResult,
+    /// 对应 tool_use 已在历史中配对的 tool_result，已丢弃
+    DuplicateToolResult,
+    /// 历史中使用但未在 tools 中定义的工具，已补充占位符定义
+    PlaceholderToolAdded,
+    /// 工具描述超过长度上限，已截断
+    ToolDescriptionTruncated,
+    /// system prompt 改写规则生效
+    SystemPromptRewritten,
+}
+
+impl WarningCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::UnsupportedContentBlock => "unsupported_content_block",
+            Self::UnsupportedImageFormat => "unsupported_image_format",
+            Self::SamplingParamsIgnored => "sampling_params_ignored",
+            Self::OrphanedToolResult => "orphaned_tool_result",
+            Self::DuplicateToolResult => "duplicate_tool_result",
+            Self::PlaceholderToolAdded => "placeholder_tool_added",
+            Self::ToolDescriptionTruncated => "tool_description_truncated",
+            Self::SystemPromptRewritten => "system_prompt_rewritten",
+        }
+    }
+}
+
+/// 非致命的转换警告（请求仍可发送，但部分内容被丢弃或改写）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConversionWarning {
+    pub code: WarningCode,
+    pub message: String,
+    /// 触发警告的请求字段路径，如 `messages[1].content[2]`
+    pub path: String,
+}
+
+impl ConversionWarning {
+    pub fn new(code: WarningCode, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            path: path.into(),
+        }
+    }
+}
+
 /// 转换结果
 #[derive(Debug)]
 pub struct ConversionResult {
     /// 转换后的 Kiro 请求
     pub conversation_state: ConversationState,
+    /// 转换过程中的非致命警告
+    pub warnings: Vec<ConversionWarning>,
 }
 
 /// 转换错误
@@ -50,6 +146,42 @@ pub struct ConversionResult {
 pub enum ConversionError {
     UnsupportedModel(String),
     EmptyMessages,
+    /// 文档内容块无效（不支持的来源/媒体类型、超出大小上限等）
+    InvalidDocument {
+        message_index: usize,
+        block_index: usize,
+        reason: String,
+    },
+    /// 图片内容块超出单张大小上限
+    ImageTooLarge {
+        message_index: usize,
+        block_index: usize,
+        size: usize,
+        max_bytes: usize,
+    },
+    /// 请求中所有图片总大小超出上限
+    RequestImagesTooLarge {
+        total: usize,
+        max_bytes: usize,
+    },
+    /// 采样参数超出取值范围
+    InvalidSamplingParameter {
+        name: &'static str,
+        value: String,
+        range: &'static str,
+    },
+    /// 末尾 assistant 片段包含 tool_use（上游无法续写工具调用）
+    ContinuationToolUse {
+        message_index: usize,
+    },
+    /// 末尾 assistant 片段前没有 user 消息
+    ContinuationWithoutUser {
+        message_index: usize,
+    },
+    /// 末尾 assistant 片段没有可续写的内容
+    ContinuationEmpty {
+        message_index: usize,
+    },
 }
 
 impl std::fmt::Display for ConversionError {
@@ -57,6 +189,48 @@ impl std::fmt::Display for ConversionError {
         match self {
             ConversionError::UnsupportedModel(model) => write!(f, "模型不支持: {}", model),
             ConversionError::EmptyMessages => write!(f, "消息列表为空"),
+            ConversionError::InvalidDocument {
+                message_index,
+                block_index,
+                reason,
+            } => write!(
+                f,
+                "messages[{}].content[{}] 文档无效: {}",
+                message_index, block_index, reason
+            ),
+            ConversionError::ImageTooLarge {
+                message_index,
+                block_index,
+                size,
+                max_bytes,
+            } => write!(
+                f,
+                "messages[{}].content[{}] 图片大小 {} 字节超过上限 {} 字节",
+                message_index, block_index, size, max_bytes
+            ),
+            ConversionError::RequestImagesTooLarge { total, max_bytes } => write!(
+                f,
+                "请求图片总大小 {} 字节超过上限 {} 字节",
+                total, max_bytes
+            ),
+            ConversionError::InvalidSamplingParameter { name, value, range } => {
+                write!(f, "{} 超出取值范围 {}: {}", name, range, value)
+            }
+            ConversionError::ContinuationToolUse { message_index } => write!(
+                f,
+                "messages[{}] 末尾的 assistant 消息包含 tool_use，无法续写",
+                message_index
+            ),
+            ConversionError::ContinuationWithoutUser { message_index } => write!(
+                f,
+                "messages[{}] 末尾的 assistant 消息必须紧跟在 user 消息之后",
+                message_index
+            ),
+            ConversionError::ContinuationEmpty { message_index } => write!(
+                f,
+                "messages[{}] 末尾的 assistant 消息为空，没有可续写的内容",
+                message_index
+            ),
         }
     }
 }
@@ -122,7 +296,10 @@ fn create_placeholder_tool(name: &str) -> Tool {
 }
 
 /// 将 Anthropic 请求转换为 Kiro 请求
-pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, ConversionError> {
+pub fn convert_request(
+    req: &MessagesRequest,
+    options: &ConversionOptions,
+) -> Result<ConversionResult, ConversionError> {
     // 1. 映射模型
     let model_id = map_model(&req.model)
         .ok_or_else(|| ConversionError::UnsupportedModel(req.model.clone()))?;
@@ -132,6 +309,47 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
         return Err(ConversionError::EmptyMessages);
     }
 
+    // 2.1 检查文档内容块（后续转换时直接内联文本）
+    validate_documents(req, options.max_document_bytes)?;
+
+    // 2.2 检查图片大小（含 tool_result 中嵌套的图片）
+    validate_images(
+        req,
+        options.max_image_bytes,
+        options.max_request_image_bytes,
+    )?;
+
+    // 2.3 以 assistant 结尾时为续写模式（max_tokens 截断后继续生成）
+    let continuation = req.messages.last().is_some_and(|m| m.role == "assistant");
+    if continuation {
+        validate_continuation(req)?;
+    }
+
+    let mut warnings = Vec::new();
+
+    // 2.4 检查采样参数（Kiro 上游不支持采样参数，校验通过后不转发）
+    validate_sampling(req)?;
+    if req.has_sampling_params() {
+        tracing::debug!(
+            temperature = ?req.temperature,
+            top_p = ?req.top_p,
+            top_k = ?req.top_k,
+            "Kiro 上游不支持采样参数，已忽略"
+        );
+        let params = [
+            ("temperature", req.temperature.is_some()),
+            ("top_p", req.top_p.is_some()),
+            ("top_k", req.top_k.is_some()),
+        ];
+        for (name, _) in params.into_iter().filter(|(_, set)| *set) {
+            warnings.push(ConversionWarning::new(
+                WarningCode::SamplingParamsIgnored,
+                name,
+                format!("Kiro 上游不支持 {}，已忽略", name),
+            ));
+        }
+    }
+
     // 3. 生成会话 ID 和代理 ID
     // 优先从 metadata.user_id 中提取 session UUID 作为 conversationId
     let conversation_id = req
@@ -146,18 +364,32 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
     let chat_trigger_type = determine_chat_trigger_type(req);
 
     // 5. 处理最后一条消息作为 current_message
-    let last_message = req.messages.last().unwrap();
-    let (text_content, images, tool_results) = process_message_content(&last_message.content)?;
+    // 续写模式下末尾 assistant 片段进入历史，当前消息为续写指令
+    let (text_content, images, tool_results) = if continuation {
+        (CONTINUATION_PROMPT.to_string(), Vec::new(), Vec::new())
+    } else {
+        let message_index = req.messages.len() - 1;
+        process_message_content(
+            &req.messages[message_index].content,
+            message_index,
+            &mut warnings,
+        )?
+    };
 
     // 6. 转换工具定义
-    let mut tools = convert_tools(&req.tools);
+    let mut tools = convert_tools(&req.tools, &mut warnings);
 
     // 7. 构建历史消息（需要先构建，以便收集历史中使用的工具）
-    let history = build_history(req, &model_id)?;
+    let history = build_history(req, &model_id, &mut warnings)?;
 
     // 8. 验证并过滤 tool_use/tool_result 配对
     // 移除孤立的 tool_result（没有对应的 tool_use）
-    let validated_tool_results = validate_tool_pairing(&history, &tool_results);
+    let validated_tool_results = validate_tool_pairing(
+        &history,
+        &tool_results,
+        req.messages.len() - 1,
+        &mut warnings,
+    );
 
     // 9. 收集历史中使用的工具名称，为缺失的工具生成占位符定义
     // Kiro API 要求：历史消息中引用的工具必须在 tools 列表中有定义
@@ -170,6 +402,14 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
 
     for tool_name in history_tool_names {
         if !existing_tool_names.contains(&tool_name.to_lowercase()) {
+            warnings.push(ConversionWarning::new(
+                WarningCode::PlaceholderToolAdded,
+                "tools",
+                format!(
+                    "历史中使用的工具 {} 未在 tools 中定义，已补充占位符定义",
+                    tool_name
+                ),
+            ));
             tools.push(create_placeholder_tool(&tool_name));
         }
     }
@@ -205,7 +445,10 @@ pub fn convert_request(req: &MessagesRequest) -> Result<ConversionResult, Conver
         .with_current_message(current_message)
         .with_history(history);
 
-    Ok(ConversionResult { conversation_state })
+    Ok(ConversionResult {
+        conversation_state,
+        warnings,
+    })
 }
 
 /// 确定聊天触发类型
@@ -215,8 +458,12 @@ fn determine_chat_trigger_type(_req: &MessagesRequest) -> String {
 }
 
 /// 处理消息内容，提取文本、图片和工具结果
+///
+/// 无法转换的内容块（未知类型、不支持的图片格式）被丢弃并记录到 `warnings`
 fn process_message_content(
     content: &serde_json::Value,
+    message_index: usize,
+    warnings: &mut Vec<ConversionWarning>,
 ) -> Result<(String, Vec<KiroImage>, Vec<ToolResult>), ConversionError> {
     let mut text_parts = Vec::new();
     let mut images = Vec::new();
@@ -227,9 +474,18 @@ fn process_message_content(
             text_parts.push(s.clone());
         }
         serde_json::Value::Array(arr) => {
-            for item in arr {
-                if let Ok(block) = serde_json::from_value::<ContentBlock>(item.clone()) {
-                    match block.block_type.as_str() {
+            for (block_index, item) in arr.iter().enumerate() {
+                let path = format!("messages[{}].content[{}]", message_index, block_index);
+                // document 的 source 结构与图片不同，需在 ContentBlock 解析前单独处理
+                if is_document_block(item) {
+                    // 已在 validate_documents 中校验
+                    if let Ok(text) = extract_document_text(item, usize::MAX) {
+                        text_parts.push(text);
+                    }
+                    continue;
+                }
+                match serde_json::from_value::<ContentBlock>(item.clone()) {
+                    Ok(block) => match block.block_type.as_str() {
                         "text" => {
                             if let Some(text) = block.text {
                                 text_parts.push(text);
@@ -237,12 +493,22 @@ fn process_message_content(
                         }
                         "image" => {
                             if let Some(source) = block.source {
-                                if let Some(format) = get_image_format(&source.media_type) {
-                                    images.push(KiroImage::from_base64(format, source.data));
+                                match get_image_format(&source.media_type) {
+                                    Some(format) => {
+                                        images.push(KiroImage::from_base64(format, source.data))
+                                    }
+                                    None => warnings
+                                        .push(unsupported_image_warning(path, &source.media_type)),
                                 }
                             }
                         }
                         "tool_result" => {
+                            // Kiro 的 toolResults 只接受文本，嵌套图片（如截图）随当前消息的图片一起发送
+                            images.extend(extract_tool_result_images(
+                                &block.content,
+                                &path,
+                                warnings,
+                            ));
                             if let Some(tool_use_id) = block.tool_use_id {
                                 let result_content = extract_tool_result_content(&block.content);
                                 let is_error = block.is_error.unwrap_or(false);
@@ -261,8 +527,9 @@ fn process_message_content(
                         "tool_use" => {
                             // tool_use 在 assistant 消息中处理，这里忽略
                         }
-                        _ => {}
-                    }
+                        other => warnings.push(unsupported_block_warning(path, other)),
+                    },
+                    Err(e) => warnings.push(invalid_block_warning(path, &e)),
                 }
             }
         }
@@ -272,6 +539,217 @@ fn process_message_content(
     Ok((text_parts.join("\n"), images, tool_results))
 }
 
+/// 不支持的内容块类型警告
+fn unsupported_block_warning(path: String, block_type: &str) -> ConversionWarning {
+    ConversionWarning::new(
+        WarningCode::UnsupportedContentBlock,
+        path,
+        format!("Kiro 不支持 {} 内容块，已丢弃", block_type),
+    )
+}
+
+/// 无法解析的内容块警告
+fn invalid_block_warning(path: String, error: &serde_json::Error) -> ConversionWarning {
+    ConversionWarning::new(
+        WarningCode::UnsupportedContentBlock,
+        path,
+        format!("内容块无法解析，已丢弃: {}", error),
+    )
+}
+
+/// 不支持的图片格式警告
+fn unsupported_image_warning(path: String, media_type: &str) -> ConversionWarning {
+    ConversionWarning::new(
+        WarningCode::UnsupportedImageFormat,
+        path,
+        format!("不支持的图片格式 {}，已丢弃", media_type),
+    )
+}
+
+/// 是否为 document 内容块
+fn is_document_block(item: &serde_json::Value) -> bool {
+    item.get("type").and_then(|v| v.as_str()) == Some("document")
+}
+
+/// 校验所有消息中的 document 内容块
+fn validate_documents(req: &MessagesRequest, max_document_bytes: usize) -> Result<(), ConversionError> {
+    for (message_index, msg) in req.messages.iter().enumerate() {
+        let Some(blocks) = msg.content.as_array() else {
+            continue;
+        };
+        for (block_index, item) in blocks.iter().enumerate() {
+            if !is_document_block(item) {
+                continue;
+            }
+            extract_document_text(item, max_document_bytes).map_err(|reason| {
+                ConversionError::InvalidDocument {
+                    message_index,
+                    block_index,
+                    reason,
+                }
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// 提取 document 内容块的文本
+///
+/// 仅支持纯文本来源（`text` 和 `content`），Kiro 不接受 PDF 等二进制文档；
+/// 提取的文本以 `<document>` 标签包裹内联到对话中
+fn extract_document_text(item: &serde_json::Value, max_bytes: usize) -> Result<String, String> {
+    let source = item
+        .get("source")
+        .and_then(|v| v.as_object())
+        .ok_or_else(|| "缺少 source".to_string())?;
+    let source_type = source.get("type").and_then(|v| v.as_str()).unwrap_or("");
+
+    let text = match source_type {
+        "text" => {
+            let media_type = source
+                .get("media_type")
+                .and_then(|v| v.as_str())
+                .unwrap_or("text/plain");
+            if media_type != "text/plain" {
+                return Err(format!("不支持的媒体类型: {}（仅支持 text/plain）", media_type));
+            }
+            source
+                .get("data")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| "缺少 source.data".to_string())?
+                .to_string()
+        }
+        "content" => match source.get("content") {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(serde_json::Value::Array(arr)) => arr
+                .iter()
+                .filter(|b| b.get("type").and_then(|v| v.as_str()) == Some("text"))
+                .filter_map(|b| b.get("text").and_then(|v| v.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => return Err("缺少 source.content".to_string()),
+        },
+        "base64" => {
+            let media_type = source
+                .get("media_type")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown");
+            return Err(format!("不支持的媒体类型: {}（仅支持纯文本文档）", media_type));
+        }
+        other => return Err(format!("不支持的文档来源类型: {}", other)),
+    };
+
+    if text.len() > max_bytes {
+        return Err(format!(
+            "文档大小 {} 字节超过上限 {} 字节",
+            text.len(),
+            max_bytes
+        ));
+    }
+
+    let mut result = String::from("<document");
+    if let Some(title) = item.get("title").and_then(|v| v.as_str()) {
+        result.push_str(&format!(" title=\"{}\"", title.replace('"', "'")));
+    }
+    result.push_str(">\n");
+    if let Some(context) = item.get("context").and_then(|v| v.as_str()) {
+        result.push_str(context);
+        result.push('\n');
+    }
+    result.push_str(&text);
+    result.push_str("\n</document>");
+    Ok(result)
+}
+
+/// 校验所有消息中的图片大小（含 tool_result 中嵌套的图片）
+fn validate_images(
+    req: &MessagesRequest,
+    max_image_bytes: usize,
+    max_request_image_bytes: usize,
+) -> Result<(), ConversionError> {
+    let mut total = 0;
+    for (message_index, msg) in req.messages.iter().enumerate() {
+        let Some(blocks) = msg.content.as_array() else {
+            continue;
+        };
+        for (block_index, item) in blocks.iter().enumerate() {
+            for data in image_data_in_block(item) {
+                let size = base64_decoded_len(data);
+                if size > max_image_bytes {
+                    return Err(ConversionError::ImageTooLarge {
+                        message_index,
+                        block_index,
+                        size,
+                        max_bytes: max_image_bytes,
+                    });
+                }
+                total += size;
+            }
+        }
+    }
+    if total > max_request_image_bytes {
+        return Err(ConversionError::RequestImagesTooLarge {
+            total,
+            max_bytes: max_request_image_bytes,
+        });
+    }
+    Ok(())
+}
+
+/// 内容块中所有图片的 base64 数据（image 块本身或 tool_result 中嵌套的 image 块）
+fn image_data_in_block(item: &serde_json::Value) -> Vec<&str> {
+    fn image_data(block: &serde_json::Value) -> Option<&str> {
+        if block.get("type").and_then(|v| v.as_str()) != Some("image") {
+            return None;
+        }
+        block.get("source")?.get("data")?.as_str()
+    }
+
+    match item.get("type").and_then(|v| v.as_str()) {
+        Some("image") => image_data(item).into_iter().collect(),
+        Some("tool_result") => item
+            .get("content")
+            .and_then(|v| v.as_array())
+            .map(|arr| arr.iter().filter_map(image_data).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// base64 数据解码后的字节数（不实际解码）
+fn base64_decoded_len(data: &str) -> usize {
+    let padding = data.bytes().rev().take_while(|b| *b == b'=').count();
+    (data.len() / 4 * 3 + data.len() % 4 * 3 / 4).saturating_sub(padding)
+}
+
+/// 提取 tool_result 内容数组中的图片
+fn extract_tool_result_images(
+    content: &Option<serde_json::Value>,
+    path: &str,
+    warnings: &mut Vec<ConversionWarning>,
+) -> Vec<KiroImage> {
+    let Some(serde_json::Value::Array(arr)) = content else {
+        return Vec::new();
+    };
+    let mut images = Vec::new();
+    for (index, item) in arr.iter().enumerate() {
+        let Ok(block) = serde_json::from_value::<ContentBlock>(item.clone()) else {
+            continue;
+        };
+        let Some(source) = block.source.filter(|_| block.block_type == "image") else {
+            continue;
+        };
+        match get_image_format(&source.media_type) {
+            Some(format) => images.push(KiroImage::from_base64(format, source.data)),
+            None => warnings.push(unsupported_image_warning(
+                format!("{}.content[{}]", path, index),
+                &source.media_type,
+            )),
+        }
+    }
+    images
+}
+
 /// 从 media_type 获取图片格式
 fn get_image_format(media_type: &str) -> Option<String> {
     match media_type {
@@ -301,6 +779,66 @@ fn extract_tool_result_content(content: &Option<serde_json::Value>) -> String {
     }
 }
 
+/// 检查采样参数是否在 Anthropic API 允许的范围内
+fn validate_sampling(req: &MessagesRequest) -> Result<(), ConversionError> {
+    let unit_range = |name: &'static str, value: Option<f64>| match value {
+        Some(v) if !(0.0..=1.0).contains(&v) => Err(ConversionError::InvalidSamplingParameter {
+            name,
+            value: v.to_string(),
+            range: "[0, 1]",
+        }),
+        _ => Ok(()),
+    };
+    unit_range("temperature", req.temperature)?;
+    unit_range("top_p", req.top_p)?;
+
+    match req.top_k {
+        Some(k) if k < 0 => Err(ConversionError::InvalidSamplingParameter {
+            name: "top_k",
+            value: k.to_string(),
+            range: ">= 0",
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// 校验续写模式（最后一条消息为 assistant）
+///
+/// 支持：紧跟在 user 消息之后、只包含 text/thinking 的 assistant 片段；
+/// 不支持：包含 tool_use 的片段（上游无法续写工具调用）和空片段
+fn validate_continuation(req: &MessagesRequest) -> Result<(), ConversionError> {
+    let message_index = req.messages.len() - 1;
+    if message_index == 0 || req.messages[message_index - 1].role != "user" {
+        return Err(ConversionError::ContinuationWithoutUser { message_index });
+    }
+
+    let has_content = match &req.messages[message_index].content {
+        serde_json::Value::String(s) => !s.trim().is_empty(),
+        serde_json::Value::Array(blocks) => {
+            let mut has_content = false;
+            for block in blocks {
+                let field = match block.get("type").and_then(|v| v.as_str()) {
+                    Some("tool_use") => {
+                        return Err(ConversionError::ContinuationToolUse { message_index });
+                    }
+                    Some(field @ ("text" | "thinking")) => field,
+                    _ => continue,
+                };
+                has_content |= block
+                    .get(field)
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|s| !s.trim().is_empty());
+            }
+            has_content
+        }
+        _ => false,
+    };
+    if !has_content {
+        return Err(ConversionError::ContinuationEmpty { message_index });
+    }
+    Ok(())
+}
+
 /// 验证并过滤 tool_use/tool_result 配对
 ///
 /// 收集所有 tool_use_id，验证 tool_result 是否匹配
@@ -312,7 +850,12 @@ fn extract_tool_result_content(content: &Option<serde_json::Value>) -> String {
 ///
 /// # Returns
 /// 经过验证和过滤后的 tool_result 列表
-fn validate_tool_pairing(history: &[Message], tool_results: &[ToolResult]) -> Vec<ToolResult> {
+fn validate_tool_pairing(
+    history: &[Message],
+    tool_results: &[ToolResult],
+    message_index: usize,
+    warnings: &mut Vec<ConversionWarning>,
+) -> Vec<ToolResult> {
     use std::collections::HashSet;
 
     // 1. 收集所有历史中的 tool_use_id
@@ -362,12 +905,28 @@ fn validate_tool_pairing(history: &[Message], tool_results: &[ToolResult]) -> Ve
                 "跳过重复的 tool_result：该 tool_use 已在历史中配对，tool_use_id={}",
                 result.tool_use_id
             );
+            warnings.push(ConversionWarning::new(
+                WarningCode::DuplicateToolResult,
+                format!("messages[{}]", message_index),
+                format!(
+                    "tool_use {} 已在历史中配对，重复的 tool_result 已丢弃",
+                    result.tool_use_id
+                ),
+            ));
         } else {
             // 孤立 tool_result - 找不到对应的 tool_use
             tracing::warn!(
                 "跳过孤立的 tool_result：找不到对应的 tool_use，tool_use_id={}",
                 result.tool_use_id
             );
+            warnings.push(ConversionWarning::new(
+                WarningCode::OrphanedToolResult,
+                format!("messages[{}]", message_index),
+                format!(
+                    "找不到 tool_result 对应的 tool_use {}，已丢弃",
+                    result.tool_use_id
+                ),
+            ));
         }
     }
 
@@ -382,19 +941,36 @@ fn validate_tool_pairing(history: &[Message], tool_results: &[ToolResult]) -> Ve
     filtered_results
 }
 
+/// 工具描述长度上限（字符）
+const MAX_TOOL_DESCRIPTION_CHARS: usize = 10000;
+
 /// 转换工具定义
-fn convert_tools(tools: &Option<Vec<super::types::Tool>>) -> Vec<Tool> {
+fn convert_tools(
+    tools: &Option<Vec<super::types::Tool>>,
+    warnings: &mut Vec<ConversionWarning>,
+) -> Vec<Tool> {
     let Some(tools) = tools else {
         return Vec::new();
     };
 
     tools
         .iter()
-        .map(|t| {
+        .enumerate()
+        .map(|(index, t)| {
             let description = t.description.clone();
             // 限制描述长度为 10000 字符（安全截断 UTF-8，单次遍历）
-            let description = match description.char_indices().nth(10000) {
-                Some((idx, _)) => description[..idx].to_string(),
+            let description = match description.char_indices().nth(MAX_TOOL_DESCRIPTION_CHARS) {
+                Some((idx, _)) => {
+                    warnings.push(ConversionWarning::new(
+                        WarningCode::ToolDescriptionTruncated,
+                        format!("tools[{}].description", index),
+                        format!(
+                            "工具 {} 的描述超过 {} 字符，已截断",
+                            t.name, MAX_TOOL_DESCRIPTION_CHARS
+                        ),
+                    ));
+                    description[..idx].to_string()
+                }
                 None => description,
             };
 
@@ -438,13 +1014,17 @@ fn has_thinking_tags(content: &str) -> bool {
 }
 
 /// 构建历史消息
-fn build_history(req: &MessagesRequest, model_id: &str) -> Result<Vec<Message>, ConversionError> {
+fn build_history(
+    req: &MessagesRequest,
+    model_id: &str,
+    warnings: &mut Vec<ConversionWarning>,
+) -> Result<Vec<Message>, ConversionError> {
     let mut history = Vec::new();
 
     // 生成thinking前缀（如果需要）
     let thinking_prefix = generate_thinking_prefix(req);
 
-    // 1. 处理系统消息
+    // 1. 处理系统消息（只取文本，cache_control 标记不转发，Kiro 请求格式不支持）
     if let Some(ref system) = req.system {
         let system_content: String = system
             .iter()
@@ -484,7 +1064,7 @@ fn build_history(req: &MessagesRequest, model_id: &str) -> Result<Vec<Message>,
     // 最后一条消息作为 currentMessage，不加入历史
     let history_end_index = req.messages.len().saturating_sub(1);
 
-    // 如果最后一条是 assistant，则包含在历史中
+    // 如果最后一条是 assistant（续写模式），则包含在历史中
     let last_is_assistant = req
         .messages
         .last()
@@ -498,22 +1078,22 @@ fn build_history(req: &MessagesRequest, model_id: &str) -> Result<Vec<Message>,
     };
 
     // 收集并配对消息
-    let mut user_buffer: Vec<&super::types::Message> = Vec::new();
+    let mut user_buffer: Vec<(usize, &super::types::Message)> = Vec::new();
 
     for i in 0..history_end_index {
         let msg = &req.messages[i];
 
         if msg.role == "user" {
-            user_buffer.push(msg);
+            user_buffer.push((i, msg));
         } else if msg.role == "assistant" {
             // 遇到 assistant，处理累积的 user 消息
             if !user_buffer.is_empty() {
-                let merged_user = merge_user_messages(&user_buffer, model_id)?;
+                let merged_user = merge_user_messages(&user_buffer, model_id, warnings)?;
                 history.push(Message::User(merged_user));
                 user_buffer.clear();
 
                 // 添加 assistant 消息
-                let assistant = convert_assistant_message(msg)?;
+                let assistant = convert_assistant_message(msg, i, warnings)?;
                 history.push(Message::Assistant(assistant));
             }
         }
@@ -521,7 +1101,7 @@ fn build_history(req: &MessagesRequest, model_id: &str) -> Result<Vec<Message>,
 
     // 处理结尾的孤立 user 消息
     if !user_buffer.is_empty() {
-        let merged_user = merge_user_messages(&user_buffer, model_id)?;
+        let merged_user = merge_user_messages(&user_buffer, model_id, warnings)?;
         history.push(Message::User(merged_user));
 
         // 自动配对一个 "OK" 的 assistant 响应
@@ -532,17 +1112,19 @@ fn build_history(req: &MessagesRequest, model_id: &str) -> Result<Vec<Message>,
     Ok(history)
 }
 
-/// 合并多个 user 消息
+/// 合并多个 user 消息（附带各自在请求中的下标）
 fn merge_user_messages(
-    messages: &[&super::types::Message],
+    messages: &[(usize, &super::types::Message)],
     model_id: &str,
+    warnings: &mut Vec<ConversionWarning>,
 ) -> Result<HistoryUserMessage, ConversionError> {
     let mut content_parts = Vec::new();
     let mut all_images = Vec::new();
     let mut all_tool_results = Vec::new();
 
-    for msg in messages {
-        let (text, images, tool_results) = process_message_content(&msg.content)?;
+    for (message_index, msg) in messages {
+        let (text, images, tool_results) =
+            process_message_content(&msg.content, *message_index, warnings)?;
         if !text.is_empty() {
             content_parts.push(text);
         }
@@ -572,6 +1154,8 @@ fn merge_user_messages(
 /// 转换 assistant 消息
 fn convert_assistant_message(
     msg: &super::types::Message,
+    message_index: usize,
+    warnings: &mut Vec<ConversionWarning>,
 ) -> Result<HistoryAssistantMessage, ConversionError> {
     let mut thinking_content = String::new();
     let mut text_content = String::new();
@@ -582,9 +1166,10 @@ fn convert_assistant_message(
             text_content = s.clone();
         }
         serde_json::Value::Array(arr) => {
-            for item in arr {
-                if let Ok(block) = serde_json::from_value::<ContentBlock>(item.clone()) {
-                    match block.block_type.as_str() {
+            for (block_index, item) in arr.iter().enumerate() {
+                let path = format!("messages[{}].content[{}]", message_index, block_index);
+                match serde_json::from_value::<ContentBlock>(item.clone()) {
+                    Ok(block) => match block.block_type.as_str() {
                         "thinking" => {
                             if let Some(thinking) = block.thinking {
                                 thinking_content.push_str(&thinking);
@@ -601,8 +1186,9 @@ fn convert_assistant_message(
                                 tool_uses.push(ToolUseEntry::new(id, name).with_input(input));
                             }
                         }
-                        _ => {}
-                    }
+                        other => warnings.push(unsupported_block_warning(path, other)),
+                    },
+                    Err(e) => warnings.push(invalid_block_warning(path, &e)),
                 }
             }
         }
@@ -691,6 +1277,9 @@ mod tests {
             tool_choice: None,
             thinking: None,
             output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
             metadata: None,
         };
         assert_eq!(determine_chat_trigger_type(&req), "MANUAL");
@@ -770,10 +1359,13 @@ mod tests {
             tool_choice: None,
             thinking: None,
             output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
             metadata: None,
         };
 
-        let result = convert_request(&req).unwrap();
+        let result = convert_request(&req, &ConversionOptions::default()).unwrap();
 
         // 验证 tools 列表中包含了历史中使用的工具的占位符定义
         let tools = &result
@@ -835,6 +1427,9 @@ mod tests {
             tool_choice: None,
             thinking: None,
             output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
             metadata: Some(Metadata {
                 user_id: Some(
                     "user_0dede55c6dcc4a11a30bbb5e7f22e6fdf86cdeba3820019cc27612af4e1243cd_account__session_a0662283-7fd3-4399-a7eb-52b9a717ae88".to_string(),
@@ -842,7 +1437,7 @@ mod tests {
             }),
         };
 
-        let result = convert_request(&req).unwrap();
+        let result = convert_request(&req, &ConversionOptions::default()).unwrap();
         assert_eq!(
             result.conversation_state.conversation_id,
             "a0662283-7fd3-4399-a7eb-52b9a717ae88"
@@ -867,10 +1462,13 @@ mod tests {
             tool_choice: None,
             thinking: None,
             output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
             metadata: None,
         };
 
-        let result = convert_request(&req).unwrap();
+        let result = convert_request(&req, &ConversionOptions::default()).unwrap();
         // 验证生成的是有效的 UUID 格式
         assert_eq!(result.conversation_state.conversation_id.len(), 36);
         assert_eq!(
@@ -895,10 +1493,14 @@ mod tests {
 
         let tool_results = vec![ToolResult::success("orphan-123", "some result")];
 
-        let filtered = validate_tool_pairing(&history, &tool_results);
+        let mut warnings = Vec::new();
+        let filtered = validate_tool_pairing(&history, &tool_results, 2, &mut warnings);
 
         // 孤立的 tool_result 应该被过滤掉
         assert!(filtered.is_empty(), "孤立的 tool_result 应该被过滤");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::OrphanedToolResult);
+        assert_eq!(warnings[0].path, "messages[2]");
     }
 
     #[test]
@@ -925,7 +1527,7 @@ mod tests {
         // 没有 tool_result
         let tool_results: Vec<ToolResult> = vec![];
 
-        let filtered = validate_tool_pairing(&history, &tool_results);
+        let filtered = validate_tool_pairing(&history, &tool_results, 0, &mut Vec::new());
 
         // 结果应该为空（因为没有 tool_result）
         // 同时应该输出警告日志（孤立的 tool_use）
@@ -955,7 +1557,7 @@ mod tests {
 
         let tool_results = vec![ToolResult::success("tool-1", "file content")];
 
-        let filtered = validate_tool_pairing(&history, &tool_results);
+        let filtered = validate_tool_pairing(&history, &tool_results, 0, &mut Vec::new());
 
         // 配对成功，应该保留
         assert_eq!(filtered.len(), 1);
@@ -986,7 +1588,7 @@ mod tests {
             ToolResult::success("tool-3", "orphan result"), // 孤立
         ];
 
-        let filtered = validate_tool_pairing(&history, &tool_results);
+        let filtered = validate_tool_pairing(&history, &tool_results, 0, &mut Vec::new());
 
         // 只有 tool-1 应该保留
         assert_eq!(filtered.len(), 1);
@@ -1033,7 +1635,7 @@ mod tests {
         // 当前消息没有 tool_results（用户只是继续对话）
         let tool_results: Vec<ToolResult> = vec![];
 
-        let filtered = validate_tool_pairing(&history, &tool_results);
+        let filtered = validate_tool_pairing(&history, &tool_results, 0, &mut Vec::new());
 
         // 结果应该为空，且不应该有孤立 tool_use 的警告
         // 因为 tool-1 已经在历史中配对了
@@ -1074,10 +1676,13 @@ mod tests {
         // 当前消息又发送了相同的 tool_result（重复）
         let tool_results = vec![ToolResult::success("tool-1", "file content again")];
 
-        let filtered = validate_tool_pairing(&history, &tool_results);
+        let mut warnings = Vec::new();
+        let filtered = validate_tool_pairing(&history, &tool_results, 0, &mut warnings);
 
         // 重复的 tool_result 应该被过滤掉
         assert!(filtered.is_empty(), "重复的 tool_result 应该被过滤");
+        let codes: Vec<_> = warnings.iter().map(|w| w.code).collect();
+        assert_eq!(codes, vec![WarningCode::DuplicateToolResult]);
     }
 
     #[test]
@@ -1093,7 +1698,7 @@ mod tests {
             ]),
         };
 
-        let result = convert_assistant_message(&msg).expect("应该成功转换");
+        let result = convert_assistant_message(&msg, 0, &mut Vec::new()).expect("应该成功转换");
 
         // 验证 content 不为空（使用占位符）
         assert!(
@@ -1128,7 +1733,7 @@ mod tests {
             ]),
         };
 
-        let result = convert_assistant_message(&msg).expect("应该成功转换");
+        let result = convert_assistant_message(&msg, 0, &mut Vec::new()).expect("应该成功转换");
 
         // 验证 content 使用原始文本（不是占位符）
         assert_eq!(
@@ -1144,4 +1749,502 @@ mod tests {
         assert_eq!(tool_uses.len(), 1);
         assert_eq!(tool_uses[0].tool_use_id, "toolu_02XYZ");
     }
+
+    fn create_document_request(content: serde_json::Value) -> MessagesRequest {
+        use super::super::types::Message as AnthropicMessage;
+
+        MessagesRequest {
+            model: "claude-sonnet-4".to_string(),
+            max_tokens: 1024,
+            messages: vec![AnthropicMessage {
+                role: "user".to_string(),
+                content,
+            }],
+            stream: false,
+            system: None,
+            tools: None,
+            tool_choice: None,
+            thinking: None,
+            output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_convert_request_with_text_document() {
+        let req = create_document_request(serde_json::json!([
+            {
+                "type": "document",
+                "source": {"type": "text", "media_type": "text/plain", "data": "The grass is green."},
+                "title": "Facts",
+                "citations": {"enabled": true}
+            },
+            {"type": "text", "text": "What color is the grass?"}
+        ]));
+
+        let result = convert_request(&req, &ConversionOptions::default()).unwrap();
+        let content = &result
+            .conversation_state
+            .current_message
+            .user_input_message
+            .content;
+
+        assert_eq!(
+            content,
+            "<document title=\"Facts\">\nThe grass is green.\n</document>\nWhat color is the grass?"
+        );
+    }
+
+    #[test]
+    fn test_convert_request_with_oversized_document() {
+        let req = create_document_request(serde_json::json!([
+            {"type": "text", "text": "Summarize:"},
+            {"type": "document", "source": {"type": "text", "media_type": "text/plain", "data": "a".repeat(11)}}
+        ]));
+        let options = ConversionOptions {
+            max_document_bytes: 10,
+            ..ConversionOptions::default()
+        };
+
+        let err = convert_request(&req, &options).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversionError::InvalidDocument {
+                message_index: 0,
+                block_index: 1,
+                ..
+            }
+        ));
+        assert!(err.to_string().contains("超过上限 10 字节"));
+    }
+
+    #[test]
+    fn test_convert_request_with_unsupported_document_media_type() {
+        let req = create_document_request(serde_json::json!([
+            {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERi0xLjQ="}}
+        ]));
+
+        let err = convert_request(&req, &ConversionOptions::default()).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("messages[0].content[0]"), "{}", message);
+        assert!(message.contains("application/pdf"), "{}", message);
+    }
+
+    /// 构造以 assistant 片段结尾的续写请求
+    fn create_continuation_request(fragment: serde_json::Value) -> MessagesRequest {
+        use super::super::types::Message as AnthropicMessage;
+
+        let mut req = create_document_request(serde_json::json!("Write a long story"));
+        req.messages.push(AnthropicMessage {
+            role: "assistant".to_string(),
+            content: fragment,
+        });
+        req
+    }
+
+    #[test]
+    fn test_convert_request_continues_trailing_assistant() {
+        let req = create_continuation_request(serde_json::json!([
+            {"type": "text", "text": "Once upon a time, there was a"}
+        ]));
+
+        let result = convert_request(&req, &ConversionOptions::default()).unwrap();
+        let state = result.conversation_state;
+
+        // 片段作为历史中最后一个 assistant 回合，前面是原始 user 消息
+        let [.., Message::User(user), Message::Assistant(fragment)] = state.history.as_slice()
+        else {
+            panic!("历史应以 user + assistant 片段结尾");
+        };
+        assert_eq!(user.user_input_message.content, "Write a long story");
+        assert_eq!(
+            fragment.assistant_response_message.content,
+            "Once upon a time, there was a"
+        );
+
+        // 当前消息为续写指令，而不是重复片段内容
+        assert_eq!(
+            state.current_message.user_input_message.content,
+            CONTINUATION_PROMPT
+        );
+    }
+
+    #[test]
+    fn test_convert_request_rejects_unsupported_continuation() {
+        // text + 未完成的 tool_use：上游无法续写工具调用
+        let req = create_continuation_request(serde_json::json!([
+            {"type": "text", "text": "Let me check the file."},
+            {"type": "tool_use", "id": "tool-1", "name": "read_file", "input": {}}
+        ]));
+        let err = convert_request(&req, &ConversionOptions::default()).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversionError::ContinuationToolUse { message_index: 1 }
+        ));
+
+        // 空片段
+        let req = create_continuation_request(serde_json::json!([{"type": "text", "text": " "}]));
+        let err = convert_request(&req, &ConversionOptions::default()).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversionError::ContinuationEmpty { message_index: 1 }
+        ));
+
+        // 只有 assistant 消息，没有对应的 user 消息
+        let mut req = create_continuation_request(serde_json::json!("Once upon"));
+        req.messages.remove(0);
+        let err = convert_request(&req, &ConversionOptions::default()).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversionError::ContinuationWithoutUser { message_index: 0 }
+        ));
+    }
+
+    /// 构造 assistant tool_use + user tool_result 的两轮请求
+    fn create_tool_result_request(tool_result_content: serde_json::Value) -> MessagesRequest {
+        use super::super::types::Message as AnthropicMessage;
+
+        let mut req = create_document_request(serde_json::json!("Take a screenshot"));
+        req.messages.push(AnthropicMessage {
+            role: "assistant".to_string(),
+            content: serde_json::json!([
+                {"type": "tool_use", "id": "tool-1", "name": "screenshot", "input": {}}
+            ]),
+        });
+        req.messages.push(AnthropicMessage {
+            role: "user".to_string(),
+            content: serde_json::json!([
+                {"type": "tool_result", "tool_use_id": "tool-1", "content": tool_result_content}
+            ]),
+        });
+        req
+    }
+
+    #[test]
+    fn test_sampling_params_serde() {
+        let base = serde_json::json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 1024,
+            "messages": [{"role": "user", "content": "Hello"}]
+        });
+        let req: MessagesRequest = serde_json::from_value(base.clone()).unwrap();
+        assert!(!req.has_sampling_params());
+        assert_eq!((req.temperature, req.top_p, req.top_k), (None, None, None));
+
+        let mut with_sampling = base.clone();
+        with_sampling["temperature"] = serde_json::json!(0);
+        with_sampling["top_p"] = serde_json::json!(0.9);
+        with_sampling["top_k"] = serde_json::json!(40);
+        let req: MessagesRequest = serde_json::from_value(with_sampling.clone()).unwrap();
+        assert!(req.has_sampling_params());
+        assert_eq!(
+            (req.temperature, req.top_p, req.top_k),
+            (Some(0.0), Some(0.9), Some(40))
+        );
+
+        // 采样参数不转发到上游
+        let result = convert_request(&req, &ConversionOptions::default()).unwrap();
+        let body = serde_json::to_string(&result.conversation_state).unwrap();
+        assert!(!body.contains("temperature"));
+
+        // count_tokens 接受并忽略采样参数
+        let count: super::super::types::CountTokensRequest =
+            serde_json::from_value(with_sampling).unwrap();
+        let body = serde_json::to_value(&count).unwrap();
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("top_k").is_none());
+    }
+
+    #[test]
+    fn test_sampling_params_validation() {
+        let request = |field: &str, value: serde_json::Value| -> MessagesRequest {
+            let mut req = serde_json::json!({
+                "model": "claude-sonnet-4",
+                "max_tokens": 1024,
+                "messages": [{"role": "user", "content": "Hello"}]
+            });
+            req[field] = value;
+            serde_json::from_value(req).unwrap()
+        };
+        let options = ConversionOptions::default();
+
+        for (field, value) in [
+            ("temperature", serde_json::json!(0.0)),
+            ("temperature", serde_json::json!(1.0)),
+            ("top_p", serde_json::json!(0.0)),
+            ("top_p", serde_json::json!(1)),
+            ("top_k", serde_json::json!(0)),
+            ("top_k", serde_json::json!(500)),
+        ] {
+            assert!(
+                convert_request(&request(field, value.clone()), &options).is_ok(),
+                "{} = {}",
+                field,
+                value
+            );
+        }
+
+        for (field, value) in [
+            ("temperature", serde_json::json!(-0.01)),
+            ("temperature", serde_json::json!(1.01)),
+            ("top_p", serde_json::json!(-1)),
+            ("top_p", serde_json::json!(1.5)),
+            ("top_k", serde_json::json!(-1)),
+        ] {
+            match convert_request(&request(field, value.clone()), &options) {
+                Err(ConversionError::InvalidSamplingParameter { name, .. }) => {
+                    assert_eq!(name, field)
+                }
+                other => panic!("{} = {}: {:?}", field, value, other.map(|_| ())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_convert_request_with_tool_result_image() {
+        let req = create_tool_result_request(serde_json::json!([
+            {"type": "text", "text": "Screenshot taken"},
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}}
+        ]));
+
+        let result = convert_request(&req, &ConversionOptions::default()).unwrap();
+        let user_input = &result.conversation_state.current_message.user_input_message;
+
+        // 嵌套图片随当前消息发送
+        let images = &user_input.images;
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].format, "png");
+        assert_eq!(images[0].source.bytes, "iVBORw0KGgo=");
+
+        let tool_results = &user_input.user_input_message_context.tool_results;
+        assert_eq!(tool_results.len(), 1);
+        assert_eq!(tool_results[0].content[0]["text"], "Screenshot taken");
+    }
+
+    #[test]
+    fn test_convert_request_with_oversized_tool_result_image() {
+        let req = create_tool_result_request(serde_json::json!([
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "A".repeat(16)}}
+        ]));
+        let options = ConversionOptions {
+            max_image_bytes: 11,
+            ..ConversionOptions::default()
+        };
+
+        // 16 个 base64 字符解码后为 12 字节
+        let err = convert_request(&req, &options).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversionError::ImageTooLarge {
+                message_index: 2,
+                block_index: 0,
+                size: 12,
+                max_bytes: 11,
+            }
+        ));
+
+        // 单张未超限，但总大小超限
+        let options = ConversionOptions {
+            max_image_bytes: 12,
+            max_request_image_bytes: 11,
+            ..ConversionOptions::default()
+        };
+        let err = convert_request(&req, &options).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversionError::RequestImagesTooLarge {
+                total: 12,
+                max_bytes: 11
+            }
+        ));
+    }
+
+    #[test]
+    fn test_base64_decoded_len() {
+        assert_eq!(base64_decoded_len(""), 0);
+        assert_eq!(base64_decoded_len("QQ=="), 1);
+        assert_eq!(base64_decoded_len("QUI="), 2);
+        assert_eq!(base64_decoded_len("QUJD"), 3);
+        assert_eq!(base64_decoded_len("QUJDRA"), 4);
+    }
+
+    /// 转换请求并返回警告
+    fn conversion_warnings(req: &MessagesRequest) -> Vec<ConversionWarning> {
+        convert_request(req, &ConversionOptions::default())
+            .unwrap()
+            .warnings
+    }
+
+    #[test]
+    fn test_no_warnings_for_supported_request() {
+        let req = create_document_request(serde_json::json!([
+            {"type": "text", "text": "Hello"},
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "QUJD"}}
+        ]));
+        assert!(conversion_warnings(&req).is_empty());
+    }
+
+    #[test]
+    fn test_warning_unsupported_content_block() {
+        use super::super::types::Message as AnthropicMessage;
+        use WarningCode as Code;
+
+        let mut req = create_document_request(serde_json::json!([
+            {"type": "text", "text": "Hello"},
+            {"type": "search_result", "title": "t", "content": []}
+        ]));
+        req.messages.push(AnthropicMessage {
+            role: "assistant".to_string(),
+            content: serde_json::json!([
+                {"type": "redacted_thinking", "data": "opaque"},
+                {"type": "text", "text": "Hi"}
+            ]),
+        });
+        req.messages.push(AnthropicMessage {
+            role: "user".to_string(),
+            content: serde_json::json!([{"text": "missing type"}]),
+        });
+
+        let warnings = conversion_warnings(&req);
+        let summary: Vec<_> = warnings.iter().map(|w| (w.code, w.path.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Code::UnsupportedContentBlock, "messages[2].content[0]"),
+                (Code::UnsupportedContentBlock, "messages[0].content[1]"),
+                (Code::UnsupportedContentBlock, "messages[1].content[0]"),
+            ]
+        );
+        assert!(warnings[1].message.contains("search_result"));
+        assert!(warnings[2].message.contains("redacted_thinking"));
+    }
+
+    #[test]
+    fn test_warning_unsupported_image_format() {
+        let req = create_document_request(serde_json::json!([
+            {"type": "image", "source": {"type": "base64", "media_type": "image/bmp", "data": "QUJD"}}
+        ]));
+        let warnings = conversion_warnings(&req);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::UnsupportedImageFormat);
+        assert_eq!(warnings[0].path, "messages[0].content[0]");
+        assert!(warnings[0].message.contains("image/bmp"));
+
+        // tool_result 中嵌套的图片（另有 screenshot 占位符工具警告）
+        let req = create_tool_result_request(serde_json::json!([
+            {"type": "text", "text": "screenshot"},
+            {"type": "image", "source": {"type": "base64", "media_type": "image/tiff", "data": "QUJD"}}
+        ]));
+        let warnings = conversion_warnings(&req);
+        let paths: Vec<_> = warnings
+            .iter()
+            .filter(|w| w.code == WarningCode::UnsupportedImageFormat)
+            .map(|w| w.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["messages[2].content[0].content[1]"]);
+    }
+
+    #[test]
+    fn test_warning_sampling_params_ignored() {
+        let mut req = create_document_request(serde_json::json!("Hello"));
+        req.temperature = Some(0.5);
+        req.top_k = Some(10);
+
+        let warnings = conversion_warnings(&req);
+        let summary: Vec<_> = warnings.iter().map(|w| (w.code, w.path.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (WarningCode::SamplingParamsIgnored, "temperature"),
+                (WarningCode::SamplingParamsIgnored, "top_k"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_warning_orphaned_tool_result() {
+        let mut req = create_tool_result_request(serde_json::json!("done"));
+        req.messages[2].content = serde_json::json!([
+            {"type": "tool_result", "tool_use_id": "unknown", "content": "done"}
+        ]);
+
+        let warnings = conversion_warnings(&req);
+        let orphaned: Vec<_> = warnings
+            .iter()
+            .filter(|w| w.code == WarningCode::OrphanedToolResult)
+            .collect();
+        assert_eq!(orphaned.len(), 1);
+        assert_eq!(orphaned[0].path, "messages[2]");
+        assert!(orphaned[0].message.contains("unknown"));
+    }
+
+    #[test]
+    fn test_warning_placeholder_tool_added() {
+        // 历史中使用了 screenshot，但请求未定义 tools
+        let req = create_tool_result_request(serde_json::json!("done"));
+        let warnings = conversion_warnings(&req);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::PlaceholderToolAdded);
+        assert_eq!(warnings[0].path, "tools");
+        assert!(warnings[0].message.contains("screenshot"));
+    }
+
+    #[test]
+    fn test_warning_tool_description_truncated() {
+        let tool = |name: &str, description: String| super::super::types::Tool {
+            tool_type: None,
+            name: name.to_string(),
+            description,
+            input_schema: Default::default(),
+            max_uses: None,
+        };
+        let mut req = create_document_request(serde_json::json!("Hello"));
+        req.tools = Some(vec![
+            tool("short", "ok".to_string()),
+            tool("long", "长".repeat(MAX_TOOL_DESCRIPTION_CHARS + 1)),
+        ]);
+
+        let result = convert_request(&req, &ConversionOptions::default()).unwrap();
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(
+            result.warnings[0].code,
+            WarningCode::ToolDescriptionTruncated
+        );
+        assert_eq!(result.warnings[0].path, "tools[1].description");
+
+        let tools = &result
+            .conversation_state
+            .current_message
+            .user_input_message
+            .user_input_message_context
+            .tools;
+        assert_eq!(
+            tools[1].tool_specification.description.chars().count(),
+            MAX_TOOL_DESCRIPTION_CHARS
+        );
+    }
+
+    #[test]
+    fn test_warning_serialization() {
+        let warning = ConversionWarning::new(
+            WarningCode::UnsupportedContentBlock,
+            "messages[0].content[1]",
+            "dropped",
+        );
+        assert_eq!(
+            serde_json::to_value(&warning).unwrap(),
+            serde_json::json!({
+                "code": "unsupported_content_block",
+                "message": "dropped",
+                "path": "messages[0].content[1]"
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(warning.code).unwrap(),
+            warning.code.as_str()
+        );
+    }
 }
@@ -0,0 +1,207 @@
+//! 请求去重
+//!
+//! 非流式请求按 `(API Key, model, messages, system, tools)` 等内容计算去重键，
+//! 相同的请求正在处理时，后到的请求等待首个请求完成并复用其响应，避免重复调用上游
+//! （如客户端并发重发的相同工具调用）。
+
+use std::future::Future;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use bytes::Bytes;
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use sha2::{Digest, Sha256};
+use tokio::sync::broadcast;
+
+use crate::common::i18n::{self, ErrorCode};
+
+use super::types::{ErrorResponse, MessagesRequest};
+
+/// 去重结果响应头
+pub const DEDUP_HEADER: &str = "x-dedup";
+
+/// 缓存的完整响应（在等待者之间共享）
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl CachedResponse {
+    /// 读取完整响应体
+    async fn from_response(response: Response) -> Result<Self, axum::Error> {
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX).await?;
+        Ok(Self {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        })
+    }
+
+    /// 重建响应
+    fn to_response(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+    }
+}
+
+/// 进行中请求的登记（首个请求结束或被取消时移除）
+struct InFlightGuard<'a> {
+    in_flight: &'a DashMap<String, broadcast::Sender<CachedResponse>>,
+    key: &'a str,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.in_flight.remove(self.key);
+    }
+}
+
+/// 请求去重器
+pub struct RequestDeduplicator {
+    /// 进行中的请求（去重键 -> 响应广播）
+    in_flight: DashMap<String, broadcast::Sender<CachedResponse>>,
+    /// 等待首个请求的最长时间
+    max_wait: Duration,
+}
+
+impl RequestDeduplicator {
+    /// 创建去重器
+    pub fn new(max_wait_secs: u64) -> Self {
+        Self {
+            in_flight: DashMap::new(),
+            max_wait: Duration::from_secs(max_wait_secs),
+        }
+    }
+
+    /// 计算去重键：SHA-256(API Key, model, max_tokens, messages, system, tools, thinking, output_config)
+    ///
+    /// 去重键只在进程内使用，thinking/output_config 以 Debug 形式参与计算
+    pub fn dedup_key(api_key: Option<&str>, payload: &MessagesRequest) -> String {
+        let mut hasher = Sha256::new();
+        let mut update = |part: &[u8]| {
+            // 带长度前缀，避免字段拼接产生歧义
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        };
+
+        update(api_key.unwrap_or_default().as_bytes());
+        update(payload.model.as_bytes());
+        update(&payload.max_tokens.to_le_bytes());
+        update(&serde_json::to_vec(&payload.messages).unwrap_or_default());
+        update(&serde_json::to_vec(&payload.system).unwrap_or_default());
+        update(&serde_json::to_vec(&payload.tools).unwrap_or_default());
+        update(format!("{:?}", payload.thinking).as_bytes());
+        update(format!("{:?}", payload.output_config).as_bytes());
+        update(format!("{:?}", (payload.temperature, payload.top_p, payload.top_k)).as_bytes());
+
+        hex::encode(hasher.finalize())
+    }
+
+    /// 当前进行中的去重键数量
+    #[allow(dead_code)]
+    pub fn in_flight_count(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// 执行请求（相同去重键的请求进行中时等待并复用其响应）
+    ///
+    /// 响应附加 `x-dedup` 头：复用其他请求结果为 `HIT`，自行处理为 `MISS`。
+    /// 等待超时或首个请求被取消时，自行处理请求
+    pub async fn run<F, Fut>(&self, key: String, handle: F) -> Response
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Response>,
+    {
+        let receiver = match self.in_flight.entry(key.clone()) {
+            Entry::Occupied(entry) => Some(entry.get().subscribe()),
+            Entry::Vacant(entry) => {
+                let (sender, _) = broadcast::channel(1);
+                entry.insert(sender);
+                None
+            }
+        };
+
+        let Some(mut receiver) = receiver else {
+            return self.run_first(&key, handle).await;
+        };
+
+        match tokio::time::timeout(self.max_wait, receiver.recv()).await {
+            Ok(Ok(cached)) => {
+                tracing::info!(dedup_key = %&key[..16], "复用进行中的相同请求结果");
+                with_dedup_header(cached.to_response(), "HIT")
+            }
+            Ok(Err(_)) => {
+                tracing::warn!(dedup_key = %&key[..16], "相同请求已取消，自行处理请求");
+                with_dedup_header(handle().await, "MISS")
+            }
+            Err(_) => {
+                tracing::warn!(
+                    dedup_key = %&key[..16],
+                    "等待相同请求超时（{} 秒），自行处理请求",
+                    self.max_wait.as_secs()
+                );
+                with_dedup_header(handle().await, "MISS")
+            }
+        }
+    }
+
+    /// 首个请求：处理并将响应广播给等待者
+    async fn run_first<F, Fut>(&self, key: &str, handle: F) -> Response
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Response>,
+    {
+        let guard = InFlightGuard {
+            in_flight: &self.in_flight,
+            key,
+        };
+
+        let response = handle().await;
+        let cached = match CachedResponse::from_response(response).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                // 返回错误时 guard 移除登记，等待者自行处理请求
+                tracing::error!("读取响应体失败，无法共享给相同请求: {}", e);
+                return with_dedup_header(
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(ErrorResponse::new(
+                            "api_error",
+                            ErrorCode::ResponseReadFailed,
+                            i18n::default_locale(),
+                        )),
+                    )
+                        .into_response(),
+                    "MISS",
+                );
+            }
+        };
+
+        // 先移除登记再广播：之后到达的相同请求重新处理，不会错过结果
+        let sender = self.in_flight.get(key).map(|entry| entry.clone());
+        drop(guard);
+        if let Some(sender) = sender {
+            let _ = sender.send(cached.clone());
+        }
+
+        with_dedup_header(cached.to_response(), "MISS")
+    }
+}
+
+/// 附加去重结果响应头
+fn with_dedup_header(mut response: Response, value: &'static str) -> Response {
+    response
+        .headers_mut()
+        .insert(DEDUP_HEADER, HeaderValue::from_static(value));
+    response
+}
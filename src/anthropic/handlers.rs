@@ -7,37 +7,80 @@
 
 use std::convert::Infallible;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use crate::admin::api_keys::ModelPolicy;
+use crate::common::features::{ENABLE_BATCH_MESSAGES, ENABLE_TOKEN_DEDUP, ENABLE_WEBSEARCH_CACHE};
+use crate::common::i18n::{ErrorCode, Locale, LocalizedError};
+use crate::kiro::error::{ProviderError, TimeoutKind};
 use crate::kiro::model::events::Event;
 use crate::kiro::parser::decoder::EventStreamDecoder;
-use crate::kiro::provider::KiroProvider;
+use crate::kiro::provider::{CredentialPermit, KiroProvider, UpstreamError};
+use crate::model::config::CONTEXT_WINDOW_SIZE;
 use crate::token;
 use axum::{
     Extension,
     Json as JsonExtractor,
     body::Body,
-    extract::State,
+    extract::{Query, State},
     http::{HeaderMap, StatusCode, header},
     response::{IntoResponse, Json, Response},
 };
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures::{Stream, StreamExt, stream};
 use serde_json::json;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::interval;
+use tracing::Instrument;
 use uuid::Uuid;
 
-use super::converter::ConversionError;
-use super::middleware::{AppState, AuthenticatedPoolId};
-use super::service::{
-    self, CONTEXT_WINDOW_SIZE, PING_INTERVAL_SECS, RequestContext, ValidationResult,
-};
+use super::concurrency::{UpstreamConcurrencyLimiter, UpstreamPermit};
+use super::converter::{ConversionError, ConversionWarning, map_model};
+use super::dedup::RequestDeduplicator;
+use super::middleware::{AppState, AuthenticatedPoolId, rate_limited_response};
+use super::postprocess::{TextSanitizer, repair_tool_json};
+use super::quota_queue::{QueueOutcome, QuotaQueue};
+use super::replay::SseReplayRegistry;
+use super::request_span::RequestSpan;
+use super::service::{self, PING_INTERVAL_SECS, RequestContext, ValidationResult};
 use super::stream::{BufferedStreamContext, SseEvent, StreamContext};
 use super::types::{
-    CountTokensRequest, CountTokensResponse, ErrorResponse, MessagesRequest, Model, ModelsResponse,
+    CountTokensPoolInfo, CountTokensQuery, CountTokensRequest, CountTokensResponse, ErrorResponse,
+    MessageBatchOutcome, MessageBatchRequest, MessageBatchResponse, MessageBatchResult,
+    MessagesRequest, Model, ModelsResponse,
 };
 use super::websearch;
 
+/// 返回给客户端的上游请求 ID 响应头
+const UPSTREAM_REQUEST_ID_HEADER: &str = "x-kiro-upstream-request-id";
+
+/// 返回给客户端的实际服务池 ID 响应头
+const SERVING_POOL_HEADER: &str = "x-kiro-pool";
+
+/// 请求溢出到溢出池时返回的响应头（值固定为 `true`）
+const POOL_OVERFLOW_HEADER: &str = "x-kiro-pool-overflow";
+
+/// 请求了 Prompt Caching 时返回的响应头（上游不支持，值固定为 `unsupported`）
+const PROMPT_CACHING_HEADER: &str = "x-kiro-prompt-caching";
+
+/// 是否已记录过 Prompt Caching 不受支持的警告
+static PROMPT_CACHING_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// 请求携带了采样参数时返回的响应头（上游不支持，值固定为 `unsupported`）
+const SAMPLING_HEADER: &str = "x-kiro-sampling";
+
+/// 是否已记录过采样参数不受支持的警告
+static SAMPLING_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// max_tokens 超过 API Key 上限被截断时返回的响应头（值为截断后的 max_tokens）
+const MAX_TOKENS_CLAMPED_HEADER: &str = "x-kiro-max-tokens-clamped";
+
+/// 单个批次最多包含的请求数
+const MAX_BATCH_REQUESTS: usize = 100;
+
+/// 批次内同时处理的请求数
+const BATCH_CONCURRENCY: usize = 4;
+
 /// GET /v1/models
 ///
 /// 返回可用的模型列表
@@ -115,15 +158,112 @@ pub async fn post_messages_cc(
     handle_messages_request(state, pool_id, headers, payload, "/cc/v1/messages", true).await
 }
 
+/// POST /v1/messages/batch
+///
+/// 批量创建消息（需启用功能开关 `enable_batch_messages`，未启用时返回 404）
+///
+/// 同步执行：各请求按非流式处理，全部完成后按提交顺序返回结果；
+/// 单个请求失败不影响其他请求
+pub async fn post_messages_batch(
+    State(state): State<AppState>,
+    Extension(pool_id): Extension<AuthenticatedPoolId>,
+    headers: HeaderMap,
+    JsonExtractor(payload): JsonExtractor<MessageBatchRequest>,
+) -> Response {
+    let locale = Locale::from_headers(&headers);
+
+    if !state.features.is_enabled(ENABLE_BATCH_MESSAGES) {
+        return create_error_response(
+            StatusCode::NOT_FOUND,
+            "not_found_error",
+            ErrorCode::FeatureDisabled.arg("name", ENABLE_BATCH_MESSAGES),
+            locale,
+        );
+    }
+    if payload.requests.is_empty() {
+        return create_error_response(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            ErrorCode::EmptyBatch,
+            locale,
+        );
+    }
+    if payload.requests.len() > MAX_BATCH_REQUESTS {
+        return create_error_response(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            ErrorCode::BatchTooLarge.arg("max", MAX_BATCH_REQUESTS),
+            locale,
+        );
+    }
+
+    tracing::info!(
+        request_count = payload.requests.len(),
+        "Received POST /v1/messages/batch request"
+    );
+
+    let results = stream::iter(payload.requests)
+        .map(|item| {
+            let state = state.clone();
+            let pool_id = pool_id.clone();
+            let headers = headers.clone();
+            async move {
+                let mut params = item.params;
+                params.stream = false;
+                let response = handle_messages_request(
+                    state,
+                    pool_id,
+                    headers,
+                    params,
+                    "/v1/messages/batch",
+                    false,
+                )
+                .await;
+                MessageBatchResult {
+                    custom_id: item.custom_id,
+                    result: batch_outcome(response, locale).await,
+                }
+            }
+        })
+        .buffered(BATCH_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    Json(MessageBatchResponse { results }).into_response()
+}
+
+/// 将单个请求的响应转换为批次结果
+async fn batch_outcome(response: Response, locale: Locale) -> MessageBatchOutcome {
+    let success = response.status().is_success();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .ok()
+        .and_then(|body| serde_json::from_slice(&body).ok());
+
+    match body {
+        Some(message) if success => MessageBatchOutcome::Succeeded { message },
+        Some(error) => MessageBatchOutcome::Errored { error },
+        None => MessageBatchOutcome::Errored {
+            error: json!(ErrorResponse::new(
+                "api_error",
+                ErrorCode::ResponseReadFailed,
+                locale
+            )),
+        },
+    }
+}
+
 /// 处理消息请求的通用逻辑
 ///
 /// # 参数
 /// - `state`: 应用状态
-/// - `pool_id`: 认证后的池 ID（来自 API Key 绑定）
+/// - `pool_id`: 认证后的池 ID 列表（来自 API Key 绑定，按回退顺序）
 /// - `headers`: HTTP 请求头
 /// - `payload`: 消息请求体
 /// - `endpoint`: 端点名称（用于日志）
 /// - `use_buffered_stream`: 是否使用缓冲流（Claude Code 端点需要）
+///
+/// 整个处理过程运行在 `handle_request` span 内，请求内的日志共享请求属性
 async fn handle_messages_request(
     state: AppState,
     pool_id: AuthenticatedPoolId,
@@ -131,127 +271,370 @@ async fn handle_messages_request(
     payload: MessagesRequest,
     endpoint: &str,
     use_buffered_stream: bool,
+) -> Response {
+    let request_span = RequestSpan::new(Uuid::new_v4().to_string(), &payload.model, payload.stream);
+    let span = request_span.span().clone();
+    process_messages_request(
+        state,
+        pool_id,
+        headers,
+        payload,
+        endpoint,
+        use_buffered_stream,
+        request_span,
+    )
+    .instrument(span)
+    .await
+}
+
+/// 处理消息请求（在请求 span 内执行）
+async fn process_messages_request(
+    state: AppState,
+    pool_id: AuthenticatedPoolId,
+    headers: HeaderMap,
+    mut payload: MessagesRequest,
+    endpoint: &str,
+    use_buffered_stream: bool,
+    request_span: RequestSpan,
 ) -> Response {
     log_request(&payload, &headers, endpoint, &pool_id);
+    let locale = Locale::from_headers(&headers);
+
+    // API Key 模型策略：在选择池和获取凭据之前检查，被拒绝的请求不产生上游开销
+    let policy = model_policy(&state, &headers);
+    if let Some(response) = reject_disallowed_model(policy.as_ref(), &payload.model, locale) {
+        return response;
+    }
+    let clamped_max_tokens = policy
+        .as_ref()
+        .and_then(|policy| policy.clamp_max_tokens(payload.max_tokens));
+    if let Some(max_tokens) = clamped_max_tokens {
+        tracing::info!(
+            requested = payload.max_tokens,
+            clamped = max_tokens,
+            "max_tokens 超过 API Key 上限，已截断"
+        );
+        payload.max_tokens = max_tokens;
+    }
 
     // 根据 pool_id 选择 KiroProvider
-    let kiro_provider = match resolve_kiro_provider(&state, &pool_id) {
-        Ok(provider) => provider,
+    let (kiro_provider, serving_pool) = match resolve_kiro_provider(&state, &pool_id) {
+        Ok(resolved) => resolved,
         Err(pool_error) => {
             return create_error_response(
                 StatusCode::SERVICE_UNAVAILABLE,
                 "pool_unavailable",
-                &pool_error,
+                pool_error,
+                locale,
             );
         }
     };
+    request_span.record_pool(serving_pool.as_ref().map(|p| p.id.as_str()));
+
+    // SSE 断线续传：携带 Last-Event-ID 的流式请求回放已有流，不再调用上游
+    if let Some(replay) = state.sse_replay.as_deref()
+        && payload.stream
+        && let Some(last_event_id) = SseReplayRegistry::last_event_id(&headers)
+    {
+        let response = replay.resume(&request_key(&headers, &payload), last_event_id);
+        return attach_serving_pool(response, serving_pool.as_ref());
+    }
 
     // 验证并准备请求
-    match service::validate_and_prepare_request(
+    let response = match service::validate_and_prepare_request(
         kiro_provider.as_ref(),
         state.profile_arn.as_ref(),
         &payload,
         &headers,
         &state.config,
+        &state.features,
+        &request_span,
     ) {
-        ValidationResult::Ok(ctx) => {
-            handle_validated_request(ctx, use_buffered_stream).await
+        ValidationResult::Ok(ctx) if ctx.is_stream => {
+            let response = handle_validated_request(
+                ctx,
+                use_buffered_stream,
+                state.quota_queue.as_deref(),
+                state.upstream_limiter.as_deref(),
+            )
+            .await;
+            match &state.sse_replay {
+                // 记录 SSE 事件，供断线后续传
+                Some(replay) => replay.record(request_key(&headers, &payload), response),
+                None => response,
+            }
         }
+        ValidationResult::Ok(ctx) => match state
+            .deduplicator
+            .as_deref()
+            .filter(|_| state.features.is_enabled(ENABLE_TOKEN_DEDUP))
+        {
+            // 非流式请求去重：相同请求进行中时复用其响应
+            Some(deduplicator) => {
+                let key = request_key(&headers, &payload);
+                deduplicator
+                    .run(key, || {
+                        handle_validated_request(
+                            ctx,
+                            use_buffered_stream,
+                            state.quota_queue.as_deref(),
+                            state.upstream_limiter.as_deref(),
+                        )
+                    })
+                    .await
+            }
+            _ => {
+                handle_validated_request(
+                    ctx,
+                    use_buffered_stream,
+                    state.quota_queue.as_deref(),
+                    state.upstream_limiter.as_deref(),
+                )
+                .await
+            }
+        },
         ValidationResult::ProviderNotConfigured => {
             create_error_response(
                 StatusCode::SERVICE_UNAVAILABLE,
                 "service_unavailable",
-                "Kiro API provider not configured",
+                ErrorCode::ProviderNotConfigured,
+                locale,
             )
         }
         ValidationResult::WebSearchRequest { provider, input_tokens } => {
-            websearch::handle_websearch_request(provider, &payload, input_tokens).await
+            let api_key = crate::common::auth::extract_api_key_from_headers(&headers);
+            let limit_override = api_key
+                .as_deref()
+                .and_then(|k| state.api_key_manager.websearch_limit_override(k));
+            websearch::handle_websearch_request(
+                provider,
+                &payload,
+                input_tokens,
+                &state.websearch_limiter,
+                state
+                    .features
+                    .is_enabled(ENABLE_WEBSEARCH_CACHE)
+                    .then_some(state.websearch_cache.as_ref()),
+                api_key.as_deref(),
+                limit_override,
+                locale,
+            )
+            .await
         }
         ValidationResult::ConversionFailed(e) => {
-            create_conversion_error_response(e)
+            create_conversion_error_response(e, locale)
         }
         ValidationResult::SerializationFailed(msg) => {
             create_error_response(
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "internal_error",
-                &format!("序列化请求失败: {}", msg),
+                ErrorCode::SerializationFailed.arg("detail", msg),
+                locale,
             )
         }
+    };
+
+    let response = if state.config.prompt_caching_notice_enabled
+        && service::requests_prompt_caching(&payload, &headers)
+    {
+        attach_prompt_caching_notice(response)
+    } else {
+        response
+    };
+
+    let mut response = if payload.has_sampling_params() && response.status().is_success() {
+        attach_sampling_notice(response)
+    } else {
+        response
+    };
+    if let Some(max_tokens) = clamped_max_tokens {
+        response.headers_mut().insert(
+            MAX_TOKENS_CLAMPED_HEADER,
+            header::HeaderValue::from(max_tokens),
+        );
+    }
+
+    attach_serving_pool(response, serving_pool.as_ref())
+}
+
+/// 获取请求所用 API Key 的模型访问策略（config.json 中的 `apiKey` 不受限制）
+fn model_policy(state: &AppState, headers: &HeaderMap) -> Option<ModelPolicy> {
+    let api_key = crate::common::auth::extract_api_key_from_headers(headers)?;
+    state.api_key_manager.model_policy(&api_key)
+}
+
+/// 检查 API Key 能否使用请求的模型（按映射后的上游模型判断），不允许时返回 403 model_not_allowed 响应
+fn reject_disallowed_model(
+    policy: Option<&ModelPolicy>,
+    model: &str,
+    locale: Locale,
+) -> Option<Response> {
+    if policy.is_none_or(|policy| policy.allows_request(model, map_model(model).as_deref())) {
+        return None;
     }
+    tracing::warn!(model = %model, "API Key 无权使用该模型，拒绝请求");
+    Some(create_error_response(
+        StatusCode::FORBIDDEN,
+        "model_not_allowed",
+        ErrorCode::ModelNotAllowed.arg("model", model),
+        locale,
+    ))
+}
+
+/// 请求键（API Key + 请求内容），用于请求去重和 SSE 断线续传
+fn request_key(headers: &HeaderMap, payload: &MessagesRequest) -> String {
+    let api_key = crate::common::auth::extract_api_key_from_headers(headers);
+    RequestDeduplicator::dedup_key(api_key.as_deref(), payload)
 }
 
-/// 根据 pool_id 解析 KiroProvider
+/// 实际服务请求的池
+struct ServingPool {
+    /// 池 ID
+    id: String,
+    /// 是否由溢出池服务
+    overflow: bool,
+}
+
+/// 根据绑定的池 ID 列表解析 KiroProvider
 ///
 /// # 返回
-/// - `Ok(Some(provider))` - 成功获取 Provider
-/// - `Ok(None)` - 无 Provider 配置
-/// - `Err(msg)` - API Key 绑定的池不可用（不应回退）
+/// - `Ok((Some(provider), pool))` - 成功获取 Provider 及实际服务的池
+/// - `Ok((None, None))` - 无 Provider 配置
+/// - `Err(error)` - API Key 绑定的池均不可用（不应回退到默认池）
 fn resolve_kiro_provider(
     state: &AppState,
     pool_id: &AuthenticatedPoolId,
-) -> Result<Option<Arc<KiroProvider>>, String> {
-    // 如果有 PoolManager，尝试根据 pool_id 获取池
+) -> Result<(Option<Arc<KiroProvider>>, Option<ServingPool>), LocalizedError> {
+    // 如果有 PoolManager，按绑定顺序选择池（无可用凭据时溢出到溢出池）
     if let Some(ref pool_manager) = state.pool_manager {
-        let pool_id_str = pool_id.0.as_deref();
-
-        // 如果 API Key 绑定了特定池，必须使用该池
-        if let Some(bound_pool_id) = pool_id_str {
-            if let Some(pool_runtime) = pool_manager.get_pool_for_api_key(Some(bound_pool_id)) {
-                tracing::debug!(
-                    pool_id = ?pool_id_str,
-                    actual_pool = %pool_runtime.config.id,
-                    "使用 API Key 绑定的池"
-                );
-                // 为该池创建 KiroProvider
-                let provider = KiroProvider::new(pool_runtime.token_manager.clone());
-                return Ok(Some(Arc::new(provider)));
-            } else {
-                // API Key 绑定的池不可用，返回错误而不是回退
-                tracing::error!(
-                    pool_id = ?bound_pool_id,
-                    "API Key 绑定的池不可用，拒绝请求"
-                );
-                return Err(format!(
-                    "API Key 绑定的池 '{}' 不可用或已禁用",
-                    bound_pool_id
-                ));
-            }
+        let bound_pool_ids = &pool_id.0;
+
+        if let Some(selection) = pool_manager.select_pool_for_api_key(bound_pool_ids) {
+            let (pool_id, provider) = {
+                let pool = selection.pool.read();
+                (pool.config.id.clone(), pool.provider.clone())
+            };
+            let serving_pool = ServingPool {
+                id: pool_id,
+                overflow: selection.overflow_from.is_some(),
+            };
+            tracing::info!(
+                pool_ids = ?bound_pool_ids,
+                serving_pool = %serving_pool.id,
+                overflow = serving_pool.overflow,
+                "选择服务池"
+            );
+            // 复用该池缓存的 KiroProvider
+            return Ok((Some(provider), Some(serving_pool)));
         }
 
-        // API Key 未绑定特定池，使用默认池
-        if let Some(pool_runtime) = pool_manager.get_pool_for_api_key(None) {
-            tracing::debug!("使用默认池");
-            let provider = KiroProvider::new(pool_runtime.token_manager.clone());
-            return Ok(Some(Arc::new(provider)));
+        // API Key 绑定的池均不可用，返回错误而不是回退
+        if !bound_pool_ids.is_empty() {
+            tracing::error!(
+                pool_ids = ?bound_pool_ids,
+                "API Key 绑定的池不可用，拒绝请求"
+            );
+            return Err(ErrorCode::PoolUnavailable.arg("pools", bound_pool_ids.join(", ")));
         }
     }
 
     // 回退到默认的 kiro_provider（无 PoolManager 时）
-    Ok(state.kiro_provider.clone())
+    Ok((state.kiro_provider.clone(), None))
 }
 
 /// POST /v1/messages/count_tokens
 ///
 /// 计算消息的 token 数量
+///
+/// `?detailed=true` 时额外返回上下文窗口、历史管理后的输入 tokens、预计输出上限，
+/// 以及 API Key 绑定池的可用凭据数和剩余额度（仅本地计算和缓存数据，不调用上游）
 pub async fn count_tokens(
+    State(state): State<AppState>,
+    Extension(pool_id): Extension<AuthenticatedPoolId>,
+    Query(query): Query<CountTokensQuery>,
+    headers: HeaderMap,
     JsonExtractor(payload): JsonExtractor<CountTokensRequest>,
-) -> impl IntoResponse {
+) -> Response {
     tracing::info!(
         model = %payload.model,
         message_count = %payload.messages.len(),
+        detailed = query.detailed,
         "Received POST /v1/messages/count_tokens request"
     );
 
-    let total_tokens = token::count_all_tokens(
+    // 与 /v1/messages 相同的模型检查
+    let policy = model_policy(&state, &headers);
+    if let Some(response) = reject_disallowed_model(
+        policy.as_ref(),
+        &payload.model,
+        Locale::from_headers(&headers),
+    ) {
+        return response;
+    }
+
+    let details = query
+        .detailed
+        .then(|| count_tokens_details(&state, &pool_id, &payload));
+
+    let (total_tokens, source) = token::count_all_tokens_with_source(
         payload.model,
         payload.system,
         payload.messages,
         payload.tools,
-    ) as i32;
+    )
+    .await;
 
     Json(CountTokensResponse {
-        input_tokens: total_tokens.max(1) as i32,
+        input_tokens: (total_tokens as i32).max(1),
+        token_count_source: Some(source),
+        ..details.unwrap_or_default()
     })
+    .into_response()
+}
+
+/// 计算 `detailed=true` 时的附加字段（`input_tokens` 由调用方填充）
+fn count_tokens_details(
+    state: &AppState,
+    pool_id: &AuthenticatedPoolId,
+    payload: &CountTokensRequest,
+) -> CountTokensResponse {
+    let effective_input_tokens =
+        (service::estimate_effective_input_tokens(payload, &state.config, &state.features) as i32)
+            .max(1);
+    let remaining_context = (CONTEXT_WINDOW_SIZE - effective_input_tokens).max(0);
+    let estimated_output_tokens = payload.max_tokens.map_or(remaining_context, |max_tokens| {
+        max_tokens.clamp(0, remaining_context)
+    });
+
+    // 仅在 API Key 绑定了池时返回池状态
+    let pool = state
+        .pool_manager
+        .as_ref()
+        .filter(|_| !pool_id.0.is_empty())
+        .and_then(|pool_manager| {
+            pool_manager
+                .select_pool_for_api_key(&pool_id.0)
+                .map(|selection| selection.pool)
+                .or_else(|| pool_manager.get_pool(&pool_id.0[0]))
+        })
+        .map(|pool| {
+            let runtime = pool.read();
+            CountTokensPoolInfo {
+                id: runtime.config.id.clone(),
+                available_credentials: runtime.token_manager.available_count(),
+                remaining_quota_percentage: runtime
+                    .token_manager
+                    .cached_remaining_quota_percentage(),
+            }
+        });
+
+    CountTokensResponse {
+        context_window: Some(CONTEXT_WINDOW_SIZE),
+        effective_input_tokens: Some(effective_input_tokens),
+        estimated_output_tokens: Some(estimated_output_tokens),
+        pool,
+        ..Default::default()
+    }
 }
 
 // ============ 内部辅助函数 ============
@@ -265,35 +648,229 @@ fn log_request(payload: &MessagesRequest, headers: &HeaderMap, endpoint: &str, p
         stream = %payload.stream,
         message_count = %payload.messages.len(),
         session_id = ?session_id.as_ref().map(|s| &s[..s.len().min(30)]),
-        pool_id = ?pool_id.0,
+        pool_ids = ?pool_id.0,
         "Received POST {} request", endpoint
     );
 }
 
-/// 创建错误响应
-fn create_error_response(status: StatusCode, error_type: &str, message: &str) -> Response {
-    (status, Json(ErrorResponse::new(error_type, message))).into_response()
+/// 创建错误响应（消息按客户端语言渲染）
+fn create_error_response(
+    status: StatusCode,
+    error_type: &str,
+    error: impl Into<LocalizedError>,
+    locale: Locale,
+) -> Response {
+    (status, Json(ErrorResponse::new(error_type, error, locale))).into_response()
+}
+
+/// 按调用失败原因确定响应状态码与错误类型
+///
+/// 无法识别失败原因的错误按上游调用失败处理（502 api_error）
+fn provider_error_status(error: Option<&ProviderError>) -> (StatusCode, &'static str) {
+    error.map_or((StatusCode::BAD_GATEWAY, "api_error"), |e| {
+        (e.status_code(), e.error_type())
+    })
+}
+
+/// 创建携带上游请求 ID 的错误响应
+///
+/// 上游请求 ID 同时写入响应体和 `x-kiro-upstream-request-id` 响应头
+fn create_upstream_error_response(
+    status: StatusCode,
+    error_type: &str,
+    error: LocalizedError,
+    locale: Locale,
+    upstream_request_id: Option<String>,
+) -> Response {
+    let header_value = upstream_request_id.clone();
+    let error =
+        ErrorResponse::new(error_type, error, locale).with_upstream_request_id(upstream_request_id);
+    attach_upstream_request_id((status, Json(error)).into_response(), header_value.as_deref())
+}
+
+/// 创建上游限流错误响应（429，透传 `Retry-After`）
+fn create_upstream_rate_limited_response(
+    retry_after: Duration,
+    error: anyhow::Error,
+    locale: Locale,
+    upstream_request_id: Option<String>,
+) -> Response {
+    // 不足 1 秒按 1 秒计，避免客户端立即重试
+    let retry_after_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    let mut response = create_upstream_error_response(
+        StatusCode::TOO_MANY_REQUESTS,
+        "rate_limit_error",
+        ErrorCode::UpstreamRateLimited
+            .arg("retry_after", retry_after_secs)
+            .arg("detail", error),
+        locale,
+        upstream_request_id,
+    );
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        header::HeaderValue::from(retry_after_secs),
+    );
+    response
+}
+
+/// 在响应头中附加上游请求 ID
+fn attach_upstream_request_id(mut response: Response, upstream_request_id: Option<&str>) -> Response {
+    if let Some(value) = upstream_request_id.and_then(|id| header::HeaderValue::from_str(id).ok()) {
+        response
+            .headers_mut()
+            .insert(UPSTREAM_REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// 在响应头中附加实际服务的池 ID（溢出时额外标记）
+fn attach_serving_pool(mut response: Response, serving_pool: Option<&ServingPool>) -> Response {
+    let Some(serving_pool) = serving_pool else {
+        return response;
+    };
+    if let Ok(value) = header::HeaderValue::from_str(&serving_pool.id) {
+        response.headers_mut().insert(SERVING_POOL_HEADER, value);
+    }
+    if serving_pool.overflow {
+        response.headers_mut().insert(
+            POOL_OVERFLOW_HEADER,
+            header::HeaderValue::from_static("true"),
+        );
+    }
+    response
+}
+
+/// 标记 Prompt Caching 不受支持（首次出现时记录一次警告）
+fn attach_prompt_caching_notice(mut response: Response) -> Response {
+    if !PROMPT_CACHING_WARNED.swap(true, Ordering::Relaxed) {
+        tracing::warn!(
+            "客户端请求了 Prompt Caching（cache_control / anthropic-beta），Kiro 上游不支持，\
+             cache_control 标记已移除，请求按完整输入计费（后续不再提示）"
+        );
+    }
+    response.headers_mut().insert(
+        PROMPT_CACHING_HEADER,
+        header::HeaderValue::from_static("unsupported"),
+    );
+    response
+}
+
+/// 标记采样参数未生效（Kiro 上游不支持 temperature / top_p / top_k）
+fn attach_sampling_notice(mut response: Response) -> Response {
+    if !SAMPLING_WARNED.swap(true, Ordering::Relaxed) {
+        tracing::warn!(
+            "客户端请求了采样参数（temperature / top_p / top_k），Kiro 上游不支持，\
+             参数已忽略（后续不再提示）"
+        );
+    }
+    response.headers_mut().insert(
+        SAMPLING_HEADER,
+        header::HeaderValue::from_static("unsupported"),
+    );
+    response
 }
 
 /// 创建转换错误响应
-fn create_conversion_error_response(e: ConversionError) -> Response {
-    let (error_type, message) = match &e {
-        ConversionError::UnsupportedModel(model) => {
-            ("invalid_request_error", format!("模型不支持: {}", model))
+fn create_conversion_error_response(e: ConversionError, locale: Locale) -> Response {
+    let error = match e {
+        ConversionError::UnsupportedModel(model) => ErrorCode::UnsupportedModel.arg("model", model),
+        ConversionError::EmptyMessages => ErrorCode::EmptyMessages.into(),
+        ConversionError::InvalidDocument {
+            message_index,
+            block_index,
+            reason,
+        } => ErrorCode::InvalidDocument
+            .arg("message_index", message_index)
+            .arg("block_index", block_index)
+            .arg("reason", reason),
+        ConversionError::ImageTooLarge {
+            message_index,
+            block_index,
+            size,
+            max_bytes,
+        } => ErrorCode::ImageTooLarge
+            .arg("message_index", message_index)
+            .arg("block_index", block_index)
+            .arg("size", size)
+            .arg("max", max_bytes),
+        ConversionError::RequestImagesTooLarge { total, max_bytes } => {
+            ErrorCode::RequestImagesTooLarge
+                .arg("total", total)
+                .arg("max", max_bytes)
+        }
+        ConversionError::InvalidSamplingParameter { name, value, range } => {
+            ErrorCode::InvalidSamplingParameter
+                .arg("name", name)
+                .arg("value", value)
+                .arg("range", range)
+        }
+        ConversionError::ContinuationToolUse { message_index } => {
+            ErrorCode::ContinuationToolUse.arg("message_index", message_index)
         }
-        ConversionError::EmptyMessages => {
-            ("invalid_request_error", "消息列表为空".to_string())
+        ConversionError::ContinuationWithoutUser { message_index } => {
+            ErrorCode::ContinuationWithoutUser.arg("message_index", message_index)
+        }
+        ConversionError::ContinuationEmpty { message_index } => {
+            ErrorCode::ContinuationEmpty.arg("message_index", message_index)
         }
     };
-    create_error_response(StatusCode::BAD_REQUEST, error_type, &message)
+    create_error_response(StatusCode::BAD_REQUEST, "invalid_request_error", error, locale)
 }
 
 /// 处理已验证的请求
-async fn handle_validated_request(ctx: RequestContext, use_buffered_stream: bool) -> Response {
+///
+/// 流式请求不参与额度用尽排队，保持快速失败
+async fn handle_validated_request(
+    ctx: RequestContext,
+    use_buffered_stream: bool,
+    quota_queue: Option<&QuotaQueue>,
+    upstream_limiter: Option<&UpstreamConcurrencyLimiter>,
+) -> Response {
+    if !ctx.is_stream
+        && let Some(queue) = quota_queue
+    {
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        let outcome = queue
+            .wait_for_credentials(ctx.provider.token_manager(), now)
+            .await;
+        if outcome == QueueOutcome::Full {
+            return create_error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "overloaded_error",
+                ErrorCode::QuotaQueueFull,
+                ctx.locale,
+            );
+        }
+    }
+
+    // 上游并发限制：名额用尽时排队等待，超时直接返回 429（不调用上游）
+    let permit = match upstream_limiter {
+        Some(limiter) => match limiter.acquire().await {
+            Some(permit) => Some(permit),
+            None => {
+                tracing::warn!(
+                    request_id = %ctx.request_id,
+                    "上游并发名额排队超时（上限 {}）",
+                    limiter.max()
+                );
+                let error = ErrorResponse::new(
+                    "rate_limit_error",
+                    ErrorCode::UpstreamConcurrencyLimit.arg("max", limiter.max()),
+                    ctx.locale,
+                );
+                return rate_limited_response(error, limiter.retry_after_secs());
+            }
+        },
+        None => None,
+    };
+
     if ctx.is_stream {
-        handle_stream_request(ctx, use_buffered_stream).await
+        // 流式请求的名额随 SSE 流释放
+        handle_stream_request(ctx, use_buffered_stream, permit).await
     } else {
-        handle_non_stream_request(ctx).await
+        let response = handle_non_stream_request(ctx).await;
+        drop(permit);
+        response
     }
 }
 
@@ -304,30 +881,51 @@ async fn handle_validated_request(ctx: RequestContext, use_buffered_stream: bool
 /// - `use_buffered_stream`: 是否使用缓冲流模式
 ///   - `false`: 标准流模式，立即发送 message_start
 ///   - `true`: 缓冲流模式（Claude Code），等待 contextUsageEvent 后再发送
-async fn handle_stream_request(ctx: RequestContext, use_buffered_stream: bool) -> Response {
+async fn handle_stream_request(
+    ctx: RequestContext,
+    use_buffered_stream: bool,
+    permit: Option<UpstreamPermit>,
+) -> Response {
     // Handler 层重试配置
     const MAX_HANDLER_RETRIES: usize = 2;
     let mut last_error = None;
+    let mut last_status = provider_error_status(None);
+    let mut last_upstream_request_id = None;
 
     for attempt in 0..MAX_HANDLER_RETRIES {
         // 调用 Kiro API（支持粘性会话轮询 + 多凭据故障转移）
-        let response = match ctx
+        let mut response = match ctx
             .provider
-            .call_api_stream_with_session(&ctx.request_body, ctx.session_id.as_deref())
+            .call_api_stream_with_session(
+                &ctx.request_body,
+                ctx.session_id.as_deref(),
+                ctx.user_key.as_deref(),
+            )
             .await
         {
             Ok(resp) => resp,
             Err(e) => {
                 let error_msg = e.to_string();
-                // 判断是否为可重试的错误（502/503/504 或网络错误）
-                let is_retryable = error_msg.contains("502")
-                    || error_msg.contains("503")
-                    || error_msg.contains("504")
-                    || error_msg.contains("Bad Gateway")
-                    || error_msg.contains("Service Unavailable")
-                    || error_msg.contains("Gateway Timeout")
-                    || error_msg.contains("connection")
-                    || error_msg.contains("timeout");
+                let upstream_request_id = UpstreamError::request_id_of(&e);
+                // 上游限流并要求退避：直接返回 429 并透传 Retry-After，不在 handler 层重试
+                if let Some(retry_after) = UpstreamError::retry_after_of(&e) {
+                    tracing::warn!(
+                        request_id = %ctx.request_id,
+                        upstream_request_id = ?upstream_request_id,
+                        "Kiro API 被限流: {}",
+                        e
+                    );
+                    return create_upstream_rate_limited_response(
+                        retry_after,
+                        e,
+                        ctx.locale,
+                        upstream_request_id,
+                    );
+                }
+                // 按失败原因判断是否可重试（上游瞬态错误、超时或链路错误）
+                let provider_error = ProviderError::of(&e);
+                let (status, error_type) = provider_error_status(provider_error);
+                let is_retryable = provider_error.is_some_and(ProviderError::is_retryable);
 
                 if is_retryable && attempt + 1 < MAX_HANDLER_RETRIES {
                     tracing::warn!(
@@ -337,20 +935,39 @@ async fn handle_stream_request(ctx: RequestContext, use_buffered_stream: bool) -
                         error_msg
                     );
                     last_error = Some(error_msg);
+                    last_status = (status, error_type);
+                    last_upstream_request_id = upstream_request_id;
                     // 短暂延迟后重试
                     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
                     continue;
                 }
 
-                tracing::error!("Kiro API 调用失败: {}", e);
-                return create_error_response(
-                    StatusCode::BAD_GATEWAY,
-                    "api_error",
-                    &format!("上游 API 调用失败: {}", e),
+                tracing::error!(
+                    request_id = %ctx.request_id,
+                    upstream_request_id = ?upstream_request_id,
+                    "Kiro API 调用失败: {}",
+                    e
+                );
+                return create_upstream_error_response(
+                    status,
+                    error_type,
+                    ErrorCode::UpstreamCallFailed.arg("detail", e),
+                    ctx.locale,
+                    upstream_request_id,
                 );
             }
         };
 
+        let upstream_request_id = KiroProvider::upstream_request_id(response.headers());
+        tracing::info!(
+            request_id = %ctx.request_id,
+            upstream_request_id = ?upstream_request_id,
+            "Kiro API 调用成功"
+        );
+
+        // 流式响应中途异常终止时用于上报凭据失败（同时持有凭据并发名额直到流结束）
+        let failure_reporter = StreamFailureReporter::new(&ctx, &mut response);
+
         // 成功获取响应，根据模式创建不同的 SSE 流
         if use_buffered_stream {
             // 缓冲流模式：等待 contextUsageEvent 后再发送 message_start
@@ -359,30 +976,59 @@ async fn handle_stream_request(ctx: RequestContext, use_buffered_stream: bool) -
                 ctx.input_tokens,
                 ctx.thinking_enabled,
             );
-            let stream = create_buffered_sse_stream(response, buffered_ctx);
-            return build_sse_response(stream);
+            let stream = create_buffered_sse_stream(
+                upstream_body(response, upstream_idle_timeout(&ctx)),
+                buffered_ctx,
+                EventStreamDecoder::new_with_config(ctx.decoder_buffer_size),
+                failure_reporter,
+                ctx.request_span.clone(),
+            );
+            return attach_upstream_request_id(
+                build_sse_response(hold_permit(
+                    with_warnings_comment(&ctx.warnings, stream),
+                    permit,
+                )),
+                upstream_request_id.as_deref(),
+            );
         } else {
             // 标准流模式：立即发送 message_start
             let mut stream_ctx = StreamContext::new_with_thinking(
                 &ctx.model,
                 ctx.input_tokens,
                 ctx.thinking_enabled,
-            );
+            )
+            .with_usage_updates();
             let initial_events = stream_ctx.generate_initial_events();
-            let stream = create_sse_stream(response, stream_ctx, initial_events);
-            return build_sse_response(stream);
+            let stream = create_sse_stream(
+                upstream_body(response, upstream_idle_timeout(&ctx)),
+                stream_ctx,
+                initial_events,
+                EventStreamDecoder::new_with_config(ctx.decoder_buffer_size),
+                failure_reporter,
+                ctx.request_span.clone(),
+            );
+            return attach_upstream_request_id(
+                build_sse_response(hold_permit(
+                    with_warnings_comment(&ctx.warnings, stream),
+                    permit,
+                )),
+                upstream_request_id.as_deref(),
+            );
         }
     }
 
     // 所有重试都失败
-    create_error_response(
-        StatusCode::BAD_GATEWAY,
-        "api_error",
-        &format!(
-            "上游 API 调用失败（已重试 {} 次）: {}",
-            MAX_HANDLER_RETRIES,
-            last_error.unwrap_or_else(|| "未知错误".to_string())
-        ),
+    create_upstream_error_response(
+        last_status.0,
+        last_status.1,
+        ErrorCode::UpstreamRetriesExhausted
+            .arg("retries", MAX_HANDLER_RETRIES)
+            .arg(
+                "detail",
+                last_error.unwrap_or_else(|| ErrorCode::UnknownError.template(ctx.locale).to_string()),
+            ),
+        ctx.locale,
+        last_upstream_request_id,
     )
 }
 
@@ -391,26 +1037,43 @@ async fn handle_non_stream_request(ctx: RequestContext) -> Response {
     // Handler 层重试配置
     const MAX_HANDLER_RETRIES: usize = 2;
     let mut last_error = None;
+    let mut last_status = provider_error_status(None);
+    let mut last_upstream_request_id = None;
 
     for attempt in 0..MAX_HANDLER_RETRIES {
         // 调用 Kiro API（支持粘性会话轮询 + 多凭据故障转移）
-        let response = match ctx
+        let mut response = match ctx
             .provider
-            .call_api_with_session(&ctx.request_body, ctx.session_id.as_deref())
+            .call_api_with_session(
+                &ctx.request_body,
+                ctx.session_id.as_deref(),
+                ctx.user_key.as_deref(),
+            )
             .await
         {
             Ok(resp) => resp,
             Err(e) => {
                 let error_msg = e.to_string();
-                // 判断是否为可重试的错误（502/503/504 或网络错误）
-                let is_retryable = error_msg.contains("502")
-                    || error_msg.contains("503")
-                    || error_msg.contains("504")
-                    || error_msg.contains("Bad Gateway")
-                    || error_msg.contains("Service Unavailable")
-                    || error_msg.contains("Gateway Timeout")
-                    || error_msg.contains("connection")
-                    || error_msg.contains("timeout");
+                let upstream_request_id = UpstreamError::request_id_of(&e);
+                // 上游限流并要求退避：直接返回 429 并透传 Retry-After，不在 handler 层重试
+                if let Some(retry_after) = UpstreamError::retry_after_of(&e) {
+                    tracing::warn!(
+                        request_id = %ctx.request_id,
+                        upstream_request_id = ?upstream_request_id,
+                        "Kiro API 被限流: {}",
+                        e
+                    );
+                    return create_upstream_rate_limited_response(
+                        retry_after,
+                        e,
+                        ctx.locale,
+                        upstream_request_id,
+                    );
+                }
+                // 按失败原因判断是否可重试（上游瞬态错误、超时或链路错误）
+                let provider_error = ProviderError::of(&e);
+                let (status, error_type) = provider_error_status(provider_error);
+                let is_retryable = provider_error.is_some_and(ProviderError::is_retryable);
 
                 if is_retryable && attempt + 1 < MAX_HANDLER_RETRIES {
                     tracing::warn!(
@@ -420,25 +1083,45 @@ async fn handle_non_stream_request(ctx: RequestContext) -> Response {
                         error_msg
                     );
                     last_error = Some(error_msg);
+                    last_status = (status, error_type);
+                    last_upstream_request_id = upstream_request_id;
                     // 短暂延迟后重试
                     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
                     continue;
                 }
 
-                tracing::error!("Kiro API 调用失败: {}", e);
-                return create_error_response(
-                    StatusCode::BAD_GATEWAY,
-                    "api_error",
-                    &format!("上游 API 调用失败: {}", e),
+                tracing::error!(
+                    request_id = %ctx.request_id,
+                    upstream_request_id = ?upstream_request_id,
+                    "Kiro API 调用失败: {}",
+                    e
+                );
+                return create_upstream_error_response(
+                    status,
+                    error_type,
+                    ErrorCode::UpstreamCallFailed.arg("detail", e),
+                    ctx.locale,
+                    upstream_request_id,
                 );
             }
         };
 
-        // 读取响应体
-        let body_bytes = match response.bytes().await {
+        let upstream_request_id = KiroProvider::upstream_request_id(response.headers());
+        tracing::info!(
+            request_id = %ctx.request_id,
+            upstream_request_id = ?upstream_request_id,
+            "Kiro API 调用成功"
+        );
+
+        // 读取响应体（读完前持有凭据并发名额，长时间未收到数据时中止）
+        let credential_permit = KiroProvider::take_credential_permit(&mut response);
+        let body_bytes = read_upstream_body(response, upstream_idle_timeout(&ctx)).await;
+        drop(credential_permit);
+        let body_bytes = match body_bytes {
             Ok(bytes) => bytes,
             Err(e) => {
                 let error_msg = e.to_string();
+                let (status, error_type) = provider_error_status(Some(&e.provider_error()));
                 if attempt + 1 < MAX_HANDLER_RETRIES {
                     tracing::warn!(
                         "读取响应体失败（尝试 {}/{}），准备重试: {}",
@@ -447,41 +1130,81 @@ async fn handle_non_stream_request(ctx: RequestContext) -> Response {
                         error_msg
                     );
                     last_error = Some(error_msg);
+                    last_status = (status, error_type);
+                    last_upstream_request_id = upstream_request_id;
                     tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
                     continue;
                 }
 
-                tracing::error!("读取响应体失败: {}", e);
-                return create_error_response(
-                    StatusCode::BAD_GATEWAY,
-                    "api_error",
-                    &format!("读取响应失败: {}", e),
+                tracing::error!(
+                    request_id = %ctx.request_id,
+                    upstream_request_id = ?upstream_request_id,
+                    "读取响应体失败: {}",
+                    e
+                );
+                return create_upstream_error_response(
+                    status,
+                    error_type,
+                    ErrorCode::UpstreamReadFailed.arg("detail", e),
+                    ctx.locale,
+                    upstream_request_id,
                 );
             }
         };
 
         // 解析事件流并构建响应
-        return build_non_stream_response(&body_bytes, &ctx.model, ctx.input_tokens);
+        return attach_upstream_request_id(
+            build_non_stream_response(
+                &body_bytes,
+                &ctx.model,
+                ctx.input_tokens,
+                ctx.decoder_buffer_size,
+                &ctx.request_span,
+                &ctx.warnings,
+                ctx.locale,
+            ),
+            upstream_request_id.as_deref(),
+        );
     }
 
     // 所有重试都失败
-    create_error_response(
-        StatusCode::BAD_GATEWAY,
-        "api_error",
-        &format!(
-            "上游 API 调用失败（已重试 {} 次）: {}",
-            MAX_HANDLER_RETRIES,
-            last_error.unwrap_or_else(|| "未知错误".to_string())
-        ),
+    create_upstream_error_response(
+        last_status.0,
+        last_status.1,
+        ErrorCode::UpstreamRetriesExhausted
+            .arg("retries", MAX_HANDLER_RETRIES)
+            .arg(
+                "detail",
+                last_error.unwrap_or_else(|| ErrorCode::UnknownError.template(ctx.locale).to_string()),
+            ),
+        ctx.locale,
+        last_upstream_request_id,
     )
 }
 
 /// 构建非流式响应
-fn build_non_stream_response(body_bytes: &[u8], model: &str, input_tokens: i32) -> Response {
+///
+/// 响应体超出解码缓冲区上限时返回 502（无法完整解析，不返回截断的内容）
+fn build_non_stream_response(
+    body_bytes: &[u8],
+    model: &str,
+    input_tokens: i32,
+    decoder_buffer_size: usize,
+    request_span: &RequestSpan,
+    warnings: &[ConversionWarning],
+    locale: Locale,
+) -> Response {
     // 解析事件流
-    let mut decoder = EventStreamDecoder::new();
+    let mut decoder = EventStreamDecoder::new_with_config(decoder_buffer_size);
     if let Err(e) = decoder.feed(body_bytes) {
-        tracing::warn!("缓冲区溢出: {}", e);
+        tracing::warn!("解码缓冲区溢出，拒绝非流式响应: {}", e);
+        request_span.finish(0);
+        return create_error_response(
+            StatusCode::BAD_GATEWAY,
+            "api_error",
+            ErrorCode::UpstreamResponseTooLarge.arg("detail", e),
+            locale,
+        );
     }
 
     let mut text_content = String::new();
@@ -491,6 +1214,8 @@ fn build_non_stream_response(body_bytes: &[u8], model: &str, input_tokens: i32)
     let mut context_input_tokens: Option<i32> = None;
     let mut tool_json_buffers: std::collections::HashMap<String, String> =
         std::collections::HashMap::new();
+    // 非流式响应的文本是单个块，只去除控制字符，不处理工具调用后的空白
+    let mut text_sanitizer = TextSanitizer::default();
 
     for result in decoder.decode_iter() {
         match result {
@@ -498,7 +1223,7 @@ fn build_non_stream_response(body_bytes: &[u8], model: &str, input_tokens: i32)
                 if let Ok(event) = Event::from_frame(frame) {
                     match event {
                         Event::AssistantResponse(resp) => {
-                            text_content.push_str(&resp.content);
+                            text_content.push_str(&text_sanitizer.sanitize(&resp.content));
                         }
                         Event::ToolUse(tool_use) => {
                             has_tool_use = true;
@@ -508,7 +1233,8 @@ fn build_non_stream_response(body_bytes: &[u8], model: &str, input_tokens: i32)
                             buffer.push_str(&tool_use.input);
 
                             if tool_use.stop {
-                                let input: serde_json::Value = serde_json::from_str(buffer)
+                                let repaired = repair_tool_json(&tool_use.tool_use_id, buffer);
+                                let input: serde_json::Value = serde_json::from_str(&repaired)
                                     .unwrap_or_else(|e| {
                                         tracing::warn!(
                                             "工具输入 JSON 解析失败: {}, tool_use_id: {}, 原始内容: {}",
@@ -569,9 +1295,10 @@ fn build_non_stream_response(body_bytes: &[u8], model: &str, input_tokens: i32)
     // 估算输出 tokens
     let output_tokens = token::estimate_output_tokens(&content);
     let final_input_tokens = context_input_tokens.unwrap_or(input_tokens);
+    request_span.finish(output_tokens);
 
     // 构建 Anthropic 响应
-    let response_body = json!({
+    let mut response_body = json!({
         "id": format!("msg_{}", Uuid::new_v4().to_string().replace('-', "")),
         "type": "message",
         "role": "assistant",
@@ -584,6 +1311,9 @@ fn build_non_stream_response(body_bytes: &[u8], model: &str, input_tokens: i32)
             "output_tokens": output_tokens
         }
     });
+    if !warnings.is_empty() {
+        response_body["kiro_warnings"] = json!(warnings);
+    }
 
     (StatusCode::OK, Json(response_body)).into_response()
 }
@@ -602,6 +1332,163 @@ where
         .unwrap()
 }
 
+/// 在 SSE 流最前面（`message_start` 之前）插入转换警告注释行
+///
+/// 格式为 `: kiro_warnings <JSON 数组>`，按 SSE 规范客户端会忽略注释行；没有警告时不插入
+fn with_warnings_comment<S>(
+    warnings: &[ConversionWarning],
+    stream: S,
+) -> impl Stream<Item = Result<Bytes, Infallible>> + use<S>
+where
+    S: Stream<Item = Result<Bytes, Infallible>>,
+{
+    let comment = (!warnings.is_empty()).then(|| {
+        Ok(Bytes::from(format!(
+            ": kiro_warnings {}\n\n",
+            serde_json::to_string(warnings).unwrap_or_default()
+        )))
+    });
+    stream::iter(comment).chain(stream)
+}
+
+/// 让 SSE 流持有上游并发名额
+///
+/// 名额随流一起释放：流结束，或客户端断开导致响应体被丢弃时
+fn hold_permit<S>(stream: S, permit: Option<UpstreamPermit>) -> impl Stream<Item = S::Item>
+where
+    S: Stream,
+{
+    stream.map(move |item| {
+        let _permit = &permit;
+        item
+    })
+}
+
+/// 流式响应异常终止时的凭据失败上报
+///
+/// HTTP 200 之后上游连接仍可能中断，此时需要计入凭据失败以便轮换不稳定的账号；
+/// 随流一起存活，因此同时持有凭据并发名额，流结束或被丢弃时释放
+struct StreamFailureReporter {
+    provider: Arc<KiroProvider>,
+    credential_id: Option<u64>,
+    /// 调用时间线中记录的模型
+    model: String,
+    /// 流开始时间（失败事件的耗时从此计算）
+    started: Instant,
+    _permit: Option<CredentialPermit>,
+}
+
+impl StreamFailureReporter {
+    fn new(ctx: &RequestContext, response: &mut reqwest::Response) -> Self {
+        Self {
+            provider: ctx.provider.clone(),
+            credential_id: KiroProvider::serving_credential(response),
+            model: KiroProvider::timeline_model(&ctx.request_body).to_string(),
+            started: Instant::now(),
+            _permit: KiroProvider::take_credential_permit(response),
+        }
+    }
+
+    fn report(&self, reason: &str) {
+        tracing::error!(credential_id = ?self.credential_id, "{}", reason);
+        if let Some(id) = self.credential_id {
+            let latency_ms = self.started.elapsed().as_millis() as u64;
+            self.provider
+                .report_stream_failure(id, &self.model, latency_ms);
+        }
+    }
+}
+
+/// 上游响应流读取失败（reqwest 错误信息不含底层原因，需要拼接 source 链）
+fn stream_interrupted_reason(e: &reqwest::Error) -> String {
+    let mut reason = format!("上游响应流中断: {}", e);
+    let mut source = std::error::Error::source(e);
+    while let Some(cause) = source {
+        reason.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    reason
+}
+
+/// 读取上游响应体失败的原因
+#[derive(Debug)]
+enum UpstreamBodyError {
+    /// 链路错误
+    Read(reqwest::Error),
+    /// 超过空闲超时未收到数据
+    Idle(Duration),
+}
+
+impl UpstreamBodyError {
+    /// 对应的调用失败原因（决定重试和 HTTP 状态码）
+    fn provider_error(&self) -> ProviderError {
+        match self {
+            Self::Read(e) => ProviderError::from_reqwest(e),
+            Self::Idle(_) => ProviderError::Timeout(TimeoutKind::Idle),
+        }
+    }
+}
+
+impl std::fmt::Display for UpstreamBodyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read(e) => f.write_str(&stream_interrupted_reason(e)),
+            Self::Idle(idle) => write!(f, "上游响应超过 {} 秒未收到数据", idle.as_secs()),
+        }
+    }
+}
+
+/// 上游响应体数据流
+type UpstreamBody = stream::BoxStream<'static, Result<Bytes, UpstreamBodyError>>;
+
+/// 为上游响应体加上空闲超时：超过 `idle` 未收到数据时产生 [`UpstreamBodyError::Idle`] 并结束
+///
+/// 计时在两次数据之间持续累计，不会因 ping 保活等并发分支打断 `next()` 而重置
+fn upstream_body(response: reqwest::Response, idle: Duration) -> UpstreamBody {
+    stream::unfold(Some(response.bytes_stream()), move |body| async move {
+        let mut body = body?;
+        match tokio::time::timeout(idle, body.next()).await {
+            Ok(Some(Ok(chunk))) => Some((Ok(chunk), Some(body))),
+            Ok(Some(Err(e))) => Some((Err(UpstreamBodyError::Read(e)), Some(body))),
+            Ok(None) => None,
+            Err(_) => Some((Err(UpstreamBodyError::Idle(idle)), None)),
+        }
+    })
+    .boxed()
+}
+
+/// 读取上游响应体的空闲超时（取请求所用池的配置）
+fn upstream_idle_timeout(ctx: &RequestContext) -> Duration {
+    ctx.provider
+        .token_manager()
+        .config()
+        .upstream_idle_timeout()
+}
+
+/// 读取完整的上游响应体（非流式请求），受空闲超时限制
+async fn read_upstream_body(
+    response: reqwest::Response,
+    idle: Duration,
+) -> Result<Bytes, UpstreamBodyError> {
+    let mut body = upstream_body(response, idle);
+    let mut bytes = BytesMut::new();
+    while let Some(chunk) = body.next().await {
+        bytes.extend_from_slice(&chunk?);
+    }
+    Ok(bytes.freeze())
+}
+
+/// 上游响应流在结束事件之前关闭
+const STREAM_ENDED_EARLY_REASON: &str = "上游响应流在结束事件之前关闭";
+
+/// 将 SSE 事件转换为响应字节
+fn sse_bytes(events: Vec<SseEvent>) -> Vec<Result<Bytes, Infallible>> {
+    events
+        .into_iter()
+        .map(|e| Ok(Bytes::from(e.to_sse_string())))
+        .collect()
+}
+
 /// 创建 ping 事件的 SSE 字符串
 fn create_ping_sse() -> Bytes {
     Bytes::from("event: ping\ndata: {\"type\": \"ping\"}\n\n")
@@ -609,9 +1496,12 @@ fn create_ping_sse() -> Bytes {
 
 /// 创建 SSE 事件流
 fn create_sse_stream(
-    response: reqwest::Response,
+    body_stream: UpstreamBody,
     ctx: StreamContext,
     initial_events: Vec<SseEvent>,
+    decoder: EventStreamDecoder,
+    failure_reporter: StreamFailureReporter,
+    request_span: RequestSpan,
 ) -> impl Stream<Item = Result<Bytes, Infallible>> {
     // 先发送初始事件
     let initial_stream = stream::iter(
@@ -621,70 +1511,71 @@ fn create_sse_stream(
     );
 
     // 然后处理 Kiro 响应流，同时每25秒发送 ping 保活
-    let body_stream = response.bytes_stream();
-
     let processing_stream = stream::unfold(
-        (body_stream, ctx, EventStreamDecoder::new(), false, interval(Duration::from_secs(PING_INTERVAL_SECS))),
-        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval)| async move {
-            if finished {
-                return None;
-            }
+        (body_stream, ctx, decoder, false, interval(Duration::from_secs(PING_INTERVAL_SECS)), failure_reporter),
+        move |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval, failure_reporter)| {
+            let request_span = request_span.clone();
+            let span = request_span.span().clone();
+            async move {
+                if finished {
+                    return None;
+                }
 
-            tokio::select! {
-                chunk_result = body_stream.next() => {
-                    match chunk_result {
-                        Some(Ok(chunk)) => {
-                            if let Err(e) = decoder.feed(&chunk) {
-                                tracing::warn!("缓冲区溢出: {}", e);
-                            }
+                tokio::select! {
+                    chunk_result = body_stream.next() => {
+                        match chunk_result {
+                            Some(Ok(chunk)) => {
+                                if let Err(e) = decoder.feed(&chunk) {
+                                    // 缓冲区溢出：继续读取只会丢失数据，以错误事件终止流
+                                    tracing::warn!("解码缓冲区溢出，终止流式响应: {}", e);
+                                    let final_events = ctx.generate_abort_events(&e.to_string());
+                                    request_span.finish(ctx.output_tokens);
+                                    return Some((stream::iter(sse_bytes(final_events)), (body_stream, ctx, decoder, true, ping_interval, failure_reporter)));
+                                }
 
-                            let mut events = Vec::new();
-                            for result in decoder.decode_iter() {
-                                match result {
-                                    Ok(frame) => {
-                                        if let Ok(event) = Event::from_frame(frame) {
-                                            let sse_events = ctx.process_kiro_event(&event);
-                                            events.extend(sse_events);
+                                let mut events = Vec::new();
+                                for result in decoder.decode_iter() {
+                                    match result {
+                                        Ok(frame) => {
+                                            if let Ok(event) = Event::from_frame(frame) {
+                                                let sse_events = ctx.process_kiro_event(&event);
+                                                events.extend(sse_events);
+                                            }
+                                        }
+                                        Err(e) => {
+                                            tracing::warn!("解码事件失败: {}", e);
                                         }
-                                    }
-                                    Err(e) => {
-                                        tracing::warn!("解码事件失败: {}", e);
                                     }
                                 }
-                            }
 
-                            let bytes: Vec<Result<Bytes, Infallible>> = events
-                                .into_iter()
-                                .map(|e| Ok(Bytes::from(e.to_sse_string())))
-                                .collect();
-
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval)))
-                        }
-                        Some(Err(e)) => {
-                            tracing::error!("读取响应流失败: {}", e);
-                            let final_events = ctx.generate_final_events();
-                            let bytes: Vec<Result<Bytes, Infallible>> = final_events
-                                .into_iter()
-                                .map(|e| Ok(Bytes::from(e.to_sse_string())))
-                                .collect();
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)))
-                        }
-                        None => {
-                            let final_events = ctx.generate_final_events();
-                            let bytes: Vec<Result<Bytes, Infallible>> = final_events
-                                .into_iter()
-                                .map(|e| Ok(Bytes::from(e.to_sse_string())))
-                                .collect();
-                            Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)))
+                                Some((stream::iter(sse_bytes(events)), (body_stream, ctx, decoder, false, ping_interval, failure_reporter)))
+                            }
+                            Some(Err(e)) => {
+                                let reason = e.to_string();
+                                failure_reporter.report(&reason);
+                                let final_events = ctx.generate_abort_events(&reason);
+                                Some((stream::iter(sse_bytes(final_events)), (body_stream, ctx, decoder, true, ping_interval, failure_reporter)))
+                            }
+                            None => {
+                                let final_events = if ctx.upstream_completed {
+                                    ctx.generate_final_events()
+                                } else {
+                                    failure_reporter.report(STREAM_ENDED_EARLY_REASON);
+                                    ctx.generate_abort_events(STREAM_ENDED_EARLY_REASON)
+                                };
+                                request_span.finish(ctx.output_tokens);
+                                Some((stream::iter(sse_bytes(final_events)), (body_stream, ctx, decoder, true, ping_interval, failure_reporter)))
+                            }
                         }
                     }
-                }
-                _ = ping_interval.tick() => {
-                    tracing::trace!("发送 ping 保活事件");
-                    let bytes: Vec<Result<Bytes, Infallible>> = vec![Ok(create_ping_sse())];
-                    Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval)))
+                    _ = ping_interval.tick() => {
+                        tracing::trace!("发送 ping 保活事件");
+                        let bytes: Vec<Result<Bytes, Infallible>> = vec![Ok(create_ping_sse())];
+                        Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval, failure_reporter)))
+                    }
                 }
             }
+            .instrument(span)
         },
     )
     .flatten();
@@ -694,76 +1585,1189 @@ fn create_sse_stream(
 
 /// 创建缓冲 SSE 事件流
 fn create_buffered_sse_stream(
-    response: reqwest::Response,
+    body_stream: UpstreamBody,
     ctx: BufferedStreamContext,
+    decoder: EventStreamDecoder,
+    failure_reporter: StreamFailureReporter,
+    request_span: RequestSpan,
 ) -> impl Stream<Item = Result<Bytes, Infallible>> {
-    let body_stream = response.bytes_stream();
-
     stream::unfold(
         (
             body_stream,
             ctx,
-            EventStreamDecoder::new(),
+            decoder,
             false,
             interval(Duration::from_secs(PING_INTERVAL_SECS)),
+            failure_reporter,
         ),
-        |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval)| async move {
-            if finished {
-                return None;
-            }
+        move |(mut body_stream, mut ctx, mut decoder, finished, mut ping_interval, failure_reporter)| {
+            let request_span = request_span.clone();
+            let span = request_span.span().clone();
+            async move {
+                if finished {
+                    return None;
+                }
 
-            loop {
-                tokio::select! {
-                    biased;
+                loop {
+                    tokio::select! {
+                        biased;
 
-                    _ = ping_interval.tick() => {
-                        tracing::trace!("发送 ping 保活事件（缓冲模式）");
-                        let bytes: Vec<Result<Bytes, Infallible>> = vec![Ok(create_ping_sse())];
-                        return Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval)));
-                    }
+                        _ = ping_interval.tick() => {
+                            tracing::trace!("发送 ping 保活事件（缓冲模式）");
+                            let bytes: Vec<Result<Bytes, Infallible>> = vec![Ok(create_ping_sse())];
+                            return Some((stream::iter(bytes), (body_stream, ctx, decoder, false, ping_interval, failure_reporter)));
+                        }
 
-                    chunk_result = body_stream.next() => {
-                        match chunk_result {
-                            Some(Ok(chunk)) => {
-                                if let Err(e) = decoder.feed(&chunk) {
-                                    tracing::warn!("缓冲区溢出: {}", e);
-                                }
+                        chunk_result = body_stream.next() => {
+                            match chunk_result {
+                                Some(Ok(chunk)) => {
+                                    if let Err(e) = decoder.feed(&chunk) {
+                                        tracing::warn!("解码缓冲区溢出，终止流式响应: {}", e);
+                                        let all_events = ctx.abort_and_get_all_events(&e.to_string());
+                                        request_span.finish(ctx.output_tokens());
+                                        return Some((stream::iter(sse_bytes(all_events)), (body_stream, ctx, decoder, true, ping_interval, failure_reporter)));
+                                    }
 
-                                for result in decoder.decode_iter() {
-                                    match result {
-                                        Ok(frame) => {
-                                            if let Ok(event) = Event::from_frame(frame) {
-                                                ctx.process_and_buffer(&event);
+                                    for result in decoder.decode_iter() {
+                                        match result {
+                                            Ok(frame) => {
+                                                if let Ok(event) = Event::from_frame(frame) {
+                                                    ctx.process_and_buffer(&event);
+                                                }
+                                            }
+                                            Err(e) => {
+                                                tracing::warn!("解码事件失败: {}", e);
                                             }
-                                        }
-                                        Err(e) => {
-                                            tracing::warn!("解码事件失败: {}", e);
                                         }
                                     }
                                 }
-                            }
-                            Some(Err(e)) => {
-                                tracing::error!("读取响应流失败: {}", e);
-                                let all_events = ctx.finish_and_get_all_events();
-                                let bytes: Vec<Result<Bytes, Infallible>> = all_events
-                                    .into_iter()
-                                    .map(|e| Ok(Bytes::from(e.to_sse_string())))
-                                    .collect();
-                                return Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)));
-                            }
-                            None => {
-                                let all_events = ctx.finish_and_get_all_events();
-                                let bytes: Vec<Result<Bytes, Infallible>> = all_events
-                                    .into_iter()
-                                    .map(|e| Ok(Bytes::from(e.to_sse_string())))
-                                    .collect();
-                                return Some((stream::iter(bytes), (body_stream, ctx, decoder, true, ping_interval)));
+                                Some(Err(e)) => {
+                                    let reason = e.to_string();
+                                    failure_reporter.report(&reason);
+                                    let all_events = ctx.abort_and_get_all_events(&reason);
+                                    return Some((stream::iter(sse_bytes(all_events)), (body_stream, ctx, decoder, true, ping_interval, failure_reporter)));
+                                }
+                                None => {
+                                    let all_events = if ctx.is_upstream_completed() {
+                                        ctx.finish_and_get_all_events()
+                                    } else {
+                                        failure_reporter.report(STREAM_ENDED_EARLY_REASON);
+                                        ctx.abort_and_get_all_events(STREAM_ENDED_EARLY_REASON)
+                                    };
+                                    request_span.finish(ctx.output_tokens());
+                                    return Some((stream::iter(sse_bytes(all_events)), (body_stream, ctx, decoder, true, ping_interval, failure_reporter)));
+                                }
                             }
                         }
                     }
                 }
             }
+            .instrument(span)
         },
     )
     .flatten()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin::ApiKeyManager;
+    use crate::anthropic::dedup::DEDUP_HEADER;
+    use crate::anthropic::mock_provider::{MOCK_REQUEST_ID, MockKiroProvider, MockResponse};
+    use crate::common::features::FeatureFlags;
+    use crate::model::config::Config;
+
+    /// 创建使用 Mock Provider 的应用状态
+    fn mock_state(provider: &Arc<KiroProvider>) -> AppState {
+        let dir = tempfile::tempdir().unwrap();
+        let api_key_manager =
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap());
+        let mut state = AppState::new(api_key_manager, Arc::new(Config::default()));
+        state.kiro_provider = Some(provider.clone());
+        state
+    }
+
+    /// 使用 Mock Provider 调用完整的消息处理流程
+    async fn send(
+        provider: &Arc<KiroProvider>,
+        request: serde_json::Value,
+        use_buffered_stream: bool,
+    ) -> (StatusCode, HeaderMap, String) {
+        send_with_state(mock_state(provider), request, use_buffered_stream).await
+    }
+
+    /// 使用指定应用状态调用完整的消息处理流程
+    async fn send_with_state(
+        state: AppState,
+        request: serde_json::Value,
+        use_buffered_stream: bool,
+    ) -> (StatusCode, HeaderMap, String) {
+        send_with_headers(state, request, HeaderMap::new(), use_buffered_stream).await
+    }
+
+    /// 使用指定应用状态和请求头调用完整的消息处理流程
+    async fn send_with_headers(
+        state: AppState,
+        request: serde_json::Value,
+        headers: HeaderMap,
+        use_buffered_stream: bool,
+    ) -> (StatusCode, HeaderMap, String) {
+        let payload: MessagesRequest = serde_json::from_value(request).unwrap();
+        let response = handle_messages_request(
+            state,
+            AuthenticatedPoolId(vec![]),
+            headers,
+            payload,
+            "/v1/messages",
+            use_buffered_stream,
+        )
+        .await;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, headers, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn request(stream: bool) -> serde_json::Value {
+        json!({
+            "model": "claude-sonnet-4-5-20250929",
+            "max_tokens": 1024,
+            "stream": stream,
+            "messages": [{"role": "user", "content": "What's the weather in Paris?"}]
+        })
+    }
+
+    fn mock(provider: &KiroProvider) -> &MockKiroProvider {
+        provider.mock().unwrap()
+    }
+
+    /// 调用 count_tokens 并解析响应 JSON
+    async fn count(
+        state: AppState,
+        pool_ids: Vec<String>,
+        detailed: bool,
+        request: serde_json::Value,
+    ) -> serde_json::Value {
+        let response = count_tokens(
+            State(state),
+            Extension(AuthenticatedPoolId(pool_ids)),
+            Query(CountTokensQuery { detailed }),
+            HeaderMap::new(),
+            JsonExtractor(serde_json::from_value(request).unwrap()),
+        )
+        .await
+        .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens_detailed_is_additive() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![]));
+        let request = json!({
+            "model": "claude-sonnet-4-5-20250929",
+            "max_tokens": 1024,
+            "messages": [{"role": "user", "content": "What's the weather in Paris?"}]
+        });
+
+        // 默认只返回 input_tokens 和计数来源
+        let body = count(mock_state(&provider), vec![], false, request.clone()).await;
+        assert_eq!(body.as_object().unwrap().len(), 2);
+        assert_eq!(body["token_count_source"], "estimated");
+        let input_tokens = body["input_tokens"].as_i64().unwrap();
+
+        let body = count(mock_state(&provider), vec![], true, request).await;
+        assert_eq!(body["input_tokens"].as_i64().unwrap(), input_tokens);
+        assert_eq!(body["context_window"], CONTEXT_WINDOW_SIZE);
+        assert_eq!(
+            body["effective_input_tokens"].as_i64().unwrap(),
+            input_tokens
+        );
+        assert_eq!(body["estimated_output_tokens"], 1024);
+        // 未绑定池时不返回池状态
+        assert!(body.get("pool").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens_detailed_reports_bound_pool() {
+        use crate::kiro::pool::{Pool, PoolsConfig};
+        use crate::kiro::pool_manager::PoolManager;
+
+        let dir = tempfile::tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        let pools = PoolsConfig {
+            pools: vec![Pool::new("gold", "金池")],
+        };
+        std::fs::write(&pools_path, serde_json::to_string(&pools).unwrap()).unwrap();
+        let credentials: Vec<serde_json::Value> = (1..=3)
+            .map(|id| json!({"id": id, "refreshToken": "a".repeat(150), "poolId": "gold"}))
+            .collect();
+        std::fs::write(
+            &credentials_path,
+            serde_json::to_string(&credentials).unwrap(),
+        )
+        .unwrap();
+        let pool_manager = Arc::new(
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap(),
+        );
+
+        let token_manager = pool_manager
+            .get_pool("gold")
+            .unwrap()
+            .read()
+            .token_manager
+            .clone();
+        token_manager.set_disabled(3, true, "admin").unwrap();
+        token_manager.set_cached_usage(1, 30.0, 100.0);
+        token_manager.set_cached_usage(2, 100.0, 100.0);
+
+        let provider = Arc::new(KiroProvider::new_mock(vec![]));
+        let mut state = mock_state(&provider);
+        state.pool_manager = Some(pool_manager);
+        let request = json!({
+            "model": "claude-sonnet-4-5-20250929",
+            "max_tokens": 500000,
+            "messages": [{"role": "user", "content": "hi"}]
+        });
+
+        let body = count(state, vec!["gold".to_string()], true, request).await;
+        assert_eq!(body["pool"]["id"], "gold");
+        assert_eq!(body["pool"]["available_credentials"], 2);
+        assert_eq!(body["pool"]["remaining_quota_percentage"], 35.0);
+        // 输出上限不超过剩余上下文
+        let effective = body["effective_input_tokens"].as_i64().unwrap();
+        assert_eq!(
+            body["estimated_output_tokens"].as_i64().unwrap(),
+            CONTEXT_WINDOW_SIZE as i64 - effective
+        );
+    }
+
+    #[tokio::test]
+    async fn test_non_stream_text_response() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Json(
+            r#"[
+                {"assistantResponseEvent": {"content": "Hello"}},
+                {"assistantResponseEvent": {"content": ", world!"}},
+                {"contextUsageEvent": {"contextUsagePercentage": 1.0}}
+            ]"#
+            .to_string(),
+        )]));
+
+        let (status, headers, body) = send(&provider, request(false), false).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[UPSTREAM_REQUEST_ID_HEADER], MOCK_REQUEST_ID);
+
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["content"][0]["text"], "Hello, world!");
+        assert_eq!(body["stop_reason"], "end_turn");
+        assert_eq!(body["usage"]["input_tokens"], CONTEXT_WINDOW_SIZE / 100);
+
+        assert_eq!(mock(&provider).call_count(), 1);
+        let last_request = mock(&provider).last_request().unwrap();
+        assert!(last_request.contains("What's the weather in Paris?"));
+    }
+
+    /// 收集日志输出
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<parking_lot::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_span_fields_in_logs() {
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        // Pretty 格式：每个事件下列出所在 span 及其字段
+        let subscriber = tracing_subscriber::fmt()
+            .pretty()
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        for stream in [false, true] {
+            let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Json(
+                r#"[
+                    {"assistantResponseEvent": {"content": "Hello"}},
+                    {"contextUsageEvent": {"contextUsagePercentage": 1.0}}
+                ]"#
+                .to_string(),
+            )]));
+            let (status, _, _) = send(&provider, request(stream), false).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        let output = String::from_utf8(logs.0.lock().clone()).unwrap();
+        let completed: Vec<&str> = output
+            .split("请求完成")
+            .skip(1)
+            .map(|rest| rest.split("\n\n").next().unwrap())
+            .collect();
+        assert_eq!(completed.len(), 2, "{}", output);
+        for (event, stream) in completed.iter().zip(["false", "true"]) {
+            assert!(event.contains("in kiro_rs::anthropic::request_span::handle_request"));
+            assert!(event.contains("request_id: "));
+            assert!(event.contains("model: claude-sonnet-4-5-20250929"));
+            assert!(event.contains(&format!("stream: {}", stream)));
+            assert!(event.contains("pool_id: default"));
+            assert!(event.contains("credential_id: 1"));
+            assert!(event.contains("input_tokens: "));
+            assert!(event.contains("output_tokens: "));
+            assert!(event.contains("latency_ms: "));
+        }
+        // 请求内的其他日志同样携带请求属性
+        assert!(output.contains("Kiro API 调用成功"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_text_response() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Stream(vec![
+            r#"{"assistantResponseEvent": {"content": "Hello"}}"#.to_string(),
+            r#"{"assistantResponseEvent": {"content": ", world!"}}"#.to_string(),
+            r#"{"contextUsageEvent": {"contextUsagePercentage": 1.0}}"#.to_string(),
+        ])]));
+
+        let (status, headers, body) = send(&provider, request(true), false).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[header::CONTENT_TYPE], "text/event-stream");
+        assert_eq!(headers[UPSTREAM_REQUEST_ID_HEADER], MOCK_REQUEST_ID);
+
+        let message_start = body.find("event: message_start").unwrap();
+        let first_delta = body.find(r#""text":"Hello""#).unwrap();
+        let second_delta = body.find(r#""text":", world!""#).unwrap();
+        let message_stop = body.find("event: message_stop").unwrap();
+        assert!(message_start < first_delta);
+        assert!(first_delta < second_delta);
+        assert!(second_delta < message_stop);
+        assert!(!body.contains("event: error"), "{}", body);
+        assert!(body.contains(r#""stop_reason":"end_turn""#), "{}", body);
+        assert_eq!(mock(&provider).call_count(), 1);
+    }
+
+    /// 凭据的失败次数
+    fn failure_count(provider: &KiroProvider) -> u32 {
+        provider.token_manager().snapshot().entries[0].failure_count
+    }
+
+    #[tokio::test]
+    async fn test_stream_interrupted_emits_error_event() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::StreamError {
+            events: vec![
+                r#"{"assistantResponseEvent": {"content": "Hello"}}"#.to_string(),
+                r#"{"assistantResponseEvent": {"content": ", wor"}}"#.to_string(),
+            ],
+            error: "connection reset by peer".to_string(),
+        }]));
+
+        let (status, _, body) = send(&provider, request(true), false).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let first_delta = body.find(r#""text":"Hello""#).unwrap();
+        let second_delta = body.find(r#""text":", wor""#).unwrap();
+        let block_stop = body.find("event: content_block_stop").unwrap();
+        let error = body.find("event: error").unwrap();
+        let message_delta = body.find("event: message_delta").unwrap();
+        let message_stop = body.find("event: message_stop").unwrap();
+        assert!(first_delta < second_delta);
+        assert!(second_delta < block_stop);
+        assert!(block_stop < error);
+        assert!(error < message_delta);
+        assert!(message_delta < message_stop);
+
+        assert!(body.contains(r#""type":"overloaded_error""#), "{}", body);
+        assert!(body.contains("connection reset by peer"), "{}", body);
+        assert!(body.contains(r#""stop_reason":null"#), "{}", body);
+        assert_eq!(failure_count(&provider), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_upstream_body_hits_idle_timeout() {
+        let config = Config {
+            upstream_idle_timeout_secs: 1,
+            ..Config::default()
+        };
+        let provider = Arc::new(KiroProvider::new_mock_with_config(
+            config,
+            vec![MockResponse::Stall(vec![
+                r#"{"assistantResponseEvent": {"content": "Hello"}}"#.to_string(),
+            ])],
+        ));
+        let state = mock_state(&provider);
+
+        // 流式：以错误事件结束，并上报凭据失败
+        let (status, _, body) = send_with_state(state.clone(), request(true), false).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#""text":"Hello""#), "{}", body);
+        assert!(body.contains("上游响应超过 1 秒未收到数据"), "{}", body);
+        assert!(body.contains("event: message_stop"), "{}", body);
+        assert_eq!(failure_count(&provider), 1);
+
+        // 非流式：按读取失败重试，最终返回 504
+        let (status, _, body) = send_with_state(state, request(false), false).await;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT, "{}", body);
+        assert!(body.contains("upstream_read_failed"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn test_buffered_stream_ended_early_emits_error_event() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Stream(vec![
+            r#"{"assistantResponseEvent": {"content": "Hello"}}"#.to_string(),
+            r#"{"assistantResponseEvent": {"content": ", wor"}}"#.to_string(),
+        ])]));
+
+        let (status, _, body) = send(&provider, request(true), true).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let second_delta = body.find(r#""text":", wor""#).unwrap();
+        let error = body.find("event: error").unwrap();
+        let message_stop = body.find("event: message_stop").unwrap();
+        assert!(second_delta < error);
+        assert!(error < message_stop);
+        assert!(body.contains(STREAM_ENDED_EARLY_REASON), "{}", body);
+        assert!(body.contains(r#""stop_reason":null"#), "{}", body);
+        assert_eq!(failure_count(&provider), 1);
+    }
+
+    #[tokio::test]
+    async fn test_completed_stream_does_not_report_failure() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Stream(vec![
+            r#"{"assistantResponseEvent": {"content": "Hello"}}"#.to_string(),
+            r#"{"meteringEvent": {}}"#.to_string(),
+        ])]));
+
+        let (_, _, body) = send(&provider, request(true), true).await;
+        assert!(!body.contains("event: error"), "{}", body);
+        assert_eq!(failure_count(&provider), 0);
+    }
+
+    #[tokio::test]
+    async fn test_non_stream_tool_use_response() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Json(
+            r#"[
+                {"assistantResponseEvent": {"content": "Let me check."}},
+                {"toolUseEvent": {"name": "get_weather", "toolUseId": "tooluse_1", "input": "{\"city\":"}},
+                {"toolUseEvent": {"name": "get_weather", "toolUseId": "tooluse_1", "input": "\"Paris\"}", "stop": true}}
+            ]"#
+            .to_string(),
+        )]));
+
+        let (status, _, body) = send(&provider, request(false), false).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["stop_reason"], "tool_use");
+        assert_eq!(body["content"][0]["text"], "Let me check.");
+        assert_eq!(body["content"][1]["type"], "tool_use");
+        assert_eq!(body["content"][1]["id"], "tooluse_1");
+        assert_eq!(body["content"][1]["name"], "get_weather");
+        assert_eq!(body["content"][1]["input"], json!({"city": "Paris"}));
+    }
+
+    #[tokio::test]
+    async fn test_buffered_stream_tool_use_response() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Stream(vec![
+            r#"{"toolUseEvent": {"name": "get_weather", "toolUseId": "tooluse_1", "input": "{\"city\":\"Paris\"}", "stop": true}}"#.to_string(),
+            r#"{"contextUsageEvent": {"contextUsagePercentage": 2.0}}"#.to_string(),
+        ])]));
+
+        let (status, _, body) = send(&provider, request(true), true).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#""type":"tool_use""#), "{}", body);
+        assert!(body.contains(r#""name":"get_weather""#), "{}", body);
+        assert!(body.contains(r#""stop_reason":"tool_use""#), "{}", body);
+        // 缓冲模式下 message_start 使用 contextUsageEvent 计算的 input_tokens
+        let input_tokens = format!(r#""input_tokens":{}"#, CONTEXT_WINDOW_SIZE * 2 / 100);
+        assert!(body.contains(&input_tokens), "{}", body);
+    }
+
+    /// 流式响应中所有 message_delta 事件的数据
+    fn message_deltas(body: &str) -> Vec<serde_json::Value> {
+        body.split("\n\n")
+            .filter(|chunk| chunk.lines().any(|l| l == "event: message_delta"))
+            .filter_map(|chunk| chunk.lines().find_map(|l| l.strip_prefix("data: ")))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_stream_usage_matches_non_stream() {
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(20);
+        let events = vec![
+            json!({"assistantResponseEvent": {"content": text}}).to_string(),
+            json!({"assistantResponseEvent": {"content": text}}).to_string(),
+            r#"{"toolUseEvent": {"name": "get_weather", "toolUseId": "tooluse_1", "input": "{\"city\":"}}"#.to_string(),
+            r#"{"toolUseEvent": {"name": "get_weather", "toolUseId": "tooluse_1", "input": "\"Paris\"}", "stop": true}}"#.to_string(),
+            r#"{"contextUsageEvent": {"contextUsagePercentage": 2.0}}"#.to_string(),
+        ];
+
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Json(format!(
+            "[{}]",
+            events.join(",")
+        ))]));
+        let (_, _, body) = send(&provider, request(false), false).await;
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let expected = &body["usage"];
+        assert_eq!(expected["input_tokens"], CONTEXT_WINDOW_SIZE * 2 / 100);
+
+        for buffered in [false, true] {
+            let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Stream(
+                events.clone(),
+            )]));
+            let (_, _, body) = send(&provider, request(true), buffered).await;
+            let deltas = message_deltas(&body);
+            let usage = &deltas.last().unwrap()["usage"];
+            assert_eq!(
+                usage["output_tokens"], expected["output_tokens"],
+                "{}",
+                body
+            );
+            assert_eq!(usage["input_tokens"], expected["input_tokens"], "{}", body);
+
+            // 标准流模式在输出过程中发送携带累计 output_tokens 的中间 message_delta
+            let intermediate = &deltas[..deltas.len() - 1];
+            if buffered {
+                assert!(intermediate.is_empty(), "{}", body);
+            } else {
+                assert!(!intermediate.is_empty(), "{}", body);
+                for delta in intermediate {
+                    assert!(delta["delta"]["stop_reason"].is_null());
+                    assert!(delta["usage"]["output_tokens"].as_i64().unwrap() > 0);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_stream_decoder_overflow_returns_bad_gateway() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Stream(vec![
+            r#"{"assistantResponseEvent": {"content": "Hello"}}"#.to_string(),
+        ])]));
+        let mut state = mock_state(&provider);
+        state.config = Arc::new(Config {
+            decoder_buffer_size_bytes: 16,
+            ..Config::default()
+        });
+
+        let (status, headers, body) = send_with_state(state, request(false), false).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(headers[UPSTREAM_REQUEST_ID_HEADER], MOCK_REQUEST_ID);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"]["type"], "api_error");
+        assert_eq!(body["error"]["code"], "upstream_response_too_large");
+    }
+
+    #[tokio::test]
+    async fn test_upstream_client_error_not_retried() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Error {
+            status: 400,
+            body: "Improperly formed request".to_string(),
+        }]));
+
+        let (status, headers, body) = send(&provider, request(false), false).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(headers[UPSTREAM_REQUEST_ID_HEADER], MOCK_REQUEST_ID);
+
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"]["type"], "api_error");
+        assert!(
+            body["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("Improperly formed request")
+        );
+        assert_eq!(mock(&provider).call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_rate_limit_propagates_retry_after() {
+        for stream in [false, true] {
+            let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::RateLimited {
+                retry_after: Duration::from_secs(30),
+            }]));
+
+            let (status, headers, body) = send(&provider, request(stream), false).await;
+            assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(headers[header::RETRY_AFTER], "30");
+            assert_eq!(headers[UPSTREAM_REQUEST_ID_HEADER], MOCK_REQUEST_ID);
+
+            let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(body["error"]["type"], "rate_limit_error");
+            assert_eq!(body["error"]["code"], "upstream_rate_limited");
+            // 不在 handler 层重试
+            assert_eq!(mock(&provider).call_count(), 1);
+        }
+    }
+
+    /// 按 Accept-Language 发送请求，返回错误响应体
+    async fn send_localized(request: serde_json::Value, accept_language: &str) -> serde_json::Value {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Error {
+            status: 400,
+            body: "Improperly formed request".to_string(),
+        }]));
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_LANGUAGE, accept_language.parse().unwrap());
+        let (_, _, body) = send_with_headers(mock_state(&provider), request, headers, false).await;
+        serde_json::from_str(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_error_message_follows_accept_language() {
+        let mut unsupported = request(false);
+        unsupported["model"] = json!("gpt-4");
+
+        let en = send_localized(unsupported.clone(), "en-US,en;q=0.9").await;
+        assert_eq!(en["error"]["type"], "invalid_request_error");
+        assert_eq!(en["error"]["code"], "unsupported_model");
+        assert_eq!(en["error"]["message"], "Unsupported model: gpt-4");
+
+        let zh = send_localized(unsupported, "zh-CN").await;
+        assert_eq!(zh["error"]["code"], "unsupported_model");
+        assert_eq!(zh["error"]["message"], "模型不支持: gpt-4");
+
+        let en = send_localized(request(false), "en").await;
+        assert_eq!(en["error"]["code"], "upstream_call_failed");
+        assert!(
+            en["error"]["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("Upstream API call failed: ")
+        );
+
+        let zh = send_localized(request(false), "zh").await;
+        assert_eq!(zh["error"]["code"], "upstream_call_failed");
+        assert!(
+            zh["error"]["message"]
+                .as_str()
+                .unwrap()
+                .starts_with("上游 API 调用失败: ")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upstream_server_error_retried() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![
+            MockResponse::Error {
+                status: 503,
+                body: "high load".to_string(),
+            },
+            MockResponse::Json(r#"{"assistantResponseEvent": {"content": "OK"}}"#.to_string()),
+        ]));
+
+        let (status, _, body) = send(&provider, request(false), false).await;
+        assert_eq!(status, StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["content"][0]["text"], "OK");
+        assert_eq!(mock(&provider).call_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_identical_concurrent_requests_deduplicated() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Json(
+            r#"[
+                {"assistantResponseEvent": {"content": "Sunny"}},
+                {"contextUsageEvent": {"contextUsagePercentage": 1.0}}
+            ]"#
+            .to_string(),
+        )]));
+        mock(&provider).set_delay(Duration::from_millis(200));
+
+        let deduplicator = Arc::new(RequestDeduplicator::new(30));
+        let state = mock_state(&provider).with_deduplicator(deduplicator.clone());
+
+        let (first, second) = tokio::join!(
+            send_with_state(state.clone(), request(false), false),
+            send_with_state(state.clone(), request(false), false),
+        );
+
+        // 只调用一次上游，两个请求得到相同的响应
+        assert_eq!(mock(&provider).call_count(), 1);
+        assert_eq!(first.0, StatusCode::OK);
+        assert_eq!(first.2, second.2);
+        let mut dedup: Vec<_> = [&first.1, &second.1]
+            .iter()
+            .map(|h| h[DEDUP_HEADER].to_str().unwrap().to_string())
+            .collect();
+        dedup.sort();
+        assert_eq!(dedup, vec!["HIT", "MISS"]);
+        assert_eq!(deduplicator.in_flight_count(), 0);
+
+        // 请求完成后再次发送相同请求会重新调用上游
+        let (_, headers, _) = send_with_state(state.clone(), request(false), false).await;
+        assert_eq!(headers[DEDUP_HEADER], "MISS");
+        assert_eq!(mock(&provider).call_count(), 2);
+
+        // 流式请求不参与去重
+        let (first, second) = tokio::join!(
+            send_with_state(state.clone(), request(true), false),
+            send_with_state(state, request(true), false),
+        );
+        assert!(!first.1.contains_key(DEDUP_HEADER));
+        assert!(!second.1.contains_key(DEDUP_HEADER));
+        assert_eq!(mock(&provider).call_count(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_prompt_caching_notice_header() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Json(
+            r#"[
+                {"assistantResponseEvent": {"content": "OK"}},
+                {"contextUsageEvent": {"contextUsagePercentage": 1.0}}
+            ]"#
+            .to_string(),
+        )]));
+        let mut cached = request(false);
+        cached["system"] = json!([
+            {"type": "text", "text": "Project context", "cache_control": {"type": "ephemeral"}}
+        ]);
+
+        let (status, headers, _) = send(&provider, cached.clone(), false).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[PROMPT_CACHING_HEADER], "unsupported");
+        // cache_control 不转发到上游
+        let last_request = mock(&provider).last_request().unwrap();
+        assert!(last_request.contains("Project context"));
+        assert!(!last_request.contains("cache_control"));
+
+        // 只带 anthropic-beta 头也视为请求了 Prompt Caching
+        let mut beta = HeaderMap::new();
+        beta.insert(
+            "anthropic-beta",
+            "prompt-caching-2024-07-31".parse().unwrap(),
+        );
+        let (_, headers, _) =
+            send_with_headers(mock_state(&provider), request(false), beta, false).await;
+        assert_eq!(headers[PROMPT_CACHING_HEADER], "unsupported");
+
+        let (_, headers, _) = send(&provider, request(false), false).await;
+        assert!(!headers.contains_key(PROMPT_CACHING_HEADER));
+
+        // 关闭提示后不附带响应头
+        let mut state = mock_state(&provider);
+        state.config = Arc::new(Config {
+            prompt_caching_notice_enabled: false,
+            ..Config::default()
+        });
+        let (_, headers, _) = send_with_state(state, cached, false).await;
+        assert!(!headers.contains_key(PROMPT_CACHING_HEADER));
+    }
+
+    #[tokio::test]
+    async fn test_sampling_notice_header() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Json(
+            r#"[
+                {"assistantResponseEvent": {"content": "OK"}},
+                {"contextUsageEvent": {"contextUsagePercentage": 1.0}}
+            ]"#
+            .to_string(),
+        )]));
+        let mut sampled = request(false);
+        sampled["temperature"] = json!(0);
+
+        let (status, headers, _) = send(&provider, sampled.clone(), false).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers[SAMPLING_HEADER], "unsupported");
+        assert!(
+            !mock(&provider)
+                .last_request()
+                .unwrap()
+                .contains("temperature")
+        );
+
+        // 超出范围返回 400，不调用上游
+        sampled["temperature"] = json!(1.5);
+        let (status, headers, body) = send(&provider, sampled, false).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(!headers.contains_key(SAMPLING_HEADER));
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"]["type"], "invalid_request_error");
+        assert_eq!(mock(&provider).call_count(), 1);
+
+        let (_, headers, _) = send(&provider, request(false), false).await;
+        assert!(!headers.contains_key(SAMPLING_HEADER));
+    }
+
+    #[tokio::test]
+    async fn test_api_key_model_policy() {
+        use crate::admin::api_keys::CreateApiKeyRequest;
+
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Json(
+            r#"[
+                {"assistantResponseEvent": {"content": "OK"}},
+                {"contextUsageEvent": {"contextUsagePercentage": 1.0}}
+            ]"#
+            .to_string(),
+        )]));
+        let dir = tempfile::tempdir().unwrap();
+        let api_key_manager =
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap());
+        let key = api_key_manager
+            .create_with_full_key(CreateApiKeyRequest {
+                name: "Collaborator".to_string(),
+                description: None,
+                key: None,
+                pool_id: None,
+                websearch_rate_limit_per_hour: None,
+                allowed_models: Some(vec!["claude-sonnet-*".to_string()]),
+                denied_models: Some(vec!["*-4-5-20250930".to_string()]),
+                max_tokens_limit: Some(512),
+            })
+            .unwrap();
+        let mut state = AppState::new(api_key_manager, Arc::new(Config::default()));
+        state.kiro_provider = Some(provider.clone());
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", key.key.parse().unwrap());
+
+        // 允许的模型：max_tokens 截断到上限
+        let (status, response_headers, _) =
+            send_with_headers(state.clone(), request(false), headers.clone(), false).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response_headers[MAX_TOKENS_CLAMPED_HEADER], "512");
+
+        // 未命中允许列表或命中禁止列表：403，不调用上游
+        for model in ["claude-opus-4-5-20251101", "claude-sonnet-4-5-20250930"] {
+            let mut denied = request(false);
+            denied["model"] = json!(model);
+            let (status, _, body) =
+                send_with_headers(state.clone(), denied, headers.clone(), false).await;
+            assert_eq!(status, StatusCode::FORBIDDEN);
+            let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(body["error"]["type"], "model_not_allowed");
+            assert!(body["error"]["message"].as_str().unwrap().contains(model));
+        }
+        assert_eq!(mock(&provider).call_count(), 1);
+
+        // count_tokens 使用相同的模型检查
+        let count_tokens_status = |model: &str| {
+            let state = state.clone();
+            let headers = headers.clone();
+            let request = json!({
+                "model": model,
+                "messages": [{"role": "user", "content": "hi"}]
+            });
+            async move {
+                count_tokens(
+                    State(state),
+                    Extension(AuthenticatedPoolId(vec![])),
+                    Query(CountTokensQuery { detailed: false }),
+                    headers,
+                    JsonExtractor(serde_json::from_value(request).unwrap()),
+                )
+                .await
+                .status()
+            }
+        };
+        assert_eq!(
+            count_tokens_status("claude-sonnet-4-5-20250929").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            count_tokens_status("claude-opus-4-5-20251101").await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conversion_warnings_returned_to_client() {
+        let json_response = || {
+            MockResponse::Json(
+                r#"[
+                    {"assistantResponseEvent": {"content": "OK"}},
+                    {"contextUsageEvent": {"contextUsagePercentage": 1.0}}
+                ]"#
+                .to_string(),
+            )
+        };
+        let stream_response = || {
+            MockResponse::Stream(vec![
+                r#"{"assistantResponseEvent": {"content": "OK"}}"#.to_string(),
+                r#"{"contextUsageEvent": {"contextUsagePercentage": 1.0}}"#.to_string(),
+            ])
+        };
+        let provider = Arc::new(KiroProvider::new_mock(vec![
+            json_response(),
+            stream_response(),
+            stream_response(),
+            json_response(),
+            stream_response(),
+        ]));
+        let degraded = |stream: bool| {
+            let mut req = request(stream);
+            req["messages"][0]["content"] = json!([
+                {"type": "text", "text": "Hello"},
+                {"type": "search_result", "title": "t", "content": []}
+            ]);
+            req
+        };
+
+        let (_, _, body) = send(&provider, degraded(false), false).await;
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            body["kiro_warnings"],
+            json!([{
+                "code": "unsupported_content_block",
+                "message": "Kiro 不支持 search_result 内容块，已丢弃",
+                "path": "messages[0].content[1]"
+            }])
+        );
+
+        // 流式响应（标准和缓冲模式）在 message_start 之前发送注释行
+        for buffered in [false, true] {
+            let (_, _, body) = send(&provider, degraded(true), buffered).await;
+            assert!(
+                body.starts_with(": kiro_warnings [{\"code\":\"unsupported_content_block\""),
+                "{}",
+                body
+            );
+            assert!(
+                body.find(": kiro_warnings").unwrap() < body.find("event: message_start").unwrap()
+            );
+        }
+
+        // 无警告时响应不变
+        let (_, _, body) = send(&provider, request(false), false).await;
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert!(body.get("kiro_warnings").is_none());
+
+        // 关闭后不返回给客户端
+        let dir = tempfile::tempdir().unwrap();
+        let api_key_manager =
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap());
+        let config = Config {
+            suppress_conversion_warnings: true,
+            ..Config::default()
+        };
+        let mut state = AppState::new(api_key_manager, Arc::new(config));
+        state.kiro_provider = Some(provider.clone());
+        let (_, _, body) = send_with_state(state, degraded(true), false).await;
+        assert!(!body.contains("kiro_warnings"), "{}", body);
+        assert!(body.starts_with("event: message_start"), "{}", body);
+    }
+
+    /// 调用批量消息接口
+    async fn send_batch(
+        state: AppState,
+        request: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let response = post_messages_batch(
+            State(state),
+            Extension(AuthenticatedPoolId(vec![])),
+            HeaderMap::new(),
+            JsonExtractor(serde_json::from_value(request).unwrap()),
+        )
+        .await;
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_batch_messages_gated_by_feature_flag() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Json(
+            r#"[
+                {"assistantResponseEvent": {"content": "Sunny"}},
+                {"contextUsageEvent": {"contextUsagePercentage": 1.0}}
+            ]"#
+            .to_string(),
+        )]));
+        let features = Arc::new(FeatureFlags::default());
+        features.set(ENABLE_BATCH_MESSAGES, true).unwrap();
+        let state = mock_state(&provider).with_features(features.clone());
+        let batch = json!({
+            "requests": [
+                {"custom_id": "a", "params": request(true)},
+                {"custom_id": "b", "params": {"model": "claude-sonnet-4-5-20250929", "max_tokens": 1024, "messages": []}}
+            ]
+        });
+
+        let (status, body) = send_batch(state.clone(), batch.clone()).await;
+        assert_eq!(status, StatusCode::OK);
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results[0]["custom_id"], "a");
+        assert_eq!(results[0]["result"]["type"], "succeeded");
+        assert_eq!(
+            results[0]["result"]["message"]["content"][0]["text"],
+            "Sunny"
+        );
+        assert_eq!(results[1]["custom_id"], "b");
+        assert_eq!(results[1]["result"]["type"], "errored");
+        assert_eq!(mock(&provider).call_count(), 1);
+
+        // 运行时关闭后返回 404，不再调用上游
+        features.set(ENABLE_BATCH_MESSAGES, false).unwrap();
+        let (status, body) = send_batch(state, batch).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "feature_disabled");
+        assert_eq!(mock(&provider).call_count(), 1);
+    }
+
+    /// SSE 事件的 id 列表
+    fn event_ids(body: &str) -> Vec<u64> {
+        body.lines()
+            .filter_map(|line| line.strip_prefix("id: "))
+            .map(|id| id.parse().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_stream_resume_with_last_event_id() {
+        use crate::anthropic::replay::{LAST_EVENT_ID_HEADER, SseReplayRegistry};
+
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Stream(vec![
+            r#"{"assistantResponseEvent": {"content": "Hello"}}"#.to_string(),
+            r#"{"assistantResponseEvent": {"content": ", world!"}}"#.to_string(),
+            r#"{"contextUsageEvent": {"contextUsagePercentage": 1.0}}"#.to_string(),
+        ])]));
+        let state = mock_state(&provider).with_sse_replay(Arc::new(SseReplayRegistry::new(100)));
+
+        let (status, _, body) = send_with_state(state.clone(), request(true), false).await;
+        assert_eq!(status, StatusCode::OK);
+        let ids = event_ids(&body);
+        assert!(ids.len() > 6, "{}", body);
+        assert_eq!(ids, (1..=ids.len() as u64).collect::<Vec<_>>());
+
+        // 在事件 5 之后断线重连：回放事件 6 及之后的事件，不再调用上游
+        let mut headers = HeaderMap::new();
+        headers.insert(LAST_EVENT_ID_HEADER, "5".parse().unwrap());
+        let (status, resumed_headers, resumed) =
+            send_with_headers(state.clone(), request(true), headers, false).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(resumed_headers[header::CONTENT_TYPE], "text/event-stream");
+        assert_eq!(event_ids(&resumed), (6..=ids.len() as u64).collect::<Vec<_>>());
+        assert!(body.ends_with(&resumed), "{}", resumed);
+        assert!(resumed.contains("event: message_stop"));
+        assert_eq!(mock(&provider).call_count(), 1);
+
+        // 不同请求没有可续传的流
+        let mut other = request(true);
+        other["max_tokens"] = json!(2048);
+        let mut headers = HeaderMap::new();
+        headers.insert(LAST_EVENT_ID_HEADER, "5".parse().unwrap());
+        let (_, _, expired) = send_with_headers(state, other, headers, false).await;
+        assert!(expired.contains(r#""error":"stream_expired""#), "{}", expired);
+        assert_eq!(mock(&provider).call_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_resume_after_buffer_overwritten() {
+        use crate::anthropic::replay::{LAST_EVENT_ID_HEADER, SseReplayRegistry};
+
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Stream(vec![
+            r#"{"assistantResponseEvent": {"content": "Hello"}}"#.to_string(),
+            r#"{"contextUsageEvent": {"contextUsagePercentage": 1.0}}"#.to_string(),
+        ])]));
+        let state = mock_state(&provider).with_sse_replay(Arc::new(SseReplayRegistry::new(2)));
+
+        let (_, _, body) = send_with_state(state.clone(), request(true), false).await;
+        assert!(event_ids(&body).len() > 3, "{}", body);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(LAST_EVENT_ID_HEADER, "1".parse().unwrap());
+        let (_, _, resumed) = send_with_headers(state, request(true), headers, false).await;
+        assert!(resumed.contains("event: message_error"), "{}", resumed);
+        assert!(resumed.contains(r#""error":"stream_expired""#), "{}", resumed);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_concurrency_limit_rejects_without_calling_upstream() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Json(
+            r#"{"assistantResponseEvent": {"content": "Sunny"}}"#.to_string(),
+        )]));
+        let limiter = Arc::new(UpstreamConcurrencyLimiter::new(1, 20));
+        let state = mock_state(&provider).with_upstream_limiter(limiter.clone());
+
+        // 名额被占用：排队超时后返回 429，不调用上游
+        let permit = limiter.acquire().await.unwrap();
+        let (status, headers, body) = send_with_state(state.clone(), request(false), false).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(headers[header::RETRY_AFTER], "1");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"]["type"], "rate_limit_error");
+        assert_eq!(body["error"]["code"], "upstream_concurrency_limit");
+        assert_eq!(mock(&provider).call_count(), 0);
+
+        // 名额归还后正常处理，非流式请求结束即释放名额
+        drop(permit);
+        let (status, _, _) = send_with_state(state, request(false), false).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(limiter.stats().in_use, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_holds_permit_until_body_dropped() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Stream(vec![
+            r#"{"assistantResponseEvent": {"content": "Hello"}}"#.to_string(),
+            r#"{"contextUsageEvent": {"contextUsagePercentage": 1.0}}"#.to_string(),
+        ])]));
+        let limiter = Arc::new(UpstreamConcurrencyLimiter::new(2, 0));
+        let state = mock_state(&provider).with_upstream_limiter(limiter.clone());
+        let stream_request = || {
+            handle_messages_request(
+                state.clone(),
+                AuthenticatedPoolId(vec![]),
+                HeaderMap::new(),
+                serde_json::from_value(request(true)).unwrap(),
+                "/v1/messages",
+                false,
+            )
+        };
+
+        // 响应返回后仍持有名额，直到 SSE 流读完
+        let response = stream_request().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(limiter.stats().in_use, 1);
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(limiter.stats().in_use, 0);
+
+        // 客户端断开（响应体被丢弃）同样释放名额
+        let response = stream_request().await;
+        assert_eq!(limiter.stats().in_use, 1);
+        drop(response);
+        assert_eq!(limiter.stats().in_use, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_holds_credential_permit_until_body_ends() {
+        let provider = Arc::new(KiroProvider::new_mock(vec![MockResponse::Stream(vec![
+            r#"{"assistantResponseEvent": {"content": "Hello"}}"#.to_string(),
+            r#"{"contextUsageEvent": {"contextUsagePercentage": 1.0}}"#.to_string(),
+        ])]));
+        let state = mock_state(&provider);
+        let in_flight = || provider.token_manager().snapshot().entries[0].current_concurrency;
+
+        // 响应头返回后凭据并发名额仍被流持有，流读完才释放
+        let response = handle_messages_request(
+            state,
+            AuthenticatedPoolId(vec![]),
+            HeaderMap::new(),
+            serde_json::from_value(request(true)).unwrap(),
+            "/v1/messages",
+            false,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(in_flight(), 1);
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(in_flight(), 0);
+    }
+}
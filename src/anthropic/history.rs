@@ -4,7 +4,9 @@
 //! 1. **自动截断**：超过阈值时截断早期消息
 //! 2. **AI 摘要**：使用 Haiku 模型摘要历史消息
 //! 3. **图片占位符**：历史消息中的图片替换为 `[Image]`
-//! 4. **缓存复用**：利用 Anthropic 的 Prompt Caching 功能
+//! 4. **缓存复用**：截断时保留客户端用 `cache_control` 标记的缓存前缀，保持前缀稳定
+
+use std::ops::Range;
 
 use crate::anthropic::types::{ContentBlock, Message, SystemMessage};
 use crate::token;
@@ -20,7 +22,7 @@ pub struct HistoryConfig {
     pub enable_ai_summary: bool,
     /// 是否启用图片占位符
     pub enable_image_placeholder: bool,
-    /// 是否启用缓存复用
+    /// 是否启用缓存复用（截断时保留 cache_control 标记的缓存前缀）
     pub enable_prompt_caching: bool,
     /// 保留最近的消息数量（截断时）
     pub keep_recent_messages: usize,
@@ -56,15 +58,28 @@ pub struct HistoryManagementResult {
     pub original_tokens: u64,
     /// 处理后 token 数量
     pub processed_tokens: u64,
+    /// 截断删除的原始消息下标区间（删除位置插入了一条截断提示消息）
+    pub removed_messages: Option<Range<usize>>,
+}
+
+/// 处理后的消息下标对应的原始消息下标（截断提示消息返回 None）
+///
+/// `removed` 为 [`HistoryManagementResult::removed_messages`]
+pub fn original_message_index(removed: Option<&Range<usize>>, index: usize) -> Option<usize> {
+    match removed {
+        Some(removed) if index == removed.start => None,
+        Some(removed) if index > removed.start => Some(index - 1 + removed.len()),
+        _ => Some(index),
+    }
 }
 
 /// 智能管理消息历史
 ///
 /// 根据配置应用四层策略：
-/// 1. 图片占位符（如果启用）
-/// 2. 计算 token 数量
-/// 3. 如果超过阈值，应用截断或 AI 摘要
-/// 4. 添加缓存标记（如果启用）
+/// 1. 确定客户端标记的缓存前缀（如果启用缓存复用）
+/// 2. 图片占位符（如果启用）
+/// 3. 计算 token 数量
+/// 4. 如果超过阈值，应用截断或 AI 摘要（不删除缓存前缀内的消息）
 pub fn manage_history(
     config: &HistoryConfig,
     messages: Vec<Message>,
@@ -81,9 +96,17 @@ pub fn manage_history(
             image_placeholder_applied: false,
             original_tokens,
             processed_tokens: original_tokens,
+            removed_messages: None,
         };
     }
 
+    // 策略 4: 缓存复用（在图片占位符之前确定，图片块上的标记也生效）
+    let cached_prefix = if config.enable_prompt_caching {
+        apply_prompt_caching(&messages, config.keep_recent_messages)
+    } else {
+        0
+    };
+
     // 策略 3: 图片占位符
     let (processed_messages, image_placeholder_applied) = if config.enable_image_placeholder {
         (apply_image_placeholder(&messages), true)
@@ -111,19 +134,35 @@ pub fn manage_history(
             image_placeholder_applied,
             original_tokens,
             processed_tokens: original_tokens,
+            removed_messages: None,
         };
     }
 
     // 超过阈值，应用策略
+    let removed_messages = truncation_range(
+        &processed_messages,
+        config.keep_recent_messages,
+        cached_prefix,
+    );
     let (final_messages, final_system, truncated, summarized) = if config.enable_ai_summary {
         // 策略 2: AI 摘要（优先）
         tracing::info!("应用 AI 摘要策略（tokens: {} > {}）", original_tokens, config.truncate_threshold);
-        let (msgs, sys) = apply_ai_summary(&processed_messages, &system);
+        let (msgs, sys) = apply_ai_summary(
+            &processed_messages,
+            &system,
+            config.keep_recent_messages,
+            cached_prefix,
+        );
         (msgs, sys, false, true)
     } else {
         // 策略 1: 自动截断
         tracing::info!("应用自动截断策略（tokens: {} > {}）", original_tokens, config.truncate_threshold);
-        let (msgs, sys) = apply_truncation(&processed_messages, &system, config.keep_recent_messages);
+        let (msgs, sys) = apply_truncation(
+            &processed_messages,
+            &system,
+            config.keep_recent_messages,
+            cached_prefix,
+        );
         (msgs, sys, true, false)
     };
 
@@ -145,23 +184,24 @@ pub fn manage_history(
         image_placeholder_applied,
         original_tokens,
         processed_tokens,
+        removed_messages,
     }
 }
 
 /// 策略 1: 自动截断早期消息
 ///
-/// 保留最近的 N 条消息和 system prompt
+/// 保留最近的 N 条消息和 system prompt；`cached_prefix` 大于 0 时同时保留
+/// 最前面的 `cached_prefix` 条消息（客户端缓存前缀），只删除前缀与最近消息之间的部分
 fn apply_truncation(
     messages: &[Message],
     system: &Option<Vec<SystemMessage>>,
     keep_recent: usize,
+    cached_prefix: usize,
 ) -> (Vec<Message>, Option<Vec<SystemMessage>>) {
-    if messages.len() <= keep_recent {
+    let Some(removed) = truncation_range(messages, keep_recent, cached_prefix) else {
         return (messages.to_vec(), system.clone());
-    }
-
-    // 保留最后 N 条消息
-    let start_index = messages.len().saturating_sub(keep_recent);
+    };
+    let start_index = removed.end;
     let truncated_messages = messages[start_index..].to_vec();
 
     tracing::debug!(
@@ -170,8 +210,8 @@ fn apply_truncation(
         truncated_messages.len()
     );
 
-    // 在截断的消息前添加提示
-    let mut result_messages = Vec::new();
+    // 缓存前缀原样保留，然后在截断的消息前添加提示
+    let mut result_messages = messages[..cached_prefix].to_vec();
 
     // 添加截断提示消息
     let truncation_notice = Message {
@@ -186,6 +226,29 @@ fn apply_truncation(
     (result_messages, system.clone())
 }
 
+/// 截断时删除的消息下标区间（缓存前缀与最近 N 条消息之间），无需删除时返回 None
+fn truncation_range(
+    messages: &[Message],
+    keep_recent: usize,
+    cached_prefix: usize,
+) -> Option<Range<usize>> {
+    // 保留最后 N 条消息（不与缓存前缀重叠）
+    let start_index = recent_start(messages, keep_recent).max(cached_prefix);
+    (start_index > cached_prefix).then_some(cached_prefix..start_index)
+}
+
+/// 截断时保留的最近消息的起始下标
+///
+/// 续写模式（以 assistant 片段结尾）至少保留该片段及其前面的 user 消息
+fn recent_start(messages: &[Message], keep_recent: usize) -> usize {
+    let keep_recent = if messages.last().is_some_and(|m| m.role == "assistant") {
+        keep_recent.max(2)
+    } else {
+        keep_recent
+    };
+    messages.len().saturating_sub(keep_recent)
+}
+
 /// 策略 2: AI 摘要历史消息
 ///
 /// 使用 Haiku 模型摘要历史消息，将长历史压缩为简短摘要
@@ -194,6 +257,8 @@ fn apply_truncation(
 fn apply_ai_summary(
     messages: &[Message],
     system: &Option<Vec<SystemMessage>>,
+    keep_recent: usize,
+    cached_prefix: usize,
 ) -> (Vec<Message>, Option<Vec<SystemMessage>>) {
     // TODO: 实现 AI 摘要功能
     // 1. 将历史消息格式化为文本
@@ -203,7 +268,7 @@ fn apply_ai_summary(
     tracing::warn!("AI 摘要功能尚未实现，回退到截断策略");
 
     // 暂时回退到截断策略
-    apply_truncation(messages, system, 20)
+    apply_truncation(messages, system, keep_recent, cached_prefix)
 }
 
 /// 策略 3: 图片占位符
@@ -222,7 +287,7 @@ fn apply_image_placeholder(messages: &[Message]) -> Vec<Message> {
         .collect()
 }
 
-/// 替换内容中的图片为占位符
+/// 替换内容中的图片为占位符（包括 tool_result 中嵌套的图片）
 fn replace_images_in_content(content: &serde_json::Value) -> serde_json::Value {
     match content {
         serde_json::Value::String(s) => serde_json::json!(s),
@@ -238,6 +303,13 @@ fn replace_images_in_content(content: &serde_json::Value) -> serde_json::Value {
                                 "text": "[Image]"
                             });
                         }
+                        if block.block_type == "tool_result"
+                            && let Some(nested) = item.get("content")
+                        {
+                            let mut item = item.clone();
+                            item["content"] = replace_images_in_content(nested);
+                            return item;
+                        }
                     }
                     item.clone()
                 })
@@ -323,23 +395,24 @@ fn estimate_message_tokens(msg: &Message) -> u64 {
     }
 }
 
-/// 策略 4: 添加缓存标记
+/// 策略 4: 确定缓存前缀
 ///
-/// 为 system prompt 和历史消息添加缓存标记，利用 Anthropic 的 Prompt Caching 功能
+/// Kiro 上游不接受 `cache_control` 标记（转换请求时不会转发），这里只做本地部分：
+/// system 加上最后一条带 `cache_control` 的消息及其之前的所有消息构成稳定前缀，
+/// 截断时不再删除前缀内的消息，使连续请求发送到上游的前缀保持一致。
+/// 客户端通常也会标记最后一条消息，最近 `keep_recent` 条消息本来就会保留，
+/// 其中的标记不计入前缀，否则前缀覆盖全部消息，截断永远不会生效
 ///
-/// 注意：Anthropic Prompt Caching 需要在请求中添加特殊的缓存标记
-/// 当前 Kiro API 可能不支持此功能，此函数为占位符
-#[allow(dead_code)]
-fn apply_prompt_caching(
-    messages: Vec<Message>,
-    system: Option<Vec<SystemMessage>>,
-) -> (Vec<Message>, Option<Vec<SystemMessage>>) {
-    // TODO: 实现 Prompt Caching 标记
-    // Anthropic 的 Prompt Caching 需要在 system 和 messages 中添加特殊字段
-    // 参考: https://docs.anthropic.com/claude/docs/prompt-caching
-
-    tracing::debug!("Prompt Caching 功能尚未实现");
-    (messages, system)
+/// 返回缓存前缀包含的消息数（没有标记时为 0）
+fn apply_prompt_caching(messages: &[Message], keep_recent: usize) -> usize {
+    let cached_prefix = messages[..recent_start(messages, keep_recent)]
+        .iter()
+        .rposition(Message::has_cache_control)
+        .map_or(0, |index| index + 1);
+    if cached_prefix > 0 {
+        tracing::debug!("缓存前缀：前 {} 条消息", cached_prefix);
+    }
+    cached_prefix
 }
 
 #[cfg(test)]
@@ -373,9 +446,10 @@ mod tests {
 
         let system = Some(vec![SystemMessage {
             text: "You are a helpful assistant.".to_string(),
+            cache_control: None,
         }]);
 
-        let (truncated_messages, truncated_system) = apply_truncation(&messages, &system, 2);
+        let (truncated_messages, truncated_system) = apply_truncation(&messages, &system, 2, 0);
 
         // 应该保留最后 2 条消息 + 1 条截断提示
         assert_eq!(truncated_messages.len(), 3);
@@ -390,6 +464,26 @@ mod tests {
         assert!(truncated_system.is_some());
     }
 
+    #[test]
+    fn test_truncation_keeps_trailing_assistant_fragment() {
+        let messages: Vec<Message> = ["Message 1", "Response 1", "Message 2", "Partial resp"]
+            .iter()
+            .enumerate()
+            .map(|(i, text)| Message {
+                role: if i % 2 == 0 { "user" } else { "assistant" }.to_string(),
+                content: serde_json::json!(text),
+            })
+            .collect();
+
+        let (truncated, _) = apply_truncation(&messages, &None, 1, 0);
+
+        // 截断提示 + 触发续写的 user 消息 + assistant 片段
+        assert_eq!(truncated.len(), 3);
+        assert_eq!(truncated[1].content, "Message 2");
+        assert_eq!(truncated[2].role, "assistant");
+        assert_eq!(truncated[2].content, "Partial resp");
+    }
+
     #[test]
     fn test_apply_image_placeholder() {
         let messages = vec![
@@ -420,6 +514,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_apply_image_placeholder_nested_in_tool_result() {
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: serde_json::json!([
+                {
+                    "type": "tool_result",
+                    "tool_use_id": "tool-1",
+                    "content": [
+                        {"type": "text", "text": "Screenshot taken"},
+                        {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "base64data"}}
+                    ]
+                }
+            ]),
+        }];
+
+        let processed = apply_image_placeholder(&messages);
+        let result = &processed[0].content[0];
+        assert_eq!(result["tool_use_id"], "tool-1");
+        assert_eq!(result["content"][0]["text"], "Screenshot taken");
+        assert_eq!(result["content"][1]["type"], "text");
+        assert_eq!(result["content"][1]["text"], "[Image]");
+    }
+
     #[test]
     fn test_manage_history_no_truncation() {
         let config = HistoryConfig {
@@ -481,6 +599,125 @@ mod tests {
         assert!(!result.summarized);
         // 应该有截断提示 + 保留的消息
         assert!(result.messages.len() <= 2);
+        // 处理后的下标映射回原始下标：截断提示没有对应消息
+        assert_eq!(result.removed_messages, Some(0..2));
+        let removed = result.removed_messages.as_ref();
+        assert_eq!(original_message_index(removed, 0), None);
+        assert_eq!(original_message_index(removed, 1), Some(2));
+    }
+
+    /// 模拟一轮对话：第 2 条消息带 cache_control，之后追加 `turns` 轮问答
+    fn cached_conversation(turns: usize) -> Vec<Message> {
+        let mut messages = vec![
+            Message {
+                role: "user".to_string(),
+                content: serde_json::json!("Here is the project context for this session."),
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: serde_json::json!([
+                    {"type": "text", "text": "Understood, I have read the context.", "cache_control": {"type": "ephemeral"}}
+                ]),
+            },
+        ];
+        for i in 0..turns {
+            messages.push(Message {
+                role: "user".to_string(),
+                content: serde_json::json!(format!("Question number {} about the project", i)),
+            });
+            messages.push(Message {
+                role: "assistant".to_string(),
+                content: serde_json::json!(format!("Answer number {} about the project", i)),
+            });
+        }
+        messages
+    }
+
+    #[test]
+    fn test_cached_prefix_stable_across_requests() {
+        let config = HistoryConfig {
+            enabled: true,
+            truncate_threshold: 5, // 强制截断
+            enable_ai_summary: false,
+            enable_image_placeholder: false,
+            enable_prompt_caching: true,
+            keep_recent_messages: 2,
+        };
+        let system = Some(vec![SystemMessage {
+            text: "You are a helpful assistant.".to_string(),
+            cache_control: Some(serde_json::json!({"type": "ephemeral"})),
+        }]);
+        let prefix = |messages: &[Message]| serde_json::to_value(&messages[..2]).unwrap();
+
+        // 连续两次请求（第二次多一轮对话），发送到上游的前缀保持一致
+        let first = manage_history(&config, cached_conversation(3), system.clone(), None);
+        let second = manage_history(&config, cached_conversation(4), system.clone(), None);
+        assert!(first.truncated && second.truncated);
+        assert_eq!(prefix(&first.messages), prefix(&cached_conversation(0)));
+        assert_eq!(prefix(&first.messages), prefix(&second.messages));
+        assert_eq!(
+            first.system.unwrap()[0].text,
+            second.system.unwrap()[0].text
+        );
+        // 缓存前缀 + 截断提示 + 最近 2 条
+        assert_eq!(first.messages.len(), 5);
+        assert!(
+            first.messages[2]
+                .content
+                .as_str()
+                .unwrap()
+                .contains("truncated")
+        );
+
+        // 关闭缓存复用时，截断窗口随对话滑动，前缀每次都不同
+        let config = HistoryConfig {
+            enable_prompt_caching: false,
+            ..config
+        };
+        let first = manage_history(&config, cached_conversation(3), system.clone(), None);
+        let second = manage_history(&config, cached_conversation(4), system, None);
+        assert_ne!(prefix(&first.messages), prefix(&second.messages));
+    }
+
+    #[test]
+    fn test_truncation_skipped_when_only_cached_prefix_remains() {
+        let messages = cached_conversation(1);
+        let (kept, _) = apply_truncation(&messages, &None, 2, apply_prompt_caching(&messages, 2));
+        assert_eq!(kept.len(), messages.len());
+    }
+
+    #[test]
+    fn test_truncation_with_cache_marker_on_last_message() {
+        let config = HistoryConfig {
+            enabled: true,
+            truncate_threshold: 5, // 强制截断
+            enable_ai_summary: false,
+            enable_image_placeholder: false,
+            enable_prompt_caching: true,
+            keep_recent_messages: 2,
+        };
+        // 客户端同时标记了上下文消息和最后一条消息
+        let mut messages = cached_conversation(4);
+        messages.push(Message {
+            role: "user".to_string(),
+            content: serde_json::json!([
+                {"type": "text", "text": "Latest question", "cache_control": {"type": "ephemeral"}}
+            ]),
+        });
+        assert_eq!(apply_prompt_caching(&messages, 2), 2);
+
+        let result = manage_history(&config, messages.clone(), None, None);
+        assert!(result.truncated);
+        // 缓存前缀 + 截断提示 + 最近 2 条
+        assert_eq!(result.messages.len(), 5);
+        assert_eq!(
+            serde_json::to_value(&result.messages[..2]).unwrap(),
+            serde_json::to_value(&messages[..2]).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&result.messages[3..]).unwrap(),
+            serde_json::to_value(&messages[messages.len() - 2..]).unwrap()
+        );
     }
 
     #[test]
@@ -4,18 +4,31 @@ use std::sync::Arc;
 
 use axum::{
     body::Body,
-    extract::State,
-    http::{Request, StatusCode},
+    extract::{ConnectInfo, State},
+    http::{HeaderValue, Request, StatusCode, header},
     middleware::Next,
     response::{IntoResponse, Json, Response},
 };
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
-use std::time::Instant;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 use crate::admin::ApiKeyManager;
+use crate::common::features::FeatureFlags;
 use crate::kiro::pool_manager::PoolManager;
 use crate::kiro::provider::KiroProvider;
-use crate::model::config::Config;
+use crate::model::config::{Config, RateLimitExemption, RateLimiterType};
+
+use super::concurrency::UpstreamConcurrencyLimiter;
+use super::dedup::RequestDeduplicator;
+use super::replay::SseReplayRegistry;
+use super::websearch::WebSearchCache;
+use super::quota_queue::QuotaQueue;
+use crate::common::i18n::{ErrorCode, Locale, LocalizedError};
 
 use super::types::ErrorResponse;
 
@@ -33,6 +46,24 @@ pub struct AppState {
     pub pool_manager: Option<Arc<PoolManager>>,
     /// 限流器（可选）
     pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// 令牌桶限流器（可选，rateLimiterType 为 tokenBucket 时代替 `rate_limiter`）
+    pub token_bucket: Option<Arc<TokenBucketLimiter>>,
+    /// WebSearch 限流器（独立于普通消息限流）
+    pub websearch_limiter: Arc<WebSearchRateLimiter>,
+    /// 限流豁免规则（与 Admin 共享以便运行时增删）
+    pub rate_limit_exemptions: Arc<RateLimitExemptions>,
+    /// 额度用尽排队队列（可选，启用 quota_queue_enabled 时设置）
+    pub quota_queue: Option<Arc<QuotaQueue>>,
+    /// 请求去重器（可选，启用 dedup_enabled 时设置）
+    pub deduplicator: Option<Arc<RequestDeduplicator>>,
+    /// 上游并发限制器（可选，max_concurrent_upstream_requests 大于 0 时设置）
+    pub upstream_limiter: Option<Arc<UpstreamConcurrencyLimiter>>,
+    /// SSE 断线续传注册表（可选，sse_replay_buffer_size 大于 0 时设置）
+    pub sse_replay: Option<Arc<SseReplayRegistry>>,
+    /// WebSearch 结果缓存（功能开关 enable_websearch_cache 启用时使用）
+    pub websearch_cache: Arc<WebSearchCache>,
+    /// 功能开关（与 Admin 共享以便运行时切换）
+    pub features: Arc<FeatureFlags>,
     /// 应用配置
     pub config: Arc<Config>,
 }
@@ -46,6 +77,19 @@ impl AppState {
             api_key_manager,
             pool_manager: None,
             rate_limiter: None,
+            token_bucket: None,
+            websearch_limiter: Arc::new(WebSearchRateLimiter::new(
+                config.websearch_rate_limit_per_hour,
+            )),
+            rate_limit_exemptions: Arc::new(RateLimitExemptions::new(
+                config.rate_limit_exemptions.clone(),
+            )),
+            quota_queue: None,
+            deduplicator: None,
+            upstream_limiter: None,
+            sse_replay: None,
+            websearch_cache: Arc::new(WebSearchCache::new()),
+            features: Arc::new(FeatureFlags::new(&config.feature_flags)),
             config,
         }
     }
@@ -73,17 +117,67 @@ impl AppState {
         self.rate_limiter = Some(limiter);
         self
     }
+
+    /// 设置令牌桶限流器
+    pub fn with_token_bucket(mut self, limiter: Arc<TokenBucketLimiter>) -> Self {
+        self.token_bucket = Some(limiter);
+        self
+    }
+
+    /// 设置 WebSearch 限流器（与 Admin 共享以便统计）
+    pub fn with_websearch_limiter(mut self, limiter: Arc<WebSearchRateLimiter>) -> Self {
+        self.websearch_limiter = limiter;
+        self
+    }
+
+    /// 设置限流豁免规则（与 Admin 共享）
+    pub fn with_rate_limit_exemptions(mut self, exemptions: Arc<RateLimitExemptions>) -> Self {
+        self.rate_limit_exemptions = exemptions;
+        self
+    }
+
+    /// 设置额度用尽排队队列
+    pub fn with_quota_queue(mut self, queue: Arc<QuotaQueue>) -> Self {
+        self.quota_queue = Some(queue);
+        self
+    }
+
+    /// 设置请求去重器
+    pub fn with_deduplicator(mut self, deduplicator: Arc<RequestDeduplicator>) -> Self {
+        self.deduplicator = Some(deduplicator);
+        self
+    }
+
+    /// 设置上游并发限制器
+    pub fn with_upstream_limiter(mut self, limiter: Arc<UpstreamConcurrencyLimiter>) -> Self {
+        self.upstream_limiter = Some(limiter);
+        self
+    }
+
+    /// 设置 SSE 断线续传注册表
+    pub fn with_sse_replay(mut self, registry: Arc<SseReplayRegistry>) -> Self {
+        self.sse_replay = Some(registry);
+        self
+    }
+
+    /// 设置功能开关
+    pub fn with_features(mut self, features: Arc<FeatureFlags>) -> Self {
+        self.features = features;
+        self
+    }
 }
 
-/// 请求扩展：存储验证后的 pool_id
+/// 请求扩展：存储验证后绑定的池 ID 列表（按回退顺序，为空表示默认池）
 #[derive(Clone, Debug)]
-pub struct AuthenticatedPoolId(pub Option<String>);
+pub struct AuthenticatedPoolId(pub Vec<String>);
 
 /// API Key 认证中间件
 ///
 /// 通过 ApiKeyManager 验证 API Key：
 /// - 验证 API Key 是否在 api_keys.json 中且已启用
-/// - 提取绑定的 pool_id 并存入请求扩展
+/// - 提取绑定的池 ID 列表并存入请求扩展
+///
+/// config.json 中配置的 `apiKey` 同样有效（使用默认池）
 pub async fn auth_middleware(
     State(state): State<AppState>,
     mut request: Request<Body>,
@@ -94,20 +188,30 @@ pub async fn auth_middleware(
     let key = match auth::extract_api_key(&request) {
         Some(k) => k,
         None => {
-            let error = ErrorResponse::authentication_error();
+            let error =
+                ErrorResponse::authentication_error(Locale::from_headers(request.headers()));
             return (StatusCode::UNAUTHORIZED, Json(error)).into_response();
         }
     };
 
     // 使用 ApiKeyManager 验证
-    if let Some(pool_id) = state.api_key_manager.validate_and_get_pool(&key) {
-        // API Key 有效，存储 pool_id 到请求扩展
-        request.extensions_mut().insert(AuthenticatedPoolId(pool_id));
+    if let Some(pool_ids) = state.api_key_manager.validate_and_get_pool(&key) {
+        // API Key 有效，存储绑定的池 ID 列表到请求扩展
+        state.api_key_manager.record_request(&key);
+        request.extensions_mut().insert(AuthenticatedPoolId(pool_ids));
+        return next.run(request).await;
+    }
+
+    // config.json 中的 apiKey（不绑定池）
+    if let Some(api_key) = state.config.api_key.as_deref()
+        && auth::constant_time_eq(&key, api_key)
+    {
+        request.extensions_mut().insert(AuthenticatedPoolId(vec![]));
         return next.run(request).await;
     }
 
     // 认证失败
-    let error = ErrorResponse::authentication_error();
+    let error = ErrorResponse::authentication_error(Locale::from_headers(request.headers()));
     (StatusCode::UNAUTHORIZED, Json(error)).into_response()
 }
 
@@ -129,6 +233,28 @@ pub fn cors_layer() -> tower_http::cors::CorsLayer {
         .allow_headers(Any)
 }
 
+/// 限流拒绝：错误信息和建议的重试等待时间
+#[derive(Debug)]
+pub struct RateLimitExceeded {
+    /// 错误信息
+    pub error: LocalizedError,
+    /// 距当前窗口重置的秒数（用于 `Retry-After` 响应头）
+    pub retry_after_secs: u64,
+}
+
+impl std::fmt::Display for RateLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}（{} 秒后重置）", self.error, self.retry_after_secs)
+    }
+}
+
+/// 距窗口重置的秒数（向上取整，至少 1 秒）
+fn seconds_until_window_reset(now: Duration, window_secs: u64) -> u64 {
+    let window_ms = window_secs * 1000;
+    let remaining_ms = window_ms - (now.as_millis() as u64 % window_ms);
+    remaining_ms.div_ceil(1000).max(1)
+}
+
 /// 限流器
 ///
 /// 支持全局限流和每 API Key 限流
@@ -149,10 +275,56 @@ pub struct RateLimiter {
     key_minute_requests: Arc<DashMap<String, DashMap<u64, u64>>>,
     /// 每 API Key 请求记录（小时级）
     key_hour_requests: Arc<DashMap<String, DashMap<u64, u64>>>,
+    /// 是否收集每 API Key 统计
+    stats_enabled: bool,
+    /// 每 API Key 最近一次请求时间（仅收集统计时记录）
+    key_last_request: Arc<DashMap<String, DateTime<Utc>>>,
     /// 启动时间
     start_time: Instant,
 }
 
+/// 统计中 API Key 仅保留的前缀字符数
+const STATS_KEY_PREFIX_CHARS: usize = 8;
+
+/// 限流统计快照
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitStats {
+    /// 全局计数
+    pub global: GlobalRateLimitStats,
+    /// 每 API Key 计数（按当前分钟请求数降序；关闭 rateLimitStatsEnabled 时为空）
+    pub per_key: Vec<KeyRateLimitStats>,
+}
+
+/// 全局限流统计
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobalRateLimitStats {
+    /// 当前分钟窗口内的请求数
+    pub requests_last_minute: u64,
+    /// 当前小时窗口内的请求数
+    pub requests_last_hour: u64,
+    /// 每分钟上限
+    pub limit_minute: u64,
+    /// 每小时上限
+    pub limit_hour: u64,
+}
+
+/// 单个 API Key 的限流统计
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyRateLimitStats {
+    /// API Key 前 8 位
+    pub key_prefix: String,
+    /// 当前分钟窗口内的请求数
+    pub requests_last_minute: u64,
+    /// 当前小时窗口内的请求数
+    pub requests_last_hour: u64,
+    /// 每分钟上限
+    pub limit_minute: u64,
+    /// 每小时上限
+    pub limit_hour: u64,
+    /// 最近一次请求时间
+    pub last_request_at: Option<DateTime<Utc>>,
+}
+
 impl RateLimiter {
     /// 创建新的限流器
     pub fn new(
@@ -170,17 +342,47 @@ impl RateLimiter {
             global_hour_requests: Arc::new(DashMap::new()),
             key_minute_requests: Arc::new(DashMap::new()),
             key_hour_requests: Arc::new(DashMap::new()),
+            stats_enabled: true,
+            key_last_request: Arc::new(DashMap::new()),
             start_time: Instant::now(),
         }
     }
 
+    /// 按配置创建限流器（未启用限流或使用令牌桶时返回 None）
+    pub fn from_config(config: &Config) -> Option<Self> {
+        (config.rate_limit_enabled && config.rate_limiter_type == RateLimiterType::SlidingWindow)
+            .then(|| {
+                Self::new(
+                    config.rate_limit_per_minute,
+                    config.rate_limit_per_hour,
+                    config.rate_limit_per_key_per_minute,
+                    config.rate_limit_per_key_per_hour,
+                )
+                .with_stats_enabled(config.rate_limit_stats_enabled)
+            })
+    }
+
+    /// 设置是否收集每 API Key 统计（不影响每 Key 限流本身）
+    pub fn with_stats_enabled(mut self, enabled: bool) -> Self {
+        self.stats_enabled = enabled;
+        self
+    }
+
     /// 检查是否允许请求
     ///
-    /// 返回 Ok(()) 如果允许，返回 Err(message) 如果被限流
-    pub fn check_rate_limit(&self, api_key: Option<&str>) -> Result<(), String> {
+    /// 返回 Ok(()) 如果允许，被限流时返回错误信息和距触发窗口重置的秒数
+    pub fn check_rate_limit(&self, api_key: Option<&str>) -> Result<(), RateLimitExceeded> {
         let now = self.start_time.elapsed();
         let current_minute = now.as_secs() / 60;
         let current_hour = now.as_secs() / 3600;
+        let minute_exceeded = |error| RateLimitExceeded {
+            error,
+            retry_after_secs: seconds_until_window_reset(now, 60),
+        };
+        let hour_exceeded = |error| RateLimitExceeded {
+            error,
+            retry_after_secs: seconds_until_window_reset(now, 3600),
+        };
 
         // 检查全局限流（分钟级）
         let global_minute_count = self
@@ -191,9 +393,8 @@ impl RateLimiter {
             .clone();
 
         if global_minute_count >= self.global_per_minute {
-            return Err(format!(
-                "全局限流：每分钟最多 {} 个请求",
-                self.global_per_minute
+            return Err(minute_exceeded(
+                ErrorCode::RateLimitGlobalMinute.arg("limit", self.global_per_minute),
             ));
         }
 
@@ -206,9 +407,8 @@ impl RateLimiter {
             .clone();
 
         if global_hour_count >= self.global_per_hour {
-            return Err(format!(
-                "全局限流：每小时最多 {} 个请求",
-                self.global_per_hour
+            return Err(hour_exceeded(
+                ErrorCode::RateLimitGlobalHour.arg("limit", self.global_per_hour),
             ));
         }
 
@@ -226,9 +426,8 @@ impl RateLimiter {
                 .clone();
 
             if key_minute_count >= self.per_key_per_minute {
-                return Err(format!(
-                    "API Key 限流：每分钟最多 {} 个请求",
-                    self.per_key_per_minute
+                return Err(minute_exceeded(
+                    ErrorCode::RateLimitKeyMinute.arg("limit", self.per_key_per_minute),
                 ));
             }
 
@@ -244,9 +443,8 @@ impl RateLimiter {
                 .clone();
 
             if key_hour_count >= self.per_key_per_hour {
-                return Err(format!(
-                    "API Key 限流：每小时最多 {} 个请求",
-                    self.per_key_per_hour
+                return Err(hour_exceeded(
+                    ErrorCode::RateLimitKeyHour.arg("limit", self.per_key_per_hour),
                 ));
             }
         }
@@ -290,31 +488,344 @@ impl RateLimiter {
                 .entry(current_hour)
                 .and_modify(|count| *count += 1)
                 .or_insert(1);
+
+            if self.stats_enabled {
+                self.key_last_request.insert(key.to_string(), Utc::now());
+            }
         }
 
         // 清理过期数据（保留最近 2 小时的数据）
         self.cleanup_old_records(current_minute, current_hour);
     }
 
+    /// 获取当前窗口的限流统计
+    pub fn stats(&self) -> RateLimitStats {
+        let now = self.start_time.elapsed();
+        let current_minute = now.as_secs() / 60;
+        let current_hour = now.as_secs() / 3600;
+        let count = |map: &DashMap<u64, u64>, window: u64| map.get(&window).map_or(0, |c| *c);
+
+        let global = GlobalRateLimitStats {
+            requests_last_minute: count(&self.global_minute_requests, current_minute),
+            requests_last_hour: count(&self.global_hour_requests, current_hour),
+            limit_minute: self.global_per_minute,
+            limit_hour: self.global_per_hour,
+        };
+        if !self.stats_enabled {
+            return RateLimitStats {
+                global,
+                per_key: Vec::new(),
+            };
+        }
+
+        // 先取出小时计数再逐个查询分钟计数，避免同时持有两个 DashMap 的分片锁
+        let hour_counts: Vec<(String, u64)> = self
+            .key_hour_requests
+            .iter()
+            .map(|entry| (entry.key().clone(), count(entry.value(), current_hour)))
+            .filter(|(_, hour_count)| *hour_count > 0)
+            .collect();
+        let mut per_key: Vec<KeyRateLimitStats> = hour_counts
+            .into_iter()
+            .map(|(key, requests_last_hour)| KeyRateLimitStats {
+                key_prefix: key.chars().take(STATS_KEY_PREFIX_CHARS).collect(),
+                requests_last_minute: self
+                    .key_minute_requests
+                    .get(&key)
+                    .map_or(0, |map| count(&map, current_minute)),
+                requests_last_hour,
+                limit_minute: self.per_key_per_minute,
+                limit_hour: self.per_key_per_hour,
+                last_request_at: self.key_last_request.get(&key).map(|t| *t),
+            })
+            .collect();
+        per_key.sort_by(|a, b| {
+            b.requests_last_minute
+                .cmp(&a.requests_last_minute)
+                .then(b.requests_last_hour.cmp(&a.requests_last_hour))
+        });
+
+        RateLimitStats { global, per_key }
+    }
+
+    /// 清空所有计数（限流窗口随之重置）
+    pub fn reset_stats(&self) {
+        self.global_minute_requests.clear();
+        self.global_hour_requests.clear();
+        self.key_minute_requests.clear();
+        self.key_hour_requests.clear();
+        self.key_last_request.clear();
+    }
+
     /// 清理过期记录
     fn cleanup_old_records(&self, current_minute: u64, current_hour: u64) {
         // 清理超过 2 小时的分钟级记录
-        let minute_threshold = current_minute.saturating_sub(120);
+        // 注意：不能用 saturating_sub 计算阈值，否则启动后前 2 小时会误删当前窗口
         self.global_minute_requests
-            .retain(|&k, _| k > minute_threshold);
+            .retain(|&k, _| k + 120 > current_minute);
 
         // 清理超过 2 小时的小时级记录
-        let hour_threshold = current_hour.saturating_sub(2);
-        self.global_hour_requests.retain(|&k, _| k > hour_threshold);
+        self.global_hour_requests.retain(|&k, _| k + 2 > current_hour);
 
         // 清理每 API Key 的过期记录
         for entry in self.key_minute_requests.iter_mut() {
-            entry.value().retain(|&k, _| k > minute_threshold);
+            entry.value().retain(|&k, _| k + 120 > current_minute);
         }
 
         for entry in self.key_hour_requests.iter_mut() {
-            entry.value().retain(|&k, _| k > hour_threshold);
+            entry.value().retain(|&k, _| k + 2 > current_hour);
         }
+
+        let cutoff = Utc::now() - chrono::Duration::hours(2);
+        self.key_last_request.retain(|_, at| *at > cutoff);
+    }
+}
+
+/// WebSearch 限流器
+///
+/// 与 [`RateLimiter`] 相同的时间窗口计数方式，但只统计 WebSearch 请求：
+/// 每个 API Key 拥有独立的每小时预算，不占用普通消息限流额度
+pub struct WebSearchRateLimiter {
+    /// 默认每 API Key 每小时请求数（0 表示不限制）
+    default_per_hour: u64,
+    /// 每 API Key 请求记录（小时级）
+    key_hour_requests: Arc<DashMap<String, DashMap<u64, u64>>>,
+    /// 已放行的 WebSearch 请求总数
+    total_requests: AtomicU64,
+    /// 被限流拒绝的 WebSearch 请求总数
+    rejected_requests: AtomicU64,
+    /// 启动时间
+    start_time: Instant,
+}
+
+impl WebSearchRateLimiter {
+    /// 创建新的 WebSearch 限流器
+    pub fn new(default_per_hour: u64) -> Self {
+        Self {
+            default_per_hour,
+            key_hour_requests: Arc::new(DashMap::new()),
+            total_requests: AtomicU64::new(0),
+            rejected_requests: AtomicU64::new(0),
+            start_time: Instant::now(),
+        }
+    }
+
+    /// 检查并记录一次 WebSearch 请求
+    ///
+    /// `limit_override` 为 API Key 上配置的覆盖值，未配置时使用默认值。
+    /// 返回 Ok(()) 如果允许（并计入预算），返回 Err(message) 如果被限流
+    pub fn check_and_record(
+        &self,
+        api_key: Option<&str>,
+        limit_override: Option<u64>,
+    ) -> Result<(), String> {
+        let limit = limit_override.unwrap_or(self.default_per_hour);
+        let current_hour = self.start_time.elapsed().as_secs() / 3600;
+
+        if let Some(key) = api_key {
+            let key_hour_map = self
+                .key_hour_requests
+                .entry(key.to_string())
+                .or_default();
+            let mut count = key_hour_map.entry(current_hour).or_insert(0);
+
+            if limit > 0 && *count >= limit {
+                self.rejected_requests.fetch_add(1, Ordering::Relaxed);
+                return Err(format!("WebSearch 限流：每小时最多 {} 个请求", limit));
+            }
+            *count += 1;
+            drop(count);
+
+            // 清理过期数据（保留最近 2 小时的数据）
+            key_hour_map.retain(|&k, _| k + 2 > current_hour);
+        }
+
+        self.total_requests.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// 已放行的 WebSearch 请求总数
+    pub fn total_requests(&self) -> u64 {
+        self.total_requests.load(Ordering::Relaxed)
+    }
+
+    /// 被限流拒绝的 WebSearch 请求总数
+    pub fn rejected_requests(&self) -> u64 {
+        self.rejected_requests.load(Ordering::Relaxed)
+    }
+}
+
+/// 限流豁免规则列表
+///
+/// 启动时从配置加载，Admin API 增删后整体替换，限流中间件每次请求读取
+pub struct RateLimitExemptions {
+    rules: RwLock<Vec<RateLimitExemption>>,
+}
+
+impl RateLimitExemptions {
+    /// 创建豁免规则列表
+    pub fn new(rules: Vec<RateLimitExemption>) -> Self {
+        Self {
+            rules: RwLock::new(rules),
+        }
+    }
+
+    /// 当前所有规则
+    pub fn list(&self) -> Vec<RateLimitExemption> {
+        self.rules.read().clone()
+    }
+
+    /// 整体替换规则
+    pub fn replace(&self, rules: Vec<RateLimitExemption>) {
+        *self.rules.write() = rules;
+    }
+
+    /// 查找第一条命中的规则，返回其豁免原因
+    pub fn find(&self, api_key: Option<&str>, client_ip: Option<IpAddr>) -> Option<String> {
+        self.rules
+            .read()
+            .iter()
+            .find(|rule| rule.matches(api_key, client_ip))
+            .map(|rule| rule.reason.clone())
+    }
+}
+
+/// 定点数放大倍数（令牌数 × 1000 存储，以支持小数补充速率）
+const TOKEN_BUCKET_SCALE: u64 = 1000;
+
+/// 令牌桶限流器
+///
+/// 容量即允许的最大突发请求数，令牌按固定速率（可为小数，如 2.5 个/秒）补充。
+/// 不使用后台任务：每次 `try_consume` 时根据距上次补充的时间惰性补充，
+/// 令牌数和补充时间均为原子变量，通过 CAS 循环保证并发安全
+pub struct TokenBucketLimiter {
+    /// 桶容量（令牌数）
+    capacity: u64,
+    /// 每秒补充的令牌数（定点数，× TOKEN_BUCKET_SCALE）
+    refill_per_second: u64,
+    /// 当前令牌数（定点数，× TOKEN_BUCKET_SCALE）
+    tokens: AtomicU64,
+    /// 已折算为令牌的时间点（相对 start_time 的微秒数）
+    last_refill: AtomicU64,
+    /// 启动时间
+    start_time: Instant,
+    /// 放行的请求数（含豁免请求）
+    total_requests: AtomicU64,
+    /// 命中豁免规则的请求数（不消耗令牌）
+    exempt_requests: AtomicU64,
+}
+
+impl TokenBucketLimiter {
+    /// 创建新的令牌桶（初始为满桶）
+    pub fn new(capacity: u64, refill_per_second: f64) -> Self {
+        Self {
+            capacity,
+            refill_per_second: (refill_per_second * TOKEN_BUCKET_SCALE as f64).round() as u64,
+            tokens: AtomicU64::new(capacity.saturating_mul(TOKEN_BUCKET_SCALE)),
+            last_refill: AtomicU64::new(0),
+            start_time: Instant::now(),
+            total_requests: AtomicU64::new(0),
+            exempt_requests: AtomicU64::new(0),
+        }
+    }
+
+    /// 按配置创建令牌桶（未启用限流或使用滑动窗口时为 None）
+    pub fn from_config(config: &Config) -> Option<Self> {
+        (config.rate_limit_enabled && config.rate_limiter_type == RateLimiterType::TokenBucket)
+            .then(|| {
+                Self::new(
+                    config.token_bucket_capacity,
+                    config.token_bucket_refill_per_second,
+                )
+            })
+    }
+
+    /// 尝试消耗 `cost` 个令牌，令牌不足时返回 false（不扣减）
+    pub fn try_consume(&self, cost: u64) -> bool {
+        self.try_consume_at(cost, self.start_time.elapsed().as_millis() as u64)
+    }
+
+    /// 在指定时间点（相对 start_time 的毫秒数）尝试消耗令牌
+    fn try_consume_at(&self, cost: u64, now_ms: u64) -> bool {
+        self.refill(now_ms);
+
+        let cost = cost.saturating_mul(TOKEN_BUCKET_SCALE);
+        let consumed = self
+            .tokens
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |tokens| {
+                tokens.checked_sub(cost)
+            })
+            .is_ok();
+        if consumed {
+            self.total_requests.fetch_add(1, Ordering::Relaxed);
+        }
+        consumed
+    }
+
+    /// 记录一次豁免请求（计入请求数，不消耗令牌）
+    pub fn record_exempt(&self) {
+        self.total_requests.fetch_add(1, Ordering::Relaxed);
+        self.exempt_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 放行的请求总数（含豁免请求）
+    pub fn total_requests(&self) -> u64 {
+        self.total_requests.load(Ordering::Relaxed)
+    }
+
+    /// 命中豁免规则的请求数
+    pub fn exempt_requests(&self) -> u64 {
+        self.exempt_requests.load(Ordering::Relaxed)
+    }
+
+    /// 补足一个令牌所需的秒数（向上取整，至少 1 秒；不补充时按 60 秒）
+    pub fn retry_after_secs(&self) -> u64 {
+        if self.refill_per_second == 0 {
+            return 60;
+        }
+        let tokens = self.tokens.load(Ordering::Acquire);
+        let deficit = TOKEN_BUCKET_SCALE.saturating_sub(tokens);
+        deficit.div_ceil(self.refill_per_second).max(1)
+    }
+
+    /// 按距上次补充的时间补充令牌（不超过容量）
+    ///
+    /// `last_refill` 只推进已折算为令牌的时间，不足一个定点单位的余下时间留到下次累计，
+    /// 低速率下频繁调用也不会丢失补充量
+    fn refill(&self, now_ms: u64) {
+        if self.refill_per_second == 0 {
+            return;
+        }
+        let now_us = now_ms.saturating_mul(1000);
+        let mut last = self.last_refill.load(Ordering::Acquire);
+        let added = loop {
+            if now_us <= last {
+                return;
+            }
+            let added =
+                ((now_us - last) as u128 * self.refill_per_second as u128 / 1_000_000) as u64;
+            if added == 0 {
+                return;
+            }
+            let converted_us = (added as u128 * 1_000_000 / self.refill_per_second as u128) as u64;
+            // 只有成功推进 last_refill 的线程负责补充这段时间的令牌
+            match self.last_refill.compare_exchange_weak(
+                last,
+                last + converted_us,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break added,
+                Err(actual) => last = actual,
+            }
+        };
+
+        let max = self.capacity.saturating_mul(TOKEN_BUCKET_SCALE);
+        let _ = self
+            .tokens
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |tokens| {
+                Some(tokens.saturating_add(added).min(max))
+            });
     }
 }
 
@@ -326,20 +837,62 @@ pub async fn rate_limit_middleware(
     request: Request<Body>,
     next: Next,
 ) -> Response {
+    // 提取 API Key 和客户端 IP，命中豁免规则时跳过限流检查
+    // 限流在认证之前执行：keyPrefix 只匹配有效的 API Key，避免未认证请求伪造前缀绕过限流
+    let api_key = crate::common::auth::extract_api_key(&request);
+    let client_ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip());
+    let authenticated_key = api_key
+        .as_deref()
+        .filter(|key| is_valid_api_key(&state, key));
+    let exemption = state
+        .rate_limit_exemptions
+        .find(authenticated_key, client_ip);
+    if let Some(reason) = &exemption {
+        tracing::debug!("请求命中限流豁免规则: {}", reason);
+    }
+
+    // 令牌桶模式（豁免请求计入请求数，不消耗令牌）
+    if let Some(bucket) = &state.token_bucket {
+        if exemption.is_some() {
+            bucket.record_exempt();
+        } else if !bucket.try_consume(1) {
+            let e = ErrorCode::RateLimitTokenBucket
+                .arg("capacity", bucket.capacity)
+                .arg(
+                    "rate",
+                    bucket.refill_per_second as f64 / TOKEN_BUCKET_SCALE as f64,
+                );
+            tracing::warn!("限流触发: {}", e);
+            let error = ErrorResponse::new(
+                "rate_limit_error",
+                e,
+                Locale::from_headers(request.headers()),
+            );
+            return rate_limited_response(error, bucket.retry_after_secs());
+        }
+        return next.run(request).await;
+    }
+
     // 如果没有配置限流器，直接放行
     let limiter = match &state.rate_limiter {
         Some(l) => l,
         None => return next.run(request).await,
     };
 
-    // 提取 API Key（如果有）
-    let api_key = crate::common::auth::extract_api_key(&request);
-
-    // 检查限流
-    if let Err(message) = limiter.check_rate_limit(api_key.as_deref()) {
-        tracing::warn!("限流触发: {}", message);
-        let error = ErrorResponse::new("rate_limit_error", &message);
-        return (StatusCode::TOO_MANY_REQUESTS, Json(error)).into_response();
+    // 检查限流（豁免的请求跳过检查）
+    if exemption.is_none()
+        && let Err(e) = limiter.check_rate_limit(api_key.as_deref())
+    {
+        tracing::warn!("限流触发: {}", e);
+        let error = ErrorResponse::new(
+            "rate_limit_error",
+            e.error,
+            Locale::from_headers(request.headers()),
+        );
+        return rate_limited_response(error, e.retry_after_secs);
     }
 
     // 记录请求
@@ -347,3 +900,328 @@ pub async fn rate_limit_middleware(
 
     next.run(request).await
 }
+
+/// API Key 是否有效（ApiKeyManager 中已启用的 Key 或 config.json 中的 apiKey）
+fn is_valid_api_key(state: &AppState, key: &str) -> bool {
+    state.api_key_manager.validate_and_get_pool(key).is_some()
+        || state
+            .config
+            .api_key
+            .as_deref()
+            .is_some_and(|api_key| crate::common::auth::constant_time_eq(key, api_key))
+}
+
+/// 429 响应，附带 `Retry-After` 响应头
+pub(crate) fn rate_limited_response(error: ErrorResponse, retry_after_secs: u64) -> Response {
+    let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(error)).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websearch_limiter_uses_override() {
+        let limiter = WebSearchRateLimiter::new(1);
+
+        assert!(limiter.check_and_record(Some("sk-a"), None).is_ok());
+        assert!(limiter.check_and_record(Some("sk-a"), None).is_err());
+
+        // 覆盖值优先于默认值
+        assert!(limiter.check_and_record(Some("sk-b"), Some(2)).is_ok());
+        assert!(limiter.check_and_record(Some("sk-b"), Some(2)).is_ok());
+        assert!(limiter.check_and_record(Some("sk-b"), Some(2)).is_err());
+
+        assert_eq!(limiter.total_requests(), 3);
+        assert_eq!(limiter.rejected_requests(), 2);
+    }
+
+    #[test]
+    fn test_websearch_limiter_zero_means_unlimited() {
+        let limiter = WebSearchRateLimiter::new(0);
+        for _ in 0..10 {
+            assert!(limiter.check_and_record(Some("sk-a"), None).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_websearch_limits_independent_of_message_limits() {
+        let message_limiter = RateLimiter::new(100, 100, 1, 100);
+        let websearch_limiter = WebSearchRateLimiter::new(1);
+
+        // 耗尽普通消息限流额度
+        assert!(message_limiter.check_rate_limit(Some("sk-a")).is_ok());
+        message_limiter.record_request(Some("sk-a"));
+        assert!(message_limiter.check_rate_limit(Some("sk-a")).is_err());
+
+        // WebSearch 预算不受影响
+        assert!(websearch_limiter.check_and_record(Some("sk-a"), None).is_ok());
+        assert!(websearch_limiter.check_and_record(Some("sk-a"), None).is_err());
+
+        // 耗尽 WebSearch 预算也不影响其他 Key 的普通消息限流
+        assert!(message_limiter.check_rate_limit(Some("sk-b")).is_ok());
+    }
+
+    #[test]
+    fn test_seconds_until_window_reset() {
+        assert_eq!(seconds_until_window_reset(Duration::from_secs(0), 60), 60);
+        assert_eq!(
+            seconds_until_window_reset(Duration::from_millis(45_500), 60),
+            15
+        );
+        assert_eq!(
+            seconds_until_window_reset(Duration::from_millis(59_999), 60),
+            1
+        );
+        assert_eq!(
+            seconds_until_window_reset(Duration::from_secs(3599), 3600),
+            1
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_reports_window_reset() {
+        // 分钟级限流
+        let limiter = RateLimiter::new(100, 100, 1, 100);
+        limiter.record_request(Some("sk-a"));
+        let e = limiter.check_rate_limit(Some("sk-a")).unwrap_err();
+        assert!((1..=60).contains(&e.retry_after_secs));
+
+        // 小时级限流
+        let limiter = RateLimiter::new(100, 1, 100, 100);
+        limiter.record_request(None);
+        let e = limiter.check_rate_limit(None).unwrap_err();
+        assert!(e.retry_after_secs > 60 && e.retry_after_secs <= 3600);
+    }
+
+    #[test]
+    fn test_rate_limit_stats_reflect_traffic() {
+        let limiter = RateLimiter::new(100, 1000, 30, 500);
+        for _ in 0..3 {
+            limiter.record_request(Some("sk-busy-0123456789"));
+        }
+        limiter.record_request(Some("sk-idle-0123456789"));
+        limiter.record_request(None);
+        // 只检查未记录的 Key 不出现在统计中
+        assert!(limiter.check_rate_limit(Some("sk-unused")).is_ok());
+
+        let stats = limiter.stats();
+        assert_eq!(stats.global.requests_last_minute, 5);
+        assert_eq!(stats.global.requests_last_hour, 5);
+        assert_eq!(stats.global.limit_minute, 100);
+        assert_eq!(stats.global.limit_hour, 1000);
+
+        let [busy, idle] = &stats.per_key[..] else {
+            panic!("unexpected per_key: {:?}", stats.per_key);
+        };
+        assert_eq!(busy.key_prefix, "sk-busy-");
+        assert_eq!(busy.requests_last_minute, 3);
+        assert_eq!(busy.requests_last_hour, 3);
+        assert_eq!((busy.limit_minute, busy.limit_hour), (30, 500));
+        assert!(busy.last_request_at.is_some());
+        assert_eq!(idle.key_prefix, "sk-idle-");
+        assert_eq!(idle.requests_last_minute, 1);
+
+        limiter.reset_stats();
+        let stats = limiter.stats();
+        assert_eq!(stats.global.requests_last_minute, 0);
+        assert!(stats.per_key.is_empty());
+    }
+
+    #[test]
+    fn test_rate_limit_stats_disabled_omits_per_key() {
+        let limiter = RateLimiter::new(100, 1000, 1, 500).with_stats_enabled(false);
+        limiter.record_request(Some("sk-a"));
+
+        let stats = limiter.stats();
+        assert_eq!(stats.global.requests_last_minute, 1);
+        assert!(stats.per_key.is_empty());
+        // 每 Key 限流不受影响
+        assert!(limiter.check_rate_limit(Some("sk-a")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_middleware_sets_retry_after() {
+        use tower::ServiceExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let api_key_manager =
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap());
+        let state = AppState::new(api_key_manager, Arc::new(Config::default()))
+            .with_rate_limiter(Arc::new(RateLimiter::new(1, 100, 100, 100)));
+        let app = axum::Router::new()
+            .route("/", axum::routing::get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                state,
+                rate_limit_middleware,
+            ));
+
+        let response = app
+            .clone()
+            .oneshot(Request::new(Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::RETRY_AFTER).is_none());
+
+        let response = app.oneshot(Request::new(Body::empty())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=60).contains(&retry_after));
+    }
+
+    #[tokio::test]
+    async fn test_exempt_api_key_never_rate_limited() {
+        use tower::ServiceExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let api_key_manager =
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap());
+        let config = Config {
+            api_key: Some("sk-internal-a".to_string()),
+            rate_limit_exemptions: vec![RateLimitExemption {
+                key_prefix: Some("sk-internal-".to_string()),
+                cidr: None,
+                reason: "内部服务".to_string(),
+            }],
+            ..Config::default()
+        };
+        let state = AppState::new(api_key_manager, Arc::new(config))
+            .with_rate_limiter(Arc::new(RateLimiter::new(1, 100, 100, 100)));
+        let app = axum::Router::new()
+            .route("/", axum::routing::get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                state,
+                rate_limit_middleware,
+            ));
+        let request = |key: &str| {
+            Request::builder()
+                .header("x-api-key", key)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // 全局每分钟限额为 1，豁免 Key 连续请求仍全部放行
+        for _ in 0..5 {
+            let response = app.clone().oneshot(request("sk-internal-a")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        // 豁免请求仍计入统计，普通 Key 已被全局限额拦截
+        let response = app.clone().oneshot(request("sk-public-a")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // 前缀相同但未通过认证的 Key 不享受豁免
+        let response = app.oneshot(request("sk-internal-forged")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_token_bucket_records_exempt_requests_without_consuming() {
+        use tower::ServiceExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let api_key_manager =
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap());
+        let config = Config {
+            api_key: Some("sk-internal-a".to_string()),
+            rate_limit_exemptions: vec![RateLimitExemption {
+                key_prefix: Some("sk-internal-".to_string()),
+                cidr: None,
+                reason: "内部服务".to_string(),
+            }],
+            ..Config::default()
+        };
+        let bucket = Arc::new(TokenBucketLimiter::new(1, 0.001));
+        let state =
+            AppState::new(api_key_manager, Arc::new(config)).with_token_bucket(bucket.clone());
+        let app = axum::Router::new()
+            .route("/", axum::routing::get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(
+                state,
+                rate_limit_middleware,
+            ));
+        let request = |key: &str| {
+            Request::builder()
+                .header("x-api-key", key)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        for _ in 0..3 {
+            let response = app.clone().oneshot(request("sk-internal-a")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        assert_eq!(bucket.exempt_requests(), 3);
+
+        // 豁免请求未消耗令牌：普通请求仍可用掉唯一的令牌，之后被拒绝
+        let response = app.clone().oneshot(request("sk-public-a")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.oneshot(request("sk-public-a")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(bucket.total_requests(), 4);
+    }
+
+    #[test]
+    fn test_token_bucket_empties_and_refills() {
+        // 容量 3，每秒补充 2.5 个令牌
+        let bucket = TokenBucketLimiter::new(3, 2.5);
+
+        // 初始满桶：允许突发 3 个请求
+        assert!(bucket.try_consume_at(1, 0));
+        assert!(bucket.try_consume_at(1, 0));
+        assert!(bucket.try_consume_at(1, 0));
+        assert!(!bucket.try_consume_at(1, 0));
+
+        // 400ms 补充 1.0 个
+        assert!(bucket.try_consume_at(1, 400));
+        assert!(!bucket.try_consume_at(1, 400));
+
+        // 再过 600ms 补充 1.5 个，剩余 0.5 个不足以放行
+        assert!(bucket.try_consume_at(1, 1000));
+        assert!(!bucket.try_consume_at(1, 1000));
+
+        // 再过 200ms 补充 0.5 个，凑满 1 个
+        assert!(bucket.try_consume_at(1, 1200));
+        assert!(!bucket.try_consume_at(1, 1200));
+
+        // 空桶按 2.5 个/秒补充，1 秒内可补足一个令牌
+        assert_eq!(bucket.retry_after_secs(), 1);
+
+        // 长时间空闲后不超过容量
+        assert!(bucket.try_consume_at(2, 100_000));
+        assert!(!bucket.try_consume_at(2, 100_000));
+        assert!(bucket.try_consume_at(1, 100_000));
+        assert!(!bucket.try_consume_at(1, 100_000));
+    }
+
+    #[test]
+    fn test_token_bucket_slow_rate_with_frequent_calls() {
+        // 每秒补充 0.5 个令牌，请求每 1ms 一次持续到达（被拒绝的请求也会触发补充）
+        let bucket = TokenBucketLimiter::new(1, 0.5);
+        assert!(bucket.try_consume_at(1, 0));
+
+        let admitted: Vec<u64> = (1..=4_000)
+            .filter(|&now_ms| bucket.try_consume_at(1, now_ms))
+            .collect();
+        // 每 2 秒补足一个令牌
+        assert_eq!(admitted, vec![2_000, 4_000]);
+    }
+
+    #[test]
+    fn test_token_bucket_time_does_not_go_backwards() {
+        let bucket = TokenBucketLimiter::new(1, 1.0);
+        assert!(bucket.try_consume_at(1, 5_000));
+        // 更早的时间点不会重复补充
+        assert!(!bucket.try_consume_at(1, 4_000));
+        assert!(bucket.try_consume_at(1, 6_000));
+    }
+}
@@ -0,0 +1,304 @@
+//! Mock Kiro Provider（仅测试使用）
+//!
+//! 返回预置的上游响应，用于在没有真实凭据的情况下测试完整的 handler 流程。
+//!
+//! 事件使用单键 JSON 对象描述，键为事件类型、值为事件负载，例如：
+//! `{"assistantResponseEvent":{"content":"Hello"}}`，
+//! 返回前会编码为 AWS Event Stream 帧。
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use bytes::Bytes;
+use parking_lot::Mutex;
+
+use crate::kiro::error::ProviderError;
+use crate::kiro::parser::crc::crc32;
+use crate::kiro::provider::UpstreamError;
+
+/// Mock 响应携带的上游请求 ID
+pub const MOCK_REQUEST_ID: &str = "mock-request-id";
+
+/// 预置的上游响应
+#[derive(Debug, Clone)]
+pub enum MockResponse {
+    /// 一次性返回的事件（单个事件对象或事件对象数组）
+    Json(String),
+    /// 逐块返回的事件流（每个事件一个数据块）
+    Stream(Vec<String>),
+    /// 逐块返回事件后响应流中断（模拟上游连接断开）
+    StreamError { events: Vec<String>, error: String },
+    /// 逐块返回事件后既不再发送数据也不关闭连接（模拟上游卡住）
+    Stall(Vec<String>),
+    /// 上游错误响应
+    Error { status: u16, body: String },
+    /// 上游 429 限流并要求退避（`Retry-After`）
+    RateLimited { retry_after: Duration },
+}
+
+/// Mock Kiro Provider
+///
+/// 按顺序消费预置响应，最后一个响应会被重复使用（便于覆盖 handler 层重试）
+pub struct MockKiroProvider {
+    responses: Mutex<VecDeque<MockResponse>>,
+    call_count: AtomicUsize,
+    last_request: Mutex<Option<String>>,
+    delay: Mutex<Option<Duration>>,
+}
+
+impl MockKiroProvider {
+    /// 按响应序列创建
+    pub fn new(responses: Vec<MockResponse>) -> Self {
+        Self {
+            responses: Mutex::new(responses.into()),
+            call_count: AtomicUsize::new(0),
+            last_request: Mutex::new(None),
+            delay: Mutex::new(None),
+        }
+    }
+
+    /// 一次性返回指定事件
+    pub fn returning(response_json: &str) -> Self {
+        Self::new(vec![MockResponse::Json(response_json.to_string())])
+    }
+
+    /// 逐块返回指定事件流
+    pub fn returning_stream(events: Vec<&str>) -> Self {
+        Self::new(vec![MockResponse::Stream(
+            events.into_iter().map(str::to_string).collect(),
+        )])
+    }
+
+    /// 已收到的调用次数
+    pub fn call_count(&self) -> usize {
+        self.call_count.load(Ordering::SeqCst)
+    }
+
+    /// 最近一次调用的请求体
+    pub fn last_request(&self) -> Option<String> {
+        self.last_request.lock().clone()
+    }
+
+    /// 设置每次响应前的延迟（模拟上游耗时）
+    pub fn set_delay(&self, delay: Duration) {
+        *self.delay.lock() = Some(delay);
+    }
+
+    /// 等待预置延迟（未设置时立即返回）
+    pub(crate) async fn wait_delay(&self) {
+        let delay = *self.delay.lock();
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// 发送非流式 API 请求
+    pub async fn call_api_with_session(
+        &self,
+        request_body: &str,
+        _session_id: Option<&str>,
+        _user_key: Option<&str>,
+    ) -> anyhow::Result<reqwest::Response> {
+        self.wait_delay().await;
+        self.respond(request_body, false)
+    }
+
+    /// 发送流式 API 请求
+    pub async fn call_api_stream_with_session(
+        &self,
+        request_body: &str,
+        _session_id: Option<&str>,
+        _user_key: Option<&str>,
+    ) -> anyhow::Result<reqwest::Response> {
+        self.wait_delay().await;
+        self.respond(request_body, true)
+    }
+
+    /// 记录请求并返回下一个预置响应
+    pub(crate) fn respond(
+        &self,
+        request_body: &str,
+        is_stream: bool,
+    ) -> anyhow::Result<reqwest::Response> {
+        self.call_count.fetch_add(1, Ordering::SeqCst);
+        *self.last_request.lock() = Some(request_body.to_string());
+
+        let response = {
+            let mut responses = self.responses.lock();
+            if responses.len() > 1 {
+                responses.pop_front()
+            } else {
+                responses.front().cloned()
+            }
+        }
+        .ok_or_else(|| anyhow::anyhow!("MockKiroProvider 没有预置响应"))?;
+
+        let body = match response {
+            MockResponse::Json(json) => reqwest::Body::from(encode_events(&json)?),
+            MockResponse::Stream(events) => {
+                let chunks = encode_chunks(&events)?;
+                reqwest::Body::wrap_stream(futures::stream::iter(
+                    chunks.into_iter().map(Ok::<_, std::io::Error>),
+                ))
+            }
+            MockResponse::StreamError { events, error } => {
+                let chunks = encode_chunks(&events)?;
+                reqwest::Body::wrap_stream(futures::stream::iter(
+                    chunks
+                        .into_iter()
+                        .map(Ok)
+                        .chain(std::iter::once(Err(std::io::Error::other(error)))),
+                ))
+            }
+            MockResponse::Stall(events) => {
+                let chunks = encode_chunks(&events)?;
+                reqwest::Body::wrap_stream(futures::StreamExt::chain(
+                    futures::stream::iter(chunks.into_iter().map(Ok::<_, std::io::Error>)),
+                    futures::stream::pending(),
+                ))
+            }
+            MockResponse::Error { status, body } => {
+                let status = reqwest::StatusCode::from_u16(status)?;
+                let api_type = if is_stream { "流式" } else { "非流式" };
+                return Err(UpstreamError {
+                    message: format!("{} API 请求失败: {} {}", api_type, status, body),
+                    error: ProviderError::Http {
+                        status: status.as_u16(),
+                        body,
+                    },
+                    request_id: Some(MOCK_REQUEST_ID.to_string()),
+                    retry_after: None,
+                }
+                .into());
+            }
+            MockResponse::RateLimited { retry_after } => {
+                let api_type = if is_stream { "流式" } else { "非流式" };
+                return Err(UpstreamError {
+                    message: format!("{} API 请求失败: 429 Too Many Requests", api_type),
+                    error: ProviderError::Http {
+                        status: 429,
+                        body: String::new(),
+                    },
+                    request_id: Some(MOCK_REQUEST_ID.to_string()),
+                    retry_after: Some(retry_after),
+                }
+                .into());
+            }
+        };
+
+        let response = http::Response::builder()
+            .status(200)
+            .header("content-type", "application/vnd.amazon.eventstream")
+            .header("x-amzn-requestid", MOCK_REQUEST_ID)
+            .body(body)?;
+        Ok(reqwest::Response::from(response))
+    }
+}
+
+/// 将每个事件 JSON 编码为一个数据块
+fn encode_chunks(events: &[String]) -> anyhow::Result<Vec<Bytes>> {
+    events
+        .iter()
+        .map(|event| encode_events(event).map(Bytes::from))
+        .collect()
+}
+
+/// 将事件 JSON（单个事件对象或事件对象数组）编码为事件流字节
+fn encode_events(json: &str) -> anyhow::Result<Vec<u8>> {
+    let value: serde_json::Value = serde_json::from_str(json)?;
+    let events = match value {
+        serde_json::Value::Array(events) => events,
+        event => vec![event],
+    };
+
+    let mut bytes = Vec::new();
+    for event in events {
+        let object = event
+            .as_object()
+            .filter(|o| o.len() == 1)
+            .ok_or_else(|| anyhow::anyhow!("事件必须是单键对象: {}", event))?;
+        let (event_type, payload) = object.iter().next().expect("单键对象");
+        bytes.extend(encode_frame(event_type, payload));
+    }
+    Ok(bytes)
+}
+
+/// 编码单个 AWS Event Stream 事件帧
+fn encode_frame(event_type: &str, payload: &serde_json::Value) -> Vec<u8> {
+    let mut headers = Vec::new();
+    for (name, value) in [
+        (":message-type", "event"),
+        (":event-type", event_type),
+        (":content-type", "application/json"),
+    ] {
+        headers.push(name.len() as u8);
+        headers.extend_from_slice(name.as_bytes());
+        headers.push(7); // String 类型
+        headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        headers.extend_from_slice(value.as_bytes());
+    }
+    let payload = serde_json::to_vec(payload).expect("序列化事件负载失败");
+
+    // prelude(12) + headers + payload + message_crc(4)
+    let total_length = 12 + headers.len() + payload.len() + 4;
+    let mut frame = Vec::with_capacity(total_length);
+    frame.extend_from_slice(&(total_length as u32).to_be_bytes());
+    frame.extend_from_slice(&(headers.len() as u32).to_be_bytes());
+    let prelude_crc = crc32(&frame);
+    frame.extend_from_slice(&prelude_crc.to_be_bytes());
+    frame.extend_from_slice(&headers);
+    frame.extend_from_slice(&payload);
+    let message_crc = crc32(&frame);
+    frame.extend_from_slice(&message_crc.to_be_bytes());
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiro::model::events::Event;
+    use crate::kiro::parser::decoder::EventStreamDecoder;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_returning_records_calls() {
+        let mock = MockKiroProvider::returning(r#"{"assistantResponseEvent":{"content":"Hi"}}"#);
+        assert_eq!(mock.call_count(), 0);
+        assert!(mock.last_request().is_none());
+
+        let response = mock.call_api_with_session("{}", None, None).await.unwrap();
+        let body = response.bytes().await.unwrap();
+        let mut decoder = EventStreamDecoder::new();
+        decoder.feed(&body).unwrap();
+        let frame = decoder.decode().unwrap().unwrap();
+        match Event::from_frame(frame).unwrap() {
+            Event::AssistantResponse(event) => assert_eq!(event.content, "Hi"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        assert_eq!(mock.call_count(), 1);
+        assert_eq!(mock.last_request().as_deref(), Some("{}"));
+    }
+
+    #[tokio::test]
+    async fn test_returning_stream_yields_chunk_per_event() {
+        let mock = MockKiroProvider::returning_stream(vec![
+            r#"{"assistantResponseEvent":{"content":"a"}}"#,
+            r#"{"assistantResponseEvent":{"content":"b"}}"#,
+        ]);
+
+        let response = mock.call_api_stream_with_session("{}", None, None).await.unwrap();
+        let chunks: Vec<_> = response.bytes_stream().collect().await;
+        assert_eq!(chunks.len(), 2);
+        // 最后一个响应会被重复使用
+        assert!(mock.call_api_stream_with_session("{}", None, None).await.is_ok());
+        assert_eq!(mock.call_count(), 2);
+    }
+
+    #[test]
+    fn test_encode_events_rejects_multi_key_object() {
+        assert!(encode_events(r#"{"a":{},"b":{}}"#).is_err());
+        assert!(encode_events("not json").is_err());
+    }
+}
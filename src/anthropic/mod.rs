@@ -13,6 +13,10 @@
 //! - `POST /cc/v1/messages` - 创建消息（流式响应会等待 contextUsageEvent 后再发送 message_start，确保 input_tokens 准确）
 //! - `POST /cc/v1/messages/count_tokens` - 计算 token 数量（与 /v1 相同）
 //!
+//! ## API 文档（无需认证，`openapiEnabled` 控制）
+//! - `GET /openapi.json` / `GET /openapi.yaml` - OpenAPI 3.1 规范
+//! - `GET /docs` - Swagger UI
+//!
 //! # 使用示例
 //! ```rust,ignore
 //! use kiro_rs::anthropic;
@@ -22,14 +26,30 @@
 //! axum::serve(listener, app).await?;
 //! ```
 
+mod concurrency;
 mod converter;
+mod dedup;
 mod handlers;
 mod history;
 mod middleware;
+#[cfg(test)]
+pub(crate) mod mock_provider;
+mod openapi;
+mod postprocess;
+mod quota_queue;
+mod replay;
+mod request_span;
 mod router;
 mod service;
 mod stream;
+mod system_rules;
 pub mod types;
 mod websearch;
 
+pub use concurrency::{UpstreamConcurrencyLimiter, UpstreamConcurrencyStats};
+pub use middleware::{
+    RateLimitExemptions, RateLimitStats, RateLimiter, TokenBucketLimiter, WebSearchRateLimiter,
+};
+pub use postprocess::repair_stats;
+pub use replay::SseReplayRegistry;
 pub use router::create_router;
@@ -0,0 +1,188 @@
+//! OpenAPI 规范与 Swagger UI
+//!
+//! 手写的 OpenAPI 3.1 规范（`openapi.json`）描述 `/v1` 公开端点，编译时嵌入二进制：
+//! - `GET /openapi.json` - JSON 格式规范
+//! - `GET /openapi.yaml` - YAML 格式规范（由 JSON 转换）
+//! - `GET /docs` - Swagger UI（从 CDN 加载，读取 `/openapi.json`）
+//!
+//! 以上端点无需认证，可通过配置 `openapiEnabled: false` 关闭。
+
+use std::sync::LazyLock;
+
+use axum::{
+    Router,
+    http::header,
+    response::{Html, IntoResponse},
+    routing::get,
+};
+
+/// OpenAPI 规范（JSON）
+const OPENAPI_JSON: &str = include_str!("openapi.json");
+
+/// OpenAPI 规范（YAML，首次访问时转换）
+static OPENAPI_YAML: LazyLock<String> = LazyLock::new(|| {
+    let spec: serde_json::Value =
+        serde_json::from_str(OPENAPI_JSON).expect("openapi.json 不是合法的 JSON");
+    serde_yaml::to_string(&spec).expect("OpenAPI 规范转换 YAML 失败")
+});
+
+/// Swagger UI 页面
+const SWAGGER_UI_HTML: &str = r##"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+  <meta charset="utf-8" />
+  <meta name="viewport" content="width=device-width, initial-scale=1" />
+  <title>kiro-rs API 文档</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js" crossorigin></script>
+  <script>
+    window.onload = () => {
+      window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+    };
+  </script>
+</body>
+</html>
+"##;
+
+/// 创建 OpenAPI 文档路由（无需认证）
+pub fn create_openapi_router<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/openapi.json", get(get_openapi_json))
+        .route("/openapi.yaml", get(get_openapi_yaml))
+        .route("/docs", get(get_docs))
+}
+
+/// GET /openapi.json
+async fn get_openapi_json() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/json")], OPENAPI_JSON)
+}
+
+/// GET /openapi.yaml
+async fn get_openapi_yaml() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/yaml")],
+        OPENAPI_YAML.as_str(),
+    )
+}
+
+/// GET /docs
+async fn get_docs() -> Html<&'static str> {
+    Html(SWAGGER_UI_HTML)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    /// OpenAPI 3.1 官方 JSON Schema（https://spec.openapis.org/oas/3.1/schema/2022-10-07）
+    const OAS_31_SCHEMA: &str = include_str!("../../tests/fixtures/openapi/oas-3.1-schema.json");
+
+    fn spec() -> serde_json::Value {
+        serde_json::from_str(OPENAPI_JSON).unwrap()
+    }
+
+    async fn get_body(path: &str) -> (StatusCode, String, String) {
+        let response = create_openapi_router::<()>()
+            .oneshot(Request::builder().uri(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let content_type = response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (
+            status,
+            content_type,
+            String::from_utf8(body.to_vec()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_spec_is_valid_openapi_31() {
+        let schema: serde_json::Value = serde_json::from_str(OAS_31_SCHEMA).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let spec = spec();
+        let errors: Vec<String> = validator
+            .iter_errors(&spec)
+            .map(|e| format!("{}: {}", e.instance_path, e))
+            .collect();
+        assert!(errors.is_empty(), "{:#?}", errors);
+    }
+
+    #[test]
+    fn test_spec_covers_public_endpoints() {
+        let spec = spec();
+        for (path, method) in [
+            ("/v1/models", "get"),
+            ("/v1/messages", "post"),
+            ("/v1/messages/count_tokens", "post"),
+            ("/v1/messages/batch", "post"),
+        ] {
+            assert!(
+                spec["paths"][path][method].is_object(),
+                "{} {}",
+                method,
+                path
+            );
+        }
+        let schemes = &spec["components"]["securitySchemes"];
+        assert_eq!(schemes["bearerAuth"]["scheme"], "bearer");
+        assert_eq!(schemes["apiKeyAuth"]["name"], "x-api-key");
+
+        let responses = &spec["paths"]["/v1/messages"]["post"]["responses"];
+        for status in ["200", "400", "401", "429", "502"] {
+            assert!(responses[status].is_object(), "{}", status);
+        }
+
+        // 所有 $ref 指向已定义的组件
+        fn check_refs(value: &serde_json::Value, spec: &serde_json::Value) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    if let Some(serde_json::Value::String(target)) = map.get("$ref") {
+                        let pointer = target.trim_start_matches('#');
+                        assert!(spec.pointer(pointer).is_some(), "悬空引用: {}", target);
+                    }
+                    map.values().for_each(|v| check_refs(v, spec));
+                }
+                serde_json::Value::Array(items) => items.iter().for_each(|v| check_refs(v, spec)),
+                _ => {}
+            }
+        }
+        check_refs(&spec, &spec);
+    }
+
+    #[tokio::test]
+    async fn test_serves_json_yaml_and_docs() {
+        let (status, content_type, body) = get_body("/openapi.json").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "application/json");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            spec()
+        );
+
+        let (status, content_type, body) = get_body("/openapi.yaml").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type, "application/yaml");
+        assert_eq!(
+            serde_yaml::from_str::<serde_json::Value>(&body).unwrap(),
+            spec()
+        );
+
+        let (status, content_type, body) = get_body("/docs").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(content_type.starts_with("text/html"));
+        assert!(body.contains(r#"url: "/openapi.json""#));
+    }
+}
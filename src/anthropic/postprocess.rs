@@ -0,0 +1,239 @@
+//! 上游响应后处理
+//!
+//! Kiro 响应偶尔带有瑕疵，直接透传会破坏严格的 JSON 客户端：
+//! - 文本中的控制字符（如 `\0`）和 chunk 边界处的 BOM
+//! - 工具调用后紧接的文本带有重复的前导空白
+//! - tool_use 输入 JSON 含尾随逗号、字符串内未转义的换行
+//!
+//! 修复是保守的：tool_use 输入能直接解析时不做任何修改，修复后仍无法解析时原样透传并记录日志。
+//! 修复次数通过 [`repair_stats`] 汇总，在 Admin `/stats` 中展示。
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::de::IgnoredAny;
+
+/// 清理过瑕疵的文本增量数
+static TEXT_ARTIFACTS_STRIPPED: AtomicU64 = AtomicU64::new(0);
+
+/// 修复成功的 tool_use 输入数
+static TOOL_JSON_REPAIRED: AtomicU64 = AtomicU64::new(0);
+
+/// 无法修复、原样透传的 tool_use 输入数
+static TOOL_JSON_REPAIR_FAILED: AtomicU64 = AtomicU64::new(0);
+
+/// 响应修复统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResponseRepairStats {
+    /// 清理过控制字符、BOM 或多余前导空白的文本增量数
+    pub text_artifacts_stripped: u64,
+    /// 修复成功的 tool_use 输入数
+    pub tool_json_repaired: u64,
+    /// 无法修复、原样透传的 tool_use 输入数
+    pub tool_json_repair_failed: u64,
+}
+
+/// 获取进程启动以来的响应修复统计
+pub fn repair_stats() -> ResponseRepairStats {
+    ResponseRepairStats {
+        text_artifacts_stripped: TEXT_ARTIFACTS_STRIPPED.load(Ordering::Relaxed),
+        tool_json_repaired: TOOL_JSON_REPAIRED.load(Ordering::Relaxed),
+        tool_json_repair_failed: TOOL_JSON_REPAIR_FAILED.load(Ordering::Relaxed),
+    }
+}
+
+/// 文本后处理器（每个响应一个实例）
+#[derive(Debug, Default)]
+pub struct TextSanitizer {
+    /// 是否需要去除前导空白（工具调用之后、尚未收到非空白文本）
+    trim_leading: bool,
+}
+
+impl TextSanitizer {
+    /// 工具调用之后，下一段文本的前导空白被视为瑕疵并去除
+    ///
+    /// 仅用于流式响应：工具调用后的文本开启新的文本块，前导空白没有意义
+    pub fn on_tool_use(&mut self) {
+        self.trim_leading = true;
+    }
+
+    /// 清理文本增量：去除控制字符（保留 `\n`、`\t`）和 BOM，以及工具调用后的前导空白
+    ///
+    /// 返回值可能为空（整段都是瑕疵）
+    pub fn sanitize<'a>(&mut self, text: &'a str) -> Cow<'a, str> {
+        let mut cleaned = strip_control_chars(text);
+        let mut changed = matches!(cleaned, Cow::Owned(_));
+
+        if self.trim_leading && !cleaned.is_empty() {
+            let trimmed = cleaned.trim_start();
+            if trimmed.len() != cleaned.len() {
+                changed = true;
+                cleaned = Cow::Owned(trimmed.to_string());
+            }
+            // 前导空白可能跨 chunk，直到出现非空白文本才结束
+            self.trim_leading = cleaned.is_empty();
+        }
+
+        if changed {
+            TEXT_ARTIFACTS_STRIPPED.fetch_add(1, Ordering::Relaxed);
+        }
+        cleaned
+    }
+}
+
+/// 需要从文本中去除的字符
+fn is_text_artifact(c: char) -> bool {
+    c == '\u{FEFF}' || (c.is_control() && c != '\n' && c != '\t')
+}
+
+/// 去除控制字符（保留 `\n`、`\t`）和 BOM
+fn strip_control_chars(text: &str) -> Cow<'_, str> {
+    if text.contains(is_text_artifact) {
+        Cow::Owned(text.chars().filter(|&c| !is_text_artifact(c)).collect())
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// 校验并修复完整的 tool_use 输入 JSON
+///
+/// - 空输入或能直接解析的输入原样返回
+/// - 否则尝试修复（尾随逗号、字符串内未转义的换行/制表符、控制字符和 BOM），修复后能解析则返回修复结果
+/// - 修复失败时原样返回并记录警告，不改动数据
+pub fn repair_tool_json<'a>(tool_use_id: &str, input: &'a str) -> Cow<'a, str> {
+    if input.is_empty() || is_valid_json(input) {
+        return Cow::Borrowed(input);
+    }
+
+    let repaired = repair_json(input);
+    if is_valid_json(&repaired) {
+        TOOL_JSON_REPAIRED.fetch_add(1, Ordering::Relaxed);
+        tracing::info!("已修复工具输入 JSON, tool_use_id: {}", tool_use_id);
+        Cow::Owned(repaired)
+    } else {
+        TOOL_JSON_REPAIR_FAILED.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!(
+            "工具输入 JSON 无法修复，原样透传, tool_use_id: {}, 原始内容: {}",
+            tool_use_id,
+            input
+        );
+        Cow::Borrowed(input)
+    }
+}
+
+fn is_valid_json(input: &str) -> bool {
+    serde_json::from_str::<IgnoredAny>(input).is_ok()
+}
+
+/// 逐字符修复常见的 JSON 瑕疵（结果需再次校验）
+fn repair_json(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in input.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+                out.push(c);
+                continue;
+            }
+            match c {
+                '\\' => {
+                    escaped = true;
+                    out.push(c);
+                }
+                '"' => {
+                    in_string = false;
+                    out.push(c);
+                }
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                // 其余 JSON 字符串中不允许的控制字符（如 `\0`）直接去除
+                c if c < ' ' => {}
+                c => out.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '}' | ']' => {
+                // 去除尾随逗号（及其后的空白）
+                let content_len = out.trim_end().len();
+                if out[..content_len].ends_with(',') {
+                    out.truncate(content_len - 1);
+                }
+                out.push(c);
+            }
+            '\u{FEFF}' => {}
+            c if c < ' ' && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 从上游响应中截取的瑕疵样本
+    const CORPUS: &str = include_str!("../../tests/fixtures/postprocess/corpus.json");
+
+    fn corpus() -> serde_json::Value {
+        serde_json::from_str(CORPUS).unwrap()
+    }
+
+    #[test]
+    fn test_tool_input_corpus() {
+        for sample in corpus()["toolInputs"].as_array().unwrap() {
+            let name = sample["name"].as_str().unwrap();
+            let input = sample["input"].as_str().unwrap();
+            let output = repair_tool_json(name, input);
+
+            match sample.get("expected") {
+                // 可修复：修复结果与期望的 JSON 等价
+                Some(expected) => {
+                    let actual: serde_json::Value =
+                        serde_json::from_str(&output).unwrap_or_else(|e| panic!("{}: {}", name, e));
+                    assert_eq!(&actual, expected, "{}", name);
+                }
+                // 无法修复：原样透传
+                None => assert_eq!(output, input, "{}", name),
+            }
+        }
+    }
+
+    #[test]
+    fn test_text_delta_corpus() {
+        for sample in corpus()["textDeltas"].as_array().unwrap() {
+            let name = sample["name"].as_str().unwrap();
+            let mut sanitizer = TextSanitizer::default();
+            if sample["afterToolUse"].as_bool().unwrap_or(false) {
+                sanitizer.on_tool_use();
+            }
+            let output: String = sample["chunks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|chunk| sanitizer.sanitize(chunk.as_str().unwrap()).into_owned())
+                .collect();
+            assert_eq!(output, sample["expected"].as_str().unwrap(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_valid_input_is_untouched() {
+        let input = "{\"path\": \"a\\nb\", \"items\": [1, 2]}";
+        assert!(matches!(repair_tool_json("t", input), Cow::Borrowed(_)));
+
+        let mut sanitizer = TextSanitizer::default();
+        assert!(matches!(sanitizer.sanitize("  hello\n"), Cow::Borrowed(_)));
+    }
+}
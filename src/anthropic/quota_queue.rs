@@ -0,0 +1,184 @@
+//! 额度用尽排队
+//!
+//! 所有凭据均因额度用尽（MONTHLY_REQUEST_COUNT）被禁用、且最早的额度重置时间
+//! 在等待上限内时，非流式请求保持连接排队，凭据重新启用后自动继续执行。
+//! 流式请求不排队，仍然快速失败。
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::kiro::token_manager::MultiTokenManager;
+
+/// 重置时间之后的额外等待（秒）
+///
+/// 后台任务按固定间隔检查重置时间，重新启用可能略晚于 nextDateReset
+const RESET_GRACE_SECS: u64 = crate::health::QUOTA_RESET_CHECK_INTERVAL_SECS * 2;
+
+/// 排队结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueOutcome {
+    /// 无需排队（有可用凭据，或不满足排队条件）
+    NotQueued,
+    /// 排队后凭据已恢复
+    Ready,
+    /// 排队后超时仍无可用凭据
+    TimedOut,
+    /// 队列已满
+    Full,
+}
+
+/// 额度用尽排队队列
+pub struct QuotaQueue {
+    /// 最大排队请求数
+    max_size: usize,
+    /// 最长等待时间（秒）
+    max_wait_secs: u64,
+    /// 当前排队请求数
+    waiting: AtomicUsize,
+}
+
+/// 排队名额（释放时归还）
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl QuotaQueue {
+    /// 创建排队队列
+    pub fn new(max_size: usize, max_wait_secs: u64) -> Self {
+        Self {
+            max_size,
+            max_wait_secs,
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// 当前排队请求数
+    #[allow(dead_code)]
+    pub fn waiting(&self) -> usize {
+        self.waiting.load(Ordering::Acquire)
+    }
+
+    /// 在所有凭据额度用尽时排队等待
+    ///
+    /// # Arguments
+    /// * `token_manager` - 请求所用池的 Token 管理器
+    /// * `now_secs` - 当前时间（Unix 时间戳秒）
+    pub async fn wait_for_credentials(
+        &self,
+        token_manager: &MultiTokenManager,
+        now_secs: u64,
+    ) -> QueueOutcome {
+        if token_manager.available_count() > 0 {
+            return QueueOutcome::NotQueued;
+        }
+        let Some(reset_at) = token_manager.earliest_quota_reset_at() else {
+            return QueueOutcome::NotQueued;
+        };
+        let wait_secs = reset_at.saturating_sub(now_secs);
+        if wait_secs > self.max_wait_secs {
+            return QueueOutcome::NotQueued;
+        }
+
+        let Some(_slot) = self.try_acquire_slot() else {
+            tracing::warn!("额度用尽排队已满（{}），拒绝请求", self.max_size);
+            return QueueOutcome::Full;
+        };
+
+        tracing::info!("所有凭据额度已用尽，请求排队等待 {} 秒后重置", wait_secs);
+        let timeout = Duration::from_secs(wait_secs + RESET_GRACE_SECS);
+        if token_manager.wait_until_available(timeout).await {
+            tracing::info!("凭据额度已重置，继续执行排队请求");
+            QueueOutcome::Ready
+        } else {
+            tracing::warn!("额度用尽排队超时，仍无可用凭据");
+            QueueOutcome::TimedOut
+        }
+    }
+
+    fn try_acquire_slot(&self) -> Option<QueueSlot<'_>> {
+        self.waiting
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < self.max_size).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| QueueSlot(&self.waiting))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiro::model::credentials::KiroCredentials;
+    use crate::model::config::Config;
+    use std::sync::Arc;
+
+    fn create_manager() -> MultiTokenManager {
+        let mut cred = KiroCredentials::default();
+        cred.refresh_token = Some("a".repeat(150));
+        MultiTokenManager::new(Config::default(), vec![cred], None, None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_not_queued_when_reset_unknown_or_too_far() {
+        let queue = QuotaQueue::new(10, 300);
+        let manager = create_manager();
+
+        // 有可用凭据
+        assert_eq!(
+            queue.wait_for_credentials(&manager, 0).await,
+            QueueOutcome::NotQueued
+        );
+
+        // 重置时间未知
+        manager.report_quota_exhausted(1);
+        assert_eq!(
+            queue.wait_for_credentials(&manager, 0).await,
+            QueueOutcome::NotQueued
+        );
+
+        // 重置时间超过等待上限
+        manager.set_quota_reset_at(1, 1000);
+        assert_eq!(
+            queue.wait_for_credentials(&manager, 100).await,
+            QueueOutcome::NotQueued
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queued_request_resumes_after_reenable() {
+        let queue = Arc::new(QuotaQueue::new(10, 300));
+        let manager = Arc::new(create_manager());
+        manager.report_quota_exhausted(1);
+        manager.set_quota_reset_at(1, 1000);
+
+        let waiter = {
+            let queue = queue.clone();
+            let manager = manager.clone();
+            tokio::spawn(async move { queue.wait_for_credentials(&manager, 990).await })
+        };
+        while queue.waiting() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        manager.reenable_quota_reset_credentials(1000);
+        assert_eq!(waiter.await.unwrap(), QueueOutcome::Ready);
+        assert_eq!(queue.waiting(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_rejects() {
+        let queue = QuotaQueue::new(0, 300);
+        let manager = create_manager();
+        manager.report_quota_exhausted(1);
+        manager.set_quota_reset_at(1, 1000);
+
+        assert_eq!(
+            queue.wait_for_credentials(&manager, 990).await,
+            QueueOutcome::Full
+        );
+    }
+}
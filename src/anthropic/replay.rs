@@ -0,0 +1,389 @@
+//! SSE 断线续传
+//!
+//! 流式响应的每个 SSE 事件带有单调递增的 `id`（从 1 开始），并保存在环形缓冲区中
+//! （最近 `sseReplayBufferSize` 个事件）。上游流在后台任务中读取，客户端断开不会中断生成。
+//!
+//! 客户端断线后携带 `Last-Event-ID: <id>` 重新发送相同请求（同一 API Key 与请求体）时，
+//! 从缓冲区回放该事件之后的事件并继续推送后续事件；所需事件已被覆盖或流已过期时，
+//! 返回 `message_error`（`stream_expired`）事件。
+//!
+//! 后台读取的流中仍会记录用量和凭据状态，关闭服务时需通过 [`SseReplayRegistry::drain`]
+//! 等待这些流读取完毕。
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::{HeaderMap, StatusCode, header},
+    response::Response,
+};
+use bytes::Bytes;
+use dashmap::DashMap;
+use futures::channel::mpsc;
+use futures::{Stream, StreamExt, stream};
+use parking_lot::Mutex;
+use tokio::sync::watch;
+
+/// 断线续传请求头
+pub const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// 流结束后保留回放缓冲区的时间
+const FINISHED_STREAM_RETENTION: Duration = Duration::from_secs(60);
+
+/// 环形缓冲区：保存最近的 `(序号, SSE 字节)`
+#[derive(Debug)]
+pub struct SseRingBuffer {
+    events: VecDeque<(u64, Bytes)>,
+    capacity: usize,
+    /// 最近一个事件的序号（尚无事件时为 0）
+    last_seq: u64,
+    /// 上游流是否已结束
+    finished: bool,
+}
+
+/// 请求的事件已不在缓冲区中
+#[derive(Debug, PartialEq, Eq)]
+pub struct StreamExpired;
+
+impl SseRingBuffer {
+    /// 创建缓冲区（容量至少为 1）
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity.max(1)),
+            capacity: capacity.max(1),
+            last_seq: 0,
+            finished: false,
+        }
+    }
+
+    /// 追加一个 SSE 事件（写入 `id:` 行后保存），返回分配的序号和带 id 的事件
+    pub fn push(&mut self, event: &[u8]) -> (u64, Bytes) {
+        self.last_seq += 1;
+        let mut bytes = format!("id: {}\n", self.last_seq).into_bytes();
+        bytes.extend_from_slice(event);
+
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        let bytes = Bytes::from(bytes);
+        self.events.push_back((self.last_seq, bytes.clone()));
+        (self.last_seq, bytes)
+    }
+
+    /// 序号大于 `after` 的事件
+    ///
+    /// 下一个需要的事件已被覆盖时返回 `StreamExpired`
+    pub fn events_after(&self, after: u64) -> Result<Vec<(u64, Bytes)>, StreamExpired> {
+        if let Some((oldest, _)) = self.events.front()
+            && after + 1 < *oldest
+        {
+            return Err(StreamExpired);
+        }
+        Ok(self
+            .events
+            .iter()
+            .filter(|(seq, _)| *seq > after)
+            .cloned()
+            .collect())
+    }
+
+    /// 标记上游流结束
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// 上游流是否已结束
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// 单个流式响应的回放状态
+struct ReplayStream {
+    buffer: Arc<Mutex<SseRingBuffer>>,
+    /// 通知读取方有新事件（值为最新序号）
+    notify: watch::Sender<u64>,
+    /// 原响应头（重连时复用）
+    headers: HeaderMap,
+}
+
+/// SSE 回放注册表（按请求键索引进行中及刚结束的流）
+pub struct SseReplayRegistry {
+    streams: DashMap<String, Arc<ReplayStream>>,
+    buffer_size: usize,
+    /// 正在后台读取上游的流数量
+    producers: watch::Sender<usize>,
+}
+
+/// 后台读取任务计数（任务结束或被取消时减一）
+struct ProducerGuard(Arc<SseReplayRegistry>);
+
+impl ProducerGuard {
+    fn new(registry: Arc<SseReplayRegistry>) -> Self {
+        registry.producers.send_modify(|count| *count += 1);
+        Self(registry)
+    }
+}
+
+impl Drop for ProducerGuard {
+    fn drop(&mut self) {
+        self.0.producers.send_modify(|count| *count -= 1);
+    }
+}
+
+impl SseReplayRegistry {
+    /// 创建注册表
+    pub fn new(buffer_size: usize) -> Self {
+        Self {
+            streams: DashMap::new(),
+            buffer_size,
+            producers: watch::channel(0).0,
+        }
+    }
+
+    /// 正在后台读取上游的流数量
+    #[allow(dead_code)]
+    pub fn active_streams(&self) -> usize {
+        *self.producers.borrow()
+    }
+
+    /// 等待所有后台读取上游的流结束（关闭服务时调用，不包括流结束后的保留期）
+    pub async fn drain(&self) {
+        let mut producers = self.producers.subscribe();
+        let _ = producers.wait_for(|count| *count == 0).await;
+    }
+
+    /// 当前登记的流数量
+    #[allow(dead_code)]
+    pub fn stream_count(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// 解析 `Last-Event-ID` 请求头（不存在或无效时返回 None）
+    pub fn last_event_id(headers: &HeaderMap) -> Option<u64> {
+        headers
+            .get(LAST_EVENT_ID_HEADER)?
+            .to_str()
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// 登记流式响应：在后台读取响应体并写入缓冲区，返回带事件 ID 的响应
+    ///
+    /// 非 200 或非 SSE 响应原样返回
+    pub fn record(self: &Arc<Self>, key: String, response: Response) -> Response {
+        let is_sse = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .is_some_and(|v| v.as_bytes().starts_with(b"text/event-stream"));
+        if response.status() != StatusCode::OK || !is_sse {
+            return response;
+        }
+
+        let (parts, body) = response.into_parts();
+        let (notify, _) = watch::channel(0);
+        let replay = Arc::new(ReplayStream {
+            buffer: Arc::new(Mutex::new(SseRingBuffer::new(self.buffer_size))),
+            notify,
+            headers: parts.headers,
+        });
+        self.streams.insert(key.clone(), replay.clone());
+
+        // 原客户端直接接收事件，不受缓冲区容量影响；客户端断开后继续写入缓冲区
+        let (live_tx, live_rx) = mpsc::unbounded::<Result<Bytes, Infallible>>();
+        let registry = self.clone();
+        let producer = replay.clone();
+        let guard = ProducerGuard::new(self.clone());
+        tokio::spawn(async move {
+            let mut body = body.into_data_stream();
+            while let Some(chunk) = body.next().await {
+                let Ok(chunk) = chunk else {
+                    break;
+                };
+                let (seq, event) = producer.buffer.lock().push(&chunk);
+                let _ = live_tx.unbounded_send(Ok(event));
+                producer.notify.send_replace(seq);
+            }
+            drop(live_tx);
+            producer.buffer.lock().finish();
+            producer.notify.send_modify(|_| {});
+            drop(guard);
+
+            // 保留一段时间供断线的客户端续传
+            tokio::time::sleep(FINISHED_STREAM_RETENTION).await;
+            registry
+                .streams
+                .remove_if(&key, |_, current| Arc::ptr_eq(current, &producer));
+        });
+
+        build_response(replay.headers.clone(), live_rx)
+    }
+
+    /// 断线续传：回放 `last_event_id` 之后的事件
+    ///
+    /// 流不存在（已过期）或事件已被覆盖时返回 `stream_expired` 错误事件
+    pub fn resume(&self, key: &str, last_event_id: u64) -> Response {
+        let Some(replay) = self.streams.get(key).map(|entry| entry.clone()) else {
+            tracing::warn!(last_event_id, "续传的流不存在或已过期");
+            return expired_response(HeaderMap::new());
+        };
+
+        if replay.buffer.lock().events_after(last_event_id).is_err() {
+            tracing::warn!(last_event_id, "续传的事件已不在回放缓冲区中");
+            return expired_response(replay.headers.clone());
+        }
+
+        tracing::info!(last_event_id, "SSE 断线续传");
+        replay_response(&replay, last_event_id)
+    }
+}
+
+/// 从缓冲区读取事件的续传响应（读完已有事件后等待新事件，直到上游流结束）
+fn replay_response(replay: &Arc<ReplayStream>, after: u64) -> Response {
+    let stream = stream::unfold(
+        (replay.clone(), replay.notify.subscribe(), after, false),
+        |(replay, mut notify, mut cursor, done)| async move {
+            if done {
+                return None;
+            }
+            loop {
+                // 先标记已读再读取缓冲区，避免错过读取期间写入的事件
+                notify.borrow_and_update();
+                let (events, finished) = {
+                    let buffer = replay.buffer.lock();
+                    (buffer.events_after(cursor), buffer.is_finished())
+                };
+
+                match events {
+                    Ok(events) if !events.is_empty() => {
+                        cursor = events.last().map(|(seq, _)| *seq).unwrap_or(cursor);
+                        let bytes: Vec<Result<Bytes, Infallible>> =
+                            events.into_iter().map(|(_, bytes)| Ok(bytes)).collect();
+                        return Some((stream::iter(bytes), (replay, notify, cursor, false)));
+                    }
+                    Ok(_) if finished => return None,
+                    Ok(_) => {
+                        if notify.changed().await.is_err() {
+                            return None;
+                        }
+                    }
+                    // 读取方过慢，所需事件已被覆盖
+                    Err(StreamExpired) => {
+                        return Some((
+                            stream::iter(vec![Ok(stream_expired_event())]),
+                            (replay, notify, cursor, true),
+                        ));
+                    }
+                }
+            }
+        },
+    )
+    .flatten();
+
+    build_response(replay.headers.clone(), stream)
+}
+
+/// 仅包含 `stream_expired` 事件的响应
+fn expired_response(headers: HeaderMap) -> Response {
+    build_response(headers, stream::iter(vec![Ok(stream_expired_event())]))
+}
+
+fn build_response<S>(headers: HeaderMap, stream: S) -> Response
+where
+    S: Stream<Item = Result<Bytes, Infallible>> + Send + 'static,
+{
+    let mut response = Response::new(Body::from_stream(stream));
+    *response.headers_mut() = headers;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("text/event-stream"),
+    );
+    response
+}
+
+/// 流已过期事件
+fn stream_expired_event() -> Bytes {
+    Bytes::from(
+        "event: message_error\ndata: {\"type\":\"message_error\",\"error\":\"stream_expired\"}\n\n",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_buffer_keeps_recent_events() {
+        let mut buffer = SseRingBuffer::new(3);
+        for i in 1..=5 {
+            assert_eq!(buffer.push(format!("data: {}\n\n", i).as_bytes()).0, i);
+        }
+
+        let events = buffer.events_after(3).unwrap();
+        let seqs: Vec<u64> = events.iter().map(|(seq, _)| *seq).collect();
+        assert_eq!(seqs, vec![4, 5]);
+        assert_eq!(events[0].1, Bytes::from("id: 4\ndata: 4\n\n"));
+
+        // 事件 3 仍在缓冲区中，可以从事件 2 之后续传
+        assert_eq!(buffer.events_after(2).unwrap().len(), 3);
+        // 事件 2 已被覆盖
+        assert_eq!(buffer.events_after(1), Err(StreamExpired));
+        assert!(buffer.events_after(5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_last_event_id_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(SseReplayRegistry::last_event_id(&headers), None);
+
+        headers.insert(LAST_EVENT_ID_HEADER, " 5 ".parse().unwrap());
+        assert_eq!(SseReplayRegistry::last_event_id(&headers), Some(5));
+
+        headers.insert(LAST_EVENT_ID_HEADER, "abc".parse().unwrap());
+        assert_eq!(SseReplayRegistry::last_event_id(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_background_streams() {
+        let registry = Arc::new(SseReplayRegistry::new(100));
+        let (tx, rx) = mpsc::unbounded::<Result<Bytes, Infallible>>();
+        let mut response = Response::new(Body::from_stream(rx));
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            header::HeaderValue::from_static("text/event-stream"),
+        );
+
+        // 客户端已断开，上游流仍在后台读取
+        drop(registry.record("key".to_string(), response));
+        assert_eq!(registry.active_streams(), 1);
+        tx.unbounded_send(Ok(Bytes::from("data: 1\n\n"))).unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), registry.drain())
+                .await
+                .is_err()
+        );
+
+        drop(tx);
+        tokio::time::timeout(Duration::from_secs(1), registry.drain())
+            .await
+            .unwrap();
+        assert_eq!(registry.active_streams(), 0);
+        // 流结束后仍保留供续传
+        assert_eq!(registry.stream_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resume_unknown_stream_returns_expired() {
+        let registry = SseReplayRegistry::new(100);
+        let response = registry.resume("missing", 3);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("stream_expired"));
+    }
+}
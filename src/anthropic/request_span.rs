@@ -0,0 +1,66 @@
+//! 请求级 tracing span
+//!
+//! 每个消息请求创建一个 `handle_request` span，请求内的所有日志事件共享
+//! request_id、模型、池、凭据和 token 等属性，便于按请求检索日志。
+
+use std::time::Instant;
+
+use tracing::Span;
+use tracing::field;
+
+/// 请求级 span 及计时
+#[derive(Clone)]
+pub struct RequestSpan {
+    span: Span,
+    request_id: String,
+    started: Instant,
+}
+
+impl RequestSpan {
+    /// 创建 `handle_request` span（凭据、token 和耗时字段稍后记录）
+    pub fn new(request_id: String, model: &str, stream: bool) -> Self {
+        let span = tracing::info_span!(
+            "handle_request",
+            request_id = %request_id,
+            model = %model,
+            stream = %stream,
+            pool_id = field::Empty,
+            credential_id = field::Empty,
+            input_tokens = field::Empty,
+            output_tokens = field::Empty,
+            latency_ms = field::Empty,
+        );
+        Self {
+            span,
+            request_id,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// 记录实际服务的池（未使用池时为 "default"）
+    pub fn record_pool(&self, pool_id: Option<&str>) {
+        self.span
+            .record("pool_id", field::display(pool_id.unwrap_or("default")));
+    }
+
+    /// 记录估算的输入 tokens
+    pub fn record_input_tokens(&self, tokens: i32) {
+        self.span.record("input_tokens", tokens);
+    }
+
+    /// 请求完成：记录输出 tokens 和总耗时
+    pub fn finish(&self, output_tokens: i32) {
+        let latency_ms = self.started.elapsed().as_millis() as u64;
+        self.span.record("output_tokens", output_tokens);
+        self.span.record("latency_ms", latency_ms);
+        self.span.in_scope(|| tracing::info!("请求完成"));
+    }
+}
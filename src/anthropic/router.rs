@@ -10,14 +10,23 @@ use axum::{
 };
 
 use crate::admin::ApiKeyManager;
+use crate::common::features::FeatureFlags;
 use crate::health::HealthCheckState;
 use crate::kiro::pool_manager::PoolManager;
 use crate::kiro::provider::KiroProvider;
 use crate::kiro::token_manager::MultiTokenManager;
 
 use super::{
-    handlers::{count_tokens, get_models, post_messages, post_messages_cc},
-    middleware::{AppState, RateLimiter, auth_middleware, cors_layer, rate_limit_middleware},
+    concurrency::UpstreamConcurrencyLimiter,
+    dedup::RequestDeduplicator,
+    handlers::{count_tokens, get_models, post_messages, post_messages_batch, post_messages_cc},
+    middleware::{
+        AppState, RateLimitExemptions, RateLimiter, TokenBucketLimiter, WebSearchRateLimiter,
+        auth_middleware, cors_layer, rate_limit_middleware,
+    },
+    openapi::create_openapi_router,
+    quota_queue::QuotaQueue,
+    replay::SseReplayRegistry,
 };
 
 /// 请求体最大大小限制 (50MB)
@@ -29,7 +38,9 @@ const MAX_BODY_SIZE: usize = 50 * 1024 * 1024;
 /// - `GET /health` - 健康检查
 /// - `GET /v1/models` - 获取可用模型列表
 /// - `POST /v1/messages` - 创建消息（对话）
+/// - `POST /v1/messages/batch` - 批量创建消息（需启用功能开关 `enable_batch_messages`）
 /// - `POST /v1/messages/count_tokens` - 计算 token 数量
+/// - `GET /openapi.json`、`GET /openapi.yaml`、`GET /docs` - API 文档（`openapi_enabled` 时启用，无需认证）
 ///
 /// # 认证
 /// 所有 `/v1` 路径需要 API Key 认证，支持：
@@ -43,6 +54,13 @@ const MAX_BODY_SIZE: usize = 50 * 1024 * 1024;
 /// - `pool_manager`: 可选的池管理器（API Key 绑定池路由）
 /// - `token_manager`: 可选的 Token 管理器（用于健康检查）
 /// - `config`: 应用配置
+/// - `websearch_limiter`: WebSearch 限流器（与 Admin 统计共享）
+/// - `rate_limiter`: 滑动窗口限流器（与 Admin 限流统计共享，未启用或使用令牌桶时为 None）
+/// - `token_bucket`: 令牌桶限流器（与 Admin 运行统计共享，未启用或使用滑动窗口时为 None）
+/// - `rate_limit_exemptions`: 限流豁免规则（与 Admin 共享，运行时增删）
+/// - `sse_replay`: SSE 断线续传注册表（关闭服务时等待后台读取的流结束，未启用时为 None）
+/// - `features`: 功能开关（与 Admin 共享，运行时切换）
+#[allow(clippy::too_many_arguments)]
 pub fn create_router(
     api_key_manager: Arc<ApiKeyManager>,
     kiro_provider: Option<KiroProvider>,
@@ -50,8 +68,17 @@ pub fn create_router(
     pool_manager: Option<Arc<PoolManager>>,
     token_manager: Option<Arc<MultiTokenManager>>,
     config: Arc<crate::model::config::Config>,
+    websearch_limiter: Arc<WebSearchRateLimiter>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    token_bucket: Option<Arc<TokenBucketLimiter>>,
+    rate_limit_exemptions: Arc<RateLimitExemptions>,
+    features: Arc<FeatureFlags>,
+    sse_replay: Option<Arc<SseReplayRegistry>>,
 ) -> Router {
-    let mut state = AppState::new(api_key_manager.clone(), config.clone());
+    let mut state = AppState::new(api_key_manager.clone(), config.clone())
+        .with_websearch_limiter(websearch_limiter)
+        .with_rate_limit_exemptions(rate_limit_exemptions)
+        .with_features(features);
     if let Some(provider) = kiro_provider {
         state = state.with_kiro_provider(provider);
     }
@@ -63,27 +90,56 @@ pub fn create_router(
     }
 
     // 配置限流器
-    if config.rate_limit_enabled {
-        let limiter = Arc::new(RateLimiter::new(
-            config.rate_limit_per_minute,
-            config.rate_limit_per_hour,
-            config.rate_limit_per_key_per_minute,
-            config.rate_limit_per_key_per_hour,
-        ));
+    if let Some(limiter) = rate_limiter {
         state = state.with_rate_limiter(limiter);
     }
+    if let Some(limiter) = token_bucket {
+        state = state.with_token_bucket(limiter);
+    }
+
+    // 配置额度用尽排队（仅非流式请求）
+    if config.quota_queue_enabled {
+        state = state.with_quota_queue(Arc::new(QuotaQueue::new(
+            config.quota_queue_max_size,
+            config.queue_max_wait_secs,
+        )));
+    }
+
+    // 配置请求去重（仅非流式请求）
+    if config.dedup_enabled {
+        state = state.with_deduplicator(Arc::new(RequestDeduplicator::new(
+            config.dedup_max_wait_secs,
+        )));
+    }
+
+    // 配置上游并发限制
+    let upstream_limiter = (config.max_concurrent_upstream_requests > 0).then(|| {
+        Arc::new(UpstreamConcurrencyLimiter::new(
+            config.max_concurrent_upstream_requests,
+            config.upstream_queue_timeout_ms,
+        ))
+    });
+    if let Some(limiter) = upstream_limiter.clone() {
+        state = state.with_upstream_limiter(limiter);
+    }
+
+    // 配置 SSE 断线续传（仅流式请求）
+    if let Some(registry) = sse_replay {
+        state = state.with_sse_replay(registry);
+    }
 
     // 创建健康检查状态
-    let health_state = Arc::new(HealthCheckState::new(
-        token_manager,
-        pool_manager,
-        api_key_manager,
-    ));
+    let mut health_state = HealthCheckState::new(token_manager, pool_manager, api_key_manager);
+    if let Some(limiter) = upstream_limiter {
+        health_state = health_state.with_upstream_limiter(limiter);
+    }
+    let health_state = Arc::new(health_state);
 
     // 需要认证的 /v1 路由
     let v1_routes = Router::new()
         .route("/models", get(get_models))
         .route("/messages", post(post_messages))
+        .route("/messages/batch", post(post_messages_batch))
         .route("/messages/count_tokens", post(count_tokens))
         .layer(middleware::from_fn_with_state(
             state.clone(),
@@ -104,7 +160,14 @@ pub fn create_router(
         .route("/health", get(crate::health::health_check))
         .with_state(health_state)
         .nest("/v1", v1_routes)
-        .nest("/cc/v1", cc_v1_routes)
+        .nest("/cc/v1", cc_v1_routes);
+
+    // API 文档（无需认证）
+    if config.openapi_enabled {
+        router = router.merge(create_openapi_router());
+    }
+
+    let mut router = router
         .layer(cors_layer())
         .layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
         .with_state(state.clone());
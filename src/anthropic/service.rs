@@ -6,23 +6,30 @@
 //! - 会话标识提取
 //! - 流式/非流式响应处理
 
+use std::ops::Range;
 use std::sync::Arc;
 
 use axum::http::HeaderMap;
 use sha2::{Digest, Sha256};
 
+use crate::common::features::{ENABLE_AI_SUMMARY, FeatureFlags};
+use crate::common::i18n::Locale;
+use crate::kiro::fairness::hash_user_id;
 use crate::kiro::model::requests::kiro::KiroRequest;
 use crate::kiro::provider::KiroProvider;
+use crate::kiro::stickiness::SessionIdSource;
 use crate::token;
 
-use super::converter::{ConversionError, ConversionResult, convert_request};
-use super::history::{HistoryConfig, manage_history};
-use super::types::MessagesRequest;
+use super::converter::{
+    ConversionError, ConversionOptions, ConversionResult, ConversionWarning, WarningCode,
+    convert_request,
+};
+use super::history::{HistoryConfig, manage_history, original_message_index};
+use super::request_span::RequestSpan;
+use super::system_rules::apply_system_prompt_rules;
+use super::types::{CountTokensRequest, MessagesRequest};
 use super::websearch;
 
-/// 上下文窗口大小（200k tokens）
-pub const CONTEXT_WINDOW_SIZE: i32 = 200_000;
-
 /// Ping 事件间隔（25秒）
 pub const PING_INTERVAL_SECS: u64 = 25;
 
@@ -30,6 +37,10 @@ pub const PING_INTERVAL_SECS: u64 = 25;
 ///
 /// 包含处理请求所需的所有信息
 pub struct RequestContext {
+    /// 本服务生成的请求 ID（用于日志关联上游请求 ID）
+    pub request_id: String,
+    /// 请求级 tracing span（记录凭据、token 和耗时）
+    pub request_span: RequestSpan,
     /// KiroProvider 实例
     pub provider: Arc<KiroProvider>,
     /// 序列化后的 Kiro 请求体
@@ -42,8 +53,16 @@ pub struct RequestContext {
     pub thinking_enabled: bool,
     /// 会话标识（用于粘性会话轮询）
     pub session_id: Option<String>,
+    /// 用户标识哈希（用于按用户公平调度）
+    pub user_key: Option<String>,
     /// 是否为流式请求
     pub is_stream: bool,
+    /// 错误消息语言（来自 Accept-Language）
+    pub locale: Locale,
+    /// 上游事件流解码缓冲区上限（字节）
+    pub decoder_buffer_size: usize,
+    /// 返回给客户端的转换警告（`suppressConversionWarnings` 开启时为空）
+    pub warnings: Vec<ConversionWarning>,
 }
 
 /// 请求验证结果
@@ -70,7 +89,18 @@ pub enum ValidationResult {
 /// 1. metadata.user_id 中的 session_xxx（Claude Code 自带）
 /// 2. x-session-id header（自定义）
 /// 3. system prompt 哈希（兜底）
+///
+/// 应传入改写前的原始请求：system prompt 哈希基于客户端发送的原始 system 计算，
+/// 调整 `systemPromptRules` 不会改变已有会话的标识
 pub fn extract_session_id(req: &MessagesRequest, headers: &HeaderMap) -> Option<String> {
+    extract_session_id_with_source(req, headers).0
+}
+
+/// 从请求中提取会话标识及其来源（用于粘性会话诊断，规则同 [`extract_session_id`]）
+pub fn extract_session_id_with_source(
+    req: &MessagesRequest,
+    headers: &HeaderMap,
+) -> (Option<String>, SessionIdSource) {
     // 优先级 1: metadata.user_id 中的 session
     // 格式: user_xxx_account__session_0b4445e1-f5be-49e1-87ce-62bbc28ad705
     if let Some(ref metadata) = req.metadata {
@@ -79,7 +109,10 @@ pub fn extract_session_id(req: &MessagesRequest, headers: &HeaderMap) -> Option<
                 let session_part = &user_id[pos..];
                 // 取 session_xxx 部分（到下一个 __ 或结尾）
                 let end = session_part.find("__").unwrap_or(session_part.len());
-                return Some(session_part[..end].to_string());
+                return (
+                    Some(session_part[..end].to_string()),
+                    SessionIdSource::Metadata,
+                );
             }
         }
     }
@@ -88,7 +121,7 @@ pub fn extract_session_id(req: &MessagesRequest, headers: &HeaderMap) -> Option<
     if let Some(session_id) = headers.get("x-session-id") {
         if let Ok(s) = session_id.to_str() {
             if !s.is_empty() {
-                return Some(s.to_string());
+                return (Some(s.to_string()), SessionIdSource::Header);
             }
         }
     }
@@ -111,11 +144,26 @@ pub fn extract_session_id(req: &MessagesRequest, headers: &HeaderMap) -> Option<
                 &session_id,
                 content.len()
             );
-            return Some(session_id);
+            return (Some(session_id), SessionIdSource::SystemHash);
         }
     }
 
-    None
+    (None, SessionIdSource::None)
+}
+
+/// 从 metadata.user_id 中提取用户标识并哈希
+///
+/// 格式: user_xxx_account__session_xxx，取 session 之前的用户部分；
+/// 返回哈希值，调度器不接触原始标识
+pub fn extract_user_key(req: &MessagesRequest) -> Option<String> {
+    let user_id = req.metadata.as_ref()?.user_id.as_deref()?;
+    let end = ["_account", "__session_", "_session_"]
+        .iter()
+        .filter_map(|sep| user_id.find(sep))
+        .min()
+        .unwrap_or(user_id.len());
+    let user = user_id[..end].trim();
+    (!user.is_empty()).then(|| hash_user_id(user))
 }
 
 /// 估算输入 tokens
@@ -143,16 +191,20 @@ pub fn is_websearch_request(payload: &MessagesRequest) -> bool {
 }
 
 /// 转换请求并构建 Kiro 请求体
+///
+/// `payload` 应为已应用 system prompt 改写规则和历史管理的请求（见 `apply_history_management`）
 pub fn convert_and_build_request(
     payload: &MessagesRequest,
     profile_arn: Option<&str>,
     config: &crate::model::config::Config,
 ) -> Result<(String, ConversionResult), ConversionError> {
-    // 应用历史管理（如果启用）
-    let managed_payload = apply_history_management(payload, config);
-
     // 转换请求
-    let conversion_result = convert_request(&managed_payload)?;
+    let options = ConversionOptions {
+        max_document_bytes: config.max_document_bytes,
+        max_image_bytes: config.max_image_bytes,
+        max_request_image_bytes: config.max_request_image_bytes,
+    };
+    let conversion_result = convert_request(payload, &options)?;
 
     // 构建 Kiro 请求
     let kiro_request = KiroRequest {
@@ -170,29 +222,48 @@ pub fn convert_and_build_request(
 /// 应用历史管理策略
 ///
 /// 根据配置对消息历史进行智能管理，包括：
+/// - system prompt 改写规则（最先应用，后续 token 计数基于改写后的 system）
 /// - 自动截断
 /// - AI 摘要
 /// - 图片占位符
 /// - 缓存复用
+///
+/// 同时返回生效的 system prompt 改写规则对应的转换警告，以及截断删除的原始消息下标区间
 fn apply_history_management(
     payload: &MessagesRequest,
     config: &crate::model::config::Config,
-) -> MessagesRequest {
-    // 创建历史管理配置
-    let history_config = HistoryConfig {
-        enabled: config.history_management_enabled,
-        truncate_threshold: config.history_truncate_threshold,
-        enable_ai_summary: config.history_enable_ai_summary,
-        enable_image_placeholder: config.history_enable_image_placeholder,
-        enable_prompt_caching: false, // 暂未实现
-        keep_recent_messages: config.history_keep_recent_messages,
-    };
+    features: &FeatureFlags,
+) -> (
+    MessagesRequest,
+    Vec<ConversionWarning>,
+    Option<Range<usize>>,
+) {
+    // 应用 system prompt 改写规则（仅处理 system，不修改消息）
+    let rules_outcome =
+        apply_system_prompt_rules(&config.system_prompt_rules, payload.system.clone());
+    if !rules_outcome.fired.is_empty() {
+        tracing::info!(
+            "system prompt 改写规则生效: {}",
+            rules_outcome.fired.join(", ")
+        );
+    }
+    let warnings = rules_outcome
+        .fired
+        .iter()
+        .map(|rule| {
+            ConversionWarning::new(
+                WarningCode::SystemPromptRewritten,
+                "system",
+                format!("system prompt 改写规则 {} 生效", rule),
+            )
+        })
+        .collect();
 
     // 应用历史管理
     let result = manage_history(
-        &history_config,
+        &history_config(config, features),
         payload.messages.clone(),
-        payload.system.clone(),
+        rules_outcome.system,
         payload.tools.as_ref(),
     );
 
@@ -209,7 +280,7 @@ fn apply_history_management(
     }
 
     // 返回处理后的请求
-    MessagesRequest {
+    let request = MessagesRequest {
         model: payload.model.clone(),
         max_tokens: payload.max_tokens,
         messages: result.messages,
@@ -219,10 +290,86 @@ fn apply_history_management(
         tool_choice: payload.tool_choice.clone(),
         thinking: payload.thinking.clone(),
         output_config: payload.output_config.clone(),
+        temperature: payload.temperature,
+        top_p: payload.top_p,
+        top_k: payload.top_k,
         metadata: payload.metadata.clone(),
+    };
+    (request, warnings, result.removed_messages)
+}
+
+/// 将警告路径中的消息下标（`messages[N]`）映射回客户端原始请求的下标
+fn remap_warning_path(path: &mut String, removed: Option<&Range<usize>>) {
+    let Some(rest) = path.strip_prefix("messages[") else {
+        return;
+    };
+    let Some((index, suffix)) = rest.split_once(']') else {
+        return;
+    };
+    let Some(original) = index
+        .parse()
+        .ok()
+        .and_then(|i| original_message_index(removed, i))
+    else {
+        return;
+    };
+    *path = format!("messages[{}]{}", original, suffix);
+}
+
+/// 是否请求了 Prompt Caching
+///
+/// system 或消息内容块带有 `cache_control` 标记，或 `anthropic-beta` 头包含 `prompt-caching-*`
+pub fn requests_prompt_caching(payload: &MessagesRequest, headers: &HeaderMap) -> bool {
+    let beta_header = headers
+        .get_all("anthropic-beta")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|beta| beta.trim().starts_with("prompt-caching"));
+
+    beta_header
+        || payload
+            .system
+            .iter()
+            .flatten()
+            .any(|s| s.cache_control.is_some())
+        || payload.messages.iter().any(|m| m.has_cache_control())
+}
+
+/// 创建历史管理配置
+///
+/// AI 摘要需同时开启 `historyEnableAiSummary` 和功能开关 `enable_ai_summary`
+fn history_config(config: &crate::model::config::Config, features: &FeatureFlags) -> HistoryConfig {
+    HistoryConfig {
+        enabled: config.history_management_enabled,
+        truncate_threshold: config.history_truncate_threshold,
+        enable_ai_summary: config.history_enable_ai_summary
+            && features.is_enabled(ENABLE_AI_SUMMARY),
+        enable_image_placeholder: config.history_enable_image_placeholder,
+        enable_prompt_caching: true,
+        keep_recent_messages: config.history_keep_recent_messages,
     }
 }
 
+/// 估算历史管理后实际发送的输入 tokens（仅本地计算）
+///
+/// 与正式请求相同：先应用 system prompt 改写规则，再应用历史管理
+pub fn estimate_effective_input_tokens(
+    payload: &CountTokensRequest,
+    config: &crate::model::config::Config,
+    features: &FeatureFlags,
+) -> u64 {
+    let system = apply_system_prompt_rules(&config.system_prompt_rules, payload.system.clone())
+        .system;
+    manage_history(
+        &history_config(config, features),
+        payload.messages.clone(),
+        system,
+        payload.tools.as_ref(),
+    )
+    .processed_tokens
+}
+
 /// 验证并准备请求
 ///
 /// 执行以下步骤：
@@ -237,6 +384,8 @@ pub fn validate_and_prepare_request(
     payload: &MessagesRequest,
     headers: &HeaderMap,
     config: &crate::model::config::Config,
+    features: &FeatureFlags,
+    request_span: &RequestSpan,
 ) -> ValidationResult {
     // 检查 KiroProvider 是否可用
     let provider = match provider {
@@ -257,8 +406,17 @@ pub fn validate_and_prepare_request(
         };
     }
 
+    // 提取会话标识（基于改写前的原始 system，保证规则调整后会话仍然粘性）
+    let (session_id, session_source) = extract_session_id_with_source(payload, headers);
+    provider.token_manager().record_session_source(session_source);
+    tracing::debug!(session_source = %session_source, "会话标识来源");
+
+    // 应用 system prompt 改写规则和历史管理（在 token 计数之前）
+    let (managed_payload, mut warnings, removed_messages) =
+        apply_history_management(payload, config, features);
+
     // 转换请求
-    let (request_body, _conversion_result) = match convert_and_build_request(payload, profile_arn.map(|s| s.as_str()), config) {
+    let (request_body, conversion_result) = match convert_and_build_request(&managed_payload, profile_arn.map(|s| s.as_str()), config) {
         Ok(result) => result,
         Err(e) => {
             tracing::warn!("请求转换失败: {}", e);
@@ -268,23 +426,57 @@ pub fn validate_and_prepare_request(
 
     tracing::debug!("Kiro request body: {}", request_body);
 
-    // 估算输入 tokens
-    let input_tokens = estimate_input_tokens(payload);
+    // 转换警告始终写入日志，按配置决定是否返回给客户端
+    // 转换基于历史管理后的请求，警告路径映射回客户端原始请求的消息下标
+    warnings.extend(conversion_result.warnings.into_iter().map(|mut warning| {
+        remap_warning_path(&mut warning.path, removed_messages.as_ref());
+        warning
+    }));
+    for warning in &warnings {
+        // 采样参数被忽略很常见，只在 debug 级别记录
+        if warning.code == WarningCode::SamplingParamsIgnored {
+            tracing::debug!(
+                request_id = %request_span.request_id(),
+                code = warning.code.as_str(),
+                path = %warning.path,
+                "转换警告: {}",
+                warning.message
+            );
+            continue;
+        }
+        tracing::warn!(
+            request_id = %request_span.request_id(),
+            code = warning.code.as_str(),
+            path = %warning.path,
+            "转换警告: {}",
+            warning.message
+        );
+    }
+    if config.suppress_conversion_warnings {
+        warnings.clear();
+    }
+
+    // 估算输入 tokens（基于实际发送的请求）
+    let input_tokens = estimate_input_tokens(&managed_payload);
+    request_span.record_input_tokens(input_tokens);
 
     // 检查是否启用了 thinking
     let thinking_enabled = is_thinking_enabled(payload);
 
-    // 提取会话标识
-    let session_id = extract_session_id(payload, headers);
-
     ValidationResult::Ok(RequestContext {
+        request_id: request_span.request_id().to_string(),
+        request_span: request_span.clone(),
         provider,
         request_body,
         model: payload.model.clone(),
         input_tokens,
         thinking_enabled,
         session_id,
+        user_key: extract_user_key(payload),
         is_stream: payload.stream,
+        locale: Locale::from_headers(headers),
+        decoder_buffer_size: config.decoder_buffer_size_bytes,
+        warnings,
     })
 }
 
@@ -309,6 +501,10 @@ mod tests {
                 ),
             }),
             tool_choice: None,
+            output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
         };
 
         let headers = HeaderMap::new();
@@ -318,6 +514,35 @@ mod tests {
         assert!(session_id.unwrap().starts_with("session_"));
     }
 
+    #[test]
+    fn test_extract_user_key_ignores_session() {
+        let with_session = |user_id: &str| MessagesRequest {
+            model: "claude-3-opus".to_string(),
+            max_tokens: 1024,
+            messages: vec![],
+            stream: false,
+            system: None,
+            tools: None,
+            thinking: None,
+            metadata: Some(Metadata {
+                user_id: Some(user_id.to_string()),
+            }),
+            tool_choice: None,
+            output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
+        };
+
+        let a = extract_user_key(&with_session("user_abc_account__session_1")).unwrap();
+        let b = extract_user_key(&with_session("user_abc_account__session_2")).unwrap();
+        let c = extract_user_key(&with_session("user_def_account__session_1")).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a, hash_user_id("user_abc"));
+        assert!(extract_user_key(&with_session("__session_1")).is_none());
+    }
+
     #[test]
     fn test_extract_session_id_from_header() {
         let req = MessagesRequest {
@@ -330,6 +555,10 @@ mod tests {
             thinking: None,
             metadata: None,
             tool_choice: None,
+            output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
         };
 
         let mut headers = HeaderMap::new();
@@ -349,11 +578,16 @@ mod tests {
             stream: false,
             system: Some(vec![SystemMessage {
                 text: "You are a helpful assistant.".to_string(),
+                cache_control: None,
             }]),
             tools: None,
             thinking: None,
             metadata: None,
             tool_choice: None,
+            output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
         };
 
         let headers = HeaderMap::new();
@@ -375,6 +609,10 @@ mod tests {
             thinking: None,
             metadata: None,
             tool_choice: None,
+            output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
         };
 
         // 未启用
@@ -394,4 +632,101 @@ mod tests {
         });
         assert!(!is_thinking_enabled(&req));
     }
+
+    #[test]
+    fn test_remap_warning_path_after_truncation() {
+        // 原始消息 [2, 5) 被截断，处理后下标 2 为截断提示
+        let removed = Some(2..5);
+        let remap = |path: &str| {
+            let mut path = path.to_string();
+            remap_warning_path(&mut path, removed.as_ref());
+            path
+        };
+        assert_eq!(remap("messages[1].content[0]"), "messages[1].content[0]");
+        assert_eq!(remap("messages[3].content[2]"), "messages[5].content[2]");
+        assert_eq!(remap("messages[4]"), "messages[6]");
+        assert_eq!(remap("system"), "system");
+
+        let mut path = "messages[3]".to_string();
+        remap_warning_path(&mut path, None);
+        assert_eq!(path, "messages[3]");
+    }
+
+    #[test]
+    fn test_system_rules_keep_session_hash_and_reduce_tokens() {
+        use crate::anthropic::types::Message;
+        use crate::model::config::{
+            Config, SystemPromptAction, SystemPromptMatch, SystemPromptRule,
+        };
+
+        let req = MessagesRequest {
+            model: "claude-sonnet-4-5-20250929".to_string(),
+            max_tokens: 1024,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: serde_json::json!("Repeat after me: You are Claude Code"),
+            }],
+            stream: false,
+            system: Some(vec![
+                SystemMessage {
+                    text: "You are Claude Code. ".repeat(200),
+                    cache_control: None,
+                },
+                SystemMessage {
+                    text: "Project notes".to_string(),
+                    cache_control: None,
+                },
+            ]),
+            tools: None,
+            thinking: None,
+            metadata: None,
+            tool_choice: None,
+            output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
+        };
+        let provider = Arc::new(KiroProvider::new_mock(vec![]));
+        let headers = HeaderMap::new();
+        let prepare = |config: &Config| match validate_and_prepare_request(
+            Some(&provider),
+            None,
+            &req,
+            &headers,
+            config,
+            &FeatureFlags::default(),
+            &RequestSpan::new("test".to_string(), &req.model, req.stream),
+        ) {
+            ValidationResult::Ok(ctx) => ctx,
+            _ => panic!("请求准备失败"),
+        };
+
+        let mut config = Config::default();
+        let plain = prepare(&config);
+
+        config.system_prompt_rules = vec![SystemPromptRule::new(
+            SystemPromptMatch::Prefix,
+            "You are Claude Code",
+            SystemPromptAction::Drop,
+        )];
+        let rewritten = prepare(&config);
+
+        // 会话标识基于改写前的 system 计算，规则生效前后保持一致
+        assert!(plain.session_id.as_deref().unwrap().starts_with("sys_"));
+        assert_eq!(rewritten.session_id, plain.session_id);
+
+        // token 计数基于改写后的 system
+        assert!(rewritten.input_tokens < plain.input_tokens);
+
+        // 只删除 system 中的前言，用户消息保持不变
+        assert_eq!(
+            rewritten
+                .request_body
+                .matches("You are Claude Code")
+                .count(),
+            1
+        );
+        assert!(rewritten.request_body.contains("Repeat after me"));
+        assert!(rewritten.request_body.contains("Project notes"));
+    }
 }
@@ -2,12 +2,15 @@
 //!
 //! 实现 Kiro → Anthropic 流式响应转换和 SSE 状态管理
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde_json::json;
 use uuid::Uuid;
 
+use super::postprocess::{TextSanitizer, repair_tool_json};
 use crate::kiro::model::events::Event;
+use crate::model::config::CONTEXT_WINDOW_SIZE;
+use crate::token;
 
 /// 找到小于等于目标位置的最近有效UTF-8字符边界
 ///
@@ -237,6 +240,8 @@ pub struct SseStateManager {
     stop_reason: Option<String>,
     /// 是否有工具调用
     has_tool_use: bool,
+    /// 流是否异常终止（message_delta 的 stop_reason 为 null）
+    aborted: bool,
 }
 
 impl Default for SseStateManager {
@@ -255,6 +260,7 @@ impl SseStateManager {
             next_block_index: 0,
             stop_reason: None,
             has_tool_use: false,
+            aborted: false,
         }
     }
 
@@ -282,6 +288,11 @@ impl SseStateManager {
         self.stop_reason = Some(reason.into());
     }
 
+    /// 标记流异常终止
+    pub fn set_aborted(&mut self) {
+        self.aborted = true;
+    }
+
     /// 获取最终的 stop_reason
     pub fn get_stop_reason(&self) -> String {
         if let Some(ref reason) = self.stop_reason {
@@ -414,15 +425,20 @@ impl SseStateManager {
             }
         }
 
-        // 发送 message_delta
+        // 发送 message_delta（异常终止时 stop_reason 为 null）
         if !self.message_delta_sent {
             self.message_delta_sent = true;
+            let stop_reason = if self.aborted {
+                serde_json::Value::Null
+            } else {
+                json!(self.get_stop_reason())
+            };
             events.push(SseEvent::new(
                 "message_delta",
                 json!({
                     "type": "message_delta",
                     "delta": {
-                        "stop_reason": self.get_stop_reason(),
+                        "stop_reason": stop_reason,
                         "stop_sequence": null
                     },
                     "usage": {
@@ -446,8 +462,8 @@ impl SseStateManager {
     }
 }
 
-/// 上下文窗口大小（200k tokens）
-const CONTEXT_WINDOW_SIZE: i32 = 200_000;
+/// 中间 message_delta 的发送间隔（估算输出 tokens）
+const USAGE_UPDATE_INTERVAL_TOKENS: i32 = 200;
 
 /// thinking_buffer 最大长度限制（1MB）
 ///
@@ -466,10 +482,22 @@ pub struct StreamContext {
     pub input_tokens: i32,
     /// 从 contextUsageEvent 计算的实际输入 tokens
     pub context_input_tokens: Option<i32>,
-    /// 输出 tokens 累计
+    /// 输出 tokens 累计（流式过程中为估算值，生成最终事件时按实际发送内容重新计算）
     pub output_tokens: i32,
+    /// 已发送的文本和 thinking 内容
+    emitted_text: String,
+    /// 已发送的工具参数 JSON（block_index -> partial_json 拼接）
+    emitted_tool_json: BTreeMap<i32, String>,
+    /// 是否在流式过程中发送携带累计输出 tokens 的中间 message_delta
+    usage_updates: bool,
+    /// 上次发送中间 message_delta 时的输出 tokens
+    last_usage_update: i32,
     /// 工具块索引映射 (tool_id -> block_index)
     pub tool_block_indices: HashMap<String, i32>,
+    /// 待校验的工具参数 JSON（block_index -> (tool_id, 累计输入)），完整后修复并一次性发送
+    tool_json_buffers: BTreeMap<i32, (String, String)>,
+    /// 文本增量后处理（去除控制字符、工具调用后的多余空白）
+    text_sanitizer: TextSanitizer,
     /// thinking 是否启用
     pub thinking_enabled: bool,
     /// thinking 内容缓冲区
@@ -482,6 +510,10 @@ pub struct StreamContext {
     pub thinking_block_index: Option<i32>,
     /// 文本块索引（thinking 启用时动态分配）
     pub text_block_index: Option<i32>,
+    /// 是否收到上游的结束事件（contextUsageEvent / meteringEvent）
+    pub upstream_completed: bool,
+    /// 上游返回的错误事件信息
+    pub upstream_error: Option<String>,
 }
 
 impl StreamContext {
@@ -498,16 +530,32 @@ impl StreamContext {
             input_tokens,
             context_input_tokens: None,
             output_tokens: 0,
+            emitted_text: String::new(),
+            emitted_tool_json: BTreeMap::new(),
+            usage_updates: false,
+            last_usage_update: 0,
             tool_block_indices: HashMap::new(),
+            tool_json_buffers: BTreeMap::new(),
+            text_sanitizer: TextSanitizer::default(),
             thinking_enabled,
             thinking_buffer: String::new(),
             in_thinking_block: false,
             thinking_extracted: false,
             thinking_block_index: None,
             text_block_index: None,
+            upstream_completed: false,
+            upstream_error: None,
         }
     }
 
+    /// 流式过程中每输出约 200 tokens 发送一次携带累计 output_tokens 的 message_delta
+    ///
+    /// 中间 message_delta 的 stop_reason 为 null，仅用于客户端实时显示 token 数
+    pub fn with_usage_updates(mut self) -> Self {
+        self.usage_updates = true;
+        self
+    }
+
     /// 生成 message_start 事件
     pub fn create_message_start_event(&self) -> serde_json::Value {
         json!({
@@ -569,6 +617,15 @@ impl StreamContext {
 
     /// 处理 Kiro 事件并转换为 Anthropic SSE 事件
     pub fn process_kiro_event(&mut self, event: &Event) -> Vec<SseEvent> {
+        let mut events = self.convert_kiro_event(event);
+        self.record_emitted(&events);
+        if let Some(update) = self.usage_update_event() {
+            events.push(update);
+        }
+        events
+    }
+
+    fn convert_kiro_event(&mut self, event: &Event) -> Vec<SseEvent> {
         match event {
             Event::AssistantResponse(resp) => self.process_assistant_response(&resp.content),
             Event::ToolUse(tool_use) => self.process_tool_use(tool_use),
@@ -579,6 +636,7 @@ impl StreamContext {
                     * (CONTEXT_WINDOW_SIZE as f64)
                     / 100.0) as i32;
                 self.context_input_tokens = Some(actual_input_tokens);
+                self.upstream_completed = true;
                 tracing::debug!(
                     "收到 contextUsageEvent: {}%, 计算 input_tokens: {}",
                     context_usage.context_usage_percentage,
@@ -586,11 +644,16 @@ impl StreamContext {
                 );
                 Vec::new()
             }
+            Event::Metering(()) => {
+                self.upstream_completed = true;
+                Vec::new()
+            }
             Event::Error {
                 error_code,
                 error_message,
             } => {
                 tracing::error!("收到错误事件: {} - {}", error_code, error_message);
+                self.upstream_error = Some(format!("{}: {}", error_code, error_message));
                 Vec::new()
             }
             Event::Exception {
@@ -608,8 +671,91 @@ impl StreamContext {
         }
     }
 
+    /// 记录已发送的内容增量，用于计算最终输出 tokens
+    fn record_emitted(&mut self, events: &[SseEvent]) {
+        for event in events {
+            match event.event.as_str() {
+                "content_block_start" => {
+                    if event.data["content_block"]["type"] == "tool_use"
+                        && let Some(index) = event.data["index"].as_i64()
+                    {
+                        self.emitted_tool_json.entry(index as i32).or_default();
+                    }
+                }
+                "content_block_delta" => {
+                    let delta = &event.data["delta"];
+                    match delta["type"].as_str() {
+                        Some("text_delta") => {
+                            self.emitted_text
+                                .push_str(delta["text"].as_str().unwrap_or_default());
+                        }
+                        Some("thinking_delta") => {
+                            self.emitted_text
+                                .push_str(delta["thinking"].as_str().unwrap_or_default());
+                        }
+                        Some("input_json_delta") => {
+                            if let Some(index) = event.data["index"].as_i64() {
+                                self.emitted_tool_json
+                                    .entry(index as i32)
+                                    .or_default()
+                                    .push_str(delta["partial_json"].as_str().unwrap_or_default());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// 输出 tokens 增长超过间隔时生成中间 message_delta
+    fn usage_update_event(&mut self) -> Option<SseEvent> {
+        if !self.usage_updates
+            || self.output_tokens - self.last_usage_update < USAGE_UPDATE_INTERVAL_TOKENS
+        {
+            return None;
+        }
+        self.last_usage_update = self.output_tokens;
+        Some(SseEvent::new(
+            "message_delta",
+            json!({
+                "type": "message_delta",
+                "delta": {
+                    "stop_reason": null,
+                    "stop_sequence": null
+                },
+                "usage": {
+                    "output_tokens": self.output_tokens
+                }
+            }),
+        ))
+    }
+
+    /// 按实际发送的内容计算输出 tokens（与非流式响应的计算方式一致）
+    fn emitted_output_tokens(&self) -> i32 {
+        let mut content = Vec::new();
+        if !self.emitted_text.is_empty() {
+            content.push(json!({
+                "type": "text",
+                "text": self.emitted_text
+            }));
+        }
+        for partial_json in self.emitted_tool_json.values() {
+            let input = serde_json::from_str::<serde_json::Value>(partial_json)
+                .unwrap_or_else(|_| json!({}));
+            content.push(json!({
+                "type": "tool_use",
+                "input": input
+            }));
+        }
+        token::estimate_output_tokens(&content)
+    }
+
     /// 处理助手响应事件
     fn process_assistant_response(&mut self, content: &str) -> Vec<SseEvent> {
+        let content = self.text_sanitizer.sanitize(content);
+        let content = content.as_ref();
         if content.is_empty() {
             return Vec::new();
         }
@@ -855,6 +1001,7 @@ impl StreamContext {
         let mut events = Vec::new();
 
         self.state_manager.set_has_tool_use(true);
+        self.text_sanitizer.on_tool_use();
 
         // tool_use 必须发生在 thinking 结束之后。
         // 但当 `</thinking>` 后面没有 `\n\n`（例如紧跟 tool_use 或流结束）时，
@@ -935,27 +1082,20 @@ impl StreamContext {
         );
         events.extend(start_events);
 
-        // 发送参数增量 (ToolUseEvent.input 是 String 类型)
+        // 累计参数 (ToolUseEvent.input 是 String 类型)，完整后校验修复再发送
         if !tool_use.input.is_empty() {
             self.output_tokens += (tool_use.input.len() as i32 + 3) / 4; // 估算 token
 
-            if let Some(delta_event) = self.state_manager.handle_content_block_delta(
-                block_index,
-                json!({
-                    "type": "content_block_delta",
-                    "index": block_index,
-                    "delta": {
-                        "type": "input_json_delta",
-                        "partial_json": tool_use.input
-                    }
-                }),
-            ) {
-                events.push(delta_event);
-            }
+            self.tool_json_buffers
+                .entry(block_index)
+                .or_insert_with(|| (tool_use.tool_use_id.clone(), String::new()))
+                .1
+                .push_str(&tool_use.input);
         }
 
-        // 如果是完整的工具调用（stop=true），发送 content_block_stop
+        // 如果是完整的工具调用（stop=true），发送参数和 content_block_stop
         if tool_use.stop {
+            events.extend(self.flush_tool_json(block_index));
             if let Some(stop_event) = self.state_manager.handle_content_block_stop(block_index) {
                 events.push(stop_event);
             }
@@ -964,6 +1104,23 @@ impl StreamContext {
         events
     }
 
+    /// 发送工具块累计的参数 JSON（修复常见瑕疵后作为单个 input_json_delta）
+    fn flush_tool_json(&mut self, block_index: i32) -> Option<SseEvent> {
+        let (tool_use_id, input) = self.tool_json_buffers.remove(&block_index)?;
+        let input = repair_tool_json(&tool_use_id, &input);
+        self.state_manager.handle_content_block_delta(
+            block_index,
+            json!({
+                "type": "content_block_delta",
+                "index": block_index,
+                "delta": {
+                    "type": "input_json_delta",
+                    "partial_json": input
+                }
+            }),
+        )
+    }
+
     /// 生成最终事件序列
     pub fn generate_final_events(&mut self) -> Vec<SseEvent> {
         let mut events = Vec::new();
@@ -1029,8 +1186,16 @@ impl StreamContext {
             }
         }
 
+        // 发送未收到 stop 的工具参数
+        let pending: Vec<i32> = self.tool_json_buffers.keys().copied().collect();
+        for block_index in pending {
+            events.extend(self.flush_tool_json(block_index));
+        }
+
         // 使用从 contextUsageEvent 计算的 input_tokens，如果没有则使用估算值
         let final_input_tokens = self.context_input_tokens.unwrap_or(self.input_tokens);
+        self.record_emitted(&events);
+        self.output_tokens = self.emitted_output_tokens();
 
         // 生成最终事件
         events.extend(
@@ -1039,6 +1204,36 @@ impl StreamContext {
         );
         events
     }
+
+    /// 生成异常终止的最终事件序列
+    ///
+    /// 在 message_delta 之前插入 `error` 事件（overloaded_error），
+    /// 且 message_delta 的 stop_reason 为 null，避免客户端把截断的输出当作完整消息
+    pub fn generate_abort_events(&mut self, reason: &str) -> Vec<SseEvent> {
+        self.state_manager.set_aborted();
+        let mut events = self.generate_final_events();
+
+        let message = match &self.upstream_error {
+            Some(upstream_error) => format!("{}: {}", reason, upstream_error),
+            None => reason.to_string(),
+        };
+        let error_event = SseEvent::new(
+            "error",
+            json!({
+                "type": "error",
+                "error": {
+                    "type": "overloaded_error",
+                    "message": message
+                }
+            }),
+        );
+        let position = events
+            .iter()
+            .position(|e| e.event == "message_delta" || e.event == "message_stop")
+            .unwrap_or(events.len());
+        events.insert(position, error_event);
+        events
+    }
 }
 
 /// 缓冲流处理上下文 - 用于 /cc/v1/messages 流式请求
@@ -1079,6 +1274,11 @@ impl BufferedStreamContext {
         }
     }
 
+    /// 输出 tokens 累计
+    pub fn output_tokens(&self) -> i32 {
+        self.inner.output_tokens
+    }
+
     /// 处理 Kiro 事件并缓冲结果
     ///
     /// 复用 StreamContext 的事件处理逻辑，但把结果缓存而不是立即发送。
@@ -1095,6 +1295,11 @@ impl BufferedStreamContext {
         self.event_buffer.extend(events);
     }
 
+    /// 是否收到上游的结束事件
+    pub fn is_upstream_completed(&self) -> bool {
+        self.inner.upstream_completed
+    }
+
     /// 完成流处理并返回所有事件
     ///
     /// 此方法会：
@@ -1102,6 +1307,15 @@ impl BufferedStreamContext {
     /// 2. 用正确的 input_tokens 更正 message_start 事件
     /// 3. 返回所有缓冲的事件
     pub fn finish_and_get_all_events(&mut self) -> Vec<SseEvent> {
+        self.finish(None)
+    }
+
+    /// 异常终止流处理并返回所有事件（最终事件中包含 `error` 事件）
+    pub fn abort_and_get_all_events(&mut self, reason: &str) -> Vec<SseEvent> {
+        self.finish(Some(reason))
+    }
+
+    fn finish(&mut self, abort_reason: Option<&str>) -> Vec<SseEvent> {
         // 如果从未处理过事件，也要生成初始事件
         if !self.initial_events_generated {
             let initial_events = self.inner.generate_initial_events();
@@ -1110,7 +1324,10 @@ impl BufferedStreamContext {
         }
 
         // 生成最终事件
-        let final_events = self.inner.generate_final_events();
+        let final_events = match abort_reason {
+            Some(reason) => self.inner.generate_abort_events(reason),
+            None => self.inner.generate_final_events(),
+        };
         self.event_buffer.extend(final_events);
 
         // 获取正确的 input_tokens
@@ -1204,6 +1421,41 @@ mod tests {
         assert!(event.is_none());
     }
 
+    #[test]
+    fn test_tool_input_repaired_before_emit() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        let _initial_events = ctx.generate_initial_events();
+
+        let mut events = Vec::new();
+        for (input, stop) in [("{\"path\": \"a.txt\",", false), ("}", true)] {
+            let tool_use = crate::kiro::model::events::ToolUseEvent {
+                name: "Read".to_string(),
+                tool_use_id: "tool_1".to_string(),
+                input: input.to_string(),
+                stop,
+            };
+            events.extend(ctx.process_kiro_event(&Event::ToolUse(tool_use)));
+        }
+        // 工具调用后的多余空白和控制字符被去除
+        for content in ["\n\n", "Done\u{0}."] {
+            events.extend(ctx.process_assistant_response(content));
+        }
+
+        let deltas: Vec<&str> = events
+            .iter()
+            .filter(|e| e.data["delta"]["type"] == "input_json_delta")
+            .map(|e| e.data["delta"]["partial_json"].as_str().unwrap())
+            .collect();
+        assert_eq!(deltas, vec!["{\"path\": \"a.txt\"}"]);
+
+        let text: String = events
+            .iter()
+            .filter(|e| e.data["delta"]["type"] == "text_delta")
+            .map(|e| e.data["delta"]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(text, "Done.");
+    }
+
     #[test]
     fn test_text_delta_after_tool_use_restarts_text_block() {
         let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
@@ -1342,6 +1594,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_abort_events_insert_error_before_message_delta() {
+        let mut ctx = StreamContext::new_with_thinking("test-model", 1, false);
+        let _ = ctx.generate_initial_events();
+        let _ = ctx.process_assistant_response("partial");
+        ctx.upstream_error = Some("InternalFailure: boom".to_string());
+
+        let events = ctx.generate_abort_events("上游响应流中断");
+        let names: Vec<&str> = events.iter().map(|e| e.event.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "content_block_stop",
+                "error",
+                "message_delta",
+                "message_stop"
+            ]
+        );
+
+        let error = &events[1].data["error"];
+        assert_eq!(error["type"], "overloaded_error");
+        assert_eq!(error["message"], "上游响应流中断: InternalFailure: boom");
+        assert!(events[2].data["delta"]["stop_reason"].is_null());
+    }
+
     #[test]
     fn test_estimate_tokens() {
         assert!(estimate_tokens("Hello") > 0);
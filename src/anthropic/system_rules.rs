@@ -0,0 +1,209 @@
+//! system prompt 改写规则
+//!
+//! 按配置的 `systemPromptRules` 顺序改写 system 字段中的文本段（删除、替换或截断），
+//! 用于去除客户端附带的大段固定前言以节省上下文。只处理 system，不修改 user/assistant 消息。
+//!
+//! 会话标识（system prompt 哈希）基于改写前的原始 system 计算，
+//! 因此调整规则不会让已有会话失去粘性。
+
+use crate::anthropic::types::SystemMessage;
+use crate::model::config::{SystemPromptAction, SystemPromptMatch, SystemPromptRule};
+
+/// 规则应用结果
+#[derive(Debug, Default)]
+pub struct SystemRulesOutcome {
+    /// 改写后的 system（所有文本段都被删除时为 None）
+    pub system: Option<Vec<SystemMessage>>,
+    /// 生效的规则描述（如 `strip-preamble(drop)`）
+    pub fired: Vec<String>,
+}
+
+/// 按顺序对每个 system 文本段应用规则
+///
+/// 文本段被删除后不再应用后续规则；没有规则或 system 为空时原样返回
+pub fn apply_system_prompt_rules(
+    rules: &[SystemPromptRule],
+    system: Option<Vec<SystemMessage>>,
+) -> SystemRulesOutcome {
+    let Some(system) = system else {
+        return SystemRulesOutcome::default();
+    };
+    if rules.is_empty() {
+        return SystemRulesOutcome {
+            system: Some(system),
+            fired: Vec::new(),
+        };
+    }
+
+    let mut fired = Vec::new();
+    let mut rewritten = Vec::with_capacity(system.len());
+    'segments: for mut message in system {
+        for (index, rule) in rules.iter().enumerate() {
+            if !matches(rule, &message.text) {
+                continue;
+            }
+            fired.push(describe(rule, index));
+            match &rule.action {
+                SystemPromptAction::Drop => continue 'segments,
+                SystemPromptAction::Replace { with } => {
+                    message.text = replace(rule, &message.text, with);
+                }
+                SystemPromptAction::Truncate { max_chars } => {
+                    if let Some((end, _)) = message.text.char_indices().nth(*max_chars) {
+                        message.text.truncate(end);
+                    }
+                }
+            }
+        }
+        rewritten.push(message);
+    }
+
+    SystemRulesOutcome {
+        system: (!rewritten.is_empty()).then_some(rewritten),
+        fired,
+    }
+}
+
+/// 规则是否匹配文本
+fn matches(rule: &SystemPromptRule, text: &str) -> bool {
+    match rule.match_type {
+        SystemPromptMatch::Exact => text == rule.pattern,
+        SystemPromptMatch::Prefix => text.starts_with(&rule.pattern),
+        SystemPromptMatch::Regex => rule.regex().is_some_and(|re| re.is_match(text)),
+    }
+}
+
+/// 替换匹配部分（exact 为整段，prefix 为前缀，regex 为所有匹配）
+fn replace(rule: &SystemPromptRule, text: &str, with: &str) -> String {
+    match rule.match_type {
+        SystemPromptMatch::Exact => with.to_string(),
+        SystemPromptMatch::Prefix => format!("{}{}", with, &text[rule.pattern.len()..]),
+        SystemPromptMatch::Regex => match rule.regex() {
+            Some(re) => re.replace_all(text, with).into_owned(),
+            None => text.to_string(),
+        },
+    }
+}
+
+/// 规则描述（用于日志）
+fn describe(rule: &SystemPromptRule, index: usize) -> String {
+    let name = rule
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("#{}", index + 1));
+    let action = match rule.action {
+        SystemPromptAction::Drop => "drop",
+        SystemPromptAction::Replace { .. } => "replace",
+        SystemPromptAction::Truncate { .. } => "truncate",
+    };
+    format!("{}({})", name, action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(texts: &[&str]) -> Option<Vec<SystemMessage>> {
+        Some(
+            texts
+                .iter()
+                .map(|t| SystemMessage {
+                    text: t.to_string(),
+                    cache_control: None,
+                })
+                .collect(),
+        )
+    }
+
+    fn texts(outcome: &SystemRulesOutcome) -> Vec<&str> {
+        outcome
+            .system
+            .iter()
+            .flatten()
+            .map(|s| s.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_rules_apply_in_order() {
+        let rules = vec![
+            SystemPromptRule::new(
+                SystemPromptMatch::Prefix,
+                "You are Claude Code",
+                SystemPromptAction::Drop,
+            )
+            .with_name("strip-preamble"),
+            SystemPromptRule::new(
+                SystemPromptMatch::Regex,
+                r"<env>[\s\S]*?</env>",
+                SystemPromptAction::Replace {
+                    with: "<env/>".to_string(),
+                },
+            ),
+            SystemPromptRule::new(
+                SystemPromptMatch::Exact,
+                "Be brief.",
+                SystemPromptAction::Truncate { max_chars: 2 },
+            ),
+        ];
+
+        let outcome = apply_system_prompt_rules(
+            &rules,
+            system(&[
+                "You are Claude Code, Anthropic's official CLI.",
+                "Project rules <env>cwd: /tmp\nos: linux</env> end",
+                "Be brief.",
+                "Untouched 中文",
+            ]),
+        );
+
+        assert_eq!(
+            texts(&outcome),
+            vec!["Project rules <env/> end", "Be", "Untouched 中文"]
+        );
+        assert_eq!(
+            outcome.fired,
+            vec!["strip-preamble(drop)", "#2(replace)", "#3(truncate)"]
+        );
+    }
+
+    #[test]
+    fn test_prefix_replace_and_char_truncation() {
+        let rules = vec![
+            SystemPromptRule::new(
+                SystemPromptMatch::Prefix,
+                "前言：",
+                SystemPromptAction::Replace {
+                    with: "".to_string(),
+                },
+            ),
+            SystemPromptRule::new(
+                SystemPromptMatch::Regex,
+                ".",
+                SystemPromptAction::Truncate { max_chars: 3 },
+            ),
+        ];
+
+        let outcome = apply_system_prompt_rules(&rules, system(&["前言：你好世界"]));
+        // 按字符截断，不会截断在 UTF-8 字符中间
+        assert_eq!(texts(&outcome), vec!["你好世"]);
+    }
+
+    #[test]
+    fn test_drop_all_segments_clears_system() {
+        let rules = vec![SystemPromptRule::new(
+            SystemPromptMatch::Regex,
+            "^",
+            SystemPromptAction::Drop,
+        )];
+
+        let outcome = apply_system_prompt_rules(&rules, system(&["a", "b"]));
+        assert!(outcome.system.is_none());
+        assert_eq!(outcome.fired.len(), 2);
+
+        // 无规则时原样返回
+        let outcome = apply_system_prompt_rules(&[], system(&["a"]));
+        assert_eq!(texts(&outcome), vec!["a"]);
+        assert!(outcome.fired.is_empty());
+    }
+}
@@ -3,12 +3,17 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::common::i18n::{ErrorCode, Locale, LocalizedError};
+
 // === 错误响应 ===
 
 /// API 错误响应
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
     pub error: ErrorDetail,
+    /// 上游（Kiro）请求 ID，便于向 Kiro 支持反馈问题
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_request_id: Option<String>,
 }
 
 /// 错误详情
@@ -16,23 +21,42 @@ pub struct ErrorResponse {
 pub struct ErrorDetail {
     #[serde(rename = "type")]
     pub error_type: String,
+    /// 机器可读的稳定错误码
+    pub code: String,
     pub message: String,
 }
 
 impl ErrorResponse {
-    /// 创建新的错误响应
-    pub fn new(error_type: impl Into<String>, message: impl Into<String>) -> Self {
+    /// 创建新的错误响应，消息按客户端语言渲染
+    pub fn new(
+        error_type: impl Into<String>,
+        error: impl Into<LocalizedError>,
+        locale: Locale,
+    ) -> Self {
+        let error = error.into();
         Self {
             error: ErrorDetail {
                 error_type: error_type.into(),
-                message: message.into(),
+                code: error.code().as_str().to_string(),
+                message: error.render(locale),
             },
+            upstream_request_id: None,
         }
     }
 
+    /// 附加上游请求 ID
+    pub fn with_upstream_request_id(mut self, request_id: Option<String>) -> Self {
+        self.upstream_request_id = request_id;
+        self
+    }
+
     /// 创建认证错误响应
-    pub fn authentication_error() -> Self {
-        Self::new("authentication_error", "Invalid API key")
+    pub fn authentication_error(locale: Locale) -> Self {
+        Self::new(
+            "authentication_error",
+            ErrorCode::AuthenticationFailed,
+            locale,
+        )
     }
 }
 
@@ -127,10 +151,23 @@ pub struct MessagesRequest {
     pub tool_choice: Option<serde_json::Value>,
     pub thinking: Option<Thinking>,
     pub output_config: Option<OutputConfig>,
+    /// 采样温度（0.0 ~ 1.0）
+    pub temperature: Option<f64>,
+    /// 核采样概率阈值（0.0 ~ 1.0）
+    pub top_p: Option<f64>,
+    /// 只从概率最高的 K 个 token 中采样（>= 0；使用有符号类型以便对负数返回 400 而不是反序列化失败）
+    pub top_k: Option<i64>,
     /// Claude Code 请求中的 metadata，包含 session 信息
     pub metadata: Option<Metadata>,
 }
 
+impl MessagesRequest {
+    /// 是否携带了采样参数（temperature / top_p / top_k）
+    pub fn has_sampling_params(&self) -> bool {
+        self.temperature.is_some() || self.top_p.is_some() || self.top_k.is_some()
+    }
+}
+
 /// 反序列化 system 字段，支持字符串或数组格式
 fn deserialize_system<'de, D>(deserializer: D) -> Result<Option<Vec<SystemMessage>>, D::Error>
 where
@@ -152,6 +189,7 @@ where
         {
             Ok(Some(vec![SystemMessage {
                 text: value.to_string(),
+                cache_control: None,
             }]))
         }
 
@@ -196,10 +234,22 @@ pub struct Message {
     pub content: serde_json::Value,
 }
 
+impl Message {
+    /// 内容块上是否带有 Prompt Caching 缓存标记（cache_control）
+    pub fn has_cache_control(&self) -> bool {
+        self.content
+            .as_array()
+            .is_some_and(|blocks| blocks.iter().any(|b| b.get("cache_control").is_some()))
+    }
+}
+
 /// 系统消息
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SystemMessage {
     pub text: String,
+    /// Prompt Caching 缓存标记（不转发到上游，仅用于识别客户端请求的缓存）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<serde_json::Value>,
 }
 
 /// 工具定义
@@ -273,6 +323,8 @@ pub struct ImageSource {
 // === Count Tokens 端点类型 ===
 
 /// Token 计数请求
+///
+/// 采样参数（temperature / top_p / top_k）不影响计数，反序列化时直接忽略
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CountTokensRequest {
     pub model: String,
@@ -285,10 +337,97 @@ pub struct CountTokensRequest {
     pub system: Option<Vec<SystemMessage>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
+    /// 最大输出 tokens（仅用于 `detailed=true` 时估算输出上限）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<i32>,
+}
+
+/// Token 计数查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct CountTokensQuery {
+    /// 是否返回详细的成本估算信息
+    #[serde(default)]
+    pub detailed: bool,
 }
 
 /// Token 计数响应
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// `detailed=true` 时附带成本估算字段，默认只返回 `input_tokens`
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct CountTokensResponse {
     pub input_tokens: i32,
+    /// 模型上下文窗口大小
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<i32>,
+    /// 历史管理（截断/图片占位符等）后实际发送的输入 tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_input_tokens: Option<i32>,
+    /// 预计输出 tokens 上限（max_tokens 与剩余上下文中的较小值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_output_tokens: Option<i32>,
+    /// API Key 绑定池的状态
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool: Option<CountTokensPoolInfo>,
+    /// `input_tokens` 的来源（扩展字段）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_count_source: Option<TokenCountSource>,
+}
+
+/// Token 计数来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenCountSource {
+    /// 外部 count_tokens API
+    Remote,
+    /// 本地估算（官方 tokenizer / 启发式算法）
+    Estimated,
+}
+
+/// Token 计数响应中的池状态（仅本地缓存数据）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CountTokensPoolInfo {
+    /// 实际服务的池 ID
+    pub id: String,
+    /// 当前可用凭据数量
+    pub available_credentials: usize,
+    /// 剩余额度百分比（来自缓存的余额；尚未查询过余额时为 null）
+    pub remaining_quota_percentage: Option<f64>,
+}
+
+/// 批量消息请求体（`POST /v1/messages/batch`）
+#[derive(Debug, Deserialize)]
+pub struct MessageBatchRequest {
+    pub requests: Vec<MessageBatchItem>,
+}
+
+/// 批量消息中的单个请求
+#[derive(Debug, Deserialize)]
+pub struct MessageBatchItem {
+    /// 调用方指定的请求标识，原样返回
+    pub custom_id: String,
+    /// 消息请求参数（`stream` 会被忽略，统一按非流式处理）
+    pub params: MessagesRequest,
+}
+
+/// 批量消息响应（结果顺序与请求一致）
+#[derive(Debug, Serialize)]
+pub struct MessageBatchResponse {
+    pub results: Vec<MessageBatchResult>,
+}
+
+/// 批量消息中单个请求的结果
+#[derive(Debug, Serialize)]
+pub struct MessageBatchResult {
+    pub custom_id: String,
+    pub result: MessageBatchOutcome,
+}
+
+/// 单个请求的执行结果
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessageBatchOutcome {
+    /// 成功，`message` 为与 /v1/messages 相同的响应体
+    Succeeded { message: serde_json::Value },
+    /// 失败，`error` 为与 /v1/messages 相同的错误响应体
+    Errored { error: serde_json::Value },
 }
@@ -3,6 +3,7 @@
 //! 实现 Anthropic WebSearch 请求到 Kiro MCP 的转换和响应生成
 
 use std::convert::Infallible;
+use std::time::Duration;
 
 use axum::{
     body::Body,
@@ -11,10 +12,14 @@ use axum::{
 };
 use bytes::Bytes;
 use futures::{Stream, stream};
+use moka::sync::Cache;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use uuid::Uuid;
 
+use crate::common::i18n::{ErrorCode, Locale};
+
+use super::middleware::WebSearchRateLimiter;
 use super::stream::SseEvent;
 use super::types::{ErrorResponse, MessagesRequest};
 
@@ -75,7 +80,7 @@ pub struct McpContent {
 }
 
 /// WebSearch 搜索结果
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[allow(dead_code)]
 pub struct WebSearchResults {
     pub results: Vec<WebSearchResult>,
@@ -102,6 +107,51 @@ pub struct WebSearchResult {
     pub public_domain: Option<bool>,
 }
 
+/// WebSearch 结果缓存有效期（10 分钟）
+const WEBSEARCH_CACHE_TTL_SECS: u64 = 600;
+
+/// WebSearch 结果缓存最大条目数
+const WEBSEARCH_CACHE_MAX_CAPACITY: u64 = 1000;
+
+/// WebSearch 结果缓存
+///
+/// 按规范化后的查询（去除首尾空白、转小写）缓存成功的搜索结果，
+/// 有效期内的相同查询不再调用上游 MCP
+pub struct WebSearchCache {
+    cache: Cache<String, WebSearchResults>,
+}
+
+impl WebSearchCache {
+    pub fn new() -> Self {
+        Self {
+            cache: Cache::builder()
+                .max_capacity(WEBSEARCH_CACHE_MAX_CAPACITY)
+                .time_to_live(Duration::from_secs(WEBSEARCH_CACHE_TTL_SECS))
+                .build(),
+        }
+    }
+
+    fn key(query: &str) -> String {
+        query.trim().to_lowercase()
+    }
+
+    /// 获取缓存的搜索结果
+    pub fn get(&self, query: &str) -> Option<WebSearchResults> {
+        self.cache.get(&Self::key(query))
+    }
+
+    /// 缓存搜索结果
+    pub fn insert(&self, query: &str, results: WebSearchResults) {
+        self.cache.insert(Self::key(query), results);
+    }
+}
+
+impl Default for WebSearchCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// 检查请求是否为纯 WebSearch 请求
 ///
 /// 条件：tools 有且只有一个，且 name 为 web_search
@@ -444,10 +494,20 @@ fn generate_search_summary(query: &str, results: &Option<WebSearchResults>) -> S
 }
 
 /// 处理 WebSearch 请求
+///
+/// 在调用上游 MCP 之前先检查 WebSearch 独立限流预算，
+/// `limit_override` 为 API Key 上配置的每小时限额覆盖值；
+/// 传入 `cache` 时优先复用缓存的搜索结果
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_websearch_request(
     provider: std::sync::Arc<crate::kiro::provider::KiroProvider>,
     payload: &MessagesRequest,
     input_tokens: i32,
+    limiter: &WebSearchRateLimiter,
+    cache: Option<&WebSearchCache>,
+    api_key: Option<&str>,
+    limit_override: Option<u64>,
+    locale: Locale,
 ) -> Response {
     // 1. 提取搜索查询
     let query = match extract_search_query(payload) {
@@ -457,28 +517,54 @@ pub async fn handle_websearch_request(
                 StatusCode::BAD_REQUEST,
                 Json(ErrorResponse::new(
                     "invalid_request_error",
-                    "无法从消息中提取搜索查询",
+                    ErrorCode::WebSearchQueryMissing,
+                    locale,
                 )),
             )
                 .into_response();
         }
     };
 
+    // 2. 检查 WebSearch 限流（独立于普通消息限流）
+    if let Err(message) = limiter.check_and_record(api_key, limit_override) {
+        tracing::info!("WebSearch 限流触发: {}", message);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(json!({
+                "error": "websearch_rate_limit_exceeded",
+                "message": message,
+            })),
+        )
+            .into_response();
+    }
+
     tracing::info!(query = %query, "处理 WebSearch 请求");
 
-    // 2. 创建 MCP 请求
+    // 3. 创建 MCP 请求
     let (tool_use_id, mcp_request) = create_mcp_request(&query);
 
-    // 3. 调用 Kiro MCP API
-    let search_results = match call_mcp_api(&provider, &mcp_request).await {
-        Ok(response) => parse_search_results(&response),
-        Err(e) => {
-            tracing::warn!("MCP API 调用失败: {}", e);
-            None
+    // 4. 调用 Kiro MCP API（命中缓存时跳过）
+    let search_results = match cache.and_then(|c| c.get(&query)) {
+        Some(cached) => {
+            tracing::info!(query = %query, "WebSearch 命中缓存");
+            Some(cached)
+        }
+        None => {
+            let results = match call_mcp_api(&provider, &mcp_request).await {
+                Ok(response) => parse_search_results(&response),
+                Err(e) => {
+                    tracing::warn!("MCP API 调用失败: {}", e);
+                    None
+                }
+            };
+            if let (Some(cache), Some(results)) = (cache, &results) {
+                cache.insert(&query, results.clone());
+            }
+            results
         }
     };
 
-    // 4. 生成 SSE 响应
+    // 5. 生成 SSE 响应
     let model = payload.model.clone();
     let stream =
         create_websearch_sse_stream(model, query, tool_use_id, search_results, input_tokens);
@@ -546,6 +632,9 @@ mod tests {
             tool_choice: None,
             thinking: None,
             output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
             metadata: None,
         };
 
@@ -584,6 +673,9 @@ mod tests {
             tool_choice: None,
             thinking: None,
             output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
             metadata: None,
         };
 
@@ -611,6 +703,9 @@ mod tests {
             tool_choice: None,
             thinking: None,
             output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
             metadata: None,
         };
 
@@ -636,6 +731,9 @@ mod tests {
             tool_choice: None,
             thinking: None,
             output_config: None,
+            temperature: None,
+            top_p: None,
+            top_k: None,
             metadata: None,
         };
 
@@ -707,6 +805,25 @@ mod tests {
         assert_eq!(results.results[0].title, "Test");
     }
 
+    #[test]
+    fn test_websearch_cache_normalizes_query() {
+        let cache = WebSearchCache::new();
+        assert!(cache.get("rust async").is_none());
+
+        cache.insert(
+            " Rust Async ",
+            WebSearchResults {
+                results: vec![],
+                total_results: Some(0),
+                query: Some("Rust Async".to_string()),
+                error: None,
+            },
+        );
+        let cached = cache.get("rust async").unwrap();
+        assert_eq!(cached.total_results, Some(0));
+        assert!(cache.get("rust").is_none());
+    }
+
     #[test]
     fn test_generate_search_summary() {
         let results = WebSearchResults {
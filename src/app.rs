@@ -0,0 +1,715 @@
+//! 应用组装
+//!
+//! [`AppBuilder`] 按配置创建 Token 管理器、池管理器、Anthropic API 路由和 Admin 路由，
+//! 返回组合好的 axum 路由以及运行时控制句柄。既可以嵌入到已有的 axum 应用中，
+//! 也可以通过 [`App::serve`] 作为独立服务运行（与 `kiro-rs` 可执行文件行为一致）。
+//!
+//! ```no_run
+//! use std::net::SocketAddr;
+//!
+//! use kiro_rs::AppBuilder;
+//! use kiro_rs::model::config::Config;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let app = AppBuilder::new(Config::default())
+//!     .with_credentials_path("config/credentials.json")
+//!     .build()
+//!     .await?;
+//!
+//! // 运行时控制：查看/调整凭据
+//! let token_manager = app.token_manager.clone();
+//! tracing::info!("可用凭据: {}", token_manager.available_count());
+//!
+//! // 挂载到宿主应用的 /llm 下（客户端请求 /llm/v1/messages）
+//! let host = axum::Router::new()
+//!     .route("/", axum::routing::get(|| async { "host" }))
+//!     .nest("/llm", app.router);
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
+//! // 附带连接信息，限流豁免按客户端 IP 匹配
+//! axum::serve(
+//!     listener,
+//!     host.into_make_service_with_connect_info::<SocketAddr>(),
+//! )
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::Router;
+
+use crate::admin;
+use crate::admin::backup::{self, BackupKind, BackupManager};
+use crate::admin::magic_link;
+use crate::admin_ui;
+use crate::anthropic;
+use crate::common::features::FeatureFlags;
+use crate::common::i18n;
+use crate::health;
+use crate::http_client::{self, ProxyConfig};
+use crate::kiro::model::credentials::{CredentialsConfig, KiroCredentials};
+use crate::kiro::pool;
+use crate::kiro::pool_manager::{self, PoolManager};
+use crate::kiro::provider::KiroProvider;
+use crate::kiro::token_manager::MultiTokenManager;
+use crate::model::config::{Config, RateLimiterType};
+use crate::server::{DRAIN_TIMEOUT, ServerSupervisor};
+use crate::token;
+
+/// 凭据来源
+#[derive(Debug, Clone)]
+pub enum CredentialsSource {
+    /// 从文件加载（文件不存在或解析失败时以空凭据启动），变更回写到该文件
+    Path(PathBuf),
+    /// 内存中的凭据（不持久化，池管理不可用）
+    InMemory(Vec<KiroCredentials>),
+}
+
+/// 应用构建器
+pub struct AppBuilder {
+    config: Config,
+    config_path: PathBuf,
+    credentials: CredentialsSource,
+    pools_enabled: bool,
+    admin_enabled: bool,
+    proxy: Option<Option<ProxyConfig>>,
+    background_tasks: bool,
+}
+
+impl AppBuilder {
+    /// 使用指定配置创建构建器
+    ///
+    /// 默认从 `config/credentials.json` 加载凭据，启用池管理和 Admin（配置了 `adminApiKey` 时），
+    /// 代理取自配置，并启动健康检查和额度重置后台任务
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            config_path: PathBuf::from(Config::default_config_path()),
+            credentials: CredentialsSource::Path(PathBuf::from(
+                KiroCredentials::default_credentials_path(),
+            )),
+            pools_enabled: true,
+            admin_enabled: true,
+            proxy: None,
+            background_tasks: true,
+        }
+    }
+
+    /// 从配置文件加载并校验配置（文件不存在时使用默认配置）
+    pub fn from_config_path(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let config = Config::load(&path).context("加载配置失败")?;
+        match config.validate() {
+            Ok(warnings) => {
+                for warning in warnings {
+                    tracing::warn!("配置警告: {}", warning);
+                }
+            }
+            Err(errors) => anyhow::bail!("配置验证失败: {}", errors.join("; ")),
+        }
+        Ok(Self::new(config).with_config_path(path))
+    }
+
+    /// 设置配置文件路径
+    ///
+    /// Admin 修改配置时写回该文件；`api_keys.json`、`pools.json`、`features.json`
+    /// 位于同一目录
+    pub fn with_config_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = path.into();
+        self
+    }
+
+    /// 从文件加载凭据
+    pub fn with_credentials_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.credentials = CredentialsSource::Path(path.into());
+        self
+    }
+
+    /// 使用内存中的凭据（不持久化，池管理不可用）
+    pub fn with_credentials(mut self, credentials: Vec<KiroCredentials>) -> Self {
+        self.credentials = CredentialsSource::InMemory(credentials);
+        self
+    }
+
+    /// 是否启用池管理（需要从文件加载凭据，默认启用）
+    pub fn with_pools(mut self, enabled: bool) -> Self {
+        self.pools_enabled = enabled;
+        self
+    }
+
+    /// 是否挂载 Admin API 和 Admin UI（还需要配置非空的 `adminApiKey`，默认启用）
+    pub fn with_admin(mut self, enabled: bool) -> Self {
+        self.admin_enabled = enabled;
+        self
+    }
+
+    /// 覆盖代理配置（None 表示不使用代理，不调用时取自配置）
+    pub fn with_proxy(mut self, proxy: Option<ProxyConfig>) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// 是否启动健康检查和额度重置后台任务（默认启动）
+    pub fn with_background_tasks(mut self, enabled: bool) -> Self {
+        self.background_tasks = enabled;
+        self
+    }
+
+    /// 组装应用
+    ///
+    /// 按配置预热凭据并启动后台任务，需要在 Tokio 运行时中调用
+    pub async fn build(self) -> anyhow::Result<App> {
+        let Self {
+            config,
+            config_path,
+            credentials,
+            pools_enabled,
+            admin_enabled,
+            proxy,
+            background_tasks,
+        } = self;
+
+        let config_dir = config_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+
+        let (mut credentials_list, credentials_path) = match credentials {
+            CredentialsSource::Path(path) => {
+                let list = match CredentialsConfig::load(&path) {
+                    Ok(credentials_config) => credentials_config.into_sorted_credentials(),
+                    Err(e) => {
+                        // 凭证文件不存在或解析失败，使用空列表（可以后续通过前端添加）
+                        tracing::warn!("加载凭证失败: {}，将以空凭证启动", e);
+                        tracing::warn!("可以通过 Admin UI 添加凭证");
+                        Vec::new()
+                    }
+                };
+                (list, Some(path))
+            }
+            CredentialsSource::InMemory(list) => (list, None),
+        };
+
+        // 额外凭据目录中的凭据（运行时变更回写到各自的凭据文件）
+        if !config.credential_dirs.is_empty() {
+            let mut taken_ids = credentials_list.iter().filter_map(|c| c.id).collect();
+            let dir_credentials = CredentialsConfig::load_from_dirs(
+                &config.credential_dir_paths(&config_dir),
+                &mut taken_ids,
+            );
+            credentials_list.extend(dir_credentials);
+            credentials_list.sort_by_key(|c| c.priority);
+        }
+        tracing::info!("已加载 {} 个凭据配置", credentials_list.len());
+
+        // 第一个凭据的 Profile ARN 用于默认请求
+        let profile_arn = credentials_list.first().and_then(|c| c.profile_arn.clone());
+
+        let proxy_config = proxy.unwrap_or_else(|| proxy_from_config(&config));
+        if let Some(proxy) = &proxy_config {
+            tracing::info!("已配置 HTTP 代理: {}", proxy.url);
+        }
+        http_client::log_tls_config(config.tls_options());
+
+        let token_manager = Arc::new(
+            MultiTokenManager::new(
+                config.clone(),
+                credentials_list,
+                proxy_config.clone(),
+                credentials_path.clone(),
+            )
+            .context("创建 Token 管理器失败")?,
+        );
+
+        // 初始化 count_tokens 配置
+        token::init_config(token::CountTokensConfig {
+            api_url: config.count_tokens_api_url.clone(),
+            api_key: config.count_tokens_api_key.clone(),
+            auth_type: config.count_tokens_auth_type.clone(),
+            proxy: proxy_config.clone(),
+            tls: config.tls_options(),
+        });
+
+        // 错误消息默认语言（客户端未携带 Accept-Language 时使用）
+        i18n::set_default_locale(config.default_locale);
+
+        // 创建 API Key 管理器（必需，用于 API 认证）
+        let api_key_manager = Arc::new(
+            admin::ApiKeyManager::new(config_dir.join("api_keys.json"))
+                .map(|manager| manager.with_max_keys(config.max_api_keys))
+                .context("创建 API Key 管理器失败")?,
+        );
+
+        // 创建池管理器（可选，初始化失败时池管理功能不可用）
+        let pool_manager = match &credentials_path {
+            Some(credentials_path) if pools_enabled => match PoolManager::new(
+                config.clone(),
+                proxy_config.clone(),
+                config_dir.join("pools.json"),
+                credentials_path,
+            ) {
+                Ok(pm) => {
+                    tracing::info!("池管理器已初始化，共 {} 个池", pm.pool_count());
+                    Some(Arc::new(pm))
+                }
+                Err(e) => {
+                    tracing::warn!("池管理器初始化失败: {}，池管理功能不可用", e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        // 凭据预热：开始服务前刷新过期或即将过期的 Token，输出预热报告
+        // 启用池管理时由各池预热（默认池与全局管理器共享同一批凭据，避免重复刷新）
+        if config.warmup_on_startup {
+            match pool_manager {
+                Some(ref pm) => {
+                    for (pool_id, report) in pm.warm_up().await {
+                        tracing::info!(
+                            "池 {} 凭据预热完成: 刷新 {}，跳过 {}，失败 {}，耗时 {} ms",
+                            pool_id,
+                            report.refreshed(),
+                            report.skipped(),
+                            report.failed(),
+                            report.elapsed_ms
+                        );
+                    }
+                }
+                None => {
+                    let warmup_report = token_manager.warm_up().await;
+                    tracing::info!(
+                        "凭据预热完成，耗时 {} ms\n{}",
+                        warmup_report.elapsed_ms,
+                        warmup_report
+                    );
+                }
+            }
+        } else {
+            tracing::info!("启动预热已关闭（warmupOnStartup = false）");
+        }
+
+        // 构建 Anthropic API 路由
+        let kiro_provider = KiroProvider::with_proxy(token_manager.clone(), proxy_config);
+        let websearch_limiter = Arc::new(anthropic::WebSearchRateLimiter::new(
+            config.websearch_rate_limit_per_hour,
+        ));
+        // 滑动窗口限流器（Anthropic API 与 Admin 共享，Admin 查看/重置限流统计）
+        let rate_limiter = anthropic::RateLimiter::from_config(&config).map(Arc::new);
+        // 令牌桶限流器（Anthropic API 与 Admin 共享，Admin 查看放行/豁免请求数）
+        let token_bucket = anthropic::TokenBucketLimiter::from_config(&config).map(Arc::new);
+        // 限流豁免规则（Anthropic API 与 Admin 共享，Admin 增删后立即生效）
+        let rate_limit_exemptions = Arc::new(anthropic::RateLimitExemptions::new(
+            config.rate_limit_exemptions.clone(),
+        ));
+        // 功能开关（Anthropic API 与 Admin 共享，运行时切换写入 features.json）
+        let features = Arc::new(FeatureFlags::load(
+            FeatureFlags::default_path(&config_dir),
+            &config.feature_flags,
+        ));
+        // SSE 断线续传（停止服务时等待后台读取的流结束）
+        let sse_replay = (config.sse_replay_buffer_size > 0).then(|| {
+            Arc::new(anthropic::SseReplayRegistry::new(
+                config.sse_replay_buffer_size,
+            ))
+        });
+        let anthropic_app = anthropic::create_router(
+            api_key_manager.clone(),
+            Some(kiro_provider),
+            profile_arn,
+            pool_manager.clone(),
+            Some(token_manager.clone()),
+            Arc::new(config.clone()),
+            websearch_limiter.clone(),
+            rate_limiter.clone(),
+            token_bucket.clone(),
+            rate_limit_exemptions.clone(),
+            features.clone(),
+            sse_replay.clone(),
+        );
+
+        // 配置文件自动备份（配置 backupDir 时启用）
+        let backup_manager = config.backup_dir.as_deref().map(|dir| {
+            let mut manager = BackupManager::new(config_dir.join(dir), config.backup_retention)
+                .with_source(BackupKind::Config, &config_path)
+                .with_source(BackupKind::Pools, config_dir.join("pools.json"))
+                .with_source(BackupKind::ApiKeys, config_dir.join("api_keys.json"));
+            if let Some(path) = &credentials_path {
+                manager = manager.with_source(BackupKind::Credentials, path);
+            }
+            Arc::new(manager)
+        });
+
+        if background_tasks {
+            // 启动配置备份后台任务
+            if let Some(manager) = &backup_manager {
+                tracing::info!(
+                    "启动配置备份任务，间隔 {} 小时，保留 {} 份",
+                    config.backup_interval_hours,
+                    config.backup_retention
+                );
+                backup::start_backup_task(manager.clone(), config.backup_interval_hours);
+            }
+
+            // 启动健康检查后台任务
+            if config.health_check_interval_secs > 0 {
+                tracing::info!(
+                    "启动健康检查任务，间隔 {} 秒",
+                    config.health_check_interval_secs
+                );
+                health::start_health_check_task(
+                    token_manager.clone(),
+                    config.health_check_interval_secs,
+                );
+            }
+
+            // 启动额度重置后台任务（额度用尽的凭据到达重置时间后自动重新启用）
+            health::start_quota_reset_task(token_manager.clone(), pool_manager.clone());
+
+            // 启动池优先级自动调整后台任务
+            if config.auto_adjust_pool_priority
+                && let Some(ref pm) = pool_manager
+            {
+                tracing::info!(
+                    "启动池优先级自动调整任务，间隔 {} 秒，优先级范围 [{}, {}]",
+                    config.pool_priority_check_interval_secs,
+                    config.auto_priority_min,
+                    config.auto_priority_max
+                );
+                pool_manager::start_auto_priority_task(
+                    pm.clone(),
+                    config.pool_priority_check_interval_secs,
+                );
+            }
+        }
+
+        // 服务监督任务（Admin 修改 host/port 时切换监听地址，仅 `App::serve` 时生效）
+        let (supervisor, server_handle) = ServerSupervisor::new();
+
+        // 构建 Admin API 路由（需要配置非空的 admin_api_key）
+        // 安全检查：空字符串被视为未配置，防止空 key 绕过认证
+        let admin_key = config
+            .admin_api_key
+            .clone()
+            .filter(|k| !k.trim().is_empty());
+        if config.admin_api_key.is_some() && admin_key.is_none() {
+            tracing::warn!("admin_api_key 配置为空，Admin API 未启用");
+        }
+        let admin_key = admin_key.filter(|_| admin_enabled);
+
+        let mut admin_listener = None;
+        let router = match &admin_key {
+            Some(admin_key) => {
+                let mut admin_service = admin::AdminService::new(token_manager.clone());
+                if let Some(ref pm) = pool_manager {
+                    admin_service = admin_service.with_pool_manager(pm.clone());
+                }
+
+                let mut admin_state = admin::AdminState::new(
+                    admin_key,
+                    admin_service,
+                    config.clone(),
+                    &config_path,
+                    api_key_manager,
+                );
+                if let Some(ref pm) = pool_manager {
+                    admin_state = admin_state.with_pool_manager(pm.clone());
+                }
+                if let Some(ref limiter) = rate_limiter {
+                    admin_state = admin_state.with_rate_limiter(limiter.clone());
+                }
+                if let Some(ref bucket) = token_bucket {
+                    admin_state = admin_state.with_token_bucket(bucket.clone());
+                }
+                admin_state = admin_state
+                    .with_websearch_limiter(websearch_limiter)
+                    .with_rate_limit_exemptions(rate_limit_exemptions)
+                    .with_features(features)
+                    .with_server(server_handle);
+                if let Some(ref manager) = backup_manager {
+                    admin_state = admin_state.with_backup_manager(manager.clone());
+                }
+                if let Some(ref path) = credentials_path {
+                    admin_state = admin_state.with_credentials_path(path);
+                }
+
+                // Admin 实时事件：Token 管理器发布，Admin UI 通过 SSE 订阅
+                let admin_events = admin::events::channel();
+                token_manager.set_event_sender(admin_events.clone(), pool::DEFAULT_POOL_ID);
+                if let Some(ref pm) = pool_manager {
+                    pm.set_event_sender(admin_events.clone());
+                }
+                admin_state = admin_state.with_event_sender(admin_events);
+
+                let admin_app = Router::new()
+                    .nest(
+                        magic_link::ADMIN_API_PATH,
+                        admin::create_admin_router(admin_state.clone()),
+                    )
+                    .nest(
+                        magic_link::ADMIN_UI_PATH,
+                        admin_ui::create_admin_ui_router(admin_state),
+                    );
+
+                tracing::info!("Admin API 已启用");
+                tracing::info!("Admin UI 已启用: /admin");
+                tracing::info!("多 API Key 支持已启用（api_keys.json）");
+                if pool_manager.is_some() {
+                    tracing::info!("API Key 绑定池路由已启用");
+                }
+
+                // 启用 mTLS 时 Admin 路由只在独立的 TLS 端口上提供服务
+                match admin::mtls::server_config(&config, &config_dir)
+                    .context("加载 Admin mTLS 证书失败")?
+                {
+                    Some(tls) => {
+                        admin_listener = Some(AdminListener {
+                            router: admin_app,
+                            tls,
+                        });
+                        anthropic_app
+                    }
+                    None => anthropic_app.merge(admin_app),
+                }
+            }
+            None => anthropic_app,
+        };
+
+        Ok(App {
+            router,
+            token_manager,
+            pool_manager,
+            backup_manager,
+            config,
+            admin_enabled: admin_key.is_some(),
+            admin_listener,
+            supervisor,
+            sse_replay,
+        })
+    }
+}
+
+/// 启用 mTLS 时独立监听的 Admin 服务
+struct AdminListener {
+    /// Admin API 和 Admin UI 路由
+    router: Router,
+    /// TLS 配置（含客户端证书校验）
+    tls: Arc<rustls::ServerConfig>,
+}
+
+/// 组装好的应用
+pub struct App {
+    /// Anthropic API 路由（启用 Admin 时包含 `/api/admin` 和 `/admin`；
+    /// 启用 Admin mTLS 时 Admin 路由只由 [`App::serve`] 在 `adminPort` 上提供）
+    pub router: Router,
+    /// 默认池的 Token 管理器（运行时管理凭据）
+    pub token_manager: Arc<MultiTokenManager>,
+    /// 池管理器（未启用或初始化失败时为 None）
+    pub pool_manager: Option<Arc<PoolManager>>,
+    /// 备份管理器（未配置 backupDir 时为 None）
+    pub backup_manager: Option<Arc<BackupManager>>,
+    config: Config,
+    admin_enabled: bool,
+    admin_listener: Option<AdminListener>,
+    supervisor: ServerSupervisor,
+    sse_replay: Option<Arc<anthropic::SseReplayRegistry>>,
+}
+
+impl App {
+    /// 生效的配置
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// 在配置的 `host:port` 上启动独立服务
+    pub async fn serve_default(self) -> std::io::Result<()> {
+        let addr = format!("{}:{}", self.config.host, self.config.port);
+        self.serve(&addr).await
+    }
+
+    /// 绑定地址并运行服务（Admin 修改 host/port 时切换监听地址）
+    ///
+    /// 启用 Admin mTLS 时同时在 `host:adminPort` 上运行 Admin TLS 服务。
+    /// 收到 Ctrl+C / SIGTERM 后停止接受新连接，等待已有连接和 SSE 断线续传的
+    /// 后台流结束（各最长 [`DRAIN_TIMEOUT`]）后返回
+    pub async fn serve(self, addr: &str) -> std::io::Result<()> {
+        self.log_startup(addr);
+        let sse_replay = self.sse_replay.clone();
+        let result = self.serve_until_signal(addr).await;
+
+        // 客户端断开后仍在后台读取的流中记录用量和凭据状态，等待其读取完毕
+        if let Some(replay) = sse_replay
+            && replay.active_streams() > 0
+        {
+            tracing::info!("等待 {} 个 SSE 后台流结束", replay.active_streams());
+            if tokio::time::timeout(DRAIN_TIMEOUT, replay.drain())
+                .await
+                .is_err()
+            {
+                tracing::warn!(
+                    "SSE 后台流在 {} 秒内未全部结束，不再等待",
+                    DRAIN_TIMEOUT.as_secs()
+                );
+            }
+        }
+        result
+    }
+
+    async fn serve_until_signal(self, addr: &str) -> std::io::Result<()> {
+        let Some(admin) = self.admin_listener else {
+            return self
+                .supervisor
+                .run_until(addr, self.router, shutdown_signal())
+                .await;
+        };
+        let admin_addr = format!(
+            "{}:{}",
+            self.config.host,
+            self.config.admin_port.unwrap_or_default()
+        );
+        let admin_tcp = tokio::net::TcpListener::bind(&admin_addr).await?;
+        tracing::info!(
+            "Admin mTLS 已启用: https://{}（客户端证书{}）",
+            admin_addr,
+            if self.config.admin_mtls_required { "必需" } else { "可选" }
+        );
+        // 主服务排空后 Admin TLS 服务随之停止
+        tokio::select! {
+            result = self.supervisor.run_until(addr, self.router, shutdown_signal()) => result,
+            result = admin::mtls::serve(admin_tcp, admin.tls, admin.router) => result,
+        }
+    }
+
+    /// 输出启动信息
+    fn log_startup(&self, addr: &str) {
+        let config = &self.config;
+        tracing::info!("启动服务: {}", addr);
+        tracing::info!("API Key 认证已启用（api_keys.json）");
+        if config.api_key.is_some() {
+            tracing::info!("config.json apiKey 认证已启用（默认池）");
+        }
+        tracing::info!("可用 API:");
+        tracing::info!("  GET  /health");
+        tracing::info!("  GET  /v1/models");
+        tracing::info!("  POST /v1/messages");
+        tracing::info!("  POST /v1/messages/count_tokens");
+
+        if config.rate_limit_enabled {
+            match config.rate_limiter_type {
+                RateLimiterType::SlidingWindow => {
+                    tracing::info!("限流已启用:");
+                    tracing::info!(
+                        "  全局: {}/分钟, {}/小时",
+                        config.rate_limit_per_minute,
+                        config.rate_limit_per_hour
+                    );
+                    tracing::info!(
+                        "  每 API Key: {}/分钟, {}/小时",
+                        config.rate_limit_per_key_per_minute,
+                        config.rate_limit_per_key_per_hour
+                    );
+                }
+                RateLimiterType::TokenBucket => {
+                    tracing::info!(
+                        "令牌桶限流已启用: 容量 {}, 每秒补充 {}",
+                        config.token_bucket_capacity,
+                        config.token_bucket_refill_per_second
+                    );
+                }
+            }
+        }
+        if config.quota_queue_enabled {
+            tracing::info!(
+                "额度用尽排队已启用: 最长等待 {} 秒, 最多 {} 个请求",
+                config.queue_max_wait_secs,
+                config.quota_queue_max_size
+            );
+        }
+        if config.dedup_enabled {
+            tracing::info!("请求去重已启用: 最长等待 {} 秒", config.dedup_max_wait_secs);
+        }
+        if config.max_concurrent_upstream_requests > 0 {
+            tracing::info!(
+                "上游并发限制已启用: 最多 {} 个并发请求, 排队最长 {} 毫秒",
+                config.max_concurrent_upstream_requests,
+                config.upstream_queue_timeout_ms
+            );
+        }
+        if config.sse_replay_buffer_size > 0 {
+            tracing::info!(
+                "SSE 断线续传已启用: 每个流保留最近 {} 个事件",
+                config.sse_replay_buffer_size
+            );
+        }
+        if config.websearch_rate_limit_per_hour > 0 {
+            tracing::info!(
+                "WebSearch 限流: 每 API Key {}/小时",
+                config.websearch_rate_limit_per_hour
+            );
+        }
+
+        if self.admin_enabled {
+            tracing::info!("Admin API:");
+            tracing::info!("  GET  /api/admin/credentials");
+            tracing::info!("  POST /api/admin/credentials/:id/disabled");
+            tracing::info!("  POST /api/admin/credentials/:id/priority");
+            tracing::info!("  POST /api/admin/credentials/:id/reset");
+            tracing::info!("  GET  /api/admin/credentials/:id/balance");
+            tracing::info!("  POST /api/admin/credentials/:id/pool");
+            tracing::info!("  GET  /api/admin/pools");
+            tracing::info!("  POST /api/admin/pools");
+            tracing::info!("  GET  /api/admin/pools/:id");
+            tracing::info!("  PUT  /api/admin/pools/:id");
+            tracing::info!("  DELETE /api/admin/pools/:id");
+            tracing::info!("  POST /api/admin/pools/:id/disabled");
+            tracing::info!("  GET  /api/admin/stats");
+            tracing::info!("Admin UI:");
+            tracing::info!("  GET  /admin");
+            tracing::info!("  GET  /admin/api/live-status");
+            tracing::info!("  GET  /admin/api/init");
+            tracing::info!("  GET  /admin/api/preferences");
+            tracing::info!("  POST /admin/api/preferences");
+        }
+    }
+}
+
+/// 等待停止信号（Ctrl+C，Unix 下还包括 SIGTERM）
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("监听 Ctrl+C 失败: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("监听 SIGTERM 失败: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("收到停止信号，开始关闭服务");
+}
+
+/// 从配置构建代理（配置了 proxyUrl 时）
+fn proxy_from_config(config: &Config) -> Option<ProxyConfig> {
+    config.proxy_url.as_ref().map(|url| {
+        let mut proxy = ProxyConfig::new(url);
+        if let (Some(username), Some(password)) = (&config.proxy_username, &config.proxy_password) {
+            proxy = proxy.with_auth(username, password);
+        }
+        proxy
+    })
+}
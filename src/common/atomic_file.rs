@@ -0,0 +1,197 @@
+//! 多文件原子写入
+//!
+//! 先将所有新内容写入同目录下的临时文件，再逐个 rename 覆盖目标文件；
+//! 任一 rename 失败时，将已覆盖的文件恢复为原内容，保证多个文件同时更新或同时不变。
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+
+/// 单个待提交的写入
+struct StagedWrite {
+    /// 目标文件
+    path: PathBuf,
+    /// 已写入新内容的临时文件
+    temp_path: PathBuf,
+    /// 原内容（目标文件不存在时为 None）
+    original: Option<Vec<u8>>,
+}
+
+/// 多文件写入事务
+///
+/// `stage` 阶段失败时不会修改任何目标文件；`commit` 阶段失败时回滚已覆盖的文件，
+/// 回滚也失败时返回的错误中会列出未能恢复的文件
+#[derive(Default)]
+pub struct FileTransaction {
+    staged: Vec<StagedWrite>,
+}
+
+impl FileTransaction {
+    /// 创建空事务
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 暂存写入：读取原内容并将新内容写入临时文件
+    pub fn stage(
+        &mut self,
+        path: impl AsRef<Path>,
+        content: impl AsRef<[u8]>,
+    ) -> anyhow::Result<()> {
+        let path = path.as_ref().to_path_buf();
+
+        let original = match fs::read(&path) {
+            Ok(bytes) => Some(bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| format!("读取文件失败: {:?}", path)),
+        };
+
+        let temp_path = temp_path_for(&path);
+        fs::write(&temp_path, content)
+            .with_context(|| format!("写入临时文件失败: {:?}", temp_path))?;
+
+        self.staged.push(StagedWrite {
+            path,
+            temp_path,
+            original,
+        });
+        Ok(())
+    }
+
+    /// 提交事务：按暂存顺序覆盖目标文件，失败时回滚
+    pub fn commit(mut self) -> anyhow::Result<()> {
+        let staged = std::mem::take(&mut self.staged);
+
+        let mut committed: Vec<&StagedWrite> = Vec::with_capacity(staged.len());
+        let mut failure = None;
+        for write in &staged {
+            match fs::rename(&write.temp_path, &write.path) {
+                Ok(()) => committed.push(write),
+                Err(e) => {
+                    failure = Some(anyhow!(e).context(format!("覆盖文件失败: {:?}", write.path)));
+                    break;
+                }
+            }
+        }
+
+        let Some(error) = failure else {
+            return Ok(());
+        };
+
+        // 清理未提交的临时文件
+        for write in &staged[committed.len()..] {
+            let _ = fs::remove_file(&write.temp_path);
+        }
+
+        // 逆序恢复已覆盖的文件
+        let mut unrecovered = Vec::new();
+        for write in committed.iter().rev() {
+            if let Err(e) = restore(write) {
+                tracing::error!("回滚文件失败: {:?}: {}", write.path, e);
+                unrecovered.push(format!("{:?}", write.path));
+            }
+        }
+
+        if unrecovered.is_empty() {
+            Err(error.context("写入失败，已回滚所有文件"))
+        } else {
+            Err(error.context(format!(
+                "写入失败且回滚未完成，以下文件可能不一致: {}",
+                unrecovered.join(", ")
+            )))
+        }
+    }
+}
+
+impl Drop for FileTransaction {
+    fn drop(&mut self) {
+        // 未提交的事务：删除临时文件
+        for write in &self.staged {
+            let _ = fs::remove_file(&write.temp_path);
+        }
+    }
+}
+
+/// 将目标文件恢复为原内容（原本不存在则删除）
+fn restore(write: &StagedWrite) -> io::Result<()> {
+    match &write.original {
+        Some(original) => {
+            fs::write(&write.temp_path, original)?;
+            fs::rename(&write.temp_path, &write.path)
+        }
+        None => fs::remove_file(&write.path),
+    }
+}
+
+/// 临时文件路径（与目标文件同目录，保证 rename 不跨文件系统）
+fn temp_path_for(path: &Path) -> PathBuf {
+    super::io::temp_path(path, ".tmp")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_commit_writes_all_files() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.json");
+        let b = dir.path().join("b.json");
+        fs::write(&a, "old-a").unwrap();
+
+        let mut tx = FileTransaction::new();
+        tx.stage(&a, "new-a").unwrap();
+        tx.stage(&b, "new-b").unwrap();
+        tx.commit().unwrap();
+
+        assert_eq!(fs::read_to_string(&a).unwrap(), "new-a");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "new-b");
+        assert!(!temp_path_for(&a).exists());
+        assert!(!temp_path_for(&b).exists());
+    }
+
+    #[test]
+    fn test_commit_failure_rolls_back() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.json");
+        let b = dir.path().join("b.json");
+        let c = dir.path().join("c.json");
+        fs::write(&a, "old-a").unwrap();
+        fs::write(&c, "old-c").unwrap();
+
+        let mut tx = FileTransaction::new();
+        tx.stage(&a, "new-a").unwrap();
+        tx.stage(&b, "new-b").unwrap();
+        tx.stage(&c, "new-c").unwrap();
+
+        // 提交前将 c 替换为非空目录，使其 rename 失败
+        fs::remove_file(&c).unwrap();
+        fs::create_dir(&c).unwrap();
+        fs::write(c.join("keep"), "").unwrap();
+
+        let err = tx.commit().unwrap_err();
+        assert!(format!("{:#}", err).contains("已回滚"));
+
+        assert_eq!(fs::read_to_string(&a).unwrap(), "old-a");
+        assert!(!b.exists());
+        assert!(!temp_path_for(&a).exists());
+        assert!(!temp_path_for(&c).exists());
+    }
+
+    #[test]
+    fn test_dropped_transaction_cleans_up() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.json");
+        fs::write(&a, "old-a").unwrap();
+
+        let mut tx = FileTransaction::new();
+        tx.stage(&a, "new-a").unwrap();
+        drop(tx);
+
+        assert_eq!(fs::read_to_string(&a).unwrap(), "old-a");
+        assert!(!temp_path_for(&a).exists());
+    }
+}
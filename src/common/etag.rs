@@ -0,0 +1,24 @@
+//! HTTP ETag 工具
+//!
+//! 用于 Admin UI 静态资源和 Admin API 响应的条件请求（If-None-Match → 304）
+
+use axum::http::{HeaderMap, header};
+use sha2::{Digest, Sha256};
+
+/// 由内容的 SHA-256 生成强 ETag
+pub fn content_etag(content: &[u8]) -> String {
+    format!("\"{}\"", hex::encode(Sha256::digest(content)))
+}
+
+/// 判断 If-None-Match 是否命中 ETag（按弱比较，忽略 `W/` 前缀）
+pub fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        })
+}
@@ -0,0 +1,207 @@
+//! 功能开关
+//!
+//! 用于逐步放开新功能。开关取值依次由内置默认值、config.json 的 `featureFlags`
+//! 和配置目录下的 features.json（Admin API 运行时切换后写入）决定，后者优先。
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use parking_lot::RwLock;
+
+/// 批量消息接口（`POST /v1/messages/batch`）
+pub const ENABLE_BATCH_MESSAGES: &str = "enable_batch_messages";
+/// WebSearch 结果缓存（相同查询在有效期内复用搜索结果）
+pub const ENABLE_WEBSEARCH_CACHE: &str = "enable_websearch_cache";
+/// 历史消息 AI 摘要（仍需 `historyEnableAiSummary`）
+pub const ENABLE_AI_SUMMARY: &str = "enable_ai_summary";
+/// 非流式请求去重（仍需 `dedupEnabled`）
+pub const ENABLE_TOKEN_DEDUP: &str = "enable_token_dedup";
+
+/// 已知开关：(名称, 默认值, 说明)
+pub const KNOWN_FLAGS: &[(&str, bool, &str)] = &[
+    (
+        ENABLE_BATCH_MESSAGES,
+        false,
+        "批量消息接口 POST /v1/messages/batch",
+    ),
+    (ENABLE_WEBSEARCH_CACHE, false, "WebSearch 结果缓存"),
+    (
+        ENABLE_AI_SUMMARY,
+        true,
+        "历史消息 AI 摘要（仍需 historyEnableAiSummary）",
+    ),
+    (
+        ENABLE_TOKEN_DEDUP,
+        true,
+        "非流式请求去重（仍需 dedupEnabled）",
+    ),
+];
+
+/// 是否为已知开关
+pub fn is_known(name: &str) -> bool {
+    KNOWN_FLAGS.iter().any(|(n, _, _)| *n == name)
+}
+
+/// 功能开关错误
+#[derive(Debug, thiserror::Error)]
+pub enum FeatureFlagError {
+    /// 未知开关
+    #[error("未知功能开关: {0}")]
+    Unknown(String),
+    /// 写入 features.json 失败
+    #[error("保存功能开关失败: {0}")]
+    Save(#[from] anyhow::Error),
+}
+
+/// 功能开关集合
+pub struct FeatureFlags {
+    flags: RwLock<BTreeMap<String, bool>>,
+    /// 运行时切换的持久化文件（未设置时只在内存中生效）
+    file_path: Option<PathBuf>,
+}
+
+impl FeatureFlags {
+    /// 按内置默认值和配置覆盖值创建（不持久化）
+    pub fn new(overrides: &HashMap<String, bool>) -> Self {
+        let mut flags: BTreeMap<String, bool> = KNOWN_FLAGS
+            .iter()
+            .map(|(name, default, _)| (name.to_string(), *default))
+            .collect();
+        for (name, enabled) in overrides {
+            if let Some(value) = flags.get_mut(name) {
+                *value = *enabled;
+            }
+        }
+        Self {
+            flags: RwLock::new(flags),
+            file_path: None,
+        }
+    }
+
+    /// 获取配置目录下的默认文件路径
+    pub fn default_path(config_dir: &Path) -> PathBuf {
+        config_dir.join("features.json")
+    }
+
+    /// 创建并加载 features.json 中的运行时覆盖值
+    ///
+    /// 文件不存在或解析失败时只使用默认值和配置覆盖值（不会创建文件）
+    pub fn load<P: AsRef<Path>>(file_path: P, overrides: &HashMap<String, bool>) -> Self {
+        let file_path = file_path.as_ref().to_path_buf();
+        let mut features = Self::new(overrides);
+
+        match Self::load_from_file(&file_path) {
+            Ok(saved) => {
+                let mut flags = features.flags.write();
+                for (name, enabled) in saved {
+                    match flags.get_mut(&name) {
+                        Some(value) => *value = enabled,
+                        None => tracing::warn!("features.json 包含未知功能开关 {}，已忽略", name),
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("加载功能开关失败: {}，使用配置值", e),
+        }
+
+        features.file_path = Some(file_path);
+        features
+    }
+
+    fn load_from_file(path: &Path) -> anyhow::Result<HashMap<String, bool>> {
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let content = fs::read_to_string(path)?;
+        if content.trim().is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// 开关是否启用（未知开关视为关闭）
+    pub fn is_enabled(&self, flag: &str) -> bool {
+        self.flags.read().get(flag).copied().unwrap_or(false)
+    }
+
+    /// 当前所有开关的取值（按名称排序）
+    pub fn snapshot(&self) -> BTreeMap<String, bool> {
+        self.flags.read().clone()
+    }
+
+    /// 运行时切换开关（写入 features.json 后生效）
+    pub fn set(&self, flag: &str, enabled: bool) -> Result<(), FeatureFlagError> {
+        let mut flags = self.flags.write();
+        let previous = *flags
+            .get(flag)
+            .ok_or_else(|| FeatureFlagError::Unknown(flag.to_string()))?;
+
+        if let Some(path) = &self.file_path {
+            let mut updated = flags.clone();
+            updated.insert(flag.to_string(), enabled);
+            let content = serde_json::to_string_pretty(&updated).map_err(anyhow::Error::from)?;
+            fs::write(path, content).map_err(anyhow::Error::from)?;
+        }
+        flags.insert(flag.to_string(), enabled);
+
+        tracing::info!("功能开关 {} 已切换: {} -> {}", flag, previous, enabled);
+        Ok(())
+    }
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        Self::new(&HashMap::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_and_config_overrides() {
+        let features = FeatureFlags::default();
+        assert!(!features.is_enabled(ENABLE_BATCH_MESSAGES));
+        assert!(features.is_enabled(ENABLE_TOKEN_DEDUP));
+        assert!(!features.is_enabled("unknown_flag"));
+
+        let overrides = HashMap::from([
+            (ENABLE_BATCH_MESSAGES.to_string(), true),
+            (ENABLE_TOKEN_DEDUP.to_string(), false),
+            ("unknown_flag".to_string(), true),
+        ]);
+        let features = FeatureFlags::new(&overrides);
+        assert!(features.is_enabled(ENABLE_BATCH_MESSAGES));
+        assert!(!features.is_enabled(ENABLE_TOKEN_DEDUP));
+        assert!(!features.snapshot().contains_key("unknown_flag"));
+    }
+
+    #[test]
+    fn test_set_persists_and_overrides_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = FeatureFlags::default_path(dir.path());
+        let overrides = HashMap::from([(ENABLE_BATCH_MESSAGES.to_string(), true)]);
+
+        let features = FeatureFlags::load(&path, &overrides);
+        assert!(features.is_enabled(ENABLE_BATCH_MESSAGES));
+        // 仅加载不创建文件
+        assert!(!path.exists());
+
+        features.set(ENABLE_BATCH_MESSAGES, false).unwrap();
+        assert!(!features.is_enabled(ENABLE_BATCH_MESSAGES));
+        assert!(path.exists());
+
+        // features.json 优先于配置覆盖值
+        let reloaded = FeatureFlags::load(&path, &overrides);
+        assert!(!reloaded.is_enabled(ENABLE_BATCH_MESSAGES));
+        assert_eq!(reloaded.snapshot(), features.snapshot());
+
+        assert!(matches!(
+            features.set("unknown_flag", true),
+            Err(FeatureFlagError::Unknown(_))
+        ));
+    }
+}
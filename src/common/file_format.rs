@@ -0,0 +1,61 @@
+//! 配置文件格式（JSON / YAML）
+//!
+//! 按文件扩展名识别格式：`.yaml`/`.yml` 为 YAML，`.json` 为 JSON，
+//! 其他扩展名读取时先尝试 JSON 再尝试 YAML，写入时使用 JSON。
+
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Serialize, de::DeserializeOwned};
+
+/// 配置文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+    Yaml,
+}
+
+impl FileFormat {
+    /// 按扩展名识别格式（无法识别时返回 None）
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// 写入时使用的格式（无法识别时使用 JSON）
+    pub fn for_write(path: &Path) -> Self {
+        Self::from_path(path).unwrap_or(Self::Json)
+    }
+
+    /// 按格式反序列化
+    pub fn parse<T: DeserializeOwned>(self, content: &str) -> anyhow::Result<T> {
+        match self {
+            Self::Json => serde_json::from_str(content).context("解析 JSON 失败"),
+            Self::Yaml => serde_yaml::from_str(content).context("解析 YAML 失败"),
+        }
+    }
+
+    /// 按格式序列化（JSON 使用 pretty 格式）
+    pub fn to_string<T: Serialize>(self, value: &T) -> anyhow::Result<String> {
+        match self {
+            Self::Json => serde_json::to_string_pretty(value).context("序列化为 JSON 失败"),
+            Self::Yaml => serde_yaml::to_string(value).context("序列化为 YAML 失败"),
+        }
+    }
+}
+
+/// 按文件扩展名解析内容
+///
+/// 扩展名无法识别时先尝试 JSON，失败再尝试 YAML；两者都失败时返回 JSON 的错误
+pub fn parse_by_path<T: DeserializeOwned>(path: &Path, content: &str) -> anyhow::Result<T> {
+    match FileFormat::from_path(path) {
+        Some(format) => format.parse(content),
+        None => FileFormat::Json
+            .parse(content)
+            .or_else(|json_err| FileFormat::Yaml.parse(content).map_err(|_| json_err)),
+    }
+}
@@ -0,0 +1,673 @@
+//! 错误消息国际化
+//!
+//! 错误以稳定的错误码 + 参数表示，按客户端 `Accept-Language` 渲染为中文或英文，
+//! 未携带或不支持时回退到配置的 `defaultLocale`。日志仍使用中文。
+
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use axum::extract::FromRequestParts;
+use axum::http::{HeaderMap, header, request::Parts};
+use serde::{Deserialize, Serialize};
+
+/// 错误消息语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Zh,
+    En,
+}
+
+/// 全局默认语言（启动时由配置设置）
+static DEFAULT_LOCALE: AtomicU8 = AtomicU8::new(0);
+
+/// 设置默认语言
+pub fn set_default_locale(locale: Locale) {
+    DEFAULT_LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+/// 获取默认语言
+pub fn default_locale() -> Locale {
+    match DEFAULT_LOCALE.load(Ordering::Relaxed) {
+        1 => Locale::En,
+        _ => Locale::Zh,
+    }
+}
+
+impl Locale {
+    /// 解析语言标签（如 `zh`、`zh-CN`、`en-US`），不支持的语言返回 None
+    pub fn parse(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?;
+        if primary.eq_ignore_ascii_case("zh") {
+            Some(Self::Zh)
+        } else if primary.eq_ignore_ascii_case("en") {
+            Some(Self::En)
+        } else {
+            None
+        }
+    }
+
+    /// 从 `Accept-Language` 值中选出权重最高的受支持语言
+    ///
+    /// 权重相同时取先出现者，`q=0` 表示不接受
+    pub fn from_accept_language(value: &str) -> Option<Self> {
+        let mut best: Option<(Self, f32)> = None;
+        for item in value.split(',') {
+            let mut parts = item.split(';');
+            let Some(locale) = parts.next().and_then(Self::parse) else {
+                continue;
+            };
+            let quality = parts
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((locale, quality));
+            }
+        }
+        best.map(|(locale, _)| locale)
+    }
+
+    /// 根据请求头选择语言，未携带或不支持时回退到默认语言
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(Self::from_accept_language)
+            .unwrap_or_else(default_locale)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Locale {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_headers(&parts.headers))
+    }
+}
+
+/// 错误码
+///
+/// `as_str()` 返回的错误码是对外稳定的机器可读标识，新增错误码只能追加
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    // ===== Anthropic API =====
+    AuthenticationFailed,
+    RateLimitGlobalMinute,
+    RateLimitGlobalHour,
+    RateLimitKeyMinute,
+    RateLimitKeyHour,
+    RateLimitTokenBucket,
+    PoolUnavailable,
+    ProviderNotConfigured,
+    SerializationFailed,
+    QuotaQueueFull,
+    UpstreamCallFailed,
+    UpstreamReadFailed,
+    UpstreamResponseTooLarge,
+    UpstreamRetriesExhausted,
+    UnknownError,
+    UnsupportedModel,
+    ModelNotAllowed,
+    EmptyMessages,
+    InvalidDocument,
+    ImageTooLarge,
+    RequestImagesTooLarge,
+    InvalidSamplingParameter,
+    ContinuationToolUse,
+    ContinuationWithoutUser,
+    ContinuationEmpty,
+    WebSearchQueryMissing,
+    ResponseReadFailed,
+    FeatureDisabled,
+    EmptyBatch,
+    BatchTooLarge,
+    UpstreamRateLimited,
+    UpstreamConcurrencyLimit,
+    // ===== Admin API =====
+    AdminAuthenticationFailed,
+    CsrfTokenInvalid,
+    PoolManagerUnavailable,
+    PoolNotFound,
+    PoolAlreadyExists,
+    CannotDeleteDefaultPool,
+    PoolNotEmpty,
+    PoolHasApiKeys,
+    CannotRenameDefaultPool,
+    InvalidPoolId,
+    InvalidPoolConfig,
+    CredentialNotFound,
+    ConfigLoadFailed,
+    PersistFailed,
+    InvalidJson,
+    TokenManagerError,
+    UpstreamServiceError,
+    InternalError,
+    InvalidCredential,
+    ApiKeyNotFound,
+    ApiKeyDuplicateName,
+    ApiKeyCreateFailed,
+    ApiKeyTooShort,
+    ApiKeyWhitespace,
+    SessionCacheCapacityTooSmall,
+    NotesTooLong,
+    TagTooLong,
+    InvalidCustomHeader,
+    EmptyCredentialList,
+    InvalidSimulationScenario,
+    CsvNotUtf8,
+    ConfigSaveFailed,
+    PreferencesSaveFailed,
+    WarmupReportUnavailable,
+    CredentialRateLimited,
+    UnknownFeatureFlag,
+    FeatureFlagSaveFailed,
+    ListenerRebindFailed,
+    ApiKeyLimitExceeded,
+    ApiKeyBulkImportTooLarge,
+    InvalidRateLimitExemption,
+    RateLimitExemptionNotFound,
+    RateLimitStatsUnavailable,
+    DuplicatePriority,
+    CredentialVersionNotFound,
+    PoolDisabled,
+    BackupsDisabled,
+    BackupNotFound,
+    BackupInvalid,
+    BackupRequiresRestart,
+    BackupRestoreFailed,
+    MagicLinkInvalid,
+    MagicLinkRateLimited,
+}
+
+impl ErrorCode {
+    /// 稳定的错误码字符串
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::AuthenticationFailed => "authentication_failed",
+            Self::RateLimitGlobalMinute => "rate_limit_global_minute",
+            Self::RateLimitGlobalHour => "rate_limit_global_hour",
+            Self::RateLimitKeyMinute => "rate_limit_key_minute",
+            Self::RateLimitKeyHour => "rate_limit_key_hour",
+            Self::RateLimitTokenBucket => "rate_limit_token_bucket",
+            Self::PoolUnavailable => "pool_unavailable",
+            Self::ProviderNotConfigured => "provider_not_configured",
+            Self::SerializationFailed => "serialization_failed",
+            Self::QuotaQueueFull => "quota_queue_full",
+            Self::UpstreamCallFailed => "upstream_call_failed",
+            Self::UpstreamReadFailed => "upstream_read_failed",
+            Self::UpstreamResponseTooLarge => "upstream_response_too_large",
+            Self::UpstreamRetriesExhausted => "upstream_retries_exhausted",
+            Self::UnknownError => "unknown_error",
+            Self::UnsupportedModel => "unsupported_model",
+            Self::ModelNotAllowed => "model_not_allowed",
+            Self::EmptyMessages => "empty_messages",
+            Self::InvalidDocument => "invalid_document",
+            Self::ImageTooLarge => "image_too_large",
+            Self::RequestImagesTooLarge => "request_images_too_large",
+            Self::InvalidSamplingParameter => "invalid_sampling_parameter",
+            Self::ContinuationToolUse => "continuation_tool_use",
+            Self::ContinuationWithoutUser => "continuation_without_user",
+            Self::ContinuationEmpty => "continuation_empty",
+            Self::WebSearchQueryMissing => "web_search_query_missing",
+            Self::ResponseReadFailed => "response_read_failed",
+            Self::FeatureDisabled => "feature_disabled",
+            Self::EmptyBatch => "empty_batch",
+            Self::BatchTooLarge => "batch_too_large",
+            Self::UpstreamRateLimited => "upstream_rate_limited",
+            Self::UpstreamConcurrencyLimit => "upstream_concurrency_limit",
+            Self::AdminAuthenticationFailed => "admin_authentication_failed",
+            Self::CsrfTokenInvalid => "csrf_token_invalid",
+            Self::PoolManagerUnavailable => "pool_manager_unavailable",
+            Self::PoolNotFound => "pool_not_found",
+            Self::PoolAlreadyExists => "pool_already_exists",
+            Self::CannotDeleteDefaultPool => "cannot_delete_default_pool",
+            Self::PoolNotEmpty => "pool_not_empty",
+            Self::PoolHasApiKeys => "pool_has_api_keys",
+            Self::CannotRenameDefaultPool => "cannot_rename_default_pool",
+            Self::InvalidPoolId => "invalid_pool_id",
+            Self::InvalidPoolConfig => "invalid_pool_config",
+            Self::CredentialNotFound => "credential_not_found",
+            Self::ConfigLoadFailed => "config_load_failed",
+            Self::PersistFailed => "persist_failed",
+            Self::InvalidJson => "invalid_json",
+            Self::TokenManagerError => "token_manager_error",
+            Self::UpstreamServiceError => "upstream_service_error",
+            Self::InternalError => "internal_error",
+            Self::InvalidCredential => "invalid_credential",
+            Self::ApiKeyNotFound => "api_key_not_found",
+            Self::ApiKeyDuplicateName => "api_key_duplicate_name",
+            Self::ApiKeyCreateFailed => "api_key_create_failed",
+            Self::ApiKeyTooShort => "api_key_too_short",
+            Self::ApiKeyWhitespace => "api_key_whitespace",
+            Self::SessionCacheCapacityTooSmall => "session_cache_capacity_too_small",
+            Self::NotesTooLong => "notes_too_long",
+            Self::TagTooLong => "tag_too_long",
+            Self::InvalidCustomHeader => "invalid_custom_header",
+            Self::EmptyCredentialList => "empty_credential_list",
+            Self::InvalidSimulationScenario => "invalid_simulation_scenario",
+            Self::CsvNotUtf8 => "csv_not_utf8",
+            Self::ConfigSaveFailed => "config_save_failed",
+            Self::PreferencesSaveFailed => "preferences_save_failed",
+            Self::WarmupReportUnavailable => "warmup_report_unavailable",
+            Self::CredentialRateLimited => "credential_rate_limited",
+            Self::UnknownFeatureFlag => "unknown_feature_flag",
+            Self::FeatureFlagSaveFailed => "feature_flag_save_failed",
+            Self::ListenerRebindFailed => "listener_rebind_failed",
+            Self::ApiKeyLimitExceeded => "api_key_limit_exceeded",
+            Self::ApiKeyBulkImportTooLarge => "api_key_bulk_import_too_large",
+            Self::InvalidRateLimitExemption => "invalid_rate_limit_exemption",
+            Self::RateLimitExemptionNotFound => "rate_limit_exemption_not_found",
+            Self::RateLimitStatsUnavailable => "rate_limit_stats_unavailable",
+            Self::DuplicatePriority => "duplicate_priority",
+            Self::CredentialVersionNotFound => "credential_version_not_found",
+            Self::PoolDisabled => "pool_disabled",
+            Self::BackupsDisabled => "backups_disabled",
+            Self::BackupNotFound => "backup_not_found",
+            Self::BackupInvalid => "backup_invalid",
+            Self::BackupRequiresRestart => "backup_requires_restart",
+            Self::BackupRestoreFailed => "backup_restore_failed",
+            Self::MagicLinkInvalid => "magic_link_invalid",
+            Self::MagicLinkRateLimited => "magic_link_rate_limited",
+        }
+    }
+
+    /// 消息模板（中文, 英文），`{name}` 为参数占位符
+    fn templates(self) -> (&'static str, &'static str) {
+        match self {
+            Self::AuthenticationFailed => ("API Key 无效", "Invalid API key"),
+            Self::RateLimitGlobalMinute => (
+                "全局限流：每分钟最多 {limit} 个请求",
+                "Global rate limit: at most {limit} requests per minute",
+            ),
+            Self::RateLimitGlobalHour => (
+                "全局限流：每小时最多 {limit} 个请求",
+                "Global rate limit: at most {limit} requests per hour",
+            ),
+            Self::RateLimitKeyMinute => (
+                "API Key 限流：每分钟最多 {limit} 个请求",
+                "API key rate limit: at most {limit} requests per minute",
+            ),
+            Self::RateLimitKeyHour => (
+                "API Key 限流：每小时最多 {limit} 个请求",
+                "API key rate limit: at most {limit} requests per hour",
+            ),
+            Self::RateLimitTokenBucket => (
+                "全局限流：突发上限 {capacity} 个请求，每秒补充 {rate} 个",
+                "Global rate limit: burst of {capacity} requests, refilled at {rate} per second",
+            ),
+            Self::PoolUnavailable => (
+                "API Key 绑定的池 '{pools}' 不可用或已禁用",
+                "Pool '{pools}' bound to the API key is unavailable or disabled",
+            ),
+            Self::ProviderNotConfigured => (
+                "Kiro API Provider 未配置",
+                "Kiro API provider not configured",
+            ),
+            Self::SerializationFailed => (
+                "序列化请求失败: {detail}",
+                "Failed to serialize request: {detail}",
+            ),
+            Self::QuotaQueueFull => (
+                "所有凭据额度已用尽，排队请求已满，请稍后重试",
+                "All credentials are out of quota and the wait queue is full, please retry later",
+            ),
+            Self::UpstreamCallFailed => (
+                "上游 API 调用失败: {detail}",
+                "Upstream API call failed: {detail}",
+            ),
+            Self::UpstreamRateLimited => (
+                "上游限流，请 {retry_after} 秒后重试: {detail}",
+                "Upstream rate limited, retry in {retry_after} seconds: {detail}",
+            ),
+            Self::UpstreamConcurrencyLimit => (
+                "上游并发请求已达上限 {max}，排队超时，请稍后重试",
+                "Upstream concurrency limit of {max} reached and queue wait timed out, please retry later",
+            ),
+            Self::UpstreamReadFailed => (
+                "读取响应失败: {detail}",
+                "Failed to read upstream response: {detail}",
+            ),
+            Self::UpstreamResponseTooLarge => (
+                "上游响应超出解码缓冲区上限: {detail}",
+                "Upstream response exceeds the decoder buffer limit: {detail}",
+            ),
+            Self::UpstreamRetriesExhausted => (
+                "上游 API 调用失败（已重试 {retries} 次）: {detail}",
+                "Upstream API call failed after {retries} attempts: {detail}",
+            ),
+            Self::UnknownError => ("未知错误", "Unknown error"),
+            Self::UnsupportedModel => ("模型不支持: {model}", "Unsupported model: {model}"),
+            Self::ModelNotAllowed => (
+                "当前 API Key 无权使用模型: {model}",
+                "This API key is not allowed to use model: {model}",
+            ),
+            Self::EmptyMessages => ("消息列表为空", "Messages must not be empty"),
+            Self::InvalidDocument => (
+                "messages[{message_index}].content[{block_index}] 文档无效: {reason}",
+                "messages[{message_index}].content[{block_index}] has an invalid document: {reason}",
+            ),
+            Self::ImageTooLarge => (
+                "messages[{message_index}].content[{block_index}] 图片大小 {size} 字节超过上限 {max} 字节",
+                "messages[{message_index}].content[{block_index}] image size {size} bytes exceeds the limit of {max} bytes",
+            ),
+            Self::RequestImagesTooLarge => (
+                "请求图片总大小 {total} 字节超过上限 {max} 字节",
+                "Total image size {total} bytes exceeds the per-request limit of {max} bytes",
+            ),
+            Self::InvalidSamplingParameter => (
+                "{name} 超出取值范围 {range}: {value}",
+                "{name} must be in the range {range}, got {value}",
+            ),
+            Self::ContinuationToolUse => (
+                "messages[{message_index}] 末尾的 assistant 消息包含 tool_use，无法续写：上游不支持续写工具调用。\
+                 续写仅支持只包含 text/thinking 的 assistant 片段（如 max_tokens 截断的回复）；\
+                 工具调用请将 tool_result 作为新的 user 消息发送",
+                "messages[{message_index}] is a trailing assistant message containing tool_use, which cannot be continued: \
+                 the upstream does not support continuing tool calls. Continuation only supports assistant fragments \
+                 with text/thinking blocks (e.g. a reply cut off by max_tokens); for tool calls, send the tool_result \
+                 in a new user message",
+            ),
+            Self::ContinuationWithoutUser => (
+                "messages[{message_index}] 末尾的 assistant 消息必须紧跟在 user 消息之后：\
+                 续写模式要求对话以 user 消息 + 被截断的 assistant 回复结尾",
+                "messages[{message_index}] is a trailing assistant message that must directly follow a user message: \
+                 continuation expects the conversation to end with a user message followed by the truncated assistant reply",
+            ),
+            Self::ContinuationEmpty => (
+                "messages[{message_index}] 末尾的 assistant 消息为空，没有可续写的内容；\
+                 续写需要包含已生成的 text/thinking 片段，不续写时请去掉该消息",
+                "messages[{message_index}] is an empty trailing assistant message with nothing to continue; \
+                 include the already generated text/thinking fragment, or drop the message if no continuation is intended",
+            ),
+            Self::WebSearchQueryMissing => (
+                "无法从消息中提取搜索查询",
+                "Unable to extract a search query from the messages",
+            ),
+            Self::ResponseReadFailed => ("读取响应失败", "Failed to read response"),
+            Self::FeatureDisabled => ("功能未启用: {name}", "Feature is not enabled: {name}"),
+            Self::EmptyBatch => ("requests 不能为空", "requests must not be empty"),
+            Self::BatchTooLarge => (
+                "单个批次最多 {max} 个请求",
+                "A batch may contain at most {max} requests",
+            ),
+            Self::AdminAuthenticationFailed => (
+                "Admin API Key 无效或缺失",
+                "Invalid or missing admin API key",
+            ),
+            Self::CsrfTokenInvalid => ("CSRF Token 无效或缺失", "Invalid or missing CSRF token"),
+            Self::PoolManagerUnavailable => ("池管理器未初始化", "Pool manager is not initialized"),
+            Self::PoolNotFound => ("池不存在: {pool_id}", "Pool not found: {pool_id}"),
+            Self::PoolAlreadyExists => ("池已存在: {pool_id}", "Pool already exists: {pool_id}"),
+            Self::CannotDeleteDefaultPool => {
+                ("不能删除默认池", "The default pool cannot be deleted")
+            }
+            Self::PoolNotEmpty => (
+                "池 {pool_id} 仍有凭据: {credential_ids}（可指定 reassign_to 或 force=true）",
+                "Pool {pool_id} still has credentials: {credential_ids} (pass reassign_to or force=true)",
+            ),
+            Self::PoolHasApiKeys => (
+                "池 {pool_id} 仍被 API Key 绑定: {api_keys}（可指定 reassign_to 或 force=true）",
+                "Pool {pool_id} is still bound to API keys: {api_keys} (pass reassign_to or force=true)",
+            ),
+            Self::CannotRenameDefaultPool => {
+                ("不能重命名默认池", "The default pool cannot be renamed")
+            }
+            Self::InvalidPoolId => ("池 ID 无效: {reason}", "Invalid pool ID: {reason}"),
+            Self::InvalidPoolConfig => (
+                "池配置无效: {reason}",
+                "Invalid pool configuration: {reason}",
+            ),
+            Self::CredentialNotFound => ("凭据不存在: {id}", "Credential not found: {id}"),
+            Self::ConfigLoadFailed => (
+                "配置加载失败: {reason}",
+                "Failed to load configuration: {reason}",
+            ),
+            Self::PersistFailed => ("持久化失败: {reason}", "Failed to persist: {reason}"),
+            Self::InvalidJson => ("JSON 错误: {detail}", "Invalid JSON: {detail}"),
+            Self::TokenManagerError => (
+                "Token 管理器错误: {detail}",
+                "Token manager error: {detail}",
+            ),
+            Self::UpstreamServiceError => {
+                ("上游服务错误: {detail}", "Upstream service error: {detail}")
+            }
+            Self::InternalError => ("内部错误: {detail}", "Internal error: {detail}"),
+            Self::InvalidCredential => ("凭据无效: {detail}", "Invalid credential: {detail}"),
+            Self::ApiKeyNotFound => ("API Key 不存在: {id}", "API key not found: {id}"),
+            Self::ApiKeyDuplicateName => (
+                "API Key 名称已存在: {name}",
+                "API key name already exists: {name}",
+            ),
+            Self::ApiKeyCreateFailed => (
+                "创建 API Key 失败: {detail}",
+                "Failed to create API key: {detail}",
+            ),
+            Self::ApiKeyTooShort => (
+                "apiKey 长度不能少于 {min} 个字符",
+                "apiKey must be at least {min} characters long",
+            ),
+            Self::ApiKeyWhitespace => (
+                "apiKey 不能包含空白字符",
+                "apiKey must not contain whitespace",
+            ),
+            Self::SessionCacheCapacityTooSmall => (
+                "sessionCacheMaxCapacity 不能小于 {min}，当前值: {value}",
+                "sessionCacheMaxCapacity must be at least {min}, got {value}",
+            ),
+            Self::NotesTooLong => (
+                "备注不能超过 {max} 个字符",
+                "Notes must not exceed {max} characters",
+            ),
+            Self::TagTooLong => (
+                "标签不能超过 {max} 个字符",
+                "Tags must not exceed {max} characters",
+            ),
+            Self::InvalidCustomHeader => (
+                "自定义请求头无效: {detail}",
+                "Invalid custom header: {detail}",
+            ),
+            Self::EmptyCredentialList => ("凭据列表不能为空", "Credential list must not be empty"),
+            Self::InvalidSimulationScenario => (
+                "模拟场景无效: {reason}",
+                "Invalid simulation scenario: {reason}",
+            ),
+            Self::CsvNotUtf8 => ("CSV 必须为 UTF-8 编码", "CSV must be UTF-8 encoded"),
+            Self::ConfigSaveFailed => (
+                "保存配置失败: {detail}",
+                "Failed to save configuration: {detail}",
+            ),
+            Self::PreferencesSaveFailed => (
+                "保存偏好设置失败: {detail}",
+                "Failed to save preferences: {detail}",
+            ),
+            Self::WarmupReportUnavailable => (
+                "凭据预热尚未完成",
+                "Credential warm-up has not completed yet",
+            ),
+            Self::CredentialRateLimited => (
+                "凭据 #{id} 操作过于频繁，请 {retry_after} 秒后重试",
+                "Too many requests for credential #{id}, retry in {retry_after} seconds",
+            ),
+            Self::UnknownFeatureFlag => ("未知功能开关: {name}", "Unknown feature flag: {name}"),
+            Self::FeatureFlagSaveFailed => (
+                "保存功能开关失败: {detail}",
+                "Failed to save feature flags: {detail}",
+            ),
+            Self::ListenerRebindFailed => (
+                "监听地址 {addr} 绑定失败，仍使用原地址，配置未修改: {detail}",
+                "Failed to bind listen address {addr}; still serving on the previous address and configuration is unchanged: {detail}",
+            ),
+            Self::ApiKeyLimitExceeded => (
+                "API Key 数量已达上限 {max}",
+                "The number of API keys has reached the limit of {max}",
+            ),
+            Self::ApiKeyBulkImportTooLarge => (
+                "单次最多导入 {max} 个 API Key",
+                "At most {max} API keys can be imported at once",
+            ),
+            Self::InvalidRateLimitExemption => (
+                "限流豁免规则无效: {detail}",
+                "Invalid rate limit exemption: {detail}",
+            ),
+            Self::RateLimitExemptionNotFound => (
+                "限流豁免规则 #{index} 不存在",
+                "Rate limit exemption #{index} not found",
+            ),
+            Self::RateLimitStatsUnavailable => (
+                "未启用滑动窗口限流，没有限流统计（rateLimitEnabled 为 false 或 rateLimiterType 为 tokenBucket）",
+                "No rate limit stats: sliding window rate limiting is not enabled (rateLimitEnabled is false or rateLimiterType is tokenBucket)",
+            ),
+            Self::DuplicatePriority => (
+                "同一批次中优先级 {priority} 重复",
+                "Priority {priority} appears more than once in the batch",
+            ),
+            Self::CredentialVersionNotFound => (
+                "凭据 #{id} 没有版本 {version}（共 {max} 个历史版本）",
+                "Credential #{id} has no version {version} ({max} versions available)",
+            ),
+            Self::PoolDisabled => ("池已禁用: {pool_id}", "Pool is disabled: {pool_id}"),
+            Self::BackupsDisabled => (
+                "未启用自动备份（配置 backupDir 后启用）",
+                "Automatic backups are disabled (set backupDir to enable them)",
+            ),
+            Self::BackupNotFound => ("备份不存在: {timestamp}", "Backup not found: {timestamp}"),
+            Self::BackupInvalid => (
+                "备份 {timestamp} 中的 {file} 校验失败，未做任何修改: {detail}",
+                "{file} in backup {timestamp} failed validation; nothing was changed: {detail}",
+            ),
+            Self::BackupRequiresRestart => (
+                "备份 {timestamp} 中的配置项与运行配置不同，运行时无法切换，未做任何修改: {fields}",
+                "Settings in backup {timestamp} differ from the running configuration and cannot be applied at runtime; nothing was changed: {fields}",
+            ),
+            Self::BackupRestoreFailed => (
+                "恢复备份 {timestamp} 失败: {detail}",
+                "Failed to restore backup {timestamp}: {detail}",
+            ),
+            Self::MagicLinkInvalid => (
+                "登录链接无效、已过期或已被使用",
+                "Login link is invalid, expired or already used",
+            ),
+            Self::MagicLinkRateLimited => (
+                "Admin Key 校验失败次数过多，请 {retry_after} 秒后重试",
+                "Too many failed Admin Key attempts, retry in {retry_after} seconds",
+            ),
+        }
+    }
+
+    /// 指定语言的消息模板
+    pub fn template(self, locale: Locale) -> &'static str {
+        let (zh, en) = self.templates();
+        match locale {
+            Locale::Zh => zh,
+            Locale::En => en,
+        }
+    }
+
+    /// 附加参数，生成可本地化的错误
+    pub fn arg(self, name: &'static str, value: impl ToString) -> LocalizedError {
+        LocalizedError::from(self).arg(name, value)
+    }
+}
+
+/// 可本地化的错误：错误码 + 参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalizedError {
+    code: ErrorCode,
+    params: Vec<(&'static str, String)>,
+}
+
+impl From<ErrorCode> for LocalizedError {
+    fn from(code: ErrorCode) -> Self {
+        Self {
+            code,
+            params: Vec::new(),
+        }
+    }
+}
+
+impl LocalizedError {
+    /// 附加参数
+    pub fn arg(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.params.push((name, value.to_string()));
+        self
+    }
+
+    /// 错误码
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// 按指定语言渲染错误消息
+    pub fn render(&self, locale: Locale) -> String {
+        let mut message = self.code.template(locale).to_string();
+        for (name, value) in &self.params {
+            message = message.replace(&format!("{{{}}}", name), value);
+        }
+        message
+    }
+}
+
+/// 日志等内部场景统一使用中文
+impl std::fmt::Display for LocalizedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render(Locale::Zh))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_language_picks_highest_quality() {
+        assert_eq!(
+            Locale::from_accept_language("en-US,en;q=0.9"),
+            Some(Locale::En)
+        );
+        assert_eq!(
+            Locale::from_accept_language("fr;q=1.0, en;q=0.5, zh-CN;q=0.8"),
+            Some(Locale::Zh)
+        );
+        assert_eq!(
+            Locale::from_accept_language("zh-Hans, en"),
+            Some(Locale::Zh)
+        );
+        assert_eq!(
+            Locale::from_accept_language("en;q=0, zh;q=0.1"),
+            Some(Locale::Zh)
+        );
+        assert_eq!(Locale::from_accept_language("fr, de"), None);
+        assert_eq!(Locale::from_accept_language(""), None);
+    }
+
+    #[test]
+    fn test_from_headers_falls_back_to_default() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(Locale::from_headers(&headers), default_locale());
+
+        headers.insert(header::ACCEPT_LANGUAGE, "ja-JP".parse().unwrap());
+        assert_eq!(Locale::from_headers(&headers), default_locale());
+
+        headers.insert(header::ACCEPT_LANGUAGE, "en-GB".parse().unwrap());
+        assert_eq!(Locale::from_headers(&headers), Locale::En);
+    }
+
+    #[test]
+    fn test_render_substitutes_params() {
+        let error = ErrorCode::UpstreamRetriesExhausted
+            .arg("retries", 2)
+            .arg("detail", "timeout");
+        assert_eq!(error.code().as_str(), "upstream_retries_exhausted");
+        assert_eq!(
+            error.render(Locale::Zh),
+            "上游 API 调用失败（已重试 2 次）: timeout"
+        );
+        assert_eq!(
+            error.render(Locale::En),
+            "Upstream API call failed after 2 attempts: timeout"
+        );
+        assert_eq!(error.to_string(), error.render(Locale::Zh));
+    }
+}
@@ -0,0 +1,87 @@
+//! 文件写入工具
+//!
+//! 原子写入：先写入同目录临时文件并 fsync，再 rename 覆盖目标文件，
+//! 进程在写入途中崩溃时目标文件保持原内容，不会出现半截文件。
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// 原子写入文件（临时文件为 `<文件名>.tmp`）
+#[cfg(unix)]
+pub fn atomic_write(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref();
+    write_via_temp(path, &temp_path(path, ".tmp"), content.as_ref())
+}
+
+/// 原子写入文件
+///
+/// 非 POSIX 平台上 rename 覆盖已有文件不保证原子性，沿用直接写入目标文件的方式
+#[cfg(not(unix))]
+pub fn atomic_write(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> io::Result<()> {
+    fs::write(path, content)
+}
+
+/// 同目录下的临时文件路径（保证 rename 不跨文件系统）
+pub(crate) fn temp_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+/// 写入指定临时文件后 rename 覆盖目标文件，失败时删除临时文件
+pub(crate) fn write_via_temp(path: &Path, temp_path: &Path, content: &[u8]) -> io::Result<()> {
+    let result = write_temp(temp_path, content).and_then(|()| {
+        fs::rename(temp_path, path)?;
+        sync_parent_dir(path)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(temp_path);
+    }
+    result
+}
+
+/// 写入临时文件并 fsync
+fn write_temp(temp_path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut file = File::create(temp_path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+/// fsync 所在目录，确保 rename 落盘
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(dir) => File::open(dir)?.sync_all(),
+        None => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_before_rename_keeps_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, r#"{"port": 8990}"#).unwrap();
+
+        // 模拟进程在临时文件写入之后、rename 之前崩溃
+        let temp = temp_path(&path, ".tmp");
+        write_temp(&temp, br#"{"port": 9"#).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"port": 8990}"#);
+
+        // 重启后的下一次写入覆盖残留的临时文件
+        atomic_write(&path, r#"{"port": 9000}"#).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"port": 9000}"#);
+        if cfg!(unix) {
+            assert!(!temp.exists());
+        }
+    }
+}
@@ -1,3 +1,10 @@
 //! 公共工具模块
 
+pub mod atomic_file;
 pub mod auth;
+pub mod etag;
+pub mod features;
+pub mod file_format;
+pub mod i18n;
+pub mod io;
+pub mod persist;
@@ -0,0 +1,485 @@
+//! 配置文件持久化
+//!
+//! 每个文件（api_keys.json、pools.json、credentials.json）由唯一的写入线程负责：
+//! 修改方提交变更后由写入线程按提交顺序应用，同一批次内的多次变更合并为一次写入。
+//! 写入使用临时文件 + fsync + rename，不会出现交错写入或半截文件。
+//!
+//! 每个管理操作写入成功后，在同目录的 `changes.log` 追加一行 JSON 变更记录
+//! （时间、操作方、文件、变更摘要），超过 1 MiB 时轮转。
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, LazyLock, Weak};
+use std::thread;
+
+use anyhow::{Context, anyhow};
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+
+/// 变更记录文件名
+pub const JOURNAL_FILE_NAME: &str = "changes.log";
+
+/// 变更记录轮转阈值
+const MAX_JOURNAL_BYTES: u64 = 1024 * 1024;
+
+/// 保留的历史变更记录文件数（changes.log.1 ~ changes.log.N）
+const JOURNAL_BACKUPS: usize = 3;
+
+/// 各文件的写入器（按绝对路径共享）
+static WRITERS: LazyLock<Mutex<HashMap<PathBuf, Weak<PersistWriter>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 各目录的变更记录（多个写入线程共享同一个 changes.log）
+static JOURNALS: LazyLock<Mutex<HashMap<PathBuf, Arc<ChangeJournal>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 变更描述
+#[derive(Debug, Clone)]
+pub struct Change {
+    /// 操作方（如 `api_keys`、`pools`、`credentials`）
+    actor: String,
+    /// 变更摘要（None 表示后台回写，不写入变更记录）
+    summary: Option<String>,
+}
+
+impl Change {
+    /// 管理操作（写入变更记录）
+    pub fn new(actor: impl Into<String>, summary: impl Into<String>) -> Self {
+        Self {
+            actor: actor.into(),
+            summary: Some(summary.into()),
+        }
+    }
+
+    /// 后台回写（如统计数据、刷新后的 Token），不写入变更记录
+    pub fn background(actor: impl Into<String>) -> Self {
+        Self {
+            actor: actor.into(),
+            summary: None,
+        }
+    }
+}
+
+/// 基于文件当前内容生成新内容（文件不存在时为 None）
+type Update = Box<dyn FnOnce(Option<&str>) -> anyhow::Result<String> + Send>;
+
+/// 排队中的变更
+struct PendingChange {
+    change: Change,
+    update: Update,
+    done: oneshot::Sender<Result<(), String>>,
+}
+
+/// 持久化完成通知
+///
+/// 异步代码使用 `wait`，同步代码使用 `wait_blocking`
+pub struct PersistTicket(oneshot::Receiver<Result<(), String>>);
+
+impl PersistTicket {
+    /// 已完成的通知（用于提交前就失败的变更）
+    fn ready(result: Result<(), String>) -> Self {
+        let (done, receiver) = oneshot::channel();
+        let _ = done.send(result);
+        Self(receiver)
+    }
+
+    /// 等待写入完成
+    #[allow(dead_code)]
+    pub async fn wait(self) -> anyhow::Result<()> {
+        Self::into_result(self.0.await)
+    }
+
+    /// 阻塞等待写入完成（多线程运行时内使用 block_in_place，避免阻塞 worker）
+    pub fn wait_blocking(self) -> anyhow::Result<()> {
+        let receiver = self.0;
+        let in_multi_thread_runtime = tokio::runtime::Handle::try_current()
+            .is_ok_and(|h| h.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread);
+        let result = if in_multi_thread_runtime {
+            tokio::task::block_in_place(|| futures::executor::block_on(receiver))
+        } else {
+            futures::executor::block_on(receiver)
+        };
+        Self::into_result(result)
+    }
+
+    fn into_result(
+        result: Result<Result<(), String>, oneshot::error::RecvError>,
+    ) -> anyhow::Result<()> {
+        match result {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(anyhow!(e)),
+            Err(_) => Err(anyhow!("持久化写入线程已退出")),
+        }
+    }
+}
+
+/// 单个文件的写入器
+pub struct PersistWriter {
+    path: PathBuf,
+    /// 提交锁：变更内容在锁内生成并入队，保证入队顺序与内容生成顺序一致
+    queue: Mutex<mpsc::Sender<PendingChange>>,
+}
+
+impl PersistWriter {
+    /// 获取文件的写入器（同一文件共享同一个写入线程）
+    pub fn for_path(path: impl AsRef<Path>) -> Arc<Self> {
+        let path = std::path::absolute(path.as_ref()).unwrap_or_else(|_| path.as_ref().into());
+
+        let mut writers = WRITERS.lock();
+        if let Some(writer) = writers.get(&path).and_then(Weak::upgrade) {
+            return writer;
+        }
+        writers.retain(|_, w| w.strong_count() > 0);
+
+        let (sender, receiver) = mpsc::channel();
+        let journal = ChangeJournal::for_file(&path);
+        let thread_path = path.clone();
+        thread::Builder::new()
+            .name("persist-writer".to_string())
+            .spawn(move || run_writer(thread_path, receiver, journal))
+            .expect("创建持久化写入线程失败");
+
+        let writer = Arc::new(Self {
+            path: path.clone(),
+            queue: Mutex::new(sender),
+        });
+        writers.insert(path, Arc::downgrade(&writer));
+        writer
+    }
+
+    /// 目标文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 整文件替换（`content` 在提交锁内生成）
+    pub fn replace(
+        &self,
+        change: Change,
+        content: impl FnOnce() -> anyhow::Result<String>,
+    ) -> PersistTicket {
+        self.submit(change, || {
+            let content = content()?;
+            Ok(move |_: Option<&str>| Ok(content))
+        })
+    }
+
+    /// 提交变更
+    ///
+    /// `prepare` 在提交锁内执行（用于获取内存快照），返回的更新函数在写入线程中
+    /// 基于文件当前内容执行
+    pub fn submit<U>(
+        &self,
+        change: Change,
+        prepare: impl FnOnce() -> anyhow::Result<U>,
+    ) -> PersistTicket
+    where
+        U: FnOnce(Option<&str>) -> anyhow::Result<String> + Send + 'static,
+    {
+        let queue = self.queue.lock();
+        let update = match prepare() {
+            Ok(update) => update,
+            Err(e) => return PersistTicket::ready(Err(format!("{:#}", e))),
+        };
+
+        let (done, receiver) = oneshot::channel();
+        let pending = PendingChange {
+            change,
+            update: Box::new(update),
+            done,
+        };
+        if let Err(mpsc::SendError(pending)) = queue.send(pending) {
+            let _ = pending.done.send(Err("持久化写入线程已退出".to_string()));
+        }
+        PersistTicket(receiver)
+    }
+}
+
+/// 写入线程：依次处理变更，合并排队中的变更后一次写入
+fn run_writer(path: PathBuf, receiver: mpsc::Receiver<PendingChange>, journal: Arc<ChangeJournal>) {
+    while let Ok(first) = receiver.recv() {
+        let batch: Vec<PendingChange> = std::iter::once(first).chain(receiver.try_iter()).collect();
+        if batch.len() > 1 {
+            tracing::debug!("合并 {} 个变更写入 {:?}", batch.len(), path);
+        }
+
+        let original = match read_optional(&path) {
+            Ok(original) => original,
+            Err(e) => {
+                let reason = format!("读取文件失败: {:?}: {}", path, e);
+                for pending in batch {
+                    let _ = pending.done.send(Err(reason.clone()));
+                }
+                continue;
+            }
+        };
+
+        // 依次应用变更，失败的变更不影响同批次的其他变更
+        let mut content = original.clone();
+        let mut applied = Vec::with_capacity(batch.len());
+        for pending in batch {
+            match (pending.update)(content.as_deref()) {
+                Ok(updated) => {
+                    content = Some(updated);
+                    applied.push((pending.change, pending.done));
+                }
+                Err(e) => {
+                    let _ = pending.done.send(Err(format!("{:#}", e)));
+                }
+            }
+        }
+        if applied.is_empty() {
+            continue;
+        }
+
+        let result = match &content {
+            Some(content) if Some(content) != original.as_ref() => {
+                write_atomic(&path, content.as_bytes())
+                    .map_err(|e| format!("写入文件失败: {:?}: {}", path, e))
+            }
+            _ => Ok(()),
+        };
+
+        for (change, done) in applied {
+            if result.is_ok()
+                && let Err(e) = journal.append(&path, &change)
+            {
+                tracing::warn!("写入变更记录失败: {}", e);
+            }
+            let _ = done.send(result.clone());
+        }
+    }
+}
+
+/// 读取文件内容（不存在时返回 None）
+fn read_optional(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// 原子写入：写入同目录临时文件并 fsync，再 rename 覆盖目标文件
+///
+/// 临时文件使用独立的 `.persist.tmp` 后缀，避免与多文件事务的 `.tmp` 临时文件冲突
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    super::io::write_via_temp(path, &super::io::temp_path(path, ".persist.tmp"), content)
+}
+
+/// 变更记录（`changes.log`，每行一个 JSON 对象）
+pub struct ChangeJournal {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl ChangeJournal {
+    /// 获取文件所在目录的变更记录
+    pub fn for_file(file: &Path) -> Arc<Self> {
+        let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+        JOURNALS
+            .lock()
+            .entry(dir.clone())
+            .or_insert_with(|| {
+                Arc::new(Self {
+                    path: dir.join(JOURNAL_FILE_NAME),
+                    lock: Mutex::new(()),
+                })
+            })
+            .clone()
+    }
+
+    /// 追加一条变更记录（后台回写不记录）
+    pub fn append(&self, file: &Path, change: &Change) -> anyhow::Result<()> {
+        let Some(summary) = &change.summary else {
+            return Ok(());
+        };
+
+        let entry = serde_json::json!({
+            "time": chrono::Utc::now().to_rfc3339(),
+            "actor": change.actor,
+            "file": file.file_name().map(|n| n.to_string_lossy()),
+            "change": summary,
+        });
+
+        let _guard = self.lock.lock();
+        self.rotate_if_needed()?;
+        let mut journal = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("打开变更记录失败: {:?}", self.path))?;
+        writeln!(journal, "{}", entry)?;
+        Ok(())
+    }
+
+    /// 超过阈值时轮转：changes.log -> changes.log.1 -> ... -> changes.log.N
+    fn rotate_if_needed(&self) -> io::Result<()> {
+        let size = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if size < MAX_JOURNAL_BYTES {
+            return Ok(());
+        }
+
+        let backup = |index: usize| {
+            let mut name = self.path.as_os_str().to_os_string();
+            name.push(format!(".{}", index));
+            PathBuf::from(name)
+        };
+        for index in (1..JOURNAL_BACKUPS).rev() {
+            let from = backup(index);
+            if from.exists() {
+                fs::rename(&from, backup(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, backup(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn journal_lines(dir: &Path) -> Vec<serde_json::Value> {
+        fs::read_to_string(dir.join(JOURNAL_FILE_NAME))
+            .unwrap_or_default()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_replace_and_update_are_journaled() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("api_keys.json");
+        let writer = PersistWriter::for_path(&path);
+
+        writer
+            .replace(Change::new("api_keys", "创建 API Key #1"), || {
+                Ok("[1]".to_string())
+            })
+            .wait_blocking()
+            .unwrap();
+        writer
+            .submit(Change::new("api_keys", "创建 API Key #2"), || {
+                Ok(|current: Option<&str>| {
+                    let mut ids: Vec<u64> = serde_json::from_str(current.unwrap())?;
+                    ids.push(2);
+                    Ok(serde_json::to_string(&ids)?)
+                })
+            })
+            .wait_blocking()
+            .unwrap();
+        writer
+            .replace(Change::background("api_keys"), || Ok("[1,2]".to_string()))
+            .wait_blocking()
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[1,2]");
+        let lines = journal_lines(dir.path());
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["actor"], "api_keys");
+        assert_eq!(lines[0]["file"], "api_keys.json");
+        assert_eq!(lines[1]["change"], "创建 API Key #2");
+        assert!(lines[1]["time"].is_string());
+    }
+
+    #[test]
+    fn test_concurrent_updates_are_serialized() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pools.json");
+        fs::write(&path, "0").unwrap();
+
+        // 同一文件的写入器在各线程间共享，读-改-写不会丢失更新
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    let writer = PersistWriter::for_path(&path);
+                    for _ in 0..10 {
+                        writer
+                            .submit(Change::new("pools", format!("线程 {}", i)), || {
+                                Ok(|current: Option<&str>| {
+                                    let value: u64 = current.unwrap_or("0").parse()?;
+                                    Ok((value + 1).to_string())
+                                })
+                            })
+                            .wait_blocking()
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "80");
+        assert_eq!(journal_lines(dir.path()).len(), 80);
+        assert!(!dir.path().join("pools.json.persist.tmp").exists());
+    }
+
+    #[test]
+    fn test_failed_update_reports_error_and_keeps_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("credentials.json");
+        fs::write(&path, "original").unwrap();
+        let writer = PersistWriter::for_path(&path);
+
+        let err = writer
+            .submit(Change::new("credentials", "无效变更"), || {
+                Ok(|_: Option<&str>| -> anyhow::Result<String> { Err(anyhow!("凭据不存在")) })
+            })
+            .wait_blocking()
+            .unwrap_err();
+        assert!(err.to_string().contains("凭据不存在"));
+
+        let err = writer
+            .replace(Change::new("credentials", "序列化失败"), || {
+                Err(anyhow!("序列化失败"))
+            })
+            .wait_blocking()
+            .unwrap_err();
+        assert!(err.to_string().contains("序列化失败"));
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        assert!(journal_lines(dir.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ticket_can_be_awaited() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pools.json");
+        let writer = PersistWriter::for_path(&path);
+
+        writer
+            .replace(Change::new("pools", "创建池 premium"), || {
+                Ok("{}".to_string())
+            })
+            .wait()
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_journal_rotation() {
+        let dir = tempdir().unwrap();
+        let journal = ChangeJournal::for_file(&dir.path().join("pools.json"));
+        fs::write(&journal.path, vec![b'x'; MAX_JOURNAL_BYTES as usize]).unwrap();
+
+        journal
+            .append(Path::new("pools.json"), &Change::new("pools", "轮转后"))
+            .unwrap();
+
+        assert_eq!(journal_lines(dir.path()).len(), 1);
+        assert!(dir.path().join("changes.log.1").exists());
+    }
+}
@@ -14,6 +14,7 @@ use std::sync::Arc;
 use tokio::time::{Duration, interval};
 
 use crate::admin::ApiKeyManager;
+use crate::anthropic::{UpstreamConcurrencyLimiter, UpstreamConcurrencyStats};
 use crate::kiro::pool_manager::PoolManager;
 use crate::kiro::token_manager::MultiTokenManager;
 
@@ -31,6 +32,9 @@ pub struct HealthResponse {
     /// 池状态（可选）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pools: Option<Vec<PoolHealth>>,
+    /// 上游并发状态（未启用并发限制时省略）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_concurrency: Option<UpstreamConcurrencyStats>,
 }
 
 /// 健康状态
@@ -81,6 +85,8 @@ pub struct HealthCheckState {
     pub pool_manager: Option<Arc<PoolManager>>,
     /// API Key 管理器
     pub api_key_manager: Arc<ApiKeyManager>,
+    /// 上游并发限制器（可选）
+    pub upstream_limiter: Option<Arc<UpstreamConcurrencyLimiter>>,
     /// 服务版本
     pub version: String,
 }
@@ -96,9 +102,16 @@ impl HealthCheckState {
             token_manager,
             pool_manager,
             api_key_manager,
+            upstream_limiter: None,
             version: env!("CARGO_PKG_VERSION").to_string(),
         }
     }
+
+    /// 设置上游并发限制器（健康检查展示名额占用和排队深度）
+    pub fn with_upstream_limiter(mut self, limiter: Arc<UpstreamConcurrencyLimiter>) -> Self {
+        self.upstream_limiter = Some(limiter);
+        self
+    }
 }
 
 /// GET /health
@@ -163,6 +176,7 @@ pub async fn health_check(State(state): State<Arc<HealthCheckState>>) -> Respons
         version: state.version.clone(),
         credentials: credentials_health,
         pools: pools_health,
+        upstream_concurrency: state.upstream_limiter.as_ref().map(|l| l.stats()),
     };
 
     // 根据健康状态返回不同的 HTTP 状态码
@@ -208,6 +222,36 @@ pub fn start_health_check_task(
     })
 }
 
+/// 额度重置检查间隔（秒）
+pub const QUOTA_RESET_CHECK_INTERVAL_SECS: u64 = 30;
+
+/// 启动额度重置后台任务
+///
+/// 定期检查因额度用尽被禁用的凭据，到达重置时间（nextDateReset）后自动重新启用，
+/// 覆盖主 Token 管理器及所有池的 Token 管理器
+pub fn start_quota_reset_task(
+    token_manager: Arc<MultiTokenManager>,
+    pool_manager: Option<Arc<PoolManager>>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(QUOTA_RESET_CHECK_INTERVAL_SECS));
+
+        loop {
+            ticker.tick().await;
+            let now = Utc::now().timestamp().max(0) as u64;
+
+            token_manager.reenable_quota_reset_credentials(now);
+            if let Some(ref pm) = pool_manager {
+                for pool_id in pm.pool_ids() {
+                    pm.with_pool(&pool_id, |pool| {
+                        pool.token_manager.reenable_quota_reset_credentials(now)
+                    });
+                }
+            }
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,14 +1,21 @@
 //! HTTP Client 构建模块
 //!
 //! 提供统一的 HTTP Client 构建功能，支持代理配置
+//!
+//! `shared_client` 按（代理配置, 超时配置, TLS 配置）缓存 Client 并在调用间复用，
+//! 以复用连接池，避免每次请求都重新建立 TCP/TLS 连接
 
+use dashmap::DashMap;
 use reqwest::{Client, Proxy};
+use rustls::SupportedProtocolVersion;
+use serde::Serialize;
+use std::sync::{Arc, LazyLock};
 use std::time::Duration;
 
-use crate::model::config::TlsBackend;
+use crate::model::config::{TlsBackend, TlsVersion};
 
 /// 代理配置
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct ProxyConfig {
     /// 代理地址，支持 http/https/socks5
     pub url: String,
@@ -36,25 +43,193 @@ impl ProxyConfig {
     }
 }
 
+/// HTTP Client 超时配置
+///
+/// 建立连接超时应较短以快速失败；流式请求不设整体超时，长时间生成不会被截断
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HttpTimeouts {
+    /// 建立连接（含 TLS 握手）超时
+    pub connect: Duration,
+    /// 整个请求（含读取响应体）超时，None 表示不限制
+    pub request: Option<Duration>,
+}
+
+impl HttpTimeouts {
+    /// 限制整个请求时长（连接超时取两者较小值）
+    pub fn total(connect: Duration, request: Duration) -> Self {
+        Self {
+            connect: connect.min(request),
+            request: Some(request),
+        }
+    }
+
+    /// 仅限制建立连接，不限制整体时长（用于流式请求）
+    pub fn streaming(connect: Duration) -> Self {
+        Self {
+            connect,
+            request: None,
+        }
+    }
+}
+
+/// 仅 TLS 1.3
+static TLS13_ONLY: &[&SupportedProtocolVersion] = &[&rustls::version::TLS13];
+
+/// HTTP Client 的 TLS 配置（见 [`crate::model::config::Config::tls_options`]）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TlsOptions {
+    /// TLS 后端
+    pub backend: TlsBackend,
+    /// 最低协议版本
+    pub min_version: TlsVersion,
+}
+
+impl TlsOptions {
+    /// rustls 启用的协议版本
+    fn protocol_versions(self) -> &'static [&'static SupportedProtocolVersion] {
+        match self.min_version {
+            TlsVersion::Tls12 => rustls::ALL_VERSIONS,
+            TlsVersion::Tls13 => TLS13_ONLY,
+        }
+    }
+
+    /// rustls 可协商的密码套件（按最低版本过滤）
+    fn cipher_suites(self) -> Vec<rustls::SupportedCipherSuite> {
+        let versions = self.protocol_versions();
+        rustls::crypto::ring::default_provider()
+            .cipher_suites
+            .into_iter()
+            .filter(|suite| {
+                versions
+                    .iter()
+                    .any(|v| v.version == suite.version().version)
+            })
+            .collect()
+    }
+
+    /// 构建 rustls 客户端配置（ring 加密实现 + webpki 根证书，与 reqwest 内置配置一致）
+    fn rustls_config(self) -> anyhow::Result<rustls::ClientConfig> {
+        let provider = rustls::crypto::CryptoProvider {
+            cipher_suites: self.cipher_suites(),
+            ..rustls::crypto::ring::default_provider()
+        };
+        let roots = rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let mut config = rustls::ClientConfig::builder_with_provider(Arc::new(provider))
+            .with_protocol_versions(self.protocol_versions())?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        // 未启用 HTTP/2，只协商 HTTP/1.1
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(config)
+    }
+
+    /// 当前生效的 TLS 配置（用于启动日志和 Admin API）
+    ///
+    /// 协议版本和密码套件取自实际构建的 rustls 配置；native-tls 由系统 TLS 库协商，
+    /// 无法获取，两者均为 None
+    pub fn info(self) -> TlsInfo {
+        let (protocol_versions, cipher_suites) = match self.backend {
+            TlsBackend::Rustls => (
+                Some(
+                    self.protocol_versions()
+                        .iter()
+                        .map(|v| protocol_name(v.version))
+                        .collect(),
+                ),
+                Some(
+                    self.cipher_suites()
+                        .iter()
+                        .map(|suite| format!("{:?}", suite.suite()))
+                        .collect(),
+                ),
+            ),
+            TlsBackend::NativeTls => (None, None),
+        };
+        TlsInfo {
+            backend: self.backend,
+            min_version: self.min_version,
+            protocol_versions,
+            cipher_suites,
+        }
+    }
+}
+
+/// 协议版本名称（`TLSv1.2`、`TLSv1.3`）
+fn protocol_name(version: rustls::ProtocolVersion) -> String {
+    match version {
+        rustls::ProtocolVersion::TLSv1_2 => "TLSv1.2".to_string(),
+        rustls::ProtocolVersion::TLSv1_3 => "TLSv1.3".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// 当前生效的 TLS 配置
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsInfo {
+    /// TLS 后端
+    pub backend: TlsBackend,
+    /// 最低协议版本
+    pub min_version: TlsVersion,
+    /// 可协商的协议版本（native-tls 由系统决定，为 null）
+    pub protocol_versions: Option<Vec<String>>,
+    /// 可协商的密码套件（native-tls 由系统决定，为 null）
+    pub cipher_suites: Option<Vec<String>>,
+}
+
+/// 启动时记录 TLS 配置
+pub fn log_tls_config(tls: TlsOptions) {
+    let info = tls.info();
+    match (&info.protocol_versions, &info.cipher_suites) {
+        (Some(versions), Some(suites)) => tracing::info!(
+            "TLS 配置: 后端 {:?}，最低版本 {}，协议 {}，密码套件 {} 个",
+            info.backend,
+            info.min_version,
+            versions.join("/"),
+            suites.len()
+        ),
+        _ => tracing::info!(
+            "TLS 配置: 后端 {:?}，最低版本 {}（协议和密码套件由系统 TLS 库决定）",
+            info.backend,
+            info.min_version
+        ),
+    }
+}
+
 /// 构建 HTTP Client
 ///
 /// # Arguments
 /// * `proxy` - 可选的代理配置
-/// * `timeout_secs` - 超时时间（秒）
+/// * `timeouts` - 超时配置
+/// * `tls` - TLS 配置
 ///
 /// # Returns
 /// 配置好的 reqwest::Client
 pub fn build_client(
     proxy: Option<&ProxyConfig>,
-    timeout_secs: u64,
-    tls_backend: TlsBackend,
+    timeouts: HttpTimeouts,
+    tls: TlsOptions,
 ) -> anyhow::Result<Client> {
-    let mut builder = Client::builder().timeout(Duration::from_secs(timeout_secs));
-
-    if tls_backend == TlsBackend::Rustls {
-        builder = builder.use_rustls_tls();
+    // 按 Content-Encoding 自动解压响应体；请求未显式设置 Accept-Encoding 时自动声明
+    let mut builder = Client::builder()
+        .connect_timeout(timeouts.connect)
+        .gzip(true)
+        .deflate(true)
+        .brotli(true);
+    if let Some(timeout) = timeouts.request {
+        builder = builder.timeout(timeout);
     }
 
+    builder = match tls.backend {
+        TlsBackend::Rustls => builder.use_preconfigured_tls(tls.rustls_config()?),
+        TlsBackend::NativeTls => builder.min_tls_version(match tls.min_version {
+            TlsVersion::Tls12 => reqwest::tls::Version::TLS_1_2,
+            TlsVersion::Tls13 => reqwest::tls::Version::TLS_1_3,
+        }),
+    };
+
     if let Some(proxy_config) = proxy {
         let mut proxy = Proxy::all(&proxy_config.url)?;
 
@@ -70,9 +245,62 @@ pub fn build_client(
     Ok(builder.build()?)
 }
 
+/// Client 缓存键
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientKey {
+    proxy: Option<ProxyConfig>,
+    timeouts: HttpTimeouts,
+    tls: TlsOptions,
+}
+
+/// 已构建的 Client（reqwest::Client 内部为 Arc，克隆共享同一连接池）
+static CLIENTS: LazyLock<DashMap<ClientKey, Client>> = LazyLock::new(DashMap::new);
+
+/// 获取共享的 HTTP Client
+///
+/// 相同（代理配置, 超时配置, TLS 配置）复用同一个 Client，首次使用时构建
+pub fn shared_client(
+    proxy: Option<&ProxyConfig>,
+    timeouts: HttpTimeouts,
+    tls: TlsOptions,
+) -> anyhow::Result<Client> {
+    let key = ClientKey {
+        proxy: proxy.cloned(),
+        timeouts,
+        tls,
+    };
+    if let Some(client) = CLIENTS.get(&key) {
+        return Ok(client.clone());
+    }
+
+    let client = build_client(proxy, timeouts, tls)?;
+    Ok(CLIENTS.entry(key).or_insert(client).clone())
+}
+
+/// 移除使用指定代理的缓存 Client（代理配置变更后调用）
+///
+/// 已持有 Client 的调用方不受影响，之后的调用会按新配置重新构建
+pub fn invalidate_proxy(proxy: Option<&ProxyConfig>) {
+    CLIENTS.retain(|key, _| key.proxy.as_ref() != proxy);
+}
+
+/// 清空所有缓存的 Client
+pub fn clear_client_cache() {
+    CLIENTS.clear();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn timeouts(secs: u64) -> HttpTimeouts {
+        HttpTimeouts::total(Duration::from_secs(10), Duration::from_secs(secs))
+    }
 
     #[test]
     fn test_proxy_config_new() {
@@ -92,14 +320,147 @@ mod tests {
 
     #[test]
     fn test_build_client_without_proxy() {
-        let client = build_client(None, 30, TlsBackend::Rustls);
+        let client = build_client(None, timeouts(30), TlsOptions::default());
         assert!(client.is_ok());
     }
 
     #[test]
     fn test_build_client_with_proxy() {
         let config = ProxyConfig::new("http://127.0.0.1:7890");
-        let client = build_client(Some(&config), 30, TlsBackend::Rustls);
+        let client = build_client(Some(&config), timeouts(30), TlsOptions::default());
         assert!(client.is_ok());
     }
+
+    #[test]
+    fn test_tls13_only_client() {
+        let tls = TlsOptions {
+            min_version: TlsVersion::Tls13,
+            ..TlsOptions::default()
+        };
+        assert!(build_client(None, timeouts(30), tls).is_ok());
+
+        let config = tls.rustls_config().unwrap();
+        let suites = config.crypto_provider().cipher_suites.clone();
+        assert!(!suites.is_empty());
+        assert!(
+            suites
+                .iter()
+                .all(|s| s.version().version == rustls::ProtocolVersion::TLSv1_3)
+        );
+
+        let info = tls.info();
+        assert_eq!(info.protocol_versions.unwrap(), vec!["TLSv1.3"]);
+        assert!(
+            info.cipher_suites
+                .unwrap()
+                .iter()
+                .all(|s| s.starts_with("TLS13_"))
+        );
+
+        // 默认 TLS 1.2 起，包含 1.2 的密码套件
+        let info = TlsOptions::default().info();
+        let mut versions = info.protocol_versions.unwrap();
+        versions.sort();
+        assert_eq!(versions, vec!["TLSv1.2", "TLSv1.3"]);
+        assert!(
+            info.cipher_suites
+                .unwrap()
+                .iter()
+                .any(|s| s.starts_with("TLS_ECDHE_"))
+        );
+
+        // native-tls 的协议和密码套件由系统决定，不报告
+        let info = TlsOptions {
+            backend: TlsBackend::NativeTls,
+            ..TlsOptions::default()
+        }
+        .info();
+        assert!(info.protocol_versions.is_none());
+        assert!(info.cipher_suites.is_none());
+    }
+
+    /// 本地 HTTP/1.1 服务：统计建立的 TCP 连接数，每个连接上循环响应请求（keep-alive）
+    async fn spawn_counting_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        let counter = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    loop {
+                        let Ok(n) = socket.read(&mut chunk).await else {
+                            return;
+                        };
+                        if n == 0 {
+                            return;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                        while let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            buf.drain(..end + 4);
+                            let response = b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+                            if socket.write_all(response).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        (url, connections)
+    }
+
+    /// 对比每次新建 Client 与复用共享 Client 建立的连接数
+    #[tokio::test]
+    async fn test_shared_client_reuses_connections() {
+        const REQUESTS: usize = 20;
+
+        let (url, connections) = spawn_counting_server().await;
+        let started = Instant::now();
+        for _ in 0..REQUESTS {
+            let client = build_client(None, timeouts(30), TlsOptions::default()).unwrap();
+            client.get(&url).send().await.unwrap().text().await.unwrap();
+        }
+        let per_request = (connections.load(Ordering::SeqCst), started.elapsed());
+
+        let (url, connections) = spawn_counting_server().await;
+        let started = Instant::now();
+        for _ in 0..REQUESTS {
+            let client = shared_client(None, timeouts(31), TlsOptions::default()).unwrap();
+            client.get(&url).send().await.unwrap().text().await.unwrap();
+        }
+        let shared = (connections.load(Ordering::SeqCst), started.elapsed());
+
+        tracing::info!(
+            "{} 次请求：每次新建 Client {} 个连接 / {:?}，共享 Client {} 个连接 / {:?}",
+            REQUESTS,
+            per_request.0,
+            per_request.1,
+            shared.0,
+            shared.1
+        );
+        assert_eq!(per_request.0, REQUESTS);
+        assert_eq!(shared.0, 1);
+    }
+
+    #[test]
+    fn test_invalidate_proxy_rebuilds_client() {
+        let proxy = ProxyConfig::new("http://127.0.0.1:7891");
+        let key = ClientKey {
+            proxy: Some(proxy.clone()),
+            timeouts: timeouts(32),
+            tls: TlsOptions::default(),
+        };
+
+        shared_client(Some(&proxy), timeouts(32), TlsOptions::default()).unwrap();
+        assert!(CLIENTS.contains_key(&key));
+
+        invalidate_proxy(Some(&proxy));
+        assert!(!CLIENTS.contains_key(&key));
+    }
 }
@@ -0,0 +1,166 @@
+//! 凭据获取压测工具
+//!
+//! 模拟并发请求调用 `acquire_context_for_session`，统计吞吐量与延迟分位数。
+//! 凭据需持有未过期的 accessToken，否则压测会触发真实的 Token 刷新请求。
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::kiro::token_manager::MultiTokenManager;
+
+/// 压测结果
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct BenchmarkResult {
+    /// 总请求数
+    pub total_requests: usize,
+    /// 失败请求数
+    pub failed_requests: usize,
+    /// 总耗时
+    pub elapsed: Duration,
+    /// 吞吐量（请求/秒）
+    pub throughput_rps: f64,
+    /// 延迟 P50
+    pub p50: Duration,
+    /// 延迟 P95
+    pub p95: Duration,
+    /// 延迟 P99
+    pub p99: Duration,
+}
+
+#[allow(dead_code)]
+impl BenchmarkResult {
+    fn from_latencies(
+        mut latencies: Vec<Duration>,
+        failed_requests: usize,
+        elapsed: Duration,
+    ) -> Self {
+        latencies.sort_unstable();
+        let total_requests = latencies.len();
+        let throughput_rps = if elapsed.is_zero() {
+            0.0
+        } else {
+            total_requests as f64 / elapsed.as_secs_f64()
+        };
+
+        Self {
+            total_requests,
+            failed_requests,
+            elapsed,
+            throughput_rps,
+            p50: percentile(&latencies, 50),
+            p95: percentile(&latencies, 95),
+            p99: percentile(&latencies, 99),
+        }
+    }
+}
+
+/// 计算已排序延迟的分位数（nearest-rank）
+#[allow(dead_code)]
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[allow(dead_code)]
+impl MultiTokenManager {
+    /// 并发获取凭据压测（每个请求使用不同的会话 ID）
+    ///
+    /// # Arguments
+    /// * `concurrency` - 并发任务数
+    /// * `iterations` - 每个任务的请求次数
+    pub async fn benchmark_acquire(
+        self: &Arc<Self>,
+        concurrency: usize,
+        iterations: usize,
+    ) -> BenchmarkResult {
+        self.benchmark_acquire_with_sessions(concurrency, iterations, None)
+            .await
+    }
+
+    /// 并发获取凭据压测（可指定会话池大小）
+    ///
+    /// `session_pool` 为 Some(n) 时请求在 n 个固定会话 ID 间循环（命中粘性会话缓存），
+    /// 为 None 时每个请求使用不同的会话 ID
+    pub async fn benchmark_acquire_with_sessions(
+        self: &Arc<Self>,
+        concurrency: usize,
+        iterations: usize,
+        session_pool: Option<usize>,
+    ) -> BenchmarkResult {
+        // 独立会话使用本次压测唯一的前缀，避免多次压测之间命中会话缓存
+        let run_id = uuid::Uuid::new_v4().simple().to_string();
+        let start = Instant::now();
+        let handles: Vec<_> = (0..concurrency)
+            .map(|task| {
+                let manager = Arc::clone(self);
+                let run_id = run_id.clone();
+                tokio::spawn(async move {
+                    let mut latencies = Vec::with_capacity(iterations);
+                    let mut failed = 0;
+                    for i in 0..iterations {
+                        let seq = task * iterations + i;
+                        let session_id = match session_pool {
+                            Some(n) => format!("bench-session-{}", seq % n.max(1)),
+                            None => format!("bench-{}-{}", run_id, seq),
+                        };
+                        let request_start = Instant::now();
+                        if manager
+                            .acquire_context_for_session(Some(&session_id))
+                            .await
+                            .is_err()
+                        {
+                            failed += 1;
+                        }
+                        latencies.push(request_start.elapsed());
+                    }
+                    (latencies, failed)
+                })
+            })
+            .collect();
+
+        let mut latencies = Vec::with_capacity(concurrency * iterations);
+        let mut failed_requests = 0;
+        for handle in handles {
+            if let Ok((task_latencies, task_failed)) = handle.await {
+                latencies.extend(task_latencies);
+                failed_requests += task_failed;
+            }
+        }
+
+        BenchmarkResult::from_latencies(latencies, failed_requests, start.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kiro::model::credentials::KiroCredentials;
+    use crate::model::config::Config;
+
+    #[test]
+    fn test_percentile() {
+        let latencies: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&latencies, 50), Duration::from_millis(50));
+        assert_eq!(percentile(&latencies, 99), Duration::from_millis(99));
+        assert_eq!(percentile(&[], 50), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_acquire() {
+        let mut cred = KiroCredentials::default();
+        cred.refresh_token = Some("a".repeat(150));
+        cred.access_token = Some("token".to_string());
+        cred.expires_at = Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339());
+        let manager =
+            Arc::new(MultiTokenManager::new(Config::default(), vec![cred], None, None).unwrap());
+
+        let result = manager.benchmark_acquire(4, 10).await;
+        assert_eq!(result.total_requests, 40);
+        assert_eq!(result.failed_requests, 0);
+        assert!(result.p50 <= result.p95 && result.p95 <= result.p99);
+    }
+}
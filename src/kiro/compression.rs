@@ -0,0 +1,144 @@
+//! 上游请求体压缩
+//!
+//! 启用 `requestCompressionEnabled` 后，达到 `requestCompressionMinBytes` 的对话请求体
+//! 以 gzip 压缩发送（`content-encoding: gzip`）；压缩后未变小时按原样发送。
+//! 压缩前后的字节数通过 [`compression_stats`] 汇总，在 Admin `/stats` 中展示。
+
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use bytes::Bytes;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use crate::model::config::Config;
+
+/// 以 gzip 压缩发送的请求数
+static COMPRESSED_REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+/// 压缩前的请求体总字节数
+static BYTES_BEFORE: AtomicU64 = AtomicU64::new(0);
+
+/// 压缩后的请求体总字节数
+static BYTES_AFTER: AtomicU64 = AtomicU64::new(0);
+
+/// 请求体压缩统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestCompressionStats {
+    /// 以 gzip 压缩发送的请求数
+    pub compressed_requests: u64,
+    /// 压缩前的请求体总字节数
+    pub bytes_before: u64,
+    /// 压缩后的请求体总字节数
+    pub bytes_after: u64,
+}
+
+/// 获取进程启动以来的请求体压缩统计
+pub fn compression_stats() -> RequestCompressionStats {
+    RequestCompressionStats {
+        compressed_requests: COMPRESSED_REQUESTS.load(Ordering::Relaxed),
+        bytes_before: BYTES_BEFORE.load(Ordering::Relaxed),
+        bytes_after: BYTES_AFTER.load(Ordering::Relaxed),
+    }
+}
+
+/// 待发送的请求体
+#[derive(Debug, Clone)]
+pub struct RequestBody {
+    /// 请求体字节（重试时克隆共享同一缓冲区）
+    pub bytes: Bytes,
+    /// 是否已 gzip 压缩（需携带 `content-encoding: gzip`）
+    pub gzip: bool,
+}
+
+impl RequestBody {
+    /// 按配置决定是否压缩请求体
+    pub fn prepare(body: &str, config: &Config) -> Self {
+        let plain = || Self {
+            bytes: Bytes::copy_from_slice(body.as_bytes()),
+            gzip: false,
+        };
+        if !config.request_compression_enabled || body.len() < config.request_compression_min_bytes
+        {
+            return plain();
+        }
+
+        let compressed = match gzip(body.as_bytes()) {
+            Ok(compressed) => compressed,
+            Err(e) => {
+                tracing::warn!("请求体 gzip 压缩失败，按原样发送: {}", e);
+                return plain();
+            }
+        };
+        if compressed.len() >= body.len() {
+            return plain();
+        }
+
+        COMPRESSED_REQUESTS.fetch_add(1, Ordering::Relaxed);
+        BYTES_BEFORE.fetch_add(body.len() as u64, Ordering::Relaxed);
+        BYTES_AFTER.fetch_add(compressed.len() as u64, Ordering::Relaxed);
+        tracing::debug!(
+            "请求体已 gzip 压缩: {} -> {} 字节（减少 {:.1}%）",
+            body.len(),
+            compressed.len(),
+            (1.0 - compressed.len() as f64 / body.len() as f64) * 100.0
+        );
+
+        Self {
+            bytes: Bytes::from(compressed),
+            gzip: true,
+        }
+    }
+}
+
+/// gzip 压缩
+fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    fn config(enabled: bool, min_bytes: usize) -> Config {
+        Config {
+            request_compression_enabled: enabled,
+            request_compression_min_bytes: min_bytes,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_prepare_compresses_large_body() {
+        let body = serde_json::json!({ "content": "hello ".repeat(1000) }).to_string();
+
+        let prepared = RequestBody::prepare(&body, &config(true, 1024));
+        assert!(prepared.gzip);
+        assert!(prepared.bytes.len() < body.len());
+        let mut decoded = String::new();
+        GzDecoder::new(&prepared.bytes[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+
+        // 未启用或未达阈值时按原样发送
+        for config in [config(false, 0), config(true, body.len() + 1)] {
+            let prepared = RequestBody::prepare(&body, &config);
+            assert!(!prepared.gzip);
+            assert_eq!(prepared.bytes, body.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_prepare_skips_incompressible_body() {
+        let prepared = RequestBody::prepare("{}", &config(true, 0));
+        assert!(!prepared.gzip);
+        assert_eq!(prepared.bytes, "{}".as_bytes());
+    }
+}
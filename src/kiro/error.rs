@@ -0,0 +1,445 @@
+//! Kiro 模块错误类型
+//!
+//! 凭据管理操作（获取调用上下文、刷新 Token、添加凭据）返回结构化错误，
+//! 调用方按变体决定是否禁用凭据以及映射为哪种 HTTP 响应，而不是匹配错误文本；
+//! Kiro API 调用失败同样以 [`ProviderError`] 区分 HTTP 错误、网络错误、超时和凭据获取失败；
+//! 超时按 [`TimeoutKind`] 区分建立连接、等待首字节和整个请求超时
+
+use crate::kiro::upstream_error::{ClassifiedError, UpstreamErrorKind};
+
+/// Kiro 凭据管理错误
+#[derive(Debug, thiserror::Error)]
+pub enum KiroError {
+    /// Token 刷新失败（网络错误、刷新后 Token 仍无效等）
+    #[error("凭据 #{credential_id} Token 刷新失败: {reason}")]
+    TokenRefreshFailed { credential_id: u64, reason: String },
+
+    /// Token 刷新请求超时（建立连接超时或超过 refreshRequestTimeoutSecs）
+    #[error("凭据 #{credential_id} Token 刷新超时（{kind}）")]
+    RefreshTimeout {
+        credential_id: u64,
+        kind: TimeoutKind,
+    },
+
+    /// 凭据不存在
+    #[error("凭据 #{0} 不存在")]
+    CredentialNotFound(u64),
+
+    /// 没有凭据能提供有效 Token
+    #[error("所有凭据均无法获取有效 Token（可用: {available}/{total}）")]
+    AllCredentialsExhausted { available: usize, total: usize },
+
+    /// 凭据并发已达上限，等待空闲超时
+    #[error("凭据 #{credential_id} 并发请求已达上限 {max}，等待 {timeout_ms}ms 后仍无空闲")]
+    CredentialThrottled {
+        credential_id: u64,
+        max: u32,
+        timeout_ms: u64,
+    },
+
+    /// 凭据额度已用尽
+    #[error("凭据 #{credential_id} 额度已用尽")]
+    QuotaExceeded { credential_id: u64 },
+
+    /// refreshToken 无效（缺失、被截断或被上游拒绝）
+    #[error("{reason}")]
+    InvalidRefreshToken { reason: String },
+
+    /// 凭据刷新时会轮换 refreshToken（Social），不能克隆
+    #[error("凭据 #{0} 为 Social 凭据，刷新时会轮换 refreshToken，不能克隆")]
+    RotatingRefreshToken(u64),
+
+    /// 上游返回非成功状态码
+    #[error("{body}")]
+    UpstreamError { status: u16, body: String },
+
+    /// 文件读写失败
+    #[error("{0}")]
+    IoError(#[from] std::io::Error),
+}
+
+impl KiroError {
+    /// 将 Token 刷新（或 refreshToken 校验）的错误转换为结构化错误
+    ///
+    /// - 认证失效 → `InvalidRefreshToken`
+    /// - 月度额度用尽 → `QuotaExceeded`
+    /// - 其他 HTTP 错误 → `UpstreamError`
+    /// - 请求超时 → `RefreshTimeout`
+    /// - 网络等非 HTTP 错误 → `TokenRefreshFailed`
+    pub fn from_refresh_error(credential_id: u64, error: anyhow::Error) -> Self {
+        if let Some(kind) = error
+            .chain()
+            .find_map(|e| e.downcast_ref::<reqwest::Error>())
+            .and_then(TimeoutKind::of)
+        {
+            return Self::RefreshTimeout {
+                credential_id,
+                kind,
+            };
+        }
+        match error.downcast_ref::<ClassifiedError>() {
+            Some(e) if e.kind == UpstreamErrorKind::AuthExpired => Self::InvalidRefreshToken {
+                reason: e.message.clone(),
+            },
+            Some(e) if e.kind == UpstreamErrorKind::QuotaMonthly => {
+                Self::QuotaExceeded { credential_id }
+            }
+            Some(ClassifiedError {
+                status: Some(status),
+                message,
+                ..
+            }) => Self::UpstreamError {
+                status: *status,
+                body: message.clone(),
+            },
+            _ => Self::TokenRefreshFailed {
+                credential_id,
+                reason: error.to_string(),
+            },
+        }
+    }
+
+    /// 对应的上游错误分类（用于凭据禁用/故障转移决策）
+    pub fn kind(&self) -> UpstreamErrorKind {
+        match self {
+            Self::InvalidRefreshToken { .. } => UpstreamErrorKind::AuthExpired,
+            Self::QuotaExceeded { .. } => UpstreamErrorKind::QuotaMonthly,
+            Self::UpstreamError { status, body } => UpstreamErrorKind::from_response(*status, body),
+            _ => UpstreamErrorKind::Unknown,
+        }
+    }
+}
+
+/// 网络错误类型（对应 `reqwest::Error` 的判定方法）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkErrorKind {
+    /// 建立连接失败（DNS、拒绝连接、TLS 握手等）
+    Connect,
+    /// 发送请求失败
+    Request,
+    /// 读写请求/响应体失败
+    Body,
+    /// 响应解码失败
+    Decode,
+    /// 重定向策略错误
+    Redirect,
+    /// 其他错误（如构建请求失败）
+    Other,
+}
+
+impl NetworkErrorKind {
+    /// 从 `reqwest::Error` 判定网络错误类型
+    pub fn of(error: &reqwest::Error) -> Self {
+        if error.is_connect() {
+            Self::Connect
+        } else if error.is_body() {
+            Self::Body
+        } else if error.is_decode() {
+            Self::Decode
+        } else if error.is_redirect() {
+            Self::Redirect
+        } else if error.is_request() {
+            Self::Request
+        } else {
+            Self::Other
+        }
+    }
+
+    /// 是否为链路瞬态问题（重试可能成功）
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::Connect | Self::Request | Self::Body)
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Connect => "connect",
+            Self::Request => "request",
+            Self::Body => "body",
+            Self::Decode => "decode",
+            Self::Redirect => "redirect",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for NetworkErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 超时类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// 建立连接（含 TLS 握手）超时（connectTimeoutSecs）
+    Connect,
+    /// 等待上游响应首字节超时（upstreamFirstByteTimeoutSecs）
+    FirstByte,
+    /// 整个请求超时（如 Token 刷新的 refreshRequestTimeoutSecs）
+    Request,
+    /// 读取上游响应体时长时间未收到数据（upstreamIdleTimeoutSecs）
+    Idle,
+}
+
+impl TimeoutKind {
+    /// 从 `reqwest::Error` 判定超时类型（非超时错误返回 None）
+    pub fn of(error: &reqwest::Error) -> Option<Self> {
+        if !error.is_timeout() {
+            None
+        } else if error.is_connect() {
+            Some(Self::Connect)
+        } else {
+            Some(Self::Request)
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Connect => "connect",
+            Self::FirstByte => "firstByte",
+            Self::Request => "request",
+            Self::Idle => "idle",
+        }
+    }
+}
+
+impl std::fmt::Display for TimeoutKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Kiro API 调用失败的原因
+///
+/// handler 按变体决定是否重试以及映射为哪种 HTTP 响应，而不是匹配错误文本
+#[derive(Debug, thiserror::Error)]
+pub enum ProviderError {
+    /// 上游返回非成功状态码
+    #[error("上游返回 {status}: {body}")]
+    Http { status: u16, body: String },
+
+    /// 网络错误
+    #[error("网络错误（{0}）")]
+    Network(NetworkErrorKind),
+
+    /// 请求超时
+    #[error("请求超时（{0}）")]
+    Timeout(TimeoutKind),
+
+    /// 获取调用凭据失败（没有可用凭据、Token 刷新失败等）
+    #[error("{0}")]
+    TokenAcquisition(anyhow::Error),
+}
+
+impl ProviderError {
+    /// 从 `reqwest::Error` 转换（超时优先于其他网络错误类型）
+    pub fn from_reqwest(error: &reqwest::Error) -> Self {
+        match TimeoutKind::of(error) {
+            Some(kind) => Self::Timeout(kind),
+            None => Self::Network(NetworkErrorKind::of(error)),
+        }
+    }
+
+    /// 从 anyhow 错误中取出调用失败原因（直接携带或包装在 `UpstreamError` 中）
+    pub fn of(error: &anyhow::Error) -> Option<&Self> {
+        error.downcast_ref::<Self>().or_else(|| {
+            error
+                .downcast_ref::<crate::kiro::provider::UpstreamError>()
+                .map(|e| &e.error)
+        })
+    }
+
+    /// 对应的上游错误分类（用于凭据禁用/故障转移决策）
+    pub fn kind(&self) -> UpstreamErrorKind {
+        match self {
+            Self::Http { status, body } => UpstreamErrorKind::from_response(*status, body),
+            Self::Network(_) | Self::Timeout(_) => UpstreamErrorKind::Transient,
+            Self::TokenAcquisition(e) => UpstreamErrorKind::of(e),
+        }
+    }
+
+    /// handler 层是否应重试
+    ///
+    /// - 上游服务端瞬态错误（5xx、408 等）、超时和链路错误可重试
+    /// - 限流、认证失效、额度用尽、请求错误和凭据获取失败不重试
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Http { .. } => self.kind() == UpstreamErrorKind::Transient,
+            Self::Network(kind) => kind.is_retryable(),
+            Self::Timeout(_) => true,
+            Self::TokenAcquisition(_) => false,
+        }
+    }
+
+    /// 返回给客户端的 HTTP 状态码
+    pub fn status_code(&self) -> http::StatusCode {
+        match self {
+            Self::Http { status: 429, .. } => http::StatusCode::TOO_MANY_REQUESTS,
+            Self::Http { .. } | Self::Network(_) => http::StatusCode::BAD_GATEWAY,
+            Self::Timeout(_) => http::StatusCode::GATEWAY_TIMEOUT,
+            Self::TokenAcquisition(_) => http::StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// 返回给客户端的 Anthropic 错误类型
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            Self::Http { status: 429, .. } => "rate_limit_error",
+            Self::Http { .. } | Self::Network(_) | Self::Timeout(_) => "api_error",
+            Self::TokenAcquisition(_) => "overloaded_error",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_refresh_error_maps_classified_errors() {
+        let auth = anyhow::Error::new(
+            ClassifiedError::new(UpstreamErrorKind::AuthExpired, "凭证已过期").with_status(401),
+        );
+        let err = KiroError::from_refresh_error(1, auth);
+        assert!(matches!(err, KiroError::InvalidRefreshToken { .. }));
+        assert_eq!(err.kind(), UpstreamErrorKind::AuthExpired);
+        assert_eq!(err.to_string(), "凭证已过期");
+
+        let server = anyhow::Error::new(
+            ClassifiedError::new(UpstreamErrorKind::Transient, "服务器错误").with_status(503),
+        );
+        let err = KiroError::from_refresh_error(2, server);
+        assert!(matches!(err, KiroError::UpstreamError { status: 503, .. }));
+        assert_eq!(err.kind(), UpstreamErrorKind::Transient);
+
+        let network = anyhow::anyhow!("error trying to connect");
+        let err = KiroError::from_refresh_error(3, network);
+        assert!(matches!(
+            err,
+            KiroError::TokenRefreshFailed {
+                credential_id: 3,
+                ..
+            }
+        ));
+        assert_eq!(err.kind(), UpstreamErrorKind::Unknown);
+    }
+
+    #[test]
+    fn test_kind_survives_anyhow_conversion() {
+        let err: anyhow::Error = KiroError::InvalidRefreshToken {
+            reason: "refreshToken 为空".to_string(),
+        }
+        .into();
+        assert_eq!(UpstreamErrorKind::of(&err), UpstreamErrorKind::AuthExpired);
+
+        let err: anyhow::Error = KiroError::CredentialNotFound(7).into();
+        assert_eq!(UpstreamErrorKind::of(&err), UpstreamErrorKind::Unknown);
+        assert_eq!(err.to_string(), "凭据 #7 不存在");
+    }
+
+    #[test]
+    fn test_provider_error_classification_table() {
+        use NetworkErrorKind::*;
+
+        const QUOTA: &str = r#"{"reason":"MONTHLY_REQUEST_COUNT"}"#;
+        const THROTTLED: &str = r#"{"__type":"ThrottlingException"}"#;
+        let http = |status: u16, body: &str| ProviderError::Http {
+            status,
+            body: body.to_string(),
+        };
+        let net = ProviderError::Network;
+        let timeout = ProviderError::Timeout;
+        let no_token =
+            || ProviderError::TokenAcquisition(anyhow::anyhow!("connection timeout 502"));
+
+        // (错误, 是否重试, 响应状态码, 错误类型)
+        let table = [
+            (http(400, "Bad Request"), false, 502, "api_error"),
+            (http(400, THROTTLED), false, 502, "api_error"),
+            (http(401, ""), false, 502, "api_error"),
+            (http(402, QUOTA), false, 502, "api_error"),
+            (http(403, ""), false, 502, "api_error"),
+            (http(408, ""), true, 502, "api_error"),
+            (http(429, ""), false, 429, "rate_limit_error"),
+            (http(500, ""), true, 502, "api_error"),
+            (http(502, "Bad Gateway"), true, 502, "api_error"),
+            (http(503, "high load"), true, 502, "api_error"),
+            (http(504, ""), true, 502, "api_error"),
+            (net(Connect), true, 502, "api_error"),
+            (net(Request), true, 502, "api_error"),
+            (net(Body), true, 502, "api_error"),
+            (net(Decode), false, 502, "api_error"),
+            (net(Redirect), false, 502, "api_error"),
+            (net(Other), false, 502, "api_error"),
+            (timeout(TimeoutKind::Connect), true, 504, "api_error"),
+            (timeout(TimeoutKind::FirstByte), true, 504, "api_error"),
+            (timeout(TimeoutKind::Request), true, 504, "api_error"),
+            (timeout(TimeoutKind::Idle), true, 504, "api_error"),
+            (no_token(), false, 503, "overloaded_error"),
+        ];
+
+        for (error, retryable, status, error_type) in table {
+            assert_eq!(error.is_retryable(), retryable, "{:?}", error);
+            assert_eq!(error.status_code().as_u16(), status, "{:?}", error);
+            assert_eq!(error.error_type(), error_type, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn test_provider_error_kind_and_downcast() {
+        let quota = ProviderError::Http {
+            status: 402,
+            body: r#"{"reason":"MONTHLY_REQUEST_COUNT"}"#.to_string(),
+        };
+        assert_eq!(quota.kind(), UpstreamErrorKind::QuotaMonthly);
+        assert_eq!(
+            ProviderError::Timeout(TimeoutKind::FirstByte).kind(),
+            UpstreamErrorKind::Transient
+        );
+
+        let refresh: anyhow::Error = KiroError::InvalidRefreshToken {
+            reason: "refreshToken 为空".to_string(),
+        }
+        .into();
+        let acquisition = ProviderError::TokenAcquisition(refresh);
+        assert_eq!(acquisition.kind(), UpstreamErrorKind::AuthExpired);
+
+        // 包装在 UpstreamError 中时仍可取出失败原因
+        let err: anyhow::Error = crate::kiro::provider::UpstreamError {
+            message: "非流式 API 请求失败: 请求超时".to_string(),
+            error: ProviderError::Timeout(TimeoutKind::FirstByte),
+            request_id: None,
+            retry_after: None,
+        }
+        .into();
+        assert!(matches!(
+            ProviderError::of(&err),
+            Some(ProviderError::Timeout(TimeoutKind::FirstByte))
+        ));
+        assert_eq!(UpstreamErrorKind::of(&err), UpstreamErrorKind::Transient);
+
+        let err: anyhow::Error = ProviderError::Network(NetworkErrorKind::Connect).into();
+        assert!(ProviderError::of(&err).is_some_and(ProviderError::is_retryable));
+        assert!(ProviderError::of(&anyhow::anyhow!("502 Bad Gateway")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_provider_error_from_reqwest_connect_failure() {
+        // 绑定后立即释放端口，连接会被拒绝
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let error = reqwest::Client::new()
+            .get(format!("http://{}", addr))
+            .send()
+            .await
+            .unwrap_err();
+        let error = ProviderError::from_reqwest(&error);
+        assert!(matches!(
+            error,
+            ProviderError::Network(NetworkErrorKind::Connect)
+        ));
+        assert!(error.is_retryable());
+    }
+}
@@ -0,0 +1,182 @@
+//! 按用户公平调度
+//!
+//! 基于 `metadata.user_id` 提取的用户标识，跟踪每个用户的活跃会话及其绑定的凭据，
+//! 为新会话分配凭据时限制单个用户最多占用 `userMaxShare` 比例的可用凭据，
+//! 避免少数用户独占凭据池。
+//!
+//! 用户标识在进入调度器之前即做 SHA-256 哈希，内存中不保存原始标识。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use moka::sync::Cache;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// 对原始用户标识做哈希，得到调度器使用的用户键
+pub fn hash_user_id(raw: &str) -> String {
+    let hash = Sha256::digest(raw.as_bytes());
+    hash[..16].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 单个用户可占用的凭据数上限（至少为 1）
+pub fn user_cap(available: usize, max_share: f64) -> usize {
+    ((available as f64 * max_share).floor() as usize).max(1)
+}
+
+/// 会话绑定信息
+#[derive(Debug, Clone)]
+struct SessionBinding {
+    user: Arc<str>,
+    credential_id: u64,
+}
+
+/// 单个用户的活跃会话统计
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSessionCount {
+    /// 用户标识哈希
+    pub user_hash: String,
+    /// 活跃会话数
+    pub sessions: usize,
+    /// 占用的凭据数
+    pub credentials: usize,
+}
+
+/// 按用户公平调度跟踪器
+pub struct UserFairness {
+    /// 会话 -> 用户及凭据绑定（与会话缓存相同的容量和 TTL）
+    sessions: Cache<String, SessionBinding>,
+}
+
+impl UserFairness {
+    pub fn new(max_capacity: u64, ttl: Duration) -> Self {
+        Self {
+            sessions: Cache::builder()
+                .max_capacity(max_capacity)
+                .time_to_live(ttl)
+                .build(),
+        }
+    }
+
+    /// 记录会话所属用户及绑定的凭据
+    pub fn bind(&self, session_id: &str, user_hash: &str, credential_id: u64) {
+        self.sessions.insert(
+            session_id.to_string(),
+            SessionBinding {
+                user: Arc::from(user_hash),
+                credential_id,
+            },
+        );
+    }
+
+    /// 用户当前占用的凭据及每个凭据上的会话数
+    fn user_credentials(&self, user_hash: &str) -> HashMap<u64, usize> {
+        let mut usage = HashMap::new();
+        for (_, binding) in self.sessions.iter() {
+            if &*binding.user == user_hash {
+                *usage.entry(binding.credential_id).or_insert(0) += 1;
+            }
+        }
+        usage
+    }
+
+    /// 为用户的新会话挑选凭据
+    ///
+    /// `candidates` 为可用凭据 ID，`preferred` 为调度模式原本选择的凭据。
+    /// 用户占用的凭据数未达上限时沿用 `preferred`；
+    /// 达到上限时只能在已占用的凭据中选择会话最少的一个
+    pub fn select(
+        &self,
+        user_hash: &str,
+        candidates: &[u64],
+        preferred: Option<u64>,
+        max_share: f64,
+    ) -> Option<u64> {
+        let usage = self.user_credentials(user_hash);
+        let owned: Vec<u64> = candidates
+            .iter()
+            .copied()
+            .filter(|id| usage.contains_key(id))
+            .collect();
+
+        if owned.len() < user_cap(candidates.len(), max_share) {
+            return preferred;
+        }
+        if preferred.is_some_and(|id| owned.contains(&id)) {
+            return preferred;
+        }
+        owned.into_iter().min_by_key(|id| usage[id])
+    }
+
+    /// 各用户的活跃会话统计（按会话数降序）
+    pub fn active_sessions(&self) -> Vec<UserSessionCount> {
+        let mut users: HashMap<Arc<str>, HashMap<u64, usize>> = HashMap::new();
+        for (_, binding) in self.sessions.iter() {
+            *users
+                .entry(binding.user.clone())
+                .or_default()
+                .entry(binding.credential_id)
+                .or_insert(0) += 1;
+        }
+
+        let mut result: Vec<UserSessionCount> = users
+            .into_iter()
+            .map(|(user, credentials)| UserSessionCount {
+                user_hash: user.to_string(),
+                sessions: credentials.values().sum(),
+                credentials: credentials.len(),
+            })
+            .collect();
+        result.sort_by(|a, b| {
+            b.sessions
+                .cmp(&a.sessions)
+                .then_with(|| a.user_hash.cmp(&b.user_hash))
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_user_id_hides_raw_value() {
+        let hash = hash_user_id("user_abc");
+        assert_eq!(hash.len(), 32);
+        assert_eq!(hash, hash_user_id("user_abc"));
+        assert_ne!(hash, hash_user_id("user_abd"));
+        assert!(!hash.contains("abc"));
+    }
+
+    #[test]
+    fn test_user_cap() {
+        assert_eq!(user_cap(4, 0.5), 2);
+        assert_eq!(user_cap(3, 0.5), 1);
+        assert_eq!(user_cap(1, 0.1), 1);
+        assert_eq!(user_cap(4, 1.0), 4);
+    }
+
+    #[test]
+    fn test_select_restricts_to_owned_when_capped() {
+        let fairness = UserFairness::new(100, Duration::from_secs(60));
+        let candidates = [1, 2, 3, 4];
+
+        fairness.bind("s1", "u", 1);
+        // 未达上限（4 * 0.5 = 2）：沿用调度模式的选择
+        assert_eq!(fairness.select("u", &candidates, Some(3), 0.5), Some(3));
+
+        fairness.bind("s2", "u", 3);
+        fairness.bind("s3", "u", 3);
+        // 已达上限：只能在已占用的凭据中选择会话最少的
+        assert_eq!(fairness.select("u", &candidates, Some(4), 0.5), Some(1));
+        assert_eq!(fairness.select("u", &candidates, Some(3), 0.5), Some(3));
+
+        let stats = fairness.active_sessions();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].sessions, 3);
+        assert_eq!(stats[0].credentials, 2);
+    }
+}
@@ -0,0 +1,244 @@
+//! 上游请求头构建
+//!
+//! 对话、MCP、额度查询和 Token 刷新请求的请求头统一在此构建，
+//! User-Agent / x-amz-user-agent 只在这里拼接，Kiro IDE 升级时只需调整本模块或配置
+
+use reqwest::header::{
+    ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, AUTHORIZATION, CONNECTION, CONTENT_TYPE, HOST,
+    HeaderMap, HeaderValue, USER_AGENT,
+};
+use uuid::Uuid;
+
+use crate::kiro::machine_id;
+use crate::kiro::model::credentials::KiroCredentials;
+use crate::model::config::Config;
+
+/// IdC Token 刷新所需的 x-amz-user-agent header
+const IDC_AMZ_USER_AGENT: &str = "aws-sdk-js/3.738.0 ua/2.1 os/other lang/js md/browser#unknown_unknown api/sso-oidc#3.738.0 m/E KiroIDE";
+
+/// 上游请求类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamApi {
+    /// 对话（generateAssistantResponse）
+    Conversation,
+    /// MCP（WebSearch 等工具）
+    Mcp,
+    /// 额度查询（getUsageLimits）
+    UsageLimits,
+    /// Social Token 刷新
+    RefreshSocial,
+    /// IdC Token 刷新
+    RefreshIdc,
+}
+
+/// 构建请求头所需的上下文
+pub struct HeaderContext<'a> {
+    pub api: UpstreamApi,
+    pub credentials: &'a KiroCredentials,
+    /// 访问 Token（Token 刷新请求不需要）
+    pub token: Option<&'a str>,
+    /// Host 请求头
+    pub host: &'a str,
+}
+
+/// 构建上游请求头
+///
+/// Kiro API 请求的 User-Agent 使用配置中的 kiroVersion / systemVersion / nodeVersion，
+/// 无法生成 machineId 时返回错误（IdC 刷新不需要 machineId）
+pub fn build_headers(ctx: &HeaderContext, config: &Config) -> anyhow::Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+
+    if ctx.api == UpstreamApi::RefreshIdc {
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(HOST, HeaderValue::from_str(ctx.host)?);
+        headers.insert(CONNECTION, HeaderValue::from_static("keep-alive"));
+        headers.insert(
+            "x-amz-user-agent",
+            HeaderValue::from_static(IDC_AMZ_USER_AGENT),
+        );
+        headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+        headers.insert(ACCEPT_LANGUAGE, HeaderValue::from_static("*"));
+        headers.insert("sec-fetch-mode", HeaderValue::from_static("cors"));
+        headers.insert(USER_AGENT, HeaderValue::from_static("node"));
+        headers.insert(
+            ACCEPT_ENCODING,
+            HeaderValue::from_static("br, gzip, deflate"),
+        );
+        return Ok(headers);
+    }
+
+    let machine_id = machine_id::generate_from_credentials(ctx.credentials, config)
+        .ok_or_else(|| anyhow::anyhow!("无法生成 machine_id，请检查凭证配置"))?;
+    let kiro_ide = format!("KiroIDE-{}-{}", config.kiro_version, machine_id);
+
+    if ctx.api == UpstreamApi::RefreshSocial {
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/json, text/plain, */*"),
+        );
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(USER_AGENT, HeaderValue::from_str(&kiro_ide)?);
+        headers.insert(
+            ACCEPT_ENCODING,
+            HeaderValue::from_static("gzip, compress, deflate, br"),
+        );
+        headers.insert(HOST, HeaderValue::from_str(ctx.host)?);
+        return Ok(headers);
+    }
+
+    // (SDK 版本, API 名称, 特性标记, 最大尝试次数)
+    let (sdk_version, api_name, features, max_attempts) = match ctx.api {
+        UpstreamApi::UsageLimits => ("1.0.0", "codewhispererruntime", "N,E", 1),
+        _ => ("1.0.27", "codewhispererstreaming", "E", 3),
+    };
+    let x_amz_user_agent = format!("aws-sdk-js/{} {}", sdk_version, kiro_ide);
+    let user_agent = format!(
+        "aws-sdk-js/{} ua/2.1 os/{} lang/js md/nodejs#{} api/{}#{} m/{} {}",
+        sdk_version,
+        config.system_version,
+        config.node_version,
+        api_name,
+        sdk_version,
+        features,
+        kiro_ide
+    );
+
+    // 按照严格顺序添加请求头
+    if ctx.api != UpstreamApi::UsageLimits {
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    }
+    if ctx.api == UpstreamApi::Conversation {
+        headers.insert(
+            "x-amzn-codewhisperer-optout",
+            HeaderValue::from_static("true"),
+        );
+        headers.insert("x-amzn-kiro-agent-mode", HeaderValue::from_static("vibe"));
+    }
+    headers.insert(
+        "x-amz-user-agent",
+        HeaderValue::from_str(&x_amz_user_agent)?,
+    );
+    headers.insert(USER_AGENT, HeaderValue::from_str(&user_agent)?);
+    headers.insert(HOST, HeaderValue::from_str(ctx.host)?);
+    headers.insert(
+        "amz-sdk-invocation-id",
+        HeaderValue::from_str(&Uuid::new_v4().to_string())?,
+    );
+    headers.insert(
+        "amz-sdk-request",
+        HeaderValue::from_str(&format!("attempt=1; max={}", max_attempts))?,
+    );
+    if let Some(token) = ctx.token {
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token))?,
+        );
+    }
+    if ctx.api == UpstreamApi::Conversation {
+        headers.insert(CONNECTION, HeaderValue::from_static("close"));
+        // 二进制 Event Stream 不声明可压缩；中间代理仍压缩时由 Client 按 Content-Encoding
+        // 透明解压后再交给 EventStreamDecoder
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+    }
+
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> Config {
+        Config {
+            kiro_version: "0.9.2".to_string(),
+            system_version: "darwin#24.6.0".to_string(),
+            node_version: "22.21.1".to_string(),
+            ..Config::default()
+        }
+    }
+
+    fn test_credentials() -> KiroCredentials {
+        KiroCredentials {
+            refresh_token: Some("a".repeat(150)),
+            ..Default::default()
+        }
+    }
+
+    fn build(api: UpstreamApi, config: &Config, token: Option<&str>) -> HeaderMap {
+        let credentials = test_credentials();
+        let ctx = HeaderContext {
+            api,
+            credentials: &credentials,
+            token,
+            host: "q.us-east-1.amazonaws.com",
+        };
+        build_headers(&ctx, config).unwrap()
+    }
+
+    fn header<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+        headers.get(name).unwrap().to_str().unwrap()
+    }
+
+    #[test]
+    fn test_conversation_headers_default() {
+        let config = test_config();
+        let machine_id =
+            machine_id::generate_from_credentials(&test_credentials(), &config).unwrap();
+        let headers = build(UpstreamApi::Conversation, &config, Some("tok"));
+
+        assert_eq!(
+            header(&headers, "x-amz-user-agent"),
+            format!("aws-sdk-js/1.0.27 KiroIDE-0.9.2-{}", machine_id)
+        );
+        assert_eq!(
+            header(&headers, "user-agent"),
+            format!(
+                "aws-sdk-js/1.0.27 ua/2.1 os/darwin#24.6.0 lang/js md/nodejs#22.21.1 \
+                 api/codewhispererstreaming#1.0.27 m/E KiroIDE-0.9.2-{}",
+                machine_id
+            )
+        );
+        assert_eq!(header(&headers, "host"), "q.us-east-1.amazonaws.com");
+        assert_eq!(header(&headers, "authorization"), "Bearer tok");
+        assert_eq!(header(&headers, "amz-sdk-request"), "attempt=1; max=3");
+        assert_eq!(header(&headers, "x-amzn-kiro-agent-mode"), "vibe");
+        assert_eq!(header(&headers, "connection"), "close");
+    }
+
+    #[test]
+    fn test_headers_follow_overridden_versions() {
+        let mut config = test_config();
+        config.kiro_version = "1.2.3".to_string();
+        config.system_version = "win32#10.0.22631".to_string();
+        config.node_version = "24.0.0".to_string();
+
+        let headers = build(UpstreamApi::UsageLimits, &config, Some("tok"));
+        let user_agent = header(&headers, "user-agent");
+        assert!(user_agent.starts_with(
+            "aws-sdk-js/1.0.0 ua/2.1 os/win32#10.0.22631 lang/js md/nodejs#24.0.0 \
+             api/codewhispererruntime#1.0.0 m/N,E KiroIDE-1.2.3-"
+        ));
+        assert!(
+            header(&headers, "x-amz-user-agent").starts_with("aws-sdk-js/1.0.0 KiroIDE-1.2.3-")
+        );
+        assert_eq!(header(&headers, "amz-sdk-request"), "attempt=1; max=1");
+        assert!(headers.get("content-type").is_none());
+
+        let headers = build(UpstreamApi::Mcp, &config, Some("tok"));
+        assert!(header(&headers, "user-agent").contains("os/win32#10.0.22631"));
+        assert!(headers.get("x-amzn-kiro-agent-mode").is_none());
+    }
+
+    #[test]
+    fn test_refresh_headers() {
+        let config = test_config();
+        let headers = build(UpstreamApi::RefreshSocial, &config, None);
+        assert!(header(&headers, "user-agent").starts_with("KiroIDE-0.9.2-"));
+        assert!(headers.get("authorization").is_none());
+
+        let headers = build(UpstreamApi::RefreshIdc, &config, None);
+        assert_eq!(header(&headers, "user-agent"), "node");
+        assert_eq!(header(&headers, "x-amz-user-agent"), IDC_AMZ_USER_AGENT);
+        assert_eq!(header(&headers, "host"), "q.us-east-1.amazonaws.com");
+    }
+}
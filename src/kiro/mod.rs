@@ -1,9 +1,21 @@
 //! Kiro API 客户端模块
 
+pub mod benchmark;
+pub mod compression;
+pub mod error;
+pub mod fairness;
+pub mod headers;
 pub mod machine_id;
 pub mod model;
 pub mod parser;
+pub mod performance;
 pub mod pool;
 pub mod pool_manager;
 pub mod provider;
+pub mod scheduling;
+pub mod simulation;
+pub mod stickiness;
+pub mod timeline;
 pub mod token_manager;
+pub mod upstream_error;
+pub mod warmup;
@@ -3,9 +3,16 @@
 //! 支持从 Kiro IDE 的凭证文件加载，使用 Social 认证方式
 //! 支持单凭据和多凭据配置格式
 
+use anyhow::Context;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::common::file_format::{FileFormat, parse_by_path};
+use crate::common::io::atomic_write;
+use crate::common::persist::{Change, PersistWriter};
 
 /// Kiro OAuth 凭证
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -48,6 +55,10 @@ pub struct KiroCredentials {
     #[serde(skip_serializing_if = "is_zero")]
     pub priority: u32,
 
+    /// 最大并发请求数（未配置或为 0 时不限制）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_requests: Option<u32>,
+
     /// 凭据级 Region 配置（用于 OIDC token 刷新）
     /// 未配置时回退到 config.json 的全局 region
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -58,6 +69,14 @@ pub struct KiroCredentials {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub machine_id: Option<String>,
 
+    /// 备注（仅用于管理，不影响调度）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+
+    /// 标签（仅用于管理和筛选，不影响调度）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
     // ============ 池和代理配置 ============
 
     /// 所属池 ID（未配置时归入默认池）
@@ -76,6 +95,10 @@ pub struct KiroCredentials {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub proxy_password: Option<String>,
 
+    /// 自定义上游请求头（在标准请求头之后添加，可覆盖同名标准请求头）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub custom_headers: HashMap<String, String>,
+
     // ============ 调用统计（持久化） ============
 
     /// 成功调用次数（总计）
@@ -112,6 +135,22 @@ pub struct KiroCredentials {
     /// 最后 Token 刷新时间（Unix 时间戳毫秒）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_token_refresh_time: Option<u64>,
+
+    // ============ 禁用记录（持久化） ============
+
+    /// 最近一次禁用时间
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled_at: Option<DateTime<Utc>>,
+
+    /// 最近一次禁用原因（可读文本）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled_reason: Option<String>,
+
+    /// 来源凭据文件（来自 `credentialDirs` 目录时为该目录的凭据文件，None 表示主凭据文件）
+    ///
+    /// 不序列化，回写时按来源文件分组写入
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
 }
 
 /// 判断是否为零（用于跳过序列化）
@@ -124,6 +163,47 @@ fn is_zero_u64(value: &u64) -> bool {
     *value == 0
 }
 
+/// 规范化标签列表：去除首尾空白、丢弃空标签，并按大小写不敏感去重（保留首次出现的顺序）
+pub fn normalize_tags(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !normalized.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+/// 不允许通过自定义请求头覆盖的请求头（认证、Host 和报文编码由代理自身控制）
+const FORBIDDEN_CUSTOM_HEADERS: &[&str] = &[
+    "authorization",
+    "host",
+    "content-length",
+    "content-encoding",
+    "transfer-encoding",
+    "connection",
+];
+
+/// 校验自定义请求头：名称须为合法的 HTTP 字段名且不在禁止列表中，值不含控制字符
+pub fn validate_custom_headers(headers: &HashMap<String, String>) -> Result<(), String> {
+    for (name, value) in headers {
+        let header_name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("请求头名称不合法: {}", name))?;
+        if FORBIDDEN_CUSTOM_HEADERS.contains(&header_name.as_str()) {
+            return Err(format!("不允许自定义请求头: {}", name));
+        }
+        reqwest::header::HeaderValue::from_str(value)
+            .map_err(|_| format!("请求头 {} 的值不合法", name))?;
+    }
+    Ok(())
+}
+
+/// 平均响应时间（毫秒，无成功调用时为 None）
+pub fn avg_latency_ms(total_response_time_ms: u64, success_count: u64) -> Option<f64> {
+    (success_count > 0).then(|| total_response_time_ms as f64 / success_count as f64)
+}
+
 fn canonicalize_auth_method_value(value: &str) -> &str {
     if value.eq_ignore_ascii_case("builder-id") || value.eq_ignore_ascii_case("iam") {
         "idc"
@@ -132,9 +212,39 @@ fn canonicalize_auth_method_value(value: &str) -> &str {
     }
 }
 
+/// 凭据目录下的凭据文件名
+const DIR_CREDENTIALS_FILE: &str = "credentials.json";
+
+/// 凭据目录下的目录配置文件名
+const DIR_CONFIG_FILE: &str = "dir_config.json";
+
+/// 凭据目录配置（`dir_config.json`）
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CredentialDirConfig {
+    /// 目录内未指定 `poolId` 的凭据所属的池
+    #[serde(default)]
+    default_pool_id: Option<String>,
+}
+
+impl CredentialDirConfig {
+    /// 读取目录配置（文件不存在时使用默认值）
+    fn load(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(DIR_CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)?;
+        if content.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        parse_by_path(&path, &content)
+    }
+}
+
 /// 凭据配置（仅支持数组格式）
 ///
-/// 配置文件必须为 JSON 数组格式，支持多凭据管理
+/// 配置文件必须为数组格式（JSON 或 YAML，按扩展名识别），支持多凭据管理
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct CredentialsConfig(Vec<KiroCredentials>);
@@ -145,9 +255,22 @@ impl CredentialsConfig {
     /// - 如果文件不存在，返回空数组
     /// - 如果文件内容为空，返回空数组
     /// - 仅支持数组格式
+    /// - `.yaml`/`.yml` 按 YAML 解析，其他按 JSON 解析（扩展名无法识别时回退 YAML）
     pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
         let path = path.as_ref();
+        Self::load_with(path, |content| parse_by_path(path, content))
+    }
 
+    /// 从 YAML 文件加载凭据配置
+    #[allow(dead_code)]
+    pub fn load_yaml<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        Self::load_with(path.as_ref(), |content| FileFormat::Yaml.parse(content))
+    }
+
+    fn load_with(
+        path: &Path,
+        parse: impl FnOnce(&str) -> anyhow::Result<Self>,
+    ) -> anyhow::Result<Self> {
         // 文件不存在时返回空数组
         if !path.exists() {
             return Ok(CredentialsConfig(vec![]));
@@ -160,8 +283,112 @@ impl CredentialsConfig {
             return Ok(CredentialsConfig(vec![]));
         }
 
-        let config = serde_json::from_str(&content)?;
-        Ok(config)
+        parse(&content)
+    }
+
+    /// 保存凭据配置到文件（格式按扩展名识别，无法识别时使用 JSON）
+    #[allow(dead_code)]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        atomic_write(path, FileFormat::for_write(path).to_string(self)?)?;
+        Ok(())
+    }
+
+    /// 以 YAML 格式保存凭据配置到文件
+    #[allow(dead_code)]
+    pub fn save_yaml<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        atomic_write(path, FileFormat::Yaml.to_string(self)?)?;
+        Ok(())
+    }
+
+    /// 从多个凭据目录加载凭据
+    ///
+    /// - 读取每个目录下的 `credentials.json`（不存在时视为空），凭据记录来源文件，
+    ///   运行时的变更（刷新后的 Token、统计数据等）回写到来源文件，不写入主凭据文件
+    /// - 目录下的 `dir_config.json` 配置了 `defaultPoolId` 时，未指定 `poolId` 的凭据归入该池
+    /// - 同一来源文件中 clientId 相同的凭据只加载首次出现的一个
+    /// - 缺少 ID 或 ID 已被占用（`taken_ids`，含主凭据和之前目录的凭据）的凭据分配新 ID
+    ///   并回写来源文件，保证各文件之间的凭据 ID 不冲突
+    /// - 单个目录加载失败时记录警告并跳过该目录，不影响其他目录
+    pub fn load_from_dirs(dirs: &[PathBuf], taken_ids: &mut HashSet<u64>) -> Vec<KiroCredentials> {
+        let mut credentials = Vec::new();
+        for dir in dirs {
+            match Self::load_dir(dir, taken_ids) {
+                Ok(loaded) => credentials.extend(loaded),
+                Err(e) => tracing::warn!("加载凭据目录 {} 失败，已跳过: {:#}", dir.display(), e),
+            }
+        }
+        credentials
+    }
+
+    /// 加载单个凭据目录（成功时将该目录的凭据 ID 加入 `taken_ids`）
+    fn load_dir(dir: &Path, taken_ids: &mut HashSet<u64>) -> anyhow::Result<Vec<KiroCredentials>> {
+        if !dir.is_dir() {
+            anyhow::bail!("凭据目录不存在");
+        }
+        let dir_config = CredentialDirConfig::load(dir)
+            .with_context(|| format!("读取 {} 失败", DIR_CONFIG_FILE))?;
+        let path = dir.join(DIR_CREDENTIALS_FILE);
+        let mut loaded = Self::load(&path)
+            .with_context(|| format!("读取 {} 失败", DIR_CREDENTIALS_FILE))?
+            .0;
+
+        // 分配 ID：重复或缺失的 ID 接在所有已知 ID 之后
+        let mut ids = taken_ids.clone();
+        let mut next_id = ids
+            .iter()
+            .copied()
+            .chain(loaded.iter().filter_map(|c| c.id))
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let mut reassigned = 0;
+        for cred in &mut loaded {
+            match cred.id {
+                Some(id) if ids.insert(id) => {}
+                _ => {
+                    cred.id = Some(next_id);
+                    ids.insert(next_id);
+                    next_id += 1;
+                    reassigned += 1;
+                }
+            }
+        }
+        if reassigned > 0 {
+            let content = FileFormat::for_write(&path).to_string(&loaded)?;
+            PersistWriter::for_path(&path)
+                .replace(
+                    Change::new("credentials", format!("为 {} 个凭据分配 ID", reassigned)),
+                    || Ok(content),
+                )
+                .wait_blocking()
+                .with_context(|| format!("回写 {} 失败", DIR_CREDENTIALS_FILE))?;
+        }
+        *taken_ids = ids;
+
+        let mut client_ids = HashSet::new();
+        let mut credentials = Vec::with_capacity(loaded.len());
+        let mut duplicates = 0;
+        for mut cred in loaded {
+            if let Some(client_id) = cred.client_id.clone()
+                && !client_ids.insert(client_id)
+            {
+                duplicates += 1;
+                continue;
+            }
+            if cred.pool_id.is_none() {
+                cred.pool_id = dir_config.default_pool_id.clone();
+            }
+            cred.source = Some(path.clone());
+            credentials.push(cred);
+        }
+        tracing::info!(
+            "从凭据目录 {} 加载了 {} 个凭据（跳过 {} 个 clientId 重复的凭据）",
+            dir.display(),
+            credentials.len(),
+            duplicates
+        );
+        Ok(credentials)
     }
 
     /// 转换为按优先级排序的凭据列表
@@ -175,6 +402,45 @@ impl CredentialsConfig {
         creds
     }
 
+    /// 将属于 `old_pool_id` 的凭据改为属于 `new_pool_id`，返回修改的凭据数量
+    pub fn rename_pool(&mut self, old_pool_id: &str, new_pool_id: &str) -> usize {
+        let mut renamed = 0;
+        for cred in self
+            .0
+            .iter_mut()
+            .filter(|c| c.pool_id.as_deref() == Some(old_pool_id))
+        {
+            cred.pool_id = Some(new_pool_id.to_string());
+            renamed += 1;
+        }
+        renamed
+    }
+
+    /// 将凭据 `credential_id` 分配到 `pool_id`，凭据不存在时返回 false
+    pub fn set_pool(&mut self, credential_id: u64, pool_id: &str) -> bool {
+        match self.0.iter_mut().find(|c| c.id == Some(credential_id)) {
+            Some(cred) => {
+                cred.pool_id = Some(pool_id.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 遍历所有凭据
+    pub fn iter(&self) -> impl Iterator<Item = &KiroCredentials> {
+        self.0.iter()
+    }
+
+    /// 属于 `pool_id` 的凭据 ID（未分配 ID 的凭据不列出）
+    pub fn credential_ids_in_pool(&self, pool_id: &str) -> Vec<u64> {
+        self.0
+            .iter()
+            .filter(|c| c.pool_id.as_deref() == Some(pool_id))
+            .filter_map(|c| c.id)
+            .collect()
+    }
+
     /// 获取凭据数量
     #[allow(dead_code)]
     pub fn len(&self) -> usize {
@@ -188,6 +454,12 @@ impl CredentialsConfig {
     }
 }
 
+impl From<Vec<KiroCredentials>> for CredentialsConfig {
+    fn from(credentials: Vec<KiroCredentials>) -> Self {
+        CredentialsConfig(credentials)
+    }
+}
+
 impl KiroCredentials {
     /// 获取默认凭证文件路径
     pub fn default_credentials_path() -> &'static str {
@@ -217,6 +489,12 @@ impl KiroCredentials {
         serde_json::to_string_pretty(self)
     }
 
+    /// 序列化为 YAML 字符串
+    #[allow(dead_code)]
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+
     pub fn canonicalize_auth_method(&mut self) {
         let auth_method = match &self.auth_method {
             Some(m) => m,
@@ -228,12 +506,52 @@ impl KiroCredentials {
             self.auth_method = Some(canonical.to_string());
         }
     }
+
+    /// 平均响应时间（毫秒，按持久化的 `totalResponseTimeMs / successCount` 计算）
+    pub fn avg_latency_ms(&self) -> Option<f64> {
+        avg_latency_ms(self.total_response_time_ms, self.success_count)
+    }
+
+    /// 从 profileArn 推断 Region
+    ///
+    /// ARN 格式为 `arn:partition:service:region:account:resource`，
+    /// Region 为第 4 段；缺少该段或为空时返回 None
+    pub fn infer_region(&self) -> Option<String> {
+        let region = self.profile_arn.as_deref()?.split(':').nth(3)?;
+        let valid = !region.is_empty()
+            && region
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        valid.then(|| region.to_string())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_infer_region_from_profile_arn() {
+        let with_arn = |arn: &str| KiroCredentials {
+            profile_arn: Some(arn.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            with_arn("arn:aws:codewhisperer:us-east-1:123456789012:profile/ABC").infer_region(),
+            Some("us-east-1".to_string())
+        );
+        assert_eq!(
+            with_arn("arn:aws:codewhisperer:eu-central-1:123456789012:profile/ABC").infer_region(),
+            Some("eu-central-1".to_string())
+        );
+        // 缺少 region 段或 region 为空
+        assert_eq!(with_arn("arn:aws:s3:::my-bucket").infer_region(), None);
+        assert_eq!(with_arn("arn:aws:test").infer_region(), None);
+        assert_eq!(with_arn("not-an-arn").infer_region(), None);
+        assert_eq!(KiroCredentials::default().infer_region(), None);
+    }
+
     #[test]
     fn test_from_json() {
         let json = r#"{
@@ -275,12 +593,16 @@ mod tests {
             client_id: None,
             client_secret: None,
             priority: 0,
+            max_concurrent_requests: None,
             region: None,
             machine_id: None,
+            notes: None,
+            tags: Vec::new(),
             pool_id: None,
             proxy_url: None,
             proxy_username: None,
             proxy_password: None,
+            custom_headers: HashMap::new(),
             success_count: 0,
             total_failure_count: 0,
             last_call_time: None,
@@ -288,6 +610,9 @@ mod tests {
             last_token_refresh_time: None,
             token_refresh_count: 0,
             token_refresh_failure_count: 0,
+            disabled_at: None,
+            disabled_reason: None,
+            source: None,
         };
 
         let json = creds.to_pretty_json().unwrap();
@@ -387,12 +712,16 @@ mod tests {
             client_id: None,
             client_secret: None,
             priority: 0,
+            max_concurrent_requests: None,
             region: Some("eu-west-1".to_string()),
             machine_id: None,
+            notes: None,
+            tags: Vec::new(),
             pool_id: None,
             proxy_url: None,
             proxy_username: None,
             proxy_password: None,
+            custom_headers: HashMap::new(),
             success_count: 0,
             total_failure_count: 0,
             last_call_time: None,
@@ -400,6 +729,9 @@ mod tests {
             last_token_refresh_time: None,
             token_refresh_count: 0,
             token_refresh_failure_count: 0,
+            disabled_at: None,
+            disabled_reason: None,
+            source: None,
         };
 
         let json = creds.to_pretty_json().unwrap();
@@ -420,12 +752,16 @@ mod tests {
             client_id: None,
             client_secret: None,
             priority: 0,
+            max_concurrent_requests: None,
             region: None,
             machine_id: None,
+            notes: None,
+            tags: Vec::new(),
             pool_id: None,
             proxy_url: None,
             proxy_username: None,
             proxy_password: None,
+            custom_headers: HashMap::new(),
             success_count: 0,
             total_failure_count: 0,
             last_call_time: None,
@@ -433,6 +769,9 @@ mod tests {
             last_token_refresh_time: None,
             token_refresh_count: 0,
             token_refresh_failure_count: 0,
+            disabled_at: None,
+            disabled_reason: None,
+            source: None,
         };
 
         let json = creds.to_pretty_json().unwrap();
@@ -535,12 +874,16 @@ mod tests {
             client_id: None,
             client_secret: None,
             priority: 3,
+            max_concurrent_requests: None,
             region: Some("us-west-2".to_string()),
             machine_id: Some("c".repeat(64)),
+            notes: None,
+            tags: Vec::new(),
             pool_id: None,
             proxy_url: None,
             proxy_username: None,
             proxy_password: None,
+            custom_headers: HashMap::new(),
             success_count: 0,
             total_failure_count: 0,
             last_call_time: None,
@@ -548,6 +891,9 @@ mod tests {
             last_token_refresh_time: None,
             token_refresh_count: 0,
             token_refresh_failure_count: 0,
+            disabled_at: None,
+            disabled_reason: None,
+            source: None,
         };
 
         let json = original.to_pretty_json().unwrap();
@@ -561,6 +907,29 @@ mod tests {
         assert_eq!(parsed.machine_id, original.machine_id);
     }
 
+    #[test]
+    fn test_tags_roundtrip_and_normalize() {
+        let creds = KiroCredentials::from_json(
+            r#"{"refreshToken": "test_refresh", "tags": ["team-a", "vendor-x"]}"#,
+        )
+        .unwrap();
+        assert_eq!(creds.tags, vec!["team-a", "vendor-x"]);
+
+        let json = creds.to_pretty_json().unwrap();
+        assert_eq!(KiroCredentials::from_json(&json).unwrap().tags, creds.tags);
+
+        // 无标签时不写入文件
+        let json = KiroCredentials::default().to_pretty_json().unwrap();
+        assert!(!json.contains("tags"));
+
+        let tags = normalize_tags(
+            [" team-a ", "", "Team-A", "vendor-x"]
+                .into_iter()
+                .map(String::from),
+        );
+        assert_eq!(tags, vec!["team-a", "vendor-x"]);
+    }
+
     // ============ Pool 和 Proxy 字段测试 ============
 
     #[test]
@@ -588,4 +957,136 @@ mod tests {
         assert_eq!(creds.proxy_username, Some("user".to_string()));
         assert_eq!(creds.proxy_password, Some("pass".to_string()));
     }
+
+    #[test]
+    fn test_credentials_yaml_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let yaml_path = dir.path().join("credentials.yaml");
+        fs::write(
+            &yaml_path,
+            "- refreshToken: token-a\n  authMethod: social\n  priority: 1\n\
+             - refreshToken: token-b\n  authMethod: idc\n  clientId: cid\n  clientSecret: secret\n  region: eu-west-1\n",
+        )
+        .unwrap();
+
+        let config = CredentialsConfig::load(&yaml_path).unwrap();
+        assert_eq!(config.len(), 2);
+
+        // YAML 与 JSON 保存后加载结果一致
+        let json_path = dir.path().join("credentials.json");
+        config.save(&json_path).unwrap();
+        config.save_yaml(&yaml_path).unwrap();
+        let from_json = CredentialsConfig::load(&json_path).unwrap();
+        let from_yaml = CredentialsConfig::load_yaml(&yaml_path).unwrap();
+        assert_eq!(
+            serde_json::to_value(&from_yaml).unwrap(),
+            serde_json::to_value(&from_json).unwrap()
+        );
+
+        // 按优先级排序后 token-b（priority 0）在前
+        let creds = from_yaml.into_sorted_credentials();
+        assert_eq!(creds[0].client_id.as_deref(), Some("cid"));
+        assert_eq!(creds[0].region.as_deref(), Some("eu-west-1"));
+        assert_eq!(creds[1].priority, 1);
+
+        let yaml = creds[0].to_yaml().unwrap();
+        assert!(yaml.contains("refreshToken: token-b"), "{}", yaml);
+    }
+
+    #[test]
+    fn test_validate_custom_headers() {
+        let headers =
+            |name: &str, value: &str| HashMap::from([(name.to_string(), value.to_string())]);
+
+        assert!(validate_custom_headers(&HashMap::new()).is_ok());
+        assert!(validate_custom_headers(&headers("x-shard", "7")).is_ok());
+        assert!(validate_custom_headers(&headers("X-Amzn-Kiro-Agent-Mode", "spec")).is_ok());
+        // 非法字段名或值
+        assert!(validate_custom_headers(&headers("bad header", "1")).is_err());
+        assert!(validate_custom_headers(&headers("x-shard", "a\nb")).is_err());
+        // 受保护的请求头（不区分大小写）
+        assert!(validate_custom_headers(&headers("Authorization", "Bearer x")).is_err());
+        assert!(validate_custom_headers(&headers("host", "example.com")).is_err());
+    }
+
+    #[test]
+    fn test_custom_headers_roundtrip() {
+        let json = r#"{"refreshToken": "r", "customHeaders": {"x-shard": "7"}}"#;
+        let credentials: KiroCredentials = serde_json::from_str(json).unwrap();
+        assert_eq!(credentials.custom_headers["x-shard"], "7");
+
+        // 未配置时不写回文件
+        let serialized = serde_json::to_string(&KiroCredentials::default()).unwrap();
+        assert!(!serialized.contains("customHeaders"));
+    }
+
+    #[test]
+    fn test_load_from_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        let team_a = root.path().join("team-a");
+        let team_b = root.path().join("team-b");
+        fs::create_dir_all(&team_a).unwrap();
+        fs::create_dir_all(&team_b).unwrap();
+        let team_a_file = r#"[{"id": 1, "refreshToken": "a1"}, {"id": 2, "refreshToken": "a2"}]"#;
+        fs::write(team_a.join("credentials.json"), team_a_file).unwrap();
+        fs::write(
+            team_a.join("dir_config.json"),
+            r#"{"defaultPoolId": "team-a"}"#,
+        )
+        .unwrap();
+        // clientId 相同的凭据被跳过，显式指定的 poolId 保持不变
+        fs::write(
+            team_b.join("credentials.json"),
+            r#"[
+                {"id": 1, "refreshToken": "b1", "clientId": "c1", "poolId": "vip"},
+                {"refreshToken": "b2", "clientId": "c1"}
+            ]"#,
+        )
+        .unwrap();
+        // 目录配置无法解析时只跳过该目录
+        let broken = root.path().join("broken");
+        fs::create_dir_all(&broken).unwrap();
+        fs::write(broken.join("dir_config.json"), "{").unwrap();
+
+        // 主凭据占用 ID 2
+        let mut taken_ids = HashSet::from([2]);
+        let dirs = [
+            team_a.clone(),
+            root.path().join("missing"),
+            broken,
+            team_b.clone(),
+        ];
+        let creds = CredentialsConfig::load_from_dirs(&dirs, &mut taken_ids);
+        let summary: Vec<_> = creds
+            .iter()
+            .map(|c| (c.id, c.refresh_token.as_deref(), c.pool_id.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Some(1), Some("a1"), Some("team-a")),
+                (Some(3), Some("a2"), Some("team-a")),
+                (Some(4), Some("b1"), Some("vip")),
+            ]
+        );
+        assert_eq!(creds[0].source, Some(team_a.join("credentials.json")));
+        assert_eq!(creds[2].source, Some(team_b.join("credentials.json")));
+        assert_eq!(taken_ids, HashSet::from([1, 2, 3, 4, 5]));
+
+        // 重新分配的 ID 回写到来源文件，再次加载时 ID 保持不变
+        let team_a_ids: Vec<_> = CredentialsConfig::load(team_a.join("credentials.json"))
+            .unwrap()
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(team_a_ids, vec![Some(1), Some(3)]);
+        let mut taken_ids = HashSet::from([2]);
+        let reloaded = CredentialsConfig::load_from_dirs(&[team_a, team_b], &mut taken_ids);
+        let ids: Vec<_> = reloaded.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![Some(1), Some(3), Some(4)]);
+
+        // 来源文件不写入序列化结果
+        let serialized = serde_json::to_string(&reloaded[0]).unwrap();
+        assert!(!serialized.contains("source"));
+    }
 }
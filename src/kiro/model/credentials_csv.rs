@@ -0,0 +1,263 @@
+//! CSV 凭据导入
+//!
+//! 列定义（首行为表头，列顺序不限，除 `refresh_token` 外均可省略）：
+//! `refresh_token,auth_method,priority,region,pool_id,client_id,client_secret,notes`
+//!
+//! CLI 的 `credentials import --format csv` 与 Admin API 的
+//! `POST /api/admin/credentials/import`（`Content-Type: text/csv`）共用此解析逻辑。
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+use super::credentials::KiroCredentials;
+use crate::kiro::token_manager::validate_refresh_token;
+
+/// CSV 标准列
+pub const CSV_COLUMNS: &[&str] = &[
+    "refresh_token",
+    "auth_method",
+    "priority",
+    "region",
+    "pool_id",
+    "client_id",
+    "client_secret",
+    "notes",
+];
+
+/// CSV 数据行
+#[derive(Debug, Deserialize)]
+struct CsvCredentialRow {
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    auth_method: Option<String>,
+    #[serde(default)]
+    priority: Option<String>,
+    #[serde(default)]
+    region: Option<String>,
+    #[serde(default)]
+    pool_id: Option<String>,
+    #[serde(default)]
+    client_id: Option<String>,
+    #[serde(default)]
+    client_secret: Option<String>,
+    #[serde(default)]
+    notes: Option<String>,
+}
+
+/// 被跳过的行
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedRow {
+    /// 数据行号（不含表头，从 1 开始）
+    pub row: usize,
+    /// 跳过原因
+    pub reason: String,
+}
+
+/// CSV 解析结果
+#[derive(Debug, Default)]
+pub struct CsvCredentials {
+    /// 通过校验的凭据（数据行号, 凭据）
+    pub credentials: Vec<(usize, KiroCredentials)>,
+    /// 被跳过的行
+    pub skipped: Vec<SkippedRow>,
+}
+
+/// 解析 CSV 凭据
+///
+/// 表头缺少 `refresh_token` 列或包含未知列时返回错误；
+/// 单行校验失败时跳过该行并记录原因，不影响其他行
+pub fn parse_credentials_csv(content: &str) -> anyhow::Result<CsvCredentials> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+
+    let headers = reader.headers()?.clone();
+    if let Some(unknown) = headers.iter().find(|h| !CSV_COLUMNS.contains(h)) {
+        bail!(
+            "CSV 包含未知列: {}（支持的列: {}）",
+            unknown,
+            CSV_COLUMNS.join(",")
+        );
+    }
+    if !headers.iter().any(|h| h == "refresh_token") {
+        bail!("CSV 缺少 refresh_token 列");
+    }
+
+    let mut result = CsvCredentials::default();
+    for (index, row) in reader.deserialize::<CsvCredentialRow>().enumerate() {
+        let row_number = index + 1;
+        let parsed = row
+            .map_err(|e| describe_csv_error(&e))
+            .and_then(row_to_credentials);
+        match parsed {
+            Ok(Some(cred)) => result.credentials.push((row_number, cred)),
+            // 空 refresh_token 的行直接跳过（如空行、占位行）
+            Ok(None) => result.skipped.push(SkippedRow {
+                row: row_number,
+                reason: "refresh_token 为空".to_string(),
+            }),
+            Err(reason) => result.skipped.push(SkippedRow {
+                row: row_number,
+                reason,
+            }),
+        }
+    }
+
+    Ok(result)
+}
+
+/// 将数据行转换为凭据（refresh_token 为空时返回 None）
+fn row_to_credentials(row: CsvCredentialRow) -> Result<Option<KiroCredentials>, String> {
+    let Some(refresh_token) = row.refresh_token else {
+        return Ok(None);
+    };
+
+    let priority = match row.priority.as_deref() {
+        Some(p) => p
+            .parse::<u32>()
+            .map_err(|_| format!("priority 无效: {}", p))?,
+        None => 0,
+    };
+
+    // 未指定认证方式时：有 client_id 和 client_secret 则为 IdC，否则为 Social
+    let has_client = row.client_id.is_some() && row.client_secret.is_some();
+    let auth_method = match row.auth_method.as_deref().map(str::to_ascii_lowercase) {
+        None if has_client => "idc".to_string(),
+        None => "social".to_string(),
+        Some(m) if m == "social" => m,
+        Some(m) if m == "idc" || m == "builder-id" || m == "iam" => {
+            if !has_client {
+                return Err(format!("{} 认证需要 client_id 和 client_secret", m));
+            }
+            "idc".to_string()
+        }
+        Some(m) => return Err(format!("auth_method 无效: {}（支持 social/idc）", m)),
+    };
+
+    let cred = KiroCredentials {
+        refresh_token: Some(refresh_token),
+        auth_method: Some(auth_method),
+        priority,
+        region: row.region,
+        pool_id: row.pool_id,
+        client_id: row.client_id,
+        client_secret: row.client_secret,
+        notes: row.notes,
+        ..Default::default()
+    };
+
+    validate_refresh_token(&cred).map_err(|e| {
+        // 只保留首行，避免多行提示混入导入报告
+        e.to_string().lines().next().unwrap_or_default().to_string()
+    })?;
+
+    Ok(Some(cred))
+}
+
+/// 将 CSV 解析错误转换为可读原因
+fn describe_csv_error(error: &csv::Error) -> String {
+    match error.kind() {
+        csv::ErrorKind::UnequalLengths {
+            expected_len, len, ..
+        } => format!("列数不匹配: 期望 {} 列，实际 {} 列", expected_len, len),
+        csv::ErrorKind::Utf8 { .. } => "包含无效的 UTF-8 字符".to_string(),
+        _ => format!("解析失败: {}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(c: char) -> String {
+        c.to_string().repeat(120)
+    }
+
+    #[test]
+    fn test_parse_valid_and_invalid_rows() {
+        let csv = format!(
+            "refresh_token,auth_method,priority,region,pool_id,client_id,client_secret\n\
+             {a},social,1,us-east-1,premium,,\n\
+             {b},,,,,cid,secret\n\
+             {c},idc,,,,,\n\
+             ,social,0,,,,\n\
+             {d}...,social,,,,,\n\
+             short,social,,,,,\n\
+             {e},social,abc,,,,\n\
+             {f},social,0\n\
+             {g},oauth,,,,,\n",
+            a = token('a'),
+            b = token('b'),
+            c = token('c'),
+            d = token('d'),
+            e = token('e'),
+            f = token('f'),
+            g = token('g'),
+        );
+
+        let result = parse_credentials_csv(&csv).unwrap();
+
+        let rows: Vec<usize> = result.credentials.iter().map(|(row, _)| *row).collect();
+        assert_eq!(rows, vec![1, 2]);
+
+        let (_, social) = &result.credentials[0];
+        assert_eq!(social.refresh_token.as_deref(), Some(token('a').as_str()));
+        assert_eq!(social.auth_method.as_deref(), Some("social"));
+        assert_eq!(social.priority, 1);
+        assert_eq!(social.region.as_deref(), Some("us-east-1"));
+        assert_eq!(social.pool_id.as_deref(), Some("premium"));
+        assert_eq!(social.client_id, None);
+
+        // 未指定 auth_method 但有 client_id/client_secret 时推断为 IdC
+        let (_, idc) = &result.credentials[1];
+        assert_eq!(idc.auth_method.as_deref(), Some("idc"));
+        assert_eq!(idc.client_id.as_deref(), Some("cid"));
+        assert_eq!(idc.pool_id, None);
+
+        let skipped: Vec<usize> = result.skipped.iter().map(|s| s.row).collect();
+        assert_eq!(skipped, vec![3, 4, 5, 6, 7, 8, 9]);
+        let reason = |row: usize| {
+            result
+                .skipped
+                .iter()
+                .find(|s| s.row == row)
+                .unwrap()
+                .reason
+                .clone()
+        };
+        assert!(reason(3).contains("client_id"));
+        assert_eq!(reason(4), "refresh_token 为空");
+        assert!(reason(5).contains("截断"));
+        assert!(reason(6).contains("截断"));
+        assert!(reason(7).contains("priority"));
+        assert!(reason(8).contains("列数不匹配"));
+        assert!(reason(9).contains("auth_method"));
+    }
+
+    #[test]
+    fn test_parse_subset_of_columns() {
+        let csv = format!(
+            " region , refresh_token , notes \n eu-west-1 , {} , 测试环境 \n",
+            token('a')
+        );
+        let result = parse_credentials_csv(&csv).unwrap();
+        assert!(result.skipped.is_empty());
+
+        let (_, cred) = &result.credentials[0];
+        assert_eq!(cred.refresh_token.as_deref(), Some(token('a').as_str()));
+        assert_eq!(cred.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(cred.auth_method.as_deref(), Some("social"));
+        assert_eq!(cred.notes.as_deref(), Some("测试环境"));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_header() {
+        let err = parse_credentials_csv("auth_method,region\nsocial,us-east-1\n").unwrap_err();
+        assert!(err.to_string().contains("refresh_token"));
+
+        let err = parse_credentials_csv("refresh_token,token_type\nabc,x\n").unwrap_err();
+        assert!(err.to_string().contains("token_type"));
+    }
+}
@@ -0,0 +1,207 @@
+//! Kiro IDE 本地凭据缓存导入
+//!
+//! Kiro IDE 登录后把 Token 写入 AWS SSO 缓存目录（`~/.aws/sso/cache`）：
+//! - `kiro-auth-token.json`：accessToken / refreshToken / expiresAt / authMethod / region 等
+//! - `<clientIdHash>.json`：IdC 登录时的 OIDC 客户端注册信息（clientId / clientSecret）
+//!
+//! CLI 的 `credentials import --from-kiro-ide` 使用此模块自动发现并转换为 `KiroCredentials`。
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+
+use super::credentials::KiroCredentials;
+use crate::kiro::token_manager::validate_refresh_token;
+
+/// Kiro IDE Token 缓存文件名
+pub const KIRO_AUTH_TOKEN_FILE: &str = "kiro-auth-token.json";
+
+/// `kiro-auth-token.json` 内容
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KiroIdeToken {
+    #[serde(default)]
+    access_token: Option<String>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_at: Option<String>,
+    #[serde(default)]
+    auth_method: Option<String>,
+    #[serde(default)]
+    provider: Option<String>,
+    #[serde(default)]
+    profile_arn: Option<String>,
+    #[serde(default)]
+    region: Option<String>,
+    #[serde(default)]
+    client_id_hash: Option<String>,
+}
+
+/// `<clientIdHash>.json` 内容（OIDC 客户端注册）
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KiroIdeClientRegistration {
+    client_id: String,
+    client_secret: String,
+}
+
+/// 从 Kiro IDE 缓存发现的凭据
+#[derive(Debug)]
+pub struct KiroIdeCredential {
+    /// Token 缓存文件路径
+    pub source: PathBuf,
+    /// 登录提供方（Github / Google / BuilderId / Enterprise 等）
+    pub provider: Option<String>,
+    pub credentials: KiroCredentials,
+}
+
+/// 当前系统上 Kiro IDE 的凭据缓存目录
+///
+/// - macOS / Linux：`$HOME/.aws/sso/cache`
+/// - Windows：`%USERPROFILE%\.aws\sso\cache`
+pub fn default_cache_dirs() -> Vec<PathBuf> {
+    home_dir()
+        .map(|home| vec![home.join(".aws").join("sso").join("cache")])
+        .unwrap_or_default()
+}
+
+#[cfg(windows)]
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("USERPROFILE")
+        .or_else(|| {
+            let drive = std::env::var_os("HOMEDRIVE")?;
+            let path = std::env::var_os("HOMEPATH")?;
+            let mut home = drive;
+            home.push(path);
+            Some(home)
+        })
+        .map(PathBuf::from)
+}
+
+#[cfg(not(windows))]
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+/// 在缓存目录中查找 Kiro IDE 凭据（目录或 Token 文件不存在时返回 None）
+pub fn discover(cache_dir: &Path) -> anyhow::Result<Option<KiroIdeCredential>> {
+    let token_path = cache_dir.join(KIRO_AUTH_TOKEN_FILE);
+    if !token_path.is_file() {
+        return Ok(None);
+    }
+
+    let token_json = fs::read_to_string(&token_path)
+        .with_context(|| format!("读取 {} 失败", token_path.display()))?;
+    let token: KiroIdeToken = serde_json::from_str(&token_json)
+        .with_context(|| format!("解析 {} 失败", token_path.display()))?;
+
+    let registration = match &token.client_id_hash {
+        Some(hash) => {
+            let path = cache_dir.join(format!("{}.json", hash));
+            if path.is_file() {
+                let content = fs::read_to_string(&path)
+                    .with_context(|| format!("读取 {} 失败", path.display()))?;
+                Some(
+                    serde_json::from_str::<KiroIdeClientRegistration>(&content)
+                        .with_context(|| format!("解析 {} 失败", path.display()))?,
+                )
+            } else {
+                None
+            }
+        }
+        None => None,
+    };
+
+    let provider = token.provider.clone();
+    let credentials = convert(token, registration)?;
+    Ok(Some(KiroIdeCredential {
+        source: token_path,
+        provider,
+        credentials,
+    }))
+}
+
+/// 将 Kiro IDE 缓存格式转换为 `KiroCredentials`
+fn convert(
+    token: KiroIdeToken,
+    registration: Option<KiroIdeClientRegistration>,
+) -> anyhow::Result<KiroCredentials> {
+    // authMethod 为 social 之外的值（IdC / BuilderId / IAM）均走 IdC 刷新
+    let is_social = token
+        .auth_method
+        .as_deref()
+        .is_none_or(|m| m.eq_ignore_ascii_case("social"));
+
+    let mut credentials = KiroCredentials {
+        access_token: token.access_token,
+        refresh_token: token.refresh_token,
+        profile_arn: token.profile_arn,
+        expires_at: token.expires_at,
+        auth_method: Some(if is_social { "social" } else { "idc" }.to_string()),
+        region: token.region,
+        ..Default::default()
+    };
+    validate_refresh_token(&credentials)?;
+
+    if !is_social {
+        let Some(registration) = registration else {
+            bail!(
+                "IdC 凭据缺少客户端注册文件（<clientIdHash>.json），无法获取 clientId/clientSecret"
+            );
+        };
+        credentials.client_id = Some(registration.client_id);
+        credentials.client_secret = Some(registration.client_secret);
+    }
+
+    Ok(credentials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_dir(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/kiro_ide")
+            .join(name)
+    }
+
+    #[test]
+    fn test_discover_social_token() {
+        let found = discover(&fixture_dir("social")).unwrap().unwrap();
+        assert_eq!(found.provider.as_deref(), Some("Github"));
+
+        let cred = found.credentials;
+        assert_eq!(cred.auth_method.as_deref(), Some("social"));
+        assert_eq!(cred.region.as_deref(), Some("us-east-1"));
+        assert!(cred.refresh_token.unwrap().starts_with("aorAAAAAG"));
+        assert!(cred.profile_arn.is_some());
+        assert!(cred.client_id.is_none());
+    }
+
+    #[test]
+    fn test_discover_idc_token_with_registration() {
+        let found = discover(&fixture_dir("idc")).unwrap().unwrap();
+        assert_eq!(found.provider.as_deref(), Some("Enterprise"));
+
+        let cred = found.credentials;
+        assert_eq!(cred.auth_method.as_deref(), Some("idc"));
+        assert_eq!(cred.region.as_deref(), Some("eu-west-1"));
+        assert_eq!(cred.client_id.as_deref(), Some("fixture-client-id"));
+        assert_eq!(cred.client_secret.as_deref(), Some("fixture-client-secret"));
+    }
+
+    #[test]
+    fn test_discover_idc_without_registration_fails() {
+        let err = discover(&fixture_dir("idc_missing_registration")).unwrap_err();
+        assert!(err.to_string().contains("clientIdHash"));
+    }
+
+    #[test]
+    fn test_discover_missing_dir() {
+        assert!(discover(&fixture_dir("does-not-exist")).unwrap().is_none());
+    }
+}
@@ -5,12 +5,17 @@
 //! - `events`: 响应事件类型
 //! - `requests`: 请求类型
 //! - `credentials`: OAuth 凭证
+//! - `credentials_csv`: CSV 凭据导入
+//! - `kiro_ide`: Kiro IDE 本地凭据缓存导入
 //! - `token_refresh`: Token 刷新
 //! - `usage_limits`: 使用额度查询
 
 pub mod common;
 pub mod credentials;
+pub mod credentials_csv;
 pub mod events;
+#[allow(dead_code)] // 仅 kiro-cli 使用
+pub mod kiro_ide;
 pub mod requests;
 pub mod token_refresh;
 pub mod usage_limits;
@@ -126,8 +126,18 @@ impl EventStreamDecoder {
         }
     }
 
+    /// 创建指定缓冲区上限的解码器（`decoderBufferSizeBytes`）
+    ///
+    /// 待解析数据超过上限时 `feed` 返回 [`ParseError::BufferOverflow`]
+    pub fn new_with_config(max_buffer_size_bytes: usize) -> Self {
+        Self::with_config(
+            DEFAULT_BUFFER_CAPACITY,
+            DEFAULT_MAX_ERRORS,
+            max_buffer_size_bytes,
+        )
+    }
+
     /// 创建具有自定义配置的解码器
-    #[allow(dead_code)]
     pub fn with_config(capacity: usize, max_errors: usize, max_buffer_size: usize) -> Self {
         Self {
             buffer: BytesMut::with_capacity(capacity),
@@ -360,9 +370,9 @@ impl EventStreamDecoder {
         self.bytes_skipped
     }
 
-    /// 获取缓冲区中待处理的字节数
+    /// 获取缓冲区中待处理的字节数（用于监控）
     #[allow(dead_code)]
-    pub fn buffer_len(&self) -> usize {
+    pub fn current_buffer_size(&self) -> usize {
         self.buffer.len()
     }
 
@@ -420,7 +430,7 @@ mod tests {
     fn test_decoder_feed() {
         let mut decoder = EventStreamDecoder::new();
         assert!(decoder.feed(&[1, 2, 3, 4]).is_ok());
-        assert_eq!(decoder.buffer_len(), 4);
+        assert_eq!(decoder.current_buffer_size(), 4);
     }
 
     #[test]
@@ -430,6 +440,21 @@ mod tests {
         assert!(matches!(result, Err(ParseError::BufferOverflow { .. })));
     }
 
+    #[test]
+    fn test_decoder_default_limit_rejects_17mb_chunk() {
+        let mut decoder = EventStreamDecoder::new_with_config(DEFAULT_MAX_BUFFER_SIZE);
+        let chunk = vec![0u8; 17 * 1024 * 1024];
+        match decoder.feed(&chunk) {
+            Err(ParseError::BufferOverflow { size, max }) => {
+                assert_eq!(size, chunk.len());
+                assert_eq!(max, DEFAULT_MAX_BUFFER_SIZE);
+            }
+            other => panic!("expected BufferOverflow, got {:?}", other),
+        }
+        // 溢出的数据不写入缓冲区
+        assert_eq!(decoder.current_buffer_size(), 0);
+    }
+
     #[test]
     fn test_decoder_insufficient_data() {
         let mut decoder = EventStreamDecoder::new();
@@ -447,7 +472,7 @@ mod tests {
 
         decoder.reset();
         assert_eq!(decoder.state(), DecoderState::Ready);
-        assert_eq!(decoder.buffer_len(), 0);
+        assert_eq!(decoder.current_buffer_size(), 0);
         assert_eq!(decoder.frames_decoded(), 0);
     }
 
@@ -0,0 +1,169 @@
+//! 池性能历史
+//!
+//! 按分钟聚合池内上游调用的请求数、成功/失败数与平均延迟，保留最近 60 分钟，
+//! 用于 Admin 池详情展示近期趋势。数据仅保存在内存中，重启后清空。
+
+use std::collections::{BTreeMap, VecDeque};
+
+use chrono::{DateTime, TimeZone, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// 保留的分钟桶数量
+pub const PERFORMANCE_HISTORY_MINUTES: usize = 60;
+
+/// 单分钟性能统计（用于 API 响应）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceBucket {
+    /// 分钟起始时间
+    pub minute: DateTime<Utc>,
+    /// 请求数
+    pub request_count: u64,
+    /// 成功数
+    pub success_count: u64,
+    /// 失败数
+    pub failure_count: u64,
+    /// 平均延迟（毫秒）
+    pub avg_latency_ms: u64,
+}
+
+/// 合并多个池的分钟桶（按分钟累加，平均延迟按请求数加权），只保留分钟起始时间晚于 `since` 的桶
+pub fn merge_buckets(
+    buckets: impl IntoIterator<Item = PerformanceBucket>,
+    since: DateTime<Utc>,
+) -> Vec<PerformanceBucket> {
+    let mut merged: BTreeMap<DateTime<Utc>, PerformanceBucket> = BTreeMap::new();
+    for bucket in buckets.into_iter().filter(|b| b.minute > since) {
+        let total = merged.entry(bucket.minute).or_insert(PerformanceBucket {
+            minute: bucket.minute,
+            request_count: 0,
+            success_count: 0,
+            failure_count: 0,
+            avg_latency_ms: 0,
+        });
+        let total_latency = total.avg_latency_ms * total.request_count
+            + bucket.avg_latency_ms * bucket.request_count;
+        total.request_count += bucket.request_count;
+        total.success_count += bucket.success_count;
+        total.failure_count += bucket.failure_count;
+        total.avg_latency_ms = total_latency / total.request_count.max(1);
+    }
+    merged.into_values().collect()
+}
+
+/// 分钟桶累计值
+#[derive(Debug, Default)]
+struct MinuteBucket {
+    /// Unix 分钟数
+    minute: i64,
+    success_count: u64,
+    failure_count: u64,
+    total_latency_ms: u64,
+}
+
+impl MinuteBucket {
+    fn to_bucket(&self) -> PerformanceBucket {
+        let request_count = self.success_count + self.failure_count;
+        PerformanceBucket {
+            minute: Utc
+                .timestamp_opt(self.minute * 60, 0)
+                .single()
+                .unwrap_or_default(),
+            request_count,
+            success_count: self.success_count,
+            failure_count: self.failure_count,
+            avg_latency_ms: self.total_latency_ms / request_count.max(1),
+        }
+    }
+}
+
+/// 池性能历史（最近 60 个一分钟桶）
+#[derive(Debug, Default)]
+pub struct PoolPerformanceHistory {
+    buckets: Mutex<VecDeque<MinuteBucket>>,
+}
+
+impl PoolPerformanceHistory {
+    /// 记录一次上游调用结果
+    pub fn on_call_complete(&self, success: bool, latency_ms: u64) {
+        self.record_at(Utc::now(), success, latency_ms);
+    }
+
+    /// 最近 60 分钟内有调用的分钟桶（按时间升序）
+    pub fn snapshot(&self) -> Vec<PerformanceBucket> {
+        self.snapshot_at(Utc::now())
+    }
+
+    fn record_at(&self, now: DateTime<Utc>, success: bool, latency_ms: u64) {
+        let minute = now.timestamp().div_euclid(60);
+        let mut buckets = self.buckets.lock();
+        if buckets.back().is_none_or(|b| b.minute != minute) {
+            buckets.push_back(MinuteBucket {
+                minute,
+                ..Default::default()
+            });
+        }
+        Self::evict(&mut buckets, minute);
+
+        let bucket = buckets.back_mut().expect("当前分钟桶已创建");
+        if success {
+            bucket.success_count += 1;
+        } else {
+            bucket.failure_count += 1;
+        }
+        bucket.total_latency_ms += latency_ms;
+    }
+
+    fn snapshot_at(&self, now: DateTime<Utc>) -> Vec<PerformanceBucket> {
+        let minute = now.timestamp().div_euclid(60);
+        let mut buckets = self.buckets.lock();
+        Self::evict(&mut buckets, minute);
+        buckets.iter().map(MinuteBucket::to_bucket).collect()
+    }
+
+    /// 移除超出保留窗口的分钟桶
+    fn evict(buckets: &mut VecDeque<MinuteBucket>, current_minute: i64) {
+        let oldest = current_minute - PERFORMANCE_HISTORY_MINUTES as i64 + 1;
+        while buckets.front().is_some_and(|b| b.minute < oldest) {
+            buckets.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_buckets_per_minute_and_window() {
+        let history = PoolPerformanceHistory::default();
+        let start = Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 10).unwrap();
+
+        history.record_at(start, true, 100);
+        history.record_at(start + Duration::seconds(20), false, 300);
+        history.record_at(start + Duration::minutes(1), true, 50);
+
+        let buckets = history.snapshot_at(start + Duration::minutes(1));
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(
+            buckets[0].minute,
+            Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap()
+        );
+        assert_eq!(buckets[0].request_count, 2);
+        assert_eq!(buckets[0].failure_count, 1);
+        assert_eq!(buckets[0].avg_latency_ms, 200);
+        assert_eq!(buckets[1].request_count, 1);
+
+        // 超过 60 分钟的桶被移除
+        let buckets = history.snapshot_at(start + Duration::minutes(60));
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].avg_latency_ms, 50);
+        assert!(
+            history
+                .snapshot_at(start + Duration::minutes(61))
+                .is_empty()
+        );
+    }
+}
@@ -1,5 +1,7 @@
 //! 凭证池错误类型定义
 
+use crate::common::i18n::{ErrorCode, LocalizedError};
+
 /// 池操作错误
 #[derive(Debug, thiserror::Error)]
 pub enum PoolError {
@@ -7,6 +9,10 @@ pub enum PoolError {
     #[error("池不存在: {pool_id}")]
     PoolNotFound { pool_id: String },
 
+    /// 池已禁用
+    #[error("池已禁用: {pool_id}")]
+    PoolDisabled { pool_id: String },
+
     /// 池已存在
     #[error("池已存在: {pool_id}")]
     PoolAlreadyExists { pool_id: String },
@@ -15,6 +21,32 @@ pub enum PoolError {
     #[error("不能删除默认池")]
     CannotDeleteDefaultPool,
 
+    /// 池内仍有凭据（未指定重新分配目标）
+    #[error("池 {pool_id} 仍有凭据: {credential_ids:?}")]
+    PoolNotEmpty {
+        pool_id: String,
+        credential_ids: Vec<u64>,
+    },
+
+    /// 池仍被 API Key 绑定（未指定重新分配目标）
+    #[error("池 {pool_id} 仍被 API Key 绑定: {api_key_names:?}")]
+    PoolHasApiKeys {
+        pool_id: String,
+        api_key_names: Vec<String>,
+    },
+
+    /// 不能重命名默认池
+    #[error("不能重命名默认池")]
+    CannotRenameDefaultPool,
+
+    /// 池 ID 无效
+    #[error("池 ID 无效: {reason}")]
+    InvalidPoolId { reason: String },
+
+    /// 池配置无效（如超时覆盖超出允许范围）
+    #[error("池配置无效: {reason}")]
+    InvalidPoolConfig { reason: String },
+
     /// 凭据不存在
     #[error("凭据不存在: {credential_id}")]
     CredentialNotFound { credential_id: u64 },
@@ -23,6 +55,10 @@ pub enum PoolError {
     #[error("配置加载失败: {reason}")]
     ConfigLoadFailed { reason: String },
 
+    /// 持久化失败
+    #[error("持久化失败: {reason}")]
+    PersistFailed { reason: String },
+
     /// IO 错误
     #[error("IO 错误: {0}")]
     IoError(#[from] std::io::Error),
@@ -58,3 +94,49 @@ impl PoolError {
         matches!(self, PoolError::CannotDeleteDefaultPool)
     }
 }
+
+impl PoolError {
+    /// 错误码及参数（用于按客户端语言渲染错误响应）
+    pub fn localized(&self) -> LocalizedError {
+        match self {
+            PoolError::PoolNotFound { pool_id } => ErrorCode::PoolNotFound.arg("pool_id", pool_id),
+            PoolError::PoolDisabled { pool_id } => ErrorCode::PoolDisabled.arg("pool_id", pool_id),
+            PoolError::PoolAlreadyExists { pool_id } => {
+                ErrorCode::PoolAlreadyExists.arg("pool_id", pool_id)
+            }
+            PoolError::CannotDeleteDefaultPool => ErrorCode::CannotDeleteDefaultPool.into(),
+            PoolError::PoolNotEmpty {
+                pool_id,
+                credential_ids,
+            } => ErrorCode::PoolNotEmpty.arg("pool_id", pool_id).arg(
+                "credential_ids",
+                credential_ids
+                    .iter()
+                    .map(|id| format!("#{}", id))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            PoolError::PoolHasApiKeys {
+                pool_id,
+                api_key_names,
+            } => ErrorCode::PoolHasApiKeys
+                .arg("pool_id", pool_id)
+                .arg("api_keys", api_key_names.join(", ")),
+            PoolError::CannotRenameDefaultPool => ErrorCode::CannotRenameDefaultPool.into(),
+            PoolError::InvalidPoolId { reason } => ErrorCode::InvalidPoolId.arg("reason", reason),
+            PoolError::InvalidPoolConfig { reason } => {
+                ErrorCode::InvalidPoolConfig.arg("reason", reason)
+            }
+            PoolError::CredentialNotFound { credential_id } => {
+                ErrorCode::CredentialNotFound.arg("id", credential_id)
+            }
+            PoolError::ConfigLoadFailed { reason } => {
+                ErrorCode::ConfigLoadFailed.arg("reason", reason)
+            }
+            PoolError::PersistFailed { reason } => ErrorCode::PersistFailed.arg("reason", reason),
+            PoolError::IoError(e) => ErrorCode::PersistFailed.arg("reason", e),
+            PoolError::JsonError(e) => ErrorCode::InvalidJson.arg("detail", e),
+            PoolError::TokenManagerError(msg) => ErrorCode::TokenManagerError.arg("detail", msg),
+        }
+    }
+}
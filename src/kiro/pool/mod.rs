@@ -9,11 +9,15 @@ pub use error::PoolError;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::kiro::token_manager::SchedulingMode;
+use crate::http_client::ProxyConfig;
+use crate::kiro::token_manager::{CredentialDefaults, SchedulingMode};
 
 /// 默认池 ID
 pub const DEFAULT_POOL_ID: &str = "default";
 
+/// 隔离池 ID（`by_health` 重新分配时存放不健康的凭据）
+pub const QUARANTINE_POOL_ID: &str = "quarantine";
+
 /// 凭证池配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -53,6 +57,54 @@ pub struct Pool {
     #[serde(default)]
     pub priority: u32,
 
+    /// 池级会话缓存容量（可选，未设置时使用全局 sessionCacheMaxCapacity）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_cache_max_capacity: Option<u64>,
+
+    /// 池级会话缓存 TTL（秒，可选，未设置时使用全局 sessionCacheTtlSecs）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_cache_ttl_secs: Option<u64>,
+
+    /// 池级建立连接超时（秒，可选，未设置时使用全局 connectTimeoutSecs）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_secs: Option<u64>,
+
+    /// 池级 Token 刷新请求超时（秒，可选，未设置时使用全局 refreshRequestTimeoutSecs）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_request_timeout_secs: Option<u64>,
+
+    /// 池级上游首字节超时（秒，可选，未设置时使用全局 upstreamFirstByteTimeoutSecs）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_first_byte_timeout_secs: Option<u64>,
+
+    /// 溢出池 ID（可选，本池无可用凭据时将请求转到该池，溢出不传递）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overflow_pool_id: Option<String>,
+
+    /// 成员凭据的默认 Region（可选，凭据未配置 region 时使用，未设置时使用全局 region）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_region: Option<String>,
+
+    /// 成员凭据的默认认证方式（可选，social / idc，凭据未配置 authMethod 时使用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_auth_method: Option<String>,
+
+    /// 成员凭据的默认 machineId（可选，凭据未配置 machineId 时使用，优先于全局 machineId）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_machine_id: Option<String>,
+
+    /// 成员凭据的默认代理 URL（可选，凭据未配置代理时使用，优先于池级代理）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_proxy_url: Option<String>,
+
+    /// 成员凭据的默认代理用户名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_proxy_username: Option<String>,
+
+    /// 成员凭据的默认代理密码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_proxy_password: Option<String>,
+
     /// 创建时间
     pub created_at: DateTime<Utc>,
 }
@@ -74,6 +126,18 @@ impl Pool {
             proxy_username: None,
             proxy_password: None,
             priority: 0,
+            session_cache_max_capacity: None,
+            session_cache_ttl_secs: None,
+            connect_timeout_secs: None,
+            refresh_request_timeout_secs: None,
+            upstream_first_byte_timeout_secs: None,
+            overflow_pool_id: None,
+            default_region: None,
+            default_auth_method: None,
+            default_machine_id: None,
+            default_proxy_url: None,
+            default_proxy_username: None,
+            default_proxy_password: None,
             created_at: Utc::now(),
         }
     }
@@ -114,10 +178,88 @@ impl Pool {
         self
     }
 
+    /// 设置池级会话缓存容量和 TTL（None 表示使用全局配置）
+    pub fn with_session_cache(mut self, max_capacity: Option<u64>, ttl_secs: Option<u64>) -> Self {
+        self.session_cache_max_capacity = max_capacity;
+        self.session_cache_ttl_secs = ttl_secs;
+        self
+    }
+
+    /// 设置池级超时覆盖（None 表示使用全局配置）
+    pub fn with_timeouts(
+        mut self,
+        connect_secs: Option<u64>,
+        refresh_request_secs: Option<u64>,
+        upstream_first_byte_secs: Option<u64>,
+    ) -> Self {
+        self.connect_timeout_secs = connect_secs;
+        self.refresh_request_timeout_secs = refresh_request_secs;
+        self.upstream_first_byte_timeout_secs = upstream_first_byte_secs;
+        self
+    }
+
+    /// 设置溢出池
+    pub fn with_overflow_pool(mut self, pool_id: impl Into<String>) -> Self {
+        self.overflow_pool_id = Some(pool_id.into());
+        self
+    }
+
+    /// 设置成员凭据的默认 Region 和认证方式（None 表示不设置）
+    pub fn with_credential_defaults(
+        mut self,
+        region: Option<String>,
+        auth_method: Option<String>,
+    ) -> Self {
+        self.default_region = region;
+        self.default_auth_method = auth_method;
+        self
+    }
+
+    /// 设置成员凭据的默认 machineId
+    pub fn with_default_machine_id(mut self, machine_id: impl Into<String>) -> Self {
+        self.default_machine_id = Some(machine_id.into());
+        self
+    }
+
+    /// 设置成员凭据的默认代理
+    pub fn with_default_proxy(
+        mut self,
+        url: impl Into<String>,
+        username: Option<String>,
+        password: Option<String>,
+    ) -> Self {
+        self.default_proxy_url = Some(url.into());
+        self.default_proxy_username = username;
+        self.default_proxy_password = password;
+        self
+    }
+
     /// 检查是否配置了代理
     pub fn has_proxy(&self) -> bool {
         self.proxy_url.is_some()
     }
+
+    /// 成员凭据的默认设置（未设置默认代理时取池级代理）
+    pub fn credential_defaults(&self) -> CredentialDefaults {
+        let proxy = match &self.default_proxy_url {
+            Some(url) => Some(ProxyConfig {
+                url: url.clone(),
+                username: self.default_proxy_username.clone(),
+                password: self.default_proxy_password.clone(),
+            }),
+            None => self.proxy_url.as_ref().map(|url| ProxyConfig {
+                url: url.clone(),
+                username: self.proxy_username.clone(),
+                password: self.proxy_password.clone(),
+            }),
+        };
+        CredentialDefaults {
+            region: self.default_region.clone(),
+            auth_method: self.default_auth_method.clone(),
+            proxy,
+            machine_id: self.default_machine_id.clone(),
+        }
+    }
 }
 
 impl Default for Pool {
@@ -156,9 +298,10 @@ impl PoolsConfig {
     }
 
     /// 保存池配置到文件
+    #[allow(dead_code)]
     pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), PoolError> {
         let content = serde_json::to_string_pretty(self)?;
-        std::fs::write(path, content)?;
+        crate::common::io::atomic_write(path, content)?;
         Ok(())
     }
 
@@ -209,6 +352,8 @@ pub struct PoolSnapshot {
     pub has_proxy: bool,
     /// 优先级
     pub priority: u32,
+    /// 溢出池 ID
+    pub overflow_pool_id: Option<String>,
     /// 创建时间
     pub created_at: DateTime<Utc>,
     /// 凭据总数
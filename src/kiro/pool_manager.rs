@@ -7,15 +7,28 @@
 //!
 //! 支持 API Key 绑定到特定池，实现请求路由
 
-use parking_lot::RwLock;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-
-use crate::http_client::ProxyConfig;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use tokio::sync::broadcast;
+
+use crate::admin::api_keys::ApiKeyManager;
+use crate::admin::events::AdminEvent;
+use crate::common::atomic_file::FileTransaction;
+use crate::common::persist::{Change, ChangeJournal, PersistWriter};
+use crate::http_client::{self, ProxyConfig};
+use crate::kiro::machine_id;
 use crate::kiro::model::credentials::{CredentialsConfig, KiroCredentials};
-use crate::kiro::pool::{Pool, PoolError, PoolsConfig, DEFAULT_POOL_ID};
-use crate::kiro::token_manager::{MultiTokenManager, SchedulingMode};
+use crate::kiro::performance::{PerformanceBucket, PoolPerformanceHistory, merge_buckets};
+use crate::kiro::pool::{DEFAULT_POOL_ID, Pool, PoolError, PoolsConfig, QUARANTINE_POOL_ID};
+use crate::kiro::provider::KiroProvider;
+use crate::kiro::token_manager::{CredentialEntrySnapshot, MultiTokenManager, SchedulingMode};
+use crate::kiro::warmup::WarmupReport;
 use crate::model::config::Config;
 
 /// 池运行时状态
@@ -25,11 +38,38 @@ pub struct PoolRuntime {
     /// Token 管理器
     pub token_manager: Arc<MultiTokenManager>,
     /// 池级代理配置（已解析）
-    #[allow(dead_code)]
     pub proxy_config: Option<ProxyConfig>,
+    /// 性能历史（最近 60 分钟，重新加载后保留）
+    pub performance: Arc<PoolPerformanceHistory>,
+    /// 该池的 KiroProvider（所有请求共用，代理变更时重建）
+    pub provider: Arc<KiroProvider>,
+    /// 自动调整后的有效优先级（只保存在内存中，不写入池配置；`None` 表示使用配置的优先级）
+    pub effective_priority: Option<u32>,
 }
 
 impl PoolRuntime {
+    /// 创建池运行时，同时构建使用池级代理的 KiroProvider
+    fn new(
+        config: Pool,
+        token_manager: MultiTokenManager,
+        proxy_config: Option<ProxyConfig>,
+        performance: Arc<PoolPerformanceHistory>,
+    ) -> Self {
+        let token_manager = Arc::new(token_manager);
+        let provider = Arc::new(KiroProvider::with_proxy(
+            token_manager.clone(),
+            proxy_config.clone(),
+        ));
+        Self {
+            config,
+            token_manager,
+            proxy_config,
+            performance,
+            provider,
+            effective_priority: None,
+        }
+    }
+
     /// 获取池 ID
     #[allow(dead_code)]
     pub fn id(&self) -> &str {
@@ -41,6 +81,11 @@ impl PoolRuntime {
         self.config.enabled
     }
 
+    /// 自动路由使用的优先级（自动调整结果优先于配置的优先级）
+    pub fn priority(&self) -> u32 {
+        self.effective_priority.unwrap_or(self.config.priority)
+    }
+
     /// 获取调度模式
     #[allow(dead_code)]
     pub fn scheduling_mode(&self) -> SchedulingMode {
@@ -48,6 +93,17 @@ impl PoolRuntime {
     }
 }
 
+/// 共享的池运行时（每个池独立加读写锁）
+pub type SharedPool = Arc<RwLock<PoolRuntime>>;
+
+/// 为请求选择的池
+pub struct PoolSelection {
+    /// 实际服务的池
+    pub pool: SharedPool,
+    /// 发生溢出时为原池 ID
+    pub overflow_from: Option<String>,
+}
+
 /// 池管理器
 ///
 /// 管理所有凭证池的生命周期和请求路由
@@ -57,11 +113,29 @@ pub struct PoolManager {
     /// 全局代理配置
     global_proxy: Option<ProxyConfig>,
     /// 池运行时映射 (pool_id -> PoolRuntime)
-    pools: RwLock<HashMap<String, Arc<PoolRuntime>>>,
+    ///
+    /// 读取池只持有该池的读锁，更新池只持有该池的写锁，不同池的操作互不阻塞
+    pools: DashMap<String, SharedPool>,
+    /// 结构性变更锁（重新加载、创建、删除、重命名、重新分配），不阻塞单个池的读写
+    structure_lock: Mutex<()>,
     /// 池配置文件路径
     pools_path: PathBuf,
     /// 凭据配置文件路径
     credentials_path: PathBuf,
+    /// 额外凭据目录（`credentialDirs`，相对于配置文件所在目录）
+    credential_dirs: Vec<PathBuf>,
+    /// 池配置文件写入器
+    pools_writer: Arc<PersistWriter>,
+    /// Admin 事件发布通道（重新加载后自动挂载到新的 Token 管理器）
+    event_sender: RwLock<Option<broadcast::Sender<AdminEvent>>>,
+    /// 自动路由选中各池的次数 (pool_id -> count)
+    auto_route_decisions: DashMap<String, AtomicU64>,
+    /// 自动路由最近一次选中各池的时间
+    last_selected_at: DashMap<String, Instant>,
+    /// 自动路由成功选中池的总次数
+    route_count_total: AtomicU64,
+    /// 自动路由找不到可用池的次数
+    miss_count_total: AtomicU64,
 }
 
 impl PoolManager {
@@ -80,13 +154,23 @@ impl PoolManager {
     ) -> Result<Self, PoolError> {
         let pools_path = pools_path.as_ref().to_path_buf();
         let credentials_path = credentials_path.as_ref().to_path_buf();
+        let credential_dirs =
+            global_config.credential_dir_paths(pools_path.parent().unwrap_or(Path::new(".")));
 
         let manager = Self {
             global_config,
             global_proxy,
-            pools: RwLock::new(HashMap::new()),
+            pools: DashMap::new(),
+            structure_lock: Mutex::new(()),
+            pools_writer: PersistWriter::for_path(&pools_path),
             pools_path,
             credentials_path,
+            credential_dirs,
+            event_sender: RwLock::new(None),
+            auto_route_decisions: DashMap::new(),
+            last_selected_at: DashMap::new(),
+            route_count_total: AtomicU64::new(0),
+            miss_count_total: AtomicU64::new(0),
         };
 
         // 加载池和凭据
@@ -97,6 +181,8 @@ impl PoolManager {
 
     /// 重新加载池和凭据配置
     pub fn reload(&self) -> Result<(), PoolError> {
+        let _structure = self.structure_lock.lock();
+
         // 加载池配置
         let mut pools_config = PoolsConfig::load(&self.pools_path).map_err(|e| {
             PoolError::ConfigLoadFailed {
@@ -107,67 +193,261 @@ impl PoolManager {
         // 确保默认池存在
         pools_config.ensure_default_pool();
 
-        // 加载凭据配置
-        let credentials_config =
-            CredentialsConfig::load(&self.credentials_path).map_err(|e| {
-                PoolError::ConfigLoadFailed {
-                    reason: format!("加载凭据配置失败: {}", e),
-                }
-            })?;
-        let all_credentials = credentials_config.into_sorted_credentials();
-
         // 按 pool_id 分组凭据
-        let mut credentials_by_pool: HashMap<String, Vec<KiroCredentials>> = HashMap::new();
-        for cred in all_credentials {
-            let pool_id = cred.pool_id.clone().unwrap_or_else(|| DEFAULT_POOL_ID.to_string());
-            credentials_by_pool.entry(pool_id).or_default().push(cred);
-        }
+        let pool_ids: Vec<&str> = pools_config.pools.iter().map(|p| p.id.as_str()).collect();
+        let mut credentials_by_pool = self.load_credentials_by_pool(&pool_ids)?;
 
         // 为每个池创建运行时
         let mut new_pools = HashMap::new();
         for pool in pools_config.pools {
             let pool_id = pool.id.clone();
             let credentials = credentials_by_pool.remove(&pool_id).unwrap_or_default();
+            new_pools.insert(pool_id, self.build_runtime(pool, credentials)?);
+        }
 
-            // 解析池级代理配置
-            let pool_proxy = self.resolve_pool_proxy(&pool);
+        // 更新池映射：已有池原地替换运行时（持有者随即看到新状态），再增删池
+        self.pools
+            .retain(|pool_id, _| new_pools.contains_key(pool_id));
+        for (pool_id, runtime) in new_pools {
+            match self.get_pool(&pool_id) {
+                Some(pool) => *pool.write() = runtime,
+                None => {
+                    self.pools.insert(pool_id, Arc::new(RwLock::new(runtime)));
+                }
+            }
+        }
 
-            // 创建 Token 管理器
-            let token_manager = MultiTokenManager::new(
-                self.global_config.clone(),
-                credentials,
-                pool_proxy.clone(),
-                Some(self.credentials_path.clone()),
-            )
-            .map_err(|e| PoolError::TokenManagerError(e.to_string()))?;
+        Ok(())
+    }
 
-            // 设置调度模式
-            token_manager.set_scheduling_mode(pool.scheduling_mode);
+    /// 只重新加载单个池（如池级超时变更后），其他池的运行时不受影响
+    ///
+    /// 先经写入线程回写该池内存中的凭据，再从文件加载该池的凭据并按当前池配置重建运行时
+    fn reload_pool(&self, pool_id: &str) -> Result<(), PoolError> {
+        let _structure = self.structure_lock.lock();
+        let pool = self
+            .get_pool(pool_id)
+            .ok_or_else(|| PoolError::PoolNotFound {
+                pool_id: pool_id.to_string(),
+            })?;
+        let (config, token_manager) = {
+            let runtime = pool.read();
+            (runtime.config.clone(), runtime.token_manager.clone())
+        };
+        token_manager
+            .flush_credentials()
+            .map_err(|e| PoolError::PersistFailed {
+                reason: format!("{:#}", e),
+            })?;
 
-            let runtime = PoolRuntime {
-                config: pool,
-                token_manager: Arc::new(token_manager),
-                proxy_config: pool_proxy,
-            };
+        let pool_ids: Vec<String> = self.pools.iter().map(|p| p.key().clone()).collect();
+        let pool_ids: Vec<&str> = pool_ids.iter().map(String::as_str).collect();
+        let credentials = self
+            .load_credentials_by_pool(&pool_ids)?
+            .remove(pool_id)
+            .unwrap_or_default();
+        let runtime = self.build_runtime(config, credentials)?;
+        *pool.write() = runtime;
+        Ok(())
+    }
 
-            new_pools.insert(pool_id, Arc::new(runtime));
+    /// 从凭据文件（及额外凭据目录）加载凭据并按池分组
+    ///
+    /// 引用不存在的池的凭据由默认池接管，避免凭据"消失"；
+    /// 凭据的 poolId 保持不变，池恢复后重新加载即回到原池
+    fn load_credentials_by_pool(
+        &self,
+        pool_ids: &[&str],
+    ) -> Result<HashMap<String, Vec<KiroCredentials>>, PoolError> {
+        let credentials_config = CredentialsConfig::load(&self.credentials_path).map_err(|e| {
+            PoolError::ConfigLoadFailed {
+                reason: format!("加载凭据配置失败: {}", e),
+            }
+        })?;
+        let mut all_credentials = credentials_config.into_sorted_credentials();
+
+        // 额外凭据目录中的凭据（回写到各自的凭据文件）
+        if !self.credential_dirs.is_empty() {
+            let mut taken_ids = all_credentials.iter().filter_map(|c| c.id).collect();
+            all_credentials.extend(CredentialsConfig::load_from_dirs(
+                &self.credential_dirs,
+                &mut taken_ids,
+            ));
+            all_credentials.sort_by_key(|c| c.priority);
         }
 
-        // 处理没有对应池的凭据（归入默认池）
-        if let Some(orphan_credentials) = credentials_by_pool.remove(DEFAULT_POOL_ID) {
-            if new_pools.contains_key(DEFAULT_POOL_ID) {
-                // 默认池已存在，需要合并凭据
-                // 这种情况不应该发生，因为我们已经处理过了
+        let mut credentials_by_pool: HashMap<String, Vec<KiroCredentials>> = HashMap::new();
+        for cred in all_credentials {
+            let mut pool_id = cred
+                .pool_id
+                .clone()
+                .unwrap_or_else(|| DEFAULT_POOL_ID.to_string());
+            if !pool_ids.contains(&pool_id.as_str()) {
                 tracing::warn!(
-                    "发现 {} 个孤儿凭据，但默认池已存在",
-                    orphan_credentials.len()
+                    "凭据 #{} 引用了不存在的池 {}，由默认池接管",
+                    cred.id.map_or_else(|| "?".to_string(), |id| id.to_string()),
+                    pool_id
                 );
+                pool_id = DEFAULT_POOL_ID.to_string();
             }
+            credentials_by_pool.entry(pool_id).or_default().push(cred);
+        }
+        Ok(credentials_by_pool)
+    }
+
+    /// 按池配置创建运行时（沿用已有池的性能历史和凭据调用时间线）
+    fn build_runtime(
+        &self,
+        pool: Pool,
+        credentials: Vec<KiroCredentials>,
+    ) -> Result<PoolRuntime, PoolError> {
+        let pool_id = pool.id.clone();
+        self.validate_pool_overrides(&pool)
+            .map_err(|e| PoolError::ConfigLoadFailed {
+                reason: format!("池 {}: {}", pool_id, e),
+            })?;
+
+        // 解析池级代理配置
+        let pool_proxy = self.resolve_pool_proxy(&pool);
+
+        // 创建 Token 管理器
+        let token_manager = MultiTokenManager::with_credential_defaults(
+            self.pool_config(&pool),
+            credentials,
+            pool.credential_defaults(),
+            pool_proxy.clone(),
+            Some(self.credentials_path.clone()),
+        )
+        .map_err(|e| PoolError::TokenManagerError(e.to_string()))?;
+
+        // 设置调度模式
+        token_manager.set_scheduling_mode(pool.scheduling_mode);
+
+        // 挂载 Admin 事件通道
+        if let Some(sender) = self.event_sender.read().as_ref() {
+            token_manager.set_event_sender(sender.clone(), pool_id.clone());
+        }
+
+        // 沿用已有池的性能历史
+        let performance = self
+            .with_pool(&pool_id, |p| p.performance.clone())
+            .unwrap_or_default();
+        attach_performance(&token_manager, &performance);
+
+        // 沿用各池已有的调用时间线（凭据可能在重新加载后换池）
+        for existing in self.all_pools() {
+            token_manager.inherit_call_timeline(&existing.read().token_manager);
+        }
+
+        Ok(PoolRuntime::new(
+            pool,
+            token_manager,
+            pool_proxy,
+            performance,
+        ))
+    }
+
+    /// 预热所有启用池的凭据，返回 (池 ID, 预热报告) 列表（按池 ID 排序）
+    ///
+    /// 各池依次预热，池内并发数受 `warmupConcurrency` 限制
+    pub async fn warm_up(&self) -> Vec<(String, WarmupReport)> {
+        let mut pools: Vec<(String, Arc<MultiTokenManager>)> = self
+            .all_pools()
+            .iter()
+            .filter_map(|pool| {
+                let pool = pool.read();
+                pool.is_enabled()
+                    .then(|| (pool.config.id.clone(), pool.token_manager.clone()))
+            })
+            .collect();
+        pools.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut reports = Vec::with_capacity(pools.len());
+        for (pool_id, token_manager) in pools {
+            let report = token_manager.warm_up().await.clone();
+            reports.push((pool_id, report));
+        }
+        reports
+    }
+
+    /// 设置 Admin 事件发布通道
+    ///
+    /// 挂载到所有池的 Token 管理器，后续重新加载创建的管理器也会自动挂载
+    pub fn set_event_sender(&self, sender: broadcast::Sender<AdminEvent>) {
+        for pool in self.all_pools() {
+            let runtime = pool.read();
+            runtime
+                .token_manager
+                .set_event_sender(sender.clone(), runtime.config.id.clone());
+        }
+        *self.event_sender.write() = Some(sender);
+    }
+
+    /// 池的 Token 管理器配置（全局配置叠加池级会话缓存和超时覆盖）
+    fn pool_config(&self, pool: &Pool) -> Config {
+        let mut config = self.global_config.clone();
+        if let Some(capacity) = pool.session_cache_max_capacity {
+            config.session_cache_max_capacity = capacity;
+        }
+        if let Some(ttl) = pool.session_cache_ttl_secs {
+            config.session_cache_ttl_secs = ttl;
+        }
+        if let Some(secs) = pool.connect_timeout_secs {
+            config.connect_timeout_secs = secs;
+        }
+        if let Some(secs) = pool.refresh_request_timeout_secs {
+            config.refresh_request_timeout_secs = secs;
         }
+        if let Some(secs) = pool.upstream_first_byte_timeout_secs {
+            config.upstream_first_byte_timeout_secs = secs;
+        }
+        config
+    }
 
-        // 更新池映射
-        *self.pools.write() = new_pools;
+    /// 校验池级超时和会话缓存容量覆盖是否在允许范围内
+    fn validate_pool_overrides(&self, pool: &Pool) -> Result<(), PoolError> {
+        let config = self.pool_config(pool);
+        let mut errors = config.timeout_errors();
+        if let Err(e) = Config::validate_session_cache_capacity(config.session_cache_max_capacity) {
+            errors.push(e.to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(PoolError::InvalidPoolConfig {
+                reason: errors.join("; "),
+            })
+        }
+    }
 
+    /// 校验成员凭据的默认设置
+    fn validate_credential_defaults(pool: &Pool) -> Result<(), PoolError> {
+        if let Some(auth_method) = pool.default_auth_method.as_deref()
+            && !["social", "idc"].contains(&auth_method)
+        {
+            return Err(PoolError::InvalidPoolConfig {
+                reason: format!("defaultAuthMethod 只能为 social 或 idc: {}", auth_method),
+            });
+        }
+        if let Some(region) = pool.default_region.as_deref()
+            && !region
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return Err(PoolError::InvalidPoolConfig {
+                reason: format!("defaultRegion 包含非法字符: {}", region),
+            });
+        }
+        if let Some(machine_id) = pool.default_machine_id.as_deref()
+            && machine_id::normalize_machine_id(machine_id).is_none()
+        {
+            return Err(PoolError::InvalidPoolConfig {
+                reason: format!(
+                    "defaultMachineId 应为 64 位十六进制字符串或 UUID: {}",
+                    machine_id
+                ),
+            });
+        }
         Ok(())
     }
 
@@ -186,13 +466,33 @@ impl PoolManager {
     }
 
     /// 获取池（按 ID）
-    pub fn get_pool(&self, pool_id: &str) -> Option<Arc<PoolRuntime>> {
-        self.pools.read().get(pool_id).cloned()
+    pub fn get_pool(&self, pool_id: &str) -> Option<SharedPool> {
+        self.pools.get(pool_id).map(|entry| entry.value().clone())
+    }
+
+    /// 持有池的读锁执行 `f`（池不存在时返回 None）
+    ///
+    /// 只锁定该池，其他池的读写不受影响
+    pub fn with_pool<F, R>(&self, pool_id: &str, f: F) -> Option<R>
+    where
+        F: FnOnce(&PoolRuntime) -> R,
+    {
+        let pool = self.get_pool(pool_id)?;
+        let runtime = pool.read();
+        Some(f(&runtime))
+    }
+
+    /// 所有池（先复制出引用再逐个加锁，遍历时不持有映射的分片锁）
+    fn all_pools(&self) -> Vec<SharedPool> {
+        self.pools
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
     }
 
     /// 获取默认池
     #[allow(dead_code)]
-    pub fn get_default_pool(&self) -> Option<Arc<PoolRuntime>> {
+    pub fn get_default_pool(&self) -> Option<SharedPool> {
         self.get_pool(DEFAULT_POOL_ID)
     }
 
@@ -204,12 +504,12 @@ impl PoolManager {
     /// - pool_id 为 None：返回默认池
     /// - pool_id 为 "__auto__"：自动路由，按池优先级选择有可用凭据的池
     /// - pool_id 为其他值：返回指定池（如果存在且启用）
-    pub fn get_pool_for_api_key(&self, pool_id: Option<&str>) -> Option<Arc<PoolRuntime>> {
+    pub fn get_pool_for_api_key(&self, pool_id: Option<&str>) -> Option<SharedPool> {
         match pool_id {
             None => {
                 // 未绑定池，使用默认池
                 let pool = self.get_pool(DEFAULT_POOL_ID)?;
-                if pool.is_enabled() {
+                if pool.read().is_enabled() {
                     Some(pool)
                 } else {
                     tracing::warn!("默认池已禁用");
@@ -223,7 +523,7 @@ impl PoolManager {
             Some(pool_id) => {
                 // 绑定特定池
                 let pool = self.get_pool(pool_id)?;
-                if pool.is_enabled() {
+                if pool.read().is_enabled() {
                     Some(pool)
                 } else {
                     tracing::warn!(pool_id = %pool_id, "池已禁用");
@@ -233,44 +533,235 @@ impl PoolManager {
         }
     }
 
+    /// 根据 API Key 绑定的池列表按顺序回退选择池
+    ///
+    /// - 列表为空：返回默认池
+    /// - 按顺序选择第一个存在、已启用且有可用凭据的池（"__auto__" 按自动路由处理）
+    /// - 都没有可用凭据时返回第一个已启用的池，由凭据层报告具体错误
+    pub fn get_pool_for_api_key_ordered(&self, pool_ids: &[String]) -> Option<SharedPool> {
+        if pool_ids.is_empty() {
+            return self.get_pool_for_api_key(None);
+        }
+
+        let mut first_enabled = None;
+        for pool_id in pool_ids {
+            if pool_id == Self::AUTO_ROUTE_POOL_ID {
+                if let Some(pool) = self.auto_route_pool() {
+                    return Some(pool);
+                }
+                continue;
+            }
+
+            let Some(pool) = self.get_pool_for_api_key(Some(pool_id)) else {
+                continue;
+            };
+            if Self::has_available_credentials(&pool.read()) {
+                return Some(pool);
+            }
+            tracing::debug!(pool_id = %pool_id, "池无可用凭据，尝试下一个绑定的池");
+            first_enabled.get_or_insert(pool);
+        }
+
+        first_enabled
+    }
+
+    /// 根据 API Key 绑定的池列表选择池，池无可用凭据时溢出到其配置的溢出池
+    ///
+    /// 先按 [`Self::get_pool_for_api_key_ordered`] 选出池；若该池没有可用凭据，
+    /// 且配置的溢出池存在、已启用并有可用凭据，则改由溢出池服务。
+    /// 溢出不传递（溢出池自身的溢出配置被忽略），粘性会话绑定在溢出池的 Token 管理器上
+    pub fn select_pool_for_api_key(&self, pool_ids: &[String]) -> Option<PoolSelection> {
+        let pool = self.get_pool_for_api_key_ordered(pool_ids)?;
+        let pool_id = pool.read().config.id.clone();
+        let Some(overflow) = self.overflow_pool(&pool) else {
+            return Some(PoolSelection {
+                pool,
+                overflow_from: None,
+            });
+        };
+
+        tracing::warn!(
+            pool_id = %pool_id,
+            overflow_pool_id = %overflow.read().config.id,
+            "池无可用凭据，请求溢出到溢出池"
+        );
+        Some(PoolSelection {
+            pool: overflow,
+            overflow_from: Some(pool_id),
+        })
+    }
+
+    /// 池无可用凭据时返回可接收溢出的溢出池
+    fn overflow_pool(&self, pool: &SharedPool) -> Option<SharedPool> {
+        let overflow_id = {
+            let pool = pool.read();
+            let overflow_id = pool.config.overflow_pool_id.clone()?;
+            if overflow_id == pool.config.id || Self::has_available_credentials(&pool) {
+                return None;
+            }
+            overflow_id
+        };
+        let overflow = self.get_pool(&overflow_id)?;
+        let available = {
+            let runtime = overflow.read();
+            runtime.is_enabled() && Self::has_available_credentials(&runtime)
+        };
+        available.then_some(overflow)
+    }
+
+    /// 校验溢出池配置：不能指向自身，且目标池必须存在
+    fn validate_overflow_pool(
+        pools: &DashMap<String, SharedPool>,
+        pool: &Pool,
+    ) -> Result<(), PoolError> {
+        let Some(overflow_id) = pool.overflow_pool_id.as_deref() else {
+            return Ok(());
+        };
+        if overflow_id == pool.id {
+            return Err(PoolError::InvalidPoolId {
+                reason: "溢出池不能是池自身".to_string(),
+            });
+        }
+        if !pools.contains_key(overflow_id) {
+            return Err(PoolError::PoolNotFound {
+                pool_id: overflow_id.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// 池是否有可用凭据
+    fn has_available_credentials(pool: &PoolRuntime) -> bool {
+        pool.token_manager.snapshot().available > 0
+    }
+
     /// 自动路由：按池优先级选择有可用凭据的池
     ///
     /// 遍历所有启用的池（按 priority 排序），返回第一个有可用凭据的池
-    fn auto_route_pool(&self) -> Option<Arc<PoolRuntime>> {
-        let pools = self.pools.read();
-
+    fn auto_route_pool(&self) -> Option<SharedPool> {
         // 收集所有启用的池并按优先级排序
-        let mut enabled_pools: Vec<_> = pools
-            .values()
-            .filter(|p| p.is_enabled())
-            .cloned()
+        let mut enabled_pools: Vec<(u32, String, SharedPool)> = self
+            .all_pools()
+            .into_iter()
+            .filter_map(|pool| {
+                let (enabled, priority, pool_id) = {
+                    let runtime = pool.read();
+                    (
+                        runtime.is_enabled(),
+                        runtime.priority(),
+                        runtime.config.id.clone(),
+                    )
+                };
+                enabled.then_some((priority, pool_id, pool))
+            })
             .collect();
 
-        enabled_pools.sort_by_key(|p| p.config.priority);
+        enabled_pools.sort_by_key(|(priority, _, _)| *priority);
 
         // 按优先级遍历，找到第一个有可用凭据的池
-        for pool in enabled_pools {
-            let snapshot = pool.token_manager.snapshot();
-            if snapshot.available > 0 {
-                tracing::debug!(
-                    pool_id = %pool.config.id,
-                    available = snapshot.available,
-                    "自动路由选择池"
-                );
+        for (_, pool_id, pool) in enabled_pools {
+            if Self::has_available_credentials(&pool.read()) {
+                tracing::debug!(pool_id = %pool_id, "自动路由选择池");
+                self.record_auto_route(&pool_id);
                 return Some(pool);
             }
         }
 
+        self.miss_count_total.fetch_add(1, Ordering::Relaxed);
         tracing::warn!("自动路由：所有池都没有可用凭据");
         None
     }
 
+    /// 记录一次自动路由决策
+    fn record_auto_route(&self, pool_id: &str) {
+        self.auto_route_decisions
+            .entry(pool_id.to_string())
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(1, Ordering::Relaxed);
+        self.last_selected_at
+            .insert(pool_id.to_string(), Instant::now());
+        self.route_count_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 自动路由统计（各池按 ID 排序）
+    pub fn routing_stats(&self) -> RoutingStats {
+        let now = Utc::now();
+        let mut pools: Vec<PoolRoutingStat> = self
+            .auto_route_decisions
+            .iter()
+            .map(|entry| PoolRoutingStat {
+                pool_id: entry.key().clone(),
+                auto_route_count: entry.value().load(Ordering::Relaxed),
+                last_selected_at: self.last_selected_at.get(entry.key()).and_then(|at| {
+                    chrono::Duration::from_std(at.elapsed())
+                        .ok()
+                        .map(|elapsed| now - elapsed)
+                }),
+            })
+            .collect();
+        pools.sort_by(|a, b| a.pool_id.cmp(&b.pool_id));
+
+        RoutingStats {
+            route_count_total: self.route_count_total.load(Ordering::Relaxed),
+            miss_count_total: self.miss_count_total.load(Ordering::Relaxed),
+            pools,
+        }
+    }
+
+    /// 按可用凭据比例自动调整池优先级，返回发生变化的 (池 ID, 原优先级, 新优先级)
+    ///
+    /// 只调整优先级落在 [`auto_priority_min`, `auto_priority_max`] 范围内的池：
+    /// 可用比例为 1 的池取下限，没有可用凭据（或没有凭据）的池取上限，其余按比例线性插值。
+    /// 范围按配置的优先级判断，调整结果写入 [`PoolRuntime::effective_priority`]，
+    /// 不修改池配置，重新加载后由下一次调整重新计算
+    pub fn auto_adjust_priorities(&self) -> Vec<(String, u32, u32)> {
+        let min = self.global_config.auto_priority_min;
+        let max = self.global_config.auto_priority_max;
+        if min > max {
+            return Vec::new();
+        }
+
+        let mut changes = Vec::new();
+        for pool in self.all_pools() {
+            let mut runtime = pool.write();
+            let pool_id = runtime.config.id.clone();
+            if !(min..=max).contains(&runtime.config.priority) {
+                continue;
+            }
+            let current = runtime.priority();
+
+            let snapshot = runtime.token_manager.snapshot();
+            let ratio = if snapshot.total == 0 {
+                0.0
+            } else {
+                snapshot.available as f64 / snapshot.total as f64
+            };
+            let priority = max - ((max - min) as f64 * ratio).round() as u32;
+            if priority == current {
+                continue;
+            }
+
+            tracing::debug!(
+                pool_id = %pool_id,
+                available = snapshot.available,
+                total = snapshot.total,
+                "自动调整池优先级: {} -> {}",
+                current,
+                priority
+            );
+            runtime.effective_priority = Some(priority);
+            changes.push((pool_id, current, priority));
+        }
+        changes.sort();
+        changes
+    }
+
     /// 获取所有池的快照
     pub fn snapshot(&self) -> Vec<PoolSnapshot> {
-        self.pools
-            .read()
-            .values()
-            .map(|runtime| {
+        self.all_pools()
+            .iter()
+            .map(|pool| {
+                let runtime = pool.read();
                 let snapshot = runtime.token_manager.snapshot();
                 PoolSnapshot {
                     id: runtime.config.id.clone(),
@@ -280,25 +771,50 @@ impl PoolManager {
                     scheduling_mode: runtime.config.scheduling_mode,
                     has_proxy: runtime.config.has_proxy(),
                     priority: runtime.config.priority,
+                    effective_priority: runtime.priority(),
+                    overflow_pool_id: runtime.config.overflow_pool_id.clone(),
+                    default_region: runtime.config.default_region.clone(),
+                    default_auth_method: runtime.config.default_auth_method.clone(),
+                    default_machine_id: runtime.config.default_machine_id.clone(),
+                    has_default_proxy: runtime.config.default_proxy_url.is_some(),
                     total_credentials: snapshot.total,
                     available_credentials: snapshot.available,
                     current_id: snapshot.current_id,
                     session_cache_size: snapshot.session_cache_size as u64,
+                    session_cache_capacity: snapshot.session_cache_capacity,
+                    session_cache_ttl_secs: snapshot.session_cache_ttl_secs,
                     round_robin_counter: snapshot.round_robin_counter,
                 }
             })
             .collect()
     }
 
+    /// 获取池的性能历史（最近 60 分钟，池不存在时为空）
+    pub fn get_performance_history(&self, pool_id: &str) -> Vec<PerformanceBucket> {
+        self.with_pool(pool_id, |pool| pool.performance.snapshot())
+            .unwrap_or_default()
+    }
+
+    /// 所有池最近 `window_secs` 秒内的每分钟调用统计（按分钟合并，最长 60 分钟）
+    pub fn performance_timeline(&self, window_secs: u64) -> Vec<PerformanceBucket> {
+        let since = Utc::now() - chrono::Duration::seconds(window_secs as i64);
+        let buckets: Vec<PerformanceBucket> = self
+            .all_pools()
+            .iter()
+            .flat_map(|pool| pool.read().performance.snapshot())
+            .collect();
+        merge_buckets(buckets, since)
+    }
+
     /// 获取所有池 ID
     #[allow(dead_code)]
     pub fn pool_ids(&self) -> Vec<String> {
-        self.pools.read().keys().cloned().collect()
+        self.pools.iter().map(|entry| entry.key().clone()).collect()
     }
 
     /// 获取池数量
     pub fn pool_count(&self) -> usize {
-        self.pools.read().len()
+        self.pools.len()
     }
 
     // ============ 池管理 API ============
@@ -306,19 +822,24 @@ impl PoolManager {
     /// 创建新池
     pub fn create_pool(&self, pool: Pool) -> Result<(), PoolError> {
         let pool_id = pool.id.clone();
+        let structure = self.structure_lock.lock();
 
         // 检查池是否已存在
-        if self.pools.read().contains_key(&pool_id) {
+        if self.pools.contains_key(&pool_id) {
             return Err(PoolError::PoolAlreadyExists { pool_id });
         }
+        Self::validate_overflow_pool(&self.pools, &pool)?;
+        self.validate_pool_overrides(&pool)?;
+        Self::validate_credential_defaults(&pool)?;
 
         // 解析池级代理
         let pool_proxy = self.resolve_pool_proxy(&pool);
 
         // 创建空的 Token 管理器
-        let token_manager = MultiTokenManager::new(
-            self.global_config.clone(),
+        let token_manager = MultiTokenManager::with_credential_defaults(
+            self.pool_config(&pool),
             vec![],
+            pool.credential_defaults(),
             pool_proxy.clone(),
             Some(self.credentials_path.clone()),
         )
@@ -326,32 +847,41 @@ impl PoolManager {
 
         token_manager.set_scheduling_mode(pool.scheduling_mode);
 
-        let runtime = PoolRuntime {
-            config: pool.clone(),
-            token_manager: Arc::new(token_manager),
-            proxy_config: pool_proxy,
-        };
+        let performance = Arc::default();
+        attach_performance(&token_manager, &performance);
+
+        let runtime = PoolRuntime::new(pool.clone(), token_manager, pool_proxy, performance);
 
         // 添加到池映射
-        self.pools.write().insert(pool_id, Arc::new(runtime));
+        let summary = format!("创建池 {}", pool_id);
+        self.pools.insert(pool_id, Arc::new(RwLock::new(runtime)));
+        drop(structure);
 
         // 持久化
-        self.persist_pools()?;
+        self.persist_pools(summary)?;
 
         Ok(())
     }
 
     /// 更新池配置
     pub fn update_pool(&self, pool_id: &str, updates: UpdatePoolRequest) -> Result<(), PoolError> {
-        let mut pools = self.pools.write();
-
-        let runtime = pools.get(pool_id).ok_or_else(|| PoolError::PoolNotFound {
-            pool_id: pool_id.to_string(),
-        })?;
+        let pool = self
+            .get_pool(pool_id)
+            .ok_or_else(|| PoolError::PoolNotFound {
+                pool_id: pool_id.to_string(),
+            })?;
+        // 只持有该池的写锁，其他池的读写不受影响
+        let mut runtime = pool.write();
 
         // 创建更新后的配置
         let mut new_config = runtime.config.clone();
 
+        // 先校验溢出池，失败时不修改任何状态（空字符串表示清除溢出池）
+        if let Some(overflow_pool_id) = updates.overflow_pool_id {
+            new_config.overflow_pool_id =
+                (!overflow_pool_id.is_empty()).then_some(overflow_pool_id);
+            Self::validate_overflow_pool(&self.pools, &new_config)?;
+        }
         if let Some(name) = updates.name {
             new_config.name = name;
         }
@@ -362,6 +892,14 @@ impl PoolManager {
             new_config.enabled = enabled;
         }
         if let Some(scheduling_mode) = updates.scheduling_mode {
+            if scheduling_mode != new_config.scheduling_mode {
+                tracing::info!(
+                    pool_id,
+                    "池调度模式已切换: {} -> {}",
+                    new_config.scheduling_mode.label(),
+                    scheduling_mode.label()
+                );
+            }
             new_config.scheduling_mode = scheduling_mode;
             runtime.token_manager.set_scheduling_mode(scheduling_mode);
         }
@@ -374,134 +912,955 @@ impl PoolManager {
         if let Some(proxy_password) = updates.proxy_password {
             new_config.proxy_password = Some(proxy_password);
         }
+        // 手动修改优先级时丢弃自动调整结果，由下一次调整重新计算
         if let Some(priority) = updates.priority {
             new_config.priority = priority;
+            runtime.effective_priority = None;
+        }
+        // 0 表示清除池级覆盖，恢复使用全局配置
+        if let Some(capacity) = updates.session_cache_max_capacity {
+            new_config.session_cache_max_capacity = (capacity > 0).then_some(capacity);
+        }
+        if let Some(ttl) = updates.session_cache_ttl_secs {
+            new_config.session_cache_ttl_secs = (ttl > 0).then_some(ttl);
+        }
+        // 超时覆盖同样以 0 表示清除；Token 管理器创建后配置不可变，变更后只重新加载该池
+        let timeouts_changed = updates.connect_timeout_secs.is_some()
+            || updates.refresh_request_timeout_secs.is_some()
+            || updates.upstream_first_byte_timeout_secs.is_some();
+        if let Some(secs) = updates.connect_timeout_secs {
+            new_config.connect_timeout_secs = (secs > 0).then_some(secs);
+        }
+        if let Some(secs) = updates.refresh_request_timeout_secs {
+            new_config.refresh_request_timeout_secs = (secs > 0).then_some(secs);
+        }
+        if let Some(secs) = updates.upstream_first_byte_timeout_secs {
+            new_config.upstream_first_byte_timeout_secs = (secs > 0).then_some(secs);
+        }
+        // 成员凭据默认设置：空字符串表示清除
+        let non_empty = |value: String| (!value.is_empty()).then_some(value);
+        if let Some(region) = updates.default_region {
+            new_config.default_region = non_empty(region);
+        }
+        if let Some(auth_method) = updates.default_auth_method {
+            new_config.default_auth_method = non_empty(auth_method.to_ascii_lowercase());
+        }
+        if let Some(machine_id) = updates.default_machine_id {
+            new_config.default_machine_id = non_empty(machine_id);
+        }
+        if let Some(proxy_url) = updates.default_proxy_url {
+            new_config.default_proxy_url = non_empty(proxy_url);
+        }
+        if let Some(proxy_username) = updates.default_proxy_username {
+            new_config.default_proxy_username = non_empty(proxy_username);
+        }
+        if let Some(proxy_password) = updates.default_proxy_password {
+            new_config.default_proxy_password = non_empty(proxy_password);
+        }
+        self.validate_pool_overrides(&new_config)?;
+        Self::validate_credential_defaults(&new_config)?;
+        if updates.session_cache_max_capacity.is_some() || updates.session_cache_ttl_secs.is_some()
+        {
+            let effective = self.pool_config(&new_config);
+            runtime.token_manager.set_session_cache_config(
+                effective.session_cache_max_capacity,
+                effective.session_cache_ttl_secs,
+            );
         }
 
-        // 重新解析代理配置
+        // 重新解析代理配置，代理变更时释放旧代理的共享 HTTP Client 并重建 KiroProvider
         let new_proxy = self.resolve_pool_proxy(&new_config);
+        if new_proxy != runtime.proxy_config {
+            http_client::invalidate_proxy(runtime.proxy_config.as_ref());
+            runtime.provider = Arc::new(KiroProvider::with_proxy(
+                runtime.token_manager.clone(),
+                new_proxy.clone(),
+            ));
+        }
 
-        // 创建新的运行时
-        let new_runtime = PoolRuntime {
-            config: new_config,
-            token_manager: runtime.token_manager.clone(),
-            proxy_config: new_proxy,
-        };
-
-        pools.insert(pool_id.to_string(), Arc::new(new_runtime));
-        drop(pools);
+        runtime
+            .token_manager
+            .set_credential_defaults(new_config.credential_defaults());
+        runtime.config = new_config;
+        runtime.proxy_config = new_proxy;
+        drop(runtime);
 
         // 持久化
-        self.persist_pools()?;
-
-        Ok(())
-    }
-
-    /// 删除池
-    pub fn delete_pool(&self, pool_id: &str) -> Result<(), PoolError> {
-        if pool_id == DEFAULT_POOL_ID {
-            return Err(PoolError::CannotDeleteDefaultPool);
-        }
+        self.persist_pools(format!("更新池 {}", pool_id))?;
 
-        let mut pools = self.pools.write();
-
-        if pools.remove(pool_id).is_none() {
-            return Err(PoolError::PoolNotFound {
-                pool_id: pool_id.to_string(),
-            });
+        if timeouts_changed {
+            self.reload_pool(pool_id)?;
         }
 
-        drop(pools);
-
-        // 持久化
-        self.persist_pools()?;
-
         Ok(())
     }
 
-    /// 设置池启用/禁用状态
-    pub fn set_pool_disabled(&self, pool_id: &str, disabled: bool) -> Result<(), PoolError> {
+    /// 设置池的调度模式并写入池配置（重新加载后保持）
+    pub fn set_pool_scheduling_mode(
+        &self,
+        pool_id: &str,
+        mode: SchedulingMode,
+    ) -> Result<(), PoolError> {
         self.update_pool(
             pool_id,
             UpdatePoolRequest {
-                enabled: Some(!disabled),
+                scheduling_mode: Some(mode),
                 ..Default::default()
             },
         )
     }
 
-    /// 持久化池配置
-    fn persist_pools(&self) -> Result<(), PoolError> {
-        let pools = self.pools.read();
-        let pools_config = PoolsConfig {
-            pools: pools.values().map(|r| r.config.clone()).collect(),
-        };
-        pools_config.save(&self.pools_path)?;
-        Ok(())
+    /// 设置凭据所在池的调度模式并写入池配置，返回池 ID
+    pub fn set_credential_pool_scheduling_mode(
+        &self,
+        credential_id: u64,
+        mode: SchedulingMode,
+    ) -> Result<String, PoolError> {
+        let pool_id = self.find_credential_pool(credential_id)?.id;
+        self.set_pool_scheduling_mode(&pool_id, mode)?;
+        Ok(pool_id)
     }
 
-    // ============ 凭据分配 API ============
-
-    /// 将凭据分配到池
+    /// 删除池
     ///
-    /// 注意：这需要重新加载凭据配置
-    pub fn assign_credential_to_pool(
+    /// 池内仍有凭据或仍被 API Key 绑定时按 `strategy` 处理：拒绝删除，或将凭据和
+    /// API Key 绑定重新分配到目标池。重新分配时先经凭据文件的写入线程回写内存中的凭据，
+    /// 再在同一事务中写入池配置和 API Key，事务失败时撤销凭据变更；随后重新加载使凭据进入目标池
+    pub fn delete_pool(
         &self,
-        credential_id: u64,
         pool_id: &str,
-    ) -> Result<(), PoolError> {
-        // 检查目标池是否存在
-        if !self.pools.read().contains_key(pool_id) {
-            return Err(PoolError::PoolNotFound {
+        strategy: DeletePoolStrategy,
+        api_keys: &ApiKeyManager,
+    ) -> Result<PoolDeleteSummary, PoolError> {
+        if pool_id == DEFAULT_POOL_ID {
+            return Err(PoolError::CannotDeleteDefaultPool);
+        }
+
+        // 持有结构锁直到内存更新完成，避免与其他结构性变更交错
+        let structure = self.structure_lock.lock();
+
+        let pool = self
+            .get_pool(pool_id)
+            .ok_or_else(|| PoolError::PoolNotFound {
                 pool_id: pool_id.to_string(),
-            });
+            })?;
+        let member_ids = pool.read().token_manager.credential_ids();
+        let bound_api_keys: Vec<String> = api_keys
+            .bound_to_pool(pool_id)
+            .into_iter()
+            .map(|k| k.name)
+            .collect();
+
+        if member_ids.is_empty() && bound_api_keys.is_empty() {
+            self.pools.remove(pool_id);
+            drop(structure);
+            self.persist_pools(format!("删除池 {}", pool_id))?;
+            return Ok(PoolDeleteSummary::default());
         }
 
-        // 加载凭据配置
-        let content = std::fs::read_to_string(&self.credentials_path)?;
-        let mut credentials: Vec<KiroCredentials> = if content.trim().is_empty() {
-            vec![]
-        } else {
-            serde_json::from_str(&content)?
+        let target = match strategy {
+            DeletePoolStrategy::Refuse if !member_ids.is_empty() => {
+                return Err(PoolError::PoolNotEmpty {
+                    pool_id: pool_id.to_string(),
+                    credential_ids: member_ids,
+                });
+            }
+            DeletePoolStrategy::Refuse => {
+                return Err(PoolError::PoolHasApiKeys {
+                    pool_id: pool_id.to_string(),
+                    api_key_names: bound_api_keys,
+                });
+            }
+            DeletePoolStrategy::Reassign(target) => target,
         };
+        if target == pool_id {
+            return Err(PoolError::InvalidPoolId {
+                reason: "重新分配的目标池不能是被删除的池".to_string(),
+            });
+        }
+        if !self.pools.contains_key(&target) {
+            return Err(PoolError::PoolNotFound { pool_id: target });
+        }
 
-        // 找到并更新凭据
-        let found = credentials.iter_mut().find(|c| c.id == Some(credential_id));
-        if let Some(cred) = found {
-            cred.pool_id = Some(pool_id.to_string());
-        } else {
-            return Err(PoolError::CredentialNotFound { credential_id });
+        let pools_config = PoolsConfig {
+            pools: self
+                .pool_configs()
+                .into_iter()
+                .filter(|config| config.id != pool_id)
+                .collect(),
+        };
+        let pools_content = serde_json::to_string_pretty(&pools_config)?;
+
+        let reassigned = member_ids.len();
+        let undo = self.move_credentials(
+            &member_ids
+                .iter()
+                .map(|&id| (id, Some(target.clone())))
+                .collect(),
+        )?;
+
+        let rebound_api_keys = api_keys
+            .rename_pool_binding(pool_id, &target, |api_keys_path, api_keys_content| {
+                write_files_atomically(&[
+                    (&self.pools_path, pools_content),
+                    (api_keys_path, api_keys_content),
+                ])
+            })
+            .inspect_err(|_| undo_move_credentials(undo))?;
+        self.pools.remove(pool_id);
+        drop(structure);
+
+        let change = Change::new(
+            "pools",
+            format!(
+                "删除池 {}（{} 个凭据、{} 个 API Key 转入 {}）",
+                pool_id, reassigned, rebound_api_keys, target
+            ),
+        );
+        if let Err(e) = ChangeJournal::for_file(&self.pools_path).append(&self.pools_path, &change)
+        {
+            tracing::warn!("写入变更记录失败: {}", e);
         }
 
-        // 保存凭据配置
-        let content = serde_json::to_string_pretty(&credentials)?;
-        std::fs::write(&self.credentials_path, content)?;
+        tracing::info!(
+            "池 {} 已删除，凭据 {:?}、API Key {:?} 已重新分配到池 {}",
+            pool_id,
+            member_ids,
+            bound_api_keys,
+            target
+        );
 
-        // 重新加载
+        // 重新加载，使凭据进入目标池的 Token 管理器
         self.reload()?;
 
-        Ok(())
+        Ok(PoolDeleteSummary {
+            credentials: reassigned,
+            api_keys: rebound_api_keys,
+        })
     }
-}
 
-/// 池快照（用于 API 响应）
-#[derive(Debug, Clone, serde::Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PoolSnapshot {
-    pub id: String,
-    pub name: String,
-    pub description: Option<String>,
-    pub enabled: bool,
-    pub scheduling_mode: SchedulingMode,
-    pub has_proxy: bool,
+    /// 重命名池（修改池 ID）
+    ///
+    /// 同时更新属于该池的凭据和所有 API Key 绑定：先经凭据文件的写入线程回写内存中的凭据，
+    /// 再在同一事务中写入池配置和 API Key，任一写入失败时撤销且池状态不变。
+    /// 池的 Token 管理器（含会话缓存）原样保留
+    pub fn rename_pool(
+        &self,
+        old_pool_id: &str,
+        new_pool_id: &str,
+        api_keys: &ApiKeyManager,
+    ) -> Result<PoolRenameSummary, PoolError> {
+        if old_pool_id == DEFAULT_POOL_ID {
+            return Err(PoolError::CannotRenameDefaultPool);
+        }
+        if new_pool_id.trim().is_empty() {
+            return Err(PoolError::InvalidPoolId {
+                reason: "新池 ID 不能为空".to_string(),
+            });
+        }
+        if new_pool_id == Self::AUTO_ROUTE_POOL_ID {
+            return Err(PoolError::InvalidPoolId {
+                reason: format!("{} 为自动路由保留值", Self::AUTO_ROUTE_POOL_ID),
+            });
+        }
+
+        // 持有结构锁直到内存更新完成，避免与其他结构性变更交错
+        let _structure = self.structure_lock.lock();
+
+        let pool = self
+            .get_pool(old_pool_id)
+            .ok_or_else(|| PoolError::PoolNotFound {
+                pool_id: old_pool_id.to_string(),
+            })?;
+        if self.pools.contains_key(new_pool_id) {
+            return Err(PoolError::PoolAlreadyExists {
+                pool_id: new_pool_id.to_string(),
+            });
+        }
+
+        let mut new_config = pool.read().config.clone();
+        new_config.id = new_pool_id.to_string();
+
+        let pools_config = PoolsConfig {
+            pools: self
+                .pool_configs()
+                .into_iter()
+                .map(|config| {
+                    if config.id == old_pool_id {
+                        new_config.clone()
+                    } else {
+                        config
+                    }
+                })
+                .collect(),
+        };
+        let pools_content = serde_json::to_string_pretty(&pools_config)?;
+
+        let member_ids = pool.read().token_manager.credential_ids();
+        let renamed_credentials = member_ids.len();
+        let undo = self.move_credentials(
+            &member_ids
+                .iter()
+                .map(|&id| (id, Some(new_pool_id.to_string())))
+                .collect(),
+        )?;
+
+        let renamed_api_keys = api_keys
+            .rename_pool_binding(
+                old_pool_id,
+                new_pool_id,
+                |api_keys_path, api_keys_content| {
+                    write_files_atomically(&[
+                        (&self.pools_path, pools_content),
+                        (api_keys_path, api_keys_content),
+                    ])
+                },
+            )
+            .inspect_err(|_| undo_move_credentials(undo))?;
+
+        // 文件已全部写入，切换内存中的池 ID（沿用原 Token 管理器）
+        {
+            let mut runtime = pool.write();
+            runtime.token_manager.rename_pool(new_pool_id);
+            runtime.config = new_config;
+        }
+        self.pools.remove(old_pool_id);
+        self.pools.insert(new_pool_id.to_string(), pool);
+
+        let change = Change::new(
+            "pools",
+            format!("重命名池 {} 为 {}", old_pool_id, new_pool_id),
+        );
+        if let Err(e) = ChangeJournal::for_file(&self.pools_path).append(&self.pools_path, &change)
+        {
+            tracing::warn!("写入变更记录失败: {}", e);
+        }
+
+        tracing::info!(
+            "池 {} 已重命名为 {}（凭据 {} 个，API Key {} 个）",
+            old_pool_id,
+            new_pool_id,
+            renamed_credentials,
+            renamed_api_keys
+        );
+
+        Ok(PoolRenameSummary {
+            credentials: renamed_credentials,
+            api_keys: renamed_api_keys,
+        })
+    }
+
+    /// 设置池启用/禁用状态
+    pub fn set_pool_disabled(&self, pool_id: &str, disabled: bool) -> Result<(), PoolError> {
+        self.update_pool(
+            pool_id,
+            UpdatePoolRequest {
+                enabled: Some(!disabled),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// 持久化池配置（经写入线程原子写入，并记录变更）
+    fn persist_pools(&self, summary: String) -> Result<(), PoolError> {
+        self.pools_writer
+            .replace(Change::new("pools", summary), || {
+                let pools_config = PoolsConfig {
+                    pools: self.pool_configs(),
+                };
+                Ok(serde_json::to_string_pretty(&pools_config)?)
+            })
+            .wait_blocking()
+            .map_err(|e| PoolError::PersistFailed {
+                reason: format!("{:#}", e),
+            })
+    }
+
+    /// 修改凭据的所属池并回写凭据文件
+    ///
+    /// 修改各池 Token 管理器内存中的 pool_id 后经凭据文件的写入线程回写内存中的凭据，
+    /// 不从磁盘重新读取，避免用旧快照覆盖刷新后轮换的 Token；回写失败时恢复内存中的 pool_id。
+    /// 返回各管理器中凭据原来的池 ID，供后续写入失败时撤销
+    fn move_credentials(
+        &self,
+        pool_ids: &HashMap<u64, Option<String>>,
+    ) -> Result<PoolIdUndo, PoolError> {
+        let undo: PoolIdUndo = self
+            .all_pools()
+            .iter()
+            .filter_map(|pool| {
+                let token_manager = pool.read().token_manager.clone();
+                let previous = token_manager.set_pool_ids(pool_ids);
+                (!previous.is_empty()).then_some((token_manager, previous))
+            })
+            .collect();
+
+        if let Err(e) = undo
+            .iter()
+            .try_for_each(|(token_manager, _)| token_manager.flush_credentials())
+        {
+            undo_move_credentials(undo);
+            return Err(PoolError::PersistFailed {
+                reason: format!("{:#}", e),
+            });
+        }
+        Ok(undo)
+    }
+
+    /// 所有池的配置
+    fn pool_configs(&self) -> Vec<Pool> {
+        self.all_pools()
+            .iter()
+            .map(|pool| pool.read().config.clone())
+            .collect()
+    }
+
+    // ============ 凭据分配 API ============
+
+    /// 按策略在池之间重新分配凭据
+    ///
+    /// 先经凭据文件的写入线程回写内存中的凭据，再写入池配置（`by_health` 可能需要创建隔离池），
+    /// 池配置写入失败时撤销凭据变更；随后重新加载使凭据进入新池。
+    /// 隔离池中的凭据不参与 `even` / `by_priority` 分配
+    pub fn rebalance(&self, strategy: RebalanceStrategy) -> Result<RebalanceResult, PoolError> {
+        // 持有结构锁直到文件写入完成，避免与其他结构性变更交错
+        let structure = self.structure_lock.lock();
+
+        // 凭据当前所在池（引用不存在的池的凭据由默认池接管，视为默认池）
+        let mut members: Vec<RebalanceMember> = self
+            .all_pools()
+            .iter()
+            .flat_map(|pool| {
+                let runtime = pool.read();
+                let pool_id = runtime.config.id.clone();
+                runtime
+                    .token_manager
+                    .snapshot()
+                    .entries
+                    .into_iter()
+                    .map(move |e| RebalanceMember {
+                        id: e.id,
+                        priority: e.priority,
+                        pool_id: pool_id.clone(),
+                    })
+            })
+            .collect();
+        members.sort_by_key(|m| m.id);
+
+        let mut pool_configs = self.pool_configs();
+        pool_configs.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let targets = match strategy {
+            RebalanceStrategy::Even => {
+                let pool_ids: Vec<&str> = pool_configs
+                    .iter()
+                    .map(|p| p.id.as_str())
+                    .filter(|id| *id != QUARANTINE_POOL_ID)
+                    .collect();
+                plan_even(&members, &pool_ids)
+            }
+            RebalanceStrategy::ByPriority => {
+                let mut by_priority: Vec<&Pool> = pool_configs
+                    .iter()
+                    .filter(|p| p.id != QUARANTINE_POOL_ID)
+                    .collect();
+                by_priority.sort_by_key(|p| p.priority);
+                let pool_ids: Vec<&str> = by_priority.iter().map(|p| p.id.as_str()).collect();
+                plan_by_priority(&members, &pool_ids)
+            }
+            RebalanceStrategy::ByHealth => {
+                let unhealthy: Vec<u64> = self
+                    .all_pools()
+                    .iter()
+                    .flat_map(|pool| pool.read().token_manager.snapshot().entries)
+                    .filter(|e| e.disabled)
+                    .map(|e| e.id)
+                    .collect();
+                members
+                    .iter()
+                    .filter(|m| unhealthy.contains(&m.id))
+                    .map(|m| (m.id, QUARANTINE_POOL_ID.to_string()))
+                    .collect()
+            }
+        };
+
+        let moved: Vec<CredentialMove> = members
+            .iter()
+            .filter_map(|m| {
+                let (_, to_pool) = targets.iter().find(|(id, _)| *id == m.id)?;
+                (*to_pool != m.pool_id).then(|| CredentialMove {
+                    credential_id: m.id,
+                    from_pool: m.pool_id.clone(),
+                    to_pool: to_pool.clone(),
+                })
+            })
+            .collect();
+
+        if moved.is_empty() {
+            return Ok(RebalanceResult { moved });
+        }
+
+        // 隔离池不存在时创建（默认禁用，不参与请求路由）
+        let create_quarantine = moved.iter().any(|m| m.to_pool == QUARANTINE_POOL_ID)
+            && !self.pools.contains_key(QUARANTINE_POOL_ID);
+        if create_quarantine {
+            let mut quarantine = Pool::new(QUARANTINE_POOL_ID, "隔离池");
+            quarantine.enabled = false;
+            quarantine.description = Some("存放不健康凭据的隔离池".to_string());
+            pool_configs.push(quarantine);
+        }
+        let pools_content = serde_json::to_string_pretty(&PoolsConfig {
+            pools: pool_configs,
+        })?;
+
+        let undo = self.move_credentials(
+            &moved
+                .iter()
+                .map(|m| (m.credential_id, Some(m.to_pool.clone())))
+                .collect(),
+        )?;
+        if create_quarantine {
+            self.pools_writer
+                .replace(Change::background("pools"), || Ok(pools_content))
+                .wait_blocking()
+                .map_err(|e| {
+                    undo_move_credentials(undo);
+                    PoolError::PersistFailed {
+                        reason: format!("{:#}", e),
+                    }
+                })?;
+        }
+        drop(structure);
+
+        let change = Change::new(
+            "pools",
+            format!("重新分配凭据（{}，移动 {} 个）", strategy, moved.len()),
+        );
+        if let Err(e) = ChangeJournal::for_file(&self.pools_path).append(&self.pools_path, &change)
+        {
+            tracing::warn!("写入变更记录失败: {}", e);
+        }
+
+        tracing::info!("按 {} 策略重新分配凭据，移动 {} 个", strategy, moved.len());
+
+        // 重新加载，使凭据进入新池的 Token 管理器
+        self.reload()?;
+
+        Ok(RebalanceResult { moved })
+    }
+
+    /// 将凭据分配到池
+    ///
+    /// 只更新源池和目标池的 Token 管理器：凭据从源池移除后加入目标池，
+    /// 运行时状态和会话绑定重置（与重新加载一致），不验证 Token，其他池不受影响。
+    /// 加入目标池失败时凭据放回源池。
+    /// 目标池必须存在且已启用，返回凭据在目标池中的新状态
+    pub fn assign_credential_to_pool(
+        &self,
+        credential_id: u64,
+        pool_id: &str,
+    ) -> Result<CredentialAssignment, PoolError> {
+        let target = self
+            .get_pool(pool_id)
+            .ok_or_else(|| PoolError::PoolNotFound {
+                pool_id: pool_id.to_string(),
+            })?;
+        let source = self.find_credential_pool(credential_id)?;
+        let (target, target_enabled) = {
+            let runtime = target.read();
+            (PoolHandle::of(&runtime), runtime.is_enabled())
+        };
+        if !target_enabled {
+            return Err(PoolError::PoolDisabled {
+                pool_id: pool_id.to_string(),
+            });
+        }
+
+        if source.id != target.id {
+            let original = source
+                .token_manager
+                .remove_credential(credential_id)
+                .ok_or(PoolError::CredentialNotFound { credential_id })?;
+            let mut credentials = original.clone();
+            credentials.pool_id = Some(pool_id.to_string());
+            let added_to_target = !target.token_manager.contains(credential_id);
+            if let Err(e) = target.token_manager.add_credential_direct(credentials) {
+                // 目标池写入失败：撤销加入，凭据放回源池
+                if added_to_target {
+                    target.token_manager.remove_credential(credential_id);
+                }
+                if let Err(restore_err) = source.token_manager.add_credential_direct(original) {
+                    tracing::error!(
+                        "凭据 #{} 放回池 {} 后回写失败: {:#}",
+                        credential_id,
+                        source.id,
+                        restore_err
+                    );
+                }
+                return Err(PoolError::PersistFailed {
+                    reason: format!("{:#}", e),
+                });
+            }
+            tracing::info!(
+                "凭据 #{} 已从池 {} 分配到池 {}",
+                credential_id,
+                source.id,
+                pool_id
+            );
+        }
+
+        let snapshot = target.token_manager.snapshot();
+        let credential = snapshot
+            .entries
+            .into_iter()
+            .find(|e| e.id == credential_id)
+            .ok_or(PoolError::CredentialNotFound { credential_id })?;
+        Ok(CredentialAssignment {
+            source_pool_id: source.id,
+            is_current: snapshot.current_id == credential_id,
+            credential,
+        })
+    }
+
+    /// 将凭据转移到另一个池（不中断进行中的会话）
+    ///
+    /// 与 [`Self::assign_credential_to_pool`] 不同，凭据连同运行时状态
+    /// 直接从源池移到目标池，不重新验证 Token，其他凭据的会话不受影响。
+    /// `migrate_sessions` 为 true 时，源池中绑定到该凭据的会话迁移到目标池。
+    /// 返回迁移的会话数
+    pub fn transfer_credential(
+        &self,
+        credential_id: u64,
+        target_pool_id: &str,
+        migrate_sessions: bool,
+    ) -> Result<usize, PoolError> {
+        let target = self
+            .with_pool(target_pool_id, PoolHandle::of)
+            .ok_or_else(|| PoolError::PoolNotFound {
+                pool_id: target_pool_id.to_string(),
+            })?;
+        let source = self.find_credential_pool(credential_id)?;
+        if source.id == target.id {
+            return Ok(0);
+        }
+
+        let transfer = source
+            .token_manager
+            .take_credential(credential_id)
+            .ok_or(PoolError::CredentialNotFound { credential_id })?;
+        let moved_sessions = target
+            .token_manager
+            .adopt_credential(transfer, target_pool_id, migrate_sessions)
+            .map_err(|e| PoolError::PersistFailed {
+                reason: format!("{:#}", e),
+            })?;
+
+        tracing::info!(
+            "凭据 #{} 已从池 {} 转移到池 {}，迁移 {} 个会话",
+            credential_id,
+            source.id,
+            target_pool_id,
+            moved_sessions
+        );
+        Ok(moved_sessions)
+    }
+
+    /// 获取凭据所在池的 Token 管理器（凭据不属于任何池时返回 None）
+    pub fn credential_token_manager(&self, credential_id: u64) -> Option<Arc<MultiTokenManager>> {
+        self.find_credential_pool(credential_id)
+            .ok()
+            .map(|pool| pool.token_manager)
+    }
+
+    /// 查找包含凭据的池
+    fn find_credential_pool(&self, credential_id: u64) -> Result<PoolHandle, PoolError> {
+        self.all_pools()
+            .iter()
+            .find_map(|pool| {
+                let runtime = pool.read();
+                runtime
+                    .token_manager
+                    .contains(credential_id)
+                    .then(|| PoolHandle::of(&runtime))
+            })
+            .ok_or(PoolError::CredentialNotFound { credential_id })
+    }
+}
+
+/// 池 ID 和 Token 管理器（凭据分配时无需持有池锁）
+struct PoolHandle {
+    id: String,
+    token_manager: Arc<MultiTokenManager>,
+}
+
+impl PoolHandle {
+    fn of(runtime: &PoolRuntime) -> Self {
+        Self {
+            id: runtime.config.id.clone(),
+            token_manager: runtime.token_manager.clone(),
+        }
+    }
+}
+
+/// 启动池优先级自动调整后台任务（每 `interval_secs` 秒执行一次）
+pub fn start_auto_priority_task(
+    pool_manager: Arc<PoolManager>,
+    interval_secs: u64,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+        loop {
+            ticker.tick().await;
+            let changes = pool_manager.auto_adjust_priorities();
+            if !changes.is_empty() {
+                tracing::debug!("本轮自动调整了 {} 个池的优先级", changes.len());
+            }
+        }
+    })
+}
+
+/// 重新分配时参与计算的凭据
+struct RebalanceMember {
+    id: u64,
+    priority: u32,
+    pool_id: String,
+}
+
+/// `even` 策略：凭据按 ID 均分到各池（池按 ID 排序，靠前的池多分余数）
+///
+/// 各池优先保留已有凭据，只移动超出配额的部分，返回 (凭据 ID, 目标池)
+fn plan_even(members: &[RebalanceMember], pool_ids: &[&str]) -> Vec<(u64, String)> {
+    if pool_ids.is_empty() {
+        return Vec::new();
+    }
+
+    let mut movable: Vec<&RebalanceMember> = members
+        .iter()
+        .filter(|m| m.pool_id != QUARANTINE_POOL_ID)
+        .collect();
+    movable.sort_by_key(|m| m.id);
+
+    let base = movable.len() / pool_ids.len();
+    let extra = movable.len() % pool_ids.len();
+    let quota = |index: usize| base + usize::from(index < extra);
+
+    let mut counts = vec![0; pool_ids.len()];
+    let mut plan = Vec::with_capacity(movable.len());
+    let mut surplus = Vec::new();
+    for m in movable {
+        match pool_ids.iter().position(|id| *id == m.pool_id) {
+            Some(index) if counts[index] < quota(index) => {
+                counts[index] += 1;
+                plan.push((m.id, m.pool_id.clone()));
+            }
+            _ => surplus.push(m.id),
+        }
+    }
+
+    let mut index = 0;
+    for id in surplus {
+        while counts[index] >= quota(index) {
+            index += 1;
+        }
+        counts[index] += 1;
+        plan.push((id, pool_ids[index].to_string()));
+    }
+    plan
+}
+
+/// `by_priority` 策略：优先级最高的 N 个凭据分配到优先级最高的池
+///
+/// N 为均分时每池的凭据数（向上取整）；原本在最高优先级池、但不在前 N 名的凭据
+/// 移到次高优先级的池。`pool_ids` 按池优先级排序，返回 (凭据 ID, 目标池)
+fn plan_by_priority(members: &[RebalanceMember], pool_ids: &[&str]) -> Vec<(u64, String)> {
+    let (Some(top_pool), Some(next_pool)) = (pool_ids.first(), pool_ids.get(1)) else {
+        return Vec::new();
+    };
+
+    let mut movable: Vec<&RebalanceMember> = members
+        .iter()
+        .filter(|m| m.pool_id != QUARANTINE_POOL_ID)
+        .collect();
+    movable.sort_by_key(|m| (m.priority, m.id));
+
+    let top_n = movable.len().div_ceil(pool_ids.len());
+    movable
+        .iter()
+        .enumerate()
+        .filter_map(|(rank, m)| {
+            if rank < top_n {
+                Some((m.id, top_pool.to_string()))
+            } else if m.pool_id == *top_pool {
+                Some((m.id, next_pool.to_string()))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// 各 Token 管理器中凭据原来的池 ID（撤销 `move_credentials` 使用）
+type PoolIdUndo = Vec<(Arc<MultiTokenManager>, HashMap<u64, Option<String>>)>;
+
+/// 撤销凭据所属池的修改：恢复内存中的 pool_id 并回写
+fn undo_move_credentials(undo: PoolIdUndo) {
+    for (token_manager, previous) in undo {
+        token_manager.set_pool_ids(&previous);
+        if let Err(e) = token_manager.flush_credentials() {
+            tracing::error!("撤销凭据所属池修改后回写失败: {:#}", e);
+        }
+    }
+}
+
+/// 在同一事务中写入多个文件（任一失败时回滚）
+fn write_files_atomically(files: &[(&Path, String)]) -> Result<(), PoolError> {
+    let mut transaction = FileTransaction::new();
+    files
+        .iter()
+        .try_for_each(|(path, content)| transaction.stage(path, content))
+        .and_then(|_| transaction.commit())
+        .map_err(|e| PoolError::PersistFailed {
+            reason: format!("{:#}", e),
+        })
+}
+
+/// 将 Token 管理器的调用结果记录到池性能历史
+fn attach_performance(
+    token_manager: &MultiTokenManager,
+    performance: &Arc<PoolPerformanceHistory>,
+) {
+    let performance = performance.clone();
+    token_manager.set_on_call_complete(Box::new(move |success, latency_ms| {
+        performance.on_call_complete(success, latency_ms)
+    }));
+}
+
+/// 池快照（用于 API 响应）
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolSnapshot {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub scheduling_mode: SchedulingMode,
+    pub has_proxy: bool,
     pub priority: u32,
+    /// 自动路由使用的优先级（未自动调整时与 `priority` 相同）
+    pub effective_priority: u32,
+    pub overflow_pool_id: Option<String>,
+    pub default_region: Option<String>,
+    pub default_auth_method: Option<String>,
+    pub default_machine_id: Option<String>,
+    pub has_default_proxy: bool,
     pub total_credentials: usize,
     pub available_credentials: usize,
     pub current_id: u64,
     pub session_cache_size: u64,
+    pub session_cache_capacity: u64,
+    pub session_cache_ttl_secs: u64,
     pub round_robin_counter: u64,
 }
 
+/// 单个池的自动路由统计
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolRoutingStat {
+    pub pool_id: String,
+    /// 被自动路由选中的次数
+    pub auto_route_count: u64,
+    /// 最近一次被选中的时间
+    pub last_selected_at: Option<DateTime<Utc>>,
+}
+
+/// 自动路由统计
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingStats {
+    /// 成功选中池的总次数
+    pub route_count_total: u64,
+    /// 找不到可用池的次数
+    pub miss_count_total: u64,
+    pub pools: Vec<PoolRoutingStat>,
+}
+
+/// 删除池时对池内凭据的处理策略
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeletePoolStrategy {
+    /// 池内仍有凭据时拒绝删除
+    Refuse,
+    /// 将池内凭据重新分配到指定池
+    Reassign(String),
+}
+
+/// 池间重新分配凭据的策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebalanceStrategy {
+    /// 所有凭据均分到各池
+    Even,
+    /// 优先级最高的凭据集中到优先级最高的池
+    ByPriority,
+    /// 不健康（已禁用）的凭据移到隔离池
+    ByHealth,
+}
+
+impl std::fmt::Display for RebalanceStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RebalanceStrategy::Even => write!(f, "even"),
+            RebalanceStrategy::ByPriority => write!(f, "by_priority"),
+            RebalanceStrategy::ByHealth => write!(f, "by_health"),
+        }
+    }
+}
+
+/// 单个凭据的移动记录
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialMove {
+    pub credential_id: u64,
+    pub from_pool: String,
+    pub to_pool: String,
+}
+
+/// 重新分配结果
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RebalanceResult {
+    /// 实际移动的凭据（已在目标池的凭据不列出）
+    pub moved: Vec<CredentialMove>,
+}
+
+/// 凭据分配结果
+#[derive(Debug, Clone)]
+pub struct CredentialAssignment {
+    /// 分配前所在的池
+    pub source_pool_id: String,
+    /// 凭据在目标池中的新状态
+    pub credential: CredentialEntrySnapshot,
+    /// 是否为目标池的当前凭据
+    pub is_current: bool,
+}
+
+/// 池删除结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolDeleteSummary {
+    /// 重新分配的凭据数量
+    pub credentials: usize,
+    /// 重新绑定的 API Key 数量
+    pub api_keys: usize,
+}
+
+/// 池重命名结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolRenameSummary {
+    /// 更新的凭据数量
+    pub credentials: usize,
+    /// 更新的 API Key 数量
+    pub api_keys: usize,
+}
+
 /// 更新池请求
 #[derive(Debug, Default, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -514,11 +1873,24 @@ pub struct UpdatePoolRequest {
     pub proxy_username: Option<String>,
     pub proxy_password: Option<String>,
     pub priority: Option<u32>,
+    pub session_cache_max_capacity: Option<u64>,
+    pub session_cache_ttl_secs: Option<u64>,
+    pub connect_timeout_secs: Option<u64>,
+    pub refresh_request_timeout_secs: Option<u64>,
+    pub upstream_first_byte_timeout_secs: Option<u64>,
+    pub overflow_pool_id: Option<String>,
+    pub default_region: Option<String>,
+    pub default_auth_method: Option<String>,
+    pub default_machine_id: Option<String>,
+    pub default_proxy_url: Option<String>,
+    pub default_proxy_username: Option<String>,
+    pub default_proxy_password: Option<String>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::kiro::token_manager::SettingSource;
     use tempfile::tempdir;
 
     #[test]
@@ -548,6 +1920,7 @@ mod tests {
 
         let config = Config::default();
         let manager = PoolManager::new(config, None, &pools_path, &credentials_path).unwrap();
+        let api_keys = ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap();
 
         // 创建新池
         let pool = Pool::new("test", "测试池");
@@ -556,7 +1929,7 @@ mod tests {
 
         // 获取池
         let pool = manager.get_pool("test").unwrap();
-        assert_eq!(pool.config.name, "测试池");
+        assert_eq!(pool.read().config.name, "测试池");
 
         // 更新池
         manager
@@ -569,14 +1942,20 @@ mod tests {
             )
             .unwrap();
         let pool = manager.get_pool("test").unwrap();
-        assert_eq!(pool.config.name, "更新后的池");
+        assert_eq!(pool.read().config.name, "更新后的池");
 
         // 删除池
-        manager.delete_pool("test").unwrap();
+        manager
+            .delete_pool("test", DeletePoolStrategy::Refuse, &api_keys)
+            .unwrap();
         assert_eq!(manager.pool_count(), 1);
 
         // 不能删除默认池
-        assert!(manager.delete_pool(DEFAULT_POOL_ID).is_err());
+        assert!(
+            manager
+                .delete_pool(DEFAULT_POOL_ID, DeletePoolStrategy::Refuse, &api_keys)
+                .is_err()
+        );
     }
 
     #[test]
@@ -589,6 +1968,7 @@ mod tests {
 
         let config = Config::default();
         let manager = PoolManager::new(config, None, &pools_path, &credentials_path).unwrap();
+        let api_keys = ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap();
 
         // 测试 PoolAlreadyExists
         let pool = Pool::new("default", "重复池");
@@ -596,11 +1976,1564 @@ mod tests {
         assert!(err.is_pool_already_exists());
 
         // 测试 PoolNotFound
-        let err = manager.delete_pool("nonexistent").unwrap_err();
+        let err = manager
+            .delete_pool("nonexistent", DeletePoolStrategy::Refuse, &api_keys)
+            .unwrap_err();
         assert!(err.is_pool_not_found());
 
         // 测试 CannotDeleteDefaultPool
-        let err = manager.delete_pool(DEFAULT_POOL_ID).unwrap_err();
+        let err = manager
+            .delete_pool(DEFAULT_POOL_ID, DeletePoolStrategy::Refuse, &api_keys)
+            .unwrap_err();
         assert!(err.is_cannot_delete_default_pool());
     }
+
+    #[test]
+    fn test_ordered_pool_fallback() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+
+        // 只有 overflow 池有凭据
+        std::fs::write(
+            &credentials_path,
+            format!(
+                r#"[{{"refreshToken": "{}", "poolId": "overflow"}}]"#,
+                "a".repeat(100)
+            ),
+        )
+        .unwrap();
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager.create_pool(Pool::new("premium", "主池")).unwrap();
+        manager.create_pool(Pool::new("overflow", "溢出池")).unwrap();
+        manager.reload().unwrap();
+
+        let ids = |ids: &[&str]| ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        // premium 无可用凭据，回退到 overflow
+        let pool = manager
+            .get_pool_for_api_key_ordered(&ids(&["premium", "overflow"]))
+            .unwrap();
+        assert_eq!(pool.read().config.id, "overflow");
+
+        // 不存在的池被跳过
+        let pool = manager
+            .get_pool_for_api_key_ordered(&ids(&["missing", "overflow"]))
+            .unwrap();
+        assert_eq!(pool.read().config.id, "overflow");
+
+        // 都没有可用凭据时使用第一个启用的池（与单池绑定行为一致）
+        let pool = manager
+            .get_pool_for_api_key_ordered(&ids(&["premium"]))
+            .unwrap();
+        assert_eq!(pool.read().config.id, "premium");
+
+        // 禁用的池视为维护中
+        manager
+            .update_pool(
+                "overflow",
+                UpdatePoolRequest {
+                    enabled: Some(false),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let pool = manager
+            .get_pool_for_api_key_ordered(&ids(&["overflow", "premium"]))
+            .unwrap();
+        assert_eq!(pool.read().config.id, "premium");
+        assert!(
+            manager
+                .get_pool_for_api_key_ordered(&ids(&["overflow", "missing"]))
+                .is_none()
+        );
+
+        // 空列表使用默认池
+        let pool = manager.get_pool_for_api_key_ordered(&[]).unwrap();
+        assert_eq!(pool.read().config.id, DEFAULT_POOL_ID);
+    }
+
+    #[test]
+    fn test_pool_overflow_when_exhausted() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        let credentials: Vec<serde_json::Value> = [(1, "primary"), (2, "spare"), (3, "third")]
+            .into_iter()
+            .map(|(id, pool_id)| {
+                serde_json::json!({
+                    "id": id,
+                    "refreshToken": "a".repeat(100),
+                    "machineId": "0".repeat(64),
+                    "poolId": pool_id,
+                })
+            })
+            .collect();
+        std::fs::write(
+            &credentials_path,
+            serde_json::to_string(&credentials).unwrap(),
+        )
+        .unwrap();
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager.create_pool(Pool::new("third", "第三池")).unwrap();
+        manager
+            .create_pool(Pool::new("spare", "备用池").with_overflow_pool("third"))
+            .unwrap();
+        manager
+            .create_pool(Pool::new("primary", "主池").with_overflow_pool("spare"))
+            .unwrap();
+        manager.reload().unwrap();
+
+        let bound = vec!["primary".to_string()];
+        let select = || {
+            let selection = manager.select_pool_for_api_key(&bound).unwrap();
+            (
+                selection.pool.read().config.id.clone(),
+                selection.overflow_from,
+            )
+        };
+        let set_disabled = |pool_id: &str, id: u64, disabled: bool| {
+            manager
+                .get_pool(pool_id)
+                .unwrap()
+                .read()
+                .token_manager
+                .set_disabled(id, disabled, "admin")
+                .unwrap()
+        };
+
+        // 有可用凭据时不溢出
+        assert_eq!(select(), ("primary".to_string(), None));
+
+        // 主池耗尽 -> 溢出到备用池
+        set_disabled("primary", 1, true);
+        assert_eq!(select(), ("spare".to_string(), Some("primary".to_string())));
+
+        // 溢出不传递：备用池也耗尽时不会继续溢出到第三池
+        set_disabled("spare", 2, true);
+        assert_eq!(select(), ("primary".to_string(), None));
+        set_disabled("spare", 2, false);
+
+        // 主池恢复后回到主池
+        set_disabled("primary", 1, false);
+        assert_eq!(select(), ("primary".to_string(), None));
+
+        // 溢出池不能指向自身或不存在的池
+        let err = manager
+            .update_pool(
+                "primary",
+                UpdatePoolRequest {
+                    overflow_pool_id: Some("primary".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, PoolError::InvalidPoolId { .. }));
+        let err = manager
+            .create_pool(Pool::new("other", "其他池").with_overflow_pool("missing"))
+            .unwrap_err();
+        assert!(err.is_pool_not_found());
+
+        // 空字符串清除溢出池，并持久化
+        manager
+            .update_pool(
+                "primary",
+                UpdatePoolRequest {
+                    overflow_pool_id: Some(String::new()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        set_disabled("primary", 1, true);
+        assert_eq!(select(), ("primary".to_string(), None));
+        let saved = PoolsConfig::load(&pools_path).unwrap();
+        assert!(saved.get("primary").unwrap().overflow_pool_id.is_none());
+        assert_eq!(
+            saved.get("spare").unwrap().overflow_pool_id.as_deref(),
+            Some("third")
+        );
+    }
+
+    #[test]
+    fn test_auto_adjust_priorities_by_availability() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        let credentials: Vec<serde_json::Value> = [(1, "a"), (2, "a"), (3, "b"), (4, "b")]
+            .into_iter()
+            .map(|(id, pool_id)| {
+                serde_json::json!({
+                    "id": id,
+                    "refreshToken": "a".repeat(100),
+                    "machineId": "0".repeat(64),
+                    "poolId": pool_id,
+                })
+            })
+            .collect();
+        std::fs::write(
+            &credentials_path,
+            serde_json::to_string(&credentials).unwrap(),
+        )
+        .unwrap();
+
+        let config = Config {
+            auto_adjust_pool_priority: true,
+            auto_priority_min: 10,
+            auto_priority_max: 20,
+            ..Config::default()
+        };
+        let manager = PoolManager::new(config, None, &pools_path, &credentials_path).unwrap();
+        let mut pool_a = Pool::new("a", "池 A");
+        pool_a.priority = 10;
+        let mut pool_b = Pool::new("b", "池 B");
+        pool_b.priority = 20;
+        manager.create_pool(pool_a).unwrap();
+        manager.create_pool(pool_b).unwrap();
+        manager.reload().unwrap();
+
+        // 池 A 只剩 1/2 可用，池 B 全部可用
+        manager
+            .get_pool("a")
+            .unwrap()
+            .read()
+            .token_manager
+            .set_disabled(1, true, "admin")
+            .unwrap();
+
+        let changes = manager.auto_adjust_priorities();
+        assert_eq!(
+            changes,
+            vec![("a".to_string(), 10, 15), ("b".to_string(), 20, 10)]
+        );
+        let priority = |pool_id: &str| manager.get_pool(pool_id).unwrap().read().priority();
+        assert_eq!(priority("a"), 15);
+        assert_eq!(priority("b"), 10);
+
+        // 默认池（优先级 0）不在调整范围内，保持不变
+        assert_eq!(priority(DEFAULT_POOL_ID), 0);
+
+        // 配置的优先级不变，写入池配置时也不会保存调整结果
+        let configured = |pool_id: &str| manager.get_pool(pool_id).unwrap().read().config.priority;
+        assert_eq!((configured("a"), configured("b")), (10, 20));
+        manager
+            .update_pool(
+                "a",
+                UpdatePoolRequest {
+                    description: Some("已更新".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let saved = PoolsConfig::load(&pools_path).unwrap();
+        let saved_priority = |id: &str| saved.pools.iter().find(|p| p.id == id).unwrap().priority;
+        assert_eq!((saved_priority("a"), saved_priority("b")), (10, 20));
+
+        // 自动路由优先选择可用比例更高的池 B
+        let pool = manager
+            .get_pool_for_api_key(Some(PoolManager::AUTO_ROUTE_POOL_ID))
+            .unwrap();
+        assert_eq!(pool.read().config.id, "b");
+
+        // 比例未变化时不再调整
+        assert!(manager.auto_adjust_priorities().is_empty());
+
+        // 手动修改优先级后丢弃调整结果，仍按配置的优先级判断范围
+        manager
+            .update_pool(
+                "b",
+                UpdatePoolRequest {
+                    priority: Some(30),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(priority("b"), 30);
+        assert!(manager.auto_adjust_priorities().is_empty());
+    }
+
+    /// 创建包含 premium 池（1 个凭据）和 2 个绑定该池的 API Key 的测试环境
+    fn setup_rename_env(dir: &Path) -> (PoolManager, ApiKeyManager) {
+        use crate::admin::api_keys::CreateApiKeyRequest;
+
+        let pools_path = dir.join("pools.json");
+        let credentials_path = dir.join("credentials.json");
+        // 预置 id 和 machineId，避免加载时各池回写凭据文件
+        std::fs::write(
+            &credentials_path,
+            format!(
+                r#"[
+                    {{"id": 1, "refreshToken": "{}", "machineId": "{}", "poolId": "premium"}},
+                    {{"id": 2, "refreshToken": "{}", "machineId": "{}"}}
+                ]"#,
+                "a".repeat(100),
+                "1".repeat(64),
+                "b".repeat(100),
+                "2".repeat(64)
+            ),
+        )
+        .unwrap();
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager.create_pool(Pool::new("premium", "主池")).unwrap();
+        manager.reload().unwrap();
+
+        let api_keys = ApiKeyManager::new(dir.join("api_keys.json")).unwrap();
+        for (name, binding) in [
+            ("single", serde_json::json!("premium")),
+            ("ordered", serde_json::json!(["premium", "default"])),
+            ("other", serde_json::json!("default")),
+        ] {
+            api_keys
+                .create(CreateApiKeyRequest {
+                    name: name.to_string(),
+                    description: None,
+                    key: None,
+                    pool_id: Some(serde_json::from_value(binding).unwrap()),
+                    websearch_rate_limit_per_hour: None,
+                    allowed_models: None,
+                    denied_models: None,
+                    max_tokens_limit: None,
+                })
+                .unwrap();
+        }
+
+        (manager, api_keys)
+    }
+
+    fn read_json(path: &Path) -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_auto_route_stats() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+
+        std::fs::write(
+            &credentials_path,
+            format!(
+                r#"[{{"refreshToken": "{}", "poolId": "primary"}}]"#,
+                "a".repeat(100)
+            ),
+        )
+        .unwrap();
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager.create_pool(Pool::new("primary", "主池")).unwrap();
+        manager.reload().unwrap();
+
+        for _ in 0..100 {
+            let pool = manager
+                .get_pool_for_api_key(Some(PoolManager::AUTO_ROUTE_POOL_ID))
+                .unwrap();
+            assert_eq!(pool.read().config.id, "primary");
+        }
+
+        // 禁用唯一有凭据的池后自动路由失败，计入 miss
+        manager
+            .update_pool(
+                "primary",
+                UpdatePoolRequest {
+                    enabled: Some(false),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(
+            manager
+                .get_pool_for_api_key(Some(PoolManager::AUTO_ROUTE_POOL_ID))
+                .is_none()
+        );
+
+        let stats = manager.routing_stats();
+        assert_eq!(stats.route_count_total, 100);
+        assert_eq!(stats.miss_count_total, 1);
+        assert_eq!(stats.pools.len(), 1);
+        assert_eq!(stats.pools[0].pool_id, "primary");
+        assert_eq!(stats.pools[0].auto_route_count, 100);
+        assert!(stats.pools[0].last_selected_at.is_some());
+        assert_eq!(
+            stats.pools.iter().map(|p| p.auto_route_count).sum::<u64>(),
+            stats.route_count_total
+        );
+    }
+
+    #[test]
+    fn test_rename_pool() {
+        let dir = tempdir().unwrap();
+        let (manager, api_keys) = setup_rename_env(dir.path());
+        let token_manager = manager
+            .get_pool("premium")
+            .unwrap()
+            .read()
+            .token_manager
+            .clone();
+
+        let summary = manager.rename_pool("premium", "gold", &api_keys).unwrap();
+        assert_eq!(
+            summary,
+            PoolRenameSummary {
+                credentials: 1,
+                api_keys: 2,
+            }
+        );
+
+        // 内存：池 ID 切换，Token 管理器沿用
+        assert!(manager.get_pool("premium").is_none());
+        let pool = manager.get_pool("gold").unwrap();
+        assert_eq!(pool.read().config.id, "gold");
+        assert!(Arc::ptr_eq(&pool.read().token_manager, &token_manager));
+        assert_eq!(pool.read().token_manager.total_count(), 1);
+
+        // 文件：池配置、凭据、API Key 一致
+        let pools = read_json(&dir.path().join("pools.json"));
+        let pool_ids: Vec<&str> = pools["pools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["id"].as_str().unwrap())
+            .collect();
+        assert!(pool_ids.contains(&"gold"));
+        assert!(!pool_ids.contains(&"premium"));
+
+        let credentials = read_json(&dir.path().join("credentials.json"));
+        assert_eq!(credentials[0]["poolId"], "gold");
+        assert!(credentials[1].get("poolId").is_none());
+
+        let keys = read_json(&dir.path().join("api_keys.json"));
+        assert_eq!(keys[0]["poolId"], "gold");
+        assert_eq!(keys[1]["poolId"], serde_json::json!(["gold", "default"]));
+        assert_eq!(keys[2]["poolId"], "default");
+
+        // 重新加载后凭据仍属于新池
+        manager.reload().unwrap();
+        assert_eq!(
+            manager
+                .get_pool("gold")
+                .unwrap()
+                .read()
+                .token_manager
+                .total_count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_delete_pool_with_credentials() {
+        let dir = tempdir().unwrap();
+        let (manager, api_keys) = setup_rename_env(dir.path());
+        manager.create_pool(Pool::new("silver", "银池")).unwrap();
+
+        // 未指定处理策略：拒绝删除并列出池内凭据
+        let err = manager
+            .delete_pool("premium", DeletePoolStrategy::Refuse, &api_keys)
+            .unwrap_err();
+        assert!(matches!(
+            &err,
+            PoolError::PoolNotEmpty { credential_ids, .. } if credential_ids == &vec![1]
+        ));
+        assert!(manager.get_pool("premium").is_some());
+
+        // 目标池不存在或为被删除的池
+        assert!(
+            manager
+                .delete_pool(
+                    "premium",
+                    DeletePoolStrategy::Reassign("missing".into()),
+                    &api_keys
+                )
+                .unwrap_err()
+                .is_pool_not_found()
+        );
+        assert!(matches!(
+            manager.delete_pool(
+                "premium",
+                DeletePoolStrategy::Reassign("premium".into()),
+                &api_keys
+            ),
+            Err(PoolError::InvalidPoolId { .. })
+        ));
+
+        // 重新分配到 silver 池（API Key 绑定一并转移）
+        let summary = manager
+            .delete_pool(
+                "premium",
+                DeletePoolStrategy::Reassign("silver".into()),
+                &api_keys,
+            )
+            .unwrap();
+        assert_eq!(
+            summary,
+            PoolDeleteSummary {
+                credentials: 1,
+                api_keys: 2
+            }
+        );
+        assert!(manager.get_pool("premium").is_none());
+        assert_eq!(
+            manager
+                .get_pool("silver")
+                .unwrap()
+                .read()
+                .token_manager
+                .total_count(),
+            1
+        );
+
+        let credentials = read_json(&dir.path().join("credentials.json"));
+        assert_eq!(credentials[0]["poolId"], "silver");
+        let keys = read_json(&dir.path().join("api_keys.json"));
+        assert_eq!(keys[0]["poolId"], "silver");
+        assert_eq!(keys[1]["poolId"], serde_json::json!(["silver", "default"]));
+        assert_eq!(api_keys.bound_to_pool("silver").len(), 2);
+        let pools = read_json(&dir.path().join("pools.json"));
+        assert!(
+            pools["pools"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .all(|p| p["id"] != "premium")
+        );
+    }
+
+    #[test]
+    fn test_delete_pool_with_bound_api_keys() {
+        use crate::admin::api_keys::CreateApiKeyRequest;
+
+        let dir = tempdir().unwrap();
+        let (manager, api_keys) = setup_rename_env(dir.path());
+        manager.create_pool(Pool::new("bronze", "铜池")).unwrap();
+        for (name, binding) in [
+            ("bronze-only", serde_json::json!("bronze")),
+            ("bronze-first", serde_json::json!(["bronze", "default"])),
+        ] {
+            api_keys
+                .create(CreateApiKeyRequest {
+                    name: name.to_string(),
+                    description: None,
+                    key: None,
+                    pool_id: Some(serde_json::from_value(binding).unwrap()),
+                    websearch_rate_limit_per_hour: None,
+                    allowed_models: None,
+                    denied_models: None,
+                    max_tokens_limit: None,
+                })
+                .unwrap();
+        }
+
+        // 池内没有凭据，但仍被 API Key 绑定：拒绝删除并列出 Key 名称
+        let err = manager
+            .delete_pool("bronze", DeletePoolStrategy::Refuse, &api_keys)
+            .unwrap_err();
+        assert!(matches!(
+            &err,
+            PoolError::PoolHasApiKeys { api_key_names, .. }
+                if api_key_names == &vec!["bronze-only".to_string(), "bronze-first".to_string()]
+        ));
+        assert!(manager.get_pool("bronze").is_some());
+
+        // force：绑定转入默认池，池列表中的重复项被去除
+        let summary = manager
+            .delete_pool(
+                "bronze",
+                DeletePoolStrategy::Reassign(DEFAULT_POOL_ID.to_string()),
+                &api_keys,
+            )
+            .unwrap();
+        assert_eq!(
+            summary,
+            PoolDeleteSummary {
+                credentials: 0,
+                api_keys: 2
+            }
+        );
+        assert!(manager.get_pool("bronze").is_none());
+        let keys = read_json(&dir.path().join("api_keys.json"));
+        assert_eq!(keys[3]["poolId"], "default");
+        assert_eq!(keys[4]["poolId"], serde_json::json!(["default"]));
+        assert!(api_keys.bound_to_pool("bronze").is_empty());
+    }
+
+    #[test]
+    fn test_reload_moves_unknown_pool_credentials_to_default() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        std::fs::write(
+            &credentials_path,
+            format!(
+                r#"[
+                    {{"id": 1, "refreshToken": "{}", "machineId": "{}", "poolId": "deleted"}},
+                    {{"id": 2, "refreshToken": "{}", "machineId": "{}"}}
+                ]"#,
+                "a".repeat(100),
+                "1".repeat(64),
+                "b".repeat(100),
+                "2".repeat(64)
+            ),
+        )
+        .unwrap();
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        assert_eq!(manager.pool_count(), 1);
+        assert_eq!(
+            manager
+                .get_default_pool()
+                .unwrap()
+                .read()
+                .token_manager
+                .total_count(),
+            2
+        );
+
+        // 池恢复后凭据回到原池
+        manager
+            .create_pool(Pool::new("deleted", "恢复的池"))
+            .unwrap();
+        manager.reload().unwrap();
+        assert_eq!(
+            manager
+                .get_pool("deleted")
+                .unwrap()
+                .read()
+                .token_manager
+                .total_count(),
+            1
+        );
+        assert_eq!(
+            manager
+                .get_default_pool()
+                .unwrap()
+                .read()
+                .token_manager
+                .total_count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_performance_history_counts_calls() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        std::fs::write(
+            &credentials_path,
+            format!(
+                r#"[{{"id": 1, "refreshToken": "{}", "machineId": "{}"}}]"#,
+                "a".repeat(100),
+                "1".repeat(64)
+            ),
+        )
+        .unwrap();
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        let token_manager = manager
+            .get_default_pool()
+            .unwrap()
+            .read()
+            .token_manager
+            .clone();
+        for _ in 0..5 {
+            token_manager.report_success_with_time(1, Some(100));
+        }
+        for _ in 0..3 {
+            token_manager.report_failure_with_time(1, Some("500"), Some(500));
+        }
+        // 未携带响应时间的上报（如流式响应中断）不计入
+        token_manager.report_failure_with_time(1, Some("流式响应异常终止"), None);
+
+        // 测试可能跨越分钟边界，按所有桶汇总
+        let sum = |buckets: &[PerformanceBucket]| {
+            buckets.iter().fold((0, 0, 0, 0), |acc, b| {
+                (
+                    acc.0 + b.request_count,
+                    acc.1 + b.success_count,
+                    acc.2 + b.failure_count,
+                    acc.3 + b.avg_latency_ms * b.request_count,
+                )
+            })
+        };
+        let history = manager.get_performance_history(DEFAULT_POOL_ID);
+        assert_eq!(sum(&history), (8, 5, 3, 2000));
+        if history.len() == 1 {
+            assert_eq!(history[0].avg_latency_ms, 250);
+        }
+
+        // 重新加载后保留历史
+        manager.reload().unwrap();
+        assert_eq!(sum(&manager.get_performance_history(DEFAULT_POOL_ID)).0, 8);
+        assert!(manager.get_performance_history("missing").is_empty());
+    }
+
+    #[test]
+    fn test_rename_pool_rejects_invalid_requests() {
+        let dir = tempdir().unwrap();
+        let (manager, api_keys) = setup_rename_env(dir.path());
+        manager.create_pool(Pool::new("silver", "银池")).unwrap();
+
+        assert!(matches!(
+            manager.rename_pool(DEFAULT_POOL_ID, "main", &api_keys),
+            Err(PoolError::CannotRenameDefaultPool)
+        ));
+        assert!(
+            manager
+                .rename_pool("premium", "silver", &api_keys)
+                .unwrap_err()
+                .is_pool_already_exists()
+        );
+        assert!(
+            manager
+                .rename_pool("premium", DEFAULT_POOL_ID, &api_keys)
+                .unwrap_err()
+                .is_pool_already_exists()
+        );
+        assert!(
+            manager
+                .rename_pool("missing", "gold", &api_keys)
+                .unwrap_err()
+                .is_pool_not_found()
+        );
+        assert!(matches!(
+            manager.rename_pool("premium", PoolManager::AUTO_ROUTE_POOL_ID, &api_keys),
+            Err(PoolError::InvalidPoolId { .. })
+        ));
+        assert!(manager.get_pool("premium").is_some());
+    }
+
+    #[test]
+    fn test_rename_pool_write_failure_keeps_state() {
+        let dir = tempdir().unwrap();
+        let (manager, api_keys) = setup_rename_env(dir.path());
+
+        let pools_before = std::fs::read_to_string(dir.path().join("pools.json")).unwrap();
+
+        // 将 API Key 文件替换为目录，使其写入失败
+        let api_keys_path = dir.path().join("api_keys.json");
+        std::fs::remove_file(&api_keys_path).unwrap();
+        std::fs::create_dir(&api_keys_path).unwrap();
+
+        let err = manager.rename_pool("premium", "gold", &api_keys).unwrap_err();
+        assert!(matches!(err, PoolError::PersistFailed { .. }));
+
+        // 池配置未变更，凭据的所属池已恢复，也没有残留临时文件
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("pools.json")).unwrap(),
+            pools_before
+        );
+        let credentials = read_json(&dir.path().join("credentials.json"));
+        assert_eq!(credentials[0]["poolId"], "premium");
+        assert!(credentials[1].get("poolId").is_none());
+        let leftovers: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        // 内存状态未变更
+        assert!(manager.get_pool("premium").is_some());
+        assert!(manager.get_pool("gold").is_none());
+        let keys = api_keys.list();
+        assert_eq!(keys[0].pool_id, Some("premium".into()));
+    }
+
+    #[test]
+    fn test_pool_changes_write_in_memory_credentials() {
+        let dir = tempdir().unwrap();
+        let (manager, api_keys) = setup_rename_env(dir.path());
+        let credentials_path = dir.path().join("credentials.json");
+
+        // 无法解析的凭据文件：拒绝覆盖，文件和池状态不变
+        std::fs::write(&credentials_path, "not json").unwrap();
+        let err = manager.rename_pool("premium", "gold", &api_keys).unwrap_err();
+        assert!(matches!(err, PoolError::PersistFailed { .. }));
+        assert_eq!(
+            std::fs::read_to_string(&credentials_path).unwrap(),
+            "not json"
+        );
+        assert!(manager.get_pool("premium").is_some());
+
+        // 磁盘上的 Token 落后于内存（刷新后尚未回写）：写入内存中的 Token
+        std::fs::write(
+            &credentials_path,
+            format!(
+                r#"[{{"id": 1, "refreshToken": "{}", "poolId": "premium"}}, {{"id": 2, "refreshToken": "{}"}}]"#,
+                "stale".repeat(20),
+                "b".repeat(100)
+            ),
+        )
+        .unwrap();
+        manager.rename_pool("premium", "gold", &api_keys).unwrap();
+        let credentials = read_json(&credentials_path);
+        assert_eq!(credentials[0]["poolId"], "gold");
+        assert_eq!(credentials[0]["refreshToken"], "a".repeat(100));
+    }
+
+    /// 写入 `count` 个凭据（ID 1..=count，优先级与 ID 相同，均属于默认池）
+    fn write_numbered_credentials(path: &Path, count: u64) {
+        let credentials: Vec<serde_json::Value> = (1..=count)
+            .map(|id| {
+                serde_json::json!({
+                    "id": id,
+                    "refreshToken": "a".repeat(100),
+                    "priority": id,
+                })
+            })
+            .collect();
+        std::fs::write(path, serde_json::to_string(&credentials).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_rebalance_even() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        write_numbered_credentials(&credentials_path, 10);
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager.create_pool(Pool::new("alpha", "Alpha")).unwrap();
+        manager.create_pool(Pool::new("beta", "Beta")).unwrap();
+
+        // 池按 ID 排序（alpha、beta、default），余数分给靠前的池：4 / 3 / 3
+        let result = manager.rebalance(RebalanceStrategy::Even).unwrap();
+        assert_eq!(result.moved.len(), 7);
+        assert!(result.moved.iter().all(|m| m.from_pool == DEFAULT_POOL_ID));
+        assert_eq!(
+            result.moved[0],
+            CredentialMove {
+                credential_id: 4,
+                from_pool: DEFAULT_POOL_ID.to_string(),
+                to_pool: "alpha".to_string(),
+            }
+        );
+
+        let count = |id: &str| {
+            manager
+                .get_pool(id)
+                .unwrap()
+                .read()
+                .token_manager
+                .total_count()
+        };
+        assert_eq!(count("alpha"), 4);
+        assert_eq!(count("beta"), 3);
+        assert_eq!(count(DEFAULT_POOL_ID), 3);
+
+        // 凭据文件已更新
+        let saved = CredentialsConfig::load(&credentials_path).unwrap();
+        assert_eq!(saved.credential_ids_in_pool("alpha"), vec![4, 5, 6, 7]);
+        assert_eq!(saved.credential_ids_in_pool("beta"), vec![8, 9, 10]);
+
+        // 已均衡时不再移动
+        assert!(
+            manager
+                .rebalance(RebalanceStrategy::Even)
+                .unwrap()
+                .moved
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_rebalance_by_priority() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        write_numbered_credentials(&credentials_path, 4);
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager
+            .create_pool(Pool::new("gold", "金池").with_priority(0))
+            .unwrap();
+        manager
+            .update_pool(
+                DEFAULT_POOL_ID,
+                UpdatePoolRequest {
+                    priority: Some(1),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // 2 个池、4 个凭据：优先级最高的 2 个进入 gold
+        let result = manager.rebalance(RebalanceStrategy::ByPriority).unwrap();
+        let moved: Vec<u64> = result.moved.iter().map(|m| m.credential_id).collect();
+        assert_eq!(moved, vec![1, 2]);
+        assert!(result.moved.iter().all(|m| m.to_pool == "gold"));
+    }
+
+    #[test]
+    fn test_rebalance_by_health_creates_quarantine_pool() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        write_numbered_credentials(&credentials_path, 3);
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager
+            .get_default_pool()
+            .unwrap()
+            .read()
+            .token_manager
+            .set_disabled(2, true, "admin")
+            .unwrap();
+
+        let result = manager.rebalance(RebalanceStrategy::ByHealth).unwrap();
+        assert_eq!(
+            result.moved,
+            vec![CredentialMove {
+                credential_id: 2,
+                from_pool: DEFAULT_POOL_ID.to_string(),
+                to_pool: QUARANTINE_POOL_ID.to_string(),
+            }]
+        );
+
+        let quarantine = manager.get_pool(QUARANTINE_POOL_ID).unwrap();
+        assert!(!quarantine.read().is_enabled());
+        assert_eq!(quarantine.read().token_manager.total_count(), 1);
+        let saved = PoolsConfig::load(&pools_path).unwrap();
+        assert!(saved.get(QUARANTINE_POOL_ID).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_skips_disabled_pools() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        std::fs::write(&credentials_path, "[]").unwrap();
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager.create_pool(Pool::new("alpha", "Alpha")).unwrap();
+        manager.create_pool(Pool::new("beta", "Beta")).unwrap();
+        manager.set_pool_disabled("beta", true).unwrap();
+
+        let reports = manager.warm_up().await;
+        let ids: Vec<&str> = reports.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["alpha", DEFAULT_POOL_ID]);
+        assert!(reports.iter().all(|(_, r)| r.total() == 0));
+    }
+
+    #[test]
+    fn test_pool_session_cache_overrides() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        std::fs::write(&credentials_path, "[]").unwrap();
+
+        let config = Config {
+            session_cache_max_capacity: 2000,
+            session_cache_ttl_secs: 1800,
+            ..Config::default()
+        };
+        let manager = PoolManager::new(config, None, &pools_path, &credentials_path).unwrap();
+        manager
+            .create_pool(Pool::new("tiny", "小池").with_session_cache(Some(50), None))
+            .unwrap();
+
+        let snapshot = |id: &str| manager.snapshot().into_iter().find(|p| p.id == id).unwrap();
+        let tiny = snapshot("tiny");
+        assert_eq!(tiny.session_cache_capacity, 50);
+        assert_eq!(tiny.session_cache_ttl_secs, 1800);
+        assert_eq!(snapshot(DEFAULT_POOL_ID).session_cache_capacity, 2000);
+
+        // 更新池配置后立即重建缓存，并持久化覆盖值
+        manager
+            .update_pool(
+                "tiny",
+                UpdatePoolRequest {
+                    session_cache_ttl_secs: Some(300),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let tiny = snapshot("tiny");
+        assert_eq!(tiny.session_cache_capacity, 50);
+        assert_eq!(tiny.session_cache_ttl_secs, 300);
+        let saved = PoolsConfig::load(&pools_path).unwrap();
+        assert_eq!(saved.get("tiny").unwrap().session_cache_ttl_secs, Some(300));
+
+        // 0 清除覆盖，恢复全局配置
+        manager
+            .update_pool(
+                "tiny",
+                UpdatePoolRequest {
+                    session_cache_max_capacity: Some(0),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(snapshot("tiny").session_cache_capacity, 2000);
+        assert!(
+            manager
+                .get_pool("tiny")
+                .unwrap()
+                .read()
+                .config
+                .session_cache_max_capacity
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_pool_timeout_overrides() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        std::fs::write(&credentials_path, "[]").unwrap();
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager
+            .create_pool(Pool::new("proxied", "代理池").with_timeouts(Some(30), None, Some(600)))
+            .unwrap();
+
+        let config = |id: &str| {
+            manager
+                .get_pool(id)
+                .unwrap()
+                .read()
+                .token_manager
+                .config()
+                .clone()
+        };
+        let proxied = config("proxied");
+        assert_eq!(proxied.connect_timeout_secs, 30);
+        assert_eq!(proxied.refresh_request_timeout_secs, 60);
+        assert_eq!(proxied.upstream_first_byte_timeout_secs, 600);
+        assert_eq!(config(DEFAULT_POOL_ID).connect_timeout_secs, 10);
+
+        // 超出范围的覆盖被拒绝，不修改任何状态
+        let err = manager
+            .update_pool(
+                "proxied",
+                UpdatePoolRequest {
+                    refresh_request_timeout_secs: Some(10_000),
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, PoolError::InvalidPoolConfig { .. }));
+        let err = manager
+            .create_pool(Pool::new("bad", "无效").with_timeouts(Some(1000), None, None))
+            .unwrap_err();
+        assert!(matches!(err, PoolError::InvalidPoolConfig { .. }));
+        // 会话缓存容量覆盖同样按全局下限校验
+        let err = manager
+            .create_pool(Pool::new("tiny", "过小").with_session_cache(Some(5), None))
+            .unwrap_err();
+        assert!(matches!(err, PoolError::InvalidPoolConfig { .. }));
+        let err = manager
+            .update_pool(
+                "proxied",
+                UpdatePoolRequest {
+                    session_cache_max_capacity: Some(5),
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert!(matches!(err, PoolError::InvalidPoolConfig { .. }));
+
+        // 更新后只重新加载该池，新的 Token 管理器使用新超时；0 清除覆盖
+        let token_manager = |id: &str| manager.get_pool(id).unwrap().read().token_manager.clone();
+        let default_manager = token_manager(DEFAULT_POOL_ID);
+        let proxied_manager = token_manager("proxied");
+        manager
+            .update_pool(
+                "proxied",
+                UpdatePoolRequest {
+                    connect_timeout_secs: Some(0),
+                    refresh_request_timeout_secs: Some(120),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let proxied = config("proxied");
+        assert_eq!(proxied.connect_timeout_secs, 10);
+        assert_eq!(proxied.refresh_request_timeout_secs, 120);
+        assert!(!Arc::ptr_eq(&token_manager("proxied"), &proxied_manager));
+        assert!(Arc::ptr_eq(
+            &token_manager(DEFAULT_POOL_ID),
+            &default_manager
+        ));
+        let saved = PoolsConfig::load(&pools_path).unwrap();
+        let saved = saved.get("proxied").unwrap();
+        assert_eq!(saved.connect_timeout_secs, None);
+        assert_eq!(saved.upstream_first_byte_timeout_secs, Some(600));
+    }
+
+    #[test]
+    fn test_reload_keeps_call_timeline() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        let credentials = serde_json::json!([{
+            "id": 1,
+            "refreshToken": "a".repeat(100),
+            "machineId": "0".repeat(64),
+        }]);
+        std::fs::write(&credentials_path, credentials.to_string()).unwrap();
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        let token_manager = || {
+            manager
+                .get_pool(DEFAULT_POOL_ID)
+                .unwrap()
+                .read()
+                .token_manager
+                .clone()
+        };
+        token_manager().record_call(1, "claude-sonnet-4.5", false, 100);
+
+        // 重新加载后新的 Token 管理器沿用凭据的调用时间线
+        manager.reload().unwrap();
+        let buckets = token_manager().call_timeline(1, 5, 5).unwrap();
+        assert_eq!(buckets.iter().map(|b| b.failures).sum::<u64>(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_credential_migrates_sessions() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        let expires_at = (Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        let credentials: Vec<serde_json::Value> = [(1, "alpha"), (2, "alpha"), (3, "beta")]
+            .into_iter()
+            .map(|(id, pool_id)| {
+                serde_json::json!({
+                    "id": id,
+                    "accessToken": format!("token-{}", id),
+                    "refreshToken": "a".repeat(100),
+                    "expiresAt": expires_at,
+                    "machineId": "0".repeat(64),
+                    "priority": id,
+                    "poolId": pool_id,
+                })
+            })
+            .collect();
+        std::fs::write(
+            &credentials_path,
+            serde_json::to_string(&credentials).unwrap(),
+        )
+        .unwrap();
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager.create_pool(Pool::new("alpha", "Alpha")).unwrap();
+        manager.create_pool(Pool::new("beta", "Beta")).unwrap();
+        manager.reload().unwrap();
+        let alpha = manager
+            .get_pool("alpha")
+            .unwrap()
+            .read()
+            .token_manager
+            .clone();
+        let beta = manager
+            .get_pool("beta")
+            .unwrap()
+            .read()
+            .token_manager
+            .clone();
+
+        // 会话绑定到源池中的凭据
+        let ctx = alpha.acquire_context_for_session(Some("s1")).await.unwrap();
+        let id = ctx.id;
+
+        let moved = manager.transfer_credential(id, "beta", true).unwrap();
+        assert_eq!(moved, 1);
+        assert!(!alpha.contains(id));
+        assert!(beta.contains(id));
+
+        // 迁移后的会话在目标池中继续路由到同一凭据
+        let ctx = beta.acquire_context_for_session(Some("s1")).await.unwrap();
+        assert_eq!(ctx.id, id);
+        assert_eq!(ctx.credentials.pool_id.as_deref(), Some("beta"));
+
+        // 持久化新的 poolId
+        let saved = CredentialsConfig::load(&credentials_path).unwrap();
+        assert!(saved.credential_ids_in_pool("beta").contains(&id));
+        assert!(!saved.credential_ids_in_pool("alpha").contains(&id));
+
+        // 已在目标池时不移动；目标池不存在时报错
+        assert_eq!(manager.transfer_credential(id, "beta", true).unwrap(), 0);
+        assert!(matches!(
+            manager.transfer_credential(id, "missing", true),
+            Err(PoolError::PoolNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_assign_credential_updates_source_and_target_pools() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        let credentials: Vec<serde_json::Value> = [(1, "alpha"), (2, "alpha"), (3, "beta")]
+            .into_iter()
+            .map(|(id, pool_id)| {
+                serde_json::json!({
+                    "id": id,
+                    "refreshToken": "a".repeat(100),
+                    "priority": id,
+                    "poolId": pool_id,
+                })
+            })
+            .collect();
+        std::fs::write(
+            &credentials_path,
+            serde_json::to_string(&credentials).unwrap(),
+        )
+        .unwrap();
+
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager.create_pool(Pool::new("alpha", "Alpha")).unwrap();
+        manager.create_pool(Pool::new("beta", "Beta")).unwrap();
+        manager.reload().unwrap();
+        let default = manager.get_default_pool().unwrap();
+        let alpha = manager.get_pool("alpha").unwrap();
+        let beta = manager.get_pool("beta").unwrap();
+
+        let assignment = manager.assign_credential_to_pool(2, "beta").unwrap();
+        assert_eq!(assignment.source_pool_id, "alpha");
+        assert_eq!(assignment.credential.id, 2);
+        assert_eq!(assignment.credential.pool_id.as_deref(), Some("beta"));
+
+        // 凭据出现在目标池快照中，不再出现在源池快照中
+        let ids = |pool: &PoolRuntime| -> Vec<u64> {
+            pool.token_manager
+                .snapshot()
+                .entries
+                .iter()
+                .map(|e| e.id)
+                .collect()
+        };
+        assert_eq!(ids(&alpha.read()), vec![1]);
+        assert_eq!(ids(&beta.read()), vec![2, 3]);
+
+        // 只更新源池和目标池，不重新加载其他池
+        assert!(Arc::ptr_eq(&manager.get_default_pool().unwrap(), &default));
+        assert!(Arc::ptr_eq(&manager.get_pool("beta").unwrap(), &beta));
+
+        // 持久化新的 poolId
+        let saved = CredentialsConfig::load(&credentials_path).unwrap();
+        assert_eq!(saved.credential_ids_in_pool("beta"), vec![2, 3]);
+        assert_eq!(saved.credential_ids_in_pool("alpha"), vec![1]);
+
+        // 目标池不存在、已禁用或凭据不存在时报错，且不移动凭据
+        assert!(matches!(
+            manager.assign_credential_to_pool(1, "missing"),
+            Err(PoolError::PoolNotFound { .. })
+        ));
+        manager.set_pool_disabled("beta", true).unwrap();
+        assert!(matches!(
+            manager.assign_credential_to_pool(1, "beta"),
+            Err(PoolError::PoolDisabled { .. })
+        ));
+        assert!(matches!(
+            manager.assign_credential_to_pool(99, "alpha"),
+            Err(PoolError::CredentialNotFound { credential_id: 99 })
+        ));
+        assert_eq!(ids(&manager.get_pool("alpha").unwrap().read()), vec![1]);
+
+        // 写入目标池失败（凭据文件损坏，拒绝覆盖）：凭据留在源池
+        manager.set_pool_disabled("beta", false).unwrap();
+        std::fs::write(&credentials_path, "not json").unwrap();
+        assert!(matches!(
+            manager.assign_credential_to_pool(1, "beta"),
+            Err(PoolError::PersistFailed { .. })
+        ));
+        assert_eq!(ids(&alpha.read()), vec![1]);
+        assert_eq!(ids(&beta.read()), vec![2, 3]);
+        assert_eq!(manager.find_credential_pool(1).unwrap().id, "alpha");
+    }
+
+    #[test]
+    fn test_concurrent_updates_on_different_pools() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        std::fs::write(&credentials_path, "[]").unwrap();
+
+        let manager = Arc::new(
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap(),
+        );
+        manager.create_pool(Pool::new("alpha", "Alpha")).unwrap();
+        manager.create_pool(Pool::new("beta", "Beta")).unwrap();
+
+        // 两个池同时更新，互不覆盖
+        let handles: Vec<_> = ["alpha", "beta"]
+            .into_iter()
+            .map(|pool_id| {
+                let manager = manager.clone();
+                std::thread::spawn(move || {
+                    for priority in 1..=20 {
+                        manager
+                            .update_pool(
+                                pool_id,
+                                UpdatePoolRequest {
+                                    priority: Some(priority),
+                                    name: Some(format!("{}-{}", pool_id, priority)),
+                                    ..Default::default()
+                                },
+                            )
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        for pool_id in ["alpha", "beta"] {
+            let (priority, name) = manager
+                .with_pool(pool_id, |p| (p.config.priority, p.config.name.clone()))
+                .unwrap();
+            assert_eq!(priority, 20);
+            assert_eq!(name, format!("{}-20", pool_id));
+        }
+        let saved = PoolsConfig::load(&pools_path).unwrap();
+        let saved_priority = |id: &str| saved.pools.iter().find(|p| p.id == id).map(|p| p.priority);
+        assert_eq!(saved_priority("alpha"), Some(20));
+        assert_eq!(saved_priority("beta"), Some(20));
+
+        // 持有 alpha 的写锁时，读取 beta 不被阻塞
+        let alpha = manager.get_pool("alpha").unwrap();
+        let _alpha_guard = alpha.write();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let reader = manager.clone();
+        std::thread::spawn(move || {
+            let pool = reader.get_pool_for_api_key(Some("beta")).unwrap();
+            let name = pool.read().config.name.clone();
+            tx.send(name).unwrap();
+        });
+        let name = rx
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .expect("读取 beta 被 alpha 的写锁阻塞");
+        assert_eq!(name, "beta-20");
+        assert_eq!(manager.with_pool("missing", |_| ()), None);
+    }
+
+    #[test]
+    fn test_pool_provider_cached_and_rebuilt_on_proxy_change() {
+        let dir = tempdir().unwrap();
+        let credentials_path = dir.path().join("credentials.json");
+        std::fs::write(&credentials_path, "[]").unwrap();
+        let manager = PoolManager::new(
+            Config::default(),
+            None,
+            &dir.path().join("pools.json"),
+            &credentials_path,
+        )
+        .unwrap();
+        manager.create_pool(Pool::new("proxied", "代理池")).unwrap();
+        let provider = |manager: &PoolManager| {
+            manager
+                .with_pool("proxied", |p| p.provider.clone())
+                .unwrap()
+        };
+
+        // 多次请求复用同一个 KiroProvider，非代理字段的更新不会重建
+        let first = provider(&manager);
+        assert!(Arc::ptr_eq(&first, &provider(&manager)));
+        manager
+            .update_pool(
+                "proxied",
+                UpdatePoolRequest {
+                    name: Some("改名".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &provider(&manager)));
+
+        // 代理变更后换成新的 KiroProvider，仍共享池的 Token 管理器
+        manager
+            .update_pool(
+                "proxied",
+                UpdatePoolRequest {
+                    proxy_url: Some("http://127.0.0.1:18080".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let second = provider(&manager);
+        assert!(!Arc::ptr_eq(&first, &second));
+        let token_manager = manager
+            .with_pool("proxied", |p| p.token_manager.clone())
+            .unwrap();
+        assert!(std::ptr::eq(second.token_manager(), &*token_manager));
+        assert_eq!(
+            manager
+                .with_pool("proxied", |p| p.proxy_config.clone())
+                .unwrap()
+                .map(|p| p.url),
+            Some("http://127.0.0.1:18080".to_string())
+        );
+    }
+
+    #[test]
+    fn test_scheduling_mode_persisted_across_reload() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        write_numbered_credentials(&credentials_path, 2);
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        manager.create_pool(Pool::new("alpha", "Alpha")).unwrap();
+        let mode = |manager: &PoolManager, pool_id: &str| {
+            manager
+                .with_pool(pool_id, |p| {
+                    (p.config.scheduling_mode, p.token_manager.get_scheduling_mode())
+                })
+                .unwrap()
+        };
+
+        manager
+            .set_pool_scheduling_mode("alpha", SchedulingMode::PriorityFill)
+            .unwrap();
+        assert_eq!(
+            mode(&manager, "alpha"),
+            (SchedulingMode::PriorityFill, SchedulingMode::PriorityFill)
+        );
+
+        // 按凭据定位所在池
+        let pool_id = manager
+            .set_credential_pool_scheduling_mode(1, SchedulingMode::PriorityFill)
+            .unwrap();
+        assert_eq!(pool_id, DEFAULT_POOL_ID);
+        assert!(matches!(
+            manager.set_credential_pool_scheduling_mode(99, SchedulingMode::RoundRobin),
+            Err(PoolError::CredentialNotFound { credential_id: 99 })
+        ));
+
+        // 重新加载（模拟重启）后从 pools.json 恢复
+        manager.reload().unwrap();
+        let restarted =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        for manager in [&manager, &restarted] {
+            for pool_id in ["alpha", DEFAULT_POOL_ID] {
+                assert_eq!(
+                    mode(manager, pool_id),
+                    (SchedulingMode::PriorityFill, SchedulingMode::PriorityFill)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pool_credential_defaults_inherited() {
+        let dir = tempdir().unwrap();
+        let pools_path = dir.path().join("pools.json");
+        let credentials_path = dir.path().join("credentials.json");
+        write_numbered_credentials(&credentials_path, 2);
+        let manager =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        let settings = |manager: &PoolManager| {
+            manager
+                .with_pool(DEFAULT_POOL_ID, |p| {
+                    let entry = p.token_manager.snapshot().entries.remove(0);
+                    (
+                        entry.effective_region,
+                        entry.region_source,
+                        entry.proxy_source,
+                    )
+                })
+                .unwrap()
+        };
+        assert_eq!(
+            settings(&manager),
+            ("us-east-1".to_string(), SettingSource::Global, None)
+        );
+
+        // 非法的默认认证方式被拒绝，配置保持不变
+        let invalid = manager.update_pool(
+            DEFAULT_POOL_ID,
+            UpdatePoolRequest {
+                default_auth_method: Some("oauth".to_string()),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(invalid, Err(PoolError::InvalidPoolConfig { .. })));
+        let invalid = manager.update_pool(
+            DEFAULT_POOL_ID,
+            UpdatePoolRequest {
+                default_machine_id: Some("not-a-machine-id".to_string()),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(invalid, Err(PoolError::InvalidPoolConfig { .. })));
+
+        let machine_id = "2582956e-cc88-4669-b546-07adbffcb894";
+        manager
+            .update_pool(
+                DEFAULT_POOL_ID,
+                UpdatePoolRequest {
+                    default_region: Some("eu-west-1".to_string()),
+                    default_auth_method: Some("IDC".to_string()),
+                    default_machine_id: Some(machine_id.to_string()),
+                    default_proxy_url: Some("http://127.0.0.1:18081".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let inherited = (
+            "eu-west-1".to_string(),
+            SettingSource::Pool,
+            Some(SettingSource::Pool),
+        );
+        assert_eq!(settings(&manager), inherited);
+        assert_eq!(
+            manager
+                .with_pool(DEFAULT_POOL_ID, |p| p.token_manager.credential_defaults())
+                .unwrap()
+                .auth_method
+                .as_deref(),
+            Some("idc")
+        );
+
+        // 重新加载后从 pools.json 恢复，凭据文件不写入池默认值
+        let restarted =
+            PoolManager::new(Config::default(), None, &pools_path, &credentials_path).unwrap();
+        assert_eq!(settings(&restarted), inherited);
+        assert_eq!(
+            restarted
+                .with_pool(DEFAULT_POOL_ID, |p| p.token_manager.credential_defaults())
+                .unwrap()
+                .machine_id
+                .as_deref(),
+            Some(machine_id)
+        );
+        let saved = std::fs::read_to_string(&credentials_path).unwrap();
+        assert!(!saved.contains("eu-west-1"), "{}", saved);
+
+        // 空字符串清除默认值，回退到全局配置
+        manager
+            .update_pool(
+                DEFAULT_POOL_ID,
+                UpdatePoolRequest {
+                    default_region: Some(String::new()),
+                    default_auth_method: Some(String::new()),
+                    default_proxy_url: Some(String::new()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            settings(&manager),
+            ("us-east-1".to_string(), SettingSource::Global, None)
+        );
+    }
 }
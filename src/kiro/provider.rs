@@ -4,17 +4,24 @@
 //! 支持流式和非流式请求
 //! 支持多凭据故障转移和重试
 
-use reqwest::Client;
-use reqwest::header::{AUTHORIZATION, CONNECTION, CONTENT_TYPE, HOST, HeaderMap, HeaderValue};
+use reqwest::{Client, RequestBuilder};
+use reqwest::header::{CONTENT_ENCODING, HeaderMap, HeaderName, HeaderValue};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::OwnedSemaphorePermit;
 use tokio::time::sleep;
-use uuid::Uuid;
 
-use crate::http_client::{ProxyConfig, build_client};
-use crate::kiro::machine_id;
+use crate::admin::events::AdminEvent;
+use crate::http_client::{ProxyConfig, shared_client};
+use crate::kiro::compression::RequestBody;
+use crate::kiro::error::{ProviderError, TimeoutKind};
+use crate::kiro::headers::{self, HeaderContext, UpstreamApi};
+use crate::kiro::model::credentials::validate_custom_headers;
 use crate::kiro::token_manager::{CallContext, MultiTokenManager};
+use crate::kiro::upstream_error::{UpstreamErrorKind, parse_retry_after};
 
+#[cfg(test)]
+use crate::anthropic::mock_provider::{MockKiroProvider, MockResponse};
 #[cfg(test)]
 use crate::kiro::model::credentials::KiroCredentials;
 
@@ -24,6 +31,60 @@ const MAX_RETRIES_PER_CREDENTIAL: usize = 3;
 /// 总重试次数硬上限（避免无限重试）
 const MAX_TOTAL_RETRIES: usize = 9;
 
+/// 上游可能返回请求 ID 的响应头（按优先级）
+const UPSTREAM_REQUEST_ID_HEADERS: &[&str] =
+    &["x-amzn-requestid", "x-amzn-request-id", "x-amz-request-id"];
+
+/// MCP 调用在凭据时间线中记录的模型名（MCP 请求不携带模型）
+const MCP_TIMELINE_MODEL: &str = "mcp";
+
+/// 上游请求失败错误
+///
+/// 在错误信息之外携带失败原因和上游请求 ID，便于 handler 决定重试/响应状态码
+/// 以及向 Kiro 支持反馈问题；上游 429 携带 `Retry-After` 时同时记录退避时长，
+/// 由 handler 透传给客户端
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct UpstreamError {
+    /// 错误信息
+    pub message: String,
+    /// 失败原因
+    pub error: ProviderError,
+    /// 上游请求 ID
+    pub request_id: Option<String>,
+    /// 上游要求的退避时长（429 `Retry-After`）
+    pub retry_after: Option<Duration>,
+}
+
+impl UpstreamError {
+    /// 从 anyhow 错误中提取上游请求 ID
+    pub fn request_id_of(error: &anyhow::Error) -> Option<String> {
+        error
+            .downcast_ref::<UpstreamError>()
+            .and_then(|e| e.request_id.clone())
+    }
+
+    /// 从 anyhow 错误中提取上游要求的退避时长
+    pub fn retry_after_of(error: &anyhow::Error) -> Option<Duration> {
+        error
+            .downcast_ref::<UpstreamError>()
+            .and_then(|e| e.retry_after)
+    }
+}
+
+/// 响应扩展：实际服务该请求的凭据 ID
+///
+/// 流式响应在 HTTP 200 之后仍可能中断，handler 据此上报凭据失败
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServingCredential(pub u64);
+
+/// 响应扩展：凭据并发名额
+///
+/// 响应头返回后响应体仍在传输，名额需由 handler 持有到响应体读完（或流结束）才释放，
+/// 否则 `maxConcurrentRequests` 无法约束流式请求
+#[derive(Debug, Clone)]
+pub struct CredentialPermit(#[allow(dead_code)] Arc<OwnedSemaphorePermit>);
+
 /// Kiro API Provider
 ///
 /// 核心组件，负责与 Kiro API 通信
@@ -31,6 +92,11 @@ const MAX_TOTAL_RETRIES: usize = 9;
 pub struct KiroProvider {
     token_manager: Arc<MultiTokenManager>,
     client: Client,
+    /// `client` 使用的代理（池级/全局）
+    proxy: Option<ProxyConfig>,
+    /// 测试用 Mock（设置后对话请求直接返回预置响应）
+    #[cfg(test)]
+    mock: Option<MockKiroProvider>,
 }
 
 impl KiroProvider {
@@ -42,40 +108,102 @@ impl KiroProvider {
 
     /// 创建带代理配置的 KiroProvider 实例
     pub fn with_proxy(token_manager: Arc<MultiTokenManager>, proxy: Option<ProxyConfig>) -> Self {
-        let client = build_client(proxy.as_ref(), 720, token_manager.config().tls_backend)
-            .expect("创建 HTTP 客户端失败");
+        let config = token_manager.config();
+        let client = shared_client(
+            proxy.as_ref(),
+            config.upstream_http_timeouts(),
+            config.tls_options(),
+        )
+        .expect("创建 HTTP 客户端失败");
 
         Self {
             token_manager,
             client,
+            proxy,
+            #[cfg(test)]
+            mock: None,
         }
     }
 
+    /// 创建返回预置响应的 KiroProvider（仅测试使用）
+    ///
+    /// 使用持有有效 Token 的单个凭据，不会发出任何网络请求
+    #[cfg(test)]
+    pub fn new_mock(responses: Vec<MockResponse>) -> Self {
+        Self::new_mock_with_config(crate::model::config::Config::default(), responses)
+    }
+
+    /// 创建使用指定配置、返回预置响应的 KiroProvider（仅测试使用）
+    #[cfg(test)]
+    pub fn new_mock_with_config(
+        config: crate::model::config::Config,
+        responses: Vec<MockResponse>,
+    ) -> Self {
+        let credentials = KiroCredentials {
+            access_token: Some("mock-access-token".to_string()),
+            refresh_token: Some("a".repeat(150)),
+            expires_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+            ..KiroCredentials::default()
+        };
+        let token_manager = MultiTokenManager::new(config, vec![credentials], None, None)
+            .expect("创建 Mock TokenManager 失败");
+
+        let mut provider = Self::new(Arc::new(token_manager));
+        provider.mock = Some(MockKiroProvider::new(responses));
+        provider
+    }
+
+    /// 获取 Mock（仅测试使用）
+    #[cfg(test)]
+    pub fn mock(&self) -> Option<&MockKiroProvider> {
+        self.mock.as_ref()
+    }
+
     /// 获取 token_manager 的引用
     #[allow(dead_code)]
     pub fn token_manager(&self) -> &MultiTokenManager {
         &self.token_manager
     }
 
+    /// 调用上下文使用的 HTTP Client
+    ///
+    /// 凭据级或池默认代理与池级代理不同时，使用该代理的共享 Client
+    fn client_for(&self, ctx: &CallContext) -> Client {
+        if ctx.proxy_config == self.proxy {
+            return self.client.clone();
+        }
+        let config = self.token_manager.config();
+        shared_client(
+            ctx.proxy_config.as_ref(),
+            config.upstream_http_timeouts(),
+            config.tls_options(),
+        )
+        .unwrap_or_else(|e| {
+            tracing::warn!(
+                "创建凭据 #{} 的代理 HTTP 客户端失败，使用池级代理: {}",
+                ctx.id,
+                e
+            );
+            self.client.clone()
+        })
+    }
+
     /// 获取 API 基础 URL
     pub fn base_url(&self) -> String {
-        format!(
-            "https://q.{}.amazonaws.com/generateAssistantResponse",
-            self.token_manager.config().region
-        )
+        self.token_manager
+            .config()
+            .kiro_api_endpoint()
+            .url("/generateAssistantResponse")
     }
 
     /// 获取 MCP API URL
     pub fn mcp_url(&self) -> String {
-        format!(
-            "https://q.{}.amazonaws.com/mcp",
-            self.token_manager.config().region
-        )
+        self.token_manager.config().kiro_api_endpoint().url("/mcp")
     }
 
     /// 获取 API 基础域名
     pub fn base_domain(&self) -> String {
-        format!("q.{}.amazonaws.com", self.token_manager.config().region)
+        self.token_manager.config().kiro_api_endpoint().host
     }
 
     /// 构建请求头
@@ -83,108 +211,59 @@ impl KiroProvider {
     /// # Arguments
     /// * `ctx` - API 调用上下文，包含凭据和 token
     fn build_headers(&self, ctx: &CallContext) -> anyhow::Result<HeaderMap> {
-        let config = self.token_manager.config();
-
-        let machine_id = machine_id::generate_from_credentials(&ctx.credentials, config)
-            .ok_or_else(|| anyhow::anyhow!("无法生成 machine_id，请检查凭证配置"))?;
-
-        let kiro_version = &config.kiro_version;
-        let os_name = &config.system_version;
-        let node_version = &config.node_version;
-
-        let x_amz_user_agent = format!("aws-sdk-js/1.0.27 KiroIDE-{}-{}", kiro_version, machine_id);
-
-        let user_agent = format!(
-            "aws-sdk-js/1.0.27 ua/2.1 os/{} lang/js md/nodejs#{} api/codewhispererstreaming#1.0.27 m/E KiroIDE-{}-{}",
-            os_name, node_version, kiro_version, machine_id
-        );
-
-        let mut headers = HeaderMap::new();
-
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers.insert(
-            "x-amzn-codewhisperer-optout",
-            HeaderValue::from_static("true"),
-        );
-        headers.insert("x-amzn-kiro-agent-mode", HeaderValue::from_static("vibe"));
-        headers.insert(
-            "x-amz-user-agent",
-            HeaderValue::from_str(&x_amz_user_agent).unwrap(),
-        );
-        headers.insert(
-            reqwest::header::USER_AGENT,
-            HeaderValue::from_str(&user_agent).unwrap(),
-        );
-        headers.insert(HOST, HeaderValue::from_str(&self.base_domain()).unwrap());
-        headers.insert(
-            "amz-sdk-invocation-id",
-            HeaderValue::from_str(&Uuid::new_v4().to_string()).unwrap(),
-        );
-        headers.insert(
-            "amz-sdk-request",
-            HeaderValue::from_static("attempt=1; max=3"),
-        );
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", ctx.token)).unwrap(),
-        );
-        headers.insert(CONNECTION, HeaderValue::from_static("close"));
-
-        Ok(headers)
+        self.build_upstream_headers(UpstreamApi::Conversation, ctx)
     }
 
     /// 构建 MCP 请求头
     fn build_mcp_headers(&self, ctx: &CallContext) -> anyhow::Result<HeaderMap> {
-        let config = self.token_manager.config();
-
-        let machine_id = machine_id::generate_from_credentials(&ctx.credentials, config)
-            .ok_or_else(|| anyhow::anyhow!("无法生成 machine_id，请检查凭证配置"))?;
-
-        let kiro_version = &config.kiro_version;
-        let os_name = &config.system_version;
-        let node_version = &config.node_version;
-
-        let x_amz_user_agent = format!("aws-sdk-js/1.0.27 KiroIDE-{}-{}", kiro_version, machine_id);
-
-        let user_agent = format!(
-            "aws-sdk-js/1.0.27 ua/2.1 os/{} lang/js md/nodejs#{} api/codewhispererstreaming#1.0.27 m/E KiroIDE-{}-{}",
-            os_name, node_version, kiro_version, machine_id
-        );
-
-        let mut headers = HeaderMap::new();
+        self.build_upstream_headers(UpstreamApi::Mcp, ctx)
+    }
 
-        // 按照严格顺序添加请求头
-        headers.insert("content-type", HeaderValue::from_static("application/json"));
-        headers.insert(
-            "x-amz-user-agent",
-            HeaderValue::from_str(&x_amz_user_agent).unwrap(),
-        );
-        headers.insert("user-agent", HeaderValue::from_str(&user_agent).unwrap());
-        headers.insert("host", HeaderValue::from_str(&self.base_domain()).unwrap());
-        headers.insert(
-            "amz-sdk-invocation-id",
-            HeaderValue::from_str(&Uuid::new_v4().to_string()).unwrap(),
-        );
-        headers.insert(
-            "amz-sdk-request",
-            HeaderValue::from_static("attempt=1; max=3"),
-        );
-        headers.insert(
-            "Authorization",
-            HeaderValue::from_str(&format!("Bearer {}", ctx.token)).unwrap(),
-        );
-        headers.insert("Connection", HeaderValue::from_static("close"));
+    /// 构建请求头并追加凭据的自定义请求头
+    fn build_upstream_headers(
+        &self,
+        api: UpstreamApi,
+        ctx: &CallContext,
+    ) -> anyhow::Result<HeaderMap> {
+        let host = self.base_domain();
+        let header_ctx = HeaderContext {
+            api,
+            credentials: &ctx.credentials,
+            token: Some(&ctx.token),
+            host: &host,
+        };
+        let mut headers = headers::build_headers(&header_ctx, self.token_manager.config())?;
+        Self::apply_custom_headers(&mut headers, ctx);
 
         Ok(headers)
     }
 
+    /// 添加凭据的自定义请求头（覆盖同名标准请求头）
+    ///
+    /// Admin API 写入时已校验；手动编辑凭据文件导致校验失败时整体忽略并记录警告
+    fn apply_custom_headers(headers: &mut HeaderMap, ctx: &CallContext) {
+        let custom_headers = &ctx.credentials.custom_headers;
+        if let Err(e) = validate_custom_headers(custom_headers) {
+            tracing::warn!("凭据 #{} 的自定义请求头无效，已忽略: {}", ctx.id, e);
+            return;
+        }
+        for (name, value) in custom_headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+    }
+
     /// 发送非流式 API 请求
     ///
-    /// 支持多凭据故障转移：
-    /// - 400 Bad Request: 直接返回错误，不计入凭据失败
-    /// - 401/403: 视为凭据/权限问题，计入失败次数并允许故障转移
-    /// - 402 MONTHLY_REQUEST_COUNT: 视为额度用尽，禁用凭据并切换
-    /// - 429/5xx/网络等瞬态错误: 重试但不禁用或切换凭据（避免误把所有凭据锁死）
+    /// 支持多凭据故障转移（按 `UpstreamErrorKind` 分类决策）：
+    /// - QuotaMonthly（MONTHLY_REQUEST_COUNT）: 视为额度用尽，禁用凭据并切换
+    /// - AuthExpired（401/403 等）: 视为凭据/权限问题，计入失败次数并允许故障转移
+    /// - Throttled/Transient（429/5xx/网络等）: 重试但不禁用或切换凭据（避免误把所有凭据锁死）
+    /// - Unknown 4xx（如 400 Bad Request）: 直接返回错误，不计入凭据失败
     ///
     /// # Arguments
     /// * `request_body` - JSON 格式的请求体字符串
@@ -193,7 +272,7 @@ impl KiroProvider {
     /// 返回原始的 HTTP Response，不做解析
     #[allow(dead_code)]
     pub async fn call_api(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, false, None).await
+        self.call_api_with_retry(request_body, false, None, None).await
     }
 
     /// 发送非流式 API 请求（带会话粘性）
@@ -203,6 +282,7 @@ impl KiroProvider {
     /// # Arguments
     /// * `request_body` - JSON 格式的请求体字符串
     /// * `session_id` - 会话标识（可选）
+    /// * `user_key` - 用户标识哈希（可选，用于按用户公平调度）
     ///
     /// # Returns
     /// 返回原始的 HTTP Response，不做解析
@@ -211,18 +291,19 @@ impl KiroProvider {
         &self,
         request_body: &str,
         session_id: Option<&str>,
+        user_key: Option<&str>,
     ) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, false, session_id)
+        self.call_api_with_retry(request_body, false, session_id, user_key)
             .await
     }
 
     /// 发送流式 API 请求
     ///
-    /// 支持多凭据故障转移：
-    /// - 400 Bad Request: 直接返回错误，不计入凭据失败
-    /// - 401/403: 视为凭据/权限问题，计入失败次数并允许故障转移
-    /// - 402 MONTHLY_REQUEST_COUNT: 视为额度用尽，禁用凭据并切换
-    /// - 429/5xx/网络等瞬态错误: 重试但不禁用或切换凭据（避免误把所有凭据锁死）
+    /// 支持多凭据故障转移（按 `UpstreamErrorKind` 分类决策）：
+    /// - QuotaMonthly（MONTHLY_REQUEST_COUNT）: 视为额度用尽，禁用凭据并切换
+    /// - AuthExpired（401/403 等）: 视为凭据/权限问题，计入失败次数并允许故障转移
+    /// - Throttled/Transient（429/5xx/网络等）: 重试但不禁用或切换凭据（避免误把所有凭据锁死）
+    /// - Unknown 4xx（如 400 Bad Request）: 直接返回错误，不计入凭据失败
     ///
     /// # Arguments
     /// * `request_body` - JSON 格式的请求体字符串
@@ -231,7 +312,7 @@ impl KiroProvider {
     /// 返回原始的 HTTP Response，调用方负责处理流式数据
     #[allow(dead_code)]
     pub async fn call_api_stream(&self, request_body: &str) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, true, None).await
+        self.call_api_with_retry(request_body, true, None, None).await
     }
 
     /// 发送流式 API 请求（带会话粘性）
@@ -241,6 +322,7 @@ impl KiroProvider {
     /// # Arguments
     /// * `request_body` - JSON 格式的请求体字符串
     /// * `session_id` - 会话标识（可选）
+    /// * `user_key` - 用户标识哈希（可选，用于按用户公平调度）
     ///
     /// # Returns
     /// 返回原始的 HTTP Response，调用方负责处理流式数据
@@ -248,8 +330,9 @@ impl KiroProvider {
         &self,
         request_body: &str,
         session_id: Option<&str>,
+        user_key: Option<&str>,
     ) -> anyhow::Result<reqwest::Response> {
-        self.call_api_with_retry(request_body, true, session_id)
+        self.call_api_with_retry(request_body, true, session_id, user_key)
             .await
     }
 
@@ -277,7 +360,10 @@ impl KiroProvider {
             let ctx = match self.token_manager.acquire_context().await {
                 Ok(c) => c,
                 Err(e) => {
-                    last_error = Some(e);
+                    if let Some(error) = self.backing_off_error("MCP 请求失败") {
+                        return Err(error);
+                    }
+                    last_error = Some(Self::token_acquisition_error("MCP 请求失败", e.into()));
                     continue;
                 }
             };
@@ -286,21 +372,19 @@ impl KiroProvider {
             let headers = match self.build_mcp_headers(&ctx) {
                 Ok(h) => h,
                 Err(e) => {
-                    last_error = Some(e);
+                    last_error = Some(Self::token_acquisition_error("MCP 请求失败", e));
                     continue;
                 }
             };
 
             // 发送请求（记录开始时间用于统计响应时间）
             let request_start = std::time::Instant::now();
-            let response = match self
-                .client
+            let request = self
+                .client_for(&ctx)
                 .post(&url)
                 .headers(headers)
-                .body(request_body.to_string())
-                .send()
-                .await
-            {
+                .body(request_body.to_string());
+            let response = match self.send(request, "MCP 请求失败").await {
                 Ok(resp) => resp,
                 Err(e) => {
                     tracing::warn!(
@@ -309,7 +393,8 @@ impl KiroProvider {
                         max_retries,
                         e
                     );
-                    last_error = Some(e.into());
+                    self.record_failed_call(ctx.id, MCP_TIMELINE_MODEL, request_start);
+                    last_error = Some(e);
                     if attempt + 1 < max_retries {
                         sleep(Self::retry_delay(attempt)).await;
                     }
@@ -318,46 +403,86 @@ impl KiroProvider {
             };
 
             let status = response.status();
+            let upstream_request_id = Self::upstream_request_id(response.headers());
 
             // 成功响应
             if status.is_success() {
                 // 计算响应时间并上报
                 let response_time_ms = request_start.elapsed().as_millis() as u64;
                 self.token_manager
-                    .report_success_with_time(ctx.id, Some(response_time_ms));
+                    .report_success(ctx.id, MCP_TIMELINE_MODEL, response_time_ms);
                 return Ok(response);
             }
 
             // 失败响应
+            let retry_after = Self::retry_after(status, response.headers());
             let body = response.text().await.unwrap_or_default();
-
-            // 402 额度用尽
-            if status.as_u16() == 402 && Self::is_monthly_request_limit(&body) {
-                let has_available = self.token_manager.report_quota_exhausted(ctx.id);
+            let kind = UpstreamErrorKind::from_response(status.as_u16(), &body);
+            let latency_ms = request_start.elapsed().as_millis() as u64;
+            self.token_manager
+                .record_call(ctx.id, MCP_TIMELINE_MODEL, false, latency_ms);
+
+            // 额度用尽
+            if kind == UpstreamErrorKind::QuotaMonthly {
+                let has_available = self.report_quota_exhausted(ctx.id);
                 if !has_available {
-                    anyhow::bail!("MCP 请求失败（所有凭据已用尽）: {} {}", status, body);
+                    return Err(Self::upstream_error(
+                        Self::http_error(status, &body),
+                        format!("MCP 请求失败（所有凭据已用尽）: {} {}", status, body),
+                        upstream_request_id.as_deref(),
+                    ));
                 }
-                last_error = Some(anyhow::anyhow!("MCP 请求失败: {} {}", status, body));
+                last_error = Some(Self::upstream_error(
+                    Self::http_error(status, &body),
+                    format!("MCP 请求失败: {} {}", status, body),
+                    upstream_request_id.as_deref(),
+                ));
                 continue;
             }
 
-            // 400 Bad Request
-            if status.as_u16() == 400 {
-                anyhow::bail!("MCP 请求失败: {} {}", status, body);
+            // 认证失效
+            if kind == UpstreamErrorKind::AuthExpired {
+                let has_available = self.token_manager.report_failure_with_time(
+                    ctx.id,
+                    Some(&format!("{} {}", status, body)),
+                    Some(latency_ms),
+                );
+                if !has_available {
+                    return Err(Self::upstream_error(
+                        Self::http_error(status, &body),
+                        format!("MCP 请求失败（所有凭据已用尽）: {} {}", status, body),
+                        upstream_request_id.as_deref(),
+                    ));
+                }
+                last_error = Some(Self::upstream_error(
+                    Self::http_error(status, &body),
+                    format!("MCP 请求失败: {} {}", status, body),
+                    upstream_request_id.as_deref(),
+                ));
+                continue;
             }
 
-            // 401/403 凭据问题
-            if matches!(status.as_u16(), 401 | 403) {
-                let has_available = self.token_manager.report_failure(ctx.id);
+            // 限流且要求退避：凭据退避期内不参与调度，直接切换凭据
+            if let Some(retry_after) = retry_after {
+                let (error, has_available) = self.report_retry_after(
+                    ctx.id,
+                    retry_after,
+                    Self::http_error(status, &body),
+                    format!("MCP 请求失败: {} {}", status, body),
+                    upstream_request_id.as_deref(),
+                );
                 if !has_available {
-                    anyhow::bail!("MCP 请求失败（所有凭据已用尽）: {} {}", status, body);
+                    return Err(error);
                 }
-                last_error = Some(anyhow::anyhow!("MCP 请求失败: {} {}", status, body));
+                last_error = Some(error);
                 continue;
             }
 
-            // 瞬态错误
-            if matches!(status.as_u16(), 408 | 429) || status.is_server_error() {
+            // 限流/瞬态错误
+            if matches!(
+                kind,
+                UpstreamErrorKind::Throttled | UpstreamErrorKind::Transient
+            ) {
                 tracing::warn!(
                     "MCP 请求失败（上游瞬态错误，尝试 {}/{}）: {} {}",
                     attempt + 1,
@@ -365,27 +490,42 @@ impl KiroProvider {
                     status,
                     body
                 );
-                last_error = Some(anyhow::anyhow!("MCP 请求失败: {} {}", status, body));
+                last_error = Some(Self::upstream_error(
+                    Self::http_error(status, &body),
+                    format!("MCP 请求失败: {} {}", status, body),
+                    upstream_request_id.as_deref(),
+                ));
                 if attempt + 1 < max_retries {
                     sleep(Self::retry_delay(attempt)).await;
                 }
                 continue;
             }
 
-            // 其他 4xx
+            // 无法识别的 4xx（如 400 Bad Request）
             if status.is_client_error() {
-                anyhow::bail!("MCP 请求失败: {} {}", status, body);
+                return Err(Self::upstream_error(
+                    Self::http_error(status, &body),
+                    format!("MCP 请求失败: {} {}", status, body),
+                    upstream_request_id.as_deref(),
+                ));
             }
 
             // 兜底
-            last_error = Some(anyhow::anyhow!("MCP 请求失败: {} {}", status, body));
+            last_error = Some(Self::upstream_error(
+                Self::http_error(status, &body),
+                format!("MCP 请求失败: {} {}", status, body),
+                upstream_request_id.as_deref(),
+            ));
             if attempt + 1 < max_retries {
                 sleep(Self::retry_delay(attempt)).await;
             }
         }
 
         Err(last_error.unwrap_or_else(|| {
-            anyhow::anyhow!("MCP 请求失败：已达到最大重试次数（{}次）", max_retries)
+            Self::token_acquisition_error(
+                "MCP 请求失败",
+                anyhow::anyhow!("已达到最大重试次数（{}次）", max_retries),
+            )
         }))
     }
 
@@ -399,48 +539,87 @@ impl KiroProvider {
     /// 粘性会话：
     /// - 如果提供了 session_id，同一会话的请求会路由到同一凭据
     /// - 新会话按轮询方式分配凭据
+    /// - 启用按用户公平调度时，新会话分配受 `user_key` 的占用上限约束
     async fn call_api_with_retry(
         &self,
         request_body: &str,
         is_stream: bool,
         session_id: Option<&str>,
+        user_key: Option<&str>,
     ) -> anyhow::Result<reqwest::Response> {
+        #[cfg(test)]
+        if let Some(ref mock) = self.mock {
+            mock.wait_delay().await;
+            let mut response = mock.respond(request_body, is_stream)?;
+            let mut ctx = self
+                .token_manager
+                .acquire_context_for_user(session_id, user_key)
+                .await?;
+            tracing::Span::current().record("credential_id", ctx.id);
+            response.extensions_mut().insert(ServingCredential(ctx.id));
+            if let Some(permit) = ctx.permit.take() {
+                response
+                    .extensions_mut()
+                    .insert(CredentialPermit(Arc::new(permit)));
+            }
+            return Ok(response);
+        }
+
         let total_credentials = self.token_manager.total_count();
         let max_retries = (total_credentials * MAX_RETRIES_PER_CREDENTIAL).min(MAX_TOTAL_RETRIES);
         let mut last_error: Option<anyhow::Error> = None;
         let api_type = if is_stream { "流式" } else { "非流式" };
+        let body = RequestBody::prepare(request_body, self.token_manager.config());
+        let model = Self::timeline_model(request_body);
 
         for attempt in 0..max_retries {
             // 获取调用上下文（支持粘性会话）
-            let ctx = match self
+            let mut ctx = match self
                 .token_manager
-                .acquire_context_for_session(session_id)
+                .acquire_context_for_user(session_id, user_key)
                 .await
             {
                 Ok(c) => c,
                 Err(e) => {
-                    last_error = Some(e);
+                    if let Some(error) =
+                        self.backing_off_error(&format!("{} API 请求失败", api_type))
+                    {
+                        return Err(error);
+                    }
+                    last_error = Some(Self::token_acquisition_error(
+                        &format!("{} API 请求失败", api_type),
+                        e.into(),
+                    ));
                     continue;
                 }
             };
+            // 记录到请求 span（不在请求 span 内时无效果）
+            tracing::Span::current().record("credential_id", ctx.id);
 
             let url = self.base_url();
-            let headers = match self.build_headers(&ctx) {
+            let mut headers = match self.build_headers(&ctx) {
                 Ok(h) => h,
                 Err(e) => {
-                    last_error = Some(e);
+                    last_error = Some(Self::token_acquisition_error(
+                        &format!("{} API 请求失败", api_type),
+                        e,
+                    ));
                     continue;
                 }
             };
+            if body.gzip {
+                headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            }
 
             // 发送请求（记录开始时间用于统计响应时间）
             let request_start = std::time::Instant::now();
-            let response = match self
-                .client
+            let request = self
+                .client_for(&ctx)
                 .post(&url)
                 .headers(headers)
-                .body(request_body.to_string())
-                .send()
+                .body(body.bytes.clone());
+            let mut response = match self
+                .send(request, &format!("{} API 请求失败", api_type))
                 .await
             {
                 Ok(resp) => resp,
@@ -451,9 +630,10 @@ impl KiroProvider {
                         max_retries,
                         e
                     );
-                    // 网络错误通常是上游/链路瞬态问题，不应导致"禁用凭据"或"切换凭据"
+                    // 网络错误和超时通常是上游/链路瞬态问题，不应导致"禁用凭据"或"切换凭据"
                     // （否则一段时间网络抖动会把所有凭据都误禁用，需要重启才能恢复）
-                    last_error = Some(e.into());
+                    self.record_failed_call(ctx.id, model, request_start);
+                    last_error = Some(e);
                     if attempt + 1 < max_retries {
                         sleep(Self::retry_delay(attempt)).await;
                     }
@@ -462,21 +642,35 @@ impl KiroProvider {
             };
 
             let status = response.status();
+            let upstream_request_id = Self::upstream_request_id(response.headers());
 
             // 成功响应
             if status.is_success() {
                 // 计算响应时间并上报
                 let response_time_ms = request_start.elapsed().as_millis() as u64;
                 self.token_manager
-                    .report_success_with_time(ctx.id, Some(response_time_ms));
+                    .report_success(ctx.id, model, response_time_ms);
+                self.publish_new_request(request_body, ctx.id);
+                response.extensions_mut().insert(ServingCredential(ctx.id));
+                if let Some(permit) = ctx.permit.take() {
+                    response
+                        .extensions_mut()
+                        .insert(CredentialPermit(Arc::new(permit)));
+                }
                 return Ok(response);
             }
 
-            // 失败响应：读取 body 用于日志/错误信息
+            // 失败响应：读取 body 用于日志/错误信息，并按结构化错误分类
+            let retry_after = Self::retry_after(status, response.headers());
             let body = response.text().await.unwrap_or_default();
-
-            // 402 Payment Required 且额度用尽：禁用凭据并故障转移
-            if status.as_u16() == 402 && Self::is_monthly_request_limit(&body) {
+            let kind = UpstreamErrorKind::from_response(status.as_u16(), &body);
+            // 所有失败响应都记录到调用时间线（认证失效另外计入凭据失败）
+            let latency_ms = request_start.elapsed().as_millis() as u64;
+            self.token_manager
+                .record_call(ctx.id, model, false, latency_ms);
+
+            // 额度用尽：禁用凭据并故障转移
+            if kind == UpstreamErrorKind::QuotaMonthly {
                 tracing::warn!(
                     "API 请求失败（额度已用尽，禁用凭据并切换，尝试 {}/{}）: {} {}",
                     attempt + 1,
@@ -485,32 +679,28 @@ impl KiroProvider {
                     body
                 );
 
-                let has_available = self.token_manager.report_quota_exhausted(ctx.id);
+                let has_available = self.report_quota_exhausted(ctx.id);
                 if !has_available {
-                    anyhow::bail!(
-                        "{} API 请求失败（所有凭据已用尽）: {} {}",
-                        api_type,
-                        status,
-                        body
-                    );
+                    return Err(Self::upstream_error(
+                        Self::http_error(status, &body),
+                        format!(
+                            "{} API 请求失败（所有凭据已用尽）: {} {}",
+                            api_type, status, body
+                        ),
+                        upstream_request_id.as_deref(),
+                    ));
                 }
 
-                last_error = Some(anyhow::anyhow!(
-                    "{} API 请求失败: {} {}",
-                    api_type,
-                    status,
-                    body
+                last_error = Some(Self::upstream_error(
+                    Self::http_error(status, &body),
+                    format!("{} API 请求失败: {} {}", api_type, status, body),
+                    upstream_request_id.as_deref(),
                 ));
                 continue;
             }
 
-            // 400 Bad Request - 请求问题，重试/切换凭据无意义
-            if status.as_u16() == 400 {
-                anyhow::bail!("{} API 请求失败: {} {}", api_type, status, body);
-            }
-
-            // 401/403 - 更可能是凭据/权限问题：计入失败并允许故障转移
-            if matches!(status.as_u16(), 401 | 403) {
+            // 认证失效（401/403 等）：计入失败并允许故障转移
+            if kind == UpstreamErrorKind::AuthExpired {
                 tracing::warn!(
                     "API 请求失败（可能为凭据错误，尝试 {}/{}）: {} {}",
                     attempt + 1,
@@ -519,28 +709,61 @@ impl KiroProvider {
                     body
                 );
 
-                let has_available = self.token_manager.report_failure(ctx.id);
+                let has_available = self.token_manager.report_failure_with_time(
+                    ctx.id,
+                    Some(&format!("{} {}", status, body)),
+                    Some(latency_ms),
+                );
                 if !has_available {
-                    anyhow::bail!(
-                        "{} API 请求失败（所有凭据已用尽）: {} {}",
-                        api_type,
-                        status,
-                        body
-                    );
+                    return Err(Self::upstream_error(
+                        Self::http_error(status, &body),
+                        format!(
+                            "{} API 请求失败（所有凭据已用尽）: {} {}",
+                            api_type, status, body
+                        ),
+                        upstream_request_id.as_deref(),
+                    ));
                 }
 
-                last_error = Some(anyhow::anyhow!(
-                    "{} API 请求失败: {} {}",
-                    api_type,
+                last_error = Some(Self::upstream_error(
+                    Self::http_error(status, &body),
+                    format!("{} API 请求失败: {} {}", api_type, status, body),
+                    upstream_request_id.as_deref(),
+                ));
+                continue;
+            }
+
+            // 限流且要求退避（429 Retry-After）：凭据退避期内不参与调度，直接切换凭据；
+            // 没有其他可用凭据时把退避时长返回给客户端
+            if let Some(retry_after) = retry_after {
+                tracing::warn!(
+                    "API 请求被限流（Retry-After: {}s，尝试 {}/{}）: {} {}",
+                    retry_after.as_secs(),
+                    attempt + 1,
+                    max_retries,
                     status,
                     body
-                ));
+                );
+                let (error, has_available) = self.report_retry_after(
+                    ctx.id,
+                    retry_after,
+                    Self::http_error(status, &body),
+                    format!("{} API 请求失败: {} {}", api_type, status, body),
+                    upstream_request_id.as_deref(),
+                );
+                if !has_available {
+                    return Err(error);
+                }
+                last_error = Some(error);
                 continue;
             }
 
-            // 429/408/5xx - 瞬态上游错误：重试但不禁用或切换凭据
+            // 限流/瞬态上游错误：重试但不禁用或切换凭据
             // （避免 429 high traffic / 502 high load 等瞬态错误把所有凭据锁死）
-            if matches!(status.as_u16(), 408 | 429) || status.is_server_error() {
+            if matches!(
+                kind,
+                UpstreamErrorKind::Throttled | UpstreamErrorKind::Transient
+            ) {
                 tracing::warn!(
                     "API 请求失败（上游瞬态错误，尝试 {}/{}）: {} {}",
                     attempt + 1,
@@ -548,11 +771,10 @@ impl KiroProvider {
                     status,
                     body
                 );
-                last_error = Some(anyhow::anyhow!(
-                    "{} API 请求失败: {} {}",
-                    api_type,
-                    status,
-                    body
+                last_error = Some(Self::upstream_error(
+                    Self::http_error(status, &body),
+                    format!("{} API 请求失败: {} {}", api_type, status, body),
+                    upstream_request_id.as_deref(),
                 ));
                 if attempt + 1 < max_retries {
                     sleep(Self::retry_delay(attempt)).await;
@@ -560,9 +782,14 @@ impl KiroProvider {
                 continue;
             }
 
-            // 其他 4xx - 通常为请求/配置问题：直接返回，不计入凭据失败
+            // 无法识别的 4xx（如 400 Bad Request）- 通常为请求/配置问题：
+            // 直接返回，不计入凭据失败
             if status.is_client_error() {
-                anyhow::bail!("{} API 请求失败: {} {}", api_type, status, body);
+                return Err(Self::upstream_error(
+                    Self::http_error(status, &body),
+                    format!("{} API 请求失败: {} {}", api_type, status, body),
+                    upstream_request_id.as_deref(),
+                ));
             }
 
             // 兜底：当作可重试的瞬态错误处理（不切换凭据）
@@ -573,11 +800,10 @@ impl KiroProvider {
                 status,
                 body
             );
-            last_error = Some(anyhow::anyhow!(
-                "{} API 请求失败: {} {}",
-                api_type,
-                status,
-                body
+            last_error = Some(Self::upstream_error(
+                Self::http_error(status, &body),
+                format!("{} API 请求失败: {} {}", api_type, status, body),
+                upstream_request_id.as_deref(),
             ));
             if attempt + 1 < max_retries {
                 sleep(Self::retry_delay(attempt)).await;
@@ -586,14 +812,177 @@ impl KiroProvider {
 
         // 所有重试都失败
         Err(last_error.unwrap_or_else(|| {
-            anyhow::anyhow!(
-                "{} API 请求失败：已达到最大重试次数（{}次）",
-                api_type,
-                max_retries
+            Self::token_acquisition_error(
+                &format!("{} API 请求失败", api_type),
+                anyhow::anyhow!("已达到最大重试次数（{}次）", max_retries),
             )
         }))
     }
 
+    /// 获取实际服务该响应的凭据 ID
+    pub fn serving_credential(response: &reqwest::Response) -> Option<u64> {
+        response
+            .extensions()
+            .get::<ServingCredential>()
+            .map(|c| c.0)
+    }
+
+    /// 取出响应携带的凭据并发名额（调用方持有到响应体读完）
+    pub fn take_credential_permit(response: &mut reqwest::Response) -> Option<CredentialPermit> {
+        response.extensions_mut().remove::<CredentialPermit>()
+    }
+
+    /// 上报流式响应异常终止
+    ///
+    /// 计入凭据失败次数并记录到调用时间线，持续不稳定的凭据会被轮换出去
+    pub fn report_stream_failure(&self, credential_id: u64, model: &str, latency_ms: u64) {
+        let has_available = self.token_manager.report_failure(
+            credential_id,
+            model,
+            Some("流式响应异常终止"),
+            latency_ms,
+        );
+        tracing::warn!(
+            credential_id,
+            has_available,
+            "流式响应异常终止，已记录凭据失败"
+        );
+    }
+
+    /// 请求在调用时间线中记录的模型（请求体中当前消息的 modelId）
+    pub fn timeline_model(request_body: &str) -> &str {
+        Self::extract_model_id(request_body).unwrap_or("unknown")
+    }
+
+    /// 请求未得到上游响应（网络错误或超时），只记录到调用时间线
+    fn record_failed_call(&self, id: u64, model: &str, request_start: std::time::Instant) {
+        let latency_ms = request_start.elapsed().as_millis() as u64;
+        self.token_manager.record_call(id, model, false, latency_ms);
+    }
+
+    /// 从响应头中提取上游请求 ID
+    pub fn upstream_request_id(headers: &HeaderMap) -> Option<String> {
+        UPSTREAM_REQUEST_ID_HEADERS.iter().find_map(|name| {
+            headers
+                .get(*name)
+                .and_then(|v| v.to_str().ok())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+        })
+    }
+
+    /// 构建携带失败原因和上游请求 ID 的错误
+    fn upstream_error(
+        error: ProviderError,
+        message: String,
+        request_id: Option<&str>,
+    ) -> anyhow::Error {
+        if let Some(id) = request_id {
+            tracing::warn!(upstream_request_id = %id, "{}", message);
+        }
+        UpstreamError {
+            message,
+            error,
+            request_id: request_id.map(|s| s.to_string()),
+            retry_after: None,
+        }
+        .into()
+    }
+
+    /// 上游非成功响应
+    fn http_error(status: reqwest::StatusCode, body: &str) -> ProviderError {
+        ProviderError::Http {
+            status: status.as_u16(),
+            body: body.to_string(),
+        }
+    }
+
+    /// 发送请求并等待响应头
+    ///
+    /// 超过 `upstreamFirstByteTimeoutSecs` 仍未收到响应时返回首字节超时；
+    /// 之后读取响应体不设整体时长限制
+    async fn send(
+        &self,
+        request: RequestBuilder,
+        prefix: &str,
+    ) -> anyhow::Result<reqwest::Response> {
+        let timeout = self.token_manager.config().upstream_first_byte_timeout();
+        match tokio::time::timeout(timeout, request.send()).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(e)) => Err(Self::network_error(prefix, e)),
+            Err(_) => Err(Self::upstream_error(
+                ProviderError::Timeout(TimeoutKind::FirstByte),
+                format!("{}: {}s 内未收到上游响应", prefix, timeout.as_secs()),
+                None,
+            )),
+        }
+    }
+
+    /// 请求发送失败（网络错误或超时）
+    fn network_error(prefix: &str, error: reqwest::Error) -> anyhow::Error {
+        let message = format!("{}: {}", prefix, error);
+        Self::upstream_error(ProviderError::from_reqwest(&error), message, None)
+    }
+
+    /// 获取调用凭据失败
+    fn token_acquisition_error(prefix: &str, error: anyhow::Error) -> anyhow::Error {
+        let message = format!("{}: {}", prefix, error);
+        Self::upstream_error(ProviderError::TokenAcquisition(error), message, None)
+    }
+
+    /// 解析 429 响应的 `Retry-After` 响应头
+    fn retry_after(status: reqwest::StatusCode, headers: &HeaderMap) -> Option<Duration> {
+        if status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return None;
+        }
+        headers
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after)
+    }
+
+    /// 凭据进入 `Retry-After` 退避期
+    ///
+    /// 返回携带退避时长的错误，以及是否还有其他可调度的凭据
+    fn report_retry_after(
+        &self,
+        id: u64,
+        retry_after: Duration,
+        error: ProviderError,
+        message: String,
+        request_id: Option<&str>,
+    ) -> (anyhow::Error, bool) {
+        let has_available = self.token_manager.report_retry_after(id, retry_after);
+        let error = UpstreamError {
+            message,
+            error,
+            request_id: request_id.map(|s| s.to_string()),
+            retry_after: Some(retry_after),
+        };
+        (error.into(), has_available)
+    }
+
+    /// 所有未禁用凭据都在退避期内时，返回携带最短剩余退避时长的错误
+    fn backing_off_error(&self, prefix: &str) -> Option<anyhow::Error> {
+        let remaining = self.token_manager.retry_after_remaining()?;
+        Some(
+            UpstreamError {
+                message: format!(
+                    "{}：所有可用凭据均被上游限流，{} 秒后重试",
+                    prefix,
+                    remaining.as_secs().max(1)
+                ),
+                error: ProviderError::Http {
+                    status: 429,
+                    body: String::new(),
+                },
+                request_id: None,
+                retry_after: Some(remaining),
+            }
+            .into(),
+        )
+    }
+
     fn retry_delay(attempt: usize) -> Duration {
         // 指数退避 + 少量抖动，避免上游抖动时放大故障
         const BASE_MS: u64 = 200;
@@ -605,27 +994,41 @@ impl KiroProvider {
         Duration::from_millis(backoff.saturating_add(jitter))
     }
 
-    fn is_monthly_request_limit(body: &str) -> bool {
-        if body.contains("MONTHLY_REQUEST_COUNT") {
-            return true;
-        }
-
-        let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
-            return false;
-        };
+    /// 禁用额度用尽的凭据，并在后台获取其额度重置时间（用于到期自动重新启用）
+    ///
+    /// 返回是否还有可用凭据
+    fn report_quota_exhausted(&self, id: u64) -> bool {
+        let has_available = self.token_manager.report_quota_exhausted(id);
+        let token_manager = self.token_manager.clone();
+        tokio::spawn(async move {
+            if let Err(e) = token_manager.refresh_quota_reset_at(id).await {
+                tracing::warn!("获取凭据 #{} 额度重置时间失败: {}", id, e);
+            }
+        });
+        has_available
+    }
 
-        if value
-            .get("reason")
-            .and_then(|v| v.as_str())
-            .is_some_and(|v| v == "MONTHLY_REQUEST_COUNT")
-        {
-            return true;
+    /// 发布 NewRequest 事件（仅在有 Admin 订阅者时解析模型 ID）
+    fn publish_new_request(&self, request_body: &str, credential_id: u64) {
+        if !self.token_manager.has_event_subscribers() {
+            return;
         }
+        let model = Self::extract_model_id(request_body).unwrap_or("unknown");
+        self.token_manager.publish_event(AdminEvent::NewRequest {
+            model: model.to_string(),
+            credential_id,
+        });
+    }
 
-        value
-            .pointer("/error/reason")
-            .and_then(|v| v.as_str())
-            .is_some_and(|v| v == "MONTHLY_REQUEST_COUNT")
+    /// 从 Kiro 请求体中提取当前消息的 modelId
+    ///
+    /// currentMessage 序列化在 history 之前，且字符串值内的引号会被转义，
+    /// 因此第一个未转义的 `"modelId":"` 即为当前消息的模型
+    fn extract_model_id(request_body: &str) -> Option<&str> {
+        const KEY: &str = "\"modelId\":\"";
+        let start = request_body.find(KEY)? + KEY.len();
+        let len = request_body[start..].find('"')?;
+        Some(&request_body[start..start + len])
     }
 }
 
@@ -633,7 +1036,9 @@ impl KiroProvider {
 mod tests {
     use super::*;
     use crate::kiro::token_manager::CallContext;
+    use reqwest::header::{ACCEPT_ENCODING, AUTHORIZATION, CONNECTION, CONTENT_TYPE};
     use crate::model::config::Config;
+    use std::collections::HashMap;
 
     fn create_test_provider(config: Config, credentials: KiroCredentials) -> KiroProvider {
         let tm = MultiTokenManager::new(config, vec![credentials], None, None).unwrap();
@@ -649,6 +1054,48 @@ mod tests {
         assert!(provider.base_url().contains("generateAssistantResponse"));
     }
 
+    #[test]
+    fn test_base_url_with_upstream_override() {
+        let mut config = Config::default();
+        config.upstream_base_url = Some("http://127.0.0.1:9000/".to_string());
+        let credentials = KiroCredentials::default();
+        let provider = create_test_provider(config, credentials);
+        assert_eq!(
+            provider.base_url(),
+            "http://127.0.0.1:9000/generateAssistantResponse"
+        );
+        assert_eq!(provider.mcp_url(), "http://127.0.0.1:9000/mcp");
+        // Host 仍使用真实域名
+        assert_eq!(provider.base_domain(), "q.us-east-1.amazonaws.com");
+    }
+
+    #[test]
+    fn test_base_url_with_kiro_api_override() {
+        let mut config = Config::default();
+        config.kiro_api_base_url = Some("https://vpce-123.q.example.com:8443/kiro/".to_string());
+        let mut credentials = KiroCredentials::default();
+        credentials.refresh_token = Some("a".repeat(150));
+        let provider = create_test_provider(config, credentials.clone());
+        assert_eq!(
+            provider.base_url(),
+            "https://vpce-123.q.example.com:8443/kiro/generateAssistantResponse"
+        );
+        assert_eq!(
+            provider.mcp_url(),
+            "https://vpce-123.q.example.com:8443/kiro/mcp"
+        );
+
+        let ctx = CallContext {
+            id: 1,
+            credentials,
+            token: "test_token".to_string(),
+            proxy_config: None,
+            permit: None,
+        };
+        let headers = provider.build_headers(&ctx).unwrap();
+        assert_eq!(headers.get("host").unwrap(), "vpce-123.q.example.com:8443");
+    }
+
     #[test]
     fn test_base_domain() {
         let mut config = Config::default();
@@ -674,6 +1121,7 @@ mod tests {
             credentials,
             token: "test_token".to_string(),
             proxy_config: None,
+            permit: None,
         };
         let headers = provider.build_headers(&ctx).unwrap();
 
@@ -689,23 +1137,306 @@ mod tests {
                 .starts_with("Bearer ")
         );
         assert_eq!(headers.get(CONNECTION).unwrap(), "close");
+        assert_eq!(headers.get(ACCEPT_ENCODING).unwrap(), "identity");
     }
 
     #[test]
     fn test_is_monthly_request_limit_detects_reason() {
         let body = r#"{"message":"You have reached the limit.","reason":"MONTHLY_REQUEST_COUNT"}"#;
-        assert!(KiroProvider::is_monthly_request_limit(body));
+        assert_eq!(
+            UpstreamErrorKind::from_response(402, body),
+            UpstreamErrorKind::QuotaMonthly
+        );
     }
 
     #[test]
     fn test_is_monthly_request_limit_nested_reason() {
         let body = r#"{"error":{"reason":"MONTHLY_REQUEST_COUNT"}}"#;
-        assert!(KiroProvider::is_monthly_request_limit(body));
+        assert_eq!(
+            UpstreamErrorKind::from_response(402, body),
+            UpstreamErrorKind::QuotaMonthly
+        );
     }
 
     #[test]
     fn test_is_monthly_request_limit_false() {
         let body = r#"{"message":"nope","reason":"DAILY_REQUEST_COUNT"}"#;
-        assert!(!KiroProvider::is_monthly_request_limit(body));
+        assert_ne!(
+            UpstreamErrorKind::from_response(402, body),
+            UpstreamErrorKind::QuotaMonthly
+        );
+    }
+
+    #[test]
+    fn test_extract_model_id_uses_current_message() {
+        let body = r#"{"conversationState":{"currentMessage":{"userInputMessage":{"content":"say \"modelId\":\"x\"","modelId":"claude-sonnet-4.5"}},"history":[{"userInputMessage":{"content":"hi","modelId":"claude-haiku-4.5"}}]}}"#;
+        assert_eq!(
+            KiroProvider::extract_model_id(body),
+            Some("claude-sonnet-4.5")
+        );
+        assert_eq!(KiroProvider::extract_model_id("{}"), None);
+    }
+
+    fn mock_response(header: &str, value: &str) -> reqwest::Response {
+        http::Response::builder()
+            .status(502)
+            .header(header, value)
+            .body("upstream error")
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn test_upstream_request_id_from_mocked_response() {
+        let response = mock_response("x-amzn-RequestId", "0f3c6d2a-req");
+        assert_eq!(
+            KiroProvider::upstream_request_id(response.headers()),
+            Some("0f3c6d2a-req".to_string())
+        );
+
+        let response = mock_response("x-amz-request-id", "s3-style-id");
+        assert_eq!(
+            KiroProvider::upstream_request_id(response.headers()),
+            Some("s3-style-id".to_string())
+        );
+
+        let response = mock_response("x-other", "ignored");
+        assert_eq!(KiroProvider::upstream_request_id(response.headers()), None);
+    }
+
+    #[test]
+    fn test_upstream_error_carries_request_id() {
+        let response = mock_response("x-amzn-requestid", "req-123");
+        let request_id = KiroProvider::upstream_request_id(response.headers());
+
+        let error = KiroProvider::upstream_error(
+            KiroProvider::http_error(reqwest::StatusCode::BAD_GATEWAY, "Bad Gateway"),
+            "流式 API 请求失败: 502 Bad Gateway".to_string(),
+            request_id.as_deref(),
+        );
+
+        // 错误信息保持不变，是否可重试由携带的失败原因决定
+        assert_eq!(error.to_string(), "流式 API 请求失败: 502 Bad Gateway");
+        assert!(ProviderError::of(&error).is_some_and(ProviderError::is_retryable));
+        assert_eq!(
+            UpstreamError::request_id_of(&error),
+            Some("req-123".to_string())
+        );
+        assert_eq!(
+            UpstreamError::request_id_of(&anyhow::anyhow!("other")),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_backs_off_credential() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/generateAssistantResponse"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("retry-after", "30")
+                    .insert_header("x-amzn-requestid", "req-429")
+                    .set_body_string(r#"{"__type":"ThrottlingException"}"#),
+            )
+            .mount(&server)
+            .await;
+
+        let config = Config {
+            upstream_base_url: Some(server.uri()),
+            ..Config::default()
+        };
+        let credentials = KiroCredentials {
+            access_token: Some("valid-access-token".to_string()),
+            refresh_token: Some("a".repeat(150)),
+            expires_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+            ..KiroCredentials::default()
+        };
+        let provider = create_test_provider(config, credentials);
+
+        // 唯一凭据被限流：不重试，直接返回上游要求的退避时长
+        let error = provider.call_api("{}").await.unwrap_err();
+        assert_eq!(
+            UpstreamError::retry_after_of(&error),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            UpstreamError::request_id_of(&error),
+            Some("req-429".to_string())
+        );
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+        let snapshot = provider.token_manager.snapshot();
+        let until = snapshot.entries[0].retry_after_until.as_deref().unwrap();
+        let until = chrono::DateTime::parse_from_rfc3339(until).unwrap();
+        assert!(until > chrono::Utc::now() + chrono::Duration::seconds(25));
+        // 限流不计入失败、不禁用凭据，但记录到调用时间线
+        assert_eq!(snapshot.entries[0].failure_count, 0);
+        assert!(!snapshot.entries[0].disabled);
+        let id = snapshot.entries[0].id;
+        let buckets = provider.token_manager.call_timeline(id, 5, 5).unwrap();
+        let failures: u64 = buckets.iter().map(|b| b.failures).sum();
+        assert_eq!(failures, 1);
+
+        // 退避期内不再请求上游
+        let error = provider.call_api("{}").await.unwrap_err();
+        let remaining = UpstreamError::retry_after_of(&error).unwrap();
+        assert!(remaining > Duration::from_secs(25) && remaining <= Duration::from_secs(30));
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_custom_headers_sent_upstream() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/generateAssistantResponse"))
+            .and(header("x-shard", "7"))
+            .and(header("x-amzn-kiro-agent-mode", "spec"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let config = Config {
+            upstream_base_url: Some(server.uri()),
+            ..Config::default()
+        };
+        let credentials = KiroCredentials {
+            custom_headers: HashMap::from([
+                ("x-shard".to_string(), "7".to_string()),
+                // 覆盖标准请求头
+                ("x-amzn-kiro-agent-mode".to_string(), "spec".to_string()),
+            ]),
+            ..valid_credentials()
+        };
+        let provider = create_test_provider(config, credentials);
+
+        let response = provider.call_api("{}").await.unwrap();
+        assert_eq!(response.status(), 200);
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(
+            requests[0].headers["authorization"]
+                .to_str()
+                .unwrap()
+                .starts_with("Bearer ")
+        );
+    }
+
+    /// 持有有效 Token 的测试凭据
+    fn valid_credentials() -> KiroCredentials {
+        KiroCredentials {
+            access_token: Some("valid-access-token".to_string()),
+            refresh_token: Some("a".repeat(150)),
+            expires_at: Some((chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339()),
+            ..KiroCredentials::default()
+        }
+    }
+
+    /// 发送一次对话请求，返回失败原因
+    async fn send_timeout_error(provider: &KiroProvider) -> Option<TimeoutKind> {
+        let request = provider.client.post(provider.base_url()).body("{}");
+        let error = provider.send(request, "测试请求失败").await.unwrap_err();
+        match ProviderError::of(&error) {
+            Some(ProviderError::Timeout(kind)) => Some(*kind),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout_is_distinct() {
+        // 接受 TCP 连接但从不完成 TLS 握手
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let config = Config {
+            upstream_base_url: Some(format!("https://{}", addr)),
+            connect_timeout_secs: 1,
+            upstream_first_byte_timeout_secs: 30,
+            ..Config::default()
+        };
+        let provider = create_test_provider(config, valid_credentials());
+
+        let started = std::time::Instant::now();
+        assert_eq!(
+            send_timeout_error(&provider).await,
+            Some(TimeoutKind::Connect)
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_first_byte_timeout_is_distinct() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let config = Config {
+            upstream_base_url: Some(server.uri()),
+            connect_timeout_secs: 5,
+            upstream_first_byte_timeout_secs: 1,
+            ..Config::default()
+        };
+        let provider = create_test_provider(config, valid_credentials());
+
+        let started = std::time::Instant::now();
+        assert_eq!(
+            send_timeout_error(&provider).await,
+            Some(TimeoutKind::FirstByte)
+        );
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_body_has_no_overall_timeout() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // 立即返回响应头，之后每 400ms 发送一个分块，总时长超过首字节超时
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n")
+                .await
+                .unwrap();
+            for _ in 0..4 {
+                tokio::time::sleep(Duration::from_millis(400)).await;
+                socket.write_all(b"5\r\nchunk\r\n").await.unwrap();
+            }
+            socket.write_all(b"0\r\n\r\n").await.unwrap();
+        });
+
+        let config = Config {
+            upstream_base_url: Some(format!("http://{}", addr)),
+            connect_timeout_secs: 1,
+            upstream_first_byte_timeout_secs: 1,
+            ..Config::default()
+        };
+        let provider = create_test_provider(config, valid_credentials());
+
+        let started = std::time::Instant::now();
+        let request = provider.client.post(provider.base_url()).body("{}");
+        let response = provider.send(request, "测试请求失败").await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "chunk".repeat(4));
+        assert!(started.elapsed() > Duration::from_secs(1));
     }
 }
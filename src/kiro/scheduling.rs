@@ -0,0 +1,118 @@
+//! 凭据选择策略
+//!
+//! 新会话分配凭据时按调度模式选择，策略只依赖凭据的 ID、优先级和可用状态，
+//! 由 `MultiTokenManager` 和调度模拟（`simulation`）共用
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::kiro::token_manager::SchedulingMode;
+
+/// 参与选择的凭据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candidate {
+    /// 凭据 ID
+    pub id: u64,
+    /// 优先级（数字越小优先级越高）
+    pub priority: u32,
+    /// 是否已禁用
+    pub disabled: bool,
+}
+
+/// 凭据选择策略
+pub trait SelectionStrategy {
+    /// 从候选凭据中选择一个（跳过已禁用的凭据），没有可用凭据时返回 None
+    fn select(&self, candidates: &[Candidate]) -> Option<u64>;
+}
+
+/// 优先填充：选择优先级最高（priority 最小）的可用凭据，优先级相同时取靠前的
+pub struct PriorityFill;
+
+impl SelectionStrategy for PriorityFill {
+    fn select(&self, candidates: &[Candidate]) -> Option<u64> {
+        candidates
+            .iter()
+            .filter(|c| !c.disabled)
+            .min_by_key(|c| c.priority)
+            .map(|c| c.id)
+    }
+}
+
+/// 轮询：按计数器依次选择可用凭据
+///
+/// 计数器由调用方持有（凭据列表变化时由调用方重置）
+pub struct RoundRobin<'a> {
+    counter: &'a AtomicU64,
+}
+
+impl<'a> RoundRobin<'a> {
+    pub fn new(counter: &'a AtomicU64) -> Self {
+        Self { counter }
+    }
+}
+
+impl SelectionStrategy for RoundRobin<'_> {
+    fn select(&self, candidates: &[Candidate]) -> Option<u64> {
+        let available: Vec<_> = candidates.iter().filter(|c| !c.disabled).collect();
+        if available.is_empty() {
+            return None;
+        }
+
+        let counter = self.counter.fetch_add(1, Ordering::Relaxed);
+        let index = (counter as usize) % available.len();
+        Some(available[index].id)
+    }
+}
+
+/// 按调度模式选择凭据
+pub fn select(mode: SchedulingMode, counter: &AtomicU64, candidates: &[Candidate]) -> Option<u64> {
+    match mode {
+        SchedulingMode::RoundRobin => RoundRobin::new(counter).select(candidates),
+        SchedulingMode::PriorityFill => PriorityFill.select(candidates),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: u64, priority: u32, disabled: bool) -> Candidate {
+        Candidate {
+            id,
+            priority,
+            disabled,
+        }
+    }
+
+    #[test]
+    fn test_priority_fill_skips_disabled() {
+        let candidates = [
+            candidate(1, 0, true),
+            candidate(2, 2, false),
+            candidate(3, 1, false),
+            candidate(4, 1, false),
+        ];
+        assert_eq!(PriorityFill.select(&candidates), Some(3));
+        assert_eq!(PriorityFill.select(&[candidate(1, 0, true)]), None);
+    }
+
+    #[test]
+    fn test_round_robin_cycles_available() {
+        let counter = AtomicU64::new(0);
+        let candidates = [
+            candidate(1, 0, false),
+            candidate(2, 0, true),
+            candidate(3, 0, false),
+        ];
+        let strategy = RoundRobin::new(&counter);
+
+        let picks: Vec<_> = (0..4)
+            .filter_map(|_| strategy.select(&candidates))
+            .collect();
+        assert_eq!(picks, vec![1, 3, 1, 3]);
+        assert_eq!(counter.load(Ordering::Relaxed), 4);
+
+        // 没有可用凭据时不推进计数器
+        assert_eq!(strategy.select(&[candidate(1, 0, true)]), None);
+        assert_eq!(counter.load(Ordering::Relaxed), 4);
+    }
+}
@@ -0,0 +1,359 @@
+//! 调度模拟
+//!
+//! 用合成负载驱动真实的凭据选择策略（`scheduling`），预测各凭据的分配次数、
+//! 额度耗尽时间和会话粘性命中率，用于调整调度模式或增加凭据前的容量评估。
+//! 不涉及真实凭据和上游调用
+
+use std::sync::atomic::AtomicU64;
+
+use serde::{Deserialize, Serialize};
+
+use crate::kiro::scheduling::{self, Candidate, PriorityFill, SelectionStrategy};
+use crate::kiro::token_manager::SchedulingMode;
+
+/// 单次模拟的最大请求数（requestsPerMinute × durationMinutes）
+pub const MAX_SIMULATED_REQUESTS: u64 = 1_000_000;
+
+/// 单次模拟的最大凭据数
+pub const MAX_SIMULATED_CREDENTIALS: usize = 1000;
+
+/// 未指定随机种子时使用的默认种子（相同场景结果可复现）
+const DEFAULT_SEED: u64 = 0x6b69_726f;
+
+/// 模拟场景
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationScenario {
+    /// 每分钟请求数
+    pub requests_per_minute: u32,
+    /// 请求复用已有会话的比例（0-1）
+    #[serde(default)]
+    pub session_reuse_ratio: f64,
+    /// 模拟时长（分钟）
+    pub duration_minutes: u32,
+    /// 调度模式
+    #[serde(default)]
+    pub scheduling_mode: SchedulingMode,
+    /// 模拟凭据（按顺序编号为 #1、#2…）
+    pub credentials: Vec<SimulatedCredential>,
+    /// 随机种子（可选）
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+/// 模拟凭据
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedCredential {
+    /// 优先级（数字越小优先级越高）
+    #[serde(default)]
+    pub priority: u32,
+    /// 每次请求消耗的额度（默认 1）
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    /// 剩余额度（未设置表示不限额度）
+    #[serde(default)]
+    pub quota_remaining: Option<f64>,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+/// 模拟报告
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationReport {
+    /// 总请求数
+    pub total_requests: u64,
+    /// 无可用凭据而被拒绝的请求数
+    pub rejected_requests: u64,
+    /// 复用已有会话的请求数
+    pub reused_session_requests: u64,
+    /// 复用会话时命中原凭据的次数
+    pub session_hits: u64,
+    /// 会话粘性命中率（复用会话请求中命中原凭据的比例）
+    pub session_hit_rate: f64,
+    /// 各凭据结果
+    pub credentials: Vec<SimulatedCredentialResult>,
+}
+
+/// 单个模拟凭据的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedCredentialResult {
+    /// 凭据编号（场景中的顺序，从 1 开始）
+    pub id: u64,
+    pub priority: u32,
+    /// 分配到的请求数
+    pub assignments: u64,
+    /// 消耗的额度
+    pub quota_used: f64,
+    /// 模拟结束时的剩余额度（不限额度时为 null）
+    pub quota_remaining: Option<f64>,
+    /// 模拟期间额度耗尽的时间（分钟）
+    pub exhausted_at_minute: Option<f64>,
+    /// 预计额度耗尽时间（分钟；模拟期间未耗尽时按平均消耗速度外推，不会耗尽时为 null）
+    pub predicted_exhaustion_minute: Option<f64>,
+}
+
+impl SimulationScenario {
+    /// 校验场景参数，返回错误原因
+    pub fn validate(&self) -> Result<(), String> {
+        if self.requests_per_minute == 0 {
+            return Err("requestsPerMinute 必须大于 0".to_string());
+        }
+        if self.duration_minutes == 0 {
+            return Err("durationMinutes 必须大于 0".to_string());
+        }
+        if self.total_requests() > MAX_SIMULATED_REQUESTS {
+            return Err(format!(
+                "总请求数 {} 超过上限 {}",
+                self.total_requests(),
+                MAX_SIMULATED_REQUESTS
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.session_reuse_ratio) {
+            return Err("sessionReuseRatio 必须在 0-1 之间".to_string());
+        }
+        if self.credentials.is_empty() {
+            return Err("credentials 不能为空".to_string());
+        }
+        if self.credentials.len() > MAX_SIMULATED_CREDENTIALS {
+            return Err(format!(
+                "凭据数 {} 超过上限 {}",
+                self.credentials.len(),
+                MAX_SIMULATED_CREDENTIALS
+            ));
+        }
+        for (index, cred) in self.credentials.iter().enumerate() {
+            if !cred.weight.is_finite() || cred.weight <= 0.0 {
+                return Err(format!("credentials[{}].weight 必须大于 0", index));
+            }
+            if let Some(quota) = cred.quota_remaining
+                && (!quota.is_finite() || quota < 0.0)
+            {
+                return Err(format!("credentials[{}].quotaRemaining 不能为负数", index));
+            }
+        }
+        Ok(())
+    }
+
+    /// 总请求数
+    pub fn total_requests(&self) -> u64 {
+        self.requests_per_minute as u64 * self.duration_minutes as u64
+    }
+
+    /// 运行模拟（调用方应先 `validate`）
+    ///
+    /// 请求按时间均匀到达；复用会话时优先使用会话绑定的凭据，
+    /// 绑定凭据不可用时与 `MultiTokenManager` 一致回退到优先级最高的可用凭据；
+    /// 新会话按调度模式选择。凭据剩余额度不足一次请求时视为额度用尽并禁用
+    pub fn run(&self) -> SimulationReport {
+        let mut rng = fastrand::Rng::with_seed(self.seed.unwrap_or(DEFAULT_SEED));
+        let counter = AtomicU64::new(0);
+
+        let mut candidates: Vec<Candidate> = self
+            .credentials
+            .iter()
+            .enumerate()
+            .map(|(index, cred)| Candidate {
+                id: index as u64 + 1,
+                priority: cred.priority,
+                disabled: cred.quota_remaining.is_some_and(|q| q < cred.weight),
+            })
+            .collect();
+        let mut quota: Vec<Option<f64>> =
+            self.credentials.iter().map(|c| c.quota_remaining).collect();
+        let mut assignments = vec![0u64; candidates.len()];
+        let mut exhausted_at: Vec<Option<f64>> = candidates
+            .iter()
+            .map(|c| c.disabled.then_some(0.0))
+            .collect();
+
+        // 会话 -> 绑定的凭据 ID
+        let mut sessions: Vec<u64> = Vec::new();
+        let mut rejected = 0;
+        let mut reused = 0;
+        let mut hits = 0;
+
+        for i in 0..self.total_requests() {
+            let minute = i as f64 / self.requests_per_minute as f64;
+
+            let session = if !sessions.is_empty() && rng.f64() < self.session_reuse_ratio {
+                reused += 1;
+                Some(rng.usize(..sessions.len()))
+            } else {
+                None
+            };
+
+            let cached = session
+                .map(|s| sessions[s])
+                .filter(|id| !candidates[*id as usize - 1].disabled);
+            let selected = match (cached, session) {
+                (Some(id), _) => {
+                    hits += 1;
+                    Some(id)
+                }
+                (None, Some(_)) => PriorityFill.select(&candidates),
+                (None, None) => scheduling::select(self.scheduling_mode, &counter, &candidates),
+            };
+            let Some(id) = selected else {
+                rejected += 1;
+                continue;
+            };
+
+            match session {
+                Some(s) => sessions[s] = id,
+                None => sessions.push(id),
+            }
+
+            let index = id as usize - 1;
+            assignments[index] += 1;
+            let weight = self.credentials[index].weight;
+            if let Some(remaining) = quota[index].as_mut() {
+                *remaining = (*remaining - weight).max(0.0);
+                if *remaining < weight {
+                    candidates[index].disabled = true;
+                    exhausted_at[index] = Some(minute);
+                }
+            }
+        }
+
+        let duration = self.duration_minutes as f64;
+        let credentials = self
+            .credentials
+            .iter()
+            .enumerate()
+            .map(|(index, cred)| {
+                let quota_used = assignments[index] as f64 * cred.weight;
+                let predicted = exhausted_at[index].or_else(|| {
+                    let remaining = quota[index]?;
+                    (quota_used > 0.0).then(|| duration + remaining / (quota_used / duration))
+                });
+                SimulatedCredentialResult {
+                    id: index as u64 + 1,
+                    priority: cred.priority,
+                    assignments: assignments[index],
+                    quota_used,
+                    quota_remaining: quota[index],
+                    exhausted_at_minute: exhausted_at[index],
+                    predicted_exhaustion_minute: predicted,
+                }
+            })
+            .collect();
+
+        SimulationReport {
+            total_requests: self.total_requests(),
+            rejected_requests: rejected,
+            reused_session_requests: reused,
+            session_hits: hits,
+            session_hit_rate: if reused == 0 {
+                0.0
+            } else {
+                hits as f64 / reused as f64
+            },
+            credentials,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scenario(mode: SchedulingMode, credentials: Vec<SimulatedCredential>) -> SimulationScenario {
+        SimulationScenario {
+            requests_per_minute: 10,
+            session_reuse_ratio: 0.0,
+            duration_minutes: 6,
+            scheduling_mode: mode,
+            credentials,
+            seed: None,
+        }
+    }
+
+    fn credential(priority: u32, quota_remaining: Option<f64>) -> SimulatedCredential {
+        SimulatedCredential {
+            priority,
+            weight: 1.0,
+            quota_remaining,
+        }
+    }
+
+    #[test]
+    fn test_round_robin_spreads_new_sessions() {
+        let report = scenario(
+            SchedulingMode::RoundRobin,
+            vec![
+                credential(0, None),
+                credential(0, None),
+                credential(0, None),
+            ],
+        )
+        .run();
+
+        assert_eq!(report.total_requests, 60);
+        assert_eq!(report.rejected_requests, 0);
+        let counts: Vec<u64> = report.credentials.iter().map(|c| c.assignments).collect();
+        assert_eq!(counts, vec![20, 20, 20]);
+    }
+
+    #[test]
+    fn test_priority_fill_exhausts_then_fails_over() {
+        let report = scenario(
+            SchedulingMode::PriorityFill,
+            vec![credential(1, Some(100.0)), credential(0, Some(20.0))],
+        )
+        .run();
+
+        let high = &report.credentials[1];
+        assert_eq!(high.assignments, 20);
+        assert_eq!(high.quota_remaining, Some(0.0));
+        // 第 20 个请求（下标 19）在 1.9 分钟耗尽额度
+        assert_eq!(high.exhausted_at_minute, Some(1.9));
+
+        let low = &report.credentials[0];
+        assert_eq!(low.assignments, 40);
+        assert_eq!(low.exhausted_at_minute, None);
+        // 6 分钟消耗 40，剩余 60 还能用 9 分钟
+        assert_eq!(low.predicted_exhaustion_minute, Some(15.0));
+    }
+
+    #[test]
+    fn test_session_reuse_sticks_to_bound_credential() {
+        let mut s = scenario(
+            SchedulingMode::RoundRobin,
+            vec![credential(0, None), credential(0, None)],
+        );
+        s.session_reuse_ratio = 0.8;
+
+        let report = s.run();
+        assert!(report.reused_session_requests > 0);
+        assert_eq!(report.session_hits, report.reused_session_requests);
+        assert_eq!(report.session_hit_rate, 1.0);
+
+        // 相同种子结果可复现
+        assert_eq!(
+            s.run().reused_session_requests,
+            report.reused_session_requests
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_scenarios() {
+        let mut s = scenario(SchedulingMode::RoundRobin, vec![credential(0, None)]);
+        assert!(s.validate().is_ok());
+
+        s.session_reuse_ratio = 1.5;
+        assert!(s.validate().unwrap_err().contains("sessionReuseRatio"));
+
+        s.session_reuse_ratio = 0.5;
+        s.requests_per_minute = 1_000_000;
+        assert!(s.validate().unwrap_err().contains("超过上限"));
+
+        s.requests_per_minute = 10;
+        s.credentials[0].weight = 0.0;
+        assert!(s.validate().unwrap_err().contains("weight"));
+    }
+}
@@ -0,0 +1,340 @@
+//! 粘性会话诊断统计
+//!
+//! 记录每个请求的会话标识来源（metadata / header / system 哈希 / 无）以及
+//! 会话缓存的命中情况，用于确认粘性会话是否真正生效。
+//!
+//! system prompt 哈希兜底在客户端修改 system 前言后会产生新的会话标识，
+//! 其占比过高时说明客户端应改为发送 `x-session-id`。
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+
+/// 计算 system 哈希占比前要求的最少请求数（样本过少时不告警）
+const MIN_SAMPLES_FOR_WARNING: u64 = 50;
+
+/// system 哈希占比告警的最短间隔
+const WARNING_INTERVAL: Duration = Duration::from_secs(600);
+
+/// 会话标识来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionIdSource {
+    /// metadata.user_id 中的 session_xxx
+    Metadata,
+    /// x-session-id 请求头
+    Header,
+    /// system prompt 哈希（兜底）
+    SystemHash,
+    /// 无法提取会话标识
+    None,
+}
+
+impl SessionIdSource {
+    fn index(self) -> usize {
+        match self {
+            Self::Metadata => 0,
+            Self::Header => 1,
+            Self::SystemHash => 2,
+            Self::None => 3,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Metadata => "metadata",
+            Self::Header => "header",
+            Self::SystemHash => "systemHash",
+            Self::None => "none",
+        }
+    }
+}
+
+impl fmt::Display for SessionIdSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 带会话标识的请求的凭据选择结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StickinessOutcome {
+    /// 使用了会话缓存中的凭据
+    Hit,
+    /// 缓存的凭据不可用，重新绑定到其他凭据
+    Rebound,
+    /// 会话缓存中没有记录，首次绑定
+    New,
+}
+
+impl StickinessOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Hit => "hit",
+            Self::Rebound => "rebound",
+            Self::New => "new",
+        }
+    }
+}
+
+/// 同一新会话的多个请求并发首次绑定时的竞争结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingRace {
+    /// 等待并发的首个请求完成绑定后复用其凭据
+    Joined,
+    /// 等待超时，自行选择凭据
+    WaitTimedOut,
+    /// 绑定时会话已被并发请求绑定，保留已有绑定
+    Duplicate,
+}
+
+/// 粘性会话统计（单个 Token 管理器，即单个池）
+#[derive(Default)]
+pub struct StickinessStats {
+    /// 各来源的请求数（按 [`SessionIdSource::index`] 排列）
+    sources: [AtomicU64; 4],
+    hits: AtomicU64,
+    rebinds: AtomicU64,
+    new_bindings: AtomicU64,
+    binding_waits: AtomicU64,
+    binding_wait_timeouts: AtomicU64,
+    duplicate_bindings: AtomicU64,
+    /// 上次 system 哈希占比告警时间
+    last_warned_at: Mutex<Option<Instant>>,
+}
+
+impl StickinessStats {
+    /// 记录一次请求的会话标识来源
+    pub fn record_source(&self, source: SessionIdSource) {
+        self.sources[source.index()].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一次带会话标识请求的凭据选择结果
+    pub fn record_outcome(&self, outcome: StickinessOutcome) {
+        let counter = match outcome {
+            StickinessOutcome::Hit => &self.hits,
+            StickinessOutcome::Rebound => &self.rebinds,
+            StickinessOutcome::New => &self.new_bindings,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一次新会话首次绑定的并发竞争
+    pub fn record_binding_race(&self, race: BindingRace) {
+        let counter = match race {
+            BindingRace::Joined => &self.binding_waits,
+            BindingRace::WaitTimedOut => &self.binding_wait_timeouts,
+            BindingRace::Duplicate => &self.duplicate_bindings,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// system 哈希占比超过 `warn_percent` 时返回该占比（每 10 分钟最多返回一次）
+    ///
+    /// `warn_percent` 为 0 时不告警；请求数不足 50 时不告警
+    pub fn system_hash_warning(&self, warn_percent: u8) -> Option<f64> {
+        if warn_percent == 0 {
+            return None;
+        }
+        let snapshot = self.snapshot();
+        if snapshot.sources.total() < MIN_SAMPLES_FOR_WARNING {
+            return None;
+        }
+        let ratio = snapshot.system_hash_ratio?;
+        if ratio * 100.0 <= f64::from(warn_percent) {
+            return None;
+        }
+
+        let mut last = self.last_warned_at.lock();
+        if last.is_some_and(|at| at.elapsed() < WARNING_INTERVAL) {
+            return None;
+        }
+        *last = Some(Instant::now());
+        Some(ratio)
+    }
+
+    /// 获取统计快照
+    pub fn snapshot(&self) -> StickinessSnapshot {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        StickinessSnapshot::new(
+            SourceCounts {
+                metadata: load(&self.sources[0]),
+                header: load(&self.sources[1]),
+                system_hash: load(&self.sources[2]),
+                none: load(&self.sources[3]),
+            },
+            load(&self.hits),
+            load(&self.rebinds),
+            load(&self.new_bindings),
+            BindingRaceCounts {
+                waits: load(&self.binding_waits),
+                wait_timeouts: load(&self.binding_wait_timeouts),
+                duplicates: load(&self.duplicate_bindings),
+            },
+        )
+    }
+}
+
+/// 各会话标识来源的请求数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceCounts {
+    pub metadata: u64,
+    pub header: u64,
+    pub system_hash: u64,
+    pub none: u64,
+}
+
+impl SourceCounts {
+    pub fn total(&self) -> u64 {
+        self.metadata + self.header + self.system_hash + self.none
+    }
+}
+
+/// 新会话首次绑定的并发竞争次数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BindingRaceCounts {
+    /// 等待并发的首个请求完成绑定后复用其凭据的请求数
+    pub waits: u64,
+    /// 等待超时、自行选择凭据的请求数
+    pub wait_timeouts: u64,
+    /// 会话已被并发请求绑定、本次绑定被拒绝的请求数
+    pub duplicates: u64,
+}
+
+/// 粘性会话统计快照
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StickinessSnapshot {
+    /// 各会话标识来源的请求数
+    pub sources: SourceCounts,
+    /// 使用会话缓存中凭据的请求数
+    pub hits: u64,
+    /// 缓存凭据不可用、重新绑定的请求数
+    pub rebinds: u64,
+    /// 首次绑定（缓存中无记录）的请求数
+    pub new_bindings: u64,
+    /// 新会话首次绑定的并发竞争次数
+    pub binding_races: BindingRaceCounts,
+    /// 命中率：hits / (hits + rebinds + newBindings)，无带会话标识的请求时为 null
+    pub hit_ratio: Option<f64>,
+    /// system 哈希兜底占全部请求的比例，无请求时为 null
+    pub system_hash_ratio: Option<f64>,
+}
+
+impl StickinessSnapshot {
+    fn new(
+        sources: SourceCounts,
+        hits: u64,
+        rebinds: u64,
+        new_bindings: u64,
+        binding_races: BindingRaceCounts,
+    ) -> Self {
+        let sessions = hits + rebinds + new_bindings;
+        let total = sources.total();
+        Self {
+            sources,
+            hits,
+            rebinds,
+            new_bindings,
+            binding_races,
+            hit_ratio: (sessions > 0).then(|| hits as f64 / sessions as f64),
+            system_hash_ratio: (total > 0).then(|| sources.system_hash as f64 / total as f64),
+        }
+    }
+
+    /// 合并多个池的统计（重新计算比例）
+    pub fn merge<'a>(snapshots: impl IntoIterator<Item = &'a StickinessSnapshot>) -> Self {
+        let mut sources = SourceCounts::default();
+        let (mut hits, mut rebinds, mut new_bindings) = (0, 0, 0);
+        let mut binding_races = BindingRaceCounts::default();
+        for s in snapshots {
+            sources.metadata += s.sources.metadata;
+            sources.header += s.sources.header;
+            sources.system_hash += s.sources.system_hash;
+            sources.none += s.sources.none;
+            hits += s.hits;
+            rebinds += s.rebinds;
+            new_bindings += s.new_bindings;
+            binding_races.waits += s.binding_races.waits;
+            binding_races.wait_timeouts += s.binding_races.wait_timeouts;
+            binding_races.duplicates += s.binding_races.duplicates;
+        }
+        Self::new(sources, hits, rebinds, new_bindings, binding_races)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_ratios() {
+        let stats = StickinessStats::default();
+        assert_eq!(stats.snapshot().hit_ratio, None);
+        assert_eq!(stats.snapshot().system_hash_ratio, None);
+
+        stats.record_source(SessionIdSource::Metadata);
+        stats.record_source(SessionIdSource::SystemHash);
+        stats.record_source(SessionIdSource::SystemHash);
+        stats.record_source(SessionIdSource::None);
+        stats.record_outcome(StickinessOutcome::New);
+        stats.record_outcome(StickinessOutcome::Hit);
+        stats.record_outcome(StickinessOutcome::Hit);
+        stats.record_outcome(StickinessOutcome::Rebound);
+        stats.record_binding_race(BindingRace::Joined);
+        stats.record_binding_race(BindingRace::Duplicate);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.sources.system_hash, 2);
+        assert_eq!(snapshot.sources.total(), 4);
+        assert_eq!(snapshot.hit_ratio, Some(0.5));
+        assert_eq!(snapshot.system_hash_ratio, Some(0.5));
+
+        let merged = StickinessSnapshot::merge([&snapshot, &snapshot]);
+        assert_eq!(merged.sources.total(), 8);
+        assert_eq!(merged.hits, 4);
+        assert_eq!(merged.hit_ratio, Some(0.5));
+        assert_eq!(
+            merged.binding_races,
+            BindingRaceCounts {
+                waits: 2,
+                wait_timeouts: 0,
+                duplicates: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_system_hash_warning_is_rate_limited() {
+        let stats = StickinessStats::default();
+        for _ in 0..MIN_SAMPLES_FOR_WARNING - 1 {
+            stats.record_source(SessionIdSource::SystemHash);
+        }
+        // 样本不足
+        assert_eq!(stats.system_hash_warning(50), None);
+
+        stats.record_source(SessionIdSource::SystemHash);
+        assert_eq!(stats.system_hash_warning(0), None);
+        assert_eq!(stats.system_hash_warning(50), Some(1.0));
+        // 告警间隔内不重复
+        assert_eq!(stats.system_hash_warning(50), None);
+    }
+
+    #[test]
+    fn test_system_hash_warning_below_threshold() {
+        let stats = StickinessStats::default();
+        for i in 0..100 {
+            stats.record_source(if i % 2 == 0 {
+                SessionIdSource::SystemHash
+            } else {
+                SessionIdSource::Header
+            });
+        }
+        assert_eq!(stats.system_hash_warning(50), None);
+        assert_eq!(stats.system_hash_warning(40), Some(0.5));
+    }
+}
@@ -0,0 +1,257 @@
+//! 凭据调用时间线
+//!
+//! 按凭据保存最近的上游调用事件（环形缓冲，容量由 `timelineMaxEventsPerCredential` 配置），
+//! 查询时按指定粒度聚合为时间桶，用于 Admin 查看单个凭据的调用趋势。
+//! 数据仅保存在内存中，重启后清空。
+
+use std::collections::{HashSet, VecDeque};
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+
+/// 时间线查询的最大时间窗口（分钟）
+pub const TIMELINE_MAX_WINDOW_MINUTES: u64 = 24 * 60;
+
+/// 单次调用事件
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallEvent {
+    /// 调用完成时间
+    pub timestamp: DateTime<Utc>,
+    /// 是否成功
+    pub success: bool,
+    /// 响应时间（毫秒）
+    pub latency_ms: u64,
+    /// 请求的模型
+    pub model: String,
+}
+
+/// 时间线聚合桶（用于 API 响应）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineBucket {
+    /// 桶起始时间（按粒度对齐）
+    pub bucket_start: DateTime<Utc>,
+    /// 请求数
+    pub requests: u64,
+    /// 成功数
+    pub successes: u64,
+    /// 失败数
+    pub failures: u64,
+    /// 平均延迟（毫秒，无请求时为 0）
+    pub avg_latency_ms: u64,
+}
+
+/// 各凭据的调用事件（每个凭据最多保留 `max_events` 条，超出时丢弃最旧的）
+#[derive(Debug)]
+pub struct CallTimeline {
+    events: DashMap<u64, VecDeque<CallEvent>>,
+    max_events: usize,
+}
+
+impl CallTimeline {
+    /// 创建时间线（`max_events` 为 0 时不记录）
+    pub fn new(max_events: usize) -> Self {
+        Self {
+            events: DashMap::new(),
+            max_events,
+        }
+    }
+
+    /// 记录一次调用
+    pub fn record(&self, id: u64, event: CallEvent) {
+        if self.max_events == 0 {
+            return;
+        }
+        let mut events = self.events.entry(id).or_default();
+        if events.len() >= self.max_events {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// 移除凭据的调用事件（凭据删除时调用）
+    pub fn remove(&self, id: u64) {
+        self.events.remove(&id);
+    }
+
+    /// 只保留 `ids` 中凭据的调用事件（重新加载凭据后清理已移除的凭据）
+    pub fn retain(&self, ids: &HashSet<u64>) {
+        self.events.retain(|id, _| ids.contains(id));
+    }
+
+    /// 从另一个时间线复制 `ids` 中凭据的调用事件（重建 Token 管理器时沿用）
+    ///
+    /// 已有事件的凭据不覆盖；超出容量时只保留最新的事件
+    pub fn inherit(&self, other: &CallTimeline, ids: &HashSet<u64>) {
+        if self.max_events == 0 {
+            return;
+        }
+        for entry in other.events.iter() {
+            if !ids.contains(entry.key()) || self.events.contains_key(entry.key()) {
+                continue;
+            }
+            let skip = entry.value().len().saturating_sub(self.max_events);
+            let events = entry.value().iter().skip(skip).cloned().collect();
+            self.events.insert(*entry.key(), events);
+        }
+    }
+
+    /// 凭据最近 `window_minutes` 分钟内按 `granularity_minutes` 聚合的时间桶
+    pub fn buckets(
+        &self,
+        id: u64,
+        window_minutes: u64,
+        granularity_minutes: u64,
+    ) -> Vec<TimelineBucket> {
+        let events = self.events.get(&id);
+        let events = events.iter().flat_map(|events| events.iter());
+        aggregate(events, Utc::now(), window_minutes, granularity_minutes)
+    }
+}
+
+/// 将调用事件聚合为时间桶
+///
+/// 桶起始时间按粒度对齐到 Unix 纪元，覆盖包含 `now - window` 到 `now` 的所有桶
+/// （按时间升序，无调用的桶也保留），窗口之外的事件忽略
+pub fn aggregate<'a>(
+    events: impl IntoIterator<Item = &'a CallEvent>,
+    now: DateTime<Utc>,
+    window_minutes: u64,
+    granularity_minutes: u64,
+) -> Vec<TimelineBucket> {
+    let granularity_secs = granularity_minutes.max(1) as i64 * 60;
+    let since = now - Duration::minutes(window_minutes as i64);
+    let first = since.timestamp().div_euclid(granularity_secs);
+    let last = now.timestamp().div_euclid(granularity_secs);
+
+    let mut totals = vec![(0u64, 0u64, 0u64); (last - first + 1) as usize];
+    for event in events {
+        if event.timestamp <= since || event.timestamp > now {
+            continue;
+        }
+        let index = (event.timestamp.timestamp().div_euclid(granularity_secs) - first) as usize;
+        let (successes, failures, latency) = &mut totals[index];
+        if event.success {
+            *successes += 1;
+        } else {
+            *failures += 1;
+        }
+        *latency += event.latency_ms;
+    }
+
+    totals
+        .into_iter()
+        .enumerate()
+        .map(|(index, (successes, failures, latency))| {
+            let requests = successes + failures;
+            TimelineBucket {
+                bucket_start: Utc
+                    .timestamp_opt((first + index as i64) * granularity_secs, 0)
+                    .single()
+                    .unwrap_or_default(),
+                requests,
+                successes,
+                failures,
+                avg_latency_ms: latency / requests.max(1),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(timestamp: DateTime<Utc>, success: bool, latency_ms: u64) -> CallEvent {
+        CallEvent {
+            timestamp,
+            success,
+            latency_ms,
+            model: "claude-sonnet-4.5".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_across_bucket_boundaries() {
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 12, 7, 30).unwrap();
+        let at = |h, m, s| Utc.with_ymd_and_hms(2026, 1, 1, h, m, s).unwrap();
+        let events = [
+            // 窗口之外
+            event(at(11, 52, 0), true, 999),
+            // 11:55 桶（窗口起点 11:52:30 落在该桶内）
+            event(at(11, 54, 59), true, 100),
+            // 12:00 桶的首尾两端
+            event(at(12, 0, 0), true, 100),
+            event(at(12, 4, 59), false, 300),
+            // 12:05 桶
+            event(at(12, 5, 0), true, 50),
+        ];
+
+        let buckets = aggregate(&events, now, 15, 5);
+        let starts: Vec<_> = buckets.iter().map(|b| b.bucket_start).collect();
+        assert_eq!(
+            starts,
+            vec![at(11, 50, 0), at(11, 55, 0), at(12, 0, 0), at(12, 5, 0)]
+        );
+        let counts: Vec<_> = buckets
+            .iter()
+            .map(|b| (b.requests, b.successes, b.failures, b.avg_latency_ms))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![(1, 1, 0, 100), (0, 0, 0, 0), (2, 1, 1, 200), (1, 1, 0, 50)]
+        );
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_latest_events() {
+        let timeline = CallTimeline::new(2);
+        for latency in [10, 20, 30] {
+            timeline.record(1, event(Utc::now(), true, latency));
+        }
+        let buckets = timeline.buckets(1, 5, 5);
+        assert_eq!(buckets.iter().map(|b| b.requests).sum::<u64>(), 2);
+        assert_eq!(buckets.iter().map(|b| b.avg_latency_ms).max(), Some(25));
+
+        // 容量为 0 时不记录
+        let disabled = CallTimeline::new(0);
+        disabled.record(1, event(Utc::now(), true, 10));
+        assert!(disabled.buckets(1, 5, 5).iter().all(|b| b.requests == 0));
+    }
+
+    #[test]
+    fn test_retain_and_inherit() {
+        let requests = |timeline: &CallTimeline, id| {
+            timeline
+                .buckets(id, 5, 5)
+                .iter()
+                .map(|b| b.requests)
+                .sum::<u64>()
+        };
+        let old = CallTimeline::new(10);
+        for (id, latency) in [(1, 10), (1, 20), (1, 30), (2, 40), (3, 50)] {
+            old.record(id, event(Utc::now(), true, latency));
+        }
+
+        // 只沿用新时间线拥有的凭据，超出容量时保留最新的事件
+        let rebuilt = CallTimeline::new(2);
+        rebuilt.inherit(&old, &HashSet::from([1, 2]));
+        assert_eq!(requests(&rebuilt, 1), 2);
+        assert_eq!(
+            rebuilt
+                .buckets(1, 5, 5)
+                .iter()
+                .map(|b| b.avg_latency_ms)
+                .max(),
+            Some(25)
+        );
+        assert_eq!(requests(&rebuilt, 2), 1);
+        assert_eq!(requests(&rebuilt, 3), 0);
+
+        old.retain(&HashSet::from([2, 3]));
+        assert_eq!(requests(&old, 1), 0);
+        assert_eq!(requests(&old, 2), 1);
+        assert_eq!(requests(&old, 3), 1);
+    }
+}
@@ -4,26 +4,45 @@
 //! 支持单凭据 (TokenManager) 和多凭据 (MultiTokenManager) 管理
 //! 支持粘性会话轮询：同一会话绑定同一凭据，新会话轮询分配
 
-use anyhow::bail;
+use anyhow::{Context, bail};
 use chrono::{DateTime, Duration, Utc};
 use dashmap::DashMap;
+use futures::StreamExt;
 use moka::sync::Cache;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration as StdDuration;
-use tokio::sync::Mutex as TokioMutex;
+use tokio::sync::{Mutex as TokioMutex, OwnedMutexGuard};
+use tokio::sync::broadcast;
+use tokio::sync::Notify;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 use std::path::PathBuf;
 
-use crate::http_client::{ProxyConfig, build_client};
+use crate::admin::events::AdminEvent;
+use crate::common::file_format::FileFormat;
+use crate::common::persist::{Change, PersistWriter};
+use crate::http_client::{ProxyConfig, shared_client};
+use crate::kiro::error::KiroError;
+use crate::kiro::fairness::{UserFairness, UserSessionCount};
+use crate::kiro::headers::{self, HeaderContext, UpstreamApi};
 use crate::kiro::machine_id;
-use crate::kiro::model::credentials::KiroCredentials;
+use crate::kiro::model::credentials::{KiroCredentials, avg_latency_ms, normalize_tags};
 use crate::kiro::model::token_refresh::{
     IdcRefreshRequest, IdcRefreshResponse, RefreshRequest, RefreshResponse,
 };
 use crate::kiro::model::usage_limits::UsageLimitsResponse;
+use crate::kiro::pool::DEFAULT_POOL_ID;
+use crate::kiro::scheduling::{self, Candidate};
+use crate::kiro::stickiness::{
+    BindingRace, SessionIdSource, StickinessOutcome, StickinessSnapshot, StickinessStats,
+};
+use crate::kiro::timeline::{CallEvent, CallTimeline, TimelineBucket};
+use crate::kiro::upstream_error::{ClassifiedError, UpstreamErrorKind};
+use crate::kiro::warmup::{WarmupEntry, WarmupReport};
 use crate::model::config::Config;
 
 /// Token 管理器
@@ -86,6 +105,9 @@ impl TokenManager {
     }
 }
 
+/// 强制刷新时写入的过期时间（使 Token 被视为已过期）
+const FORCE_REFRESH_EXPIRES_AT: &str = "1970-01-01T00:00:00Z";
+
 /// 检查 Token 是否在指定时间内过期
 pub fn is_token_expiring_within(
     credentials: &KiroCredentials,
@@ -110,27 +132,134 @@ pub fn is_token_expiring_soon(credentials: &KiroCredentials) -> bool {
 
 /// 验证 refreshToken 的基本有效性
 pub(crate) fn validate_refresh_token(credentials: &KiroCredentials) -> anyhow::Result<()> {
-    let refresh_token = credentials
-        .refresh_token
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("缺少 refreshToken"))?;
+    let refresh_token = credentials.refresh_token.as_ref().ok_or_else(|| {
+        ClassifiedError::new(UpstreamErrorKind::AuthExpired, "缺少 refreshToken")
+    })?;
 
     if refresh_token.is_empty() {
-        bail!("refreshToken 为空");
+        bail!(ClassifiedError::new(
+            UpstreamErrorKind::AuthExpired,
+            "refreshToken 为空"
+        ));
     }
 
     if refresh_token.len() < 100 || refresh_token.ends_with("...") || refresh_token.contains("...")
     {
-        bail!(
-            "refreshToken 已被截断（长度: {} 字符）。\n\
-             这通常是 Kiro IDE 为了防止凭证被第三方工具使用而故意截断的。",
-            refresh_token.len()
-        );
+        bail!(ClassifiedError::new(
+            UpstreamErrorKind::AuthExpired,
+            format!(
+                "refreshToken 已被截断（长度: {} 字符）。\n\
+                 这通常是 Kiro IDE 为了防止凭证被第三方工具使用而故意截断的。",
+                refresh_token.len()
+            )
+        ));
     }
 
     Ok(())
 }
 
+/// 凭据配置警告（不阻止加载，仅提示可能的配置问题）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationWarning {
+    /// refreshToken 长度在 100~150 之间，可能不完整
+    RefreshTokenMaybeIncomplete { length: usize },
+    /// authMethod 为 idc 但缺少 clientId/clientSecret
+    IdcMissingClientCredentials,
+    /// region 包含非法字符
+    InvalidRegion { region: String },
+    /// expiresAt 已过期超过 24 小时
+    TokenVeryStale { hours: i64 },
+    /// machineId 长度不在 32~128 之间
+    MachineIdLength { length: usize },
+}
+
+impl ValidationWarning {
+    /// 警告码（供 Admin API 使用）
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::RefreshTokenMaybeIncomplete { .. } => "refresh_token_maybe_incomplete",
+            Self::IdcMissingClientCredentials => "idc_missing_client_credentials",
+            Self::InvalidRegion { .. } => "invalid_region",
+            Self::TokenVeryStale { .. } => "token_very_stale",
+            Self::MachineIdLength { .. } => "machine_id_length",
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RefreshTokenMaybeIncomplete { length } => {
+                write!(f, "refreshToken 长度偏短（{} 字符），可能不完整", length)
+            }
+            Self::IdcMissingClientCredentials => {
+                write!(f, "authMethod 为 idc 但缺少 clientId/clientSecret")
+            }
+            Self::InvalidRegion { region } => write!(f, "region 包含非法字符: {}", region),
+            Self::TokenVeryStale { hours } => {
+                write!(f, "expiresAt 已过期 {} 小时，Token 非常陈旧", hours)
+            }
+            Self::MachineIdLength { length } => {
+                write!(f, "machineId 长度异常（{} 字符，应为 32~128）", length)
+            }
+        }
+    }
+}
+
+/// 检查凭据配置，返回警告列表（不影响凭据是否可用）
+pub fn validate_credential(credentials: &KiroCredentials) -> Vec<ValidationWarning> {
+    let mut warnings = Vec::new();
+
+    if let Some(token) = &credentials.refresh_token
+        && token.len() > 100
+        && token.len() < 150
+    {
+        warnings.push(ValidationWarning::RefreshTokenMaybeIncomplete {
+            length: token.len(),
+        });
+    }
+
+    let is_idc = credentials.auth_method.as_deref().is_some_and(|m| {
+        m.eq_ignore_ascii_case("idc")
+            || m.eq_ignore_ascii_case("builder-id")
+            || m.eq_ignore_ascii_case("iam")
+    });
+    if is_idc && (credentials.client_id.is_none() || credentials.client_secret.is_none()) {
+        warnings.push(ValidationWarning::IdcMissingClientCredentials);
+    }
+
+    if let Some(region) = &credentials.region
+        && (region.is_empty()
+            || !region
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'))
+    {
+        warnings.push(ValidationWarning::InvalidRegion {
+            region: region.clone(),
+        });
+    }
+
+    if let Some(expires) = credentials
+        .expires_at
+        .as_deref()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+    {
+        let hours = (Utc::now() - expires.with_timezone(&Utc)).num_hours();
+        if hours > 24 {
+            warnings.push(ValidationWarning::TokenVeryStale { hours });
+        }
+    }
+
+    if let Some(machine_id) = &credentials.machine_id {
+        let length = machine_id.len();
+        if !(32..=128).contains(&length) {
+            warnings.push(ValidationWarning::MachineIdLength { length });
+        }
+    }
+
+    warnings
+}
+
 /// 刷新 Token
 pub async fn refresh_token(
     credentials: &KiroCredentials,
@@ -139,8 +268,18 @@ pub async fn refresh_token(
 ) -> anyhow::Result<KiroCredentials> {
     validate_refresh_token(credentials)?;
 
-    // 根据 auth_method 选择刷新方式
-    // 如果未指定 auth_method，根据是否有 clientId/clientSecret 自动判断
+    if uses_social_refresh(credentials) {
+        refresh_social_token(credentials, config, proxy).await
+    } else {
+        refresh_idc_token(credentials, config, proxy).await
+    }
+}
+
+/// 凭据是否走 Social 刷新（每次刷新都会轮换 refreshToken）
+///
+/// 根据 auth_method 选择刷新方式；
+/// 如果未指定 auth_method，根据是否有 clientId/clientSecret 自动判断
+fn uses_social_refresh(credentials: &KiroCredentials) -> bool {
     let auth_method = credentials.auth_method.as_deref().unwrap_or_else(|| {
         if credentials.client_id.is_some() && credentials.client_secret.is_some() {
             "idc"
@@ -149,14 +288,9 @@ pub async fn refresh_token(
         }
     });
 
-    if auth_method.eq_ignore_ascii_case("idc")
+    !(auth_method.eq_ignore_ascii_case("idc")
         || auth_method.eq_ignore_ascii_case("builder-id")
-        || auth_method.eq_ignore_ascii_case("iam")
-    {
-        refresh_idc_token(credentials, config, proxy).await
-    } else {
-        refresh_social_token(credentials, config, proxy).await
-    }
+        || auth_method.eq_ignore_ascii_case("iam"))
 }
 
 /// 刷新 Social Token
@@ -171,28 +305,26 @@ async fn refresh_social_token(
     // 优先使用凭据级 region，未配置时回退到 config.region
     let region = credentials.region.as_ref().unwrap_or(&config.region);
 
-    let refresh_url = format!("https://prod.{}.auth.desktop.kiro.dev/refreshToken", region);
-    let refresh_domain = format!("prod.{}.auth.desktop.kiro.dev", region);
-    let machine_id = machine_id::generate_from_credentials(credentials, config)
-        .ok_or_else(|| anyhow::anyhow!("无法生成 machineId"))?;
-    let kiro_version = &config.kiro_version;
+    let endpoint = config.refresh_social_endpoint(region);
+    let refresh_url = endpoint.url("/refreshToken");
+    let headers = headers::build_headers(
+        &HeaderContext {
+            api: UpstreamApi::RefreshSocial,
+            credentials,
+            token: None,
+            host: &endpoint.host,
+        },
+        config,
+    )?;
 
-    let client = build_client(proxy, 60, config.tls_backend)?;
+    let client = shared_client(proxy, config.refresh_http_timeouts(), config.tls_options())?;
     let body = RefreshRequest {
         refresh_token: refresh_token.to_string(),
     };
 
     let response = client
         .post(&refresh_url)
-        .header("Accept", "application/json, text/plain, */*")
-        .header("Content-Type", "application/json")
-        .header(
-            "User-Agent",
-            format!("KiroIDE-{}-{}", kiro_version, machine_id),
-        )
-        .header("Accept-Encoding", "gzip, compress, deflate, br")
-        .header("host", &refresh_domain)
-        .header("Connection", "close")
+        .headers(headers)
         .json(&body)
         .send()
         .await?;
@@ -207,7 +339,11 @@ async fn refresh_social_token(
             500..=599 => "服务器错误，AWS OAuth 服务暂时不可用",
             _ => "Token 刷新失败",
         };
-        bail!("{}: {} {}", error_msg, status, body_text);
+        let kind = UpstreamErrorKind::from_response(status.as_u16(), &body_text);
+        bail!(
+            ClassifiedError::new(kind, format!("{}: {} {}", error_msg, status, body_text))
+                .with_status(status.as_u16())
+        );
     }
 
     let data: RefreshResponse = response.json().await?;
@@ -231,9 +367,6 @@ async fn refresh_social_token(
     Ok(new_credentials)
 }
 
-/// IdC Token 刷新所需的 x-amz-user-agent header
-const IDC_AMZ_USER_AGENT: &str = "aws-sdk-js/3.738.0 ua/2.1 os/other lang/js md/browser#unknown_unknown api/sso-oidc#3.738.0 m/E KiroIDE";
-
 /// 刷新 IdC Token (AWS SSO OIDC)
 async fn refresh_idc_token(
     credentials: &KiroCredentials,
@@ -254,9 +387,19 @@ async fn refresh_idc_token(
 
     // 优先使用凭据级 region，未配置时回退到 config.region
     let region = credentials.region.as_ref().unwrap_or(&config.region);
-    let refresh_url = format!("https://oidc.{}.amazonaws.com/token", region);
+    let endpoint = config.refresh_idc_endpoint(region);
+    let refresh_url = endpoint.url("/token");
+    let headers = headers::build_headers(
+        &HeaderContext {
+            api: UpstreamApi::RefreshIdc,
+            credentials,
+            token: None,
+            host: &endpoint.host,
+        },
+        config,
+    )?;
 
-    let client = build_client(proxy, 60, config.tls_backend)?;
+    let client = shared_client(proxy, config.refresh_http_timeouts(), config.tls_options())?;
     let body = IdcRefreshRequest {
         client_id: client_id.to_string(),
         client_secret: client_secret.to_string(),
@@ -266,15 +409,7 @@ async fn refresh_idc_token(
 
     let response = client
         .post(&refresh_url)
-        .header("Content-Type", "application/json")
-        .header("Host", format!("oidc.{}.amazonaws.com", region))
-        .header("Connection", "keep-alive")
-        .header("x-amz-user-agent", IDC_AMZ_USER_AGENT)
-        .header("Accept", "*/*")
-        .header("Accept-Language", "*")
-        .header("sec-fetch-mode", "cors")
-        .header("User-Agent", "node")
-        .header("Accept-Encoding", "br, gzip, deflate")
+        .headers(headers)
         .json(&body)
         .send()
         .await?;
@@ -289,7 +424,11 @@ async fn refresh_idc_token(
             500..=599 => "服务器错误，AWS OIDC 服务暂时不可用",
             _ => "IdC Token 刷新失败",
         };
-        bail!("{}: {} {}", error_msg, status, body_text);
+        let kind = UpstreamErrorKind::from_response(status.as_u16(), &body_text);
+        bail!(
+            ClassifiedError::new(kind, format!("{}: {} {}", error_msg, status, body_text))
+                .with_status(status.as_u16())
+        );
     }
 
     let data: IdcRefreshResponse = response.json().await?;
@@ -309,9 +448,6 @@ async fn refresh_idc_token(
     Ok(new_credentials)
 }
 
-/// getUsageLimits API 所需的 x-amz-user-agent header 前缀
-const USAGE_LIMITS_AMZ_USER_AGENT_PREFIX: &str = "aws-sdk-js/1.0.0";
-
 /// 获取使用额度信息
 pub(crate) async fn get_usage_limits(
     credentials: &KiroCredentials,
@@ -321,47 +457,29 @@ pub(crate) async fn get_usage_limits(
 ) -> anyhow::Result<UsageLimitsResponse> {
     tracing::debug!("正在获取使用额度信息...");
 
-    let region = &config.region;
-    let host = format!("q.{}.amazonaws.com", region);
-    let machine_id = machine_id::generate_from_credentials(credentials, config)
-        .ok_or_else(|| anyhow::anyhow!("无法生成 machineId"))?;
-    let kiro_version = &config.kiro_version;
+    let endpoint = config.kiro_api_endpoint();
 
     // 构建 URL
-    let mut url = format!(
-        "https://{}/getUsageLimits?origin=AI_EDITOR&resourceType=AGENTIC_REQUEST",
-        host
-    );
+    let mut url = endpoint.url("/getUsageLimits?origin=AI_EDITOR&resourceType=AGENTIC_REQUEST");
 
     // profileArn 是可选的
     if let Some(profile_arn) = &credentials.profile_arn {
         url.push_str(&format!("&profileArn={}", urlencoding::encode(profile_arn)));
     }
 
-    // 构建 User-Agent headers
-    let user_agent = format!(
-        "aws-sdk-js/1.0.0 ua/2.1 os/darwin#24.6.0 lang/js md/nodejs#22.21.1 \
-         api/codewhispererruntime#1.0.0 m/N,E KiroIDE-{}-{}",
-        kiro_version, machine_id
-    );
-    let amz_user_agent = format!(
-        "{} KiroIDE-{}-{}",
-        USAGE_LIMITS_AMZ_USER_AGENT_PREFIX, kiro_version, machine_id
-    );
+    let headers = headers::build_headers(
+        &HeaderContext {
+            api: UpstreamApi::UsageLimits,
+            credentials,
+            token: Some(token),
+            host: &endpoint.host,
+        },
+        config,
+    )?;
 
-    let client = build_client(proxy, 60, config.tls_backend)?;
+    let client = shared_client(proxy, config.refresh_http_timeouts(), config.tls_options())?;
 
-    let response = client
-        .get(&url)
-        .header("x-amz-user-agent", &amz_user_agent)
-        .header("User-Agent", &user_agent)
-        .header("host", &host)
-        .header("amz-sdk-invocation-id", uuid::Uuid::new_v4().to_string())
-        .header("amz-sdk-request", "attempt=1; max=1")
-        .header("Authorization", format!("Bearer {}", token))
-        .header("Connection", "close")
-        .send()
-        .await?;
+    let response = client.get(&url).headers(headers).send().await?;
 
     let status = response.status();
     if !status.is_success() {
@@ -384,6 +502,114 @@ pub(crate) async fn get_usage_limits(
 // 多凭据 Token 管理器
 // ============================================================================
 
+/// 当前 Unix 时间戳（毫秒）
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// 每个凭据保留的历史版本数
+const CREDENTIAL_HISTORY_LIMIT: usize = 5;
+
+/// 凭据管理设置变更前的快照（仅保存在内存中，用于回滚）
+///
+/// 只记录优先级、禁用状态、备注和标签；Token 不纳入快照，
+/// 避免回滚恢复已被轮换失效的 refreshToken
+#[derive(Debug, Clone)]
+pub struct CredentialSnapshot {
+    /// 快照时间
+    pub timestamp: std::time::Instant,
+    /// 优先级
+    pub priority: u32,
+    /// 是否已禁用
+    pub disabled: bool,
+    /// 备注
+    pub notes: Option<String>,
+    /// 标签
+    pub tags: Vec<String>,
+}
+
+/// 每个凭据保留的禁用/启用记录数
+const DISABLE_HISTORY_LIMIT: usize = 20;
+
+/// 每个凭据保留的最近响应时间样本数（用于计算延迟分位数）
+const RESPONSE_TIME_SAMPLE_LIMIT: usize = 100;
+
+/// 延迟直方图的桶宽（毫秒）
+pub const LATENCY_BUCKET_MS: u64 = 50;
+
+/// 延迟直方图的最大定宽桶数（超出范围的样本计入末尾的溢出桶）
+pub const LATENCY_MAX_BUCKETS: usize = 100;
+
+/// 延迟直方图的一个桶（`[start_ms, end_ms)`）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyBucket {
+    /// 桶起点（毫秒，含）
+    pub start_ms: u64,
+    /// 桶终点（毫秒，不含；溢出桶为 None）
+    pub end_ms: Option<u64>,
+    /// 落入该桶的样本数
+    pub count: usize,
+}
+
+/// 已排序样本的分位数（最近秩法，`percentile` 取 0~100；无样本时为 None）
+fn sorted_percentile(sorted: &[u64], percentile: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// 按 `LATENCY_BUCKET_MS` 划分响应时间直方图（从 0 到最大样本，空桶也保留）
+///
+/// 最多 `LATENCY_MAX_BUCKETS` 个定宽桶，超出范围的样本合并到末尾的溢出桶
+fn latency_histogram(samples: &[u64]) -> Vec<LatencyBucket> {
+    let Some(&max) = samples.iter().max() else {
+        return Vec::new();
+    };
+    let bucket_index = |sample: u64| (sample / LATENCY_BUCKET_MS).min(LATENCY_MAX_BUCKETS as u64);
+    let mut counts = vec![0; bucket_index(max) as usize + 1];
+    for &sample in samples {
+        counts[bucket_index(sample) as usize] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| LatencyBucket {
+            start_ms: i as u64 * LATENCY_BUCKET_MS,
+            end_ms: (i < LATENCY_MAX_BUCKETS).then_some((i as u64 + 1) * LATENCY_BUCKET_MS),
+            count,
+        })
+        .collect()
+}
+
+/// 自动禁用/启用（失败阈值、额度、自愈等）记录的操作者
+pub const SYSTEM_ACTOR: &str = "system";
+
+/// 凭据禁用状态的一次变化（仅保存在内存中）
+#[derive(Debug, Clone)]
+pub struct DisableTransition {
+    /// 变化时间
+    pub timestamp: DateTime<Utc>,
+    /// 变化后是否禁用
+    pub disabled: bool,
+    /// 原因（可读文本）
+    pub reason: String,
+    /// 操作者（自动变化为 `system`，手动操作为 Admin 身份）
+    pub actor: String,
+}
+
+/// 按 `maxConcurrentRequests` 创建并发信号量（未配置或为 0 时不限制）
+fn concurrency_semaphore(cred: &KiroCredentials) -> (Option<u32>, Arc<Semaphore>) {
+    let max = cred.max_concurrent_requests.filter(|&max| max > 0);
+    let permits = max.map_or(Semaphore::MAX_PERMITS, |max| max as usize);
+    (max, Arc::new(Semaphore::new(permits)))
+}
+
 /// 单个凭据条目的状态
 struct CredentialEntry {
     /// 凭据唯一 ID
@@ -396,6 +622,26 @@ struct CredentialEntry {
     disabled: bool,
     /// 禁用原因（用于区分手动禁用 vs 自动禁用，便于自愈）
     disabled_reason: Option<DisabledReason>,
+    /// 额度重置时间（Unix 时间戳秒，额度用尽禁用时从 getUsageLimits 获取）
+    quota_reset_at: Option<u64>,
+    /// 最近一次查询到的额度（`(已用量, 总额度)`，查询余额时更新）
+    cached_usage: Option<(f64, f64)>,
+    /// 最近一次导致失败的错误信息
+    last_error: Option<String>,
+    /// 上游 429 `Retry-After` 退避截止时间（之前不参与调度）
+    retry_after_until: Option<std::time::Instant>,
+    /// 运行时状态最后变化时间（Unix 时间戳毫秒，用于 Admin 增量查询）
+    last_changed: u64,
+    /// 最近的变更前快照（最旧的在前，最多 `CREDENTIAL_HISTORY_LIMIT` 个）
+    history: VecDeque<CredentialSnapshot>,
+    /// 最近一次禁用时间（重新启用后保留，持久化到凭据文件）
+    disabled_at: Option<DateTime<Utc>>,
+    /// 最近的禁用/启用记录（最旧的在前，最多 `DISABLE_HISTORY_LIMIT` 条）
+    disable_history: VecDeque<DisableTransition>,
+    /// 最大并发请求数（加载时取自 `maxConcurrentRequests`，None 表示不限制）
+    max_concurrency: Option<u32>,
+    /// 并发请求信号量（每个进行中的调用持有一个名额）
+    concurrency: Arc<Semaphore>,
     // ============ 调用统计字段 ============
     /// 成功调用次数（总计）
     success_count: u64,
@@ -405,6 +651,8 @@ struct CredentialEntry {
     last_call_time: Option<u64>,
     /// 累计响应时间（毫秒，用于计算平均值）
     total_response_time_ms: u64,
+    /// 最近的成功调用响应时间（毫秒，最旧的在前，最多 `RESPONSE_TIME_SAMPLE_LIMIT` 个，仅保存在内存中）
+    response_times: VecDeque<u64>,
     /// 今日成功调用次数
     today_success_count: u64,
     /// 今日失败调用次数
@@ -420,6 +668,171 @@ struct CredentialEntry {
     last_token_refresh_time: Option<u64>,
 }
 
+impl CredentialEntry {
+    /// 从持久化的凭据创建条目（统计从文件加载，运行时状态重置）
+    fn loaded(id: u64, cred: KiroCredentials) -> Self {
+        let (max_concurrency, concurrency) = concurrency_semaphore(&cred);
+        Self {
+            id,
+            // 从持久化数据加载统计
+            success_count: cred.success_count,
+            total_failure_count: cred.total_failure_count,
+            last_call_time: cred.last_call_time,
+            total_response_time_ms: cred.total_response_time_ms,
+            response_times: VecDeque::new(),
+            token_refresh_count: cred.token_refresh_count,
+            token_refresh_failure_count: cred.token_refresh_failure_count,
+            last_token_refresh_time: cred.last_token_refresh_time,
+            disabled_at: cred.disabled_at,
+            max_concurrency,
+            concurrency,
+            // 今日统计不持久化，每次启动重置
+            today_success_count: 0,
+            today_failure_count: 0,
+            today_date: None,
+            // 运行时状态
+            credentials: cred,
+            failure_count: 0,
+            disabled: false,
+            disabled_reason: None,
+            quota_reset_at: None,
+            cached_usage: None,
+            last_error: None,
+            retry_after_until: None,
+            last_changed: now_millis(),
+            history: VecDeque::new(),
+            disable_history: VecDeque::new(),
+        }
+    }
+
+    /// 当前进行中的调用数
+    fn current_concurrency(&self) -> u32 {
+        let capacity = self
+            .max_concurrency
+            .map_or(Semaphore::MAX_PERMITS, |max| max as usize);
+        capacity.saturating_sub(self.concurrency.available_permits()) as u32
+    }
+
+    /// 待持久化的凭据（同步统计数据）
+    fn persisted(&self) -> KiroCredentials {
+        let mut cred = self.credentials.clone();
+        cred.canonicalize_auth_method();
+        cred.success_count = self.success_count;
+        cred.total_failure_count = self.total_failure_count;
+        cred.last_call_time = self.last_call_time;
+        cred.total_response_time_ms = self.total_response_time_ms;
+        cred.token_refresh_count = self.token_refresh_count;
+        cred.token_refresh_failure_count = self.token_refresh_failure_count;
+        cred.last_token_refresh_time = self.last_token_refresh_time;
+        cred.disabled_at = self.disabled_at;
+        if let Some(latest) = self.disable_history.iter().rev().find(|t| t.disabled) {
+            cred.disabled_reason = Some(latest.reason.clone());
+        }
+        cred
+    }
+
+    /// 标记运行时状态已变化
+    fn touch(&mut self) {
+        self.last_changed = now_millis();
+    }
+
+    /// 变更前记录当前状态，超出上限时丢弃最旧的快照
+    fn record_history(&mut self) {
+        if self.history.len() == CREDENTIAL_HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.history.push_back(CredentialSnapshot {
+            timestamp: std::time::Instant::now(),
+            priority: self.credentials.priority,
+            disabled: self.disabled,
+            notes: self.credentials.notes.clone(),
+            tags: self.credentials.tags.clone(),
+        });
+    }
+
+    /// 禁用凭据并记录禁用历史（已因同一原因禁用时不重复记录）
+    fn disable(&mut self, reason: DisabledReason, actor: &str) {
+        if self.disabled && self.disabled_reason == Some(reason) {
+            return;
+        }
+        let now = Utc::now();
+        self.disabled = true;
+        self.disabled_reason = Some(reason);
+        self.disabled_at = Some(now);
+        self.push_transition(now, true, reason.description(), actor);
+    }
+
+    /// 启用凭据并记录禁用历史（未禁用时不记录）
+    fn enable(&mut self, reason: &str, actor: &str) {
+        if !self.disabled {
+            return;
+        }
+        self.disabled = false;
+        self.disabled_reason = None;
+        self.push_transition(Utc::now(), false, reason, actor);
+    }
+
+    /// 追加禁用/启用记录，超出上限时丢弃最旧的记录
+    fn push_transition(
+        &mut self,
+        timestamp: DateTime<Utc>,
+        disabled: bool,
+        reason: &str,
+        actor: &str,
+    ) {
+        if self.disable_history.len() == DISABLE_HISTORY_LIMIT {
+            self.disable_history.pop_front();
+        }
+        self.disable_history.push_back(DisableTransition {
+            timestamp,
+            disabled,
+            reason: reason.to_string(),
+            actor: actor.to_string(),
+        });
+    }
+
+    /// 记录一次成功调用的响应时间，超出上限时丢弃最旧的样本
+    fn record_response_time(&mut self, time_ms: u64) {
+        if self.response_times.len() == RESPONSE_TIME_SAMPLE_LIMIT {
+            self.response_times.pop_front();
+        }
+        self.response_times.push_back(time_ms);
+    }
+
+    /// 最近响应时间的 P50 / P95 / P99（毫秒，无样本时为 None）
+    fn latency_percentiles(&self) -> [Option<u64>; 3] {
+        let mut sorted: Vec<u64> = self.response_times.iter().copied().collect();
+        sorted.sort_unstable();
+        [50.0, 95.0, 99.0].map(|p| sorted_percentile(&sorted, p))
+    }
+
+    /// 禁用历史中的禁用次数（用于衡量凭据是否反复禁用/启用）
+    fn flap_count(&self) -> usize {
+        self.disable_history.iter().filter(|t| t.disabled).count()
+    }
+
+    /// 选择策略使用的候选视图（退避中的凭据视为不可用）
+    fn candidate(&self) -> Candidate {
+        Candidate {
+            id: self.id,
+            priority: self.credentials.priority,
+            disabled: !self.is_schedulable(),
+        }
+    }
+
+    /// 剩余的 `Retry-After` 退避时长（未退避或已到期时为 None）
+    fn retry_after_remaining(&self) -> Option<StdDuration> {
+        self.retry_after_until
+            .and_then(|until| until.checked_duration_since(std::time::Instant::now()))
+            .filter(|d| !d.is_zero())
+    }
+
+    /// 是否可参与调度（未禁用且不在 `Retry-After` 退避期内）
+    fn is_schedulable(&self) -> bool {
+        !self.disabled && self.retry_after_remaining().is_none()
+    }
+}
+
 /// 禁用原因
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum DisabledReason {
@@ -433,6 +846,90 @@ enum DisabledReason {
     TokenRefreshFailed,
 }
 
+impl DisabledReason {
+    /// 可读的禁用原因
+    fn description(self) -> &'static str {
+        match self {
+            DisabledReason::Manual => "手动禁用",
+            DisabledReason::TooManyFailures => "连续失败次数过多",
+            DisabledReason::QuotaExceeded => "额度已用尽",
+            DisabledReason::TokenRefreshFailed => "Token 刷新失败",
+        }
+    }
+}
+
+/// 失败分类（用于管理面板按类型着色）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    /// 网络错误（超时、连接失败、上游 5xx 等）
+    NetworkError,
+    /// 认证错误（refreshToken 无效、401/403 等）
+    AuthError,
+    /// 额度已用尽
+    QuotaExceeded,
+    /// Token 已过期
+    TokenExpired,
+    /// 无法识别
+    Unknown,
+}
+
+impl FailureClass {
+    /// 根据错误信息分类
+    pub fn from_error_message(message: &str) -> Self {
+        let message = message.to_lowercase();
+        let contains_any = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+
+        if contains_any(&["monthly_request_count", "quota", "额度"]) {
+            return FailureClass::QuotaExceeded;
+        }
+        // refreshToken 过期需要重新登录，归为认证错误
+        let refresh_token_error = contains_any(&["refreshtoken", "refresh token", "invalid_grant"]);
+        if !refresh_token_error && contains_any(&["expired", "过期"]) {
+            return FailureClass::TokenExpired;
+        }
+        if refresh_token_error
+            || contains_any(&[
+                "401",
+                "403",
+                "unauthorized",
+                "forbidden",
+                "access denied",
+                "accessdenied",
+                "认证",
+            ])
+        {
+            return FailureClass::AuthError;
+        }
+        if contains_any(&[
+            "timeout",
+            "timed out",
+            "connect",
+            "dns",
+            "network",
+            "error sending request",
+            "502",
+            "503",
+            "504",
+            "超时",
+            "网络",
+        ]) {
+            return FailureClass::NetworkError;
+        }
+        FailureClass::Unknown
+    }
+
+    /// 根据禁用原因分类（没有错误信息时使用）
+    fn from_disabled_reason(reason: DisabledReason) -> Option<Self> {
+        match reason {
+            DisabledReason::QuotaExceeded => Some(FailureClass::QuotaExceeded),
+            DisabledReason::TokenRefreshFailed => Some(FailureClass::AuthError),
+            DisabledReason::TooManyFailures => Some(FailureClass::Unknown),
+            DisabledReason::Manual => None,
+        }
+    }
+}
+
 // ============================================================================
 // Admin API 公开结构
 // ============================================================================
@@ -455,6 +952,38 @@ pub struct CredentialEntrySnapshot {
     pub has_profile_arn: bool,
     /// Token 过期时间
     pub expires_at: Option<String>,
+    /// 备注
+    pub notes: Option<String>,
+    /// 标签
+    pub tags: Vec<String>,
+    /// 凭据级 Region
+    pub region: Option<String>,
+    /// 生效的 Region（凭据 > 池默认值 > 全局）
+    pub effective_region: String,
+    /// 生效 Region 的来源
+    pub region_source: SettingSource,
+    /// 生效代理的来源（未配置任何代理时为 None）
+    pub proxy_source: Option<SettingSource>,
+    /// 所属池 ID（未配置时属于默认池）
+    pub pool_id: Option<String>,
+    /// 禁用原因（可读文本，未禁用时为 None）
+    pub disabled_reason: Option<String>,
+    /// 最近一次禁用时间（RFC3339，重新启用后保留）
+    pub disabled_at: Option<String>,
+    /// 最近禁用历史中的禁用次数（越大越不稳定）
+    pub flap_count: usize,
+    /// 当前进行中的调用数
+    pub current_concurrency: u32,
+    /// 最大并发请求数（None 表示不限制）
+    pub max_concurrency: Option<u32>,
+    /// 最近一次导致失败的错误信息
+    pub last_error: Option<String>,
+    /// 上游 429 `Retry-After` 退避截止时间（RFC3339，未退避时为 None）
+    pub retry_after_until: Option<String>,
+    /// 失败分类（根据错误信息推断）
+    pub failure_classification: Option<FailureClass>,
+    /// 运行时状态最后变化时间（Unix 时间戳毫秒）
+    pub last_changed: u64,
     // ============ 调用统计字段 ============
     /// 成功调用次数（总计）
     pub success_count: u64,
@@ -468,6 +997,14 @@ pub struct CredentialEntrySnapshot {
     pub last_call_time: Option<u64>,
     /// 平均响应时间（毫秒）
     pub avg_response_time_ms: Option<u64>,
+    /// 平均响应时间（毫秒，不取整）
+    pub avg_latency_ms: Option<f64>,
+    /// 最近成功调用响应时间的 P50（毫秒）
+    pub p50_latency_ms: Option<u64>,
+    /// 最近成功调用响应时间的 P95（毫秒）
+    pub p95_latency_ms: Option<u64>,
+    /// 最近成功调用响应时间的 P99（毫秒）
+    pub p99_latency_ms: Option<u64>,
     /// 今日成功调用次数
     pub today_success_count: u64,
     /// 今日失败调用次数
@@ -483,6 +1020,25 @@ pub struct CredentialEntrySnapshot {
     pub last_token_refresh_time: Option<u64>,
 }
 
+impl CredentialEntrySnapshot {
+    /// 健康分（0~100）
+    ///
+    /// 已禁用为 0；否则为成功率（无调用记录时视为 100），
+    /// 并按连续失败次数占禁用阈值的比例扣减
+    pub fn health_score(&self) -> f64 {
+        if self.disabled {
+            return 0.0;
+        }
+        let base = if self.total_calls > 0 {
+            self.success_rate
+        } else {
+            100.0
+        };
+        let failures = self.failure_count.min(MAX_FAILURES_PER_CREDENTIAL) as f64;
+        base * (1.0 - failures / MAX_FAILURES_PER_CREDENTIAL as f64)
+    }
+}
+
 /// 凭据管理器状态快照
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -497,6 +1053,10 @@ pub struct ManagerSnapshot {
     pub available: usize,
     /// 会话缓存大小（当前缓存的会话数量）
     pub session_cache_size: usize,
+    /// 会话缓存容量上限
+    pub session_cache_capacity: u64,
+    /// 会话缓存 TTL（秒）
+    pub session_cache_ttl_secs: u64,
     /// 轮询计数器（用于统计新会话分配次数）
     pub round_robin_counter: u64,
     /// 当前调度模式
@@ -514,6 +1074,19 @@ pub enum SchedulingMode {
     PriorityFill,
 }
 
+impl SchedulingMode {
+    /// 调度模式的中文名称（用于提示信息）
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::RoundRobin => "轮询模式",
+            Self::PriorityFill => "优先填充模式",
+        }
+    }
+}
+
+/// 上游调用完成回调（参数：是否成功、响应时间毫秒）
+pub type CallCompleteCallback = Box<dyn Fn(bool, u64) + Send + Sync>;
+
 /// 多凭据 Token 管理器
 ///
 /// 支持多个凭据的管理，实现粘性会话轮询 + 故障转移策略：
@@ -533,23 +1106,107 @@ pub struct MultiTokenManager {
     /// Token 刷新锁映射（按凭据 ID 分组），确保同一凭据同一时间只有一个刷新操作
     /// 使用细粒度锁避免高并发时多个凭据刷新串行化
     refresh_locks: DashMap<u64, Arc<TokioMutex<()>>>,
+    /// 新会话首次绑定锁（按会话标识分组），同一新会话的并发请求只由一个请求选择凭据
+    session_bind_locks: DashMap<String, Arc<TokioMutex<()>>>,
     /// 凭据文件路径（用于回写）
     credentials_path: Option<PathBuf>,
-    /// 会话到凭据的映射缓存（LRU + TTL）
+    /// 凭据文件写入器（多个池共享同一文件的写入线程）
+    credentials_writer: Option<Arc<PersistWriter>>,
+    /// 凭据目录中凭据的来源文件写入器（来源文件的凭据全部移除后仍保留，确保删除被回写）
+    source_writers: Mutex<HashMap<PathBuf, Arc<PersistWriter>>>,
+    /// 由本管理器维护的凭据 ID（回写时只替换这些条目，保留文件中其他池的凭据）
+    owned_ids: Mutex<HashSet<u64>>,
+    /// 会话到凭据的映射缓存（LRU + TTL，容量和 TTL 可随池配置更新重建）
     /// Key: 会话标识, Value: 凭据 ID
-    session_map: Cache<String, u64>,
+    session_map: RwLock<Cache<String, u64>>,
     /// 轮询计数器（用于新会话分配）
     round_robin_counter: AtomicU64,
     /// 调度模式
     scheduling_mode: Mutex<SchedulingMode>,
+    /// 池级凭据默认设置
+    credential_defaults: Mutex<CredentialDefaults>,
+    /// 各凭据的调用时间线（仅内存）
+    call_timeline: CallTimeline,
     /// 上次统计持久化时间（Unix 时间戳秒）
     last_stats_persist_time: AtomicU64,
+    /// Admin 事件发布器（可选，用于 Admin UI 实时状态）
+    event_publisher: OnceLock<EventPublisher>,
+    /// 凭据重新可用通知（唤醒额度用尽排队中的请求）
+    availability_notify: Notify,
+    /// 启动预热报告（预热完成后设置）
+    warmup_report: OnceLock<WarmupReport>,
+    /// 按用户公平调度跟踪器（`userFairnessEnabled` 启用时生效）
+    user_fairness: UserFairness,
+    /// 上游调用完成回调（可选，用于池性能历史）
+    on_call_complete: OnceLock<CallCompleteCallback>,
+    /// 粘性会话诊断统计（会话标识来源与会话缓存命中情况）
+    stickiness: StickinessStats,
+}
+
+/// 池间转移中的凭据（见 [`MultiTokenManager::take_credential`]）
+pub struct CredentialTransfer {
+    entry: CredentialEntry,
+    /// 原池中绑定到该凭据的会话
+    sessions: Vec<String>,
 }
 
-/// 会话缓存配置
-const SESSION_CACHE_MAX_CAPACITY: u64 = 10_000;
-/// 会话缓存 TTL（1 小时）
-const SESSION_CACHE_TTL_SECS: u64 = 3600;
+/// 克隆凭据时覆盖的字段（见 [`MultiTokenManager::clone_credential`]，None 表示沿用原凭据）
+#[derive(Debug, Clone, Default)]
+pub struct CredentialCloneOverrides {
+    pub priority: Option<u32>,
+    pub pool_id: Option<String>,
+    pub region: Option<String>,
+}
+
+/// Admin 事件发布器
+struct EventPublisher {
+    sender: broadcast::Sender<AdminEvent>,
+    /// 所属池 ID（用于 PoolStatusChanged 事件，池重命名时更新）
+    pool_id: Mutex<String>,
+}
+
+/// 构建会话缓存：LRU + TTL + 驱逐监听器
+fn build_session_cache(max_capacity: u64, ttl_secs: u64) -> Cache<String, u64> {
+    Cache::builder()
+        .max_capacity(max_capacity)
+        .time_to_live(StdDuration::from_secs(ttl_secs))
+        .eviction_listener(move |session_id: Arc<String>, credential_id: u64, cause| {
+            // 记录缓存驱逐事件，便于监控和调试
+            match cause {
+                moka::notification::RemovalCause::Expired => {
+                    tracing::debug!(
+                        "会话缓存过期: session={} -> credential_id={} (TTL={}s)",
+                        &session_id[..session_id.len().min(20)],
+                        credential_id,
+                        ttl_secs
+                    );
+                }
+                moka::notification::RemovalCause::Size => {
+                    tracing::info!(
+                        "会话缓存容量淘汰: session={} -> credential_id={} (容量上限={})",
+                        &session_id[..session_id.len().min(20)],
+                        credential_id,
+                        max_capacity
+                    );
+                }
+                moka::notification::RemovalCause::Explicit => {
+                    tracing::debug!(
+                        "会话缓存显式移除: session={} -> credential_id={}",
+                        &session_id[..session_id.len().min(20)],
+                        credential_id
+                    );
+                }
+                moka::notification::RemovalCause::Replaced => {
+                    tracing::debug!(
+                        "会话缓存替换: session={} -> credential_id={}",
+                        &session_id[..session_id.len().min(20)],
+                        credential_id
+                    );
+                }
+            }
+        })
+        .build()
+}
 
 /// 每个凭据最大 API 调用失败次数
 const MAX_FAILURES_PER_CREDENTIAL: u32 = 3;
@@ -557,11 +1214,28 @@ const MAX_FAILURES_PER_CREDENTIAL: u32 = 3;
 /// 统计数据持久化间隔（秒）- 5 分钟
 const STATS_PERSIST_INTERVAL_SECS: u64 = 300;
 
+/// 新会话首次绑定时等待并发请求完成绑定的最长时间（避免慢速 Token 刷新造成队头阻塞）
+const SESSION_BIND_WAIT: StdDuration = StdDuration::from_millis(300);
+
+/// 新会话首次绑定锁（释放时若无其他等待者则移除锁，避免锁映射随会话数增长）
+struct SessionBindGuard<'a> {
+    locks: &'a DashMap<String, Arc<TokioMutex<()>>>,
+    session_id: String,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl Drop for SessionBindGuard<'_> {
+    fn drop(&mut self) {
+        // 映射和本守卫各持有一个引用，多出的引用属于仍在等待的请求
+        self.locks
+            .remove_if(&self.session_id, |_, lock| Arc::strong_count(lock) <= 2);
+    }
+}
+
 /// API 调用上下文
 ///
 /// 绑定特定凭据的调用上下文，确保 token、credentials 和 id 的一致性
 /// 用于解决并发调用时 current_id 竞态问题
-#[derive(Clone)]
 pub struct CallContext {
     /// 凭据 ID（用于 report_success/report_failure）
     pub id: u64,
@@ -569,9 +1243,49 @@ pub struct CallContext {
     pub credentials: KiroCredentials,
     /// 访问 Token
     pub token: String,
-    /// 代理配置（凭据级 > 池级 > 全局）
-    #[allow(dead_code)]
+    /// 代理配置（凭据级 > 池默认代理 > 池级/全局）
     pub proxy_config: Option<ProxyConfig>,
+    /// 凭据并发名额（随上下文释放）
+    pub permit: Option<OwnedSemaphorePermit>,
+}
+
+/// 凭据默认设置（池级，凭据未配置对应字段时使用）
+///
+/// 解析顺序：凭据 > 池默认值 > 全局配置
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CredentialDefaults {
+    /// 默认 Region（OIDC/Social 刷新使用）
+    pub region: Option<String>,
+    /// 默认认证方式（social / idc）
+    pub auth_method: Option<String>,
+    /// 默认代理（池未设置默认代理时为池级代理）
+    pub proxy: Option<ProxyConfig>,
+    /// 默认 machineId（凭据未配置时使用，优先于全局 machineId）
+    pub machine_id: Option<String>,
+}
+
+/// 凭据设置的生效来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingSource {
+    /// 凭据自身配置
+    Credential,
+    /// 所属池的默认值
+    Pool,
+    /// 全局配置（config.json）
+    Global,
+}
+
+/// 凭据的生效设置（凭据 > 池默认值 > 全局配置）
+#[derive(Debug, Clone, PartialEq)]
+struct ResolvedSettings {
+    region: String,
+    region_source: SettingSource,
+    /// 未配置时按是否有 clientId/clientSecret 推断
+    auth_method: Option<String>,
+    proxy: Option<ProxyConfig>,
+    /// 未配置任何代理时为 None
+    proxy_source: Option<SettingSource>,
 }
 
 impl MultiTokenManager {
@@ -587,6 +1301,26 @@ impl MultiTokenManager {
         credentials: Vec<KiroCredentials>,
         proxy: Option<ProxyConfig>,
         credentials_path: Option<PathBuf>,
+    ) -> anyhow::Result<Self> {
+        Self::with_credential_defaults(
+            config,
+            credentials,
+            CredentialDefaults::default(),
+            proxy,
+            credentials_path,
+        )
+    }
+
+    /// 创建带池级凭据默认设置的 Token 管理器
+    ///
+    /// 只有 machineId 最终需要由 refreshToken 生成的凭据（凭据、池默认值和全局配置都未设置
+    /// machineId）才会补全并写回，避免 refreshToken 轮换后 machineId 随之变化
+    pub fn with_credential_defaults(
+        config: Config,
+        credentials: Vec<KiroCredentials>,
+        credential_defaults: CredentialDefaults,
+        proxy: Option<ProxyConfig>,
+        credentials_path: Option<PathBuf>,
     ) -> anyhow::Result<Self> {
         // 过滤无效凭据（示例凭据、截断凭据等）
         let (valid_credentials, skipped_count): (Vec<_>, usize) = {
@@ -594,7 +1328,12 @@ impl MultiTokenManager {
             let mut skipped = 0;
             for cred in credentials {
                 match validate_refresh_token(&cred) {
-                    Ok(()) => valid.push(cred),
+                    Ok(()) => {
+                        for warning in validate_credential(&cred) {
+                            tracing::warn!("凭据配置警告 (id={:?}): {}", cred.id, warning);
+                        }
+                        valid.push(cred);
+                    }
                     Err(e) => {
                         skipped += 1;
                         let token_preview = cred
@@ -633,12 +1372,24 @@ impl MultiTokenManager {
         let mut next_id = max_existing_id + 1;
         let mut has_new_ids = false;
         let mut has_new_machine_ids = false;
+        let machine_id_configured = credential_defaults.machine_id.is_some()
+            || config
+                .machine_id
+                .as_deref()
+                .and_then(machine_id::normalize_machine_id)
+                .is_some();
         let config_ref = &config;
 
         let entries: Vec<CredentialEntry> = valid_credentials
             .into_iter()
             .map(|mut cred| {
                 cred.canonicalize_auth_method();
+                if cred.region.is_none()
+                    && let Some(region) = cred.infer_region()
+                {
+                    tracing::debug!("凭据 #{:?} 从 profileArn 推断 region: {}", cred.id, region);
+                    cred.region = Some(region);
+                }
                 let id = cred.id.unwrap_or_else(|| {
                     let id = next_id;
                     next_id += 1;
@@ -646,34 +1397,15 @@ impl MultiTokenManager {
                     has_new_ids = true;
                     id
                 });
-                if cred.machine_id.is_none() {
-                    if let Some(machine_id) =
+                if cred.machine_id.is_none()
+                    && !machine_id_configured
+                    && let Some(machine_id) =
                         machine_id::generate_from_credentials(&cred, config_ref)
-                    {
-                        cred.machine_id = Some(machine_id);
-                        has_new_machine_ids = true;
-                    }
-                }
-                CredentialEntry {
-                    id,
-                    // 从持久化数据加载统计
-                    success_count: cred.success_count,
-                    total_failure_count: cred.total_failure_count,
-                    last_call_time: cred.last_call_time,
-                    total_response_time_ms: cred.total_response_time_ms,
-                    token_refresh_count: cred.token_refresh_count,
-                    token_refresh_failure_count: cred.token_refresh_failure_count,
-                    last_token_refresh_time: cred.last_token_refresh_time,
-                    // 今日统计不持久化，每次启动重置
-                    today_success_count: 0,
-                    today_failure_count: 0,
-                    today_date: None,
-                    // 运行时状态
-                    credentials: cred,
-                    failure_count: 0,
-                    disabled: false,
-                    disabled_reason: None,
+                {
+                    cred.machine_id = Some(machine_id);
+                    has_new_machine_ids = true;
                 }
+                CredentialEntry::loaded(id, cred)
             })
             .collect();
 
@@ -697,56 +1429,41 @@ impl MultiTokenManager {
             .unwrap_or(0);
 
         // 构建会话缓存：LRU + TTL + 驱逐监听器
-        let session_map = Cache::builder()
-            .max_capacity(SESSION_CACHE_MAX_CAPACITY)
-            .time_to_live(StdDuration::from_secs(SESSION_CACHE_TTL_SECS))
-            .eviction_listener(|session_id: Arc<String>, credential_id: u64, cause| {
-                // 记录缓存驱逐事件，便于监控和调试
-                match cause {
-                    moka::notification::RemovalCause::Expired => {
-                        tracing::debug!(
-                            "会话缓存过期: session={} -> credential_id={} (TTL={}s)",
-                            &session_id[..session_id.len().min(20)],
-                            credential_id,
-                            SESSION_CACHE_TTL_SECS
-                        );
-                    }
-                    moka::notification::RemovalCause::Size => {
-                        tracing::info!(
-                            "会话缓存容量淘汰: session={} -> credential_id={} (容量上限={})",
-                            &session_id[..session_id.len().min(20)],
-                            credential_id,
-                            SESSION_CACHE_MAX_CAPACITY
-                        );
-                    }
-                    moka::notification::RemovalCause::Explicit => {
-                        tracing::debug!(
-                            "会话缓存显式移除: session={} -> credential_id={}",
-                            &session_id[..session_id.len().min(20)],
-                            credential_id
-                        );
-                    }
-                    moka::notification::RemovalCause::Replaced => {
-                        tracing::debug!(
-                            "会话缓存替换: session={} -> credential_id={}",
-                            &session_id[..session_id.len().min(20)],
-                            credential_id
-                        );
-                    }
-                }
-            })
-            .build();
+        let session_map = build_session_cache(
+            config.session_cache_max_capacity,
+            config.session_cache_ttl_secs,
+        );
+        let user_fairness = UserFairness::new(
+            config.session_cache_max_capacity,
+            StdDuration::from_secs(config.session_cache_ttl_secs),
+        );
 
+        let owned_ids = entries.iter().map(|e| e.id).collect();
+        let source_writers = entries
+            .iter()
+            .filter_map(|e| e.credentials.source.clone())
+            .map(|path| {
+                let writer = PersistWriter::for_path(&path);
+                (path, writer)
+            })
+            .collect();
+        let call_timeline = CallTimeline::new(config.timeline_max_events_per_credential);
         let manager = Self {
             config,
             proxy,
             entries: Mutex::new(entries),
             current_id: Mutex::new(initial_id),
             refresh_locks: DashMap::new(),
+            session_bind_locks: DashMap::new(),
+            credentials_writer: credentials_path.as_ref().map(PersistWriter::for_path),
+            source_writers: Mutex::new(source_writers),
+            owned_ids: Mutex::new(owned_ids),
             credentials_path,
-            session_map,
+            session_map: RwLock::new(session_map),
             round_robin_counter: AtomicU64::new(0),
             scheduling_mode: Mutex::new(SchedulingMode::default()),
+            credential_defaults: Mutex::new(credential_defaults),
+            call_timeline,
             // 初始化为当前时间，避免启动后立即触发持久化
             last_stats_persist_time: AtomicU64::new(
                 std::time::SystemTime::now()
@@ -754,11 +1471,20 @@ impl MultiTokenManager {
                     .map(|d| d.as_secs())
                     .unwrap_or(0),
             ),
+            event_publisher: OnceLock::new(),
+            availability_notify: Notify::new(),
+            warmup_report: OnceLock::new(),
+            user_fairness,
+            on_call_complete: OnceLock::new(),
+            stickiness: StickinessStats::default(),
         };
 
         // 如果有新分配的 ID 或新生成的 machineId，立即持久化到配置文件
         if has_new_ids || has_new_machine_ids {
-            if let Err(e) = manager.persist_credentials() {
+            if let Err(e) = manager.persist_credentials(Change::new(
+                "credentials",
+                "补全凭据 ID/machineId",
+            )) {
                 tracing::warn!("补全凭据 ID/machineId 后持久化失败: {}", e);
             } else {
                 tracing::info!("已补全凭据 ID/machineId 并写回配置文件");
@@ -773,13 +1499,86 @@ impl MultiTokenManager {
         &self.config
     }
 
-    /// 获取当前活动凭据的克隆
-    #[allow(dead_code)]
-    pub fn credentials(&self) -> KiroCredentials {
-        let entries = self.entries.lock();
-        let current_id = *self.current_id.lock();
-        entries
-            .iter()
+    /// 设置 Admin 事件发布通道
+    ///
+    /// 仅首次设置生效；`pool_id` 用于标识 PoolStatusChanged 事件所属的池
+    pub fn set_event_sender(&self, sender: broadcast::Sender<AdminEvent>, pool_id: impl Into<String>) {
+        let _ = self.event_publisher.set(EventPublisher {
+            sender,
+            pool_id: Mutex::new(pool_id.into()),
+        });
+    }
+
+    /// 设置上游调用完成回调
+    ///
+    /// 仅首次设置生效；携带响应时间的成功/失败上报会触发回调
+    pub fn set_on_call_complete(&self, callback: CallCompleteCallback) {
+        let _ = self.on_call_complete.set(callback);
+    }
+
+    /// 通知上游调用完成（未设置回调时忽略）
+    fn notify_call_complete(&self, success: bool, response_time_ms: Option<u64>) {
+        if let (Some(callback), Some(time_ms)) = (self.on_call_complete.get(), response_time_ms) {
+            callback(success, time_ms);
+        }
+    }
+
+    /// 是否有 Admin 事件订阅者
+    pub fn has_event_subscribers(&self) -> bool {
+        self.event_publisher
+            .get()
+            .is_some_and(|p| p.sender.receiver_count() > 0)
+    }
+
+    /// 发布 Admin 事件（未配置通道或无订阅者时忽略）
+    pub fn publish_event(&self, event: AdminEvent) {
+        if let Some(publisher) = self.event_publisher.get() {
+            let _ = publisher.sender.send(event);
+        }
+    }
+
+    /// 发布凭据状态变化事件
+    ///
+    /// `available` 为 Some 时表示池可用凭据数量可能变化，同时发布 PoolStatusChanged
+    fn publish_credential_status(
+        &self,
+        id: u64,
+        disabled: bool,
+        failure_count: u32,
+        available: Option<usize>,
+    ) {
+        let Some(publisher) = self.event_publisher.get() else {
+            return;
+        };
+        let _ = publisher.sender.send(AdminEvent::CredentialStatusChanged {
+            id,
+            disabled,
+            failure_count,
+        });
+        if let Some(available_credentials) = available {
+            let _ = publisher.sender.send(AdminEvent::PoolStatusChanged {
+                id: publisher.pool_id.lock().clone(),
+                available_credentials,
+            });
+        }
+    }
+
+    /// 检查指定凭据的配置警告（Admin API）
+    pub fn validate_credential_by_id(&self, id: u64) -> Option<Vec<ValidationWarning>> {
+        let entries = self.entries.lock();
+        entries
+            .iter()
+            .find(|e| e.id == id)
+            .map(|e| validate_credential(&e.credentials))
+    }
+
+    /// 获取当前活动凭据的克隆
+    #[allow(dead_code)]
+    pub fn credentials(&self) -> KiroCredentials {
+        let entries = self.entries.lock();
+        let current_id = *self.current_id.lock();
+        entries
+            .iter()
             .find(|e| e.id == current_id)
             .map(|e| e.credentials.clone())
             .unwrap_or_default()
@@ -802,9 +1601,9 @@ impl MultiTokenManager {
     ///
     /// 如果 Token 过期或即将过期，会自动刷新
     /// Token 刷新失败时会尝试下一个可用凭据（不计入失败次数）
-    pub async fn acquire_context(&self) -> anyhow::Result<CallContext> {
+    pub async fn acquire_context(&self) -> Result<CallContext, KiroError> {
         // 无会话标识时，使用默认的优先级策略
-        self.acquire_context_internal(None).await
+        self.acquire_context_internal(None, None).await
     }
 
     /// 获取指定会话的 API 调用上下文（粘性会话 + 轮询）
@@ -819,34 +1618,76 @@ impl MultiTokenManager {
     pub async fn acquire_context_for_session(
         &self,
         session_id: Option<&str>,
-    ) -> anyhow::Result<CallContext> {
-        self.acquire_context_internal(session_id).await
+    ) -> Result<CallContext, KiroError> {
+        self.acquire_context_internal(session_id, None).await
+    }
+
+    /// 获取指定用户会话的 API 调用上下文（粘性会话 + 按用户公平调度）
+    ///
+    /// 启用 `userFairnessEnabled` 时，新会话分配凭据会限制单个用户
+    /// 最多占用 `userMaxShare` 比例的可用凭据
+    ///
+    /// # Arguments
+    /// * `session_id` - 会话标识（可选）
+    /// * `user_key` - 用户标识哈希（可选，见 [`crate::kiro::fairness::hash_user_id`]）
+    pub async fn acquire_context_for_user(
+        &self,
+        session_id: Option<&str>,
+        user_key: Option<&str>,
+    ) -> Result<CallContext, KiroError> {
+        self.acquire_context_internal(session_id, user_key).await
     }
 
     /// 内部方法：获取 API 调用上下文
     ///
     /// # Arguments
     /// * `session_id` - 会话标识（可选），用于粘性会话
+    /// * `user_key` - 用户标识哈希（可选），用于按用户公平调度
     async fn acquire_context_internal(
         &self,
         session_id: Option<&str>,
-    ) -> anyhow::Result<CallContext> {
+        user_key: Option<&str>,
+    ) -> Result<CallContext, KiroError> {
         let total = self.total_count();
         let mut tried_count = 0;
+        // 因并发已满而放弃的凭据数（全部如此时返回限流错误）
+        let mut throttled: Option<(usize, KiroError)> = None;
+        // 整次选择共用一个并发等待截止时间，避免依次在每个繁忙凭据上各等待一次
+        let throttle_deadline = self.throttle_deadline();
 
         // 尝试从会话缓存获取凭据 ID
-        let cached_id = session_id.and_then(|sid| self.session_map.get(sid));
+        let mut cached_id = session_id.and_then(|sid| self.session_map.read().get(sid));
+
+        // 新会话首次绑定：同一会话的并发请求只由持锁的请求选择凭据，
+        // 其余请求限时等待，获得锁后复用已完成的绑定
+        let _bind_guard = match (session_id, cached_id) {
+            (Some(sid), None) => {
+                let guard = self.lock_session_binding(sid).await;
+                cached_id = self.session_map.read().get(sid);
+                if cached_id.is_some() {
+                    self.stickiness.record_binding_race(BindingRace::Joined);
+                    None
+                } else {
+                    guard
+                }
+            }
+            _ => None,
+        };
 
         // 获取当前调度模式
         let mode = *self.scheduling_mode.lock();
 
         loop {
             if tried_count >= total {
-                anyhow::bail!(
-                    "所有凭据均无法获取有效 Token（可用: {}/{}）",
-                    self.available_count(),
-                    total
-                );
+                if let Some((count, e)) = throttled
+                    && count == tried_count
+                {
+                    return Err(e);
+                }
+                return Err(KiroError::AllCredentialsExhausted {
+                    available: self.available_count(),
+                    total,
+                });
             }
 
             let (id, credentials) = {
@@ -857,10 +1698,7 @@ impl MultiTokenManager {
                     cached_id.or_else(|| {
                         // 无缓存时，根据调度模式选择凭据
                         if session_id.is_some() {
-                            match mode {
-                                SchedulingMode::RoundRobin => self.select_by_round_robin(&entries),
-                                SchedulingMode::PriorityFill => self.select_by_priority(&entries),
-                            }
+                            self.select_for_new_session(&entries, mode, user_key)
                         } else {
                             // 无会话标识时，使用当前凭据
                             Some(*self.current_id.lock())
@@ -868,15 +1706,16 @@ impl MultiTokenManager {
                     })
                 } else {
                     // 重试时，根据调度模式选择下一个凭据
-                    match mode {
-                        SchedulingMode::RoundRobin => self.select_by_round_robin(&entries),
-                        SchedulingMode::PriorityFill => self.select_by_priority(&entries),
-                    }
+                    self.select_for_new_session(&entries, mode, user_key)
                 };
 
                 // 找到目标凭据
                 if let Some(tid) = target_id {
-                    if let Some(entry) = entries.iter().find(|e| e.id == tid && !e.disabled) {
+                    if let Some(entry) = entries
+                        .iter()
+                        .find(|e| e.id == tid)
+                        .filter(|e| e.is_schedulable())
+                    {
                         (entry.id, entry.credentials.clone())
                     } else {
                         // 目标凭据不可用，选择任意可用凭据
@@ -889,12 +1728,42 @@ impl MultiTokenManager {
             };
 
             // 尝试获取/刷新 Token
-            match self.try_ensure_token(id, &credentials).await {
+            match self
+                .try_ensure_token(id, &credentials, throttle_deadline)
+                .await
+            {
                 Ok(ctx) => {
                     // 成功后更新会话缓存
                     if let Some(sid) = session_id {
-                        self.session_map.insert(sid.to_string(), ctx.id);
+                        if let Some(bound) = self.bind_session(sid, ctx.id, cached_id) {
+                            // 等待超时的请求与首个请求各自选择了凭据，保留先完成的绑定，
+                            // 释放已获取的凭据并改用绑定的凭据（再次绑定时直接覆盖，不会重复进入）
+                            self.stickiness.record_binding_race(BindingRace::Duplicate);
+                            tracing::warn!(
+                                "会话 {} 已被并发请求绑定到凭据 #{}，放弃凭据 #{} 并改用已绑定的凭据",
+                                &sid[..sid.len().min(20)],
+                                bound,
+                                ctx.id
+                            );
+                            drop(ctx);
+                            cached_id = Some(bound);
+                            tried_count = 0;
+                            throttled = None;
+                            continue;
+                        }
+                        if self.config.user_fairness_enabled
+                            && let Some(user) = user_key
+                        {
+                            self.user_fairness.bind(sid, user, ctx.id);
+                        }
+                        let outcome = match cached_id {
+                            Some(cached) if cached == ctx.id => StickinessOutcome::Hit,
+                            Some(_) => StickinessOutcome::Rebound,
+                            None => StickinessOutcome::New,
+                        };
+                        self.stickiness.record_outcome(outcome);
                         tracing::debug!(
+                            stickiness = outcome.as_str(),
                             "会话 {} 绑定到凭据 #{}",
                             &sid[..sid.len().min(20)],
                             ctx.id
@@ -902,30 +1771,38 @@ impl MultiTokenManager {
                     }
                     return Ok(ctx);
                 }
+                Err(e @ KiroError::CredentialThrottled { .. }) => {
+                    // 并发已满只是暂时繁忙，不记录为凭据错误
+                    tracing::warn!("{}，尝试下一个凭据", e);
+                    let count = throttled.map_or(0, |(count, _)| count);
+                    throttled = Some((count + 1, e));
+                    tried_count += 1;
+                }
                 Err(e) => {
                     let error_msg = e.to_string();
                     tracing::warn!("凭据 #{} Token 刷新失败，尝试下一个凭据: {}", id, error_msg);
 
-                    // 判断是否为不可恢复的错误（需要禁用凭据）
-                    let should_disable = error_msg.contains("refreshToken")
-                        || error_msg.contains("截断")
-                        || error_msg.contains("invalid_grant")
-                        || error_msg.contains("expired")
-                        || error_msg.contains("unauthorized")
-                        || error_msg.contains("401")
-                        || error_msg.contains("403");
-
-                    if should_disable {
+                    // 仅认证失效（refreshToken 无效/过期/被截断）需要禁用凭据，
+                    // 网络错误、限流和无法识别的错误不禁用
+                    let auth_expired = e.kind() == UpstreamErrorKind::AuthExpired;
+                    if auth_expired {
                         tracing::error!(
                             "凭据 #{} 的 refreshToken 无效或已过期，自动禁用该凭据",
                             id
                         );
-                        // 禁用凭据
+                    }
+                    {
                         let mut entries = self.entries.lock();
                         if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
-                            entry.disabled = true;
-                            entry.disabled_reason = Some(DisabledReason::TokenRefreshFailed);
+                            entry.touch();
+                            entry.last_error = Some(error_msg);
+                            if auth_expired {
+                                // 禁用凭据
+                                entry.disable(DisabledReason::TokenRefreshFailed, SYSTEM_ACTOR);
+                            }
                         }
+                    }
+                    if auth_expired {
                         self.reset_round_robin_counter();
                     }
 
@@ -935,29 +1812,78 @@ impl MultiTokenManager {
         }
     }
 
-    /// 按优先级选择凭据（内部方法）
+    /// 获取新会话的首次绑定锁（内部方法）
     ///
-    /// 选择优先级最高（priority 最小）的可用凭据
-    fn select_by_priority(&self, entries: &[CredentialEntry]) -> Option<u64> {
-        entries
-            .iter()
-            .filter(|e| !e.disabled)
-            .min_by_key(|e| e.credentials.priority)
-            .map(|e| e.id)
+    /// 最多等待 `SESSION_BIND_WAIT`，超时返回 None，由调用方自行选择凭据
+    async fn lock_session_binding(&self, session_id: &str) -> Option<SessionBindGuard<'_>> {
+        let lock = self
+            .session_bind_locks
+            .entry(session_id.to_string())
+            .or_insert_with(|| Arc::new(TokioMutex::new(())))
+            .clone();
+        match tokio::time::timeout(SESSION_BIND_WAIT, lock.lock_owned()).await {
+            Ok(guard) => Some(SessionBindGuard {
+                locks: &self.session_bind_locks,
+                session_id: session_id.to_string(),
+                _guard: guard,
+            }),
+            Err(_) => {
+                self.stickiness
+                    .record_binding_race(BindingRace::WaitTimedOut);
+                tracing::debug!(
+                    "会话 {} 等待并发首次绑定超时，自行选择凭据",
+                    &session_id[..session_id.len().min(20)]
+                );
+                None
+            }
+        }
     }
 
-    /// 轮询选择凭据（内部方法）
+    /// 将会话绑定到凭据（内部方法）
     ///
-    /// 按轮询方式从可用凭据中选择一个
-    fn select_by_round_robin(&self, entries: &[CredentialEntry]) -> Option<u64> {
-        let available: Vec<_> = entries.iter().filter(|e| !e.disabled).collect();
-        if available.is_empty() {
+    /// 首次绑定（`cached_id` 为 None）时不覆盖并发请求已写入的绑定，
+    /// 此时返回已有绑定的凭据 ID；其他情况直接覆盖并返回 None
+    fn bind_session(
+        &self,
+        session_id: &str,
+        credential_id: u64,
+        cached_id: Option<u64>,
+    ) -> Option<u64> {
+        let session_map = self.session_map.read();
+        if cached_id.is_some() {
+            session_map.insert(session_id.to_string(), credential_id);
             return None;
         }
+        let entry = session_map
+            .entry(session_id.to_string())
+            .or_insert(credential_id);
+        (!entry.is_fresh() && entry.value() != &credential_id).then(|| *entry.value())
+    }
 
-        let counter = self.round_robin_counter.fetch_add(1, Ordering::Relaxed);
-        let index = (counter as usize) % available.len();
-        Some(available[index].id)
+    /// 为新会话选择凭据（内部方法）
+    ///
+    /// 先按调度模式选择，启用按用户公平调度时再按用户占用上限修正
+    fn select_for_new_session(
+        &self,
+        entries: &[CredentialEntry],
+        mode: SchedulingMode,
+        user_key: Option<&str>,
+    ) -> Option<u64> {
+        let candidates: Vec<Candidate> = entries.iter().map(CredentialEntry::candidate).collect();
+        let preferred = scheduling::select(mode, &self.round_robin_counter, &candidates);
+
+        match user_key {
+            Some(user) if self.config.user_fairness_enabled => {
+                let candidates: Vec<u64> = entries
+                    .iter()
+                    .filter(|e| e.is_schedulable())
+                    .map(|e| e.id)
+                    .collect();
+                self.user_fairness
+                    .select(user, &candidates, preferred, self.config.user_max_share)
+            }
+            _ => preferred,
+        }
     }
 
     /// 重置轮询计数器（内部方法）
@@ -982,11 +1908,11 @@ impl MultiTokenManager {
         &self,
         entries: &mut Vec<CredentialEntry>,
         total: usize,
-    ) -> anyhow::Result<(u64, KiroCredentials)> {
-        // 选择优先级最高的可用凭据
+    ) -> Result<(u64, KiroCredentials), KiroError> {
+        // 选择优先级最高的可用凭据（跳过 Retry-After 退避中的凭据）
         let mut best = entries
             .iter()
-            .filter(|e| !e.disabled)
+            .filter(|e| e.is_schedulable())
             .min_by_key(|e| e.credentials.priority);
 
         // 没有可用凭据：如果是"自动禁用导致全灭"，做一次类似重启的自愈
@@ -1010,14 +1936,14 @@ impl MultiTokenManager {
                     e.disabled_reason,
                     Some(DisabledReason::TooManyFailures) | Some(DisabledReason::TokenRefreshFailed)
                 ) {
-                    e.disabled = false;
-                    e.disabled_reason = None;
+                    e.enable("自愈：自动禁用的凭据全部重新启用", SYSTEM_ACTOR);
                     e.failure_count = 0;
+                    e.touch();
                 }
             }
             best = entries
                 .iter()
-                .filter(|e| !e.disabled)
+                .filter(|e| e.is_schedulable())
                 .min_by_key(|e| e.credentials.priority);
         }
 
@@ -1029,7 +1955,7 @@ impl MultiTokenManager {
             Ok((new_id, new_creds))
         } else {
             let available = entries.iter().filter(|e| !e.disabled).count();
-            anyhow::bail!("所有凭据均已禁用（{}/{}）", available, total);
+            Err(KiroError::AllCredentialsExhausted { available, total })
         }
     }
 
@@ -1088,11 +2014,13 @@ impl MultiTokenManager {
     /// # Arguments
     /// * `id` - 凭据 ID，用于更新正确的条目
     /// * `credentials` - 凭据信息
+    /// * `throttle_deadline` - 等待并发名额的截止时间
     async fn try_ensure_token(
         &self,
         id: u64,
         credentials: &KiroCredentials,
-    ) -> anyhow::Result<CallContext> {
+        throttle_deadline: tokio::time::Instant,
+    ) -> Result<CallContext, KiroError> {
         // 第一次检查（无锁）：快速判断是否需要刷新
         let needs_refresh = is_token_expired(credentials) || is_token_expiring_soon(credentials);
 
@@ -1112,20 +2040,22 @@ impl MultiTokenManager {
                     .iter()
                     .find(|e| e.id == id)
                     .map(|e| e.credentials.clone())
-                    .ok_or_else(|| anyhow::anyhow!("凭据 #{} 不存在", id))?
+                    .ok_or(KiroError::CredentialNotFound(id))?
             };
 
             if is_token_expired(&current_creds) || is_token_expiring_soon(&current_creds) {
                 // 确实需要刷新
-                let refresh_result =
-                    refresh_token(&current_creds, &self.config, self.proxy.as_ref()).await;
+                let refresh_result = self.refresh_resolved(&current_creds).await;
 
                 match refresh_result {
                     Ok(new_creds) => {
                         if is_token_expired(&new_creds) {
                             // 刷新后仍然无效，记录失败
                             self.report_token_refresh_failure(id);
-                            anyhow::bail!("刷新后的 Token 仍然无效或已过期");
+                            return Err(KiroError::TokenRefreshFailed {
+                                credential_id: id,
+                                reason: "刷新后的 Token 仍然无效或已过期".to_string(),
+                            });
                         }
 
                         // 记录刷新成功
@@ -1136,11 +2066,14 @@ impl MultiTokenManager {
                             let mut entries = self.entries.lock();
                             if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
                                 entry.credentials = new_creds.clone();
+                                entry.touch();
                             }
                         }
 
                         // 回写凭据到文件（仅多凭据格式），失败只记录警告
-                        if let Err(e) = self.persist_credentials() {
+                        if let Err(e) =
+                            self.persist_credentials(Change::background("credentials"))
+                        {
                             tracing::warn!("Token 刷新后持久化失败（不影响本次请求）: {}", e);
                         }
 
@@ -1149,7 +2082,7 @@ impl MultiTokenManager {
                     Err(e) => {
                         // 记录刷新失败
                         self.report_token_refresh_failure(id);
-                        return Err(e);
+                        return Err(KiroError::from_refresh_error(id, e));
                     }
                 }
             } else {
@@ -1164,73 +2097,133 @@ impl MultiTokenManager {
         let token = creds
             .access_token
             .clone()
-            .ok_or_else(|| anyhow::anyhow!("没有可用的 accessToken"))?;
+            .ok_or_else(|| KiroError::TokenRefreshFailed {
+                credential_id: id,
+                reason: "没有可用的 accessToken".to_string(),
+            })?;
 
-        // 解析代理配置：凭据级 > 池级（由调用方传入）> 全局
+        // 解析代理配置：凭据级 > 池默认代理 > 池级/全局
         let proxy_config = self.resolve_proxy_config(&creds);
 
+        // 凭据级并发限制：名额用尽时排队等待，超时放弃该凭据
+        let permit = self
+            .acquire_concurrency_permit(id, throttle_deadline)
+            .await?;
+
         Ok(CallContext {
             id,
-            credentials: creds,
+            credentials: self.resolve_credentials(&creds),
             token,
             proxy_config,
+            permit: Some(permit),
         })
     }
 
+    /// 获取凭据的并发名额
+    ///
+    /// 名额用尽时最多等待到 `deadline`，超时返回 `CredentialThrottled`
+    async fn acquire_concurrency_permit(
+        &self,
+        id: u64,
+        deadline: tokio::time::Instant,
+    ) -> Result<OwnedSemaphorePermit, KiroError> {
+        let (semaphore, max) = {
+            let entries = self.entries.lock();
+            let entry = entries
+                .iter()
+                .find(|e| e.id == id)
+                .ok_or(KiroError::CredentialNotFound(id))?;
+            (entry.concurrency.clone(), entry.max_concurrency)
+        };
+
+        let timeout_ms = self.config.credential_throttle_timeout_ms;
+        let acquired = tokio::time::timeout_at(deadline, semaphore.acquire_owned()).await;
+        match (acquired, max) {
+            (Ok(Ok(permit)), _) => Ok(permit),
+            // 信号量不会被关闭；不限制并发时名额不会耗尽
+            (_, max) => Err(KiroError::CredentialThrottled {
+                credential_id: id,
+                max: max.unwrap_or(u32::MAX),
+                timeout_ms,
+            }),
+        }
+    }
+
+    /// 从现在起等待并发名额的截止时间（`credentialThrottleTimeoutMs`）
+    fn throttle_deadline(&self) -> tokio::time::Instant {
+        tokio::time::Instant::now()
+            + StdDuration::from_millis(self.config.credential_throttle_timeout_ms)
+    }
+
     /// 将凭据列表回写到源文件
     ///
-    /// 仅在以下条件满足时回写：
-    /// - 源文件是多凭据格式（数组）
-    /// - credentials_path 已设置
+    /// 经凭据文件的写入线程原子写入：只替换本管理器维护的凭据，
+    /// 文件中其他池的凭据原样保留。凭据目录中的凭据写回各自的来源文件。
+    /// 管理操作会记录到变更日志。
     ///
     /// # Returns
     /// - `Ok(true)` - 成功写入文件
     /// - `Ok(false)` - 跳过写入（无路径配置）
     /// - `Err(_)` - 写入失败
-    fn persist_credentials(&self) -> anyhow::Result<bool> {
-        use anyhow::Context;
-
-        let path = match &self.credentials_path {
-            Some(p) => p,
-            None => return Ok(false),
-        };
+    fn persist_credentials(&self, change: Change) -> anyhow::Result<bool> {
+        let mut targets: Vec<(Option<PathBuf>, Arc<PersistWriter>)> = self
+            .source_writers
+            .lock()
+            .iter()
+            .map(|(path, writer)| (Some(path.clone()), writer.clone()))
+            .collect();
+        if let Some(writer) = &self.credentials_writer {
+            targets.insert(0, (None, writer.clone()));
+        }
+        if targets.is_empty() {
+            return Ok(false);
+        }
 
-        // 收集所有凭据，同步统计数据
-        let credentials: Vec<KiroCredentials> = {
-            let entries = self.entries.lock();
-            entries
-                .iter()
-                .map(|e| {
-                    let mut cred = e.credentials.clone();
-                    cred.canonicalize_auth_method();
-                    // 同步统计数据到 KiroCredentials
-                    cred.success_count = e.success_count;
-                    cred.total_failure_count = e.total_failure_count;
-                    cred.last_call_time = e.last_call_time;
-                    cred.total_response_time_ms = e.total_response_time_ms;
-                    cred.token_refresh_count = e.token_refresh_count;
-                    cred.token_refresh_failure_count = e.token_refresh_failure_count;
-                    cred.last_token_refresh_time = e.last_token_refresh_time;
-                    cred
+        // 主凭据文件和各来源文件分别提交，提交后统一等待写入完成
+        let tickets: Vec<_> = targets
+            .into_iter()
+            .map(|(source, writer)| {
+                let path = writer.path().to_path_buf();
+                writer.submit(change.clone(), || {
+                    // 收集属于该文件的凭据，同步统计数据
+                    let credentials: Vec<KiroCredentials> = {
+                        let entries = self.entries.lock();
+                        entries
+                            .iter()
+                            .filter(|e| e.credentials.source == source)
+                            .map(CredentialEntry::persisted)
+                            .collect()
+                    };
+                    let owned_ids = self.owned_ids.lock().clone();
+                    Ok(move |current: Option<&str>| {
+                        let credentials =
+                            merge_credentials(&path, current, owned_ids, credentials)?;
+                        // 按文件扩展名序列化（YAML 凭据文件保持 YAML 格式，其他为 pretty JSON）
+                        FileFormat::for_write(&path)
+                            .to_string(&credentials)
+                            .context("序列化凭据失败")
+                    })
                 })
-                .collect()
-        };
-
-        // 序列化为 pretty JSON
-        let json = serde_json::to_string_pretty(&credentials).context("序列化凭据失败")?;
-
-        // 写入文件（在 Tokio runtime 内使用 block_in_place 避免阻塞 worker）
-        if tokio::runtime::Handle::try_current().is_ok() {
-            tokio::task::block_in_place(|| std::fs::write(path, &json))
-                .with_context(|| format!("回写凭据文件失败: {:?}", path))?;
-        } else {
-            std::fs::write(path, &json).with_context(|| format!("回写凭据文件失败: {:?}", path))?;
+            })
+            .collect();
+        for ticket in tickets {
+            ticket.wait_blocking().context("回写凭据文件失败")?;
         }
 
-        tracing::debug!("已回写凭据到文件: {:?}", path);
+        tracing::debug!("已回写凭据到文件: {:?}", self.credentials_path);
         Ok(true)
     }
 
+    /// 记录凭据的来源文件（凭据目录中的凭据加入本管理器时调用）
+    fn track_source(&self, credentials: &KiroCredentials) {
+        if let Some(path) = &credentials.source {
+            self.source_writers
+                .lock()
+                .entry(path.clone())
+                .or_insert_with(|| PersistWriter::for_path(path));
+        }
+    }
+
     /// 检查是否需要定期持久化统计数据
     ///
     /// 每隔 STATS_PERSIST_INTERVAL_SECS 秒自动持久化一次统计数据
@@ -1252,7 +2245,7 @@ impl MultiTokenManager {
                 .is_ok()
             {
                 // 成功获取持久化权限，执行持久化
-                if let Err(e) = self.persist_credentials() {
+                if let Err(e) = self.persist_credentials(Change::background("credentials")) {
                     tracing::warn!("定期持久化统计数据失败: {}", e);
                 } else {
                     tracing::debug!("已定期持久化统计数据");
@@ -1262,16 +2255,32 @@ impl MultiTokenManager {
         }
     }
 
+    /// 记录一次调用到时间线
+    ///
+    /// `report_success` / `report_failure` 已包含此步骤，只需为不计入凭据成败的调用单独记录
+    pub fn record_call(&self, id: u64, model: &str, success: bool, latency_ms: u64) {
+        self.call_timeline.record(
+            id,
+            CallEvent {
+                timestamp: Utc::now(),
+                success,
+                latency_ms,
+                model: model.to_string(),
+            },
+        );
+    }
+
     /// 报告指定凭据 API 调用成功
     ///
     /// 重置该凭据的失败计数，并更新调用统计
     ///
     /// # Arguments
     /// * `id` - 凭据 ID（来自 CallContext）
-    /// * `response_time_ms` - 响应时间（毫秒），可选
-    #[allow(dead_code)]
-    pub fn report_success(&self, id: u64) {
-        self.report_success_with_time(id, None);
+    /// * `model` - 请求的模型（记录到调用时间线）
+    /// * `latency_ms` - 响应时间（毫秒）
+    pub fn report_success(&self, id: u64, model: &str, latency_ms: u64) {
+        self.record_call(id, model, true, latency_ms);
+        self.report_success_with_time(id, Some(latency_ms));
     }
 
     /// 报告指定凭据 API 调用成功（带响应时间）
@@ -1280,9 +2289,11 @@ impl MultiTokenManager {
     /// * `id` - 凭据 ID（来自 CallContext）
     /// * `response_time_ms` - 响应时间（毫秒），可选
     pub fn report_success_with_time(&self, id: u64, response_time_ms: Option<u64>) {
-        {
+        let status = {
             let mut entries = self.entries.lock();
-            if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+            let entry = entries.iter_mut().find(|e| e.id == id);
+            if let Some(entry) = entry {
+                entry.touch();
                 entry.failure_count = 0;
                 entry.success_count += 1;
 
@@ -1296,6 +2307,7 @@ impl MultiTokenManager {
                 // 更新响应时间统计
                 if let Some(time_ms) = response_time_ms {
                     entry.total_response_time_ms += time_ms;
+                    entry.record_response_time(time_ms);
                 }
 
                 // 更新今日统计
@@ -1310,8 +2322,16 @@ impl MultiTokenManager {
                 }
 
                 tracing::debug!("凭据 #{} API 调用成功（总计: {}）", id, entry.success_count);
+                Some(entry.disabled)
+            } else {
+                None
             }
+        };
+
+        if let Some(disabled) = status {
+            self.publish_credential_status(id, disabled, 0, None);
         }
+        self.notify_call_complete(true, response_time_ms);
 
         // 检查是否需要定期持久化统计数据
         self.maybe_persist_stats();
@@ -1324,9 +2344,35 @@ impl MultiTokenManager {
     ///
     /// # Arguments
     /// * `id` - 凭据 ID（来自 CallContext）
-    pub fn report_failure(&self, id: u64) -> bool {
+    /// * `model` - 请求的模型（记录到调用时间线）
+    /// * `error_message` - 导致失败的错误信息（可选，记录到 `last_error`）
+    /// * `latency_ms` - 响应时间（毫秒）
+    pub fn report_failure(
+        &self,
+        id: u64,
+        model: &str,
+        error_message: Option<&str>,
+        latency_ms: u64,
+    ) -> bool {
+        self.record_call(id, model, false, latency_ms);
+        self.report_failure_with_time(id, error_message, Some(latency_ms))
+    }
+
+    /// 报告指定凭据 API 调用失败（带响应时间，不记录调用时间线）
+    ///
+    /// # Arguments
+    /// * `id` - 凭据 ID（来自 CallContext）
+    /// * `error_message` - 导致失败的错误信息（可选，记录到 `last_error`）
+    /// * `response_time_ms` - 响应时间（毫秒），可选
+    pub fn report_failure_with_time(
+        &self,
+        id: u64,
+        error_message: Option<&str>,
+        response_time_ms: Option<u64>,
+    ) -> bool {
         let should_reset_counter;
         let has_available;
+        let event;
 
         {
             let mut entries = self.entries.lock();
@@ -1337,8 +2383,12 @@ impl MultiTokenManager {
                 None => return entries.iter().any(|e| !e.disabled),
             };
 
+            entry.touch();
             entry.failure_count += 1;
             entry.total_failure_count += 1; // 更新总失败计数
+            if let Some(message) = error_message {
+                entry.last_error = Some(message.to_string());
+            }
             let failure_count = entry.failure_count;
 
             // 更新最后调用时间
@@ -1368,8 +2418,7 @@ impl MultiTokenManager {
             );
 
             if failure_count >= MAX_FAILURES_PER_CREDENTIAL {
-                entry.disabled = true;
-                entry.disabled_reason = Some(DisabledReason::TooManyFailures);
+                entry.disable(DisabledReason::TooManyFailures, SYSTEM_ACTOR);
                 tracing::error!("凭据 #{} 已连续失败 {} 次，已被禁用", id, failure_count);
                 should_reset_counter = true;
 
@@ -1394,8 +2443,18 @@ impl MultiTokenManager {
                 should_reset_counter = false;
                 has_available = entries.iter().any(|e| !e.disabled);
             }
+
+            // 凭据被禁用时池可用数量随之变化
+            let available = should_reset_counter
+                .then(|| entries.iter().filter(|e| !e.disabled).count());
+            let disabled = entries.iter().any(|e| e.id == id && e.disabled);
+            event = (disabled, failure_count, available);
         }
 
+        let (disabled, failure_count, available) = event;
+        self.publish_credential_status(id, disabled, failure_count, available);
+        self.notify_call_complete(false, response_time_ms);
+
         // 凭据列表变化，重置轮询计数器确保公平性（在锁外执行）
         if should_reset_counter {
             self.reset_round_robin_counter();
@@ -1415,6 +2474,7 @@ impl MultiTokenManager {
     /// - 返回是否还有可用凭据
     pub fn report_quota_exhausted(&self, id: u64) -> bool {
         let has_available;
+        let available;
 
         {
             let mut entries = self.entries.lock();
@@ -1429,8 +2489,10 @@ impl MultiTokenManager {
                 return entries.iter().any(|e| !e.disabled);
             }
 
-            entry.disabled = true;
-            entry.disabled_reason = Some(DisabledReason::QuotaExceeded);
+            entry.touch();
+            entry.disable(DisabledReason::QuotaExceeded, SYSTEM_ACTOR);
+            // 重置时间由 refresh_quota_reset_at 异步获取
+            entry.quota_reset_at = None;
             // 设为阈值，便于在管理面板中直观看到该凭据已不可用
             entry.failure_count = MAX_FAILURES_PER_CREDENTIAL;
 
@@ -1453,14 +2515,154 @@ impl MultiTokenManager {
                 tracing::error!("所有凭据均已禁用！");
                 has_available = false;
             }
+            available = entries.iter().filter(|e| !e.disabled).count();
         }
 
+        self.publish_credential_status(id, true, MAX_FAILURES_PER_CREDENTIAL, Some(available));
+
         // 凭据列表变化，重置轮询计数器确保公平性（在锁外执行）
         self.reset_round_robin_counter();
 
         has_available
     }
 
+    /// 报告指定凭据被上游限流并要求退避（429 `Retry-After`）
+    ///
+    /// 退避期内该凭据不参与调度，不计入失败次数也不禁用凭据。
+    /// 返回是否还有其他可调度的凭据
+    pub fn report_retry_after(&self, id: u64, retry_after: StdDuration) -> bool {
+        let mut entries = self.entries.lock();
+        if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+            entry.touch();
+            entry.retry_after_until = Some(std::time::Instant::now() + retry_after);
+            entry.last_error = Some(format!(
+                "429 Too Many Requests（Retry-After: {}s）",
+                retry_after.as_secs()
+            ));
+            tracing::warn!(
+                "凭据 #{} 被上游限流，{} 秒内不参与调度",
+                id,
+                retry_after.as_secs()
+            );
+        }
+        entries.iter().any(|e| e.is_schedulable())
+    }
+
+    /// 所有未禁用的凭据都在 `Retry-After` 退避期内时，返回最早结束的剩余退避时长
+    pub fn retry_after_remaining(&self) -> Option<StdDuration> {
+        let entries = self.entries.lock();
+        let mut active = entries.iter().filter(|e| !e.disabled).peekable();
+        active.peek()?;
+        active
+            .map(|e| e.retry_after_remaining())
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .min()
+    }
+
+    /// 记录凭据额度重置时间（Unix 时间戳秒）
+    ///
+    /// 仅对因额度用尽被禁用的凭据生效，时间过后由后台任务自动重新启用
+    pub fn set_quota_reset_at(&self, id: u64, reset_at: u64) {
+        let mut entries = self.entries.lock();
+        if let Some(entry) = entries
+            .iter_mut()
+            .find(|e| e.id == id && e.disabled_reason == Some(DisabledReason::QuotaExceeded))
+        {
+            entry.quota_reset_at = Some(reset_at);
+            tracing::info!("凭据 #{} 额度重置时间: {}", id, reset_at);
+        }
+    }
+
+    /// 通过 getUsageLimits 获取并记录凭据额度重置时间
+    pub async fn refresh_quota_reset_at(&self, id: u64) -> anyhow::Result<Option<u64>> {
+        let usage = self.get_usage_limits_for(id).await?;
+        let reset_at = usage
+            .next_date_reset
+            .filter(|t| *t > 0.0)
+            .map(|t| t as u64);
+        if let Some(reset_at) = reset_at {
+            self.set_quota_reset_at(id, reset_at);
+        }
+        Ok(reset_at)
+    }
+
+    /// 获取最早的额度重置时间（Unix 时间戳秒）
+    ///
+    /// 仅当所有凭据均因额度用尽被禁用时返回；存在其他原因禁用或重置时间未知的凭据时不计入
+    pub fn earliest_quota_reset_at(&self) -> Option<u64> {
+        let entries = self.entries.lock();
+        let all_quota_exhausted = !entries.is_empty()
+            && entries.iter().all(|e| {
+                e.disabled && e.disabled_reason == Some(DisabledReason::QuotaExceeded)
+            });
+        if !all_quota_exhausted {
+            return None;
+        }
+        entries.iter().filter_map(|e| e.quota_reset_at).min()
+    }
+
+    /// 重新启用额度已重置的凭据
+    ///
+    /// # Arguments
+    /// * `now_secs` - 当前时间（Unix 时间戳秒）
+    ///
+    /// 返回重新启用的凭据 ID 列表
+    pub fn reenable_quota_reset_credentials(&self, now_secs: u64) -> Vec<u64> {
+        let (reenabled, available) = {
+            let mut entries = self.entries.lock();
+            let mut reenabled = Vec::new();
+            for entry in entries.iter_mut() {
+                let reset_due = entry.quota_reset_at.is_some_and(|t| t <= now_secs);
+                if entry.disabled
+                    && entry.disabled_reason == Some(DisabledReason::QuotaExceeded)
+                    && reset_due
+                {
+                    entry.enable("额度已重置", SYSTEM_ACTOR);
+                    entry.quota_reset_at = None;
+                    entry.failure_count = 0;
+                    entry.touch();
+                    reenabled.push(entry.id);
+                }
+            }
+            let available = entries.iter().filter(|e| !e.disabled).count();
+            (reenabled, available)
+        };
+
+        if reenabled.is_empty() {
+            return reenabled;
+        }
+
+        for id in &reenabled {
+            tracing::info!("凭据 #{} 额度已重置，已重新启用", id);
+            self.publish_credential_status(*id, false, 0, Some(available));
+        }
+        self.select_highest_priority();
+        self.reset_round_robin_counter();
+        self.availability_notify.notify_waiters();
+        reenabled
+    }
+
+    /// 等待出现可用凭据
+    ///
+    /// 凭据被重新启用时唤醒；超时后返回当前是否有可用凭据
+    pub async fn wait_until_available(&self, timeout: StdDuration) -> bool {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            // 先注册通知再检查状态，避免检查与等待之间的唤醒丢失
+            let notified = self.availability_notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            if self.available_count() > 0 {
+                return true;
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return self.available_count() > 0;
+            }
+        }
+    }
+
     /// 报告 Token 刷新成功
     ///
     /// 更新 Token 刷新统计
@@ -1470,6 +2672,7 @@ impl MultiTokenManager {
     fn report_token_refresh_success(&self, id: u64) {
         let mut entries = self.entries.lock();
         if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+            entry.touch();
             entry.token_refresh_count += 1;
             let now = std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
@@ -1493,6 +2696,7 @@ impl MultiTokenManager {
     fn report_token_refresh_failure(&self, id: u64) {
         let mut entries = self.entries.lock();
         if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+            entry.touch();
             entry.token_refresh_failure_count += 1;
             let now = std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
@@ -1541,26 +2745,117 @@ impl MultiTokenManager {
             &ctx.credentials,
             &self.config,
             &ctx.token,
-            self.proxy.as_ref(),
+            ctx.proxy_config.as_ref(),
         )
         .await
     }
 
+    /// 启动预热：并发校验所有启用凭据的 Token（过期或即将过期时刷新）
+    ///
+    /// 并发数受 `warmupConcurrency` 限制，刷新经由 `try_ensure_token`，
+    /// 与运行时共享凭据刷新锁和失败禁用逻辑。只执行一次，重复调用返回首次的报告
+    pub async fn warm_up(&self) -> &WarmupReport {
+        if let Some(report) = self.warmup_report.get() {
+            return report;
+        }
+
+        let targets: Vec<(u64, KiroCredentials)> = {
+            let entries = self.entries.lock();
+            entries
+                .iter()
+                .filter(|e| !e.disabled)
+                .map(|e| (e.id, e.credentials.clone()))
+                .collect()
+        };
+
+        let started = std::time::Instant::now();
+        let mut entries: Vec<WarmupEntry> =
+            futures::stream::iter(targets.into_iter().map(|(id, credentials)| async move {
+                let entry_started = std::time::Instant::now();
+                let refreshed =
+                    is_token_expired(&credentials) || is_token_expiring_soon(&credentials);
+                let result = self
+                    .try_ensure_token(id, &credentials, self.throttle_deadline())
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.to_string());
+                WarmupEntry {
+                    id,
+                    auth_method: WarmupEntry::auth_method_of(&credentials),
+                    priority: credentials.priority,
+                    duration_ms: entry_started.elapsed().as_millis() as u64,
+                    refreshed,
+                    result,
+                }
+            }))
+            .buffer_unordered(self.config.warmup_concurrency.max(1))
+            .collect()
+            .await;
+        entries.sort_by_key(|e| e.id);
+
+        let report = WarmupReport {
+            entries,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        };
+        self.warmup_report.get_or_init(|| report)
+    }
+
+    /// 获取启动预热报告（尚未预热时返回 None）
+    pub fn warmup_report(&self) -> Option<&WarmupReport> {
+        self.warmup_report.get()
+    }
+
+    /// 各用户的活跃会话统计（用户标识为哈希值，仅含计数）
+    pub fn user_session_counts(&self) -> Vec<UserSessionCount> {
+        self.user_fairness.active_sessions()
+    }
+
+    /// 记录一次请求的会话标识来源
+    ///
+    /// system 哈希兜底占比超过 `stickinessSystemHashWarnPercent` 时记录警告（每 10 分钟最多一次）
+    pub fn record_session_source(&self, source: SessionIdSource) {
+        self.stickiness.record_source(source);
+        if let Some(ratio) = self
+            .stickiness
+            .system_hash_warning(self.config.stickiness_system_hash_warn_percent)
+        {
+            tracing::warn!(
+                "{:.0}% 的请求使用 system prompt 哈希作为会话标识，客户端修改 system 前言后粘性会话会失效，\
+                 建议客户端发送 x-session-id 请求头",
+                ratio * 100.0
+            );
+        }
+    }
+
+    /// 粘性会话诊断统计快照
+    pub fn stickiness_snapshot(&self) -> StickinessSnapshot {
+        self.stickiness.snapshot()
+    }
+
     // ========================================================================
     // Admin API 方法
     // ========================================================================
 
     /// 获取管理器状态快照（用于 Admin API）
     pub fn snapshot(&self) -> ManagerSnapshot {
+        self.snapshot_since(None)
+    }
+
+    /// 获取管理器状态快照，只包含 `updated_since`（Unix 时间戳毫秒，含）之后状态有变化的凭据
+    ///
+    /// 未变化的凭据不构建快照条目；`total`、`available` 等汇总字段仍按全部凭据计算
+    pub fn snapshot_since(&self, updated_since: Option<u64>) -> ManagerSnapshot {
         let entries = self.entries.lock();
         let current_id = *self.current_id.lock();
         let available = entries.iter().filter(|e| !e.disabled).count();
         let mode = *self.scheduling_mode.lock();
+        let session_map = self.session_map.read();
         let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
 
         ManagerSnapshot {
             entries: entries
                 .iter()
+                .filter(|e| updated_since.is_none_or(|since| e.last_changed >= since))
                 .map(|e| {
                     let total_calls = e.success_count + e.total_failure_count;
                     let success_rate = if total_calls > 0 {
@@ -1573,6 +2868,7 @@ impl MultiTokenManager {
                     } else {
                         None
                     };
+                    let [p50_latency_ms, p95_latency_ms, p99_latency_ms] = e.latency_percentiles();
 
                     // 检查今日统计是否需要重置
                     let (today_success, today_failure) =
@@ -1582,6 +2878,8 @@ impl MultiTokenManager {
                             (0, 0)
                         };
 
+                    let settings = self.resolve_settings(&e.credentials);
+
                     CredentialEntrySnapshot {
                         id: e.id,
                         priority: e.credentials.priority,
@@ -1597,6 +2895,36 @@ impl MultiTokenManager {
                         }),
                         has_profile_arn: e.credentials.profile_arn.is_some(),
                         expires_at: e.credentials.expires_at.clone(),
+                        notes: e.credentials.notes.clone(),
+                        tags: e.credentials.tags.clone(),
+                        region: e.credentials.region.clone(),
+                        effective_region: settings.region,
+                        region_source: settings.region_source,
+                        proxy_source: settings.proxy_source,
+                        pool_id: e.credentials.pool_id.clone(),
+                        disabled_reason: e
+                            .disabled_reason
+                            .filter(|_| e.disabled)
+                            .map(|r| r.description().to_string()),
+                        disabled_at: e.disabled_at.map(|t| t.to_rfc3339()),
+                        flap_count: e.flap_count(),
+                        current_concurrency: e.current_concurrency(),
+                        max_concurrency: e.max_concurrency,
+                        last_error: e.last_error.clone(),
+                        retry_after_until: e.retry_after_remaining().map(|remaining| {
+                            (Utc::now() + Duration::from_std(remaining).unwrap_or(Duration::zero()))
+                                .to_rfc3339()
+                        }),
+                        failure_classification: e
+                            .last_error
+                            .as_deref()
+                            .map(FailureClass::from_error_message)
+                            .or_else(|| {
+                                e.disabled_reason
+                                    .filter(|_| e.disabled)
+                                    .and_then(FailureClass::from_disabled_reason)
+                            }),
+                        last_changed: e.last_changed,
                         // 调用统计字段
                         success_count: e.success_count,
                         total_failure_count: e.total_failure_count,
@@ -1604,6 +2932,10 @@ impl MultiTokenManager {
                         success_rate,
                         last_call_time: e.last_call_time,
                         avg_response_time_ms,
+                        avg_latency_ms: avg_latency_ms(e.total_response_time_ms, e.success_count),
+                        p50_latency_ms,
+                        p95_latency_ms,
+                        p99_latency_ms,
                         today_success_count: today_success,
                         today_failure_count: today_failure,
                         today_total_calls: today_success + today_failure,
@@ -1617,33 +2949,54 @@ impl MultiTokenManager {
             current_id,
             total: entries.len(),
             available,
-            session_cache_size: self.session_map.entry_count() as usize,
+            session_cache_size: session_map.entry_count() as usize,
+            session_cache_capacity: session_map.policy().max_capacity().unwrap_or(0),
+            session_cache_ttl_secs: session_map
+                .policy()
+                .time_to_live()
+                .map_or(0, |ttl| ttl.as_secs()),
             round_robin_counter: self.round_robin_counter.load(Ordering::Relaxed),
             scheduling_mode: mode,
         }
     }
 
     /// 设置凭据禁用状态（Admin API）
-    pub fn set_disabled(&self, id: u64, disabled: bool) -> anyhow::Result<()> {
-        {
+    ///
+    /// `actor` 为操作者身份，记录到禁用历史
+    pub fn set_disabled(&self, id: u64, disabled: bool, actor: &str) -> anyhow::Result<()> {
+        let (failure_count, available) = {
             let mut entries = self.entries.lock();
             let entry = entries
                 .iter_mut()
                 .find(|e| e.id == id)
                 .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
-            entry.disabled = disabled;
+            entry.record_history();
+            entry.touch();
             if !disabled {
                 // 启用时重置失败计数
+                entry.enable("手动启用", actor);
                 entry.failure_count = 0;
-                entry.disabled_reason = None;
+                entry.last_error = None;
+                entry.quota_reset_at = None;
             } else {
-                entry.disabled_reason = Some(DisabledReason::Manual);
+                entry.disable(DisabledReason::Manual, actor);
             }
+            let failure_count = entry.failure_count;
+            let available = entries.iter().filter(|e| !e.disabled).count();
+            (failure_count, available)
+        };
+        self.publish_credential_status(id, disabled, failure_count, Some(available));
+        if !disabled {
+            self.availability_notify.notify_waiters();
         }
         // 凭据列表变化，重置轮询计数器确保公平性
         self.reset_round_robin_counter();
         // 持久化更改
-        self.persist_credentials()?;
+        let action = if disabled { "禁用" } else { "启用" };
+        self.persist_credentials(Change::new(
+            "credentials",
+            format!("{}凭据 #{}", action, id),
+        ))?;
         Ok(())
     }
 
@@ -1658,31 +3011,301 @@ impl MultiTokenManager {
                 .iter_mut()
                 .find(|e| e.id == id)
                 .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
+            entry.record_history();
             entry.credentials.priority = priority;
+            entry.touch();
         }
         // 立即按新优先级重新选择当前凭据（无论持久化是否成功）
         self.select_highest_priority();
         // 持久化更改
-        self.persist_credentials()?;
+        self.persist_credentials(Change::new(
+            "credentials",
+            format!("设置凭据 #{} 优先级为 {}", id, priority),
+        ))?;
         Ok(())
     }
 
-    /// 重置凭据失败计数并重新启用（Admin API）
-    pub fn reset_and_enable(&self, id: u64) -> anyhow::Result<()> {
+    /// 批量设置凭据优先级（Admin API 拖拽排序）
+    ///
+    /// 全部条目更新后只重新选择一次当前凭据并持久化一次，返回不存在的凭据 ID
+    pub fn set_priorities(&self, priorities: &[(u64, u32)]) -> anyhow::Result<Vec<u64>> {
+        let mut missing = Vec::new();
         {
             let mut entries = self.entries.lock();
-            let entry = entries
-                .iter_mut()
-                .find(|e| e.id == id)
-                .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
-            entry.failure_count = 0;
-            entry.disabled = false;
-            entry.disabled_reason = None;
+            for &(id, priority) in priorities {
+                match entries.iter_mut().find(|e| e.id == id) {
+                    Some(entry) => {
+                        entry.record_history();
+                        entry.credentials.priority = priority;
+                        entry.touch();
+                    }
+                    None => missing.push(id),
+                }
+            }
+        }
+        if missing.len() == priorities.len() {
+            return Ok(missing);
         }
-        // 持久化更改
-        self.persist_credentials()?;
-        Ok(())
-    }
+
+        self.select_highest_priority();
+        self.persist_credentials(Change::new(
+            "credentials",
+            format!(
+                "批量设置 {} 个凭据的优先级",
+                priorities.len() - missing.len()
+            ),
+        ))?;
+        Ok(missing)
+    }
+
+    /// 获取凭据的历史版本（Admin API，最新的在前；凭据不存在时返回 None）
+    pub fn credential_history(&self, id: u64) -> Option<Vec<CredentialSnapshot>> {
+        let entries = self.entries.lock();
+        let entry = entries.iter().find(|e| e.id == id)?;
+        Some(entry.history.iter().rev().cloned().collect())
+    }
+
+    /// 获取凭据最近响应时间的直方图（Admin API，按 `LATENCY_BUCKET_MS` 分桶；凭据不存在时返回 None）
+    pub fn latency_histogram(&self, id: u64) -> Option<Vec<LatencyBucket>> {
+        let entries = self.entries.lock();
+        let entry = entries.iter().find(|e| e.id == id)?;
+        let samples: Vec<u64> = entry.response_times.iter().copied().collect();
+        Some(latency_histogram(&samples))
+    }
+
+    /// 获取凭据最近 `window_minutes` 分钟的调用时间线（Admin API，按 `granularity_minutes` 分桶；凭据不存在时返回 None）
+    pub fn call_timeline(
+        &self,
+        id: u64,
+        window_minutes: u64,
+        granularity_minutes: u64,
+    ) -> Option<Vec<TimelineBucket>> {
+        if !self.entries.lock().iter().any(|e| e.id == id) {
+            return None;
+        }
+        Some(
+            self.call_timeline
+                .buckets(id, window_minutes, granularity_minutes),
+        )
+    }
+
+    /// 沿用另一个 Token 管理器中本管理器所属凭据的调用时间线（重建池运行时时调用）
+    pub fn inherit_call_timeline(&self, other: &MultiTokenManager) {
+        let owned_ids = self.owned_ids.lock().clone();
+        self.call_timeline.inherit(&other.call_timeline, &owned_ids);
+    }
+
+    /// 获取凭据的禁用/启用记录（Admin API，最新的在前；凭据不存在时返回 None）
+    pub fn disable_history(&self, id: u64) -> Option<Vec<DisableTransition>> {
+        let entries = self.entries.lock();
+        let entry = entries.iter().find(|e| e.id == id)?;
+        Some(entry.disable_history.iter().rev().cloned().collect())
+    }
+
+    /// `since` 之后被禁用过的凭据（Admin 仪表盘，返回凭据 ID 和最近一次禁用的原因）
+    ///
+    /// 基于内存中的禁用记录，包括之后已重新启用的凭据
+    pub fn disabled_since(&self, since: DateTime<Utc>) -> Vec<(u64, String)> {
+        let entries = self.entries.lock();
+        entries
+            .iter()
+            .filter_map(|e| {
+                let transition = e
+                    .disable_history
+                    .iter()
+                    .rev()
+                    .find(|t| t.disabled && t.timestamp >= since)?;
+                Some((e.id, transition.reason.clone()))
+            })
+            .collect()
+    }
+
+    /// 回滚凭据到历史版本（Admin API）
+    ///
+    /// `version` 从 1 开始，1 为最近一次变更前的状态。只恢复优先级、禁用状态、备注和标签，
+    /// Token 保持当前值。回滚本身也会记录历史，可以再次回滚撤销；`actor` 为操作者身份，记录到禁用历史
+    pub fn rollback_credential(&self, id: u64, version: usize, actor: &str) -> anyhow::Result<()> {
+        let (disabled, failure_count, available) = {
+            let mut entries = self.entries.lock();
+            let entry = entries
+                .iter_mut()
+                .find(|e| e.id == id)
+                .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
+            let snapshot = version
+                .checked_sub(1)
+                .and_then(|i| entry.history.len().checked_sub(i + 1))
+                .and_then(|i| entry.history.get(i).cloned())
+                .ok_or_else(|| anyhow::anyhow!("凭据 #{} 没有版本 {}", id, version))?;
+
+            entry.record_history();
+            entry.touch();
+            entry.credentials.priority = snapshot.priority;
+            entry.credentials.notes = snapshot.notes;
+            entry.credentials.tags = snapshot.tags;
+            if snapshot.disabled {
+                entry.disable(DisabledReason::Manual, actor);
+            } else {
+                entry.enable("回滚到历史版本", actor);
+                entry.failure_count = 0;
+                entry.last_error = None;
+                entry.quota_reset_at = None;
+            }
+            let failure_count = entry.failure_count;
+            let available = entries.iter().filter(|e| !e.disabled).count();
+            (snapshot.disabled, failure_count, available)
+        };
+        self.publish_credential_status(id, disabled, failure_count, Some(available));
+        if !disabled {
+            self.availability_notify.notify_waiters();
+        }
+        self.reset_round_robin_counter();
+        self.select_highest_priority();
+        self.persist_credentials(Change::new(
+            "credentials",
+            format!("回滚凭据 #{} 到版本 {}", id, version),
+        ))?;
+        Ok(())
+    }
+
+    /// 设置凭据备注（Admin API，None 表示清除）
+    pub fn set_notes(&self, id: u64, notes: Option<String>) -> anyhow::Result<()> {
+        {
+            let mut entries = self.entries.lock();
+            let entry = entries
+                .iter_mut()
+                .find(|e| e.id == id)
+                .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
+            entry.record_history();
+            entry.credentials.notes = notes;
+            entry.touch();
+        }
+        // 持久化更改
+        self.persist_credentials(Change::new(
+            "credentials",
+            format!("更新凭据 #{} 备注", id),
+        ))?;
+        Ok(())
+    }
+
+    /// 替换凭据自定义请求头（Admin API），返回更新后的请求头
+    ///
+    /// 调用方负责校验（见 `validate_custom_headers`）；下一次上游请求即生效
+    pub fn set_custom_headers(
+        &self,
+        id: u64,
+        headers: HashMap<String, String>,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        {
+            let mut entries = self.entries.lock();
+            let entry = entries
+                .iter_mut()
+                .find(|e| e.id == id)
+                .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
+            entry.credentials.custom_headers = headers.clone();
+            entry.touch();
+        }
+        // 持久化更改
+        self.persist_credentials(Change::new(
+            "credentials",
+            format!("更新凭据 #{} 自定义请求头", id),
+        ))?;
+        Ok(headers)
+    }
+
+    /// 添加/移除凭据标签（Admin API），返回更新后的标签
+    ///
+    /// 先移除再添加，标签按大小写不敏感去重
+    pub fn update_tags(
+        &self,
+        id: u64,
+        add: Vec<String>,
+        remove: &[String],
+    ) -> anyhow::Result<Vec<String>> {
+        let tags = {
+            let mut entries = self.entries.lock();
+            let entry = entries
+                .iter_mut()
+                .find(|e| e.id == id)
+                .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
+            entry.record_history();
+            let kept = std::mem::take(&mut entry.credentials.tags)
+                .into_iter()
+                .filter(|t| !remove.iter().any(|r| r.trim().eq_ignore_ascii_case(t)));
+            entry.credentials.tags = normalize_tags(kept.chain(add));
+            entry.touch();
+            entry.credentials.tags.clone()
+        };
+        // 持久化更改
+        self.persist_credentials(Change::new(
+            "credentials",
+            format!("更新凭据 #{} 标签", id),
+        ))?;
+        Ok(tags)
+    }
+
+    /// 重置凭据失败计数并重新启用（Admin API，`actor` 为操作者身份）
+    pub fn reset_and_enable(&self, id: u64, actor: &str) -> anyhow::Result<()> {
+        {
+            let mut entries = self.entries.lock();
+            let entry = entries
+                .iter_mut()
+                .find(|e| e.id == id)
+                .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
+            entry.touch();
+            entry.failure_count = 0;
+            entry.enable("重置并启用", actor);
+            entry.last_error = None;
+            entry.quota_reset_at = None;
+        }
+        self.availability_notify.notify_waiters();
+        // 持久化更改
+        self.persist_credentials(Change::new(
+            "credentials",
+            format!("重置并启用凭据 #{}", id),
+        ))?;
+        Ok(())
+    }
+
+    /// 立即刷新指定凭据的 Token（Admin API）
+    ///
+    /// `force` 为 true 时先把 expiresAt 置为过去时间，即使 Token 仍在有效期内也会刷新，
+    /// 刷新失败时恢复原 expiresAt（原 Token 仍可继续使用）；返回刷新后的凭据
+    pub async fn force_refresh(&self, id: u64, force: bool) -> anyhow::Result<KiroCredentials> {
+        let (credentials, original_expires_at) = {
+            let mut entries = self.entries.lock();
+            let entry = entries
+                .iter_mut()
+                .find(|e| e.id == id)
+                .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?;
+            let original_expires_at = entry.credentials.expires_at.clone();
+            if force {
+                entry.credentials.expires_at = Some(FORCE_REFRESH_EXPIRES_AT.to_string());
+                entry.touch();
+            }
+            (entry.credentials.clone(), original_expires_at)
+        };
+
+        match self
+            .try_ensure_token(id, &credentials, self.throttle_deadline())
+            .await
+        {
+            Ok(ctx) => Ok(ctx.credentials),
+            Err(e) => {
+                // 期间未被其他刷新更新时恢复原过期时间
+                if force {
+                    let mut entries = self.entries.lock();
+                    if let Some(entry) = entries.iter_mut().find(|e| {
+                        e.id == id
+                            && e.credentials.expires_at.as_deref() == Some(FORCE_REFRESH_EXPIRES_AT)
+                    }) {
+                        entry.credentials.expires_at = original_expires_at;
+                        entry.touch();
+                    }
+                }
+                Err(e.into())
+            }
+        }
+    }
 
     /// 获取指定凭据的使用额度（Admin API）
     pub async fn get_usage_limits_for(&self, id: u64) -> anyhow::Result<UsageLimitsResponse> {
@@ -1716,8 +3339,7 @@ impl MultiTokenManager {
             };
 
             if is_token_expired(&current_creds) || is_token_expiring_soon(&current_creds) {
-                let refresh_result =
-                    refresh_token(&current_creds, &self.config, self.proxy.as_ref()).await;
+                let refresh_result = self.refresh_resolved(&current_creds).await;
 
                 match refresh_result {
                     Ok(new_creds) => {
@@ -1727,10 +3349,13 @@ impl MultiTokenManager {
                             let mut entries = self.entries.lock();
                             if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
                                 entry.credentials = new_creds.clone();
+                                entry.touch();
                             }
                         }
                         // 持久化失败只记录警告，不影响本次请求
-                        if let Err(e) = self.persist_credentials() {
+                        if let Err(e) =
+                            self.persist_credentials(Change::background("credentials"))
+                        {
                             tracing::warn!("Token 刷新后持久化失败（不影响本次请求）: {}", e);
                         }
                         new_creds
@@ -1763,7 +3388,60 @@ impl MultiTokenManager {
                 .ok_or_else(|| anyhow::anyhow!("凭据不存在: {}", id))?
         };
 
-        get_usage_limits(&credentials, &self.config, &token, self.proxy.as_ref()).await
+        let proxy = self.resolve_proxy_config(&credentials);
+        let usage = get_usage_limits(
+            &self.resolve_credentials(&credentials),
+            &self.config,
+            &token,
+            proxy.as_ref(),
+        )
+        .await?;
+        self.set_cached_usage(id, usage.current_usage(), usage.usage_limit());
+        Ok(usage)
+    }
+
+    /// 是否存在指定 ID 的凭据
+    pub fn contains(&self, id: u64) -> bool {
+        self.entries.lock().iter().any(|e| e.id == id)
+    }
+
+    /// 凭据当前是否持有未过期的 accessToken（不触发刷新）
+    pub fn has_valid_token(&self, id: u64) -> bool {
+        self.entries
+            .lock()
+            .iter()
+            .find(|e| e.id == id)
+            .is_some_and(|e| {
+                e.credentials.access_token.is_some() && !is_token_expired(&e.credentials)
+            })
+    }
+
+    /// 记录凭据最近一次查询到的额度
+    pub fn set_cached_usage(&self, id: u64, current_usage: f64, usage_limit: f64) {
+        let mut entries = self.entries.lock();
+        if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+            entry.cached_usage = Some((current_usage, usage_limit));
+        }
+    }
+
+    /// 可用凭据的剩余额度百分比（基于缓存的余额，不调用上游）
+    ///
+    /// 按可用凭据的总额度加权；没有任何可用凭据查询过余额时返回 None
+    pub fn cached_remaining_quota_percentage(&self) -> Option<f64> {
+        let entries = self.entries.lock();
+        let (remaining, limit) = entries
+            .iter()
+            .filter(|e| !e.disabled)
+            .filter_map(|e| e.cached_usage)
+            .fold(None, |acc: Option<(f64, f64)>, (current, limit)| {
+                let (remaining, total) = acc.unwrap_or_default();
+                Some((remaining + (limit - current).max(0.0), total + limit))
+            })?;
+        if limit > 0.0 {
+            Some((remaining / limit * 100.0).min(100.0))
+        } else {
+            Some(0.0)
+        }
     }
 
     /// 添加新凭据（Admin API）
@@ -1778,23 +3456,35 @@ impl MultiTokenManager {
     /// # 返回
     /// - `Ok(u64)` - 新凭据 ID
     /// - `Err(_)` - 验证失败或添加失败
-    pub async fn add_credential(&self, new_cred: KiroCredentials) -> anyhow::Result<u64> {
+    pub async fn add_credential(&self, new_cred: KiroCredentials) -> Result<u64, KiroError> {
+        let defaults = self.credential_defaults();
+        self.add_credential_with_defaults(new_cred, &defaults).await
+    }
+
+    /// 按目标池的凭据默认设置验证并添加凭据
+    ///
+    /// 启用池时新凭据经全局管理器分配 ID 和写回，但由所属池调度，
+    /// 验证刷新需使用该池的默认 Region、认证方式、代理和 machineId
+    pub async fn add_credential_with_defaults(
+        &self,
+        new_cred: KiroCredentials,
+        defaults: &CredentialDefaults,
+    ) -> Result<u64, KiroError> {
+        let next_id = || self.entries.lock().iter().map(|e| e.id).max().unwrap_or(0) + 1;
+
         // 1. 基本验证
-        validate_refresh_token(&new_cred)?;
+        validate_refresh_token(&new_cred)
+            .map_err(|e| KiroError::from_refresh_error(next_id(), e))?;
 
         // 2. 尝试刷新 Token 验证凭据有效性
-        let mut validated_cred =
-            refresh_token(&new_cred, &self.config, self.proxy.as_ref()).await?;
-
-        // 3. 分配新 ID
-        let new_id = {
-            let entries = self.entries.lock();
-            entries.iter().map(|e| e.id).max().unwrap_or(0) + 1
-        };
+        let mut validated_cred = self
+            .refresh_resolved_with(&new_cred, defaults)
+            .await
+            .map_err(|e| KiroError::from_refresh_error(next_id(), e))?;
 
-        // 4. 设置 ID 并保留用户输入的元数据
-        validated_cred.id = Some(new_id);
+        // 3. 保留用户输入的元数据
         validated_cred.priority = new_cred.priority;
+        validated_cred.max_concurrent_requests = new_cred.max_concurrent_requests;
         validated_cred.auth_method = new_cred.auth_method.map(|m| {
             if m.eq_ignore_ascii_case("builder-id") || m.eq_ignore_ascii_case("iam") {
                 "idc".to_string()
@@ -1807,36 +3497,63 @@ impl MultiTokenManager {
         validated_cred.region = new_cred.region;
         validated_cred.machine_id = new_cred.machine_id;
 
-        {
+        // 4. 分配新 ID 并持久化
+        self.insert_new_credential(validated_cred, true)
+    }
+
+    /// 分配新 ID 并添加凭据条目，然后持久化
+    ///
+    /// `refreshed` 表示添加前是否已成功刷新过 Token（计入刷新统计）
+    fn insert_new_credential(
+        &self,
+        mut credentials: KiroCredentials,
+        refreshed: bool,
+    ) -> Result<u64, KiroError> {
+        let new_id = {
+            let (max_concurrency, concurrency) = concurrency_semaphore(&credentials);
             let mut entries = self.entries.lock();
+            let new_id = entries.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+            credentials.id = Some(new_id);
             entries.push(CredentialEntry {
                 id: new_id,
-                credentials: validated_cred,
+                credentials,
                 failure_count: 0,
                 disabled: false,
                 disabled_reason: None,
+                quota_reset_at: None,
                 // 初始化统计字段
                 success_count: 0,
                 total_failure_count: 0,
                 last_call_time: None,
                 total_response_time_ms: 0,
+                response_times: VecDeque::new(),
                 today_success_count: 0,
                 today_failure_count: 0,
                 today_date: None,
-                // Token 刷新统计（添加时已成功刷新一次）
-                token_refresh_count: 1,
+                token_refresh_count: u64::from(refreshed),
                 token_refresh_failure_count: 0,
-                last_token_refresh_time: Some(
+                last_token_refresh_time: refreshed.then(|| {
                     std::time::SystemTime::now()
                         .duration_since(std::time::UNIX_EPOCH)
                         .map(|d| d.as_millis() as u64)
-                        .unwrap_or(0),
-                ),
+                        .unwrap_or(0)
+                }),
+                cached_usage: None,
+                last_error: None,
+                retry_after_until: None,
+                last_changed: now_millis(),
+                history: VecDeque::new(),
+                disabled_at: None,
+                disable_history: VecDeque::new(),
+                max_concurrency,
+                concurrency,
             });
-        }
+            new_id
+        };
 
-        // 5. 持久化
-        self.persist_credentials()?;
+        self.owned_ids.lock().insert(new_id);
+        self.persist_credentials(Change::new("credentials", format!("添加凭据 #{}", new_id)))
+            .map_err(|e| KiroError::IoError(std::io::Error::other(format!("{:#}", e))))?;
 
         // 凭据列表变化，重置轮询计数器确保公平性
         self.reset_round_robin_counter();
@@ -1845,6 +3562,66 @@ impl MultiTokenManager {
         Ok(new_id)
     }
 
+    /// 克隆凭据（Admin API）
+    ///
+    /// 复制认证字段（refreshToken、accessToken 及其过期时间、认证方式、clientId/clientSecret、
+    /// machineId）和池、区域、优先级、并发、代理配置，按 `overrides` 覆盖后作为新凭据添加：
+    /// 分配新 ID，不复制统计字段，创建时不刷新 Token（刷新可能轮换原凭据仍在使用的 refreshToken）。
+    /// Social 凭据每次刷新都会轮换 refreshToken，克隆后两者会互相使对方失效，因此拒绝克隆
+    pub fn clone_credential(
+        &self,
+        id: u64,
+        overrides: CredentialCloneOverrides,
+    ) -> anyhow::Result<u64> {
+        let source = self
+            .entries
+            .lock()
+            .iter()
+            .find(|e| e.id == id)
+            .map(|e| e.credentials.clone())
+            .ok_or(KiroError::CredentialNotFound(id))?;
+        if uses_social_refresh(&source) {
+            return Err(KiroError::RotatingRefreshToken(id).into());
+        }
+
+        let clone = KiroCredentials {
+            refresh_token: source.refresh_token,
+            access_token: source.access_token,
+            expires_at: source.expires_at,
+            profile_arn: source.profile_arn,
+            auth_method: source.auth_method,
+            client_id: source.client_id,
+            client_secret: source.client_secret,
+            machine_id: source.machine_id,
+            priority: overrides.priority.unwrap_or(source.priority),
+            pool_id: overrides.pool_id.or(source.pool_id),
+            region: overrides.region.or(source.region),
+            max_concurrent_requests: source.max_concurrent_requests,
+            proxy_url: source.proxy_url,
+            proxy_username: source.proxy_username,
+            proxy_password: source.proxy_password,
+            custom_headers: source.custom_headers,
+            tags: source.tags,
+            ..KiroCredentials::default()
+        };
+
+        let new_id = self.insert_new_credential(clone, false)?;
+        tracing::info!("凭据 #{} 已克隆为 #{}", id, new_id);
+        Ok(new_id)
+    }
+
+    /// 两个凭据当前是否使用同一个 refreshToken
+    pub fn shares_refresh_token(&self, a: u64, b: u64) -> bool {
+        let entries = self.entries.lock();
+        let refresh_token = |id: u64| {
+            entries
+                .iter()
+                .find(|e| e.id == id)
+                .and_then(|e| e.credentials.refresh_token.clone())
+        };
+        matches!((refresh_token(a), refresh_token(b)), (Some(x), Some(y)) if x == y)
+    }
+
     /// 删除凭据（Admin API）
     ///
     /// # 前置条件
@@ -1885,6 +3662,7 @@ impl MultiTokenManager {
 
             was_current
         };
+        self.call_timeline.remove(id);
 
         // 如果删除的是当前凭据，切换到优先级最高的可用凭据
         if was_current {
@@ -1902,7 +3680,7 @@ impl MultiTokenManager {
         }
 
         // 持久化更改
-        self.persist_credentials()?;
+        self.persist_credentials(Change::new("credentials", format!("删除凭据 #{}", id)))?;
 
         // 凭据列表变化，重置轮询计数器确保公平性
         self.reset_round_robin_counter();
@@ -1911,6 +3689,164 @@ impl MultiTokenManager {
         Ok(())
     }
 
+    /// 替换全部凭据（从备份恢复后重新加载）
+    ///
+    /// 新凭据按启动时的规则过滤并补全 ID；不回写文件（调用方已写入）。
+    /// 清空会话映射并重新选择当前凭据，返回加载的凭据数量
+    pub fn replace_credentials(&self, credentials: Vec<KiroCredentials>) -> anyhow::Result<usize> {
+        let loaded = Self::new(self.config.clone(), credentials, self.proxy.clone(), None)?;
+        self.source_writers
+            .lock()
+            .extend(loaded.source_writers.into_inner());
+        let entries = loaded.entries.into_inner();
+        let count = entries.len();
+
+        let owned_ids: HashSet<u64> = entries.iter().map(|e| e.id).collect();
+        // 仍存在的凭据保留调用时间线
+        self.call_timeline.retain(&owned_ids);
+        *self.owned_ids.lock() = owned_ids;
+        *self.entries.lock() = entries;
+        *self.current_id.lock() = loaded.current_id.into_inner();
+        self.session_map.read().invalidate_all();
+        self.reset_round_robin_counter();
+        self.availability_notify.notify_waiters();
+
+        tracing::info!("已重新加载 {} 个凭据", count);
+        Ok(count)
+    }
+
+    /// 移出凭据（池间转移，Admin API）
+    ///
+    /// 从凭据列表移除并解除与本池会话的绑定，运行时状态和统计随凭据一起转移；
+    /// 不回写文件（由目标池接收后写入）。凭据不存在时返回 None
+    pub fn take_credential(&self, id: u64) -> Option<CredentialTransfer> {
+        let entry = {
+            let mut entries = self.entries.lock();
+            let index = entries.iter().position(|e| e.id == id)?;
+            entries.remove(index)
+        };
+        self.owned_ids.lock().remove(&id);
+
+        // 收集并移除绑定到该凭据的会话
+        let sessions: Vec<String> = {
+            let session_map = self.session_map.read();
+            let sessions: Vec<String> = session_map
+                .iter()
+                .filter(|(_, credential_id)| *credential_id == id)
+                .map(|(session_id, _)| session_id.as_ref().clone())
+                .collect();
+            for session_id in &sessions {
+                session_map.invalidate(session_id);
+            }
+            sessions
+        };
+
+        if *self.current_id.lock() == id {
+            self.select_highest_priority();
+        }
+        if self.entries.lock().is_empty() {
+            *self.current_id.lock() = 0;
+        }
+        self.reset_round_robin_counter();
+
+        Some(CredentialTransfer { entry, sessions })
+    }
+
+    /// 移除凭据（池分配，Admin API）
+    ///
+    /// 与 [`Self::take_credential`] 不同，只返回凭据配置（已同步统计数据），
+    /// 运行时状态和会话绑定随之丢弃，相当于该凭据重新加载；不回写文件
+    /// （由目标池添加后写入）。凭据不存在时返回 None
+    pub fn remove_credential(&self, id: u64) -> Option<KiroCredentials> {
+        self.take_credential(id)
+            .map(|transfer| transfer.entry.persisted())
+    }
+
+    /// 直接添加凭据（池分配，Admin API）
+    ///
+    /// 不刷新 Token 验证，保留凭据已有的 ID 和统计数据，运行时状态重置。
+    /// 凭据缺少 ID 或 ID 已存在时返回错误
+    pub fn add_credential_direct(&self, mut credentials: KiroCredentials) -> anyhow::Result<u64> {
+        let id = credentials
+            .id
+            .ok_or_else(|| anyhow::anyhow!("凭据缺少 ID"))?;
+        credentials.canonicalize_auth_method();
+        let pool_id = credentials
+            .pool_id
+            .clone()
+            .unwrap_or_else(|| DEFAULT_POOL_ID.to_string());
+        {
+            let mut entries = self.entries.lock();
+            if entries.iter().any(|e| e.id == id) {
+                anyhow::bail!("凭据 #{} 已存在", id);
+            }
+            self.track_source(&credentials);
+            entries.push(CredentialEntry::loaded(id, credentials));
+            entries.sort_by_key(|e| e.id);
+        }
+        self.owned_ids.lock().insert(id);
+
+        let current_id = *self.current_id.lock();
+        if !self.contains(current_id) {
+            self.select_highest_priority();
+        }
+        self.reset_round_robin_counter();
+
+        self.persist_credentials(Change::new(
+            "pools",
+            format!("分配凭据 #{} 到池 {}", id, pool_id),
+        ))?;
+        Ok(id)
+    }
+
+    /// 接收其他池转移来的凭据（池间转移，Admin API）
+    ///
+    /// 不重新验证 Token；`migrate_sessions` 为 true 时将原池中绑定到该凭据的会话
+    /// 写入本池会话缓存，后续请求继续路由到同一凭据。返回迁移的会话数
+    pub fn adopt_credential(
+        &self,
+        transfer: CredentialTransfer,
+        pool_id: &str,
+        migrate_sessions: bool,
+    ) -> anyhow::Result<usize> {
+        let CredentialTransfer {
+            mut entry,
+            sessions,
+        } = transfer;
+        let id = entry.id;
+        entry.credentials.pool_id = Some(pool_id.to_string());
+        entry.touch();
+        self.track_source(&entry.credentials);
+        {
+            let mut entries = self.entries.lock();
+            entries.push(entry);
+            entries.sort_by_key(|e| e.id);
+        }
+        self.owned_ids.lock().insert(id);
+
+        let moved_sessions = if migrate_sessions {
+            let session_map = self.session_map.read();
+            for session_id in &sessions {
+                session_map.insert(session_id.clone(), id);
+            }
+            sessions.len()
+        } else {
+            0
+        };
+
+        let current_id = *self.current_id.lock();
+        if !self.contains(current_id) {
+            self.select_highest_priority();
+        }
+        self.reset_round_robin_counter();
+
+        self.persist_credentials(Change::new(
+            "pools",
+            format!("转移凭据 #{} 到池 {}", id, pool_id),
+        ))?;
+        Ok(moved_sessions)
+    }
+
     /// 设置调度模式（Admin API）
     ///
     /// # Arguments
@@ -1927,44 +3863,263 @@ impl MultiTokenManager {
         }
     }
 
-    /// 获取当前调度模式（Admin API）
-    #[allow(dead_code)]
-    pub fn get_scheduling_mode(&self) -> SchedulingMode {
-        *self.scheduling_mode.lock()
-    }
-
-    /// 解析代理配置
+    /// 更新会话缓存容量和 TTL（池配置更新，Admin API）
     ///
-    /// 优先级：凭据级 > 池级（self.proxy）> 全局
-    fn resolve_proxy_config(&self, credentials: &KiroCredentials) -> Option<ProxyConfig> {
-        // 凭据级代理优先
-        if let Some(ref proxy_url) = credentials.proxy_url {
-            return Some(ProxyConfig {
-                url: proxy_url.clone(),
-                username: credentials.proxy_username.clone(),
-                password: credentials.proxy_password.clone(),
-            });
+    /// 以新配置重建缓存，并迁移现有会话映射（最多迁移新容量条），
+    /// 避免已建立的粘性会话全部失效；迁移的条目 TTL 从重建时重新计时
+    pub fn set_session_cache_config(&self, max_capacity: u64, ttl_secs: u64) {
+        let mut session_map = self.session_map.write();
+        let policy = session_map.policy();
+        if policy.max_capacity() == Some(max_capacity)
+            && policy.time_to_live() == Some(StdDuration::from_secs(ttl_secs))
+        {
+            return;
         }
 
-        // 回退到池级/全局代理
-        self.proxy.clone()
+        let rebuilt = build_session_cache(max_capacity, ttl_secs);
+        for (session_id, credential_id) in session_map.iter().take(max_capacity as usize) {
+            rebuilt.insert(session_id.as_ref().clone(), credential_id);
+        }
+        rebuilt.run_pending_tasks();
+        tracing::info!(
+            "会话缓存已重建: 容量 {} -> {}, TTL {:?} -> {}s, 保留 {} 个会话",
+            policy.max_capacity().unwrap_or(0),
+            max_capacity,
+            policy.time_to_live().map(|ttl| ttl.as_secs()),
+            ttl_secs,
+            rebuilt.entry_count()
+        );
+        *session_map = rebuilt;
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// 更新所属池 ID（池重命名，Admin API）
+    ///
+    /// 同步修改所有凭据的 pool_id 和事件发布器的池 ID；
+    /// 不回写文件（由调用方统一写入），会话缓存和轮询状态保持不变
+    pub fn rename_pool(&self, new_pool_id: &str) {
+        for entry in self.entries.lock().iter_mut() {
+            entry.credentials.pool_id = Some(new_pool_id.to_string());
+            entry.touch();
+        }
+        if let Some(publisher) = self.event_publisher.get() {
+            *publisher.pool_id.lock() = new_pool_id.to_string();
+        }
+    }
 
-    #[test]
-    fn test_token_manager_new() {
-        let config = Config::default();
-        let credentials = KiroCredentials::default();
-        let tm = TokenManager::new(config, credentials, None);
-        assert!(tm.credentials().access_token.is_none());
+    /// 本管理器的凭据 ID（升序）
+    pub fn credential_ids(&self) -> Vec<u64> {
+        let mut ids: Vec<u64> = self.entries.lock().iter().map(|e| e.id).collect();
+        ids.sort_unstable();
+        ids
     }
 
-    #[test]
-    fn test_is_token_expired_with_expired_token() {
+    /// 修改凭据的所属池 ID（池间重新分配，Admin API）
+    ///
+    /// 只修改本管理器中列出的凭据，不回写文件（由调用方经 `flush_credentials` 写入），
+    /// 凭据仍由本管理器调度直到池重新加载。返回被修改凭据原来的池 ID，用于撤销
+    pub fn set_pool_ids(
+        &self,
+        pool_ids: &HashMap<u64, Option<String>>,
+    ) -> HashMap<u64, Option<String>> {
+        let mut previous = HashMap::new();
+        for entry in self.entries.lock().iter_mut() {
+            if let Some(pool_id) = pool_ids.get(&entry.id) {
+                let old = std::mem::replace(&mut entry.credentials.pool_id, pool_id.clone());
+                previous.insert(entry.id, old);
+                entry.touch();
+            }
+        }
+        previous
+    }
+
+    /// 将内存中的凭据回写到文件（经凭据文件的写入线程，保留刷新后轮换的 Token）
+    pub fn flush_credentials(&self) -> anyhow::Result<()> {
+        self.persist_credentials(Change::background("credentials"))
+            .map(|_| ())
+    }
+
+    /// 获取当前调度模式（Admin API）
+    #[allow(dead_code)]
+    pub fn get_scheduling_mode(&self) -> SchedulingMode {
+        *self.scheduling_mode.lock()
+    }
+
+    /// 设置池级凭据默认设置（只影响解析结果，不写回凭据文件）
+    pub fn set_credential_defaults(&self, defaults: CredentialDefaults) {
+        *self.credential_defaults.lock() = defaults;
+    }
+
+    /// 获取池级凭据默认设置
+    pub fn credential_defaults(&self) -> CredentialDefaults {
+        self.credential_defaults.lock().clone()
+    }
+
+    /// 解析凭据的生效设置
+    ///
+    /// Token 刷新、额度查询和上游调用统一经由此处，优先级：凭据级 > 池默认值 > 全局
+    fn resolve_settings(&self, credentials: &KiroCredentials) -> ResolvedSettings {
+        self.resolve_settings_with(credentials, &self.credential_defaults.lock())
+    }
+
+    /// 按指定的池默认值解析凭据的生效设置（用于尚未加入本管理器所属池的凭据）
+    fn resolve_settings_with(
+        &self,
+        credentials: &KiroCredentials,
+        defaults: &CredentialDefaults,
+    ) -> ResolvedSettings {
+        let (region, region_source) = match (&credentials.region, &defaults.region) {
+            (Some(region), _) => (region.clone(), SettingSource::Credential),
+            (None, Some(region)) => (region.clone(), SettingSource::Pool),
+            (None, None) => (self.config.region.clone(), SettingSource::Global),
+        };
+
+        let auth_method = credentials
+            .auth_method
+            .clone()
+            .or_else(|| defaults.auth_method.clone());
+
+        let (proxy, proxy_source) = if let Some(ref proxy_url) = credentials.proxy_url {
+            let proxy = ProxyConfig {
+                url: proxy_url.clone(),
+                username: credentials.proxy_username.clone(),
+                password: credentials.proxy_password.clone(),
+            };
+            (Some(proxy), Some(SettingSource::Credential))
+        } else if defaults.proxy.is_some() {
+            (defaults.proxy.clone(), Some(SettingSource::Pool))
+        } else {
+            // 池未配置代理时 self.proxy 即全局代理
+            (
+                self.proxy.clone(),
+                self.proxy.as_ref().map(|_| SettingSource::Global),
+            )
+        };
+
+        ResolvedSettings {
+            region,
+            region_source,
+            auth_method,
+            proxy,
+            proxy_source,
+        }
+    }
+
+    /// 应用池默认值后的凭据（用于刷新、额度查询和上游调用，不写回）
+    ///
+    /// 凭据未配置 machineId 时填入池默认 machineId；两者都没有时由请求头按
+    /// 全局 machineId、refreshToken 依次生成
+    fn resolve_credentials(&self, credentials: &KiroCredentials) -> KiroCredentials {
+        self.resolve_credentials_with(credentials, &self.credential_defaults.lock())
+    }
+
+    /// 按指定的池默认值解析凭据
+    fn resolve_credentials_with(
+        &self,
+        credentials: &KiroCredentials,
+        defaults: &CredentialDefaults,
+    ) -> KiroCredentials {
+        let settings = self.resolve_settings_with(credentials, defaults);
+        let mut resolved = credentials.clone();
+        if settings.region_source == SettingSource::Pool {
+            resolved.region = Some(settings.region);
+        }
+        resolved.auth_method = settings.auth_method;
+        if resolved.machine_id.is_none() {
+            resolved.machine_id = defaults.machine_id.clone();
+        }
+        resolved
+    }
+
+    /// 解析代理配置
+    ///
+    /// 优先级：凭据级 > 池默认代理（未设置时为池级代理）> 全局代理
+    fn resolve_proxy_config(&self, credentials: &KiroCredentials) -> Option<ProxyConfig> {
+        self.resolve_settings(credentials).proxy
+    }
+
+    /// 按生效设置刷新 Token
+    ///
+    /// 池默认值只参与刷新，返回的凭据保留原有的 region、authMethod 和 machineId，避免写回凭据文件
+    async fn refresh_resolved(
+        &self,
+        credentials: &KiroCredentials,
+    ) -> anyhow::Result<KiroCredentials> {
+        let defaults = self.credential_defaults();
+        self.refresh_resolved_with(credentials, &defaults).await
+    }
+
+    /// 按指定的池默认值刷新 Token（返回的凭据同样保留原有的 region、authMethod 和 machineId）
+    async fn refresh_resolved_with(
+        &self,
+        credentials: &KiroCredentials,
+        defaults: &CredentialDefaults,
+    ) -> anyhow::Result<KiroCredentials> {
+        let proxy = self.resolve_settings_with(credentials, defaults).proxy;
+        let mut refreshed = refresh_token(
+            &self.resolve_credentials_with(credentials, defaults),
+            &self.config,
+            proxy.as_ref(),
+        )
+        .await?;
+        refreshed.region = credentials.region.clone();
+        refreshed.auth_method = credentials.auth_method.clone();
+        refreshed.machine_id = credentials.machine_id.clone();
+        Ok(refreshed)
+    }
+}
+
+/// 将本管理器的凭据合并到凭据文件的当前内容
+///
+/// 移除文件中属于本管理器的条目（按 ID，或无 ID 但 refreshToken 相同的条目），
+/// 追加当前凭据后按 ID 排序；文件不存在或为空时只写入当前凭据。
+/// 文件无法解析为凭据列表时返回错误，避免覆盖其他池的凭据
+fn merge_credentials(
+    path: &std::path::Path,
+    current: Option<&str>,
+    owned_ids: HashSet<u64>,
+    credentials: Vec<KiroCredentials>,
+) -> anyhow::Result<Vec<KiroCredentials>> {
+    let existing: Vec<KiroCredentials> = match current.filter(|c| !c.trim().is_empty()) {
+        Some(content) => FileFormat::for_write(path)
+            .parse(content)
+            .with_context(|| format!("解析凭据文件失败，拒绝覆盖: {:?}", path))?,
+        None => Vec::new(),
+    };
+
+    let refresh_tokens: HashSet<&str> = credentials
+        .iter()
+        .filter_map(|c| c.refresh_token.as_deref())
+        .collect();
+    let mut merged: Vec<KiroCredentials> = existing
+        .into_iter()
+        .filter(|c| match c.id {
+            Some(id) => !owned_ids.contains(&id),
+            None => !c
+                .refresh_token
+                .as_deref()
+                .is_some_and(|token| refresh_tokens.contains(token)),
+        })
+        .collect();
+    merged.extend(credentials);
+    merged.sort_by_key(|c| c.id.unwrap_or(u64::MAX));
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_token_manager_new() {
+        let config = Config::default();
+        let credentials = KiroCredentials::default();
+        let tm = TokenManager::new(config, credentials, None);
+        assert!(tm.credentials().access_token.is_none());
+    }
+
+    #[test]
+    fn test_is_token_expired_with_expired_token() {
         let mut credentials = KiroCredentials::default();
         credentials.expires_at = Some("2020-01-01T00:00:00Z".to_string());
         assert!(is_token_expired(&credentials));
@@ -2023,6 +4178,79 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_validate_credential_clean() {
+        let mut credentials = KiroCredentials::default();
+        credentials.refresh_token = Some("a".repeat(150));
+        credentials.region = Some("us-east-1".to_string());
+        credentials.machine_id = Some("f".repeat(64));
+        assert!(validate_credential(&credentials).is_empty());
+    }
+
+    #[test]
+    fn test_validate_credential_refresh_token_maybe_incomplete() {
+        let mut credentials = KiroCredentials::default();
+        credentials.refresh_token = Some("a".repeat(120));
+        assert_eq!(
+            validate_credential(&credentials),
+            vec![ValidationWarning::RefreshTokenMaybeIncomplete { length: 120 }]
+        );
+    }
+
+    #[test]
+    fn test_validate_credential_idc_missing_client_credentials() {
+        let mut credentials = KiroCredentials::default();
+        credentials.auth_method = Some("idc".to_string());
+        credentials.client_id = Some("client".to_string());
+        assert_eq!(
+            validate_credential(&credentials),
+            vec![ValidationWarning::IdcMissingClientCredentials]
+        );
+
+        credentials.client_secret = Some("secret".to_string());
+        assert!(validate_credential(&credentials).is_empty());
+    }
+
+    #[test]
+    fn test_validate_credential_invalid_region() {
+        let mut credentials = KiroCredentials::default();
+        credentials.region = Some("us east/1".to_string());
+        let warnings = validate_credential(&credentials);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code(), "invalid_region");
+    }
+
+    #[test]
+    fn test_validate_credential_token_very_stale() {
+        let mut credentials = KiroCredentials::default();
+        credentials.expires_at = Some((Utc::now() - Duration::hours(30)).to_rfc3339());
+        let warnings = validate_credential(&credentials);
+        assert!(matches!(
+            warnings.as_slice(),
+            [ValidationWarning::TokenVeryStale { hours }] if *hours >= 29
+        ));
+
+        // 过期不足 24 小时不警告
+        credentials.expires_at = Some((Utc::now() - Duration::hours(2)).to_rfc3339());
+        assert!(validate_credential(&credentials).is_empty());
+    }
+
+    #[test]
+    fn test_validate_credential_machine_id_length() {
+        let mut credentials = KiroCredentials::default();
+        credentials.machine_id = Some("abc".to_string());
+        assert_eq!(
+            validate_credential(&credentials),
+            vec![ValidationWarning::MachineIdLength { length: 3 }]
+        );
+
+        credentials.machine_id = Some("a".repeat(129));
+        assert_eq!(
+            validate_credential(&credentials),
+            vec![ValidationWarning::MachineIdLength { length: 129 }]
+        );
+    }
+
     // MultiTokenManager 测试
 
     /// 创建有效的测试凭据（refresh_token 需要至少 100 字符）
@@ -2086,21 +4314,61 @@ mod tests {
 
         // 凭据会自动分配 ID（从 1 开始）
         // 前两次失败不会禁用（使用 ID 1）
-        assert!(manager.report_failure(1));
-        assert!(manager.report_failure(1));
+        assert!(manager.report_failure(1, "claude-sonnet-4.5", None, 0));
+        assert!(manager.report_failure(1, "claude-sonnet-4.5", None, 0));
         assert_eq!(manager.available_count(), 2);
 
         // 第三次失败会禁用第一个凭据
-        assert!(manager.report_failure(1));
+        assert!(manager.report_failure(1, "claude-sonnet-4.5", None, 0));
         assert_eq!(manager.available_count(), 1);
 
         // 继续失败第二个凭据（使用 ID 2）
-        assert!(manager.report_failure(2));
-        assert!(manager.report_failure(2));
-        assert!(!manager.report_failure(2)); // 所有凭据都禁用了
+        assert!(manager.report_failure(2, "claude-sonnet-4.5", None, 0));
+        assert!(manager.report_failure(2, "claude-sonnet-4.5", None, 0));
+        assert!(!manager.report_failure(2, "claude-sonnet-4.5", None, 0)); // 所有凭据都禁用了
         assert_eq!(manager.available_count(), 0);
     }
 
+    #[tokio::test]
+    async fn test_report_failure_publishes_credential_status_event() {
+        let config = Config::default();
+        let cred1 = create_valid_test_credential();
+        let cred2 = create_valid_test_credential();
+
+        let manager =
+            MultiTokenManager::new(config, vec![cred1, cred2], None, None).unwrap();
+        let sender = crate::admin::events::channel();
+        let mut receiver = sender.subscribe();
+        manager.set_event_sender(sender, "default");
+
+        manager.report_failure(1, "claude-sonnet-4.5", None, 0);
+
+        let event = tokio::time::timeout(StdDuration::from_millis(100), receiver.recv())
+            .await
+            .expect("100ms 内应收到事件")
+            .unwrap();
+        assert_eq!(
+            event,
+            AdminEvent::CredentialStatusChanged {
+                id: 1,
+                disabled: false,
+                failure_count: 1,
+            }
+        );
+
+        // 达到失败阈值后凭据被禁用，并发布池可用数量变化
+        manager.report_failure(1, "claude-sonnet-4.5", None, 0);
+        manager.report_failure(1, "claude-sonnet-4.5", None, 0);
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+        assert!(events.contains(&AdminEvent::PoolStatusChanged {
+            id: "default".to_string(),
+            available_credentials: 1,
+        }));
+    }
+
     #[test]
     fn test_multi_token_manager_report_success() {
         let config = Config::default();
@@ -2109,18 +4377,97 @@ mod tests {
         let manager = MultiTokenManager::new(config, vec![cred], None, None).unwrap();
 
         // 失败两次（使用 ID 1）
-        manager.report_failure(1);
-        manager.report_failure(1);
+        manager.report_failure(1, "claude-sonnet-4.5", None, 0);
+        manager.report_failure(1, "claude-sonnet-4.5", None, 0);
 
         // 成功后重置计数（使用 ID 1）
-        manager.report_success(1);
+        manager.report_success(1, "claude-sonnet-4.5", 120);
 
         // 再失败两次不会禁用
-        manager.report_failure(1);
-        manager.report_failure(1);
+        manager.report_failure(1, "claude-sonnet-4.5", None, 0);
+        manager.report_failure(1, "claude-sonnet-4.5", None, 0);
         assert_eq!(manager.available_count(), 1);
     }
 
+    #[test]
+    fn test_latency_percentiles_and_histogram() {
+        let config = Config::default();
+        let cred = create_valid_test_credential();
+        let manager = MultiTokenManager::new(config, vec![cred], None, None).unwrap();
+
+        // 超出上限的最旧样本被丢弃
+        manager.report_success(1, "claude-sonnet-4.5", 10_000);
+        for latency in 1..=100 {
+            manager.report_success(1, "claude-sonnet-4.5", latency * 10);
+        }
+
+        let entry = manager.snapshot().entries.remove(0);
+        assert_eq!(entry.p50_latency_ms, Some(500));
+        assert_eq!(entry.p95_latency_ms, Some(950));
+        assert_eq!(entry.p99_latency_ms, Some(990));
+        assert_eq!(entry.avg_latency_ms, Some((10_000.0 + 50_500.0) / 101.0));
+
+        let histogram = manager.latency_histogram(1).unwrap();
+        assert_eq!(histogram.len(), 21);
+        assert_eq!(
+            histogram[0],
+            LatencyBucket {
+                start_ms: 0,
+                end_ms: Some(50),
+                count: 4
+            }
+        );
+        assert_eq!(histogram[1].count, 5);
+        assert_eq!(histogram[20].count, 1);
+        assert_eq!(histogram.iter().map(|b| b.count).sum::<usize>(), 100);
+        assert!(manager.latency_histogram(99).is_none());
+
+        // 超长响应时间不会生成大量空桶，合并到溢出桶
+        let histogram = latency_histogram(&[10, 600_000, 3_600_000]);
+        assert_eq!(histogram.len(), LATENCY_MAX_BUCKETS + 1);
+        assert_eq!(
+            histogram[LATENCY_MAX_BUCKETS],
+            LatencyBucket {
+                start_ms: LATENCY_MAX_BUCKETS as u64 * LATENCY_BUCKET_MS,
+                end_ms: None,
+                count: 2
+            }
+        );
+
+        // 无样本时分位数为空
+        assert_eq!(sorted_percentile(&[], 99.0), None);
+        assert_eq!(sorted_percentile(&[7], 99.0), Some(7));
+    }
+
+    #[test]
+    fn test_call_timeline_records_reports() {
+        let config = Config::default();
+        let cred = create_valid_test_credential();
+        let manager = MultiTokenManager::new(config, vec![cred], None, None).unwrap();
+
+        manager.report_success(1, "claude-sonnet-4.5", 100);
+        manager.report_failure(1, "claude-sonnet-4.5", Some("403 Forbidden"), 300);
+        // 未携带模型和响应时间的上报不计入时间线
+        manager.report_failure_with_time(1, Some("流式响应异常终止"), None);
+        // 不计入凭据成败的调用（如上游瞬态错误）单独记录
+        manager.record_call(1, "claude-sonnet-4.5", false, 50);
+
+        // 测试可能跨越桶边界，按所有桶汇总
+        let buckets = manager.call_timeline(1, 10, 5).unwrap();
+        let total = |f: fn(&TimelineBucket) -> u64| buckets.iter().map(f).sum::<u64>();
+        assert_eq!(total(|b| b.requests), 3);
+        assert_eq!(total(|b| b.successes), 1);
+        assert_eq!(total(|b| b.failures), 2);
+        assert!(manager.call_timeline(99, 10, 5).is_none());
+
+        // 重新加载凭据后仍存在的凭据保留时间线
+        manager
+            .replace_credentials(vec![create_valid_test_credential()])
+            .unwrap();
+        let buckets = manager.call_timeline(1, 10, 5).unwrap();
+        assert_eq!(buckets.iter().map(|b| b.requests).sum::<u64>(), 3);
+    }
+
     #[test]
     fn test_multi_token_manager_switch_to_next() {
         let config = Config::default();
@@ -2165,10 +4512,10 @@ mod tests {
 
         // 凭据会自动分配 ID（从 1 开始）
         for _ in 0..MAX_FAILURES_PER_CREDENTIAL {
-            manager.report_failure(1);
+            manager.report_failure(1, "claude-sonnet-4.5", None, 0);
         }
         for _ in 0..MAX_FAILURES_PER_CREDENTIAL {
-            manager.report_failure(2);
+            manager.report_failure(2, "claude-sonnet-4.5", None, 0);
         }
 
         assert_eq!(manager.available_count(), 0);
@@ -2179,6 +4526,284 @@ mod tests {
         assert_eq!(manager.available_count(), 2);
     }
 
+    #[tokio::test]
+    async fn test_concurrency_limit_queues_until_permit_released() {
+        let mut config = Config::default();
+        config.credential_throttle_timeout_ms = 100;
+        let mut cred = create_valid_test_credential();
+        cred.access_token = Some("t1".to_string());
+        cred.expires_at = Some((Utc::now() + Duration::hours(1)).to_rfc3339());
+        cred.max_concurrent_requests = Some(1);
+
+        let manager = Arc::new(MultiTokenManager::new(config, vec![cred], None, None).unwrap());
+        let concurrency = |manager: &MultiTokenManager| {
+            let entry = &manager.snapshot().entries[0];
+            (entry.current_concurrency, entry.max_concurrency)
+        };
+
+        let first = manager.acquire_context().await.unwrap();
+        assert_eq!(concurrency(&manager), (1, Some(1)));
+
+        // 名额用尽：等待超时后返回限流错误
+        let err = manager.acquire_context().await.err().unwrap();
+        assert!(matches!(
+            err,
+            KiroError::CredentialThrottled {
+                credential_id: 1,
+                max: 1,
+                timeout_ms: 100,
+            }
+        ));
+
+        // 排队中的请求在前一个调用结束后继续
+        let waiting = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.acquire_context().await.map(|ctx| ctx.token) }
+        });
+        tokio::time::sleep(StdDuration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        drop(first);
+        assert_eq!(waiting.await.unwrap().unwrap(), "t1");
+        assert_eq!(concurrency(&manager), (0, Some(1)));
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_wait_shares_one_deadline() {
+        let mut config = Config::default();
+        config.credential_throttle_timeout_ms = 200;
+        let creds: Vec<_> = (1..=3)
+            .map(|i| {
+                let mut cred = create_valid_test_credential();
+                cred.refresh_token = Some("a".repeat(150) + &i.to_string());
+                cred.access_token = Some(format!("t{}", i));
+                cred.expires_at = Some((Utc::now() + Duration::hours(1)).to_rfc3339());
+                cred.max_concurrent_requests = Some(1);
+                cred
+            })
+            .collect();
+        let manager = MultiTokenManager::new(config, creds, None, None).unwrap();
+
+        let mut held = Vec::new();
+        for _ in 0..3 {
+            held.push(manager.acquire_context().await.unwrap());
+        }
+
+        // 三个凭据均已满：整次选择最多等待一个超时，而不是每个凭据各等一次
+        let started = std::time::Instant::now();
+        let err = manager.acquire_context().await.err().unwrap();
+        assert!(matches!(err, KiroError::CredentialThrottled { .. }));
+        assert!(started.elapsed() < StdDuration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_report_counts() {
+        // 刷新请求指向不可连接的地址，过期凭据预热失败
+        let mut config = Config::default();
+        config.upstream_base_url = Some("http://127.0.0.1:1".to_string());
+        config.warmup_concurrency = 1;
+
+        let valid = || {
+            let mut cred = create_valid_test_credential();
+            cred.access_token = Some("token".to_string());
+            cred.expires_at = Some((Utc::now() + Duration::hours(1)).to_rfc3339());
+            cred
+        };
+        let mut expired = create_valid_test_credential();
+        expired.expires_at = Some((Utc::now() - Duration::hours(1)).to_rfc3339());
+
+        let manager = MultiTokenManager::new(
+            config,
+            vec![valid(), expired, valid(), valid()],
+            None,
+            None,
+        )
+        .unwrap();
+        // 已禁用的凭据不参与预热
+        manager.set_disabled(4, true, "admin").unwrap();
+        assert!(manager.warmup_report().is_none());
+
+        let report = manager.warm_up().await;
+        assert_eq!(report.total(), 3);
+        assert_eq!(report.ready(), 2);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.refreshed(), 0);
+        assert_eq!(report.skipped(), 2);
+        assert_eq!(
+            report.entries.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert!(report.entries[1].result.is_err());
+        assert!(report.entries[1].refreshed);
+        assert_eq!(manager.warmup_report().unwrap().failed(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_user_fairness_caps_credential_share() {
+        let mut config = Config::default();
+        config.user_fairness_enabled = true;
+        config.user_max_share = 0.5;
+
+        let valid = || {
+            let mut cred = create_valid_test_credential();
+            cred.access_token = Some("token".to_string());
+            cred.expires_at = Some((Utc::now() + Duration::hours(1)).to_rfc3339());
+            cred
+        };
+        let manager =
+            MultiTokenManager::new(config, vec![valid(), valid(), valid(), valid()], None, None)
+                .unwrap();
+
+        let alice = crate::kiro::fairness::hash_user_id("user_alice");
+        let bob = crate::kiro::fairness::hash_user_id("user_bob");
+        let mut used: HashMap<&str, HashSet<u64>> = HashMap::new();
+        for i in 0..40 {
+            let user = if i % 4 == 3 { &bob } else { &alice };
+            let session = format!("session_{}", i);
+            let ctx = manager
+                .acquire_context_for_user(Some(&session), Some(user))
+                .await
+                .unwrap();
+            used.entry(user.as_str()).or_default().insert(ctx.id);
+        }
+
+        // 4 个凭据 × 0.5：每个用户最多占用 2 个凭据
+        assert_eq!(used[alice.as_str()].len(), 2);
+        assert!(used[bob.as_str()].len() <= 2);
+
+        let counts = manager.user_session_counts();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[0].user_hash, alice);
+        assert_eq!(counts[0].sessions, 30);
+        assert_eq!(counts[1].sessions, 10);
+        assert!(counts.iter().all(|c| c.credentials <= 2));
+    }
+
+    #[tokio::test]
+    async fn test_user_fairness_disabled_by_default() {
+        let valid = || {
+            let mut cred = create_valid_test_credential();
+            cred.access_token = Some("token".to_string());
+            cred.expires_at = Some((Utc::now() + Duration::hours(1)).to_rfc3339());
+            cred
+        };
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![valid(), valid(), valid(), valid()],
+            None,
+            None,
+        )
+        .unwrap();
+
+        let user = crate::kiro::fairness::hash_user_id("user_alice");
+        let mut used = HashSet::new();
+        for i in 0..8 {
+            let session = format!("session_{}", i);
+            let ctx = manager
+                .acquire_context_for_user(Some(&session), Some(&user))
+                .await
+                .unwrap();
+            used.insert(ctx.id);
+        }
+        assert_eq!(used.len(), 4);
+        assert!(manager.user_session_counts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stickiness_outcomes_recorded() {
+        let valid = || {
+            let mut cred = create_valid_test_credential();
+            cred.access_token = Some("token".to_string());
+            cred.expires_at = Some((Utc::now() + Duration::hours(1)).to_rfc3339());
+            cred
+        };
+        let manager =
+            MultiTokenManager::new(Config::default(), vec![valid(), valid()], None, None).unwrap();
+
+        // 首次绑定 -> 命中 -> 凭据禁用后重新绑定
+        let first = manager
+            .acquire_context_for_session(Some("session_a"))
+            .await
+            .unwrap();
+        manager
+            .acquire_context_for_session(Some("session_a"))
+            .await
+            .unwrap();
+        manager.set_disabled(first.id, true, "admin").unwrap();
+        let rebound = manager
+            .acquire_context_for_session(Some("session_a"))
+            .await
+            .unwrap();
+        assert_ne!(rebound.id, first.id);
+
+        // 无会话标识的请求不计入命中统计
+        manager.acquire_context().await.unwrap();
+        manager.record_session_source(SessionIdSource::Metadata);
+        manager.record_session_source(SessionIdSource::None);
+
+        let stats = manager.stickiness_snapshot();
+        assert_eq!((stats.hits, stats.rebinds, stats.new_bindings), (1, 1, 1));
+        assert_eq!(stats.sources.metadata, 1);
+        assert_eq!(stats.sources.none, 1);
+        assert_eq!(stats.system_hash_ratio, Some(0.0));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_first_requests_share_session_binding() {
+        let valid = || {
+            let mut cred = create_valid_test_credential();
+            cred.access_token = Some("token".to_string());
+            cred.expires_at = Some((Utc::now() + Duration::hours(1)).to_rfc3339());
+            cred
+        };
+        let manager = Arc::new(
+            MultiTokenManager::new(
+                Config::default(),
+                vec![valid(), valid(), valid(), valid()],
+                None,
+                None,
+            )
+            .unwrap(),
+        );
+
+        // 同一新会话的 8 个请求同时到达，全部绑定到同一凭据
+        let barrier = Arc::new(tokio::sync::Barrier::new(8));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let manager = manager.clone();
+                let barrier = barrier.clone();
+                tokio::spawn(async move {
+                    barrier.wait().await;
+                    manager
+                        .acquire_context_for_session(Some("session_fresh"))
+                        .await
+                        .unwrap()
+                        .id
+                })
+            })
+            .collect();
+        let mut ids = HashSet::new();
+        for handle in handles {
+            ids.insert(handle.await.unwrap());
+        }
+        assert_eq!(ids.len(), 1);
+        let bound = *ids.iter().next().unwrap();
+
+        let stats = manager.stickiness_snapshot();
+        assert_eq!(stats.new_bindings, 1);
+        assert_eq!(stats.hits, 7);
+        assert_eq!(stats.binding_races.duplicates, 0);
+        assert!(manager.session_bind_locks.is_empty());
+
+        // 等待超时后自行选择的请求不覆盖已有绑定
+        let other = if bound == 1 { 2 } else { 1 };
+        assert_eq!(
+            manager.bind_session("session_fresh", other, None),
+            Some(bound)
+        );
+        assert_eq!(manager.bind_session("session_fresh", bound, None), None);
+        assert_eq!(manager.session_map.read().get("session_fresh"), Some(bound));
+    }
+
     #[test]
     fn test_multi_token_manager_report_quota_exhausted() {
         let config = Config::default();
@@ -2211,15 +4836,527 @@ mod tests {
         manager.report_quota_exhausted(2);
         assert_eq!(manager.available_count(), 0);
 
-        let err = manager.acquire_context().await.err().unwrap().to_string();
+        let err = manager.acquire_context().await.err().unwrap();
         assert!(
-            err.contains("所有凭据均已禁用"),
+            matches!(
+                err,
+                KiroError::AllCredentialsExhausted {
+                    available: 0,
+                    total: 2
+                }
+            ),
             "错误应提示所有凭据禁用，实际: {}",
             err
         );
         assert_eq!(manager.available_count(), 0);
     }
 
+    #[test]
+    fn test_quota_reset_reenables_credentials() {
+        let config = Config::default();
+        let cred1 = create_valid_test_credential();
+        let cred2 = create_valid_test_credential();
+
+        let manager =
+            MultiTokenManager::new(config, vec![cred1, cred2], None, None).unwrap();
+
+        manager.report_quota_exhausted(1);
+        // 仍有可用凭据时不返回重置时间
+        manager.set_quota_reset_at(1, 2000);
+        assert_eq!(manager.earliest_quota_reset_at(), None);
+
+        manager.report_quota_exhausted(2);
+        // 凭据 #2 重置时间未知，取已知的最早时间
+        assert_eq!(manager.earliest_quota_reset_at(), Some(2000));
+        manager.set_quota_reset_at(2, 1000);
+        assert_eq!(manager.earliest_quota_reset_at(), Some(1000));
+
+        // 未到重置时间不启用
+        assert!(manager.reenable_quota_reset_credentials(999).is_empty());
+        assert_eq!(manager.available_count(), 0);
+
+        assert_eq!(manager.reenable_quota_reset_credentials(1000), vec![2]);
+        assert_eq!(manager.available_count(), 1);
+        assert_eq!(manager.earliest_quota_reset_at(), None);
+    }
+
+    #[test]
+    fn test_quota_reset_ignores_manually_disabled() {
+        let config = Config::default();
+        let cred = create_valid_test_credential();
+
+        let manager = MultiTokenManager::new(config, vec![cred], None, None).unwrap();
+
+        manager.set_disabled(1, true, "admin").unwrap();
+        manager.set_quota_reset_at(1, 1000);
+        assert_eq!(manager.earliest_quota_reset_at(), None);
+        assert!(manager.reenable_quota_reset_credentials(2000).is_empty());
+        assert_eq!(manager.available_count(), 0);
+    }
+
+    #[test]
+    fn test_credential_history_keeps_last_five_and_rolls_back() {
+        let cred = create_valid_test_credential();
+        let manager = MultiTokenManager::new(Config::default(), vec![cred], None, None).unwrap();
+
+        // 6 次变更：优先级 1..=5，然后禁用
+        for priority in 1..=5 {
+            manager.set_priority(1, priority).unwrap();
+        }
+        manager.set_disabled(1, true, "admin").unwrap();
+
+        // 只保留最近 5 个变更前状态（最旧的优先级 0 已被丢弃），最新的在前
+        let history = manager.credential_history(1).unwrap();
+        let states: Vec<(u32, bool)> = history.iter().map(|s| (s.priority, s.disabled)).collect();
+        assert_eq!(
+            states,
+            vec![(5, false), (4, false), (3, false), (2, false), (1, false)]
+        );
+
+        // 回滚到版本 1：撤销禁用
+        manager.rollback_credential(1, 1, "admin").unwrap();
+        assert_eq!(manager.available_count(), 1);
+        assert_eq!(manager.snapshot().entries[0].priority, 5);
+
+        // 回滚本身记录历史，版本 1 为回滚前的禁用状态
+        let history = manager.credential_history(1).unwrap();
+        assert_eq!(history.len(), CREDENTIAL_HISTORY_LIMIT);
+        assert!(history[0].disabled);
+        assert_eq!(history[0].priority, 5);
+
+        // 回滚到最旧的版本：优先级 2
+        manager.rollback_credential(1, 5, "admin").unwrap();
+        let entry = &manager.snapshot().entries[0];
+        assert_eq!(entry.priority, 2);
+        assert!(!entry.disabled);
+
+        // 回滚恢复备注和标签，Token 保持当前值
+        manager.set_notes(1, Some("备注".to_string())).unwrap();
+        manager
+            .update_tags(1, vec!["team-a".to_string()], &[])
+            .unwrap();
+        manager.entries.lock()[0].credentials.access_token = Some("rotated".to_string());
+        manager.rollback_credential(1, 2, "admin").unwrap();
+        let credentials = manager.entries.lock()[0].credentials.clone();
+        assert_eq!(credentials.notes, None);
+        assert!(credentials.tags.is_empty());
+        assert_eq!(credentials.access_token.as_deref(), Some("rotated"));
+
+        assert!(manager.rollback_credential(1, 0, "admin").is_err());
+        assert!(manager.rollback_credential(1, 6, "admin").is_err());
+        assert!(manager.rollback_credential(2, 1, "admin").is_err());
+        assert!(manager.credential_history(2).is_none());
+    }
+
+    #[test]
+    fn test_disable_history_records_transitions_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.json");
+        let credentials: Vec<KiroCredentials> = (1..=2)
+            .map(|id| {
+                let mut cred = create_valid_test_credential();
+                cred.id = Some(id);
+                cred.machine_id = Some("a".repeat(64));
+                cred
+            })
+            .collect();
+        std::fs::write(&path, serde_json::to_string(&credentials).unwrap()).unwrap();
+        let manager =
+            MultiTokenManager::new(Config::default(), credentials, None, Some(path.clone()))
+                .unwrap();
+
+        // 手动禁用/启用记录操作者；额度用尽和额度重置记录为 system
+        manager.set_disabled(1, true, "admin:sk-a***").unwrap();
+        manager.set_disabled(1, false, "admin:sk-a***").unwrap();
+        manager.report_quota_exhausted(1);
+        manager.set_quota_reset_at(1, 100);
+        assert_eq!(manager.reenable_quota_reset_credentials(200), vec![1]);
+        // 未禁用时启用不记录
+        manager.set_disabled(1, false, "admin:sk-a***").unwrap();
+
+        let history: Vec<(bool, String, String)> = manager
+            .disable_history(1)
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.disabled, t.reason, t.actor))
+            .collect();
+        let record = |disabled: bool, reason: &str, actor: &str| {
+            (disabled, reason.to_string(), actor.to_string())
+        };
+        assert_eq!(
+            history,
+            vec![
+                record(false, "额度已重置", SYSTEM_ACTOR),
+                record(true, "额度已用尽", SYSTEM_ACTOR),
+                record(false, "手动启用", "admin:sk-a***"),
+                record(true, "手动禁用", "admin:sk-a***"),
+            ]
+        );
+        let entry = manager.snapshot().entries.remove(0);
+        assert_eq!(entry.flap_count, 2);
+        assert!(entry.disabled_at.is_some());
+        assert!(manager.disable_history(3).is_none());
+
+        // 只保留最近 20 条
+        for _ in 0..12 {
+            manager.set_disabled(1, true, "admin:sk-a***").unwrap();
+            manager.set_disabled(1, false, "admin:sk-a***").unwrap();
+        }
+        manager.set_disabled(1, true, "admin:sk-a***").unwrap();
+        let history = manager.disable_history(1).unwrap();
+        assert_eq!(history.len(), DISABLE_HISTORY_LIMIT);
+        assert!(history[0].disabled);
+        let disabled_at = history[0].timestamp;
+
+        // 最近一次禁用时间和原因写入文件，重启后保留
+        let saved: Vec<KiroCredentials> =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved[0].disabled_at, Some(disabled_at));
+        assert_eq!(saved[0].disabled_reason.as_deref(), Some("手动禁用"));
+        assert_eq!(saved[1].disabled_at, None);
+
+        let reloaded = MultiTokenManager::new(Config::default(), saved, None, None).unwrap();
+        assert_eq!(
+            reloaded.snapshot().entries[0].disabled_at,
+            Some(disabled_at.to_rfc3339())
+        );
+    }
+
+    #[test]
+    fn test_set_notes_updates_snapshot_and_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.json");
+        let mut cred = create_valid_test_credential();
+        cred.id = Some(1);
+        cred.machine_id = Some("a".repeat(64));
+        std::fs::write(&path, serde_json::to_string(&vec![&cred]).unwrap()).unwrap();
+
+        let manager =
+            MultiTokenManager::new(Config::default(), vec![cred], None, Some(path.clone()))
+                .unwrap();
+        let notes_in_file = || {
+            let saved: Vec<KiroCredentials> =
+                serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            saved[0].notes.clone()
+        };
+
+        // 设置
+        manager
+            .set_notes(1, Some("用于预发环境".to_string()))
+            .unwrap();
+        assert_eq!(
+            manager.snapshot().entries[0].notes.as_deref(),
+            Some("用于预发环境")
+        );
+        assert_eq!(notes_in_file().as_deref(), Some("用于预发环境"));
+
+        // 更新
+        manager
+            .set_notes(1, Some("已迁移到生产".to_string()))
+            .unwrap();
+        assert_eq!(notes_in_file().as_deref(), Some("已迁移到生产"));
+
+        // 清除：文件中不再包含 notes 字段
+        manager.set_notes(1, None).unwrap();
+        assert_eq!(manager.snapshot().entries[0].notes, None);
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("notes"));
+
+        assert!(manager.set_notes(2, None).is_err());
+    }
+
+    #[test]
+    fn test_update_tags_persists_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.json");
+        let mut cred = create_valid_test_credential();
+        cred.id = Some(1);
+        cred.machine_id = Some("a".repeat(64));
+        cred.tags = vec!["team-a".to_string()];
+        std::fs::write(&path, serde_json::to_string(&vec![&cred]).unwrap()).unwrap();
+
+        let manager =
+            MultiTokenManager::new(Config::default(), vec![cred], None, Some(path.clone()))
+                .unwrap();
+        let tags_in_file = || {
+            let saved: Vec<KiroCredentials> =
+                serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            saved[0].tags.clone()
+        };
+
+        let tags = manager
+            .update_tags(1, vec!["vendor-x".to_string(), "TEAM-A".to_string()], &[])
+            .unwrap();
+        assert_eq!(tags, vec!["team-a", "vendor-x"]);
+        assert_eq!(manager.snapshot().entries[0].tags, tags);
+        assert_eq!(tags_in_file(), tags);
+
+        // 其他字段更新后标签仍保留
+        manager.set_notes(1, Some("备注".to_string())).unwrap();
+        assert_eq!(tags_in_file(), tags);
+
+        let tags = manager
+            .update_tags(1, Vec::new(), &["Team-A".to_string()])
+            .unwrap();
+        assert_eq!(tags, vec!["vendor-x"]);
+        assert_eq!(tags_in_file(), tags);
+
+        assert!(manager.update_tags(2, Vec::new(), &[]).is_err());
+    }
+
+    #[test]
+    fn test_failure_classification_network_error() {
+        for message in [
+            "error sending request for url (https://q.us-east-1.amazonaws.com)",
+            "operation timed out",
+            "503 Service Unavailable",
+            "网络连接中断",
+        ] {
+            assert_eq!(
+                FailureClass::from_error_message(message),
+                FailureClass::NetworkError,
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_failure_classification_auth_error() {
+        for message in [
+            "401 Unauthorized",
+            "403 Forbidden {\"message\":\"AccessDeniedException\"}",
+            "refreshToken 无效或已过期",
+            "invalid_grant",
+        ] {
+            assert_eq!(
+                FailureClass::from_error_message(message),
+                FailureClass::AuthError,
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_failure_classification_quota_exceeded() {
+        for message in [
+            "402 {\"reason\":\"MONTHLY_REQUEST_COUNT\"}",
+            "Quota exceeded for this account",
+        ] {
+            assert_eq!(
+                FailureClass::from_error_message(message),
+                FailureClass::QuotaExceeded,
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_failure_classification_token_expired() {
+        for message in [
+            "The bearer token included in the request is expired",
+            "刷新后的 Token 仍然无效或已过期",
+        ] {
+            assert_eq!(
+                FailureClass::from_error_message(message),
+                FailureClass::TokenExpired,
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_failure_classification_unknown() {
+        assert_eq!(
+            FailureClass::from_error_message("流式响应异常终止"),
+            FailureClass::Unknown
+        );
+        assert_eq!(FailureClass::from_error_message(""), FailureClass::Unknown);
+    }
+
+    #[test]
+    fn test_snapshot_exposes_failure_details() {
+        let mut cred1 = create_valid_test_credential();
+        cred1.id = Some(1);
+        let mut cred2 = create_valid_test_credential();
+        cred2.id = Some(2);
+        let manager =
+            MultiTokenManager::new(Config::default(), vec![cred1, cred2], None, None).unwrap();
+
+        for _ in 0..MAX_FAILURES_PER_CREDENTIAL {
+            manager.report_failure(1, "claude-sonnet-4.5", Some("403 Forbidden"), 0);
+        }
+        manager.report_quota_exhausted(2);
+
+        let snapshot = manager.snapshot();
+        let entry = |id| snapshot.entries.iter().find(|e| e.id == id).unwrap();
+        assert_eq!(
+            entry(1).disabled_reason.as_deref(),
+            Some("连续失败次数过多")
+        );
+        assert_eq!(entry(1).last_error.as_deref(), Some("403 Forbidden"));
+        assert_eq!(
+            entry(1).failure_classification,
+            Some(FailureClass::AuthError)
+        );
+        // 没有错误信息时按禁用原因分类
+        assert_eq!(entry(2).disabled_reason.as_deref(), Some("额度已用尽"));
+        assert_eq!(entry(2).last_error, None);
+        assert_eq!(
+            entry(2).failure_classification,
+            Some(FailureClass::QuotaExceeded)
+        );
+
+        // 重新启用后清除失败信息
+        manager.reset_and_enable(1, "admin").unwrap();
+        let snapshot = manager.snapshot();
+        let entry = snapshot.entries.iter().find(|e| e.id == 1).unwrap();
+        assert_eq!(entry.disabled_reason, None);
+        assert_eq!(entry.last_error, None);
+        assert_eq!(entry.failure_classification, None);
+    }
+
+    #[test]
+    fn test_inferred_region_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.json");
+        let mut cred = create_valid_test_credential();
+        cred.id = Some(1);
+        cred.machine_id = Some("a".repeat(64));
+        cred.profile_arn = Some("arn:aws:codewhisperer:eu-west-1:123456789012:profile/ABC".into());
+        std::fs::write(&path, serde_json::to_string(&vec![&cred]).unwrap()).unwrap();
+
+        let manager =
+            MultiTokenManager::new(Config::default(), vec![cred], None, Some(path.clone()))
+                .unwrap();
+        assert_eq!(
+            manager.entries.lock()[0].credentials.region.as_deref(),
+            Some("eu-west-1")
+        );
+
+        manager.set_notes(1, Some("触发持久化".to_string())).unwrap();
+        let saved: Vec<KiroCredentials> =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved[0].region.as_deref(), Some("eu-west-1"));
+    }
+
+    #[test]
+    fn test_persist_keeps_credentials_of_other_pools() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.json");
+        let mut creds = Vec::new();
+        for id in 1..=3 {
+            let mut cred = create_valid_test_credential();
+            cred.id = Some(id);
+            cred.refresh_token = Some(format!("{}{}", "a".repeat(150), id));
+            cred.machine_id = Some("a".repeat(64));
+            creds.push(cred);
+        }
+        std::fs::write(&path, serde_json::to_string(&creds).unwrap()).unwrap();
+
+        // 两个池共享同一凭据文件
+        let pool_a = MultiTokenManager::new(
+            Config::default(),
+            vec![creds[0].clone(), creds[2].clone()],
+            None,
+            Some(path.clone()),
+        )
+        .unwrap();
+        let pool_b = MultiTokenManager::new(
+            Config::default(),
+            vec![creds[1].clone()],
+            None,
+            Some(path.clone()),
+        )
+        .unwrap();
+
+        pool_a.set_priority(3, 7).unwrap();
+        pool_b.set_notes(2, Some("池 B".to_string())).unwrap();
+        pool_a.set_disabled(1, true, "admin").unwrap();
+        pool_a.delete_credential(1).unwrap();
+
+        let saved: Vec<KiroCredentials> =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let ids: Vec<Option<u64>> = saved.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![Some(2), Some(3)]);
+        assert_eq!(saved[0].notes.as_deref(), Some("池 B"));
+        assert_eq!(saved[1].priority, 7);
+
+        // 管理操作写入变更记录
+        let journal = std::fs::read_to_string(dir.path().join("changes.log")).unwrap();
+        assert_eq!(journal.lines().count(), 4);
+        assert!(journal.contains("删除凭据 #1"));
+    }
+
+    #[test]
+    fn test_persist_writes_dir_credentials_to_source_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.json");
+        let team_dir = dir.path().join("team-a");
+        std::fs::create_dir_all(&team_dir).unwrap();
+        let source = team_dir.join("credentials.json");
+
+        let mut primary = create_valid_test_credential();
+        primary.id = Some(1);
+        primary.machine_id = Some("a".repeat(64));
+        std::fs::write(&path, serde_json::to_string(&vec![&primary]).unwrap()).unwrap();
+        let mut from_dir = primary.clone();
+        from_dir.id = Some(2);
+        from_dir.refresh_token = Some("b".repeat(150));
+        std::fs::write(&source, serde_json::to_string(&vec![&from_dir]).unwrap()).unwrap();
+        from_dir.source = Some(source.clone());
+
+        let manager = MultiTokenManager::new(
+            Config::default(),
+            vec![primary, from_dir],
+            None,
+            Some(path.clone()),
+        )
+        .unwrap();
+        let saved = |path: &std::path::Path| -> Vec<KiroCredentials> {
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+        };
+
+        manager.set_notes(2, Some("团队 A".to_string())).unwrap();
+        let primary_saved = saved(&path);
+        assert_eq!(primary_saved.len(), 1);
+        assert_eq!(primary_saved[0].id, Some(1));
+        let source_saved = saved(&source);
+        assert_eq!(source_saved.len(), 1);
+        assert_eq!(source_saved[0].notes.as_deref(), Some("团队 A"));
+
+        // 删除来源文件中的最后一个凭据后，来源文件同步移除
+        manager.set_disabled(2, true, "admin").unwrap();
+        manager.delete_credential(2).unwrap();
+        assert!(saved(&source).is_empty());
+        assert_eq!(saved(&path).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_wait_until_available_wakes_on_reenable() {
+        let config = Config::default();
+        let cred = create_valid_test_credential();
+
+        let manager =
+            Arc::new(MultiTokenManager::new(config, vec![cred], None, None).unwrap());
+        manager.report_quota_exhausted(1);
+        manager.set_quota_reset_at(1, 1000);
+
+        // 超时前无凭据恢复
+        assert!(!manager.wait_until_available(StdDuration::from_millis(20)).await);
+
+        let waiter = {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.wait_until_available(StdDuration::from_secs(5)).await })
+        };
+        tokio::time::sleep(StdDuration::from_millis(20)).await;
+        manager.reenable_quota_reset_credentials(1000);
+
+        assert!(waiter.await.unwrap());
+    }
+
     // ============ 凭据级 Region 优先级测试 ============
 
     /// 辅助函数：获取 OIDC 刷新使用的 region（用于测试）
@@ -2230,6 +5367,152 @@ mod tests {
         credentials.region.as_ref().unwrap_or(&config.region)
     }
 
+    #[test]
+    fn test_credential_settings_fallback_layers() {
+        let mut config = Config::default();
+        config.region = "us-west-2".to_string();
+        let proxy = |url: &str| ProxyConfig::new(url);
+        let credential = |id: u64, configured: bool| KiroCredentials {
+            id: Some(id),
+            refresh_token: Some(format!("{}{}", id, "r".repeat(150))),
+            region: configured.then(|| "ap-southeast-1".to_string()),
+            auth_method: configured.then(|| "idc".to_string()),
+            proxy_url: configured.then(|| "http://credential:8080".to_string()),
+            ..Default::default()
+        };
+        let manager = MultiTokenManager::new(
+            config,
+            vec![credential(1, true), credential(2, false)],
+            Some(proxy("http://global:8080")),
+            None,
+        )
+        .unwrap();
+        let settings = |id: u64| {
+            let creds = manager
+                .entries
+                .lock()
+                .iter()
+                .find(|e| e.id == id)
+                .map(|e| e.credentials.clone())
+                .unwrap();
+            manager.resolve_settings(&creds)
+        };
+
+        // 全局配置：池未设置默认值
+        let fallback = settings(2);
+        assert_eq!(fallback.region, "us-west-2");
+        assert_eq!(fallback.region_source, SettingSource::Global);
+        assert_eq!(fallback.auth_method, None);
+        assert_eq!(fallback.proxy, Some(proxy("http://global:8080")));
+        assert_eq!(fallback.proxy_source, Some(SettingSource::Global));
+
+        // 池默认值
+        manager.set_credential_defaults(CredentialDefaults {
+            region: Some("eu-central-1".to_string()),
+            auth_method: Some("social".to_string()),
+            proxy: Some(proxy("http://pool:8080")),
+            machine_id: None,
+        });
+        let inherited = settings(2);
+        assert_eq!(inherited.region, "eu-central-1");
+        assert_eq!(inherited.region_source, SettingSource::Pool);
+        assert_eq!(inherited.auth_method.as_deref(), Some("social"));
+        assert_eq!(inherited.proxy, Some(proxy("http://pool:8080")));
+        assert_eq!(inherited.proxy_source, Some(SettingSource::Pool));
+
+        // 凭据自身配置优先
+        let own = settings(1);
+        assert_eq!(own.region, "ap-southeast-1");
+        assert_eq!(own.region_source, SettingSource::Credential);
+        assert_eq!(own.auth_method.as_deref(), Some("idc"));
+        assert_eq!(own.proxy, Some(proxy("http://credential:8080")));
+        assert_eq!(own.proxy_source, Some(SettingSource::Credential));
+
+        // 池默认值只体现在解析结果和快照中，不写入凭据
+        let snapshot = manager.snapshot();
+        let entry = snapshot.entries.iter().find(|e| e.id == 2).unwrap();
+        assert_eq!(entry.region, None);
+        assert_eq!(entry.effective_region, "eu-central-1");
+        assert_eq!(entry.region_source, SettingSource::Pool);
+        assert_eq!(entry.proxy_source, Some(SettingSource::Pool));
+        let resolved = manager.resolve_credentials(&credential(2, false));
+        assert_eq!(resolved.region.as_deref(), Some("eu-central-1"));
+        assert_eq!(resolved.auth_method.as_deref(), Some("social"));
+        assert!(
+            manager
+                .entries
+                .lock()
+                .iter()
+                .all(|e| e.id == 1 || e.credentials.region.is_none())
+        );
+    }
+
+    #[test]
+    fn test_machine_id_fallback_layers() {
+        let own = "1".repeat(64);
+        let pool = "2".repeat(64);
+        let global = "3".repeat(64);
+        let credential = |id: u64, machine_id: Option<&str>| KiroCredentials {
+            id: Some(id),
+            refresh_token: Some(format!("{}{}", id, "r".repeat(150))),
+            machine_id: machine_id.map(String::from),
+            ..Default::default()
+        };
+        let config = Config {
+            machine_id: Some(global.clone()),
+            ..Config::default()
+        };
+        let manager = MultiTokenManager::with_credential_defaults(
+            config,
+            vec![credential(1, Some(&own)), credential(2, None)],
+            CredentialDefaults {
+                machine_id: Some(pool.clone()),
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        let effective = |id: u64| {
+            let creds = manager
+                .entries
+                .lock()
+                .iter()
+                .find(|e| e.id == id)
+                .map(|e| e.credentials.clone())
+                .unwrap();
+            machine_id::generate_from_credentials(
+                &manager.resolve_credentials(&creds),
+                manager.config(),
+            )
+        };
+
+        // 凭据自身配置优先，其次为池默认值
+        assert_eq!(effective(1), Some(own));
+        assert_eq!(effective(2), Some(pool));
+
+        // 池默认值清除后回退到全局配置；池或全局配置了 machineId 时不写入凭据
+        manager.set_credential_defaults(CredentialDefaults::default());
+        assert_eq!(effective(2), Some(global));
+        assert!(
+            manager
+                .entries
+                .lock()
+                .iter()
+                .all(|e| e.id == 1 || e.credentials.machine_id.is_none())
+        );
+
+        // 都未配置时由 refreshToken 生成，并固定到凭据中（避免 refreshToken 轮换后变化）
+        let manager =
+            MultiTokenManager::new(Config::default(), vec![credential(2, None)], None, None)
+                .unwrap();
+        let generated = manager.entries.lock()[0].credentials.machine_id.clone();
+        assert_eq!(
+            generated,
+            machine_id::generate_from_credentials(&credential(2, None), &Config::default())
+        );
+    }
+
     #[test]
     fn test_credential_region_priority_uses_credential_region() {
         // 凭据配置了 region 时，应使用凭据的 region
@@ -2311,6 +5594,120 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_refresh_timeout_is_distinct() {
+        use crate::kiro::error::TimeoutKind;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/refreshToken"))
+            .respond_with(ResponseTemplate::new(200).set_delay(StdDuration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let config = Config {
+            upstream_base_url: Some(server.uri()),
+            connect_timeout_secs: 5,
+            refresh_request_timeout_secs: 1,
+            ..Config::default()
+        };
+        let credentials = KiroCredentials {
+            refresh_token: Some("a".repeat(150)),
+            ..KiroCredentials::default()
+        };
+
+        let started = std::time::Instant::now();
+        let error = refresh_token(&credentials, &config, None).await.unwrap_err();
+        assert!(started.elapsed() < StdDuration::from_secs(4));
+        assert!(matches!(
+            KiroError::from_refresh_error(1, error),
+            KiroError::RefreshTimeout {
+                credential_id: 1,
+                kind: TimeoutKind::Request,
+            }
+        ));
+    }
+
+    #[test]
+    fn test_clone_credential_is_independent() {
+        let original = KiroCredentials {
+            id: Some(1),
+            access_token: Some("original-access-token".to_string()),
+            refresh_token: Some("r".repeat(150)),
+            expires_at: Some((Utc::now() + Duration::hours(1)).to_rfc3339()),
+            auth_method: Some("idc".to_string()),
+            client_id: Some("client".to_string()),
+            client_secret: Some("secret".to_string()),
+            machine_id: Some("m".repeat(64)),
+            priority: 1,
+            pool_id: Some("alpha".to_string()),
+            success_count: 42,
+            ..KiroCredentials::default()
+        };
+        let social = KiroCredentials {
+            id: Some(2),
+            refresh_token: Some("s".repeat(150)),
+            auth_method: Some("social".to_string()),
+            ..KiroCredentials::default()
+        };
+        // 上游地址不可达：克隆时不应发起刷新
+        let config = Config {
+            upstream_base_url: Some("http://127.0.0.1:9".to_string()),
+            ..Config::default()
+        };
+        let manager = MultiTokenManager::new(config, vec![original, social], None, None).unwrap();
+
+        let new_id = manager
+            .clone_credential(
+                1,
+                CredentialCloneOverrides {
+                    priority: Some(5),
+                    region: Some("eu-west-1".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(new_id, 3);
+        assert!(manager.shares_refresh_token(1, 3));
+
+        let snapshot = manager.snapshot();
+        let entry = |id: u64| snapshot.entries.iter().find(|e| e.id == id).unwrap();
+        assert_eq!(entry(3).priority, 5);
+        assert_eq!(entry(3).region.as_deref(), Some("eu-west-1"));
+        assert_eq!(entry(3).pool_id.as_deref(), Some("alpha"));
+        assert_eq!(entry(3).success_count, 0);
+        assert_eq!(entry(3).token_refresh_count, 0);
+        assert_eq!(entry(1).success_count, 42);
+        let access_token = manager.entries.lock()[2].credentials.access_token.clone();
+        assert_eq!(access_token.as_deref(), Some("original-access-token"));
+
+        // 修改克隆不影响原凭据
+        manager.set_disabled(3, true, SYSTEM_ACTOR).unwrap();
+        let snapshot = manager.snapshot();
+        assert!(snapshot.entries.iter().any(|e| e.id == 3 && e.disabled));
+        assert!(snapshot.entries.iter().any(|e| e.id == 1 && !e.disabled));
+
+        // Social 凭据刷新会轮换 refreshToken，拒绝克隆
+        let err = manager
+            .clone_credential(2, CredentialCloneOverrides::default())
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<KiroError>(),
+            Some(KiroError::RotatingRefreshToken(2))
+        ));
+
+        let err = manager
+            .clone_credential(99, CredentialCloneOverrides::default())
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<KiroError>(),
+            Some(KiroError::CredentialNotFound(99))
+        ));
+        assert_eq!(manager.total_count(), 3);
+    }
+
     #[test]
     fn test_api_call_still_uses_config_region() {
         // 验证 API 调用（如 getUsageLimits）仍使用 config.region
@@ -2343,4 +5740,46 @@ mod tests {
         // 空字符串被视为已设置，不会回退到 config
         assert_eq!(region, "");
     }
+
+    #[test]
+    fn test_session_cache_uses_config_and_resize_keeps_sessions() {
+        let config = Config {
+            session_cache_max_capacity: 100,
+            session_cache_ttl_secs: 60,
+            ..Config::default()
+        };
+        let manager =
+            MultiTokenManager::new(config, vec![create_valid_test_credential()], None, None)
+                .unwrap();
+        let snapshot = manager.snapshot();
+        assert_eq!(snapshot.session_cache_capacity, 100);
+        assert_eq!(snapshot.session_cache_ttl_secs, 60);
+
+        for i in 0..10 {
+            manager
+                .session_map
+                .read()
+                .insert(format!("session-{}", i), 1);
+        }
+        manager.session_map.read().run_pending_tasks();
+        assert_eq!(manager.snapshot().session_cache_size, 10);
+
+        // 缩容：最多保留新容量条会话
+        manager.set_session_cache_config(5, 120);
+        let snapshot = manager.snapshot();
+        assert_eq!(snapshot.session_cache_capacity, 5);
+        assert_eq!(snapshot.session_cache_ttl_secs, 120);
+        assert_eq!(snapshot.session_cache_size, 5);
+
+        // 扩容：现有会话全部保留，粘性映射不变
+        manager.set_session_cache_config(1000, 120);
+        let session_map = manager.session_map.read();
+        session_map.run_pending_tasks();
+        assert_eq!(session_map.entry_count(), 5);
+        assert!(
+            session_map
+                .iter()
+                .all(|(_, credential_id)| credential_id == 1)
+        );
+    }
 }
@@ -0,0 +1,364 @@
+//! 上游错误解析与分类
+//!
+//! 将上游错误响应体解析为结构化字段，并按状态码与错误代码分类，
+//! 凭据禁用/故障转移决策基于分类结果而不是错误文本的子串匹配。
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::kiro::error::{KiroError, ProviderError};
+
+/// 额度用尽的原因代码
+const REASON_MONTHLY_REQUEST_COUNT: &str = "MONTHLY_REQUEST_COUNT";
+
+/// 限流类错误代码
+const THROTTLED_CODES: &[&str] = &["ThrottlingException", "TooManyRequestsException"];
+
+/// 认证失效类错误代码（含 OAuth/OIDC 错误码）
+const AUTH_EXPIRED_CODES: &[&str] = &[
+    "ExpiredTokenException",
+    "UnauthorizedException",
+    "AccessDeniedException",
+    "InvalidGrantException",
+    "UnauthorizedClientException",
+    "InvalidClientException",
+    "invalid_grant",
+    "invalid_token",
+    "invalid_client",
+    "unauthorized_client",
+];
+
+/// 服务端瞬态错误代码
+const TRANSIENT_CODES: &[&str] = &[
+    "InternalServerException",
+    "ServiceUnavailableException",
+    "InternalFailure",
+];
+
+/// `Retry-After` 退避时长上限（避免异常值把凭据长期移出调度）
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(3600);
+
+/// 解析 `Retry-After` 响应头（秒数或 HTTP-date），结果不超过 1 小时
+///
+/// 无法解析或已过期的值返回 None
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let retry_after = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                .to_std()
+                .ok()?
+        }
+    };
+    (!retry_after.is_zero()).then(|| retry_after.min(MAX_RETRY_AFTER))
+}
+
+/// 上游错误分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamErrorKind {
+    /// 月度额度用尽（MONTHLY_REQUEST_COUNT）
+    QuotaMonthly,
+    /// 限流
+    Throttled,
+    /// 认证失效（Token 过期、无效或被拒绝）
+    AuthExpired,
+    /// 服务端瞬态错误
+    Transient,
+    /// 无法识别（不应自动禁用凭据）
+    Unknown,
+}
+
+impl UpstreamErrorKind {
+    /// 根据状态码与错误响应体分类
+    ///
+    /// 错误代码优先于状态码，无法识别的错误归为 `Unknown`
+    pub fn classify(status: u16, body: &UpstreamErrorBody) -> Self {
+        if body.reason.as_deref() == Some(REASON_MONTHLY_REQUEST_COUNT) {
+            return Self::QuotaMonthly;
+        }
+
+        if let Some(code) = body.code.as_deref() {
+            if THROTTLED_CODES.contains(&code) {
+                return Self::Throttled;
+            }
+            if AUTH_EXPIRED_CODES.contains(&code) {
+                return Self::AuthExpired;
+            }
+            if TRANSIENT_CODES.contains(&code) {
+                return Self::Transient;
+            }
+        }
+
+        match status {
+            401 | 403 => Self::AuthExpired,
+            429 => Self::Throttled,
+            408 | 500..=599 => Self::Transient,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// 解析响应体并分类
+    pub fn from_response(status: u16, body: &str) -> Self {
+        Self::classify(status, &UpstreamErrorBody::parse(body))
+    }
+
+    /// 从 anyhow 错误中取出分类（非 `ClassifiedError`/`ProviderError`/`KiroError` 视为 `Unknown`）
+    pub fn of(error: &anyhow::Error) -> Self {
+        if let Some(e) = error.downcast_ref::<ClassifiedError>() {
+            return e.kind;
+        }
+        if let Some(e) = ProviderError::of(error) {
+            return e.kind();
+        }
+        error
+            .downcast_ref::<KiroError>()
+            .map(KiroError::kind)
+            .unwrap_or(Self::Unknown)
+    }
+}
+
+/// 结构化的上游错误响应体
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpstreamErrorBody {
+    /// 原因代码（如 MONTHLY_REQUEST_COUNT）
+    pub reason: Option<String>,
+    /// 错误代码（如 ThrottlingException、invalid_grant）
+    pub code: Option<String>,
+    /// 错误信息
+    pub message: Option<String>,
+}
+
+impl UpstreamErrorBody {
+    /// 解析错误响应体（非 JSON 或格式不符时返回空结构）
+    ///
+    /// 支持的格式：
+    /// - Kiro/CodeWhisperer：`{"message": "...", "reason": "..."}`
+    /// - AWS JSON 协议：`{"__type": "...#ThrottlingException", "message": "..."}`
+    /// - OAuth/OIDC：`{"error": "invalid_grant", "error_description": "..."}`
+    /// - 嵌套：`{"error": {"reason": "...", "message": "..."}}`
+    pub fn parse(body: &str) -> Self {
+        let Ok(raw) = serde_json::from_str::<RawErrorBody>(body) else {
+            return Self::default();
+        };
+        raw.into_body()
+    }
+}
+
+/// 携带分类的上游错误
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct ClassifiedError {
+    /// 错误分类
+    pub kind: UpstreamErrorKind,
+    /// 错误信息
+    pub message: String,
+    /// 上游 HTTP 状态码（非 HTTP 响应导致的错误为 None）
+    pub status: Option<u16>,
+}
+
+impl ClassifiedError {
+    pub fn new(kind: UpstreamErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            status: None,
+        }
+    }
+
+    /// 记录上游 HTTP 状态码
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+}
+
+#[derive(Deserialize)]
+struct RawErrorBody {
+    #[serde(default)]
+    reason: Option<String>,
+    #[serde(default, alias = "Message")]
+    message: Option<String>,
+    #[serde(default, rename = "__type")]
+    error_type: Option<String>,
+    #[serde(default, alias = "Code")]
+    code: Option<String>,
+    #[serde(default)]
+    error: Option<RawErrorField>,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawErrorField {
+    Code(String),
+    Nested(Box<RawErrorBody>),
+}
+
+impl RawErrorBody {
+    fn into_body(self) -> UpstreamErrorBody {
+        let (error_code, nested) = match self.error {
+            Some(RawErrorField::Code(code)) => (Some(code), UpstreamErrorBody::default()),
+            Some(RawErrorField::Nested(nested)) => (None, nested.into_body()),
+            None => (None, UpstreamErrorBody::default()),
+        };
+
+        // AWS __type 形如 "com.amazon.coral.service#ThrottlingException"
+        let type_code = self
+            .error_type
+            .map(|t| t.rsplit('#').next().unwrap_or_default().to_string());
+
+        UpstreamErrorBody {
+            reason: self.reason.or(nested.reason),
+            code: type_code.or(self.code).or(error_code).or(nested.code),
+            message: self.message.or(self.error_description).or(nested.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_upstream_error_bodies() {
+        use UpstreamErrorKind::*;
+
+        let cases: &[(u16, &str, UpstreamErrorKind)] = &[
+            // 额度用尽
+            (
+                402,
+                r#"{"message":"You have reached the limit for monthly requests.","reason":"MONTHLY_REQUEST_COUNT"}"#,
+                QuotaMonthly,
+            ),
+            (
+                402,
+                r#"{"error":{"reason":"MONTHLY_REQUEST_COUNT"}}"#,
+                QuotaMonthly,
+            ),
+            (
+                402,
+                r#"{"message":"nope","reason":"DAILY_REQUEST_COUNT"}"#,
+                Unknown,
+            ),
+            // 正文提到代码但并非结构化原因，不视为额度用尽
+            (402, "MONTHLY_REQUEST_COUNT exceeded", Unknown),
+            // 限流
+            (
+                429,
+                r#"{"message":"Too many requests, please wait before trying again.","reason":null}"#,
+                Throttled,
+            ),
+            (
+                400,
+                r#"{"__type":"com.amazon.aws.codewhisperer#ThrottlingException","message":"Rate exceeded"}"#,
+                Throttled,
+            ),
+            // 认证失效
+            (
+                403,
+                r#"{"message":"The bearer token included in the request is invalid.","reason":null}"#,
+                AuthExpired,
+            ),
+            (
+                400,
+                r#"{"error":"invalid_grant","error_description":"Invalid refresh token provided"}"#,
+                AuthExpired,
+            ),
+            (
+                400,
+                r#"{"__type":"com.amazonaws.ssooidc#InvalidGrantException","error":"invalid_grant"}"#,
+                AuthExpired,
+            ),
+            (401, r#"{"message":"Unauthorized"}"#, AuthExpired),
+            (401, "", AuthExpired),
+            // 瞬态错误
+            (
+                500,
+                r#"{"__type":"com.amazon.aws.codewhisperer#InternalServerException","message":"Encountered an unexpected error when processing the request, please try again."}"#,
+                Transient,
+            ),
+            (502, "<html><body>Bad Gateway</body></html>", Transient),
+            (
+                503,
+                r#"{"message":"Service is under high load, status 403"}"#,
+                Transient,
+            ),
+            (408, "", Transient),
+            // 未知错误：错误信息中出现 "403"/"expired" 也不应被误判
+            (
+                400,
+                r#"{"message":"Improperly formed request: token 403 expired in conversation history","reason":null}"#,
+                Unknown,
+            ),
+            (400, r#"{"message":"Input is too long."}"#, Unknown),
+            (404, "not found", Unknown),
+        ];
+
+        for (status, body, expected) in cases {
+            assert_eq!(
+                UpstreamErrorKind::from_response(*status, body),
+                *expected,
+                "status={} body={}",
+                status,
+                body
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_error_body_fields() {
+        let body = UpstreamErrorBody::parse(
+            r#"{"__type":"com.amazon.aws.codewhisperer#ThrottlingException","Message":"Rate exceeded"}"#,
+        );
+        assert_eq!(body.code.as_deref(), Some("ThrottlingException"));
+        assert_eq!(body.message.as_deref(), Some("Rate exceeded"));
+
+        let body = UpstreamErrorBody::parse(
+            r#"{"error":"invalid_grant","error_description":"Invalid refresh token provided"}"#,
+        );
+        assert_eq!(body.code.as_deref(), Some("invalid_grant"));
+        assert_eq!(
+            body.message.as_deref(),
+            Some("Invalid refresh token provided")
+        );
+
+        assert_eq!(
+            UpstreamErrorBody::parse("not json"),
+            UpstreamErrorBody::default()
+        );
+    }
+
+    #[test]
+    fn test_kind_of_error() {
+        let error: anyhow::Error =
+            ClassifiedError::new(UpstreamErrorKind::AuthExpired, "401 expired").into();
+        assert_eq!(
+            UpstreamErrorKind::of(&error),
+            UpstreamErrorKind::AuthExpired
+        );
+
+        // 未分类错误即使包含 "401"/"expired" 也视为 Unknown
+        let error = anyhow::anyhow!("connection reset: 401 expired");
+        assert_eq!(UpstreamErrorKind::of(&error), UpstreamErrorKind::Unknown);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after(" 5 "), Some(Duration::from_secs(5)));
+        assert_eq!(parse_retry_after("0"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+        // 超过上限时截断
+        assert_eq!(parse_retry_after("86400"), Some(MAX_RETRY_AFTER));
+
+        // HTTP-date
+        let at = (chrono::Utc::now() + chrono::Duration::seconds(120)).to_rfc2822();
+        let parsed = parse_retry_after(&at).unwrap();
+        assert!(parsed > Duration::from_secs(100) && parsed <= Duration::from_secs(120));
+        assert_eq!(parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT"), None);
+    }
+}
@@ -0,0 +1,166 @@
+//! 凭据预热报告
+//!
+//! 启动时预先校验所有启用凭据的 Token（必要时刷新），
+//! 汇总每个凭据的耗时和结果，输出到启动日志并供 Admin API 查询。
+
+use std::fmt;
+
+use crate::kiro::model::credentials::KiroCredentials;
+
+/// 单个凭据的预热结果
+#[derive(Debug, Clone)]
+pub struct WarmupEntry {
+    /// 凭据 ID
+    pub id: u64,
+    /// 认证方式（social / idc 等）
+    pub auth_method: String,
+    /// 优先级
+    pub priority: u32,
+    /// 耗时（毫秒）
+    pub duration_ms: u64,
+    /// 是否实际刷新了 Token（Token 仍有效时跳过刷新）
+    pub refreshed: bool,
+    /// 校验结果，失败时为错误信息
+    pub result: Result<(), String>,
+}
+
+impl WarmupEntry {
+    /// 凭据的认证方式（未显式配置时按是否有 clientId/clientSecret 推断，与刷新逻辑一致）
+    pub fn auth_method_of(credentials: &KiroCredentials) -> String {
+        credentials.auth_method.clone().unwrap_or_else(|| {
+            if credentials.client_id.is_some() && credentials.client_secret.is_some() {
+                "idc".to_string()
+            } else {
+                "social".to_string()
+            }
+        })
+    }
+}
+
+/// 凭据预热报告
+#[derive(Debug, Clone, Default)]
+pub struct WarmupReport {
+    /// 各凭据结果（按 ID 排序）
+    pub entries: Vec<WarmupEntry>,
+    /// 预热总耗时（毫秒）
+    pub elapsed_ms: u64,
+}
+
+impl WarmupReport {
+    /// 参与预热的凭据数
+    pub fn total(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 校验通过的凭据数
+    pub fn ready(&self) -> usize {
+        self.entries.iter().filter(|e| e.result.is_ok()).count()
+    }
+
+    /// 校验失败的凭据数
+    pub fn failed(&self) -> usize {
+        self.total() - self.ready()
+    }
+
+    /// 成功刷新 Token 的凭据数
+    pub fn refreshed(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| e.refreshed && e.result.is_ok())
+            .count()
+    }
+
+    /// Token 仍有效、跳过刷新的凭据数
+    pub fn skipped(&self) -> usize {
+        self.ready() - self.refreshed()
+    }
+}
+
+/// 启动日志中的报告框
+impl fmt::Display for WarmupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const TITLE: &str = "─ Credential Warm-up Report ─";
+        let width = TITLE.chars().count();
+
+        writeln!(f, "┌{}┐", TITLE)?;
+        writeln!(
+            f,
+            "│ Total: {} │ Ready: {} │ Failed: {} │ Refreshed: {} │ Skipped: {} │",
+            self.total(),
+            self.ready(),
+            self.failed(),
+            self.refreshed(),
+            self.skipped()
+        )?;
+        for entry in &self.entries {
+            let seconds = entry.duration_ms as f64 / 1000.0;
+            match &entry.result {
+                Ok(()) if !entry.refreshed => writeln!(
+                    f,
+                    "├ #{} ({}, priority={}): ✓ skipped",
+                    entry.id, entry.auth_method, entry.priority
+                )?,
+                Ok(()) => writeln!(
+                    f,
+                    "├ #{} ({}, priority={}): ✓ {:.1}s",
+                    entry.id, entry.auth_method, entry.priority, seconds
+                )?,
+                Err(e) => writeln!(
+                    f,
+                    "├ #{} ({}, priority={}): ✗ {:.1}s {}",
+                    entry.id, entry.auth_method, entry.priority, seconds, e
+                )?,
+            }
+        }
+        write!(f, "└{}┘", "─".repeat(width))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, refreshed: bool, result: Result<(), String>) -> WarmupEntry {
+        WarmupEntry {
+            id,
+            auth_method: "social".to_string(),
+            priority: 0,
+            duration_ms: 1200,
+            refreshed,
+            result,
+        }
+    }
+
+    #[test]
+    fn test_report_display() {
+        let report = WarmupReport {
+            entries: vec![
+                entry(1, true, Ok(())),
+                entry(2, true, Err("刷新失败".to_string())),
+                entry(3, false, Ok(())),
+            ],
+            elapsed_ms: 1200,
+        };
+
+        let text = report.to_string();
+        assert!(text.starts_with("┌─ Credential Warm-up Report ─┐\n"));
+        assert!(text.contains("│ Total: 3 │ Ready: 2 │ Failed: 1 │ Refreshed: 1 │ Skipped: 1 │"));
+        assert!(text.contains("├ #1 (social, priority=0): ✓ 1.2s"));
+        assert!(text.contains("├ #2 (social, priority=0): ✗ 1.2s 刷新失败"));
+        assert!(text.contains("├ #3 (social, priority=0): ✓ skipped"));
+        assert!(text.ends_with("┘"));
+    }
+
+    #[test]
+    fn test_auth_method_inferred() {
+        let mut credentials = KiroCredentials::default();
+        assert_eq!(WarmupEntry::auth_method_of(&credentials), "social");
+
+        credentials.client_id = Some("id".to_string());
+        credentials.client_secret = Some("secret".to_string());
+        assert_eq!(WarmupEntry::auth_method_of(&credentials), "idc");
+
+        credentials.auth_method = Some("builder-id".to_string());
+        assert_eq!(WarmupEntry::auth_method_of(&credentials), "builder-id");
+    }
+}
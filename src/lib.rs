@@ -1,13 +1,18 @@
 //! Kiro.rs Library
 //!
-//! 提供 Kiro API 客户端功能和凭据管理
+//! 提供 Kiro API 客户端功能和凭据管理；
+//! 通过 [`AppBuilder`] 可以将 Anthropic 兼容 API 嵌入到其他 axum 应用中
 
 pub mod admin;
 pub mod admin_ui;
 pub mod anthropic;
+pub mod app;
 pub mod common;
 pub mod health;
 pub mod http_client;
 pub mod kiro;
 pub mod model;
+pub mod server;
 pub mod token;
+
+pub use app::{App, AppBuilder, CredentialsSource};
@@ -1,8 +1,22 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
-
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+use std::path::{Path, PathBuf};
+use std::ops::RangeInclusive;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::common::features;
+use crate::common::file_format::{FileFormat, parse_by_path};
+use crate::common::i18n::{ErrorCode, Locale, LocalizedError};
+use crate::common::io::atomic_write;
+use crate::http_client::{HttpTimeouts, TlsOptions};
+use crate::kiro::parser::decoder::DEFAULT_MAX_BUFFER_SIZE;
+
+/// 上下文窗口大小（200k tokens）
+pub const CONTEXT_WINDOW_SIZE: i32 = 200_000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "kebab-case")]
 pub enum TlsBackend {
     Rustls,
@@ -15,6 +29,221 @@ impl Default for TlsBackend {
     }
 }
 
+/// TLS 最低协议版本
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum TlsVersion {
+    /// TLS 1.2 及以上
+    #[default]
+    #[serde(rename = "TLS12")]
+    Tls12,
+    /// 仅 TLS 1.3
+    #[serde(rename = "TLS13")]
+    Tls13,
+}
+
+impl std::fmt::Display for TlsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Tls12 => "TLS 1.2",
+            Self::Tls13 => "TLS 1.3",
+        })
+    }
+}
+
+/// 限流算法
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RateLimiterType {
+    /// 按分钟/小时窗口计数（全局 + 每 API Key）
+    #[default]
+    SlidingWindow,
+    /// 全局令牌桶（允许突发，按固定速率补充令牌）
+    TokenBucket,
+}
+
+/// 限流豁免规则
+///
+/// API Key 前缀或客户端 IP（CIDR）任一匹配即跳过限流检查，请求仍计入统计
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitExemption {
+    /// API Key 前缀（如 `sk-internal-`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_prefix: Option<String>,
+    /// 客户端 IP 段（如 `10.0.0.0/8`，不带前缀长度时视为单个地址）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cidr: Option<String>,
+    /// 豁免原因（用于日志和 Admin 展示）
+    pub reason: String,
+}
+
+impl RateLimitExemption {
+    /// 校验规则：至少配置 keyPrefix 或 cidr 之一，且 cidr 格式有效
+    pub fn validate(&self) -> Result<(), String> {
+        if self.key_prefix.is_none() && self.cidr.is_none() {
+            return Err("至少需要配置 keyPrefix 或 cidr".to_string());
+        }
+        if self.key_prefix.as_deref().is_some_and(str::is_empty) {
+            return Err("keyPrefix 不能为空".to_string());
+        }
+        if let Some(cidr) = &self.cidr
+            && parse_cidr(cidr).is_none()
+        {
+            return Err(format!("cidr 格式无效: {}", cidr));
+        }
+        Ok(())
+    }
+
+    /// 判断请求是否命中该规则
+    pub fn matches(&self, api_key: Option<&str>, client_ip: Option<std::net::IpAddr>) -> bool {
+        if let (Some(prefix), Some(key)) = (&self.key_prefix, api_key)
+            && !prefix.is_empty()
+            && key.starts_with(prefix.as_str())
+        {
+            return true;
+        }
+        if let (Some(cidr), Some(ip)) = (&self.cidr, client_ip)
+            && let Some((network, prefix_len)) = parse_cidr(cidr)
+        {
+            return cidr_contains(network, prefix_len, ip);
+        }
+        false
+    }
+}
+
+/// 解析 CIDR（`地址/前缀长度`，省略前缀长度时为单个地址）
+fn parse_cidr(cidr: &str) -> Option<(std::net::IpAddr, u8)> {
+    let (addr, prefix_len) = match cidr.trim().split_once('/') {
+        Some((addr, len)) => (addr, Some(len.parse::<u8>().ok()?)),
+        None => (cidr.trim(), None),
+    };
+    let addr: std::net::IpAddr = addr.parse().ok()?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix_len = prefix_len.unwrap_or(max);
+    (prefix_len <= max).then_some((addr, prefix_len))
+}
+
+/// 判断 IP 是否落在网段内（IPv4 映射的 IPv6 地址按 IPv4 处理）
+fn cidr_contains(network: std::net::IpAddr, prefix_len: u8, ip: std::net::IpAddr) -> bool {
+    use std::net::IpAddr;
+
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    };
+    match (network, ip) {
+        (IpAddr::V4(net), IpAddr::V4(ip)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+            u32::from(net) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(net), IpAddr::V6(ip)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0);
+            u128::from(net) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+/// system prompt 改写规则的匹配方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SystemPromptMatch {
+    /// 与整段 system 文本完全相同
+    Exact,
+    /// system 文本以 pattern 开头
+    Prefix,
+    /// system 文本包含正则匹配
+    Regex,
+}
+
+/// system prompt 改写规则的动作
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum SystemPromptAction {
+    /// 删除整段 system 文本
+    Drop,
+    /// 将匹配部分替换为 `with`（正则支持 `$1` 等捕获组引用）
+    Replace { with: String },
+    /// 截断为前 `max_chars` 个字符
+    Truncate { max_chars: usize },
+}
+
+/// system prompt 改写规则
+///
+/// 只作用于 system 字段中的文本段，不修改 user/assistant 消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemPromptRule {
+    /// 规则名称（用于日志，默认使用规则序号）
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 匹配方式
+    #[serde(rename = "match")]
+    pub match_type: SystemPromptMatch,
+    /// 匹配内容（exact/prefix 为文本，regex 为正则表达式）
+    pub pattern: String,
+    /// 匹配后的动作
+    pub action: SystemPromptAction,
+    /// 编译后的正则（首次使用时编译）
+    #[serde(skip)]
+    compiled: OnceLock<Option<regex::Regex>>,
+}
+
+impl SystemPromptRule {
+    /// 创建规则
+    #[allow(dead_code)]
+    pub fn new(
+        match_type: SystemPromptMatch,
+        pattern: impl Into<String>,
+        action: SystemPromptAction,
+    ) -> Self {
+        Self {
+            name: None,
+            match_type,
+            pattern: pattern.into(),
+            action,
+            compiled: OnceLock::new(),
+        }
+    }
+
+    /// 设置规则名称
+    #[allow(dead_code)]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// 获取编译后的正则（非 regex 规则或正则无效时返回 None）
+    pub fn regex(&self) -> Option<&regex::Regex> {
+        if self.match_type != SystemPromptMatch::Regex {
+            return None;
+        }
+        self.compiled
+            .get_or_init(|| regex::Regex::new(&self.pattern).ok())
+            .as_ref()
+    }
+}
+
+/// 上游服务端点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamEndpoint {
+    /// Host 请求头
+    pub host: String,
+    /// 请求地址前缀（不含末尾 `/`）
+    pub base_url: String,
+}
+
+impl UpstreamEndpoint {
+    /// 拼接请求路径
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
 /// KNA 应用配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -43,6 +272,16 @@ pub struct Config {
     #[serde(default = "default_tls_backend")]
     pub tls_backend: TlsBackend,
 
+    /// TLS 最低协议版本（`TLS12` 或 `TLS13`，默认 `TLS12`）
+    #[serde(default)]
+    pub tls_min_version: TlsVersion,
+
+    /// 要求上游证书提供证书透明度（CT）记录
+    ///
+    /// 当前 TLS 后端均不支持校验，设为 true 时配置校验失败（不会静默忽略）
+    #[serde(default)]
+    pub tls_require_certificate_transparency: bool,
+
     /// 外部 count_tokens API 地址（可选）
     #[serde(default)]
     pub count_tokens_api_url: Option<String>,
@@ -68,10 +307,52 @@ pub struct Config {
     #[serde(default)]
     pub proxy_password: Option<String>,
 
+    /// 客户端 API Key（可选，与 api_keys.json 中的 Key 同时有效，使用默认池）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+
+    /// api_keys.json 中 API Key 数量上限（默认 1000）
+    #[serde(default = "default_max_api_keys")]
+    pub max_api_keys: usize,
+
     /// Admin API 密钥（可选，启用 Admin API 功能）
     #[serde(default)]
     pub admin_api_key: Option<String>,
 
+    /// Admin UI 的 Content-Security-Policy 响应头（可选，对外暴露 Admin UI 时建议配置）
+    #[serde(default)]
+    pub admin_ui_csp: Option<String>,
+
+    /// Admin mTLS 客户端 CA 证书路径（PEM，可选，相对路径相对于配置文件目录）
+    ///
+    /// 配置后 Admin API 和 Admin UI 不再挂载到主端口，改为在 `adminPort` 上通过 TLS 提供服务，
+    /// 客户端证书按该 CA 校验，证书 CN 作为 Admin 操作者身份；adminApiKey 仍需校验
+    #[serde(default)]
+    pub admin_mtls_ca_path: Option<String>,
+
+    /// 是否要求 Admin 客户端出示证书（默认 false：可不出示，出示的证书仍需通过 CA 校验）
+    #[serde(default)]
+    pub admin_mtls_required: bool,
+
+    /// Admin 独立监听端口（启用 Admin mTLS 时必填，监听地址与 host 相同）
+    ///
+    /// Admin TLS 监听只在启动时绑定：通过 Admin API 修改 host 后主端口会切换地址，
+    /// Admin TLS 监听需要重启服务后才切换
+    #[serde(default)]
+    pub admin_port: Option<u16>,
+
+    /// Admin TLS 服务端证书路径（PEM，启用 Admin mTLS 时必填）
+    #[serde(default)]
+    pub admin_tls_cert_path: Option<String>,
+
+    /// Admin TLS 服务端私钥路径（PEM，启用 Admin mTLS 时必填）
+    #[serde(default)]
+    pub admin_tls_key_path: Option<String>,
+
+    /// 错误消息默认语言（"zh" 或 "en"，默认 "zh"；客户端 Accept-Language 优先）
+    #[serde(default)]
+    pub default_locale: Locale,
+
     /// 会话缓存最大容量（默认 10000）
     #[serde(default = "default_session_cache_max_capacity")]
     pub session_cache_max_capacity: u64,
@@ -80,10 +361,52 @@ pub struct Config {
     #[serde(default = "default_session_cache_ttl_secs")]
     pub session_cache_ttl_secs: u64,
 
+    /// system prompt 哈希兜底占比告警阈值（百分比，默认 50，0 表示不告警）
+    ///
+    /// 某个池中以 system prompt 哈希作为会话标识的请求占比超过该值时记录警告（每 10 分钟最多一次），
+    /// 说明客户端应发送 `x-session-id`
+    #[serde(default = "default_stickiness_system_hash_warn_percent")]
+    pub stickiness_system_hash_warn_percent: u8,
+
+    /// 启用按用户公平调度（基于 metadata.user_id，默认 false）
+    #[serde(default)]
+    pub user_fairness_enabled: bool,
+
+    /// 单个用户最多占用的可用凭据比例（0-1，默认 0.5，至少 1 个凭据）
+    #[serde(default = "default_user_max_share")]
+    pub user_max_share: f64,
+
+    /// 额外的凭据目录（默认不启用，相对路径相对于配置文件所在目录）
+    ///
+    /// 启动时读取每个目录下的 `credentials.json`，运行时变更回写到各自的文件（不合并到主凭据文件）；
+    /// 目录下的 `dir_config.json` 可通过 `defaultPoolId` 将该目录的凭据分配到指定池
+    #[serde(default)]
+    pub credential_dirs: Vec<String>,
+
+    /// 凭据并发已达 `maxConcurrentRequests` 时等待空闲的超时时间（毫秒，默认 5000）
+    #[serde(default = "default_credential_throttle_timeout_ms")]
+    pub credential_throttle_timeout_ms: u64,
+
+    /// 每个凭据保留的调用时间线事件数（默认 1000，0 表示不记录）
+    #[serde(default = "default_timeline_max_events_per_credential")]
+    pub timeline_max_events_per_credential: usize,
+
     /// 健康检查间隔（秒，默认 600 = 10 分钟）
     #[serde(default = "default_health_check_interval_secs")]
     pub health_check_interval_secs: u64,
 
+    /// 启动时预热凭据（刷新过期或即将过期的 Token 后再开始服务，默认 false）
+    #[serde(default = "default_warmup_on_startup")]
+    pub warmup_on_startup: bool,
+
+    /// 启动预热的最大并发刷新数（默认 8）
+    #[serde(default = "default_warmup_concurrency")]
+    pub warmup_concurrency: usize,
+
+    /// Admin 凭据连通性测试超时（秒，默认 15）
+    #[serde(default = "default_test_timeout_secs")]
+    pub test_timeout_secs: u64,
+
     /// 启用限流（默认 true）
     #[serde(default = "default_rate_limit_enabled")]
     pub rate_limit_enabled: bool,
@@ -104,6 +427,159 @@ pub struct Config {
     #[serde(default = "default_rate_limit_per_key_per_hour")]
     pub rate_limit_per_key_per_hour: u64,
 
+    /// 限流算法（默认 slidingWindow）
+    ///
+    /// `tokenBucket` 时使用全局令牌桶代替上面的分钟/小时窗口限流
+    #[serde(default)]
+    pub rate_limiter_type: RateLimiterType,
+
+    /// 令牌桶容量，即允许的最大突发请求数（默认 60）
+    #[serde(default = "default_token_bucket_capacity")]
+    pub token_bucket_capacity: u64,
+
+    /// 令牌桶每秒补充的令牌数（默认 1.0，支持小数）
+    #[serde(default = "default_token_bucket_refill_per_second")]
+    pub token_bucket_refill_per_second: f64,
+
+    /// 限流豁免规则（默认为空）
+    ///
+    /// 命中的请求跳过限流检查但仍计入统计，可通过 Admin API 运行时增删
+    #[serde(default)]
+    pub rate_limit_exemptions: Vec<RateLimitExemption>,
+
+    /// 收集每 API Key 的限流统计（默认 true）
+    ///
+    /// 关闭后 Admin 限流统计只返回全局计数，不记录各 Key 的最近请求时间
+    #[serde(default = "default_rate_limit_stats_enabled")]
+    pub rate_limit_stats_enabled: bool,
+
+    /// WebSearch 限流：每 API Key 每小时请求数（默认 100，0 表示不限制）
+    ///
+    /// 独立于普通消息限流，可在 API Key 上单独覆盖
+    #[serde(default = "default_websearch_rate_limit_per_hour")]
+    pub websearch_rate_limit_per_hour: u64,
+
+    /// 启用额度用尽排队（默认 false）
+    ///
+    /// 所有凭据均因额度用尽被禁用、且最早重置时间在 `queue_max_wait_secs` 内时，
+    /// 非流式请求保持连接排队等待，凭据重新启用后自动执行
+    #[serde(default)]
+    pub quota_queue_enabled: bool,
+
+    /// 额度用尽排队：最长等待时间（秒，默认 300）
+    #[serde(default = "default_queue_max_wait_secs")]
+    pub queue_max_wait_secs: u64,
+
+    /// 额度用尽排队：最大排队请求数（默认 100）
+    #[serde(default = "default_quota_queue_max_size")]
+    pub quota_queue_max_size: usize,
+
+    /// 启用请求去重（默认 false）
+    ///
+    /// 相同的非流式请求（同一 API Key、模型、消息、系统提示和工具）正在处理时，
+    /// 后到的请求等待首个请求完成并复用其响应，不再重复调用上游
+    #[serde(default)]
+    pub dedup_enabled: bool,
+
+    /// 请求去重：等待相同请求完成的最长时间（秒，默认 30，超时后自行处理请求）
+    #[serde(default = "default_dedup_max_wait_secs")]
+    pub dedup_max_wait_secs: u64,
+
+    /// 上游并发请求上限（默认 0，表示不限制）
+    ///
+    /// 对话请求调用上游前需要取得名额，流式请求持有名额直到 SSE 流结束或客户端断开，
+    /// 避免突发并发请求直接打到上游触发 429
+    #[serde(default)]
+    pub max_concurrent_upstream_requests: usize,
+
+    /// 等待上游并发名额的最长时间（毫秒，默认 10000，0 表示不等待），超时返回 429
+    #[serde(default = "default_upstream_queue_timeout_ms")]
+    pub upstream_queue_timeout_ms: u64,
+
+    /// 以 gzip 压缩发送超大的对话请求体（`content-encoding: gzip`，默认 false）
+    #[serde(default)]
+    pub request_compression_enabled: bool,
+
+    /// 请求体达到该字节数时才压缩（默认 262144 = 256 KiB）
+    #[serde(default = "default_request_compression_min_bytes")]
+    pub request_compression_min_bytes: usize,
+
+    /// SSE 断线续传：每个流式响应保留的最近事件数（默认 0 即禁用，推荐 100）
+    ///
+    /// 启用后 SSE 事件带有递增的 `id`，客户端断线后携带 `Last-Event-ID`
+    /// 重新发送相同请求即可从断点继续接收
+    #[serde(default = "default_sse_replay_buffer_size")]
+    pub sse_replay_buffer_size: usize,
+
+    /// 上游事件流解码缓冲区上限（字节，默认 16 MiB）
+    ///
+    /// 待解析数据超过该值时流式响应以错误事件终止
+    #[serde(default = "default_decoder_buffer_size_bytes")]
+    pub decoder_buffer_size_bytes: usize,
+
+    /// 建立连接（含 TLS 握手）超时（秒，默认 10，范围 1-120）
+    ///
+    /// 适用于所有上游请求，可按池覆盖
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+
+    /// Token 刷新和额度查询请求的整体超时（秒，默认 60，范围 1-600），可按池覆盖
+    #[serde(default = "default_refresh_request_timeout_secs")]
+    pub refresh_request_timeout_secs: u64,
+
+    /// 对话请求等待上游响应首字节的超时（秒，默认 180，范围 1-3600），可按池覆盖
+    ///
+    /// 收到响应后读取事件流不设整体时长限制，长时间的流式生成不会被截断
+    #[serde(default = "default_upstream_first_byte_timeout_secs")]
+    pub upstream_first_byte_timeout_secs: u64,
+
+    /// 读取上游响应体时两次收到数据之间的最长间隔（秒，默认 300，范围 1-3600）
+    ///
+    /// 流式与非流式请求都适用，超过后中止读取：流式响应以错误事件结束，非流式请求按读取失败处理
+    #[serde(default = "default_upstream_idle_timeout_secs")]
+    pub upstream_idle_timeout_secs: u64,
+
+    /// 上游端点覆盖地址（可选，如 `http://127.0.0.1:9000`）
+    ///
+    /// 配置后 Token 刷新、额度查询和对话请求都发往该地址，用于测试或反向代理
+    #[serde(default)]
+    pub upstream_base_url: Option<String>,
+
+    /// Kiro API 地址覆盖（可选，必须为 https，如 VPC 端点）
+    ///
+    /// 覆盖对话、MCP 和额度查询请求默认的 `https://q.{region}.amazonaws.com`，
+    /// Host 请求头同步使用该地址的主机名
+    #[serde(default)]
+    pub kiro_api_base_url: Option<String>,
+
+    /// Social Token 刷新地址覆盖（可选，必须为 https），默认 `https://prod.{region}.auth.desktop.kiro.dev`
+    #[serde(default)]
+    pub refresh_base_url_social: Option<String>,
+
+    /// IdC Token 刷新地址覆盖（可选，必须为 https），默认 `https://oidc.{region}.amazonaws.com`
+    #[serde(default)]
+    pub refresh_base_url_idc: Option<String>,
+
+    /// 单个文档内容块大小上限（字节，默认 1 MiB）
+    #[serde(default = "default_max_document_bytes")]
+    pub max_document_bytes: usize,
+
+    /// 单张图片大小上限（字节，含 tool_result 中的图片，默认 5 MiB）
+    #[serde(default = "default_max_image_bytes")]
+    pub max_image_bytes: usize,
+
+    /// 单个请求所有图片总大小上限（字节，默认 20 MiB）
+    #[serde(default = "default_max_request_image_bytes")]
+    pub max_request_image_bytes: usize,
+
+    /// 不向客户端返回转换警告（默认 false）
+    ///
+    /// 转换警告（丢弃的内容块、截断的工具描述等）默认以非流式响应的 `kiro_warnings`
+    /// 字段和流式响应 `message_start` 前的 SSE 注释行返回；需要与 Anthropic 响应
+    /// 逐字节一致时开启，警告仍会写入日志
+    #[serde(default)]
+    pub suppress_conversion_warnings: bool,
+
     /// 启用智能历史管理（默认 true）
     #[serde(default = "default_history_management_enabled")]
     pub history_management_enabled: bool,
@@ -123,6 +599,74 @@ pub struct Config {
     /// 保留最近的消息数量（默认 20）
     #[serde(default = "default_history_keep_recent_messages")]
     pub history_keep_recent_messages: usize,
+
+    /// system prompt 改写规则（默认为空，按顺序应用）
+    ///
+    /// 用于去除客户端附带的大段固定 system 前言，在历史管理和 token 计数之前应用
+    #[serde(default)]
+    pub system_prompt_rules: Vec<SystemPromptRule>,
+
+    /// 提示 Prompt Caching 不受支持（默认 true）
+    ///
+    /// Kiro 上游不支持 Prompt Caching，请求中的 `cache_control` 标记始终会被移除。
+    /// 启用时，带 `cache_control` 或 `anthropic-beta: prompt-caching-*` 的请求
+    /// 响应附带 `x-kiro-prompt-caching: unsupported` 头，并在首次出现时记录一次警告
+    #[serde(default = "default_prompt_caching_notice_enabled")]
+    pub prompt_caching_notice_enabled: bool,
+
+    /// 是否提供 OpenAPI 规范与 Swagger UI（默认 true）
+    ///
+    /// 启用时无需认证即可访问 `/openapi.json`、`/openapi.yaml` 与 `/docs`
+    #[serde(default = "default_openapi_enabled")]
+    pub openapi_enabled: bool,
+
+    /// 保存配置时使用原子写入（默认 true）
+    ///
+    /// 先写入 `config.json.tmp` 再 rename 覆盖，进程中途崩溃不会留下损坏的配置文件。
+    /// 凭据、API Key 和池文件始终原子写入，不受此项影响
+    #[serde(default = "default_atomic_writes")]
+    pub atomic_writes: bool,
+
+    /// 自动备份目录（默认不启用，相对路径相对于配置文件所在目录）
+    ///
+    /// 启用后定期将 config、credentials、pools、api_keys 文件复制到该目录下的时间戳子目录
+    #[serde(default)]
+    pub backup_dir: Option<String>,
+
+    /// 自动备份间隔（小时，默认 24）
+    #[serde(default = "default_backup_interval_hours")]
+    pub backup_interval_hours: u64,
+
+    /// 保留的备份数量（默认 7，超出时删除最旧的备份）
+    #[serde(default = "default_backup_retention")]
+    pub backup_retention: usize,
+
+    /// 按可用凭据比例自动调整池优先级（默认 false）
+    ///
+    /// 启用后定期计算各池 `可用凭据数 / 凭据总数`，可用比例越高的池优先级数字越小，
+    /// 使空闲的池在自动路由中承接更多流量。仅调整优先级落在
+    /// [`autoPriorityMin`, `autoPriorityMax`] 范围内的池，范围外的手动优先级保持不变
+    #[serde(default)]
+    pub auto_adjust_pool_priority: bool,
+
+    /// 池优先级自动调整间隔（秒，默认 60）
+    #[serde(default = "default_pool_priority_check_interval_secs")]
+    pub pool_priority_check_interval_secs: u64,
+
+    /// 自动调整的池优先级下限（默认 0，可用比例为 1 的池取该值）
+    #[serde(default)]
+    pub auto_priority_min: u32,
+
+    /// 自动调整的池优先级上限（默认 100，没有可用凭据的池取该值）
+    #[serde(default = "default_auto_priority_max")]
+    pub auto_priority_max: u32,
+
+    /// 功能开关初始值（默认为空，未列出的开关使用内置默认值）
+    ///
+    /// 可用开关见 `common::features::KNOWN_FLAGS`；运行时通过 Admin API 切换后
+    /// 写入配置目录下的 features.json，并优先于此处的值
+    #[serde(default)]
+    pub feature_flags: HashMap<String, bool>,
 }
 
 fn default_host() -> String {
@@ -133,6 +677,16 @@ fn default_port() -> u16 {
     8080
 }
 
+/// 客户端 API Key 最小长度
+const MIN_API_KEY_CHARS: usize = 8;
+
+/// 会话缓存最小容量（过小时粘性会话几乎不生效）
+const MIN_SESSION_CACHE_CAPACITY: u64 = 10;
+
+fn default_max_api_keys() -> usize {
+    1000
+}
+
 fn default_region() -> String {
     "us-east-1".to_string()
 }
@@ -166,6 +720,34 @@ fn default_session_cache_ttl_secs() -> u64 {
     3600
 }
 
+fn default_stickiness_system_hash_warn_percent() -> u8 {
+    50
+}
+
+fn default_user_max_share() -> f64 {
+    0.5
+}
+
+fn default_credential_throttle_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_timeline_max_events_per_credential() -> usize {
+    1000
+}
+
+fn default_warmup_on_startup() -> bool {
+    false
+}
+
+fn default_warmup_concurrency() -> usize {
+    8
+}
+
+fn default_test_timeout_secs() -> u64 {
+    15
+}
+
 fn default_health_check_interval_secs() -> u64 {
     600 // 10 分钟
 }
@@ -190,6 +772,87 @@ fn default_rate_limit_per_key_per_hour() -> u64 {
     500
 }
 
+fn default_rate_limit_stats_enabled() -> bool {
+    true
+}
+
+fn default_token_bucket_capacity() -> u64 {
+    60
+}
+
+fn default_token_bucket_refill_per_second() -> f64 {
+    1.0
+}
+
+fn default_websearch_rate_limit_per_hour() -> u64 {
+    100
+}
+
+fn default_queue_max_wait_secs() -> u64 {
+    300
+}
+
+fn default_quota_queue_max_size() -> usize {
+    100
+}
+
+fn default_dedup_max_wait_secs() -> u64 {
+    30
+}
+
+fn default_upstream_queue_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_request_compression_min_bytes() -> usize {
+    256 * 1024
+}
+
+fn default_sse_replay_buffer_size() -> usize {
+    0
+}
+
+fn default_decoder_buffer_size_bytes() -> usize {
+    DEFAULT_MAX_BUFFER_SIZE
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_refresh_request_timeout_secs() -> u64 {
+    60
+}
+
+fn default_upstream_first_byte_timeout_secs() -> u64 {
+    180
+}
+
+fn default_upstream_idle_timeout_secs() -> u64 {
+    300
+}
+
+/// connectTimeoutSecs 取值范围（秒）
+pub const CONNECT_TIMEOUT_RANGE: RangeInclusive<u64> = 1..=120;
+/// refreshRequestTimeoutSecs 取值范围（秒）
+pub const REFRESH_REQUEST_TIMEOUT_RANGE: RangeInclusive<u64> = 1..=600;
+/// upstreamFirstByteTimeoutSecs 取值范围（秒）
+pub const UPSTREAM_FIRST_BYTE_TIMEOUT_RANGE: RangeInclusive<u64> = 1..=3600;
+/// upstreamIdleTimeoutSecs 取值范围（秒）
+pub const UPSTREAM_IDLE_TIMEOUT_RANGE: RangeInclusive<u64> = 1..=3600;
+
+fn default_max_document_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_max_image_bytes() -> usize {
+    5 * 1024 * 1024
+}
+
+fn default_max_request_image_bytes() -> usize {
+    20 * 1024 * 1024
+}
+
 fn default_history_management_enabled() -> bool {
     true
 }
@@ -210,6 +873,34 @@ fn default_history_keep_recent_messages() -> usize {
     20
 }
 
+fn default_prompt_caching_notice_enabled() -> bool {
+    true
+}
+
+fn default_openapi_enabled() -> bool {
+    true
+}
+
+fn default_atomic_writes() -> bool {
+    true
+}
+
+fn default_backup_interval_hours() -> u64 {
+    24
+}
+
+fn default_backup_retention() -> usize {
+    7
+}
+
+fn default_pool_priority_check_interval_secs() -> u64 {
+    60
+}
+
+fn default_auto_priority_max() -> u32 {
+    100
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -221,37 +912,102 @@ impl Default for Config {
             system_version: default_system_version(),
             node_version: default_node_version(),
             tls_backend: default_tls_backend(),
+            tls_min_version: TlsVersion::default(),
+            tls_require_certificate_transparency: false,
             count_tokens_api_url: None,
             count_tokens_api_key: None,
             count_tokens_auth_type: default_count_tokens_auth_type(),
             proxy_url: None,
             proxy_username: None,
             proxy_password: None,
+            api_key: None,
+            max_api_keys: default_max_api_keys(),
             admin_api_key: None,
+            admin_ui_csp: None,
+            admin_mtls_ca_path: None,
+            admin_mtls_required: false,
+            admin_port: None,
+            admin_tls_cert_path: None,
+            admin_tls_key_path: None,
+            default_locale: Locale::default(),
             session_cache_max_capacity: default_session_cache_max_capacity(),
             session_cache_ttl_secs: default_session_cache_ttl_secs(),
+            stickiness_system_hash_warn_percent: default_stickiness_system_hash_warn_percent(),
+            user_fairness_enabled: false,
+            user_max_share: default_user_max_share(),
+            credential_dirs: Vec::new(),
+            credential_throttle_timeout_ms: default_credential_throttle_timeout_ms(),
+            timeline_max_events_per_credential: default_timeline_max_events_per_credential(),
             health_check_interval_secs: default_health_check_interval_secs(),
+            warmup_on_startup: default_warmup_on_startup(),
+            warmup_concurrency: default_warmup_concurrency(),
+            test_timeout_secs: default_test_timeout_secs(),
             rate_limit_enabled: default_rate_limit_enabled(),
             rate_limit_per_minute: default_rate_limit_per_minute(),
             rate_limit_per_hour: default_rate_limit_per_hour(),
             rate_limit_per_key_per_minute: default_rate_limit_per_key_per_minute(),
             rate_limit_per_key_per_hour: default_rate_limit_per_key_per_hour(),
+            rate_limiter_type: RateLimiterType::default(),
+            token_bucket_capacity: default_token_bucket_capacity(),
+            token_bucket_refill_per_second: default_token_bucket_refill_per_second(),
+            rate_limit_exemptions: Vec::new(),
+            rate_limit_stats_enabled: default_rate_limit_stats_enabled(),
+            websearch_rate_limit_per_hour: default_websearch_rate_limit_per_hour(),
+            quota_queue_enabled: false,
+            queue_max_wait_secs: default_queue_max_wait_secs(),
+            quota_queue_max_size: default_quota_queue_max_size(),
+            dedup_enabled: false,
+            dedup_max_wait_secs: default_dedup_max_wait_secs(),
+            max_concurrent_upstream_requests: 0,
+            upstream_queue_timeout_ms: default_upstream_queue_timeout_ms(),
+            request_compression_enabled: false,
+            request_compression_min_bytes: default_request_compression_min_bytes(),
+            sse_replay_buffer_size: default_sse_replay_buffer_size(),
+            decoder_buffer_size_bytes: default_decoder_buffer_size_bytes(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            refresh_request_timeout_secs: default_refresh_request_timeout_secs(),
+            upstream_first_byte_timeout_secs: default_upstream_first_byte_timeout_secs(),
+            upstream_idle_timeout_secs: default_upstream_idle_timeout_secs(),
+            upstream_base_url: None,
+            kiro_api_base_url: None,
+            refresh_base_url_social: None,
+            refresh_base_url_idc: None,
+            max_document_bytes: default_max_document_bytes(),
+            max_image_bytes: default_max_image_bytes(),
+            max_request_image_bytes: default_max_request_image_bytes(),
+            suppress_conversion_warnings: false,
             history_management_enabled: default_history_management_enabled(),
             history_truncate_threshold: default_history_truncate_threshold(),
             history_enable_ai_summary: default_history_enable_ai_summary(),
             history_enable_image_placeholder: default_history_enable_image_placeholder(),
             history_keep_recent_messages: default_history_keep_recent_messages(),
+            system_prompt_rules: Vec::new(),
+            prompt_caching_notice_enabled: default_prompt_caching_notice_enabled(),
+            openapi_enabled: default_openapi_enabled(),
+            atomic_writes: default_atomic_writes(),
+            backup_dir: None,
+            backup_interval_hours: default_backup_interval_hours(),
+            backup_retention: default_backup_retention(),
+            auto_adjust_pool_priority: false,
+            pool_priority_check_interval_secs: default_pool_priority_check_interval_secs(),
+            auto_priority_min: 0,
+            auto_priority_max: default_auto_priority_max(),
+            feature_flags: HashMap::new(),
         }
     }
 }
 
 impl Config {
     /// 获取默认配置文件路径
+    ///
+    /// 也可通过 `--config` 指定 YAML 配置（`.yaml`/`.yml`），格式按扩展名自动识别
     pub fn default_config_path() -> &'static str {
         "config/config.json"
     }
 
     /// 从文件加载配置
+    ///
+    /// 按扩展名识别格式：`.yaml`/`.yml` 为 YAML，`.json` 为 JSON，其他先尝试 JSON 再尝试 YAML
     pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
         let path = path.as_ref();
         if !path.exists() {
@@ -260,22 +1016,200 @@ impl Config {
         }
 
         let content = fs::read_to_string(path)?;
-        let config: Config = serde_json::from_str(&content)?;
-        Ok(config)
+        parse_by_path(path, &content)
     }
 
-    /// 保存配置到文件
+    /// 从 YAML 文件加载配置
+    #[allow(dead_code)]
+    pub fn load_yaml<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        FileFormat::Yaml.parse(&content)
+    }
+
+    /// 保存配置到文件（格式按扩展名识别，无法识别时使用 JSON）
     pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
-        let content = serde_json::to_string_pretty(self)?;
-        fs::write(path, content)?;
+        let path = path.as_ref();
+        self.save_as(path, FileFormat::for_write(path))
+    }
+
+    /// 以 YAML 格式保存配置到文件
+    #[allow(dead_code)]
+    pub fn save_yaml<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        self.save_as(path.as_ref(), FileFormat::Yaml)
+    }
+
+    fn save_as(&self, path: &Path, format: FileFormat) -> anyhow::Result<()> {
+        let content = format.to_string(self)?;
+        if self.atomic_writes {
+            atomic_write(path, content)?;
+        } else {
+            fs::write(path, content)?;
+        }
+        Ok(())
+    }
+
+    /// 额外凭据目录的路径（相对路径相对于配置文件所在目录）
+    pub fn credential_dir_paths(&self, config_dir: &Path) -> Vec<PathBuf> {
+        self.credential_dirs
+            .iter()
+            .map(|dir| config_dir.join(dir))
+            .collect()
+    }
+
+    /// 构建 HTTP Client 使用的 TLS 配置
+    pub fn tls_options(&self) -> TlsOptions {
+        TlsOptions {
+            backend: self.tls_backend,
+            min_version: self.tls_min_version,
+        }
+    }
+
+    /// Token 刷新和额度查询请求的超时配置
+    pub fn refresh_http_timeouts(&self) -> HttpTimeouts {
+        HttpTimeouts::total(
+            Duration::from_secs(self.connect_timeout_secs),
+            Duration::from_secs(self.refresh_request_timeout_secs),
+        )
+    }
+
+    /// 对话请求的超时配置（只限制建立连接，首字节超时由 Provider 单独控制）
+    pub fn upstream_http_timeouts(&self) -> HttpTimeouts {
+        HttpTimeouts::streaming(Duration::from_secs(self.connect_timeout_secs))
+    }
+
+    /// 对话请求等待上游响应首字节的超时
+    pub fn upstream_first_byte_timeout(&self) -> Duration {
+        Duration::from_secs(self.upstream_first_byte_timeout_secs)
+    }
+
+    /// 读取上游响应体的空闲超时
+    pub fn upstream_idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.upstream_idle_timeout_secs)
+    }
+
+    /// 检查超时配置是否在允许范围内（全局配置和池级覆盖共用）
+    pub fn timeout_errors(&self) -> Vec<String> {
+        [
+            (
+                "connectTimeoutSecs",
+                self.connect_timeout_secs,
+                CONNECT_TIMEOUT_RANGE,
+            ),
+            (
+                "refreshRequestTimeoutSecs",
+                self.refresh_request_timeout_secs,
+                REFRESH_REQUEST_TIMEOUT_RANGE,
+            ),
+            (
+                "upstreamFirstByteTimeoutSecs",
+                self.upstream_first_byte_timeout_secs,
+                UPSTREAM_FIRST_BYTE_TIMEOUT_RANGE,
+            ),
+            (
+                "upstreamIdleTimeoutSecs",
+                self.upstream_idle_timeout_secs,
+                UPSTREAM_IDLE_TIMEOUT_RANGE,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, value, range)| !range.contains(value))
+        .map(|(name, value, range)| {
+            format!(
+                "{} 必须在 {}-{} 之间，当前值: {}",
+                name,
+                range.start(),
+                range.end(),
+                value
+            )
+        })
+        .collect()
+    }
+
+    /// Kiro API 端点（对话、MCP、额度查询），默认 `q.{region}.amazonaws.com`
+    pub fn kiro_api_endpoint(&self) -> UpstreamEndpoint {
+        self.endpoint(
+            self.kiro_api_base_url.as_deref(),
+            format!("q.{}.amazonaws.com", self.region),
+        )
+    }
+
+    /// Social Token 刷新端点，默认 `prod.{region}.auth.desktop.kiro.dev`
+    pub fn refresh_social_endpoint(&self, region: &str) -> UpstreamEndpoint {
+        self.endpoint(
+            self.refresh_base_url_social.as_deref(),
+            format!("prod.{}.auth.desktop.kiro.dev", region),
+        )
+    }
+
+    /// IdC Token 刷新端点，默认 `oidc.{region}.amazonaws.com`
+    pub fn refresh_idc_endpoint(&self, region: &str) -> UpstreamEndpoint {
+        self.endpoint(
+            self.refresh_base_url_idc.as_deref(),
+            format!("oidc.{}.amazonaws.com", region),
+        )
+    }
+
+    /// 解析上游端点
+    ///
+    /// 请求地址优先级：`upstream_base_url` > 端点覆盖地址 > `https://{default_host}`；
+    /// Host 请求头取端点覆盖地址的主机名，未配置时使用默认域名
+    fn endpoint(&self, override_url: Option<&str>, default_host: String) -> UpstreamEndpoint {
+        let override_url = override_url
+            .filter(|u| !u.is_empty())
+            .and_then(|u| reqwest::Url::parse(u).ok());
+        let host = override_url
+            .as_ref()
+            .and_then(|u| {
+                let host = u.host_str()?;
+                Some(match u.port() {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host.to_string(),
+                })
+            })
+            .unwrap_or(default_host);
+        let base_url = match self.upstream_base_url.as_deref().filter(|u| !u.is_empty()) {
+            Some(base_url) => base_url.trim_end_matches('/').to_string(),
+            None => match override_url {
+                Some(url) => url.as_str().trim_end_matches('/').to_string(),
+                None => format!("https://{}", host),
+            },
+        };
+        UpstreamEndpoint { host, base_url }
+    }
+
+    /// 检查客户端 API Key 格式（至少 8 个字符，不含空白字符）
+    pub fn validate_api_key(api_key: &str) -> Result<(), LocalizedError> {
+        if api_key.chars().count() < MIN_API_KEY_CHARS {
+            return Err(ErrorCode::ApiKeyTooShort.arg("min", MIN_API_KEY_CHARS));
+        }
+        if api_key.chars().any(char::is_whitespace) {
+            return Err(ErrorCode::ApiKeyWhitespace.into());
+        }
+        Ok(())
+    }
+
+    /// 校验会话缓存容量（配置文件、Admin 配置更新和池级覆盖共用）
+    pub fn validate_session_cache_capacity(capacity: u64) -> Result<(), LocalizedError> {
+        if capacity < MIN_SESSION_CACHE_CAPACITY {
+            return Err(ErrorCode::SessionCacheCapacityTooSmall
+                .arg("min", MIN_SESSION_CACHE_CAPACITY)
+                .arg("value", capacity));
+        }
         Ok(())
     }
 
     /// 验证配置有效性
     ///
-    /// 检查必填字段和格式是否正确
-    pub fn validate(&self) -> Result<(), Vec<String>> {
+    /// 检查必填字段、格式以及字段间的一致性。
+    /// 成功时返回警告（通常是配置失误但不影响启动），失败时返回错误
+    pub fn validate(&self) -> Result<Vec<String>, Vec<String>> {
         let mut errors = Vec::new();
+        let mut warnings = Vec::new();
 
         // 检查 host
         if self.host.trim().is_empty() {
@@ -292,6 +1226,13 @@ impl Config {
             errors.push("region 不能为空".to_string());
         }
 
+        // 检查 apiKey
+        if let Some(ref api_key) = self.api_key
+            && let Err(e) = Self::validate_api_key(api_key)
+        {
+            errors.push(e.to_string());
+        }
+
         // 检查代理 URL 格式
         if let Some(ref proxy_url) = self.proxy_url {
             if !proxy_url.is_empty()
@@ -306,19 +1247,90 @@ impl Config {
             }
         }
 
+        // 证书透明度无法校验，拒绝启用而不是静默忽略
+        if self.tls_require_certificate_transparency {
+            errors.push(
+                "tlsRequireCertificateTransparency 不受支持：当前 TLS 后端无法校验证书透明度，请移除该配置"
+                    .to_string(),
+            );
+        }
+
+        // 检查 Admin mTLS
+        if self.admin_mtls_ca_path.is_some() {
+            match self.admin_port {
+                None | Some(0) => {
+                    errors.push("启用 Admin mTLS（adminMtlsCaPath）需要配置 adminPort".to_string())
+                }
+                Some(port) if port == self.port => errors.push(format!(
+                    "adminPort ({}) 不能与 port 相同",
+                    port
+                )),
+                Some(_) => {}
+            }
+            if self.admin_tls_cert_path.is_none() || self.admin_tls_key_path.is_none() {
+                errors.push(
+                    "启用 Admin mTLS（adminMtlsCaPath）需要配置 adminTlsCertPath 和 adminTlsKeyPath"
+                        .to_string(),
+                );
+            }
+        } else if self.admin_mtls_required || self.admin_port.is_some() {
+            warnings.push(
+                "未配置 adminMtlsCaPath，adminMtlsRequired 和 adminPort 不生效".to_string(),
+            );
+        }
+
         // 检查缓存配置
-        if self.session_cache_max_capacity == 0 {
-            errors.push("sessionCacheMaxCapacity 不能为 0".to_string());
+        if let Err(e) = Self::validate_session_cache_capacity(self.session_cache_max_capacity) {
+            errors.push(e.to_string());
         }
 
         if self.session_cache_ttl_secs == 0 {
             errors.push("sessionCacheTtlSecs 不能为 0".to_string());
         }
 
+        if self.stickiness_system_hash_warn_percent > 100 {
+            errors.push(format!(
+                "stickinessSystemHashWarnPercent 必须在 0-100 范围内，当前值: {}",
+                self.stickiness_system_hash_warn_percent
+            ));
+        }
+
+        if !(self.user_max_share > 0.0 && self.user_max_share <= 1.0) {
+            errors.push(format!(
+                "userMaxShare 必须在 (0, 1] 范围内，当前值: {}",
+                self.user_max_share
+            ));
+        }
+
         // 检查健康检查间隔
         if self.health_check_interval_secs == 0 {
             errors.push("healthCheckIntervalSecs 不能为 0".to_string());
         }
+        if self.backup_dir.is_some() {
+            if self.backup_interval_hours == 0 {
+                errors.push("backupIntervalHours 不能为 0".to_string());
+            }
+            if self.backup_retention == 0 {
+                errors.push("backupRetention 不能为 0".to_string());
+            }
+        }
+        if self.auto_adjust_pool_priority {
+            if self.pool_priority_check_interval_secs == 0 {
+                errors.push("poolPriorityCheckIntervalSecs 不能为 0".to_string());
+            }
+            if self.auto_priority_min > self.auto_priority_max {
+                errors.push(format!(
+                    "autoPriorityMin ({}) 不能大于 autoPriorityMax ({})",
+                    self.auto_priority_min, self.auto_priority_max
+                ));
+            }
+        }
+        if self.warmup_concurrency == 0 {
+            errors.push("warmupConcurrency 不能为 0".to_string());
+        }
+        if self.test_timeout_secs == 0 {
+            errors.push("testTimeoutSecs 不能为 0".to_string());
+        }
 
         // 检查限流配置
         if self.rate_limit_enabled {
@@ -334,6 +1346,98 @@ impl Config {
             if self.rate_limit_per_key_per_hour == 0 {
                 errors.push("rateLimitPerKeyPerHour 不能为 0".to_string());
             }
+            if self.rate_limit_per_key_per_minute > self.rate_limit_per_minute {
+                warnings.push(format!(
+                    "rateLimitPerKeyPerMinute ({}) 大于 rateLimitPerMinute ({})，单个 API Key 的限额不会生效",
+                    self.rate_limit_per_key_per_minute, self.rate_limit_per_minute
+                ));
+            }
+            if self.rate_limiter_type == RateLimiterType::TokenBucket {
+                if self.token_bucket_capacity == 0 {
+                    errors.push("tokenBucketCapacity 不能为 0".to_string());
+                }
+                if !(self.token_bucket_refill_per_second.is_finite()
+                    && self.token_bucket_refill_per_second > 0.0)
+                {
+                    errors.push(format!(
+                        "tokenBucketRefillPerSecond 必须为正数，当前值: {}",
+                        self.token_bucket_refill_per_second
+                    ));
+                }
+            }
+        }
+        for (index, exemption) in self.rate_limit_exemptions.iter().enumerate() {
+            if let Err(e) = exemption.validate() {
+                errors.push(format!("rateLimitExemptions #{}: {}", index + 1, e));
+            }
+        }
+
+        // 检查额度用尽排队配置
+        if self.quota_queue_enabled {
+            if self.queue_max_wait_secs == 0 {
+                errors.push("queueMaxWaitSecs 不能为 0".to_string());
+            }
+            if self.quota_queue_max_size == 0 {
+                errors.push("quotaQueueMaxSize 不能为 0".to_string());
+            }
+        }
+
+        // 检查请求去重配置
+        if self.dedup_enabled && self.dedup_max_wait_secs == 0 {
+            errors.push("dedupMaxWaitSecs 不能为 0".to_string());
+        }
+
+        // 检查上游端点覆盖地址
+        if let Some(ref base_url) = self.upstream_base_url
+            && !base_url.is_empty()
+            && !base_url.starts_with("http://")
+            && !base_url.starts_with("https://")
+        {
+            errors.push(format!(
+                "upstreamBaseUrl 格式不正确: {}，应以 http:// 或 https:// 开头",
+                base_url
+            ));
+        }
+
+        for (name, url) in [
+            ("kiroApiBaseUrl", &self.kiro_api_base_url),
+            ("refreshBaseUrlSocial", &self.refresh_base_url_social),
+            ("refreshBaseUrlIdc", &self.refresh_base_url_idc),
+        ] {
+            if let Some(url) = url.as_deref().filter(|u| !u.is_empty()) {
+                let valid = reqwest::Url::parse(url)
+                    .map(|u| u.scheme() == "https" && u.host_str().is_some())
+                    .unwrap_or(false);
+                if !valid {
+                    errors.push(format!(
+                        "{} 格式不正确: {}，应为 https:// 开头的有效 URL",
+                        name, url
+                    ));
+                }
+            }
+        }
+
+        if self.decoder_buffer_size_bytes == 0 {
+            errors.push("decoderBufferSizeBytes 不能为 0".to_string());
+        }
+        errors.extend(self.timeout_errors());
+        if self.max_document_bytes == 0 {
+            errors.push("maxDocumentBytes 不能为 0".to_string());
+        }
+        if self.max_image_bytes == 0 {
+            errors.push("maxImageBytes 不能为 0".to_string());
+        }
+        if self.max_request_image_bytes < self.max_image_bytes {
+            errors.push("maxRequestImageBytes 不能小于 maxImageBytes".to_string());
+        }
+
+        if self
+            .count_tokens_api_url
+            .as_deref()
+            .is_some_and(|url| !url.is_empty())
+            && self.count_tokens_api_key.is_none()
+        {
+            warnings.push("已配置 countTokensApiUrl 但未配置 countTokensApiKey".to_string());
         }
 
         // 检查 count_tokens_auth_type
@@ -350,15 +1454,504 @@ impl Config {
             if self.history_truncate_threshold == 0 {
                 errors.push("historyTruncateThreshold 不能为 0".to_string());
             }
+            if self.history_truncate_threshold > CONTEXT_WINDOW_SIZE as u64 {
+                warnings.push(format!(
+                    "historyTruncateThreshold ({}) 大于上下文窗口 ({})，历史截断永远不会触发",
+                    self.history_truncate_threshold, CONTEXT_WINDOW_SIZE
+                ));
+            }
             if self.history_keep_recent_messages == 0 {
                 errors.push("historyKeepRecentMessages 不能为 0".to_string());
             }
         }
 
+        // 检查 system prompt 改写规则
+        for (index, rule) in self.system_prompt_rules.iter().enumerate() {
+            let label = rule
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("#{}", index + 1));
+            if rule.pattern.is_empty() {
+                errors.push(format!("systemPromptRules {} 的 pattern 不能为空", label));
+            }
+            if rule.match_type == SystemPromptMatch::Regex
+                && let Err(e) = regex::Regex::new(&rule.pattern)
+            {
+                errors.push(format!("systemPromptRules {} 的正则无效: {}", label, e));
+            }
+        }
+
+        // 检查功能开关名称
+        let mut unknown_flags: Vec<&str> = self
+            .feature_flags
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !features::is_known(name))
+            .collect();
+        unknown_flags.sort_unstable();
+        for name in unknown_flags {
+            errors.push(format!("featureFlags 包含未知开关: {}", name));
+        }
+
         if errors.is_empty() {
-            Ok(())
+            Ok(warnings)
         } else {
             Err(errors)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const JSON_CONFIG: &str = r#"{
+        "host": "0.0.0.0",
+        "port": 9090,
+        "region": "eu-west-1",
+        "tlsBackend": "rustls",
+        "adminApiKey": "sk-admin",
+        "systemVersion": "darwin#24.6.0",
+        "proxyUrl": "socks5://127.0.0.1:1080",
+        "countTokensAuthType": "bearer"
+    }"#;
+
+    const YAML_CONFIG: &str = "
+host: 0.0.0.0
+port: 9090
+region: eu-west-1
+tlsBackend: rustls
+adminApiKey: sk-admin
+systemVersion: darwin#24.6.0
+proxyUrl: socks5://127.0.0.1:1080
+countTokensAuthType: bearer
+";
+
+    fn to_value(config: &Config) -> serde_json::Value {
+        serde_json::to_value(config).unwrap()
+    }
+
+    #[test]
+    fn test_yaml_config_roundtrip_matches_json() {
+        let dir = tempdir().unwrap();
+        let json_path = dir.path().join("config.json");
+        let yaml_path = dir.path().join("config.yaml");
+        fs::write(&json_path, JSON_CONFIG).unwrap();
+        fs::write(&yaml_path, YAML_CONFIG).unwrap();
+
+        let from_json = Config::load(&json_path).unwrap();
+        let from_yaml = Config::load(&yaml_path).unwrap();
+        assert_eq!(from_yaml.port, 9090);
+        assert_eq!(from_yaml.region, "eu-west-1");
+        assert_eq!(to_value(&from_yaml), to_value(&from_json));
+
+        // save 按扩展名保持 YAML 格式
+        from_yaml.save(&yaml_path).unwrap();
+        let saved = fs::read_to_string(&yaml_path).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&saved).is_err());
+        assert_eq!(
+            to_value(&Config::load_yaml(&yaml_path).unwrap()),
+            to_value(&from_json)
+        );
+
+        // save_yaml 写入 .yml 后仍可自动识别
+        let yml_path = dir.path().join("config.yml");
+        from_json.save_yaml(&yml_path).unwrap();
+        assert_eq!(
+            to_value(&Config::load(&yml_path).unwrap()),
+            to_value(&from_json)
+        );
+    }
+
+    #[test]
+    fn test_load_unknown_extension_falls_back_to_yaml() {
+        let dir = tempdir().unwrap();
+
+        let path = dir.path().join("config.conf");
+        fs::write(&path, YAML_CONFIG).unwrap();
+        assert_eq!(Config::load(&path).unwrap().port, 9090);
+
+        fs::write(&path, JSON_CONFIG).unwrap();
+        assert_eq!(Config::load(&path).unwrap().port, 9090);
+
+        // 未知扩展名写入 JSON
+        Config::default().save(&path).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&saved).is_ok());
+    }
+
+    #[test]
+    fn test_system_prompt_rules_parse_and_validate() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "systemPromptRules": [
+                    {
+                        "name": "strip-preamble",
+                        "match": "prefix",
+                        "pattern": "You are Claude Code",
+                        "action": {"type": "truncate", "maxChars": 10}
+                    },
+                    {"match": "regex", "pattern": "(", "action": {"type": "drop"}},
+                    {"match": "exact", "pattern": "x", "action": {"type": "replace", "with": "y"}}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let rules = &config.system_prompt_rules;
+        assert_eq!(rules[0].name.as_deref(), Some("strip-preamble"));
+        assert_eq!(rules[0].match_type, SystemPromptMatch::Prefix);
+        assert_eq!(
+            rules[0].action,
+            SystemPromptAction::Truncate { max_chars: 10 }
+        );
+        assert_eq!(
+            rules[2].action,
+            SystemPromptAction::Replace {
+                with: "y".to_string()
+            }
+        );
+
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("#2"));
+    }
+
+    #[test]
+    fn test_api_key_parse_and_serialize() {
+        let config: Config = serde_json::from_str(r#"{"apiKey": "sk-kiro-rs-123456"}"#).unwrap();
+        assert_eq!(config.api_key.as_deref(), Some("sk-kiro-rs-123456"));
+        assert!(config.validate().is_ok());
+
+        // 未配置时不写入文件
+        let saved = serde_json::to_value(Config::default()).unwrap();
+        assert!(saved.get("apiKey").is_none());
+    }
+
+    #[test]
+    fn test_api_key_too_short() {
+        let config = Config {
+            api_key: Some("sk-1234".to_string()),
+            ..Config::default()
+        };
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("apiKey"));
+
+        // 按字符计数
+        assert!(Config::validate_api_key("密钥密钥密钥密钥").is_ok());
+    }
+
+    #[test]
+    fn test_api_key_with_whitespace() {
+        for api_key in ["sk-kiro rs-123", "sk-kiro-rs-123\n", "\tsk-kiro-rs-123"] {
+            let config = Config {
+                api_key: Some(api_key.to_string()),
+                ..Config::default()
+            };
+            let errors = config.validate().unwrap_err();
+            assert_eq!(errors.len(), 1, "{:?}", api_key);
+            assert!(errors[0].contains("空白字符"));
+        }
+    }
+
+    #[test]
+    fn test_validate_rate_limit_consistency_warning() {
+        let config = Config {
+            rate_limit_enabled: true,
+            rate_limit_per_minute: 20,
+            rate_limit_per_key_per_minute: 30,
+            ..Config::default()
+        };
+        let warnings = config.validate().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("rateLimitPerKeyPerMinute"));
+
+        // 默认配置没有警告
+        assert!(Config::default().validate().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_history_threshold_above_context_window() {
+        let config = Config {
+            history_truncate_threshold: CONTEXT_WINDOW_SIZE as u64 + 1,
+            ..Config::default()
+        };
+        let warnings = config.validate().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("historyTruncateThreshold"));
+
+        // 未启用历史管理时不检查
+        let config = Config {
+            history_management_enabled: false,
+            ..config
+        };
+        assert!(config.validate().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_session_cache_capacity_minimum() {
+        let config = Config {
+            session_cache_max_capacity: 9,
+            ..Config::default()
+        };
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("sessionCacheMaxCapacity"));
+
+        let config = Config {
+            session_cache_max_capacity: 10,
+            ..Config::default()
+        };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_count_tokens_url_without_key() {
+        let config = Config {
+            count_tokens_api_url: Some("https://example.com/count_tokens".to_string()),
+            ..Config::default()
+        };
+        let warnings = config.validate().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("countTokensApiKey"));
+
+        let config = Config {
+            count_tokens_api_key: Some("secret".to_string()),
+            ..config
+        };
+        assert!(config.validate().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_rejects_certificate_transparency() {
+        let config: Config =
+            serde_json::from_str(r#"{"tlsRequireCertificateTransparency": true}"#).unwrap();
+        let errors = config.validate().unwrap_err();
+        assert!(errors[0].contains("tlsRequireCertificateTransparency"));
+    }
+
+    #[test]
+    fn test_validate_admin_mtls() {
+        let config = Config {
+            admin_mtls_ca_path: Some("admin-ca.pem".to_string()),
+            ..Config::default()
+        };
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("adminPort"));
+        assert!(errors[1].contains("adminTlsCertPath"));
+
+        // Admin 端口必须与主端口不同
+        let config = Config {
+            admin_port: Some(config.port),
+            admin_tls_cert_path: Some("admin.pem".to_string()),
+            admin_tls_key_path: Some("admin.key".to_string()),
+            ..config
+        };
+        assert!(config.validate().unwrap_err()[0].contains("不能与 port 相同"));
+
+        let config = Config {
+            admin_port: Some(config.port + 1),
+            ..config
+        };
+        assert!(config.validate().unwrap().is_empty());
+
+        // 未启用 mTLS 时 adminPort 不生效
+        let config = Config {
+            admin_port: Some(8991),
+            ..Config::default()
+        };
+        assert!(config.validate().unwrap()[0].contains("adminMtlsCaPath"));
+    }
+
+    #[test]
+    fn test_user_max_share_range() {
+        let config: Config =
+            serde_json::from_str(r#"{"userFairnessEnabled": true, "userMaxShare": 0.25}"#).unwrap();
+        assert!(config.user_fairness_enabled);
+        assert!(config.validate().is_ok());
+
+        for share in [0.0, 1.5] {
+            let config = Config {
+                user_max_share: share,
+                ..Config::default()
+            };
+            let errors = config.validate().unwrap_err();
+            assert!(errors[0].contains("userMaxShare"));
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_exemption_matching() {
+        let config: Config = serde_json::from_str(
+            r#"{"rateLimitExemptions": [
+                {"keyPrefix": "sk-internal-", "reason": "内部服务"},
+                {"cidr": "10.0.0.0/8", "reason": "内网"}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let [by_key, by_ip] = &config.rate_limit_exemptions[..] else {
+            panic!("应解析出两条豁免规则");
+        };
+        assert!(by_key.matches(Some("sk-internal-abc"), None));
+        assert!(!by_key.matches(Some("sk-public-abc"), None));
+        assert!(by_ip.matches(None, Some("10.1.2.3".parse().unwrap())));
+        assert!(by_ip.matches(None, Some("::ffff:10.1.2.3".parse().unwrap())));
+        assert!(!by_ip.matches(None, Some("192.168.1.1".parse().unwrap())));
+
+        for invalid in [
+            r#"{"reason": "空规则"}"#,
+            r#"{"cidr": "10.0.0.0/33", "reason": "前缀过长"}"#,
+            r#"{"keyPrefix": "", "reason": "空前缀"}"#,
+        ] {
+            let exemption: RateLimitExemption = serde_json::from_str(invalid).unwrap();
+            assert!(exemption.validate().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_token_bucket_config_parse_and_validate() {
+        let config: Config = serde_json::from_str(
+            r#"{"rateLimiterType": "tokenBucket", "tokenBucketCapacity": 10, "tokenBucketRefillPerSecond": 2.5}"#,
+        )
+        .unwrap();
+        assert_eq!(config.rate_limiter_type, RateLimiterType::TokenBucket);
+        assert_eq!(config.token_bucket_capacity, 10);
+        assert_eq!(config.token_bucket_refill_per_second, 2.5);
+        assert!(config.validate().is_ok());
+
+        // 默认仍为滑动窗口
+        assert_eq!(
+            Config::default().rate_limiter_type,
+            RateLimiterType::SlidingWindow
+        );
+
+        let config = Config {
+            rate_limiter_type: RateLimiterType::TokenBucket,
+            token_bucket_refill_per_second: 0.0,
+            ..Config::default()
+        };
+        let errors = config.validate().unwrap_err();
+        assert!(errors[0].contains("tokenBucketRefillPerSecond"));
+    }
+
+    #[test]
+    fn test_timeout_config_parse_and_validate() {
+        let config: Config = serde_json::from_str(
+            r#"{"connectTimeoutSecs": 5, "refreshRequestTimeoutSecs": 30, "upstreamFirstByteTimeoutSecs": 900}"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.refresh_http_timeouts(),
+            HttpTimeouts::total(Duration::from_secs(5), Duration::from_secs(30))
+        );
+        // 对话请求不设整体超时
+        assert_eq!(config.upstream_http_timeouts().request, None);
+        assert_eq!(config.upstream_first_byte_timeout(), Duration::from_secs(900));
+        assert_eq!(config.upstream_idle_timeout(), Duration::from_secs(300));
+
+        let config = Config {
+            connect_timeout_secs: 0,
+            upstream_first_byte_timeout_secs: 7200,
+            upstream_idle_timeout_secs: 0,
+            ..Config::default()
+        };
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].contains("connectTimeoutSecs"));
+        assert!(errors[1].contains("upstreamFirstByteTimeoutSecs"));
+        assert!(errors[2].contains("upstreamIdleTimeoutSecs"));
+    }
+    #[test]
+    fn test_feature_flags_parse_and_validate() {
+        let config: Config = serde_json::from_str(
+            r#"{"featureFlags": {"enable_batch_messages": true, "enable_ai_summary": false}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.feature_flags.get("enable_batch_messages"),
+            Some(&true)
+        );
+        assert!(config.validate().is_ok());
+
+        let config: Config =
+            serde_json::from_str(r#"{"featureFlags": {"enable_magic": true}}"#).unwrap();
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("enable_magic"));
+    }
+
+    #[test]
+    fn test_upstream_endpoints() {
+        let mut config = Config {
+            region: "eu-west-1".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.kiro_api_endpoint(),
+            UpstreamEndpoint {
+                host: "q.eu-west-1.amazonaws.com".to_string(),
+                base_url: "https://q.eu-west-1.amazonaws.com".to_string(),
+            }
+        );
+        assert_eq!(
+            config
+                .refresh_social_endpoint("us-east-1")
+                .url("/refreshToken"),
+            "https://prod.us-east-1.auth.desktop.kiro.dev/refreshToken"
+        );
+        assert_eq!(
+            config.refresh_idc_endpoint("us-east-1").url("/token"),
+            "https://oidc.us-east-1.amazonaws.com/token"
+        );
+
+        config.kiro_api_base_url = Some("https://vpce.example.com/kiro/".to_string());
+        config.refresh_base_url_social = Some("https://auth.example.com".to_string());
+        config.refresh_base_url_idc = Some("https://oidc.example.com:8443".to_string());
+        assert_eq!(
+            config.kiro_api_endpoint().url("/mcp"),
+            "https://vpce.example.com/kiro/mcp"
+        );
+        assert_eq!(config.kiro_api_endpoint().host, "vpce.example.com");
+        assert_eq!(
+            config
+                .refresh_social_endpoint("us-east-1")
+                .url("/refreshToken"),
+            "https://auth.example.com/refreshToken"
+        );
+        let idc = config.refresh_idc_endpoint("us-east-1");
+        assert_eq!(idc.host, "oidc.example.com:8443");
+        assert_eq!(idc.url("/token"), "https://oidc.example.com:8443/token");
+
+        // upstreamBaseUrl 优先于端点覆盖地址，Host 仍取覆盖地址
+        config.upstream_base_url = Some("http://127.0.0.1:9000".to_string());
+        let endpoint = config.kiro_api_endpoint();
+        assert_eq!(endpoint.url("/mcp"), "http://127.0.0.1:9000/mcp");
+        assert_eq!(endpoint.host, "vpce.example.com");
+    }
+
+    #[test]
+    fn test_validate_endpoint_overrides() {
+        let config = Config {
+            kiro_api_base_url: Some("https://vpce.example.com".to_string()),
+            ..Config::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let config = Config {
+            kiro_api_base_url: Some("http://vpce.example.com".to_string()),
+            refresh_base_url_social: Some("not a url".to_string()),
+            refresh_base_url_idc: Some(String::new()),
+            ..Config::default()
+        };
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("kiroApiBaseUrl"));
+        assert!(errors[1].contains("refreshBaseUrlSocial"));
+    }
+}
@@ -0,0 +1,281 @@
+//! HTTP 服务监听管理
+//!
+//! 服务循环由监督任务持有，Admin 修改 host/port 时通过 [`ServerHandle::rebind`] 切换监听地址：
+//! - 先绑定新地址并开始服务，绑定失败（端口占用、权限不足等）时保留旧监听器
+//! - 绑定成功后旧监听器立即停止接受新连接，已有连接在请求结束后关闭
+//! - 旧连接最长等待 [`DRAIN_TIMEOUT`]，超时后不再等待
+//!
+//! 停止服务时（[`ServerSupervisor::serve_until`] 的 `shutdown` 完成）同样停止接受新连接并排空已有连接
+
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::Router;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// 切换监听地址或停止服务后等待旧连接结束的最长时间
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 监督任务指令
+enum Command {
+    /// 切换到新的监听地址
+    Rebind {
+        addr: String,
+        reply: oneshot::Sender<io::Result<SocketAddr>>,
+    },
+}
+
+/// 服务句柄（可克隆，用于通知监督任务切换监听地址）
+#[derive(Clone)]
+pub struct ServerHandle {
+    commands: mpsc::Sender<Command>,
+}
+
+impl ServerHandle {
+    /// 切换监听地址，返回实际绑定的地址
+    ///
+    /// 新地址绑定失败时返回错误，旧监听器继续服务
+    pub async fn rebind(&self, addr: &str) -> io::Result<SocketAddr> {
+        let stopped = || io::Error::other("服务监督任务已退出");
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(Command::Rebind {
+                addr: addr.to_string(),
+                reply,
+            })
+            .await
+            .map_err(|_| stopped())?;
+        response.await.map_err(|_| stopped())?
+    }
+}
+
+/// 服务监督任务
+pub struct ServerSupervisor {
+    commands: mpsc::Receiver<Command>,
+}
+
+/// 正在运行的监听器
+struct RunningServer {
+    addr: SocketAddr,
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<io::Result<()>>,
+}
+
+impl RunningServer {
+    /// 开始在监听器上服务
+    fn spawn(listener: TcpListener, app: Router) -> io::Result<Self> {
+        let addr = listener.local_addr()?;
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            // 附带连接信息，供限流豁免按客户端 IP 匹配
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+                .with_graceful_shutdown(async move {
+                    let _ = signal.await;
+                })
+                .await
+        });
+        Ok(Self {
+            addr,
+            shutdown,
+            task,
+        })
+    }
+
+    /// 停止接受新连接，等待已有连接结束（最长 `timeout`）
+    async fn drain(self, timeout: Duration) {
+        let Self {
+            addr,
+            shutdown,
+            mut task,
+        } = self;
+        let _ = shutdown.send(());
+        match tokio::time::timeout(timeout, &mut task).await {
+            Ok(_) => tracing::info!("监听地址 {} 已关闭", addr),
+            Err(_) => {
+                tracing::warn!(
+                    "监听地址 {} 的连接在 {} 秒内未全部结束，不再等待",
+                    addr,
+                    timeout.as_secs()
+                );
+                task.abort();
+            }
+        }
+    }
+}
+
+impl ServerSupervisor {
+    /// 创建监督任务和对应的句柄
+    pub fn new() -> (Self, ServerHandle) {
+        let (commands_tx, commands) = mpsc::channel(4);
+        let handle = ServerHandle {
+            commands: commands_tx,
+        };
+        (Self { commands }, handle)
+    }
+
+    /// 绑定地址并运行服务，`shutdown` 完成后排空连接并返回
+    pub async fn run_until(
+        self,
+        addr: &str,
+        app: Router,
+        shutdown: impl Future<Output = ()>,
+    ) -> io::Result<()> {
+        let listener = TcpListener::bind(addr).await?;
+        self.serve_until(listener, app, shutdown).await
+    }
+
+    /// 在已绑定的监听器上运行服务，处理监听地址切换
+    #[allow(dead_code)]
+    pub async fn serve(self, listener: TcpListener, app: Router) -> io::Result<()> {
+        self.serve_until(listener, app, std::future::pending())
+            .await
+    }
+
+    /// 在已绑定的监听器上运行服务，`shutdown` 完成后停止接受新连接，
+    /// 等待已有连接结束（最长 [`DRAIN_TIMEOUT`]）后返回
+    pub async fn serve_until(
+        mut self,
+        listener: TcpListener,
+        app: Router,
+        shutdown: impl Future<Output = ()>,
+    ) -> io::Result<()> {
+        let mut current = RunningServer::spawn(listener, app.clone())?;
+        let mut shutdown = std::pin::pin!(shutdown);
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => {
+                    current.drain(DRAIN_TIMEOUT).await;
+                    return Ok(());
+                }
+                command = self.commands.recv() => match command {
+                    Some(Command::Rebind { addr, reply }) => {
+                        let result = Self::rebind(&mut current, &addr, &app).await;
+                        let _ = reply.send(result);
+                    }
+                    // 所有句柄已释放：不再切换，继续服务当前监听器
+                    None => return flatten(current.task.await),
+                },
+                result = &mut current.task => return flatten(result),
+            }
+        }
+    }
+
+    /// 绑定新地址并切换，旧监听器在后台排空
+    async fn rebind(
+        current: &mut RunningServer,
+        addr: &str,
+        app: &Router,
+    ) -> io::Result<SocketAddr> {
+        let listener = match TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::warn!(
+                    "绑定新监听地址 {} 失败，继续使用 {}: {}",
+                    addr,
+                    current.addr,
+                    e
+                );
+                return Err(e);
+            }
+        };
+        let next = RunningServer::spawn(listener, app.clone())?;
+        let new_addr = next.addr;
+        let old = std::mem::replace(current, next);
+        tracing::info!("监听地址已切换: {} -> {}", old.addr, new_addr);
+
+        tokio::spawn(old.drain(DRAIN_TIMEOUT));
+        Ok(new_addr)
+    }
+}
+
+fn flatten(result: Result<io::Result<()>, tokio::task::JoinError>) -> io::Result<()> {
+    result.map_err(io::Error::other)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+
+    fn app() -> Router {
+        Router::new().route("/", get(|| async { "ok" })).route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                "slow"
+            }),
+        )
+    }
+
+    async fn start() -> (ServerHandle, SocketAddr) {
+        let (supervisor, handle) = ServerSupervisor::new();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(supervisor.serve(listener, app()));
+        (handle, addr)
+    }
+
+    async fn get_text(addr: SocketAddr, path: &str) -> reqwest::Result<String> {
+        reqwest::get(format!("http://{}{}", addr, path))
+            .await?
+            .text()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_rebind_drains_old_listener() {
+        let (handle, old_addr) = start().await;
+        assert_eq!(get_text(old_addr, "/").await.unwrap(), "ok");
+
+        // 切换期间进行中的请求正常完成
+        let in_flight = tokio::spawn(get_text(old_addr, "/slow"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let new_addr = handle.rebind("127.0.0.1:0").await.unwrap();
+        assert_ne!(new_addr, old_addr);
+        assert_eq!(get_text(new_addr, "/").await.unwrap(), "ok");
+        assert_eq!(in_flight.await.unwrap().unwrap(), "slow");
+
+        // 旧监听器不再接受新连接
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(tokio::net::TcpStream::connect(old_addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_connections() {
+        let (supervisor, _handle) = ServerSupervisor::new();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(supervisor.serve_until(listener, app(), async move {
+            let _ = stopped.await;
+        }));
+
+        let in_flight = tokio::spawn(get_text(addr, "/slow"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stop.send(()).unwrap();
+
+        assert_eq!(in_flight.await.unwrap().unwrap(), "slow");
+        server.await.unwrap().unwrap();
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rebind_failure_keeps_old_listener() {
+        let (handle, old_addr) = start().await;
+
+        let occupied = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let occupied_addr = occupied.local_addr().unwrap().to_string();
+        let err = handle.rebind(&occupied_addr).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(get_text(old_addr, "/").await.unwrap(), "ok");
+    }
+}
@@ -10,12 +10,12 @@
 //! # 缓存机制
 //! - 使用 moka 缓存计算结果（TTL 1小时，最大 10,000 条）
 //! - 缓存键：文本内容的 SHA256 哈希
+//! - 远程 API 结果单独缓存（TTL 10 分钟，最大 1,000 条），缓存键为请求体的 SHA256 哈希
 
 use crate::anthropic::types::{
-    CountTokensRequest, CountTokensResponse, Message, SystemMessage, Tool,
+    CountTokensRequest, CountTokensResponse, Message, SystemMessage, TokenCountSource, Tool,
 };
-use crate::http_client::{ProxyConfig, build_client};
-use crate::model::config::TlsBackend;
+use crate::http_client::{HttpTimeouts, ProxyConfig, TlsOptions, shared_client};
 use std::sync::OnceLock;
 use moka::sync::Cache;
 use std::time::Duration;
@@ -32,8 +32,8 @@ pub struct CountTokensConfig {
     pub auth_type: String,
     /// 代理配置
     pub proxy: Option<ProxyConfig>,
-
-    pub tls_backend: TlsBackend,
+    /// TLS 配置
+    pub tls: TlsOptions,
 }
 
 /// 全局配置存储
@@ -52,6 +52,24 @@ fn get_cache() -> &'static Cache<String, u64> {
     })
 }
 
+/// 远程 count_tokens API 超时（超时后回退到本地计算）
+const REMOTE_COUNT_TOKENS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 远程 count_tokens 结果缓存（TTL 10 分钟，最大 1,000 条）
+///
+/// Claude Code 会反复发送相同的 system prompt 和工具定义，
+/// 按请求内容哈希缓存可避免重复调用远程 API
+static REMOTE_CACHE: OnceLock<Cache<String, u64>> = OnceLock::new();
+
+fn get_remote_cache() -> &'static Cache<String, u64> {
+    REMOTE_CACHE.get_or_init(|| {
+        Cache::builder()
+            .max_capacity(1_000)
+            .time_to_live(Duration::from_secs(600))
+            .build()
+    })
+}
+
 /// 计算文本的哈希值（用于缓存键）
 fn calculate_hash(text: &str) -> String {
     let mut hasher = Sha256::new();
@@ -166,7 +184,28 @@ fn count_tokens_heuristic(text: &str) -> u64 {
     acc_token
 }
 
-/// 估算请求的输入 tokens
+/// 估算请求的输入 tokens（同步版本）
+///
+/// 配置了远程 API 时在当前线程阻塞等待 [`count_all_tokens_with_source`]，
+/// 否则直接本地计算
+pub(crate) fn count_all_tokens(
+    model: String,
+    system: Option<Vec<SystemMessage>>,
+    messages: Vec<Message>,
+    tools: Option<Vec<Tool>>,
+) -> u64 {
+    if get_config().is_some_and(|config| config.api_url.is_some()) {
+        let (tokens, _) = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current()
+                .block_on(count_all_tokens_with_source(model, system, messages, tools))
+        });
+        return tokens;
+    }
+
+    count_all_tokens_local(system, messages, tools)
+}
+
+/// 估算请求的输入 tokens，并返回计数来源
 ///
 /// # 三层计算策略
 /// 1. **远程 API**：调用 /v1/messages/count_tokens（精准度 95%+）
@@ -174,62 +213,74 @@ fn count_tokens_heuristic(text: &str) -> u64 {
 /// 3. **启发式算法**：本地计算（精准度 85-95%，兜底方案）
 ///
 /// # 优先级
-/// - 优先调用远程 API（如果配置了）
-/// - 失败时回退到官方 tokenizer
-/// - 官方 tokenizer 失败时回退到启发式算法
-pub(crate) fn count_all_tokens(
+/// - 优先调用远程 API（如果配置了），相同请求内容命中缓存时不再调用
+/// - 调用失败或超过 [`REMOTE_COUNT_TOKENS_TIMEOUT`] 时回退到本地计算
+pub(crate) async fn count_all_tokens_with_source(
     model: String,
     system: Option<Vec<SystemMessage>>,
     messages: Vec<Message>,
     tools: Option<Vec<Tool>>,
-) -> u64 {
-    // 检查是否配置了远程 API
-    if let Some(config) = get_config() {
-        if let Some(api_url) = &config.api_url {
-            // 尝试调用远程 API
-            let result = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(call_remote_count_tokens(
-                    api_url, config, model, &system, &messages, &tools,
-                ))
-            });
-
-            match result {
-                Ok(tokens) => {
-                    tracing::debug!("远程 count_tokens API 返回: {}", tokens);
-                    return tokens;
-                }
-                Err(e) => {
-                    tracing::warn!("远程 count_tokens API 调用失败，回退到本地计算: {}", e);
-                }
+) -> (u64, TokenCountSource) {
+    count_all_tokens_with_config(
+        get_config(),
+        REMOTE_COUNT_TOKENS_TIMEOUT,
+        CountTokensRequest {
+            model,
+            messages,
+            system,
+            tools,
+            max_tokens: None,
+        },
+    )
+    .await
+}
+
+async fn count_all_tokens_with_config(
+    config: Option<&CountTokensConfig>,
+    timeout: Duration,
+    request: CountTokensRequest,
+) -> (u64, TokenCountSource) {
+    if let Some(config) = config
+        && let Some(api_url) = &config.api_url
+    {
+        match call_remote_count_tokens(api_url, config, timeout, &request).await {
+            Ok(tokens) => return (tokens, TokenCountSource::Remote),
+            Err(e) => {
+                tracing::warn!("远程 count_tokens API 调用失败，回退到本地计算: {}", e);
             }
         }
     }
 
-    // 本地计算
-    count_all_tokens_local(system, messages, tools)
+    let tokens = count_all_tokens_local(request.system, request.messages, request.tools);
+    (tokens, TokenCountSource::Estimated)
 }
 
-/// 调用远程 count_tokens API
+/// 调用远程 count_tokens API（按请求内容哈希缓存结果）
 async fn call_remote_count_tokens(
     api_url: &str,
     config: &CountTokensConfig,
-    model: String,
-    system: &Option<Vec<SystemMessage>>,
-    messages: &Vec<Message>,
-    tools: &Option<Vec<Tool>>,
-) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
-    let client = build_client(config.proxy.as_ref(), 300, config.tls_backend)?;
-
-    // 构建请求体
-    let request = CountTokensRequest {
-        model: model, // 模型名称用于 token 计算
-        messages: messages.clone(),
-        system: system.clone(),
-        tools: tools.clone(),
-    };
+    timeout: Duration,
+    request: &CountTokensRequest,
+) -> anyhow::Result<u64> {
+    let body = serde_json::to_string(request)?;
+    let cache_key = calculate_hash(&format!("{}\n{}", api_url, body));
+    let cache = get_remote_cache();
+    if let Some(cached) = cache.get(&cache_key) {
+        tracing::debug!("远程 count_tokens 缓存命中: {} tokens", cached);
+        return Ok(cached);
+    }
+
+    let client = shared_client(
+        config.proxy.as_ref(),
+        HttpTimeouts::total(timeout, timeout),
+        config.tls,
+    )?;
 
     // 构建请求
-    let mut req_builder = client.post(api_url);
+    let mut req_builder = client
+        .post(api_url)
+        .header("Content-Type", "application/json")
+        .body(body);
 
     // 设置认证头
     if let Some(api_key) = &config.api_key {
@@ -240,19 +291,21 @@ async fn call_remote_count_tokens(
         }
     }
 
-    // 发送请求
-    let response = req_builder
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        return Err(format!("API 返回错误状态: {}", response.status()).into());
-    }
+    // 发送请求（整体超时，避免阻塞 count_tokens 响应）
+    let result = tokio::time::timeout(timeout, async {
+        let response = req_builder.send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("API 返回错误状态: {}", response.status());
+        }
+        Ok(response.json::<CountTokensResponse>().await?)
+    })
+    .await
+    .map_err(|_| anyhow::anyhow!("请求超时（{} ms）", timeout.as_millis()))??;
 
-    let result: CountTokensResponse = response.json().await?;
-    Ok(result.input_tokens as u64)
+    let tokens = result.input_tokens.max(0) as u64;
+    tracing::debug!("远程 count_tokens API 返回: {}", tokens);
+    cache.insert(cache_key, tokens);
+    Ok(tokens)
 }
 
 /// 本地计算请求的输入 tokens
@@ -315,3 +368,117 @@ pub(crate) fn estimate_output_tokens(content: &[serde_json::Value]) -> i32 {
 
     total.max(1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn request(text: &str) -> CountTokensRequest {
+        CountTokensRequest {
+            model: "claude-sonnet-4-5".to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: serde_json::Value::String(text.to_string()),
+            }],
+            system: Some(vec![SystemMessage {
+                text: "你是一个乐于助人的助手".to_string(),
+                cache_control: None,
+            }]),
+            tools: None,
+            max_tokens: None,
+        }
+    }
+
+    async fn remote_server(delay: Duration) -> (MockServer, CountTokensConfig) {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/messages/count_tokens"))
+            .and(header("x-api-key", "count-key"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "input_tokens": 4242 }))
+                    .set_delay(delay),
+            )
+            .mount(&server)
+            .await;
+        let config = CountTokensConfig {
+            api_url: Some(format!("{}/v1/messages/count_tokens", server.uri())),
+            api_key: Some("count-key".to_string()),
+            auth_type: "x-api-key".to_string(),
+            ..Default::default()
+        };
+        (server, config)
+    }
+
+    #[tokio::test]
+    async fn test_remote_count_tokens_success() {
+        let (server, config) = remote_server(Duration::ZERO).await;
+
+        let result = count_all_tokens_with_config(
+            Some(&config),
+            Duration::from_secs(2),
+            request("remote success"),
+        )
+        .await;
+        assert_eq!(result, (4242, TokenCountSource::Remote));
+
+        let received = server.received_requests().await.unwrap();
+        assert_eq!(received.len(), 1);
+        let body: serde_json::Value = received[0].body_json().unwrap();
+        assert_eq!(body["model"], "claude-sonnet-4-5");
+        assert_eq!(body["messages"][0]["content"], "remote success");
+    }
+
+    #[tokio::test]
+    async fn test_remote_count_tokens_timeout_falls_back() {
+        let (_server, config) = remote_server(Duration::from_secs(1)).await;
+
+        let expected = count_all_tokens_local(
+            request("remote timeout").system,
+            request("remote timeout").messages,
+            None,
+        );
+        let result = count_all_tokens_with_config(
+            Some(&config),
+            Duration::from_millis(100),
+            request("remote timeout"),
+        )
+        .await;
+        assert_eq!(result, (expected, TokenCountSource::Estimated));
+    }
+
+    #[tokio::test]
+    async fn test_remote_count_tokens_cache_hit() {
+        let (server, config) = remote_server(Duration::ZERO).await;
+
+        for _ in 0..3 {
+            let result = count_all_tokens_with_config(
+                Some(&config),
+                Duration::from_secs(2),
+                request("remote cache hit"),
+            )
+            .await;
+            assert_eq!(result, (4242, TokenCountSource::Remote));
+        }
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+        // 内容不同则重新请求
+        count_all_tokens_with_config(
+            Some(&config),
+            Duration::from_secs(2),
+            request("remote cache miss"),
+        )
+        .await;
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens_without_remote_is_estimated() {
+        let (tokens, source) =
+            count_all_tokens_with_config(None, Duration::from_secs(2), request("local only")).await;
+        assert!(tokens > 0);
+        assert_eq!(source, TokenCountSource::Estimated);
+    }
+}
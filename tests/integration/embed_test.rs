@@ -0,0 +1,325 @@
+//! 库嵌入测试
+//!
+//! 通过 `AppBuilder` 组装应用，将返回的路由挂载到宿主 axum 应用的 `/llm` 下
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use axum::routing::get;
+use kiro_rs::AppBuilder;
+use kiro_rs::kiro::model::credentials::KiroCredentials;
+use kiro_rs::model::config::Config;
+use tower::ServiceExt;
+
+use crate::mock_server::{MockEvent, MockKiroServer};
+
+/// 客户端 API Key
+const API_KEY: &str = "sk-embedded-test-key";
+
+/// Admin API Key
+const ADMIN_KEY: &str = "sk-embedded-admin-key";
+
+/// 发送请求，返回状态码和响应体
+async fn send(router: &Router, request: Request<Body>) -> (StatusCode, String) {
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_router_nested_in_host_app() {
+    let server = MockKiroServer::new_with_events(vec![
+        MockEvent::text("Hello from kiro"),
+        MockEvent::ContextUsage(1.5),
+    ])
+    .await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        api_key: Some(API_KEY.to_string()),
+        admin_api_key: Some(ADMIN_KEY.to_string()),
+        ..server.config()
+    };
+
+    let credential = KiroCredentials {
+        refresh_token: Some("r".repeat(150)),
+        ..KiroCredentials::default()
+    };
+    let app = AppBuilder::new(config)
+        .with_config_path(dir.path().join("config.json"))
+        .with_credentials(vec![credential])
+        .with_background_tasks(false)
+        .build()
+        .await
+        .unwrap();
+
+    // 运行时控制句柄：内存凭据不启用池管理
+    assert_eq!(app.token_manager.total_count(), 1);
+    assert!(app.pool_manager.is_none());
+
+    let host = Router::new()
+        .route("/", get(|| async { "host" }))
+        .nest("/llm", app.router);
+
+    // 宿主路由不受影响
+    let (status, body) = send(&host, Request::get("/").body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "host");
+
+    // Anthropic API 挂载在 /llm 下
+    let request = Request::post("/llm/v1/messages")
+        .header("x-api-key", API_KEY)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            serde_json::json!({
+                "model": "claude-sonnet-4-20250514",
+                "max_tokens": 1024,
+                "messages": [{ "role": "user", "content": "Hi" }]
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let (status, body) = send(&host, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["content"][0]["text"], "Hello from kiro");
+
+    // 未挂载前缀的路径不可达
+    let request = Request::get("/v1/models")
+        .header("x-api-key", API_KEY)
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&host, request).await.0, StatusCode::NOT_FOUND);
+
+    // Admin API 挂载在 /llm/api/admin 下
+    let request = Request::get("/llm/api/admin/credentials")
+        .header("x-api-key", ADMIN_KEY)
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(&host, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["total"], 1);
+}
+
+#[tokio::test]
+async fn test_admin_disabled_by_builder() {
+    let server = MockKiroServer::new_with_events(vec![MockEvent::ContextUsage(1.0)]).await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        admin_api_key: Some(ADMIN_KEY.to_string()),
+        ..server.config()
+    };
+
+    let app = AppBuilder::new(config)
+        .with_config_path(dir.path().join("config.json"))
+        .with_credentials(Vec::new())
+        .with_admin(false)
+        .with_background_tasks(false)
+        .build()
+        .await
+        .unwrap();
+
+    let request = Request::get("/api/admin/credentials")
+        .header("x-api-key", ADMIN_KEY)
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&app.router, request).await.0, StatusCode::NOT_FOUND);
+}
+
+/// 构建恢复备份请求（CSRF Token 一次性使用，每次请求重新获取）
+async fn restore_request(router: &Router, timestamp: &str) -> Request<Body> {
+    let request = Request::get("/api/admin/csrf-token")
+        .header("x-api-key", ADMIN_KEY)
+        .body(Body::empty())
+        .unwrap();
+    let (_, body) = send(router, request).await;
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    Request::post(format!("/api/admin/backups/{}/restore", timestamp))
+        .header("x-api-key", ADMIN_KEY)
+        .header("x-csrf-token", body["token"].as_str().unwrap())
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_restore_backup_reloads_credentials() {
+    let server = MockKiroServer::new_with_events(vec![MockEvent::ContextUsage(1.0)]).await;
+    let dir = tempfile::tempdir().unwrap();
+    let credentials_path = dir.path().join("credentials.json");
+    let credential = |id: u64| serde_json::json!({ "id": id, "refreshToken": "r".repeat(150) });
+    std::fs::write(
+        &credentials_path,
+        serde_json::json!([credential(1)]).to_string(),
+    )
+    .unwrap();
+    let config = Config {
+        admin_api_key: Some(ADMIN_KEY.to_string()),
+        backup_dir: Some("backups".to_string()),
+        ..server.config()
+    };
+
+    let app = AppBuilder::new(config)
+        .with_config_path(dir.path().join("config.json"))
+        .with_credentials_path(&credentials_path)
+        .with_background_tasks(false)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(app.token_manager.total_count(), 1);
+
+    // 文件被修改为 2 个凭据后备份（运行状态仍为 1 个凭据）
+    std::fs::write(
+        &credentials_path,
+        serde_json::json!([credential(1), credential(2)]).to_string(),
+    )
+    .unwrap();
+    let backup_manager = app.backup_manager.clone().unwrap();
+    let timestamp = backup_manager.backup_now().unwrap().unwrap();
+
+    let request = Request::get("/api/admin/backups")
+        .header("x-api-key", ADMIN_KEY)
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(&app.router, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["backups"][0]["timestamp"], timestamp.as_str());
+
+    // 恢复后无需重启即加载快照中的凭据
+    std::fs::write(&credentials_path, "[]").unwrap();
+    let (status, body) = send(&app.router, restore_request(&app.router, &timestamp).await).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(app.token_manager.total_count(), 2);
+
+    // 快照中的文件损坏时拒绝恢复，运行状态保持不变
+    std::fs::write(
+        dir.path()
+            .join("backups")
+            .join(&timestamp)
+            .join("credentials.json"),
+        "not json",
+    )
+    .unwrap();
+    let (status, body) = send(&app.router, restore_request(&app.router, &timestamp).await).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["error"]["code"], "backup_invalid");
+    assert_eq!(app.token_manager.total_count(), 2);
+
+    let request = restore_request(&app.router, "19700101T000000Z").await;
+    let (status, _) = send(&app.router, request).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_magic_link_login() {
+    let server = MockKiroServer::new_with_events(vec![MockEvent::ContextUsage(1.0)]).await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config {
+        admin_api_key: Some(ADMIN_KEY.to_string()),
+        ..server.config()
+    };
+    let app = AppBuilder::new(config)
+        .with_config_path(dir.path().join("config.json"))
+        .with_credentials(Vec::new())
+        .with_background_tasks(false)
+        .build()
+        .await
+        .unwrap();
+
+    // Admin Key 错误时拒绝生成
+    let request = Request::post("/api/admin/auth/magic-link")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(r#"{"adminKey": "wrong"}"#))
+        .unwrap();
+    assert_eq!(send(&app.router, request).await.0, StatusCode::UNAUTHORIZED);
+
+    // Admin Key 放在请求体中，不需要 CSRF Token
+    let request = Request::post("/api/admin/auth/magic-link")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            serde_json::json!({ "adminKey": ADMIN_KEY }).to_string(),
+        ))
+        .unwrap();
+    let (status, body) = send(&app.router, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let token = body["token"].as_str().unwrap();
+    assert_eq!(token.len(), 64);
+    assert!(body["expiresAt"].is_string());
+    let login_url = body["loginUrl"].as_str().unwrap();
+    assert_eq!(login_url, format!("/admin/login?token={}", token));
+
+    // 兑换 Token：设置会话 Cookie 并跳转到 /admin
+    let response = app
+        .router
+        .clone()
+        .oneshot(Request::get(login_url).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(response.headers()[header::LOCATION], "/admin");
+    let cookies: Vec<_> = response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|v| v.to_str().unwrap().to_string())
+        .collect();
+    assert_eq!(cookies.len(), 2);
+    // 纯 HTTP 请求不设置 Secure，作用域限定在 Admin 路径下
+    assert!(cookies[0].contains("Path=/api/admin;"));
+    assert!(cookies[1].contains("Path=/admin;"));
+    assert!(
+        cookies
+            .iter()
+            .all(|c| c.contains("HttpOnly; SameSite=Strict"))
+    );
+    let session = cookies[0].split(';').next().unwrap().to_string();
+
+    // 会话 Cookie 可代替 Admin Key 访问 Admin API
+    let request = Request::get("/api/admin/credentials")
+        .header(header::COOKIE, &session)
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(&app.router, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // Token 只能使用一次
+    let (status, body) = send(
+        &app.router,
+        Request::get(login_url).body(Body::empty()).unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["error"]["code"], "magic_link_invalid");
+
+    // 注销后会话失效
+    let request = Request::get("/api/admin/csrf-token")
+        .header(header::COOKIE, &session)
+        .body(Body::empty())
+        .unwrap();
+    let (_, body) = send(&app.router, request).await;
+    let csrf: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let request = Request::post("/api/admin/auth/logout")
+        .header(header::COOKIE, &session)
+        .header("x-csrf-token", csrf["token"].as_str().unwrap())
+        .body(Body::empty())
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response.headers()[header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .contains("Max-Age=0")
+    );
+    let request = Request::get("/api/admin/credentials")
+        .header(header::COOKIE, &session)
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&app.router, request).await.0, StatusCode::UNAUTHORIZED);
+}
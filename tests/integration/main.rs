@@ -0,0 +1,8 @@
+//! 集成测试
+//!
+//! 使用 Mock Kiro 上游服务器，无需网络访问。运行：`cargo test --test integration`
+
+mod embed_test;
+mod messages_test;
+mod mock_server;
+mod token_manager_test;
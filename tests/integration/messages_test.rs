@@ -0,0 +1,366 @@
+//! `/v1/messages` 端到端测试（认证 → 调度 → Provider → SSE 转换）
+//!
+//! 启动真实的 Anthropic API 路由，上游指向 Mock Kiro 服务器
+
+use std::sync::Arc;
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use kiro_rs::admin::ApiKeyManager;
+use kiro_rs::anthropic::{
+    self, RateLimitExemptions, RateLimiter, SseReplayRegistry, TokenBucketLimiter,
+    WebSearchRateLimiter,
+};
+use kiro_rs::common::features::FeatureFlags;
+use kiro_rs::kiro::model::credentials::KiroCredentials;
+use kiro_rs::kiro::provider::KiroProvider;
+use kiro_rs::kiro::token_manager::MultiTokenManager;
+use kiro_rs::model::config::Config;
+use tower::ServiceExt;
+
+use crate::mock_server::{MockEvent, MockKiroServer};
+
+/// 客户端 API Key
+const API_KEY: &str = "sk-integration-test-key";
+
+/// 测试环境：Mock 上游 + 真实路由
+struct TestApp {
+    router: Router,
+    server: MockKiroServer,
+    _dir: tempfile::TempDir,
+}
+
+impl TestApp {
+    /// 以指定的上游事件启动路由
+    async fn start(events: Vec<MockEvent>) -> Self {
+        Self::start_with(MockKiroServer::new_with_events(events).await, |_| {}).await
+    }
+
+    /// 以指定的 Mock 上游启动路由，`configure` 用于调整配置
+    async fn start_with(server: MockKiroServer, configure: impl FnOnce(&mut Config)) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config {
+            api_key: Some(API_KEY.to_string()),
+            // 启用断线续传，SSE 事件带有递增 ID
+            sse_replay_buffer_size: 100,
+            ..server.config()
+        };
+        configure(&mut config);
+
+        let credential = KiroCredentials {
+            refresh_token: Some("r".repeat(150)),
+            ..KiroCredentials::default()
+        };
+        let token_manager =
+            Arc::new(MultiTokenManager::new(config.clone(), vec![credential], None, None).unwrap());
+        let api_key_manager =
+            Arc::new(ApiKeyManager::new(dir.path().join("api_keys.json")).unwrap());
+
+        let router = anthropic::create_router(
+            api_key_manager,
+            Some(KiroProvider::new(token_manager.clone())),
+            None,
+            None,
+            Some(token_manager),
+            Arc::new(config.clone()),
+            Arc::new(WebSearchRateLimiter::new(
+                config.websearch_rate_limit_per_hour,
+            )),
+            RateLimiter::from_config(&config).map(Arc::new),
+            TokenBucketLimiter::from_config(&config).map(Arc::new),
+            Arc::new(RateLimitExemptions::new(Vec::new())),
+            Arc::new(FeatureFlags::new(&config.feature_flags)),
+            (config.sse_replay_buffer_size > 0)
+                .then(|| Arc::new(SseReplayRegistry::new(config.sse_replay_buffer_size))),
+        );
+
+        Self {
+            router,
+            server,
+            _dir: dir,
+        }
+    }
+
+    /// 发送 `/v1/messages` 请求，返回状态码、Content-Type 和响应体
+    async fn post_messages(&self, stream: bool) -> (StatusCode, String, String) {
+        let body = serde_json::json!({
+            "model": "claude-sonnet-4-20250514",
+            "max_tokens": 1024,
+            "stream": stream,
+            "messages": [{ "role": "user", "content": "Hi" }]
+        });
+        let request = Request::post("/v1/messages")
+            .header("x-api-key", API_KEY)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            content_type,
+            String::from_utf8(body.to_vec()).unwrap(),
+        )
+    }
+}
+
+/// 将随机生成的消息 ID 替换为固定值，便于逐字节比较
+fn normalize_ids(body: &str) -> String {
+    regex::Regex::new(r"msg_[0-9a-f]{32}")
+        .unwrap()
+        .replace_all(body, "msg_ID")
+        .into_owned()
+}
+
+/// 按 (事件名, data) 序列拼出期望的 SSE 输出（事件 ID 从 1 递增）
+fn sse(events: &[(&str, &str)]) -> String {
+    events
+        .iter()
+        .enumerate()
+        .map(|(i, (event, data))| format!("id: {}\nevent: {}\ndata: {}\n\n", i + 1, event, data))
+        .collect()
+}
+
+/// 固定的 message_start 事件数据
+const MESSAGE_START: &str = r#"{"message":{"content":[],"id":"msg_ID","model":"claude-sonnet-4-20250514","role":"assistant","stop_reason":null,"stop_sequence":null,"type":"message","usage":{"input_tokens":1,"output_tokens":1}},"type":"message_start"}"#;
+
+#[tokio::test]
+async fn test_stream_text_sse_output() {
+    let app = TestApp::start(vec![
+        MockEvent::text("Hello"),
+        MockEvent::text(", world!"),
+        MockEvent::ContextUsage(1.5),
+    ])
+    .await;
+
+    let (status, content_type, body) = app.post_messages(true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type, "text/event-stream");
+    assert_eq!(
+        normalize_ids(&body),
+        sse(&[
+            ("message_start", MESSAGE_START),
+            (
+                "content_block_start",
+                r#"{"content_block":{"text":"","type":"text"},"index":0,"type":"content_block_start"}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"delta":{"text":"Hello","type":"text_delta"},"index":0,"type":"content_block_delta"}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"delta":{"text":", world!","type":"text_delta"},"index":0,"type":"content_block_delta"}"#,
+            ),
+            (
+                "content_block_stop",
+                r#"{"index":0,"type":"content_block_stop"}"#,
+            ),
+            (
+                "message_delta",
+                r#"{"delta":{"stop_reason":"end_turn","stop_sequence":null},"type":"message_delta","usage":{"input_tokens":3000,"output_tokens":4}}"#,
+            ),
+            ("message_stop", r#"{"type":"message_stop"}"#),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_stream_tool_use_sse_output() {
+    let app = TestApp::start(vec![
+        MockEvent::text("Checking."),
+        MockEvent::tool_use("tooluse_1", "get_weather", r#"{"city":"#, false),
+        MockEvent::tool_use("tooluse_1", "get_weather", r#""Paris"}"#, true),
+        MockEvent::ContextUsage(1.5),
+    ])
+    .await;
+
+    let (status, _, body) = app.post_messages(true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        normalize_ids(&body),
+        sse(&[
+            ("message_start", MESSAGE_START),
+            (
+                "content_block_start",
+                r#"{"content_block":{"text":"","type":"text"},"index":0,"type":"content_block_start"}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"delta":{"text":"Checking.","type":"text_delta"},"index":0,"type":"content_block_delta"}"#,
+            ),
+            (
+                "content_block_stop",
+                r#"{"index":0,"type":"content_block_stop"}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"content_block":{"id":"tooluse_1","input":{},"name":"get_weather","type":"tool_use"},"index":1,"type":"content_block_start"}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"delta":{"partial_json":"{\"city\":\"Paris\"}","type":"input_json_delta"},"index":1,"type":"content_block_delta"}"#,
+            ),
+            (
+                "content_block_stop",
+                r#"{"index":1,"type":"content_block_stop"}"#,
+            ),
+            (
+                "message_delta",
+                r#"{"delta":{"stop_reason":"tool_use","stop_sequence":null},"type":"message_delta","usage":{"input_tokens":3000,"output_tokens":7}}"#,
+            ),
+            ("message_stop", r#"{"type":"message_stop"}"#),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_stream_content_length_exception_sets_max_tokens() {
+    let app = TestApp::start(vec![
+        MockEvent::text("Truncated"),
+        MockEvent::exception("ContentLengthExceededException", "too long"),
+        MockEvent::ContextUsage(1.5),
+    ])
+    .await;
+
+    let (status, _, body) = app.post_messages(true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        normalize_ids(&body),
+        sse(&[
+            ("message_start", MESSAGE_START),
+            (
+                "content_block_start",
+                r#"{"content_block":{"text":"","type":"text"},"index":0,"type":"content_block_start"}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"delta":{"text":"Truncated","type":"text_delta"},"index":0,"type":"content_block_delta"}"#,
+            ),
+            (
+                "content_block_stop",
+                r#"{"index":0,"type":"content_block_stop"}"#,
+            ),
+            (
+                "message_delta",
+                r#"{"delta":{"stop_reason":"max_tokens","stop_sequence":null},"type":"message_delta","usage":{"input_tokens":3000,"output_tokens":2}}"#,
+            ),
+            ("message_stop", r#"{"type":"message_stop"}"#),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_stream_without_context_usage_reports_truncation() {
+    // 上游未发送 contextUsageEvent 即关闭连接，视为响应被截断
+    let app = TestApp::start(vec![MockEvent::text("Partial")]).await;
+
+    let (status, _, body) = app.post_messages(true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        normalize_ids(&body),
+        sse(&[
+            ("message_start", MESSAGE_START),
+            (
+                "content_block_start",
+                r#"{"content_block":{"text":"","type":"text"},"index":0,"type":"content_block_start"}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"delta":{"text":"Partial","type":"text_delta"},"index":0,"type":"content_block_delta"}"#,
+            ),
+            (
+                "content_block_stop",
+                r#"{"index":0,"type":"content_block_stop"}"#,
+            ),
+            (
+                "error",
+                r#"{"error":{"message":"上游响应流在结束事件之前关闭","type":"overloaded_error"},"type":"error"}"#,
+            ),
+            (
+                "message_delta",
+                r#"{"delta":{"stop_reason":null,"stop_sequence":null},"type":"message_delta","usage":{"input_tokens":1,"output_tokens":1}}"#,
+            ),
+            ("message_stop", r#"{"type":"message_stop"}"#),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_non_stream_response() {
+    let app = TestApp::start(vec![
+        MockEvent::text("Hello"),
+        MockEvent::tool_use("tooluse_1", "get_weather", r#"{"city":"Paris"}"#, true),
+        MockEvent::ContextUsage(1.5),
+    ])
+    .await;
+
+    let (status, content_type, body) = app.post_messages(false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type, "application/json");
+    assert_eq!(
+        normalize_ids(&body),
+        r#"{"content":[{"text":"Hello","type":"text"},{"id":"tooluse_1","input":{"city":"Paris"},"name":"get_weather","type":"tool_use"}],"id":"msg_ID","model":"claude-sonnet-4-20250514","role":"assistant","stop_reason":"tool_use","stop_sequence":null,"type":"message","usage":{"input_tokens":3000,"output_tokens":6}}"#
+    );
+}
+
+#[tokio::test]
+async fn test_gzip_encoded_event_stream_is_decompressed() {
+    let server = MockKiroServer::new_with_gzip_events(vec![
+        MockEvent::text("Hello"),
+        MockEvent::text(", world!"),
+        MockEvent::ContextUsage(1.5),
+    ])
+    .await;
+    let app = TestApp::start_with(server, |_| {}).await;
+
+    let (status, _, body) = app.post_messages(true).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains(r#""text":"Hello""#), "{}", body);
+    assert!(body.contains(r#""text":", world!""#), "{}", body);
+    assert!(body.contains(r#""stop_reason":"end_turn""#), "{}", body);
+
+    let (status, _, body) = app.post_messages(false).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains(r#""text":"Hello, world!""#), "{}", body);
+
+    // 对话请求不声明可压缩的响应编码
+    let requests = app.server.recorded_requests().await;
+    let chat = requests
+        .iter()
+        .find(|r| r.path == "/generateAssistantResponse")
+        .unwrap();
+    assert_eq!(chat.accept_encoding.as_deref(), Some("identity"));
+}
+
+#[tokio::test]
+async fn test_request_body_compression() {
+    let server = MockKiroServer::new_with_events(vec![MockEvent::text("Hello")]).await;
+    let app = TestApp::start_with(server, |config| {
+        config.request_compression_enabled = true;
+        config.request_compression_min_bytes = 0;
+    })
+    .await;
+
+    let (status, _, body) = app.post_messages(false).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let requests = app.server.recorded_requests().await;
+    let chat = requests
+        .iter()
+        .find(|r| r.path == "/generateAssistantResponse")
+        .unwrap();
+    assert_eq!(chat.content_encoding.as_deref(), Some("gzip"));
+    assert!(chat.body["conversationState"].is_object(), "{}", chat.body);
+}
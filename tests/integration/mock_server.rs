@@ -0,0 +1,359 @@
+//! Mock Kiro 上游服务器
+//!
+//! 基于 wiremock 模拟 Kiro 上游端点，配合 `Config::upstream_base_url` 使用：
+//! - `POST /refreshToken` - Social Token 刷新
+//! - `POST /token` - IdC (AWS SSO OIDC) Token 刷新
+//! - `POST /generateAssistantResponse` - 对话接口（返回预录制或按测试脚本编排的 AWS Event Stream）
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use kiro_rs::kiro::parser::crc::crc32;
+use kiro_rs::model::config::Config;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// Mock 返回的 accessToken
+pub const MOCK_ACCESS_TOKEN: &str = "mock-access-token";
+/// Mock 返回的 refreshToken
+pub const MOCK_REFRESH_TOKEN: &str = "mock-refresh-token";
+/// Mock 返回的 profileArn
+pub const MOCK_PROFILE_ARN: &str = "arn:aws:codewhisperer:us-east-1:123456789012:profile/MOCK";
+/// 预录制对话响应的文本片段
+pub const MOCK_RESPONSE_CHUNKS: [&str; 2] = ["Hello", ", world!"];
+
+/// 响应场景
+///
+/// 按顺序作用于所有端点的请求序列：
+/// - `FailNTimes(n)`：接下来 n 个请求返回 500，之后进入下一个场景
+/// - `ReturnStatus(code)`：之后的请求都返回该状态码
+/// - `AlwaysSucceed`：之后的请求都成功
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockScenario {
+    AlwaysSucceed,
+    FailNTimes(usize),
+    ReturnStatus(u16),
+}
+
+/// 对话接口返回的上游事件
+///
+/// 按顺序编码为 AWS Event Stream 帧，与 `EventStreamDecoder` 的解析互逆
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockEvent {
+    /// `assistantResponseEvent`：文本片段
+    AssistantResponse(String),
+    /// `toolUseEvent`：工具调用片段（`input` 为部分 JSON，`stop` 标记最后一块）
+    ToolUse {
+        tool_use_id: String,
+        name: String,
+        input: String,
+        stop: bool,
+    },
+    /// `contextUsageEvent`：上下文使用百分比
+    ContextUsage(f64),
+    /// 异常消息（`:message-type` 为 `exception`）
+    Exception {
+        exception_type: String,
+        message: String,
+    },
+}
+
+impl MockEvent {
+    /// 文本片段事件
+    pub fn text(content: &str) -> Self {
+        Self::AssistantResponse(content.to_string())
+    }
+
+    /// 工具调用片段事件
+    pub fn tool_use(tool_use_id: &str, name: &str, input: &str, stop: bool) -> Self {
+        Self::ToolUse {
+            tool_use_id: tool_use_id.to_string(),
+            name: name.to_string(),
+            input: input.to_string(),
+            stop,
+        }
+    }
+
+    /// 异常事件
+    pub fn exception(exception_type: &str, message: &str) -> Self {
+        Self::Exception {
+            exception_type: exception_type.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// 编码为事件帧
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::AssistantResponse(content) => encode_event_frame(
+                "assistantResponseEvent",
+                &serde_json::json!({ "content": content }),
+            ),
+            Self::ToolUse {
+                tool_use_id,
+                name,
+                input,
+                stop,
+            } => encode_event_frame(
+                "toolUseEvent",
+                &serde_json::json!({
+                    "toolUseId": tool_use_id,
+                    "name": name,
+                    "input": input,
+                    "stop": stop
+                }),
+            ),
+            Self::ContextUsage(percentage) => encode_event_frame(
+                "contextUsageEvent",
+                &serde_json::json!({ "contextUsagePercentage": percentage }),
+            ),
+            Self::Exception {
+                exception_type,
+                message,
+            } => encode_frame(
+                &[
+                    (":message-type", "exception"),
+                    (":exception-type", exception_type),
+                    (":content-type", "application/json"),
+                ],
+                message.as_bytes(),
+            ),
+        }
+    }
+}
+
+/// 已记录的请求
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    /// 请求头 `accept-encoding`
+    pub accept_encoding: Option<String>,
+    /// 请求头 `content-encoding`（gzip 请求体已解压后再解析为 `body`）
+    pub content_encoding: Option<String>,
+    pub body: serde_json::Value,
+}
+
+/// Mock Kiro 上游服务器
+pub struct MockKiroServer {
+    server: MockServer,
+}
+
+impl MockKiroServer {
+    /// 创建所有请求都成功的服务器
+    pub async fn new() -> Self {
+        Self::new_with_scenarios(vec![MockScenario::AlwaysSucceed]).await
+    }
+
+    /// 按场景序列创建服务器
+    pub async fn new_with_scenarios(scenarios: Vec<MockScenario>) -> Self {
+        Self::start(scenarios, recorded_events(), false).await
+    }
+
+    /// 创建对话接口按顺序返回指定事件的服务器（所有请求都成功）
+    pub async fn new_with_events(events: Vec<MockEvent>) -> Self {
+        Self::start(vec![MockScenario::AlwaysSucceed], events, false).await
+    }
+
+    /// 同 [`Self::new_with_events`]，但对话响应以 `content-encoding: gzip` 返回
+    ///
+    /// 模拟无视 `Accept-Encoding` 压缩响应体的中间代理
+    pub async fn new_with_gzip_events(events: Vec<MockEvent>) -> Self {
+        Self::start(vec![MockScenario::AlwaysSucceed], events, true).await
+    }
+
+    async fn start(scenarios: Vec<MockScenario>, events: Vec<MockEvent>, gzip: bool) -> Self {
+        let server = MockServer::start().await;
+        let scenarios = Arc::new(scenarios);
+        let events = Arc::new(events);
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let endpoints = [
+            ("/refreshToken", Endpoint::SocialRefresh),
+            ("/token", Endpoint::IdcRefresh),
+            ("/generateAssistantResponse", Endpoint::Chat),
+        ];
+        for (endpoint_path, endpoint) in endpoints {
+            Mock::given(method("POST"))
+                .and(path(endpoint_path))
+                .respond_with(ScenarioResponder {
+                    endpoint,
+                    scenarios: scenarios.clone(),
+                    events: events.clone(),
+                    counter: counter.clone(),
+                    gzip,
+                })
+                .mount(&server)
+                .await;
+        }
+
+        Self { server }
+    }
+
+    /// 服务器地址
+    pub fn uri(&self) -> String {
+        self.server.uri()
+    }
+
+    /// 指向本服务器的配置
+    pub fn config(&self) -> Config {
+        Config {
+            upstream_base_url: Some(self.uri()),
+            ..Config::default()
+        }
+    }
+
+    /// 已收到的请求（按接收顺序）
+    pub async fn recorded_requests(&self) -> Vec<RecordedRequest> {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|r| {
+                let header = |name: &str| {
+                    r.headers
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string)
+                };
+                let content_encoding = header("content-encoding");
+                let body = if content_encoding.as_deref() == Some("gzip") {
+                    let mut decoded = Vec::new();
+                    GzDecoder::new(&r.body[..])
+                        .read_to_end(&mut decoded)
+                        .unwrap();
+                    decoded
+                } else {
+                    r.body.clone()
+                };
+                RecordedRequest {
+                    method: r.method.to_string(),
+                    path: r.url.path().to_string(),
+                    accept_encoding: header("accept-encoding"),
+                    content_encoding,
+                    body: serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Endpoint {
+    SocialRefresh,
+    IdcRefresh,
+    Chat,
+}
+
+struct ScenarioResponder {
+    endpoint: Endpoint,
+    scenarios: Arc<Vec<MockScenario>>,
+    events: Arc<Vec<MockEvent>>,
+    counter: Arc<AtomicUsize>,
+    /// 对话响应是否 gzip 压缩
+    gzip: bool,
+}
+
+impl Respond for ScenarioResponder {
+    fn respond(&self, _request: &Request) -> ResponseTemplate {
+        let mut index = self.counter.fetch_add(1, Ordering::SeqCst);
+
+        for scenario in self.scenarios.iter() {
+            match *scenario {
+                MockScenario::FailNTimes(n) if index < n => {
+                    return ResponseTemplate::new(500).set_body_string("mock failure");
+                }
+                MockScenario::FailNTimes(n) => index -= n,
+                MockScenario::ReturnStatus(status) => {
+                    return ResponseTemplate::new(status).set_body_string("mock status");
+                }
+                MockScenario::AlwaysSucceed => break,
+            }
+        }
+
+        self.success()
+    }
+}
+
+impl ScenarioResponder {
+    fn success(&self) -> ResponseTemplate {
+        match self.endpoint {
+            Endpoint::SocialRefresh => {
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "accessToken": MOCK_ACCESS_TOKEN,
+                    "refreshToken": MOCK_REFRESH_TOKEN,
+                    "profileArn": MOCK_PROFILE_ARN,
+                    "expiresIn": 3600
+                }))
+            }
+            Endpoint::IdcRefresh => ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "accessToken": MOCK_ACCESS_TOKEN,
+                "refreshToken": MOCK_REFRESH_TOKEN,
+                "expiresIn": 3600
+            })),
+            Endpoint::Chat => {
+                let mut body: Vec<u8> = self.events.iter().flat_map(MockEvent::encode).collect();
+                let mut response =
+                    ResponseTemplate::new(200).insert_header("x-amzn-requestid", "mock-request-id");
+                if self.gzip {
+                    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                    encoder.write_all(&body).unwrap();
+                    body = encoder.finish().unwrap();
+                    response = response.insert_header("content-encoding", "gzip");
+                }
+                response.set_body_raw(body, "application/vnd.amazon.eventstream")
+            }
+        }
+    }
+}
+
+/// 预录制的对话响应事件
+fn recorded_events() -> Vec<MockEvent> {
+    MOCK_RESPONSE_CHUNKS
+        .iter()
+        .map(|chunk| MockEvent::text(chunk))
+        .collect()
+}
+
+/// 编码 AWS Event Stream 事件帧
+fn encode_event_frame(event_type: &str, payload: &serde_json::Value) -> Vec<u8> {
+    encode_frame(
+        &[
+            (":message-type", "event"),
+            (":event-type", event_type),
+            (":content-type", "application/json"),
+        ],
+        &serde_json::to_vec(payload).unwrap(),
+    )
+}
+
+/// 编码 AWS Event Stream 帧（头部均为 String 类型）
+fn encode_frame(header_values: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+    let mut headers = Vec::new();
+    for (name, value) in header_values {
+        headers.push(name.len() as u8);
+        headers.extend_from_slice(name.as_bytes());
+        headers.push(7); // String 类型
+        headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        headers.extend_from_slice(value.as_bytes());
+    }
+
+    // prelude(12) + headers + payload + message_crc(4)
+    let total_length = 12 + headers.len() + payload.len() + 4;
+    let mut frame = Vec::with_capacity(total_length);
+    frame.extend_from_slice(&(total_length as u32).to_be_bytes());
+    frame.extend_from_slice(&(headers.len() as u32).to_be_bytes());
+    let prelude_crc = crc32(&frame);
+    frame.extend_from_slice(&prelude_crc.to_be_bytes());
+    frame.extend_from_slice(&headers);
+    frame.extend_from_slice(payload);
+    let message_crc = crc32(&frame);
+    frame.extend_from_slice(&message_crc.to_be_bytes());
+    frame
+}
@@ -0,0 +1,192 @@
+//! Token 管理器集成测试（完整的凭据刷新流程）
+
+use std::sync::Arc;
+
+use kiro_rs::kiro::model::credentials::KiroCredentials;
+use kiro_rs::kiro::model::events::Event;
+use kiro_rs::kiro::parser::decoder::EventStreamDecoder;
+use kiro_rs::kiro::provider::KiroProvider;
+use kiro_rs::kiro::token_manager::MultiTokenManager;
+
+use crate::mock_server::{
+    MOCK_ACCESS_TOKEN, MOCK_PROFILE_ARN, MOCK_REFRESH_TOKEN, MOCK_RESPONSE_CHUNKS, MockKiroServer,
+    MockScenario,
+};
+
+/// 创建需要刷新的 Social 凭据（无 accessToken）
+fn social_credential(suffix: &str) -> KiroCredentials {
+    KiroCredentials {
+        refresh_token: Some("r".repeat(150) + suffix),
+        ..KiroCredentials::default()
+    }
+}
+
+/// 创建需要刷新的 IdC 凭据
+fn idc_credential() -> KiroCredentials {
+    KiroCredentials {
+        refresh_token: Some("r".repeat(150)),
+        auth_method: Some("idc".to_string()),
+        client_id: Some("mock-client-id".to_string()),
+        client_secret: Some("mock-client-secret".to_string()),
+        ..KiroCredentials::default()
+    }
+}
+
+#[tokio::test]
+async fn test_social_refresh_flow() {
+    let server = MockKiroServer::new().await;
+    let manager =
+        MultiTokenManager::new(server.config(), vec![social_credential("")], None, None).unwrap();
+
+    let ctx = manager.acquire_context().await.unwrap();
+    assert_eq!(ctx.token, MOCK_ACCESS_TOKEN);
+    assert_eq!(
+        ctx.credentials.refresh_token.as_deref(),
+        Some(MOCK_REFRESH_TOKEN)
+    );
+    assert_eq!(
+        ctx.credentials.profile_arn.as_deref(),
+        Some(MOCK_PROFILE_ARN)
+    );
+    assert!(ctx.credentials.expires_at.is_some());
+
+    let requests = server.recorded_requests().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/refreshToken");
+    assert_eq!(requests[0].body["refreshToken"], "r".repeat(150));
+
+    // Token 有效期内不再刷新
+    manager.acquire_context().await.unwrap();
+    assert_eq!(server.recorded_requests().await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_idc_refresh_flow() {
+    let server = MockKiroServer::new().await;
+    let manager =
+        MultiTokenManager::new(server.config(), vec![idc_credential()], None, None).unwrap();
+
+    let ctx = manager.acquire_context().await.unwrap();
+    assert_eq!(ctx.token, MOCK_ACCESS_TOKEN);
+
+    let requests = server.recorded_requests().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/token");
+    assert_eq!(requests[0].body["clientId"], "mock-client-id");
+    assert_eq!(requests[0].body["grantType"], "refresh_token");
+}
+
+#[tokio::test]
+async fn test_refresh_retries_after_server_error() {
+    let server = MockKiroServer::new_with_scenarios(vec![
+        MockScenario::FailNTimes(1),
+        MockScenario::AlwaysSucceed,
+    ])
+    .await;
+    let manager = MultiTokenManager::new(
+        server.config(),
+        vec![social_credential("1"), social_credential("2")],
+        None,
+        None,
+    )
+    .unwrap();
+
+    // 首次刷新返回 500 后重新选择凭据重试
+    let ctx = manager.acquire_context().await.unwrap();
+    assert_eq!(ctx.token, MOCK_ACCESS_TOKEN);
+    // 服务端错误不禁用凭据
+    assert_eq!(manager.available_count(), 2);
+
+    let requests = server.recorded_requests().await;
+    assert_eq!(requests.len(), 2);
+    assert!(requests.iter().all(|r| r.path == "/refreshToken"));
+}
+
+#[tokio::test]
+async fn test_refresh_unauthorized_disables_credential() {
+    let server = MockKiroServer::new_with_scenarios(vec![MockScenario::ReturnStatus(401)]).await;
+    let manager =
+        MultiTokenManager::new(server.config(), vec![social_credential("")], None, None).unwrap();
+
+    let err = manager.acquire_context().await.err().unwrap().to_string();
+    assert!(err.contains("所有凭据均无法获取有效 Token"), "{}", err);
+    assert_eq!(manager.available_count(), 0);
+}
+
+#[tokio::test]
+async fn test_provider_call_api_with_refreshed_token() {
+    let server = MockKiroServer::new().await;
+    let manager =
+        MultiTokenManager::new(server.config(), vec![social_credential("")], None, None).unwrap();
+    let provider = KiroProvider::new(Arc::new(manager));
+
+    let response = provider
+        .call_api(r#"{"conversationState":{}}"#)
+        .await
+        .unwrap();
+    assert_eq!(
+        KiroProvider::upstream_request_id(response.headers()).as_deref(),
+        Some("mock-request-id")
+    );
+
+    let body = response.bytes().await.unwrap();
+    let mut decoder = EventStreamDecoder::new();
+    decoder.feed(&body).unwrap();
+    let mut content = String::new();
+    while let Some(frame) = decoder.decode().unwrap() {
+        if let Event::AssistantResponse(event) = Event::from_frame(frame).unwrap() {
+            content.push_str(&event.content);
+        }
+    }
+    assert_eq!(content, MOCK_RESPONSE_CHUNKS.concat());
+
+    let paths: Vec<_> = server
+        .recorded_requests()
+        .await
+        .into_iter()
+        .map(|r| r.path)
+        .collect();
+    assert_eq!(paths, vec!["/refreshToken", "/generateAssistantResponse"]);
+}
+
+#[tokio::test]
+async fn test_refresh_unknown_error_keeps_credential() {
+    let server = MockKiroServer::new_with_scenarios(vec![MockScenario::ReturnStatus(400)]).await;
+    let manager =
+        MultiTokenManager::new(server.config(), vec![social_credential("")], None, None).unwrap();
+
+    // 无法识别的错误不自动禁用凭据
+    assert!(manager.acquire_context().await.is_err());
+    assert_eq!(manager.available_count(), 1);
+}
+
+#[tokio::test]
+async fn test_force_refresh_renews_valid_token() {
+    let server = MockKiroServer::new().await;
+    let credential = KiroCredentials {
+        access_token: Some("still-valid-access-token".to_string()),
+        expires_at: Some((chrono::Utc::now() + chrono::Duration::hours(6)).to_rfc3339()),
+        ..social_credential("")
+    };
+    let manager = MultiTokenManager::new(server.config(), vec![credential], None, None).unwrap();
+
+    // Token 仍有效时正常获取不会触发刷新
+    let ctx = manager.acquire_context().await.unwrap();
+    assert_eq!(ctx.token, "still-valid-access-token");
+    assert!(server.recorded_requests().await.is_empty());
+
+    let refreshed = manager.force_refresh(ctx.id, true).await.unwrap();
+    assert_eq!(refreshed.access_token.as_deref(), Some(MOCK_ACCESS_TOKEN));
+    assert_ne!(
+        refreshed.expires_at.as_deref(),
+        Some("1970-01-01T00:00:00Z")
+    );
+
+    let requests = server.recorded_requests().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/refreshToken");
+
+    let ctx = manager.acquire_context().await.unwrap();
+    assert_eq!(ctx.token, MOCK_ACCESS_TOKEN);
+}